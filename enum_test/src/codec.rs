@@ -0,0 +1,147 @@
+// SolanaInstruction的二进制编码（与Borsh相同的规则）：
+// 1字节变体序号 + 字段；整数小端序，字符串先写u32长度再写UTF-8内容
+
+use std::fmt;
+
+use crate::lamports::Lamports;
+use crate::SolanaInstruction;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    UnexpectedEof,        // 数据不够读
+    InvalidTag(u8),       // 没有对应的指令变体
+    InvalidUtf8,          // 地址不是合法UTF-8
+    TrailingBytes(usize), // 解析完后还有多余字节
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEof => write!(f, "指令数据不完整"),
+            DecodeError::InvalidTag(tag) => write!(f, "未知的指令类型: {}", tag),
+            DecodeError::InvalidUtf8 => write!(f, "地址不是合法的UTF-8"),
+            DecodeError::TrailingBytes(n) => write!(f, "指令数据末尾有{}个多余字节", n),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl SolanaInstruction {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            SolanaInstruction::Transfer { amount, to_address } => {
+                out.push(0);
+                out.extend_from_slice(&amount.0.to_le_bytes());
+                write_string(&mut out, to_address);
+            }
+            SolanaInstruction::CreateAccount { initial_balance } => {
+                out.push(1);
+                out.extend_from_slice(&initial_balance.0.to_le_bytes());
+            }
+            SolanaInstruction::CloseAccount { destination } => {
+                out.push(2);
+                write_string(&mut out, destination);
+            }
+        }
+        out
+    }
+
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader { data };
+        let instruction = match reader.take(1)?[0] {
+            0 => SolanaInstruction::Transfer {
+                amount: Lamports(reader.read_u64()?),
+                to_address: reader.read_string()?,
+            },
+            1 => SolanaInstruction::CreateAccount {
+                initial_balance: Lamports(reader.read_u64()?),
+            },
+            2 => SolanaInstruction::CloseAccount {
+                destination: reader.read_string()?,
+            },
+            tag => return Err(DecodeError::InvalidTag(tag)),
+        };
+        if !reader.data.is_empty() {
+            return Err(DecodeError::TrailingBytes(reader.data.len()));
+        }
+        Ok(instruction)
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+// 从前往后依次读取字段
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.data.len() < n {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn read_string(&mut self) -> Result<String, DecodeError> {
+        let len = self.read_u32()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let instruction = SolanaInstruction::CreateAccount { initial_balance: Lamports(258) };
+        assert_eq!(instruction.to_bytes(), vec![1, 2, 1, 0, 0, 0, 0, 0, 0]);
+
+        let instruction = SolanaInstruction::CloseAccount { destination: String::from("ab") };
+        assert_eq!(instruction.to_bytes(), vec![2, 2, 0, 0, 0, b'a', b'b']);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let instructions = [
+            SolanaInstruction::Transfer { amount: Lamports(100), to_address: String::from("0x1234567890") },
+            SolanaInstruction::CreateAccount { initial_balance: Lamports(u64::MAX) },
+            SolanaInstruction::CloseAccount { destination: String::from("收款地址") },
+        ];
+        for instruction in instructions {
+            assert_eq!(SolanaInstruction::try_from_bytes(&instruction.to_bytes()), Ok(instruction));
+        }
+    }
+
+    #[test]
+    fn test_reject_malformed_data() {
+        assert_eq!(SolanaInstruction::try_from_bytes(&[]), Err(DecodeError::UnexpectedEof));
+        assert_eq!(SolanaInstruction::try_from_bytes(&[7]), Err(DecodeError::InvalidTag(7)));
+        assert_eq!(SolanaInstruction::try_from_bytes(&[1, 0, 0]), Err(DecodeError::UnexpectedEof));
+        assert_eq!(
+            SolanaInstruction::try_from_bytes(&[2, 1, 0, 0, 0, 0xff]),
+            Err(DecodeError::InvalidUtf8)
+        );
+        assert_eq!(
+            SolanaInstruction::try_from_bytes(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 9]),
+            Err(DecodeError::TrailingBytes(1))
+        );
+    }
+}
@@ -0,0 +1,59 @@
+// 练习里各个函数共用的错误类型，代替原来的Result<_, String>。
+// 写法和thiserror生成的代码一样：每个变体一条Display信息，包装的底层错误通过From自动转换，并从source()返回
+
+use std::error::Error;
+use std::fmt;
+
+use crate::lamports::LamportsError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExerciseError {
+    AccountNotFound(String), // 找不到这个地址的账户
+    InsufficientFunds,       // 余额不足
+    Lamports(LamportsError), // lamports换算或计算溢出
+}
+
+impl fmt::Display for ExerciseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExerciseError::AccountNotFound(address) => write!(f, "账户不存在: {}", address),
+            ExerciseError::InsufficientFunds => write!(f, "余额不足"),
+            ExerciseError::Lamports(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for ExerciseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExerciseError::Lamports(error) => Some(error),
+            ExerciseError::AccountNotFound(_) | ExerciseError::InsufficientFunds => None,
+        }
+    }
+}
+
+impl From<LamportsError> for ExerciseError {
+    fn from(error: LamportsError) -> ExerciseError {
+        ExerciseError::Lamports(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lamports::Lamports;
+
+    #[test]
+    fn test_question_mark_converts_lamports_error() {
+        fn from_sol(sol: u64) -> Result<Lamports, ExerciseError> {
+            Ok(Lamports::from_sol(sol)?)
+        }
+        let error = from_sol(u64::MAX).unwrap_err();
+        assert_eq!(error, ExerciseError::Lamports(LamportsError::Overflow));
+        assert_eq!(error.to_string(), "lamports计算溢出");
+        assert!(error.source().is_some());
+
+        assert_eq!(ExerciseError::AccountNotFound("0x1".to_string()).to_string(), "账户不存在: 0x1");
+        assert!(ExerciseError::InsufficientFunds.source().is_none());
+    }
+}
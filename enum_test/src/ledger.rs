@@ -0,0 +1,172 @@
+// 简单的账本：账户保存在槽位(slot)数组中，关闭账户后槽位会被标记为空闲，供新账户复用
+
+use crate::lamports::Lamports;
+use crate::TransferResult;
+
+#[derive(Debug)]
+struct AccountEntry {
+    address: String,
+    balance: Lamports,
+}
+
+#[derive(Debug, Default)]
+pub struct Ledger {
+    slots: Vec<Option<AccountEntry>>,
+    free_slots: Vec<usize>, // 已关闭账户留下的空槽位
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn slot_of(&self, address: &str) -> Option<usize> {
+        self.slots.iter().position(|slot| {
+            slot.as_ref().is_some_and(|entry| entry.address == address)
+        })
+    }
+
+    fn entry_mut(&mut self, address: &str) -> Option<&mut AccountEntry> {
+        self.slots
+            .iter_mut()
+            .flatten()
+            .find(|entry| entry.address == address)
+    }
+
+    // 创建账户，优先复用空闲槽位，返回账户所在的槽位
+    pub fn create_account(&mut self, address: &str, initial_balance: Lamports) -> Option<usize> {
+        if self.slot_of(address).is_some() {
+            return None;
+        }
+
+        let entry = AccountEntry {
+            address: address.to_string(),
+            balance: initial_balance,
+        };
+        match self.free_slots.pop() {
+            Some(slot) => {
+                self.slots[slot] = Some(entry);
+                Some(slot)
+            }
+            None => {
+                self.slots.push(Some(entry));
+                Some(self.slots.len() - 1)
+            }
+        }
+    }
+
+    pub fn balance(&self, address: &str) -> Option<Lamports> {
+        self.slot_of(address)
+            .and_then(|slot| self.slots[slot].as_ref())
+            .map(|entry| entry.balance)
+    }
+
+    pub fn transfer(&mut self, from: &str, to: &str, amount: Lamports) -> TransferResult {
+        let (Some(from_balance), Some(to_balance)) = (self.balance(from), self.balance(to)) else {
+            return TransferResult::AccountNotFound;
+        };
+        if from == to {
+            return TransferResult::Success;
+        }
+
+        let Ok(new_from) = from_balance.checked_sub(amount) else {
+            return TransferResult::InsufficientBalance;
+        };
+        let Ok(new_to) = to_balance.checked_add(amount) else {
+            return TransferResult::ArithmeticOverflow;
+        };
+
+        if let Some(entry) = self.entry_mut(from) {
+            entry.balance = new_from;
+        }
+        if let Some(entry) = self.entry_mut(to) {
+            entry.balance = new_to;
+        }
+        TransferResult::Success
+    }
+
+    // 关闭账户：把剩余lamports全部转给destination，然后删除账户并回收槽位
+    pub fn close_account(&mut self, address: &str, destination: &str) -> TransferResult {
+        // 和SPL Token一样，不允许把余额退回给自己，否则lamports会随账户一起被删除
+        if address == destination {
+            return TransferResult::InvalidDestination;
+        }
+        let (Some(slot), Some(dest_balance)) = (self.slot_of(address), self.balance(destination)) else {
+            return TransferResult::AccountNotFound;
+        };
+
+        let remaining = self.slots[slot].as_ref().map_or(Lamports::ZERO, |entry| entry.balance);
+        let Ok(new_dest) = dest_balance.checked_add(remaining) else {
+            return TransferResult::ArithmeticOverflow;
+        };
+
+        if let Some(entry) = self.entry_mut(destination) {
+            entry.balance = new_dest;
+        }
+        self.slots[slot] = None;
+        self.free_slots.push(slot);
+        TransferResult::Success
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_close_account_refunds_destination() {
+        let mut ledger = Ledger::new();
+        ledger.create_account("alice", Lamports(300));
+        ledger.create_account("bob", Lamports(100));
+
+        assert_eq!(ledger.close_account("alice", "bob"), TransferResult::Success);
+        assert_eq!(ledger.balance("alice"), None);
+        assert_eq!(ledger.balance("bob"), Some(Lamports(400)));
+    }
+
+    #[test]
+    fn test_close_account_to_itself_is_rejected() {
+        let mut ledger = Ledger::new();
+        ledger.create_account("alice", Lamports(300));
+
+        assert_eq!(ledger.close_account("alice", "alice"), TransferResult::InvalidDestination);
+        // 失败时账户和余额都保持不变
+        assert_eq!(ledger.balance("alice"), Some(Lamports(300)));
+    }
+
+    #[test]
+    fn test_close_missing_account() {
+        let mut ledger = Ledger::new();
+        ledger.create_account("bob", Lamports(100));
+
+        assert_eq!(ledger.close_account("alice", "bob"), TransferResult::AccountNotFound);
+        assert_eq!(ledger.close_account("bob", "alice"), TransferResult::AccountNotFound);
+        assert_eq!(ledger.balance("bob"), Some(Lamports(100)));
+    }
+
+    #[test]
+    fn test_closed_slot_is_reused() {
+        let mut ledger = Ledger::new();
+        let alice_slot = ledger.create_account("alice", Lamports(300));
+        ledger.create_account("bob", Lamports(100));
+
+        ledger.close_account("alice", "bob");
+        assert_eq!(ledger.create_account("carol", Lamports(50)), alice_slot);
+        assert_eq!(ledger.create_account("dave", Lamports(50)), Some(2));
+        // 地址不能重复
+        assert_eq!(ledger.create_account("carol", Lamports(50)), None);
+    }
+
+    #[test]
+    fn test_transfer() {
+        let mut ledger = Ledger::new();
+        ledger.create_account("alice", Lamports(300));
+        ledger.create_account("bob", Lamports(100));
+
+        assert_eq!(ledger.transfer("alice", "bob", Lamports(200)), TransferResult::Success);
+        assert_eq!(ledger.transfer("alice", "bob", Lamports(200)), TransferResult::InsufficientBalance);
+        assert_eq!(ledger.transfer("alice", "carol", Lamports(1)), TransferResult::AccountNotFound);
+        assert_eq!(ledger.balance("alice"), Some(Lamports(100)));
+        assert_eq!(ledger.balance("bob"), Some(Lamports(300)));
+    }
+}
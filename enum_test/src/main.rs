@@ -1,19 +1,34 @@
+mod codec;
+mod error;
+mod lamports;
+mod ledger;
+
+use std::error::Error;
+
+use error::ExerciseError;
+use lamports::Lamports;
+use ledger::Ledger;
+
 // Solana转账可能的结果
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum TransferResult {
     Success,        // 转账成功
     InsufficientBalance,  // 余额不足
     AccountNotFound,      // 账户不存在
+    AccountAlreadyExists, // 账户已存在
+    InvalidDestination,   // 收款账户无效（例如关闭账户时退款给自己）
+    ArithmeticOverflow,   // 余额计算溢出
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum SolanaInstruction {
-    Transfer { amount: u64, to_address: String },
-    CreateAccount { initial_balance: u64 },
-    CloseAccount,
+    Transfer { amount: Lamports, to_address: String },
+    CreateAccount { initial_balance: Lamports },
+    CloseAccount { destination: String }, // 剩余lamports退还到destination
 }
 
-fn main() {
+// 顶层的换算、解码等操作出错时用?直接退出，由Box<dyn Error>承载任意错误类型
+fn main() -> Result<(), Box<dyn Error>> {
     let a = TransferResult::Success;
     let b = TransferResult::InsufficientBalance;
     let c = TransferResult::AccountNotFound;
@@ -26,9 +41,9 @@ fn main() {
     print_transfer_result(b);
     print_transfer_result(c);
 
-    let solana_instruction_a = SolanaInstruction::Transfer { amount: 100, to_address: String::from("0x1234567890") };
-    let solana_instruction_b = SolanaInstruction::CreateAccount { initial_balance: 100 };
-    let solana_instruction_c = SolanaInstruction::CloseAccount;
+    let solana_instruction_a = SolanaInstruction::Transfer { amount: Lamports(100), to_address: String::from("0x1234567890") };
+    let solana_instruction_b = SolanaInstruction::CreateAccount { initial_balance: Lamports(100) };
+    let solana_instruction_c = SolanaInstruction::CloseAccount { destination: String::from("0x1234567891") };
 
     println!("{:?}", solana_instruction_a);
     println!("{:?}", solana_instruction_b);
@@ -41,10 +56,10 @@ fn main() {
     let account_balance = find_account("0x1234567890");
     let new_balance = match account_balance {
         Some(balance) => {
-            balance * 10_u64.pow(9)
+            Lamports::from_sol(balance)?
         }
         None => {
-            0
+            Lamports::ZERO
         }
     };
     println!("{:?}", new_balance);
@@ -52,27 +67,27 @@ fn main() {
     let account_balance = find_account("0x1234567891");
     let new_balance = match account_balance {
         Some(balance) => {
-            balance * 10_u64.pow(9)
+            Lamports::from_sol(balance)?
         }
-        None => 0,
+        None => Lamports::ZERO,
     };
     println!("{:?}", new_balance);
 
     let account_balance = find_account("0x1234567892");
     let new_balance = match account_balance {
         Some(balance) => {
-            balance * 10_u64.pow(9)
+            Lamports::from_sol(balance)?
         }
-        None => 0,
+        None => Lamports::ZERO,
     };
     println!("{:?}", new_balance);
     
     let account_balance_error = find_account("0x1234567893");
     let new_balance = match account_balance_error {
         Some(balance) => {
-            balance * 10_u64.pow(9)
+            Lamports::from_sol(balance)?
         }
-        None => 0,
+        None => Lamports::ZERO,
     };
     println!("{:?}", new_balance);
 
@@ -82,7 +97,7 @@ fn main() {
 
     let _none_number: Option<i32> = None;
 
-    let new_balance = transfer_sol(1000, 100);
+    let new_balance = transfer_sol(Lamports(1000), Lamports(100));
     match new_balance {
         Ok(balance) => {
             println!("{}", balance);
@@ -92,9 +107,35 @@ fn main() {
         }
     };
 
-    let new_balance = complex_transfer("0x1234567890", "0x1234567891", 50);
+    // Lamports的checked运算：溢出时返回错误，而不是静默回绕
+    println!("{:?}", Lamports(500).checked_add(Lamports(250)));
+    println!("{:?}", Lamports(u64::MAX).checked_mul(2));
+    println!("{} SOL", Lamports(1_500_000_000).as_sol());
+
+    let new_balance = complex_transfer("0x1234567890", "0x1234567891", Lamports(50));
     println!("{:?}", new_balance);
 
+    // 在账本上真正执行指令
+    let mut ledger = Ledger::new();
+    ledger.create_account("0x1234567891", Lamports(1000));
+    let instructions = vec![
+        SolanaInstruction::CreateAccount { initial_balance: Lamports(500) },
+        SolanaInstruction::Transfer { amount: Lamports(200), to_address: String::from("0x1234567891") },
+        SolanaInstruction::CloseAccount { destination: String::from("0x1234567890") },
+        SolanaInstruction::CloseAccount { destination: String::from("0x1234567891") },
+    ];
+    for instruction in instructions {
+        // 指令先编码成字节传输，执行前再解码
+        let data = instruction.to_bytes();
+        println!("指令数据: {:?}", data);
+        match SolanaInstruction::try_from_bytes(&data) {
+            Ok(instruction) => print_transfer_result(execute_instruction(&mut ledger, "0x1234567890", instruction)),
+            Err(e) => println!("指令解码失败: {}", e),
+        }
+    }
+    println!("{:?}", ledger.balance("0x1234567890"));
+    println!("{:?}", ledger.balance("0x1234567891"));
+    Ok(())
 }
 
 fn print_transfer_result(result: TransferResult) {
@@ -102,6 +143,9 @@ fn print_transfer_result(result: TransferResult) {
         TransferResult::Success => println!("转账成功"),
         TransferResult::InsufficientBalance => println!("余额不足"),
         TransferResult::AccountNotFound => println!("账户不存在"),
+        TransferResult::AccountAlreadyExists => println!("账户已存在"),
+        TransferResult::InvalidDestination => println!("收款账户无效"),
+        TransferResult::ArithmeticOverflow => println!("余额计算溢出"),
     }
 }
 
@@ -113,8 +157,26 @@ fn print_solana_instruction(instruction: SolanaInstruction) {
         SolanaInstruction::CreateAccount { initial_balance } => {
             println!("创建账户，初始余额 {}", initial_balance);
         }
-        SolanaInstruction::CloseAccount => {
-            println!("关闭账户");
+        SolanaInstruction::CloseAccount { destination } => {
+            println!("关闭账户，剩余余额退还到 {}", destination);
+        }
+    }
+}
+
+// 以address的身份在账本上执行指令
+fn execute_instruction(ledger: &mut Ledger, address: &str, instruction: SolanaInstruction) -> TransferResult {
+    match instruction {
+        SolanaInstruction::Transfer { amount, to_address } => {
+            ledger.transfer(address, &to_address, amount)
+        }
+        SolanaInstruction::CreateAccount { initial_balance } => {
+            match ledger.create_account(address, initial_balance) {
+                Some(_) => TransferResult::Success,
+                None => TransferResult::AccountAlreadyExists,
+            }
+        }
+        SolanaInstruction::CloseAccount { destination } => {
+            ledger.close_account(address, &destination)
         }
     }
 }
@@ -129,22 +191,22 @@ fn find_account(address: &str) -> Option<u64> {
 }
 
 fn transfer_sol(
-    from_balance: u64,
-    amount: u64
-) -> Result<u64, String> { // 成功时返回u64，失败时返回String
-    if amount > from_balance {
-        Err("余额不足".to_string()) // Err(值): 失败，包含错误信息
-    } else {
-        Ok(from_balance - amount) // Ok(值): 成功，包含结果
-    }
+    from_balance: Lamports,
+    amount: Lamports
+) -> Result<Lamports, ExerciseError> { // 成功时返回Lamports，失败时返回ExerciseError
+    // checked_sub下溢说明余额不足
+    from_balance
+        .checked_sub(amount)
+        .map_err(|_| ExerciseError::InsufficientFunds)
 }
 
 fn complex_transfer(
     from: &str,
     to: &str,
-    amount: u64
-) -> Result<u64, String> {
-    let from_balance = find_account(from).ok_or("发送方账户不存在")?;
-    let _to_balance = find_account(to).ok_or("接收方账户不存在")?;
+    amount: Lamports
+) -> Result<Lamports, ExerciseError> {
+    let from_balance = find_account(from).ok_or_else(|| ExerciseError::AccountNotFound(from.to_string()))?;
+    let from_balance = Lamports::from_sol(from_balance)?; // LamportsError通过From自动转换
+    let _to_balance = find_account(to).ok_or_else(|| ExerciseError::AccountNotFound(to.to_string()))?;
     transfer_sol(from_balance, amount)
 }
\ No newline at end of file
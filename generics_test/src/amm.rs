@@ -0,0 +1,662 @@
+// 恒定乘积AMM - 类似于SPL Token Swap / Uniswap v2：池子把代币A和B存在两个由池子PDA控制的vault中，
+// 兑换时按x*y=k计算输出，手续费从输入中扣除后留在池子里，所以k只会变大。
+// 提供流动性的人按当前比例存入A和B得到LP代币，销毁LP代币按份额取回A和B
+//
+// 和escrow一样作为模块放在generics_test里：兑换、LP代币的铸造和销毁都要CPI到这里的Token程序，
+// Pool账户也要能进快照和JSON导出，所以指令和账户状态同样加在ProgramInstruction和AccountPayload里
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::cpi::InvokeContext;
+use crate::ledger::AccountPayload;
+use crate::mint::{Mint, MAX_FEE_BASIS_POINTS};
+use crate::pda::derive_pda;
+use crate::safe_math;
+use crate::transaction::Instruction;
+use crate::{AccountMeta, ProgramInstruction, Pubkey, TransactionResult};
+
+pub const POOL_SEED: &[u8] = b"pool";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pool {
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub vault_a: Pubkey,       // 保存代币A的Token账户，authority是池子PDA
+    pub vault_b: Pubkey,       // 保存代币B的Token账户，authority是池子PDA
+    pub lp_mint: Pubkey,       // LP代币的mint，mint authority是池子PDA
+    pub fee_basis_points: u16, // 兑换手续费，从输入数量中扣除
+    pub bump: u8,
+}
+
+impl Pool {
+    // 五个Pubkey(各32) + fee_basis_points(2) + bump(1)
+    pub const LEN: usize = 32 * 5 + 2 + 1;
+}
+
+impl BorshSerialize for Pool {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.mint_a.serialize(out);
+        self.mint_b.serialize(out);
+        self.vault_a.serialize(out);
+        self.vault_b.serialize(out);
+        self.lp_mint.serialize(out);
+        self.fee_basis_points.serialize(out);
+        self.bump.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Pool {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            mint_a: Pubkey::deserialize(buf)?,
+            mint_b: Pubkey::deserialize(buf)?,
+            vault_a: Pubkey::deserialize(buf)?,
+            vault_b: Pubkey::deserialize(buf)?,
+            lp_mint: Pubkey::deserialize(buf)?,
+            fee_basis_points: u16::deserialize(buf)?,
+            bump: u8::deserialize(buf)?,
+        })
+    }
+}
+
+// 池子地址：种子为["pool", mint_a, mint_b]，同一个程序里每个交易对只有一个池子
+pub fn pool_address(mint_a: &Pubkey, mint_b: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[POOL_SEED, mint_a.as_ref(), mint_b.as_ref()], program_id)
+}
+
+// 输入amount_in能换出的数量：扣除手续费后按(x + dx) * (y - dy) >= x * y求dy，向下取整。
+// 储备为空或者换出的数量为0时返回None
+pub fn swap_amount_out(reserve_in: u64, reserve_out: u64, amount_in: u64, fee_basis_points: u16) -> Option<u64> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return None;
+    }
+    let max = MAX_FEE_BASIS_POINTS as u128;
+    let in_after_fee = amount_in as u128 * (max - fee_basis_points.min(MAX_FEE_BASIS_POINTS) as u128) / max;
+    // 结果一定小于reserve_out，转换成u64不会截断
+    match (reserve_out as u128 * in_after_fee / (reserve_in as u128 + in_after_fee)) as u64 {
+        0 => None,
+        amount_out => Some(amount_out),
+    }
+}
+
+// 存入流动性，返回(铸造的LP数量, 实际存入的A, 实际存入的B)。
+// 第一次存入时LP数量为sqrt(a * b)；之后以按比例更少的一边为准，另一边只存入对应的部分。
+// 实际存入数量向上取整，每份LP对应的储备不会因为取整而减少
+pub fn deposit_amounts(reserve_a: u64, reserve_b: u64, lp_supply: u64, max_a: u64, max_b: u64) -> Option<(u64, u64, u64)> {
+    if lp_supply == 0 {
+        // sqrt(u64 * u64)一定在u64范围内
+        let lp = (max_a as u128 * max_b as u128).isqrt() as u64;
+        return (lp > 0).then_some((lp, max_a, max_b));
+    }
+    if reserve_a == 0 || reserve_b == 0 {
+        return None;
+    }
+    let supply = lp_supply as u128;
+    let lp = (max_a as u128 * supply / reserve_a as u128).min(max_b as u128 * supply / reserve_b as u128);
+    if lp == 0 {
+        return None;
+    }
+    let amount_a = (lp * reserve_a as u128).div_ceil(supply);
+    let amount_b = (lp * reserve_b as u128).div_ceil(supply);
+    Some((u64::try_from(lp).ok()?, amount_a as u64, amount_b as u64))
+}
+
+// 销毁lp_amount个LP能取回的(A, B)，向下取整；两边都取不到时返回None
+pub fn withdraw_amounts(reserve_a: u64, reserve_b: u64, lp_supply: u64, lp_amount: u64) -> Option<(u64, u64)> {
+    if lp_amount == 0 || lp_amount > lp_supply {
+        return None;
+    }
+    let share = |reserve: u64| safe_math::checked_mul_div(reserve, lp_amount, lp_supply).ok();
+    match (share(reserve_a)?, share(reserve_b)?) {
+        (0, 0) => None,
+        amounts => Some(amounts),
+    }
+}
+
+// AMM程序的入口函数，注册到Programs后由运行时调用
+pub fn process(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+    let program_id = instruction.program_id;
+    let metas = &instruction.accounts;
+    match instruction.data {
+        ProgramInstruction::InitializePool { fee_basis_points } => initialize(ctx, &program_id, metas, fee_basis_points),
+        ProgramInstruction::AddLiquidity { amount_a, amount_b } => add_liquidity(ctx, &program_id, metas, amount_a, amount_b),
+        ProgramInstruction::RemoveLiquidity { lp_amount } => remove_liquidity(ctx, &program_id, metas, lp_amount),
+        ProgramInstruction::Swap { amount_in, min_amount_out } => swap(ctx, &program_id, metas, amount_in, min_amount_out),
+        _ => Err(TransactionResult::InvalidInstructionData),
+    }
+}
+
+// accounts: [池子账户(可写，空的PDA账户), 创建者(签名), vault_a, vault_b, LP mint]
+fn initialize(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    fee_basis_points: u16,
+) -> Result<(), TransactionResult> {
+    let [pool_meta, creator, vault_a, vault_b, lp_mint, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !creator.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if fee_basis_points > MAX_FEE_BASIS_POINTS {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+    let (vault_a_token, token_program) = ctx.token_account(&vault_a.pubkey)?;
+    let (vault_b_token, _) = ctx.token_account(&vault_b.pubkey)?;
+    let (pool_key, bump) = pool_address(&vault_a_token.mint, &vault_b_token.mint, program_id);
+    if pool_meta.pubkey != pool_key || !pool_meta.is_writable || vault_a_token.mint == vault_b_token.mint {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let mut account = ctx.staged().get_account(&pool_key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    if account.owner != *program_id || account.data != AccountPayload::Empty {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if account.lamports < ctx.staged().rent().minimum_balance(Pool::LEN) {
+        return Err(TransactionResult::InsufficientFunds);
+    }
+    // vault和LP mint都必须是新的、由池子PDA控制的账户，否则池子无法转出代币或铸造LP
+    for vault in [&vault_a_token, &vault_b_token] {
+        if vault.owner != pool_key || vault.amount != 0 {
+            return Err(TransactionResult::InvalidAccount);
+        }
+    }
+    let lp = load_mint(ctx, &lp_mint.pubkey, &token_program)?;
+    if lp.mint_authority != Some(pool_key) || lp.supply != 0 {
+        return Err(TransactionResult::InvalidAccount);
+    }
+
+    account.data = AccountPayload::Pool(Pool {
+        mint_a: vault_a_token.mint,
+        mint_b: vault_b_token.mint,
+        vault_a: vault_a.pubkey,
+        vault_b: vault_b.pubkey,
+        lp_mint: lp_mint.pubkey,
+        fee_basis_points,
+        bump,
+    });
+    Ok(ctx.staged().set_account(pool_key, account)?)
+}
+
+// accounts: [池子账户, 存入者(签名), 存入A的账户(可写), 存入B的账户(可写), 接收LP的账户(可写),
+//            vault_a(可写), vault_b(可写), LP mint(可写)]
+fn add_liquidity(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    max_a: u64,
+    max_b: u64,
+) -> Result<(), TransactionResult> {
+    let [pool_meta, owner, source_a, source_b, lp_account, vault_a, vault_b, lp_mint, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !owner.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let pool = load(ctx, program_id, &pool_meta.pubkey)?;
+    if pool.vault_a != vault_a.pubkey || pool.vault_b != vault_b.pubkey || pool.lp_mint != lp_mint.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let (reserve_a, reserve_b, token_program) = reserves(ctx, &pool)?;
+    let lp_supply = load_mint(ctx, &pool.lp_mint, &token_program)?.supply;
+    let (lp_amount, amount_a, amount_b) =
+        deposit_amounts(reserve_a, reserve_b, lp_supply, max_a, max_b).ok_or(TransactionResult::InsufficientLiquidity)?;
+
+    for (source, vault, amount) in [(source_a, vault_a, amount_a), (source_b, vault_b, amount_b)] {
+        // 带转账手续费或抽成钩子的mint实际到账会更少，按amount铸造LP会稀释其他人的份额
+        if deposit(ctx, metas, token_program, owner, source, vault, amount)? != amount {
+            return Err(TransactionResult::InvalidAccount);
+        }
+    }
+    let bump = [pool.bump];
+    let seeds: &[&[u8]] = &[POOL_SEED, pool.mint_a.as_ref(), pool.mint_b.as_ref(), &bump];
+    let mint_lp = Instruction::new(
+        token_program,
+        ProgramInstruction::Mint { amount: lp_amount },
+        vec![
+            AccountMeta::new(lp_account.pubkey, token_program, false),
+            AccountMeta::new(pool.lp_mint, token_program, false),
+            AccountMeta::new_readonly(pool_meta.pubkey, *program_id, true),
+        ],
+    );
+    ctx.invoke_signed(&mint_lp, metas, &[seeds])
+}
+
+// accounts: [池子账户, LP持有者(签名), LP账户(可写), 接收A的账户(可写), 接收B的账户(可写),
+//            vault_a(可写), vault_b(可写), LP mint(可写)]
+fn remove_liquidity(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    lp_amount: u64,
+) -> Result<(), TransactionResult> {
+    let [pool_meta, owner, lp_account, destination_a, destination_b, vault_a, vault_b, lp_mint, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !owner.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let pool = load(ctx, program_id, &pool_meta.pubkey)?;
+    if pool.vault_a != vault_a.pubkey || pool.vault_b != vault_b.pubkey || pool.lp_mint != lp_mint.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let (reserve_a, reserve_b, token_program) = reserves(ctx, &pool)?;
+    let lp_supply = load_mint(ctx, &pool.lp_mint, &token_program)?.supply;
+    let (amount_a, amount_b) =
+        withdraw_amounts(reserve_a, reserve_b, lp_supply, lp_amount).ok_or(TransactionResult::InsufficientLiquidity)?;
+
+    // 先销毁LP：持有的LP不够时整条指令失败，不会转出任何代币
+    let burn = Instruction::new(
+        token_program,
+        ProgramInstruction::Burn { amount: lp_amount },
+        vec![
+            AccountMeta::new(lp_account.pubkey, token_program, false),
+            AccountMeta::new(pool.lp_mint, token_program, false),
+            AccountMeta::new_readonly(owner.pubkey, owner.owner, true),
+        ],
+    );
+    ctx.invoke(&burn, metas)?;
+    for (vault, destination, amount) in [(pool.vault_a, destination_a, amount_a), (pool.vault_b, destination_b, amount_b)] {
+        if amount > 0 {
+            withdraw(ctx, program_id, metas, pool_meta, &pool, (vault, destination.pubkey), amount, token_program)?;
+        }
+    }
+    Ok(())
+}
+
+// accounts: [池子账户, 兑换者(签名), 付出的Token账户(可写), 接收的Token账户(可写), vault_a(可写), vault_b(可写)]
+// 兑换方向由付出账户的mint决定
+fn swap(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    amount_in: u64,
+    min_amount_out: u64,
+) -> Result<(), TransactionResult> {
+    let [pool_meta, owner, source, destination, vault_a, vault_b, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !owner.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let pool = load(ctx, program_id, &pool_meta.pubkey)?;
+    if pool.vault_a != vault_a.pubkey || pool.vault_b != vault_b.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let (source_token, token_program) = ctx.token_account(&source.pubkey)?;
+    let (vault_in, vault_out) = match source_token.mint {
+        mint if mint == pool.mint_a => (vault_a, vault_b),
+        mint if mint == pool.mint_b => (vault_b, vault_a),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    let reserve_in = ctx.token_account(&vault_in.pubkey)?.0.amount;
+    let reserve_out = ctx.token_account(&vault_out.pubkey)?.0.amount;
+
+    // 按vault实际到账的数量计算输出，输入的mint收转账手续费时k也不会减少
+    let received = deposit(ctx, metas, token_program, owner, source, vault_in, amount_in)?;
+    let amount_out =
+        swap_amount_out(reserve_in, reserve_out, received, pool.fee_basis_points).ok_or(TransactionResult::InsufficientLiquidity)?;
+    if amount_out < min_amount_out {
+        return Err(TransactionResult::SlippageExceeded);
+    }
+    withdraw(ctx, program_id, metas, pool_meta, &pool, (vault_out.pubkey, destination.pubkey), amount_out, token_program)
+}
+
+// 用户签名把amount从source转进vault，返回vault实际增加的数量
+fn deposit(
+    ctx: &mut InvokeContext<'_, '_>,
+    metas: &[AccountMeta],
+    token_program: Pubkey,
+    owner: &AccountMeta,
+    source: &AccountMeta,
+    vault: &AccountMeta,
+    amount: u64,
+) -> Result<u64, TransactionResult> {
+    let before = ctx.token_account(&vault.pubkey)?.0.amount;
+    let transfer = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(source.pubkey, token_program, false),
+            AccountMeta::new(vault.pubkey, token_program, false),
+            AccountMeta::new_readonly(owner.pubkey, owner.owner, true),
+        ],
+    );
+    ctx.invoke(&transfer, metas)?;
+    Ok(ctx.token_account(&vault.pubkey)?.0.amount - before)
+}
+
+// 池子PDA通过invoke_signed签名，把amount从vault转给destination
+#[allow(clippy::too_many_arguments)]
+fn withdraw(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    pool_meta: &AccountMeta,
+    pool: &Pool,
+    (vault, destination): (Pubkey, Pubkey),
+    amount: u64,
+    token_program: Pubkey,
+) -> Result<(), TransactionResult> {
+    let bump = [pool.bump];
+    let seeds: &[&[u8]] = &[POOL_SEED, pool.mint_a.as_ref(), pool.mint_b.as_ref(), &bump];
+    let transfer = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(vault, token_program, false),
+            AccountMeta::new(destination, token_program, false),
+            AccountMeta::new_readonly(pool_meta.pubkey, *program_id, true),
+        ],
+    );
+    ctx.invoke_signed(&transfer, metas, &[seeds])
+}
+
+// 两个vault的余额和它们所属的Token程序
+fn reserves(ctx: &mut InvokeContext<'_, '_>, pool: &Pool) -> Result<(u64, u64, Pubkey), TransactionResult> {
+    let (vault_a, token_program) = ctx.token_account(&pool.vault_a)?;
+    let (vault_b, _) = ctx.token_account(&pool.vault_b)?;
+    Ok((vault_a.amount, vault_b.amount, token_program))
+}
+
+fn load_mint(ctx: &mut InvokeContext<'_, '_>, key: &Pubkey, token_program: &Pubkey) -> Result<Mint, TransactionResult> {
+    match ctx.staged().get_account(key) {
+        Some(account) if account.owner == *token_program => match &account.data {
+            AccountPayload::Mint(mint) => Ok(mint.clone()),
+            _ => Err(TransactionResult::InvalidAccount),
+        },
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+// 读取本程序拥有的池子账户
+fn load(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, key: &Pubkey) -> Result<Pool, TransactionResult> {
+    match ctx.staged().get_account(key) {
+        Some(account) if account.owner == *program_id => match &account.data {
+            AccountPayload::Pool(pool) => Ok(pool.clone()),
+            _ => Err(TransactionResult::InvalidAccount),
+        },
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpi::{Entrypoint, Programs};
+    use crate::ledger::{Account, Ledger};
+    use crate::signature::{Keypair, Signer};
+    use crate::test_utils::{create_mint, create_token_account, funded_keypair, sign_and_execute, token_amount, XorShift};
+    use crate::transaction::TransactionError;
+    use crate::SYSTEM_PROGRAM_ID;
+
+    // 池子状态：(reserve_a, reserve_b, lp_supply)
+    type State = (u64, u64, u64);
+
+    // 兑换（LP供应量不变）不能让k减少；存入和取出不能让每份LP对应的A和B减少
+    fn assert_invariant(before: State, after: State) {
+        let (a0, b0, s0) = before;
+        let (a1, b1, s1) = after;
+        if s0 == s1 {
+            assert!(a1 as u128 * b1 as u128 >= a0 as u128 * b0 as u128, "k减少: {:?} -> {:?}", before, after);
+        } else if s0 > 0 && s1 > 0 {
+            assert!(a1 as u128 * s0 as u128 >= a0 as u128 * s1 as u128, "每份LP的A减少: {:?} -> {:?}", before, after);
+            assert!(b1 as u128 * s0 as u128 >= b0 as u128 * s1 as u128, "每份LP的B减少: {:?} -> {:?}", before, after);
+        }
+    }
+
+    #[test]
+    fn test_swap_amount_out() {
+        // 没有手续费：1000 * 100 / 1100 = 90.9，向下取整
+        assert_eq!(swap_amount_out(1_000, 1_000, 100, 0), Some(90));
+        // 0.3%手续费：输入先扣成99
+        assert_eq!(swap_amount_out(1_000, 1_000, 100, 30), Some(90));
+        assert_eq!(swap_amount_out(1_000_000, 1_000_000, 1_000, 30), Some(996));
+        // 输入再大也换不空池子
+        assert_eq!(swap_amount_out(1_000, 1_000, u64::MAX, 0), Some(999));
+        assert_eq!(swap_amount_out(0, 1_000, 100, 30), None);
+        assert_eq!(swap_amount_out(1_000, 1_000, 1, 30), None);
+    }
+
+    #[test]
+    fn test_deposit_and_withdraw_amounts() {
+        // 第一次存入按sqrt(a * b)铸造LP
+        assert_eq!(deposit_amounts(0, 0, 0, 100, 400), Some((200, 100, 400)));
+        // 之后按比例，以较少的一边为准，另一边向上取整
+        assert_eq!(deposit_amounts(100, 400, 200, 50, 1_000), Some((100, 50, 200)));
+        assert_eq!(deposit_amounts(100, 401, 200, 50, 1_000), Some((100, 50, 201)));
+        assert_eq!(deposit_amounts(1_000, 1_000, 10, 1, 1_000), None);
+
+        assert_eq!(withdraw_amounts(100, 400, 200, 100), Some((50, 200)));
+        assert_eq!(withdraw_amounts(101, 401, 200, 100), Some((50, 200)));
+        assert_eq!(withdraw_amounts(100, 400, 200, 201), None);
+        assert_eq!(withdraw_amounts(100, 400, 200, 0), None);
+    }
+
+    // 性质测试：随机的存入、取出、兑换序列，每一步都检查不变量
+    #[test]
+    fn test_invariant_never_decreases() {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..200 {
+            let fee = rng.next(100) as u16;
+            let mut state: State = (0, 0, 0);
+            for _ in 0..200 {
+                let (a, b, supply) = state;
+                let next = match rng.next(4) {
+                    0 => deposit_amounts(a, b, supply, rng.next(1_000_000) + 1, rng.next(1_000_000) + 1)
+                        .map(|(lp, amount_a, amount_b)| (a + amount_a, b + amount_b, supply + lp)),
+                    1 => {
+                        let lp = rng.next(supply + 1);
+                        withdraw_amounts(a, b, supply, lp).map(|(amount_a, amount_b)| (a - amount_a, b - amount_b, supply - lp))
+                    }
+                    2 => {
+                        let amount_in = rng.next(1_000_000) + 1;
+                        swap_amount_out(a, b, amount_in, fee).map(|amount_out| (a + amount_in, b - amount_out, supply))
+                    }
+                    _ => {
+                        let amount_in = rng.next(1_000_000) + 1;
+                        swap_amount_out(b, a, amount_in, fee).map(|amount_out| (a - amount_out, b + amount_in, supply))
+                    }
+                };
+                if let Some(next) = next {
+                    assert_invariant(state, next);
+                    state = next;
+                }
+            }
+        }
+    }
+
+    struct Fixture {
+        ledger: Ledger,
+        programs: Programs,
+        amm_program: Pubkey,
+        token_program: Pubkey,
+        alice: Keypair, // 提供流动性
+        bob: Keypair,   // 兑换
+        alice_a: Pubkey,
+        alice_b: Pubkey,
+        alice_lp: Pubkey,
+        bob_a: Pubkey,
+        bob_b: Pubkey,
+        vault_a: Pubkey,
+        vault_b: Pubkey,
+        lp_mint: Pubkey,
+        pool: Pubkey,
+    }
+
+    fn fixture() -> Fixture {
+        let mut ledger = Ledger::default();
+        let (amm_program, token_program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (alice, bob) = (funded_keypair(&mut ledger), funded_keypair(&mut ledger));
+        let [mint_a, mint_b] = [(); 2].map(|_| create_mint(&mut ledger, token_program, Mint::new(0, Pubkey::new_unique())));
+        let (pool, _) = pool_address(&mint_a, &mint_b, &amm_program);
+        let lp_mint = create_mint(&mut ledger, token_program, Mint::new(0, pool));
+        let mut token = |mint, owner, amount| create_token_account(&mut ledger, token_program, Pubkey::new_unique(), mint, owner, amount);
+        let alice_a = token(mint_a, alice.pubkey(), 1_000_000);
+        let alice_b = token(mint_b, alice.pubkey(), 1_000_000);
+        let alice_lp = token(lp_mint, alice.pubkey(), 0);
+        let bob_a = token(mint_a, bob.pubkey(), 100_000);
+        let bob_b = token(mint_b, bob.pubkey(), 100_000);
+        let vault_a = token(mint_a, pool, 0);
+        let vault_b = token(mint_b, pool, 0);
+        let lamports = ledger.rent().minimum_balance(Pool::LEN);
+        ledger.create_account(pool, Account::new(lamports, amm_program, AccountPayload::Empty)).unwrap();
+
+        let programs = Programs::from([(amm_program, process as Entrypoint)]);
+        let mut f = Fixture {
+            ledger,
+            programs,
+            amm_program,
+            token_program,
+            alice,
+            bob,
+            alice_a,
+            alice_b,
+            alice_lp,
+            bob_a,
+            bob_b,
+            vault_a,
+            vault_b,
+            lp_mint,
+            pool,
+        };
+        let accounts = vec![
+            AccountMeta::new(f.pool, f.amm_program, false),
+            AccountMeta::new_readonly(f.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+            AccountMeta::new_readonly(f.vault_a, f.token_program, false),
+            AccountMeta::new_readonly(f.vault_b, f.token_program, false),
+            AccountMeta::new_readonly(f.lp_mint, f.token_program, false),
+        ];
+        let instruction = Instruction::new(f.amm_program, ProgramInstruction::InitializePool { fee_basis_points: 30 }, accounts);
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.alice, vec![instruction]), Ok(()));
+        f
+    }
+
+    impl Fixture {
+        fn state(&self) -> State {
+            let supply = match &self.ledger.get_account(&self.lp_mint).unwrap().data {
+                AccountPayload::Mint(mint) => mint.supply,
+                _ => panic!("不是Mint账户"),
+            };
+            (token_amount(&self.ledger, &self.vault_a), token_amount(&self.ledger, &self.vault_b), supply)
+        }
+
+        fn add_liquidity(&mut self, amount_a: u64, amount_b: u64) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new_readonly(self.pool, self.amm_program, false),
+                AccountMeta::new_readonly(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.alice_a, self.token_program, false),
+                AccountMeta::new(self.alice_b, self.token_program, false),
+                AccountMeta::new(self.alice_lp, self.token_program, false),
+                AccountMeta::new(self.vault_a, self.token_program, false),
+                AccountMeta::new(self.vault_b, self.token_program, false),
+                AccountMeta::new(self.lp_mint, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.amm_program, ProgramInstruction::AddLiquidity { amount_a, amount_b }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.alice, vec![instruction])
+        }
+
+        fn remove_liquidity(&mut self, lp_amount: u64) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new_readonly(self.pool, self.amm_program, false),
+                AccountMeta::new_readonly(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.alice_lp, self.token_program, false),
+                AccountMeta::new(self.alice_a, self.token_program, false),
+                AccountMeta::new(self.alice_b, self.token_program, false),
+                AccountMeta::new(self.vault_a, self.token_program, false),
+                AccountMeta::new(self.vault_b, self.token_program, false),
+                AccountMeta::new(self.lp_mint, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.amm_program, ProgramInstruction::RemoveLiquidity { lp_amount }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.alice, vec![instruction])
+        }
+
+        // a_to_b为true时bob用代币A换代币B
+        fn swap(&mut self, a_to_b: bool, amount_in: u64, min_amount_out: u64) -> Result<(), TransactionError> {
+            let (source, destination) = if a_to_b { (self.bob_a, self.bob_b) } else { (self.bob_b, self.bob_a) };
+            let accounts = vec![
+                AccountMeta::new_readonly(self.pool, self.amm_program, false),
+                AccountMeta::new_readonly(self.bob.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(source, self.token_program, false),
+                AccountMeta::new(destination, self.token_program, false),
+                AccountMeta::new(self.vault_a, self.token_program, false),
+                AccountMeta::new(self.vault_b, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.amm_program, ProgramInstruction::Swap { amount_in, min_amount_out }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.bob, vec![instruction])
+        }
+    }
+
+    #[test]
+    fn test_add_swap_remove() {
+        let mut f = fixture();
+        // 空池子不能兑换
+        assert_eq!(f.swap(true, 100, 0), Err(TransactionError::InstructionError(0, TransactionResult::InsufficientLiquidity)));
+
+        assert_eq!(f.add_liquidity(100_000, 400_000), Ok(()));
+        assert_eq!(f.state(), (100_000, 400_000, 200_000));
+        assert_eq!(token_amount(&f.ledger, &f.alice_lp), 200_000);
+        // 按现有比例1:4存入，多给的B不会被取走
+        assert_eq!(f.add_liquidity(10_000, 100_000), Ok(()));
+        assert_eq!(f.state(), (110_000, 440_000, 220_000));
+
+        let expected = swap_amount_out(110_000, 440_000, 1_000, 30).unwrap();
+        assert_eq!(f.swap(true, 1_000, expected), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.bob_a), token_amount(&f.ledger, &f.bob_b)), (99_000, 100_000 + expected));
+        assert_eq!(f.state(), (111_000, 440_000 - expected, 220_000));
+
+        // 全部取出，池子清空
+        assert_eq!(f.remove_liquidity(220_000), Ok(()));
+        assert_eq!(f.state(), (0, 0, 0));
+        assert_eq!(token_amount(&f.ledger, &f.alice_a), 1_000_000 - 110_000 + 111_000);
+    }
+
+    #[test]
+    fn test_swap_slippage_is_atomic() {
+        let mut f = fixture();
+        assert_eq!(f.add_liquidity(100_000, 100_000), Ok(()));
+        let expected = swap_amount_out(100_000, 100_000, 5_000, 30).unwrap();
+        let before = f.state();
+        assert_eq!(f.swap(false, 5_000, expected + 1), Err(TransactionError::InstructionError(0, TransactionResult::SlippageExceeded)));
+        // 输入已经转进vault，但整笔交易回滚
+        assert_eq!(f.state(), before);
+        assert_eq!(token_amount(&f.ledger, &f.bob_b), 100_000);
+        // 取出的LP超过持有量
+        assert_eq!(f.remove_liquidity(100_001), Err(TransactionError::InstructionError(0, TransactionResult::InsufficientLiquidity)));
+    }
+
+    // 通过程序执行随机的指令序列，检查账本上vault余额和LP供应量满足同样的不变量
+    #[test]
+    fn test_program_invariant_never_decreases() {
+        let mut f = fixture();
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        assert_eq!(f.add_liquidity(50_000, 80_000), Ok(()));
+        for _ in 0..300 {
+            let before = f.state();
+            // 余额不足等失败的操作不改变状态，不变量同样成立
+            let _ = match rng.next(4) {
+                0 => f.add_liquidity(rng.next(20_000) + 1, rng.next(20_000) + 1),
+                1 => f.remove_liquidity(rng.next(before.2 / 4 + 1) + 1),
+                2 => f.swap(true, rng.next(5_000) + 1, 0),
+                _ => f.swap(false, rng.next(5_000) + 1, 0),
+            };
+            assert_invariant(before, f.state());
+        }
+    }
+
+    #[test]
+    fn test_borsh_roundtrip_fits_len() {
+        let pool = Pool {
+            mint_a: Pubkey::new_unique(),
+            mint_b: Pubkey::new_unique(),
+            vault_a: Pubkey::new_unique(),
+            vault_b: Pubkey::new_unique(),
+            lp_mint: Pubkey::new_unique(),
+            fee_basis_points: 30,
+            bump: 255,
+        };
+        let mut bytes = Vec::new();
+        pool.serialize(&mut bytes);
+        assert_eq!(bytes.len(), Pool::LEN);
+        assert_eq!(Pool::try_from_slice(&bytes), Ok(pool));
+    }
+}
@@ -0,0 +1,640 @@
+// 英式拍卖 - 卖家把NFT存进由拍卖PDA控制的vault，出价者的代币也存在拍卖PDA控制的出价vault里。
+// 新的出价至少比当前最高价高min_increment。被超过的出价留在vault里，记到原出价者自己的出价记录上，
+// 由他随时用Refund取回——程序不主动往外转账，原出价者关掉自己的Token账户也挡不住新的出价；
+// 到end_slot之后任何人都可以结算：NFT转给最高出价者，出价转给卖家，没人出价时NFT退回卖家
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::cpi::InvokeContext;
+use crate::ledger::{Account, AccountPayload};
+use crate::pda::derive_pda;
+use crate::transaction::Instruction;
+use crate::{AccountMeta, ProgramInstruction, Pubkey, TransactionResult};
+
+pub const AUCTION_SEED: &[u8] = b"auction";
+pub const BID_RECORD_SEED: &[u8] = b"bid";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Auction {
+    pub seller: Pubkey,
+    pub nft_mint: Pubkey,
+    pub nft_vault: Pubkey,      // 保存拍品的Token账户，authority是拍卖PDA
+    pub bid_vault: Pubkey,      // 保存当前最高出价的Token账户，authority是拍卖PDA
+    pub highest_bidder: Pubkey, // 还没有人出价时是默认值
+    pub starting_bid: u64,      // 第一次出价的最低数量
+    pub min_increment: u64,     // 之后每次出价至少比最高价高这么多
+    pub end_slot: u64,          // 从这个slot开始不再接受出价
+    pub highest_bid: u64,
+    pub settled: bool,
+    pub bump: u8,
+}
+
+impl Auction {
+    // 五个Pubkey(各32) + 四个u64 + settled(1) + bump(1)
+    pub const LEN: usize = 32 * 5 + 8 * 4 + 1 + 1;
+
+    pub fn has_bids(&self) -> bool {
+        self.highest_bid > 0
+    }
+
+    // 下一次出价至少要达到的数量
+    pub fn minimum_bid(&self) -> Option<u64> {
+        if self.has_bids() { self.highest_bid.checked_add(self.min_increment) } else { Some(self.starting_bid) }
+    }
+}
+
+impl BorshSerialize for Auction {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.seller.serialize(out);
+        self.nft_mint.serialize(out);
+        self.nft_vault.serialize(out);
+        self.bid_vault.serialize(out);
+        self.highest_bidder.serialize(out);
+        self.starting_bid.serialize(out);
+        self.min_increment.serialize(out);
+        self.end_slot.serialize(out);
+        self.highest_bid.serialize(out);
+        self.settled.serialize(out);
+        self.bump.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Auction {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            seller: Pubkey::deserialize(buf)?,
+            nft_mint: Pubkey::deserialize(buf)?,
+            nft_vault: Pubkey::deserialize(buf)?,
+            bid_vault: Pubkey::deserialize(buf)?,
+            highest_bidder: Pubkey::deserialize(buf)?,
+            starting_bid: u64::deserialize(buf)?,
+            min_increment: u64::deserialize(buf)?,
+            end_slot: u64::deserialize(buf)?,
+            highest_bid: u64::deserialize(buf)?,
+            settled: bool::deserialize(buf)?,
+            bump: u8::deserialize(buf)?,
+        })
+    }
+}
+
+// 一个出价者在一次拍卖里被超过、还没有取回的出价
+#[derive(Debug, Clone, PartialEq)]
+pub struct BidRecord {
+    pub auction: Pubkey,
+    pub bidder: Pubkey,
+    pub refundable: u64,
+}
+
+impl BidRecord {
+    // 两个Pubkey(各32) + refundable(8)
+    pub const LEN: usize = 32 * 2 + 8;
+}
+
+impl BorshSerialize for BidRecord {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.auction.serialize(out);
+        self.bidder.serialize(out);
+        self.refundable.serialize(out);
+    }
+}
+
+impl BorshDeserialize for BidRecord {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self { auction: Pubkey::deserialize(buf)?, bidder: Pubkey::deserialize(buf)?, refundable: u64::deserialize(buf)? })
+    }
+}
+
+// 拍卖账户地址：种子为["auction", seller, nft_mint]
+pub fn auction_address(seller: &Pubkey, nft_mint: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[AUCTION_SEED, seller.as_ref(), nft_mint.as_ref()], program_id)
+}
+
+// 出价记录地址：种子为["bid", 拍卖账户, bidder]
+pub fn bid_record_address(auction: &Pubkey, bidder: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[BID_RECORD_SEED, auction.as_ref(), bidder.as_ref()], program_id)
+}
+
+// 拍卖程序的入口函数，注册到Programs后由运行时调用
+pub fn process(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+    let program_id = instruction.program_id;
+    let metas = &instruction.accounts;
+    match instruction.data {
+        ProgramInstruction::CreateAuction { starting_bid, min_increment, end_slot } => {
+            create(ctx, &program_id, metas, (starting_bid, min_increment, end_slot))
+        }
+        ProgramInstruction::PlaceBid { amount } => place_bid(ctx, &program_id, metas, amount),
+        ProgramInstruction::SettleAuction => settle(ctx, &program_id, metas),
+        ProgramInstruction::Refund => refund(ctx, &program_id, metas),
+        _ => Err(TransactionResult::InvalidInstructionData),
+    }
+}
+
+// accounts: [拍卖账户(可写，空的PDA账户), 卖家(签名), 卖家的NFT Token账户(可写),
+//            NFT vault(可写，authority是拍卖PDA), 出价vault(authority是拍卖PDA，余额为0)]
+fn create(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    (starting_bid, min_increment, end_slot): (u64, u64, u64),
+) -> Result<(), TransactionResult> {
+    let [auction_meta, seller, source, nft_vault, bid_vault, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !seller.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if starting_bid == 0 || min_increment == 0 || end_slot <= ctx.slot() {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+    let (nft_token, token_program) = ctx.token_account(&source.pubkey)?;
+    let (auction_key, bump) = auction_address(&seller.pubkey, &nft_token.mint, program_id);
+    if auction_meta.pubkey != auction_key || !auction_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let mut account = ctx.staged().get_account(&auction_key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    if account.owner != *program_id || account.data != AccountPayload::Empty {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if account.lamports < ctx.staged().rent().minimum_balance(Auction::LEN) {
+        return Err(TransactionResult::InsufficientFunds);
+    }
+    let (nft_vault_token, _) = ctx.token_account(&nft_vault.pubkey)?;
+    let (bid_vault_token, _) = ctx.token_account(&bid_vault.pubkey)?;
+    if nft_vault_token.owner != auction_key || nft_vault_token.mint != nft_token.mint || nft_vault_token.amount != 0 {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if bid_vault_token.owner != auction_key || bid_vault_token.amount != 0 {
+        return Err(TransactionResult::InvalidAccount);
+    }
+
+    let deposit = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount: 1 },
+        vec![
+            AccountMeta::new(source.pubkey, token_program, false),
+            AccountMeta::new(nft_vault.pubkey, token_program, false),
+            AccountMeta::new_readonly(seller.pubkey, seller.owner, true),
+        ],
+    );
+    ctx.invoke(&deposit, metas)?;
+    let auction = Auction {
+        seller: seller.pubkey,
+        nft_mint: nft_token.mint,
+        nft_vault: nft_vault.pubkey,
+        bid_vault: bid_vault.pubkey,
+        highest_bidder: Pubkey::default(),
+        starting_bid,
+        min_increment,
+        end_slot,
+        highest_bid: 0,
+        settled: false,
+        bump,
+    };
+    account.data = AccountPayload::Auction(auction);
+    Ok(ctx.staged().set_account(auction_key, account)?)
+}
+
+// accounts: [拍卖账户(可写), 出价者(签名), 出价的Token账户(可写), 出价vault(可写),
+//            出价者的出价记录(可写，第一次出价时是空的PDA账户),
+//            上一个最高出价者的出价记录(可写，第一次出价或者给自己加价时不需要)]
+fn place_bid(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta], amount: u64) -> Result<(), TransactionResult> {
+    let [auction_meta, bidder, source, bid_vault, record_meta, rest @ ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !bidder.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let mut auction = load_auction(ctx, program_id, &auction_meta.pubkey)?;
+    if auction.bid_vault != bid_vault.pubkey || !auction_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if ctx.slot() >= auction.end_slot {
+        return Err(TransactionResult::AuctionEnded);
+    }
+    match auction.minimum_bid() {
+        Some(minimum) if amount >= minimum => {}
+        _ => return Err(TransactionResult::BidTooLow),
+    }
+    let (account, mut record) = load_bid_record(ctx, program_id, &auction_meta.pubkey, record_meta, &bidder.pubkey)?;
+    // 被超过的出价记到原出价者的出价记录上，有人出过价时必须带上他的记录
+    let mut outbid = match (auction.has_bids(), rest.first()) {
+        (false, _) => None,
+        (true, _) if auction.highest_bidder == bidder.pubkey => {
+            record.refundable = record.refundable.checked_add(auction.highest_bid).ok_or(TransactionResult::ArithmeticOverflow)?;
+            None
+        }
+        (true, Some(previous)) => Some(load_bid_record(ctx, program_id, &auction_meta.pubkey, previous, &auction.highest_bidder)?),
+        (true, None) => return Err(TransactionResult::InvalidAccount),
+    };
+    if let Some((_, previous)) = &mut outbid {
+        previous.refundable = previous.refundable.checked_add(auction.highest_bid).ok_or(TransactionResult::ArithmeticOverflow)?;
+    }
+
+    let (_, token_program) = ctx.token_account(&source.pubkey)?;
+    let before = ctx.token_account(&bid_vault.pubkey)?.0.amount;
+    let deposit = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(source.pubkey, token_program, false),
+            AccountMeta::new(bid_vault.pubkey, token_program, false),
+            AccountMeta::new_readonly(bidder.pubkey, bidder.owner, true),
+        ],
+    );
+    ctx.invoke(&deposit, metas)?;
+    // 带转账手续费的代币到账数量会少于出价，不能接受
+    if ctx.token_account(&bid_vault.pubkey)?.0.amount - before != amount {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if let (Some(previous), Some((previous_account, previous_record))) = (rest.first(), outbid) {
+        store_bid_record(ctx, previous.pubkey, previous_account, previous_record)?;
+    }
+    store_bid_record(ctx, record_meta.pubkey, account, record)?;
+    auction.highest_bidder = bidder.pubkey;
+    auction.highest_bid = amount;
+    store_auction(ctx, auction_meta.pubkey, auction)
+}
+
+// accounts: [拍卖账户(可写), NFT vault(可写), 接收NFT的Token账户(可写),
+//            出价vault(可写), 卖家接收出价的Token账户(可写)]
+// 不需要签名，到期后任何人都可以结算
+fn settle(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let [auction_meta, nft_vault, nft_destination, bid_vault, proceeds, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    let mut auction = load_auction(ctx, program_id, &auction_meta.pubkey)?;
+    if auction.nft_vault != nft_vault.pubkey || auction.bid_vault != bid_vault.pubkey || !auction_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if auction.settled {
+        return Err(TransactionResult::NothingToClaim);
+    }
+    if ctx.slot() < auction.end_slot {
+        return Err(TransactionResult::AuctionInProgress);
+    }
+    // 没人出价时拍品退回卖家
+    let winner = if auction.has_bids() { auction.highest_bidder } else { auction.seller };
+    if ctx.token_account(&nft_destination.pubkey)?.0.owner != winner {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    release(ctx, program_id, metas, (auction_meta, &auction), (auction.nft_vault, nft_destination.pubkey), 1)?;
+    if auction.has_bids() {
+        if ctx.token_account(&proceeds.pubkey)?.0.owner != auction.seller {
+            return Err(TransactionResult::InvalidAccount);
+        }
+        release(ctx, program_id, metas, (auction_meta, &auction), (auction.bid_vault, proceeds.pubkey), auction.highest_bid)?;
+    }
+    auction.settled = true;
+    store_auction(ctx, auction_meta.pubkey, auction)
+}
+
+// accounts: [拍卖账户, 出价记录(可写), 出价者(签名), 出价vault(可写), 接收的Token账户(可写)]
+// 被超过的出价随时可以取回，拍卖结算前后都行
+fn refund(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let [auction_meta, record_meta, bidder, bid_vault, destination, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    let auction = load_auction(ctx, program_id, &auction_meta.pubkey)?;
+    if auction.bid_vault != bid_vault.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if !bidder.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let (account, mut record) = load_bid_record(ctx, program_id, &auction_meta.pubkey, record_meta, &bidder.pubkey)?;
+    if record.refundable == 0 {
+        return Err(TransactionResult::NothingToClaim);
+    }
+    let amount = record.refundable;
+    record.refundable = 0;
+    release(ctx, program_id, metas, (auction_meta, &auction), (auction.bid_vault, destination.pubkey), amount)?;
+    store_bid_record(ctx, record_meta.pubkey, account, record)
+}
+
+fn load_auction(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, key: &Pubkey) -> Result<Auction, TransactionResult> {
+    match ctx.staged().get_account(key) {
+        Some(account) if account.owner == *program_id => match &account.data {
+            AccountPayload::Auction(auction) => Ok(auction.clone()),
+            _ => Err(TransactionResult::InvalidAccount),
+        },
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+fn store_auction(ctx: &mut InvokeContext<'_, '_>, key: Pubkey, auction: Auction) -> Result<(), TransactionResult> {
+    let mut account = ctx.staged().get_account(&key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    account.data = AccountPayload::Auction(auction);
+    Ok(ctx.staged().set_account(key, account)?)
+}
+
+// 读取bidder在这次拍卖的出价记录，租金足够的空PDA账户当作新记录
+fn load_bid_record(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    auction: &Pubkey,
+    record_meta: &AccountMeta,
+    bidder: &Pubkey,
+) -> Result<(Account, BidRecord), TransactionResult> {
+    let (record_key, _) = bid_record_address(auction, bidder, program_id);
+    if record_meta.pubkey != record_key || !record_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let minimum = ctx.staged().rent().minimum_balance(BidRecord::LEN);
+    let account = match ctx.staged().get_account(&record_key) {
+        Some(account) if account.owner == *program_id => account.clone(),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    let record = match &account.data {
+        AccountPayload::BidRecord(record) => record.clone(),
+        AccountPayload::Empty if account.lamports >= minimum => BidRecord { auction: *auction, bidder: *bidder, refundable: 0 },
+        AccountPayload::Empty => return Err(TransactionResult::InsufficientFunds),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    Ok((account, record))
+}
+
+fn store_bid_record(ctx: &mut InvokeContext<'_, '_>, key: Pubkey, mut account: Account, record: BidRecord) -> Result<(), TransactionResult> {
+    account.data = AccountPayload::BidRecord(record);
+    Ok(ctx.staged().set_account(key, account)?)
+}
+
+// 拍卖PDA签名，把amount从vault转到destination
+fn release(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    (auction_meta, auction): (&AccountMeta, &Auction),
+    (vault, destination): (Pubkey, Pubkey),
+    amount: u64,
+) -> Result<(), TransactionResult> {
+    let (_, token_program) = ctx.token_account(&vault)?;
+    let bump = [auction.bump];
+    let seeds: &[&[u8]] = &[AUCTION_SEED, auction.seller.as_ref(), auction.nft_mint.as_ref(), &bump];
+    let transfer = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(vault, token_program, false),
+            AccountMeta::new(destination, token_program, false),
+            AccountMeta::new_readonly(auction_meta.pubkey, *program_id, true),
+        ],
+    );
+    ctx.invoke_signed(&transfer, metas, &[seeds])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpi::{Entrypoint, Programs};
+    use crate::ledger::{Account, Ledger};
+    use crate::signature::{Keypair, Signer};
+    use crate::test_utils::{create_token_account, funded_keypair, sign_and_execute, token_amount};
+    use crate::transaction::TransactionError;
+    use crate::SYSTEM_PROGRAM_ID;
+
+    const END_SLOT: u64 = 100;
+
+    struct Fixture {
+        ledger: Ledger,
+        programs: Programs,
+        auction_program: Pubkey,
+        token_program: Pubkey,
+        seller: Keypair,
+        bidders: [Keypair; 2],
+        seller_nft: Pubkey,
+        seller_proceeds: Pubkey,
+        bidder_funds: [Pubkey; 2], // 两个出价者各有1000个出价代币
+        bidder_nfts: [Pubkey; 2],
+        nft_vault: Pubkey,
+        bid_vault: Pubkey,
+        auction: Pubkey,
+        bid_records: [Pubkey; 2],
+    }
+
+    fn fixture() -> Fixture {
+        let mut ledger = Ledger::default();
+        let (auction_program, token_program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (nft_mint, bid_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (seller, bidders) = (funded_keypair(&mut ledger), [funded_keypair(&mut ledger), funded_keypair(&mut ledger)]);
+        let (auction, _) = auction_address(&seller.pubkey(), &nft_mint, &auction_program);
+        let [seller_nft, seller_proceeds, nft_vault, bid_vault] = [(); 4].map(|_| Pubkey::new_unique());
+        let (bidder_funds, bidder_nfts) = ([Pubkey::new_unique(), Pubkey::new_unique()], [Pubkey::new_unique(), Pubkey::new_unique()]);
+        for (key, mint, owner, amount) in [
+            (seller_nft, nft_mint, seller.pubkey(), 1),
+            (seller_proceeds, bid_mint, seller.pubkey(), 0),
+            (bidder_funds[0], bid_mint, bidders[0].pubkey(), 1_000),
+            (bidder_funds[1], bid_mint, bidders[1].pubkey(), 1_000),
+            (bidder_nfts[0], nft_mint, bidders[0].pubkey(), 0),
+            (bidder_nfts[1], nft_mint, bidders[1].pubkey(), 0),
+            (nft_vault, nft_mint, auction, 0),
+            (bid_vault, bid_mint, auction, 0),
+        ] {
+            create_token_account(&mut ledger, token_program, key, mint, owner, amount);
+        }
+        let bid_records = bidders.each_ref().map(|bidder| bid_record_address(&auction, &bidder.pubkey(), &auction_program).0);
+        for (key, len) in [(auction, Auction::LEN), (bid_records[0], BidRecord::LEN), (bid_records[1], BidRecord::LEN)] {
+            let lamports = ledger.rent().minimum_balance(len);
+            ledger.create_account(key, Account::new(lamports, auction_program, AccountPayload::Empty)).unwrap();
+        }
+        let programs = Programs::from([(auction_program, process as Entrypoint)]);
+        let mut f = Fixture {
+            ledger,
+            programs,
+            auction_program,
+            token_program,
+            seller,
+            bidders,
+            seller_nft,
+            seller_proceeds,
+            bidder_funds,
+            bidder_nfts,
+            nft_vault,
+            bid_vault,
+            auction,
+            bid_records,
+        };
+        // 起拍价100，每次至少加10
+        let accounts = vec![
+            AccountMeta::new(f.auction, f.auction_program, false),
+            AccountMeta::new_readonly(f.seller.pubkey(), SYSTEM_PROGRAM_ID, true),
+            AccountMeta::new(f.seller_nft, f.token_program, false),
+            AccountMeta::new(f.nft_vault, f.token_program, false),
+            AccountMeta::new_readonly(f.bid_vault, f.token_program, false),
+        ];
+        let data = ProgramInstruction::CreateAuction { starting_bid: 100, min_increment: 10, end_slot: END_SLOT };
+        let instruction = Instruction::new(f.auction_program, data, accounts);
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.seller, vec![instruction]), Ok(()));
+        f
+    }
+
+    fn error(result: TransactionResult) -> Result<(), TransactionError> {
+        Err(TransactionError::InstructionError(0, result))
+    }
+
+    impl Fixture {
+        // previous是上一个最高出价者的出价记录
+        fn bid(&mut self, bidder: usize, amount: u64, previous: Option<Pubkey>) -> Result<(), TransactionError> {
+            let mut accounts = vec![
+                AccountMeta::new(self.auction, self.auction_program, false),
+                AccountMeta::new_readonly(self.bidders[bidder].pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.bidder_funds[bidder], self.token_program, false),
+                AccountMeta::new(self.bid_vault, self.token_program, false),
+                AccountMeta::new(self.bid_records[bidder], self.auction_program, false),
+            ];
+            accounts.extend(previous.map(|key| AccountMeta::new(key, self.auction_program, false)));
+            let instruction = Instruction::new(self.auction_program, ProgramInstruction::PlaceBid { amount }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.bidders[bidder], vec![instruction])
+        }
+
+        // 由第一个出价者付费发起结算
+        fn settle(&mut self, nft_destination: Pubkey) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new(self.auction, self.auction_program, false),
+                AccountMeta::new(self.nft_vault, self.token_program, false),
+                AccountMeta::new(nft_destination, self.token_program, false),
+                AccountMeta::new(self.bid_vault, self.token_program, false),
+                AccountMeta::new(self.seller_proceeds, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.auction_program, ProgramInstruction::SettleAuction, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.bidders[0], vec![instruction])
+        }
+
+        fn refund(&mut self, bidder: usize, destination: Pubkey) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new_readonly(self.auction, self.auction_program, false),
+                AccountMeta::new(self.bid_records[bidder], self.auction_program, false),
+                AccountMeta::new_readonly(self.bidders[bidder].pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.bid_vault, self.token_program, false),
+                AccountMeta::new(destination, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.auction_program, ProgramInstruction::Refund, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.bidders[bidder], vec![instruction])
+        }
+
+        fn refundable(&self, bidder: usize) -> u64 {
+            match self.ledger.get_account(&self.bid_records[bidder]).map(|account| &account.data) {
+                Some(AccountPayload::BidRecord(record)) => record.refundable,
+                _ => 0,
+            }
+        }
+
+        fn advance_to(&mut self, slot: u64) {
+            let slots = slot - self.ledger.slot();
+            self.ledger.tick(slots);
+        }
+    }
+
+    #[test]
+    fn test_minimum_bid() {
+        let mut auction = Auction {
+            seller: Pubkey::new_unique(),
+            nft_mint: Pubkey::new_unique(),
+            nft_vault: Pubkey::new_unique(),
+            bid_vault: Pubkey::new_unique(),
+            highest_bidder: Pubkey::default(),
+            starting_bid: 100,
+            min_increment: 10,
+            end_slot: END_SLOT,
+            highest_bid: 0,
+            settled: false,
+            bump: 255,
+        };
+        assert_eq!(auction.minimum_bid(), Some(100));
+        auction.highest_bid = 150;
+        assert_eq!(auction.minimum_bid(), Some(160));
+        auction.highest_bid = u64::MAX;
+        assert_eq!(auction.minimum_bid(), None);
+
+        let mut bytes = Vec::new();
+        auction.serialize(&mut bytes);
+        assert_eq!(bytes.len(), Auction::LEN);
+        assert_eq!(Auction::deserialize(&mut bytes.as_slice()), Ok(auction));
+
+        let record = BidRecord { auction: Pubkey::new_unique(), bidder: Pubkey::new_unique(), refundable: 150 };
+        let mut bytes = Vec::new();
+        record.serialize(&mut bytes);
+        assert_eq!(bytes.len(), BidRecord::LEN);
+        assert_eq!(BidRecord::deserialize(&mut bytes.as_slice()), Ok(record));
+    }
+
+    #[test]
+    fn test_outbid_bidder_claims_refund() {
+        let mut f = fixture();
+        assert_eq!(token_amount(&f.ledger, &f.nft_vault), 1);
+        assert_eq!(f.bid(0, 99, None), error(TransactionResult::BidTooLow));
+        assert_eq!(f.bid(0, 100, None), Ok(()));
+        assert_eq!(f.bid(1, 105, Some(f.bid_records[0])), error(TransactionResult::BidTooLow));
+        // 不带上一个出价者的出价记录不能出价
+        assert_eq!(f.bid(1, 110, None), error(TransactionResult::InvalidAccount));
+        assert_eq!(f.bid(1, 110, Some(f.bid_records[1])), error(TransactionResult::InvalidAccount));
+
+        // 被超过的出价留在vault里，记到原出价者的出价记录上
+        assert_eq!(f.bid(1, 110, Some(f.bid_records[0])), Ok(()));
+        assert_eq!((f.refundable(0), f.refundable(1)), (100, 0));
+        assert_eq!(f.bid(0, 200, Some(f.bid_records[1])), Ok(()));
+        // 给自己加价时旧的出价记到自己的记录上
+        assert_eq!(f.bid(0, 210, None), Ok(()));
+        assert_eq!((f.refundable(0), f.refundable(1)), (300, 110));
+        assert_eq!(token_amount(&f.ledger, &f.bid_vault), 100 + 110 + 200 + 210);
+
+        // 只有出价者本人能取回
+        assert_eq!(f.refund(1, f.bidder_funds[1]), Ok(()));
+        assert_eq!(f.refund(1, f.bidder_funds[1]), error(TransactionResult::NothingToClaim));
+        assert_eq!(f.refund(0, f.bidder_funds[0]), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.bidder_funds[0]), token_amount(&f.ledger, &f.bidder_funds[1])), (790, 1_000));
+        assert_eq!(token_amount(&f.ledger, &f.bid_vault), 210);
+    }
+
+    #[test]
+    fn test_closed_refund_account_does_not_block_bids() {
+        let mut f = fixture();
+        assert_eq!(f.bid(0, 100, None), Ok(()));
+        // 最高出价者把出价的Token账户清空并关掉，新的出价照样成功
+        let transfer = Instruction::new(f.token_program, ProgramInstruction::Transfer { amount: 900 }, vec![
+            AccountMeta::new(f.bidder_funds[0], f.token_program, false),
+            AccountMeta::new(f.seller_proceeds, f.token_program, false),
+            AccountMeta::new_readonly(f.bidders[0].pubkey(), SYSTEM_PROGRAM_ID, true),
+        ]);
+        let close = Instruction::new(f.token_program, ProgramInstruction::CloseTokenAccount, vec![
+            AccountMeta::new(f.bidder_funds[0], f.token_program, false),
+            AccountMeta::new(f.bidders[0].pubkey(), SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new_readonly(f.bidders[0].pubkey(), SYSTEM_PROGRAM_ID, true),
+        ]);
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.bidders[0], vec![transfer, close]), Ok(()));
+        assert!(f.ledger.get_account(&f.bidder_funds[0]).is_none());
+
+        assert_eq!(f.bid(1, 110, Some(f.bid_records[0])), Ok(()));
+        assert_eq!(f.refundable(0), 100);
+        // 原出价者之后可以把退款取到另一个账户
+        assert_eq!(f.refund(0, f.seller_proceeds), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.seller_proceeds), 1_000);
+    }
+
+    #[test]
+    fn test_settle_after_end_slot() {
+        let mut f = fixture();
+        assert_eq!(f.bid(0, 100, None), Ok(()));
+        assert_eq!(f.bid(1, 150, Some(f.bid_records[0])), Ok(()));
+        assert_eq!(f.settle(f.bidder_nfts[1]), error(TransactionResult::AuctionInProgress));
+
+        f.advance_to(END_SLOT);
+        assert_eq!(f.bid(0, 300, Some(f.bid_records[1])), error(TransactionResult::AuctionEnded));
+        // NFT只能转给最高出价者
+        assert_eq!(f.settle(f.bidder_nfts[0]), error(TransactionResult::InvalidAccount));
+        assert_eq!(f.settle(f.bidder_nfts[1]), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.bidder_nfts[1]), token_amount(&f.ledger, &f.nft_vault)), (1, 0));
+        // 结算只转走最高出价，没取回的退款还留在vault里
+        assert_eq!((token_amount(&f.ledger, &f.seller_proceeds), token_amount(&f.ledger, &f.bid_vault)), (150, 100));
+        assert_eq!(f.settle(f.bidder_nfts[1]), error(TransactionResult::NothingToClaim));
+        assert_eq!(f.refund(0, f.bidder_funds[0]), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.bidder_funds[0]), token_amount(&f.ledger, &f.bid_vault)), (1_000, 0));
+    }
+
+    #[test]
+    fn test_settle_without_bids_returns_nft() {
+        let mut f = fixture();
+        f.advance_to(END_SLOT + 5);
+        assert_eq!(f.settle(f.bidder_nfts[0]), error(TransactionResult::InvalidAccount));
+        assert_eq!(f.settle(f.seller_nft), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.seller_nft), token_amount(&f.ledger, &f.seller_proceeds)), (1, 0));
+    }
+}
@@ -0,0 +1,108 @@
+// 账本审计日志 - 只追加，记录每一次账户修改：谁发起、属于哪条指令、修改前后的账户状态
+
+use std::fmt;
+
+use crate::ledger::Account;
+use crate::Pubkey;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub seq: u64,                // 日志中的序号，从0开始递增
+    pub slot: u64,               // 修改发生时的slot
+    pub instruction_id: u64,     // 同一次账本操作产生的记录共享同一个instruction_id
+    pub actor: Pubkey,           // 发起修改的账户
+    pub key: Pubkey,             // 被修改的账户
+    pub before: Option<Account>, // None表示账户此前不存在
+    pub after: Option<Account>,  // None表示账户被删除
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lamports = |account: &Option<Account>| match account {
+            Some(account) => account.lamports.to_string(),
+            None => String::from("-"),
+        };
+        write!(
+            f,
+            "#{} ix={} by {}: {} -> {} lamports",
+            self.seq,
+            self.instruction_id,
+            self.actor,
+            lamports(&self.before),
+            lamports(&self.after)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    pub fn record(
+        &mut self,
+        slot: u64,
+        instruction_id: u64,
+        actor: Pubkey,
+        key: Pubkey,
+        before: Option<Account>,
+        after: Option<Account>,
+    ) {
+        let seq = self.entries.len() as u64;
+        self.entries.push(AuditEntry { seq, slot, instruction_id, actor, key, before, after });
+    }
+
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // 某个账户的全部修改记录，按发生顺序排列
+    pub fn history<'a>(&'a self, key: &'a Pubkey) -> impl Iterator<Item = &'a AuditEntry> + 'a {
+        self.entries.iter().filter(move |entry| entry.key == *key)
+    }
+
+    // 把某个账户的历史格式化成多行文本，方便演示时打印
+    pub fn dump_history(&self, key: &Pubkey) -> String {
+        let mut out = format!("账户 {} 的修改记录:\n", key);
+        for entry in self.history(key) {
+            out.push_str(&format!("  {}\n", entry));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::AccountPayload;
+
+    #[test]
+    fn test_history_filters_by_account() {
+        let mut log = AuditLog::default();
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let account = |lamports| Some(Account::new(lamports, Pubkey::default(), AccountPayload::Empty));
+
+        log.record(0, 0, alice, alice, None, account(100));
+        log.record(0, 1, alice, alice, account(100), account(60));
+        log.record(0, 1, alice, bob, account(0), account(40));
+
+        let history: Vec<_> = log.history(&alice).collect();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].seq, 1);
+        assert_eq!(history[1].after, account(60));
+        assert_eq!(log.history(&bob).count(), 1);
+
+        let dump = log.dump_history(&alice);
+        assert!(dump.contains("- -> 100 lamports"));
+        assert!(dump.contains("100 -> 60 lamports"));
+    }
+}
@@ -0,0 +1,259 @@
+// 简化版Borsh序列化 - Solana程序中账户数据和指令最常用的二进制格式
+// 规则：整数小端序；String/Vec先写u32长度再写内容；bool和Option的标记各占1字节
+
+use std::fmt;
+
+use crate::Pubkey;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BorshError {
+    UnexpectedEof { needed: usize, remaining: usize }, // 数据不够读
+    InvalidBool(u8),                                   // bool只能是0或1
+    InvalidOptionTag(u8),                              // Option标记只能是0或1
+    InvalidEnumTag(u8),                                // 没有对应的枚举变体
+    InvalidUtf8,                                       // 字符串不是合法UTF-8
+    TrailingBytes(usize),                              // 反序列化后还有多余字节
+    DiscriminatorMismatch { expected: [u8; 8], found: [u8; 8] }, // 账户类型标识不匹配
+    DuplicateKey(Pubkey),                              // 同一个key出现了不止一次
+}
+
+impl fmt::Display for BorshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BorshError::UnexpectedEof { needed, remaining } => {
+                write!(f, "数据不足: 需要{}字节，剩余{}字节", needed, remaining)
+            }
+            BorshError::InvalidBool(value) => write!(f, "无效的bool值: {}", value),
+            BorshError::InvalidOptionTag(tag) => write!(f, "无效的Option标记: {}", tag),
+            BorshError::InvalidEnumTag(tag) => write!(f, "无效的枚举标记: {}", tag),
+            BorshError::InvalidUtf8 => write!(f, "字符串不是合法的UTF-8"),
+            BorshError::TrailingBytes(n) => write!(f, "数据末尾有{}个多余字节", n),
+            BorshError::DiscriminatorMismatch { expected, found } => {
+                write!(f, "账户类型标识不匹配: 期望{:02x?}，实际{:02x?}", expected, found)
+            }
+            BorshError::DuplicateKey(key) => write!(f, "重复的key: {}", key),
+        }
+    }
+}
+
+impl std::error::Error for BorshError {}
+
+pub trait BorshSerialize {
+    fn serialize(&self, out: &mut Vec<u8>);
+}
+
+pub trait BorshDeserialize: Sized {
+    // 从buf开头读取一个值，并把buf前移到未读部分
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError>;
+
+    // 整个切片必须恰好是一个值
+    fn try_from_slice(data: &[u8]) -> Result<Self, BorshError> {
+        let mut buf = data;
+        let value = Self::deserialize(&mut buf)?;
+        if !buf.is_empty() {
+            return Err(BorshError::TrailingBytes(buf.len()));
+        }
+        Ok(value)
+    }
+}
+
+// 从buf中取出n个字节
+pub(crate) fn take<'a>(buf: &mut &'a [u8], n: usize) -> Result<&'a [u8], BorshError> {
+    if buf.len() < n {
+        return Err(BorshError::UnexpectedEof { needed: n, remaining: buf.len() });
+    }
+    let (head, rest) = buf.split_at(n);
+    *buf = rest;
+    Ok(head)
+}
+
+macro_rules! impl_borsh_for_int {
+    ($($t:ty),*) => {$(
+        impl BorshSerialize for $t {
+            fn serialize(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+
+        impl BorshDeserialize for $t {
+            fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+                let bytes = take(buf, std::mem::size_of::<$t>())?;
+                Ok(<$t>::from_le_bytes(bytes.try_into().unwrap()))
+            }
+        }
+    )*};
+}
+
+impl_borsh_for_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl BorshSerialize for () {
+    fn serialize(&self, _out: &mut Vec<u8>) {}
+}
+
+impl BorshDeserialize for () {
+    fn deserialize(_buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(())
+    }
+}
+
+impl BorshSerialize for bool {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl BorshDeserialize for bool {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        match u8::deserialize(buf)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(BorshError::InvalidBool(value)),
+        }
+    }
+}
+
+impl BorshSerialize for str {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).serialize(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl BorshSerialize for String {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.as_str().serialize(out);
+    }
+}
+
+impl BorshDeserialize for String {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        let len = u32::deserialize(buf)? as usize;
+        let bytes = take(buf, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| BorshError::InvalidUtf8)
+    }
+}
+
+impl<T: BorshSerialize> BorshSerialize for Vec<T> {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).serialize(out);
+        for item in self {
+            item.serialize(out);
+        }
+    }
+}
+
+impl<T: BorshDeserialize> BorshDeserialize for Vec<T> {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        let len = u32::deserialize(buf)? as usize;
+        // 长度来自不可信数据，不能直接按它预分配
+        let mut items = Vec::with_capacity(len.min(buf.len()));
+        for _ in 0..len {
+            items.push(T::deserialize(buf)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<T: BorshSerialize> BorshSerialize for Option<T> {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(value) => {
+                out.push(1);
+                value.serialize(out);
+            }
+        }
+    }
+}
+
+impl<T: BorshDeserialize> BorshDeserialize for Option<T> {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        match u8::deserialize(buf)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::deserialize(buf)?)),
+            tag => Err(BorshError::InvalidOptionTag(tag)),
+        }
+    }
+}
+
+// 定长字节数组（比如Hash）和Pubkey一样没有长度前缀
+impl<const N: usize> BorshSerialize for [u8; N] {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+}
+
+impl<const N: usize> BorshDeserialize for [u8; N] {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(take(buf, N)?.try_into().unwrap())
+    }
+}
+
+// Pubkey固定32字节，没有长度前缀
+impl BorshSerialize for Pubkey {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_ref());
+    }
+}
+
+impl BorshDeserialize for Pubkey {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        let bytes = take(buf, 32)?;
+        Ok(Pubkey::new_from_array(bytes.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_vec<T: BorshSerialize + ?Sized>(value: &T) -> Vec<u8> {
+        let mut out = Vec::new();
+        value.serialize(&mut out);
+        out
+    }
+
+    #[test]
+    fn test_primitive_layout() {
+        assert_eq!(to_vec(&0x0102u16), vec![0x02, 0x01]);
+        assert_eq!(to_vec(&true), vec![1]);
+        assert_eq!(to_vec("hi"), vec![2, 0, 0, 0, b'h', b'i']);
+        assert_eq!(to_vec(&Some(7u8)), vec![1, 7]);
+        assert_eq!(to_vec(&vec![1u32]), vec![1, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(to_vec(&[7u8; 3]), vec![7, 7, 7]);
+        assert_eq!(<[u8; 2]>::try_from_slice(&[1, 2]), Ok([1, 2]));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let value = (
+            Pubkey::new_unique(),
+            vec![String::from("alice"), String::from("bob")],
+            Some(-5i64),
+        );
+        let mut out = Vec::new();
+        value.0.serialize(&mut out);
+        value.1.serialize(&mut out);
+        value.2.serialize(&mut out);
+
+        let mut buf = out.as_slice();
+        assert_eq!(Pubkey::deserialize(&mut buf), Ok(value.0));
+        assert_eq!(Vec::<String>::deserialize(&mut buf), Ok(value.1));
+        assert_eq!(Option::<i64>::deserialize(&mut buf), Ok(value.2));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_reject_malformed_data() {
+        assert_eq!(
+            u64::try_from_slice(&[1, 2, 3]),
+            Err(BorshError::UnexpectedEof { needed: 8, remaining: 3 })
+        );
+        assert_eq!(bool::try_from_slice(&[2]), Err(BorshError::InvalidBool(2)));
+        assert_eq!(Option::<u8>::try_from_slice(&[5]), Err(BorshError::InvalidOptionTag(5)));
+        assert_eq!(String::try_from_slice(&[1, 0, 0, 0, 0xff]), Err(BorshError::InvalidUtf8));
+        assert_eq!(u8::try_from_slice(&[1, 2]), Err(BorshError::TrailingBytes(1)));
+        // 声明了很长的Vec但数据不够
+        assert!(Vec::<u8>::try_from_slice(&[0xff, 0xff, 0xff, 0xff]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+// 计算预算 - 模拟Solana的compute units：执行指令和打印日志都要消耗，用完后交易中止
+
+use crate::logs::Logs;
+use crate::TransactionResult;
+
+// 与Solana一致：默认每笔交易20万，最多140万
+pub const DEFAULT_COMPUTE_UNIT_LIMIT: u64 = 200_000;
+pub const MAX_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+
+// 每条指令的固定开销
+pub const INSTRUCTION_COST: u64 = 1_000;
+// 打印日志的最低开销；消息更长时按字节数收费
+pub const LOG_BASE_COST: u64 = 100;
+
+// 除了计量compute units，还收集执行过程中写出的日志
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComputeBudget {
+    limit: u64,
+    consumed: u64,
+    logs: Logs,
+}
+
+impl Default for ComputeBudget {
+    fn default() -> Self {
+        Self::new(DEFAULT_COMPUTE_UNIT_LIMIT)
+    }
+}
+
+impl ComputeBudget {
+    // 超过上限的limit会被截断为MAX_COMPUTE_UNIT_LIMIT
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit: limit.min(MAX_COMPUTE_UNIT_LIMIT),
+            consumed: 0,
+            logs: Logs::new(),
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.limit - self.consumed
+    }
+
+    // 预算不足时不扣费，直接返回ComputeBudgetExceeded
+    pub fn consume(&mut self, units: u64) -> Result<(), TransactionResult> {
+        if units > self.remaining() {
+            self.consumed = self.limit;
+            return Err(TransactionResult::ComputeBudgetExceeded);
+        }
+        self.consumed += units;
+        Ok(())
+    }
+
+    // 写一行程序日志，一般通过msg!调用；预算不足时日志不会写入
+    pub fn log(&mut self, message: &str) -> Result<(), TransactionResult> {
+        self.consume(LOG_BASE_COST.max(message.len() as u64))?;
+        self.logs.push(message);
+        Ok(())
+    }
+
+    pub fn logs(&self) -> &Logs {
+        &self.logs
+    }
+
+    // 运行时自己写的日志（如程序调用记录）不消耗compute units
+    pub fn logs_mut(&mut self) -> &mut Logs {
+        &mut self.logs
+    }
+
+    pub fn take_logs(&mut self) -> Logs {
+        std::mem::take(&mut self.logs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consume_until_exhausted() {
+        let mut budget = ComputeBudget::new(2_500);
+        assert_eq!(budget.consume(INSTRUCTION_COST), Ok(()));
+        assert_eq!(budget.consume(INSTRUCTION_COST), Ok(()));
+        assert_eq!(budget.remaining(), 500);
+        assert_eq!(budget.consume(INSTRUCTION_COST), Err(TransactionResult::ComputeBudgetExceeded));
+        assert_eq!(budget.remaining(), 0);
+    }
+
+    #[test]
+    fn test_log_cost() {
+        let mut budget = ComputeBudget::new(1_000);
+        budget.log("short").unwrap();
+        assert_eq!(budget.consumed(), LOG_BASE_COST);
+        budget.log(&"x".repeat(300)).unwrap();
+        assert_eq!(budget.consumed(), LOG_BASE_COST + 300);
+        assert_eq!(
+            budget.log(&"x".repeat(700)),
+            Err(TransactionResult::ComputeBudgetExceeded)
+        );
+        assert_eq!(budget.logs().len(), 2);
+    }
+
+    #[test]
+    fn test_limit_is_capped() {
+        assert_eq!(ComputeBudget::new(u64::MAX).limit(), MAX_COMPUTE_UNIT_LIMIT);
+        assert_eq!(ComputeBudget::default().limit(), DEFAULT_COMPUTE_UNIT_LIMIT);
+    }
+}
@@ -0,0 +1,291 @@
+// 跨程序调用(CPI) - 程序在执行过程中通过invoke调用另一个程序的指令
+// 每次调用都压入调用栈，栈的深度有上限；被调用程序的错误会一路向上传给调用方
+
+use std::collections::BTreeMap;
+
+use crate::compute::{ComputeBudget, INSTRUCTION_COST};
+use crate::ledger::{AccountPayload, StagedLedger};
+use crate::pda::create_program_address;
+use crate::transaction::{apply_instruction, Instruction};
+use crate::{AccountMeta, Pubkey, TokenAccount, TransactionResult};
+
+// 调用栈最多4层（包括交易中的顶层指令）
+pub const MAX_INVOKE_DEPTH: usize = 4;
+
+// 程序的入口函数，类似于Solana程序的process_instruction
+pub type Entrypoint = fn(&mut InvokeContext<'_, '_>, &Instruction) -> Result<(), TransactionResult>;
+
+// 已部署的程序：program_id -> 入口函数。没有注册的程序使用内置的Token处理逻辑
+pub type Programs = BTreeMap<Pubkey, Entrypoint>;
+
+// 调用栈上的一层：正在执行的程序和它收到的账户。
+// 顶层指令的权限由交易签名校验，CPI的权限由invoke_signed对照上一层校验，
+// 所以这里的is_signer/is_writable是可信的
+struct Frame {
+    program_id: Pubkey,
+    accounts: Vec<AccountMeta>,
+}
+
+// 一笔交易执行期间的上下文：所有指令（包括CPI）共享同一个暂存区和计算预算
+pub struct InvokeContext<'a, 'l> {
+    staged: &'a mut StagedLedger<'l>,
+    budget: &'a mut ComputeBudget,
+    programs: &'a Programs,
+    stack: Vec<Frame>,
+}
+
+impl<'a, 'l> InvokeContext<'a, 'l> {
+    pub fn new(staged: &'a mut StagedLedger<'l>, budget: &'a mut ComputeBudget, programs: &'a Programs) -> Self {
+        Self {
+            staged,
+            budget,
+            programs,
+            stack: Vec::new(),
+        }
+    }
+
+    pub fn staged(&mut self) -> &mut StagedLedger<'l> {
+        self.staged
+    }
+
+    pub fn budget(&mut self) -> &mut ComputeBudget {
+        self.budget
+    }
+
+    // 当前调用栈，栈底是交易中的顶层指令
+    pub fn call_stack(&self) -> Vec<Pubkey> {
+        self.stack.iter().map(|frame| frame.program_id).collect()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // 当前slot，相当于程序读取的Clock sysvar
+    pub fn slot(&self) -> u64 {
+        self.staged.slot()
+    }
+
+    // 读取Token账户的数据和它所属的Token程序，程序要通过CPI操作这个账户时用得到
+    pub fn token_account(&self, key: &Pubkey) -> Result<(TokenAccount, Pubkey), TransactionResult> {
+        match self.staged.get_account(key) {
+            Some(account) => match &account.data {
+                AccountPayload::Token(token) => Ok((token.clone(), account.owner)),
+                _ => Err(TransactionResult::InvalidAccount),
+            },
+            None => Err(TransactionResult::InvalidAccount),
+        }
+    }
+
+    // 程序通过msg!(ctx, ...)写日志
+    pub fn log(&mut self, message: &str) -> Result<(), TransactionResult> {
+        self.budget.log(message)
+    }
+
+    // 执行一条指令：压栈、扣除指令开销、调用目标程序，返回时出栈。
+    // 和Solana一样，运行时会记录每次调用的开始和结果
+    pub fn process_instruction(&mut self, instruction: &Instruction) -> Result<(), TransactionResult> {
+        let program_id = instruction.program_id;
+        if self.stack.len() >= MAX_INVOKE_DEPTH {
+            return Err(TransactionResult::CallDepthExceeded);
+        }
+        self.budget.consume(INSTRUCTION_COST)?;
+
+        let entrypoint = self.programs.get(&program_id).copied();
+        self.stack.push(Frame { program_id, accounts: instruction.accounts.clone() });
+        self.budget.logs_mut().push(format!("Program {} invoke [{}]", program_id, self.stack.len()));
+        let result = match entrypoint {
+            Some(entrypoint) => entrypoint(self, instruction),
+            None => apply_instruction(self.staged, instruction),
+        };
+        self.stack.pop();
+        let line = match &result {
+            Ok(()) => format!("Program {} success", program_id),
+            Err(error) => format!("Program {} failed: {:?}", program_id, error),
+        };
+        self.budget.logs_mut().push(line);
+        result
+    }
+
+    // 由正在执行的程序发起CPI。accounts是调用方传给被调用程序的账户，被调用的指令只能使用其中的账户。
+    // 和Solana一样，签名和可写权限对照调用方这一层收到的账户检查，而不是调用方传进来的accounts，
+    // 所以程序自己把is_signer或is_writable改成true也得不到调用方没有的权限
+    pub fn invoke(&mut self, instruction: &Instruction, accounts: &[AccountMeta]) -> Result<(), TransactionResult> {
+        self.invoke_signed(instruction, accounts, &[])
+    }
+
+    // 和invoke相同，但用signer_seeds（包含bump）和当前程序推导出的PDA视为已签名，
+    // 这是程序为自己的PDA签名的唯一方式
+    pub fn invoke_signed(
+        &mut self,
+        instruction: &Instruction,
+        accounts: &[AccountMeta],
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<(), TransactionResult> {
+        // 只有正在执行的程序才能发起CPI，也只有它能为自己的PDA签名
+        let caller = self.stack.last().ok_or(TransactionResult::InvalidAccount)?;
+        let signed_pdas = signer_seeds
+            .iter()
+            .map(|seeds| create_program_address(seeds, &caller.program_id).map_err(|_| TransactionResult::InvalidAccount))
+            .collect::<Result<Vec<Pubkey>, _>>()?;
+        for meta in &instruction.accounts {
+            if !accounts.iter().any(|account| account.pubkey == meta.pubkey) {
+                return Err(TransactionResult::InvalidAccount);
+            }
+            let granted = caller
+                .accounts
+                .iter()
+                .find(|account| account.pubkey == meta.pubkey)
+                .ok_or(TransactionResult::InvalidAccount)?;
+            if meta.is_signer && !granted.is_signer && !signed_pdas.contains(&meta.pubkey) {
+                return Err(TransactionResult::MissingRequiredSignature);
+            }
+            if meta.is_writable && !granted.is_writable {
+                return Err(TransactionResult::AccountNotWritable);
+            }
+        }
+        self.process_instruction(instruction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute::LOG_BASE_COST;
+    use crate::ledger::Ledger;
+    use crate::mint::Mint;
+    use crate::test_utils::{create_mint, create_token_account, token_amount};
+    use crate::{ProgramInstruction, SYSTEM_PROGRAM_ID};
+
+    use crate::logs::msg;
+
+    // 代理程序：把收到的指令连同accounts[1..]原样转给accounts[1]所属的Token程序，签名权限随之传递
+    fn proxy(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+        let token_program = instruction.accounts[1].owner;
+        msg!(ctx, "代理调用 {}", token_program)?;
+        let inner = Instruction::new(token_program, instruction.data.clone(), instruction.accounts[1..].to_vec());
+        ctx.invoke(&inner, &instruction.accounts)
+    }
+
+    // 递归调用自己，直到超过调用深度
+    fn recurse(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+        let inner = Instruction::new(instruction.program_id, instruction.data.clone(), instruction.accounts.clone());
+        ctx.invoke(&inner, &instruction.accounts)
+    }
+
+    struct Fixture {
+        ledger: Ledger,
+        token_program: Pubkey,
+        token: Pubkey,
+        mint: Pubkey,
+        mint_authority: Pubkey,
+    }
+
+    fn fixture() -> Fixture {
+        let mut ledger = Ledger::default();
+        let token_program = Pubkey::new_unique();
+        let mint_authority = Pubkey::new_unique();
+        let mint = create_mint(&mut ledger, token_program, Mint::new(0, mint_authority));
+        let token = create_token_account(&mut ledger, token_program, Pubkey::new_unique(), mint, Pubkey::new_unique(), 0);
+        Fixture { ledger, token_program, token, mint, mint_authority }
+    }
+
+    #[test]
+    fn test_invoke_through_proxy() {
+        let mut f = fixture();
+        let proxy_id = Pubkey::new_unique();
+        let programs = Programs::from([(proxy_id, proxy as Entrypoint)]);
+        let accounts = vec![
+            AccountMeta::new(Pubkey::new_unique(), proxy_id, false),
+            AccountMeta::new(f.token, f.token_program, false),
+            AccountMeta::new(f.mint, f.token_program, false),
+            AccountMeta::new_readonly(f.mint_authority, SYSTEM_PROGRAM_ID, true),
+        ];
+        let instruction = Instruction::new(proxy_id, ProgramInstruction::Mint { amount: 50 }, accounts);
+
+        let mut budget = ComputeBudget::default();
+        let mut staged = f.ledger.stage();
+        let mut ctx = InvokeContext::new(&mut staged, &mut budget, &programs);
+        assert_eq!(ctx.process_instruction(&instruction), Ok(()));
+        assert_eq!(ctx.depth(), 0);
+        staged.commit(Pubkey::new_unique());
+
+        // 顶层指令和CPI各扣一次指令开销，另外一行日志
+        assert_eq!(budget.consumed(), 2 * INSTRUCTION_COST + LOG_BASE_COST);
+        assert_eq!(token_amount(&f.ledger, &f.token), 50);
+        assert_eq!(
+            budget.logs().lines(),
+            [
+                format!("Program {} invoke [1]", proxy_id),
+                format!("代理调用 {}", f.token_program),
+                format!("Program {} invoke [2]", f.token_program),
+                format!("Program {} success", f.token_program),
+                format!("Program {} success", proxy_id),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_call_depth_limit() {
+        let mut f = fixture();
+        let recurse_id = Pubkey::new_unique();
+        let programs = Programs::from([(recurse_id, recurse as Entrypoint)]);
+        let accounts = vec![AccountMeta::new(f.token, f.token_program, false)];
+        let instruction = Instruction::new(recurse_id, ProgramInstruction::Mint { amount: 1 }, accounts);
+
+        let mut budget = ComputeBudget::default();
+        let mut staged = f.ledger.stage();
+        let mut ctx = InvokeContext::new(&mut staged, &mut budget, &programs);
+        assert_eq!(ctx.process_instruction(&instruction), Err(TransactionResult::CallDepthExceeded));
+        // 错误逐层返回，每一层都已出栈
+        assert_eq!(ctx.depth(), 0);
+        assert_eq!(budget.consumed(), MAX_INVOKE_DEPTH as u64 * INSTRUCTION_COST);
+        // 失败沿调用栈逐层记录
+        let failures = budget.logs().lines().iter().filter(|line| line.ends_with("failed: CallDepthExceeded"));
+        assert_eq!(failures.count(), MAX_INVOKE_DEPTH);
+    }
+
+    // 伪造权限的程序：把收到的账户全部改成可写、最后一个（authority）改成已签名，再转给accounts[1]所属的Token程序
+    fn forge(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+        let mut forged = instruction.accounts[1..].to_vec();
+        for meta in &mut forged {
+            meta.is_writable = true;
+        }
+        if let Some(authority) = forged.last_mut() {
+            authority.is_signer = true;
+        }
+        let inner = Instruction::new(forged[0].owner, instruction.data.clone(), forged.clone());
+        ctx.invoke(&inner, &forged)
+    }
+
+    #[test]
+    fn test_invoke_cannot_escalate_privileges() {
+        let mut f = fixture();
+        let forge_id = Pubkey::new_unique();
+        let programs = Programs::from([(forge_id, forge as Entrypoint)]);
+        let mut budget = ComputeBudget::default();
+        let mut staged = f.ledger.stage();
+        let mut ctx = InvokeContext::new(&mut staged, &mut budget, &programs);
+        let forge_mint = |token, authority_signed| {
+            let accounts = vec![
+                AccountMeta::new(Pubkey::new_unique(), forge_id, false),
+                token,
+                AccountMeta::new(f.mint, f.token_program, false),
+                AccountMeta::new_readonly(f.mint_authority, SYSTEM_PROGRAM_ID, authority_signed),
+            ];
+            Instruction::new(forge_id, ProgramInstruction::Mint { amount: 777 }, accounts)
+        };
+
+        // 调用方没有mint authority的签名，自己标成已签名也不行
+        let unsigned = forge_mint(AccountMeta::new(f.token, f.token_program, false), false);
+        assert_eq!(ctx.process_instruction(&unsigned), Err(TransactionResult::MissingRequiredSignature));
+        // 调用方收到的Token账户是只读的，自己标成可写也不行
+        let readonly = forge_mint(AccountMeta::new_readonly(f.token, f.token_program, false), true);
+        assert_eq!(ctx.process_instruction(&readonly), Err(TransactionResult::AccountNotWritable));
+        // 不在任何程序中时不能发起CPI
+        let direct = Instruction::new(f.token_program, ProgramInstruction::Mint { amount: 1 }, vec![]);
+        assert_eq!(ctx.invoke(&direct, &[]), Err(TransactionResult::InvalidAccount));
+        staged.commit(Pubkey::new_unique());
+        assert_eq!(token_amount(&f.ledger, &f.token), 0);
+    }
+}
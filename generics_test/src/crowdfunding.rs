@@ -0,0 +1,509 @@
+// 众筹程序 - 发起人设置目标金额和截止slot，支持者把代币存进由众筹PDA控制的vault。
+// 截止之后按结果分两条路：达到目标时发起人取走全部资金，没达到时每个支持者按自己的出资记录取回退款
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::cpi::InvokeContext;
+use crate::ledger::AccountPayload;
+use crate::pda::derive_pda;
+use crate::transaction::Instruction;
+use crate::{AccountMeta, ProgramInstruction, Pubkey, TransactionResult};
+
+pub const CAMPAIGN_SEED: &[u8] = b"campaign";
+pub const CONTRIBUTION_SEED: &[u8] = b"contribution";
+
+// 众筹所处的阶段，由当前slot和账户状态推出来，不单独存储
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CampaignState {
+    Funding,   // 截止之前，可以继续出资
+    Succeeded, // 截止时达到目标，等待发起人取款
+    Withdrawn, // 发起人已经取走资金
+    Failed,    // 截止时没有达到目标，支持者可以退款
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Campaign {
+    pub creator: Pubkey,
+    pub vault: Pubkey, // 保存筹集代币的Token账户，authority是众筹PDA
+    pub goal: u64,
+    pub deadline_slot: u64, // 从这个slot开始不再接受出资
+    pub raised: u64,        // 累计筹到的数量，退款不会减少它
+    pub withdrawn: bool,
+    pub bump: u8,
+}
+
+impl Campaign {
+    // 两个Pubkey(各32) + 三个u64 + withdrawn(1) + bump(1)
+    pub const LEN: usize = 32 * 2 + 8 * 3 + 1 + 1;
+
+    pub fn state(&self, slot: u64) -> CampaignState {
+        match (slot < self.deadline_slot, self.raised >= self.goal, self.withdrawn) {
+            (true, _, _) => CampaignState::Funding,
+            (false, true, false) => CampaignState::Succeeded,
+            (false, true, true) => CampaignState::Withdrawn,
+            (false, false, _) => CampaignState::Failed,
+        }
+    }
+}
+
+impl BorshSerialize for Campaign {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.creator.serialize(out);
+        self.vault.serialize(out);
+        self.goal.serialize(out);
+        self.deadline_slot.serialize(out);
+        self.raised.serialize(out);
+        self.withdrawn.serialize(out);
+        self.bump.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Campaign {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            creator: Pubkey::deserialize(buf)?,
+            vault: Pubkey::deserialize(buf)?,
+            goal: u64::deserialize(buf)?,
+            deadline_slot: u64::deserialize(buf)?,
+            raised: u64::deserialize(buf)?,
+            withdrawn: bool::deserialize(buf)?,
+            bump: u8::deserialize(buf)?,
+        })
+    }
+}
+
+// 一个支持者在一次众筹里的出资记录，退款时按它转回
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contribution {
+    pub campaign: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64, // 还没有退回的出资
+}
+
+impl Contribution {
+    // 两个Pubkey(各32) + amount(8)
+    pub const LEN: usize = 32 * 2 + 8;
+}
+
+impl BorshSerialize for Contribution {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.campaign.serialize(out);
+        self.contributor.serialize(out);
+        self.amount.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Contribution {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self { campaign: Pubkey::deserialize(buf)?, contributor: Pubkey::deserialize(buf)?, amount: u64::deserialize(buf)? })
+    }
+}
+
+// 众筹账户地址：种子为["campaign", creator, vault]
+pub fn campaign_address(creator: &Pubkey, vault: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[CAMPAIGN_SEED, creator.as_ref(), vault.as_ref()], program_id)
+}
+
+// 出资记录地址：种子为["contribution", 众筹账户, contributor]
+pub fn contribution_address(campaign: &Pubkey, contributor: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[CONTRIBUTION_SEED, campaign.as_ref(), contributor.as_ref()], program_id)
+}
+
+// 众筹程序的入口函数，注册到Programs后由运行时调用
+pub fn process(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+    let program_id = instruction.program_id;
+    let metas = &instruction.accounts;
+    match instruction.data {
+        ProgramInstruction::CreateCampaign { goal, deadline_slot } => create(ctx, &program_id, metas, goal, deadline_slot),
+        ProgramInstruction::Contribute { amount } => contribute(ctx, &program_id, metas, amount),
+        ProgramInstruction::Withdraw => withdraw(ctx, &program_id, metas),
+        ProgramInstruction::Refund => refund(ctx, &program_id, metas),
+        _ => Err(TransactionResult::InvalidInstructionData),
+    }
+}
+
+// accounts: [众筹账户(可写，空的PDA账户), 发起人(签名), vault(authority是众筹PDA，余额为0)]
+fn create(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    goal: u64,
+    deadline_slot: u64,
+) -> Result<(), TransactionResult> {
+    let [campaign_meta, creator, vault, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !creator.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if goal == 0 || deadline_slot <= ctx.slot() {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+    let (campaign_key, bump) = campaign_address(&creator.pubkey, &vault.pubkey, program_id);
+    if campaign_meta.pubkey != campaign_key || !campaign_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let mut account = ctx.staged().get_account(&campaign_key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    if account.owner != *program_id || account.data != AccountPayload::Empty {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if account.lamports < ctx.staged().rent().minimum_balance(Campaign::LEN) {
+        return Err(TransactionResult::InsufficientFunds);
+    }
+    let (vault_token, _) = ctx.token_account(&vault.pubkey)?;
+    if vault_token.owner != campaign_key || vault_token.amount != 0 {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let campaign = Campaign { creator: creator.pubkey, vault: vault.pubkey, goal, deadline_slot, raised: 0, withdrawn: false, bump };
+    account.data = AccountPayload::Campaign(campaign);
+    Ok(ctx.staged().set_account(campaign_key, account)?)
+}
+
+// accounts: [众筹账户(可写), 出资记录(可写，第一次出资时是空的PDA账户), 支持者(签名),
+//            出资的Token账户(可写), vault(可写)]
+fn contribute(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta], amount: u64) -> Result<(), TransactionResult> {
+    let [campaign_meta, contribution_meta, contributor, source, vault, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !contributor.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if amount == 0 {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+    let mut campaign = load_campaign(ctx, program_id, &campaign_meta.pubkey)?;
+    if campaign.vault != vault.pubkey || !campaign_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    match campaign.state(ctx.slot()) {
+        CampaignState::Funding => {}
+        CampaignState::Succeeded | CampaignState::Withdrawn | CampaignState::Failed => return Err(TransactionResult::CampaignEnded),
+    }
+    let (contribution_key, _) = contribution_address(&campaign_meta.pubkey, &contributor.pubkey, program_id);
+    if contribution_meta.pubkey != contribution_key || !contribution_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let minimum = ctx.staged().rent().minimum_balance(Contribution::LEN);
+    let mut account = match ctx.staged().get_account(&contribution_key) {
+        Some(account) if account.owner == *program_id => account.clone(),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    let mut contribution = match &account.data {
+        AccountPayload::Contribution(contribution) => contribution.clone(),
+        AccountPayload::Empty if account.lamports >= minimum => {
+            Contribution { campaign: campaign_meta.pubkey, contributor: contributor.pubkey, amount: 0 }
+        }
+        AccountPayload::Empty => return Err(TransactionResult::InsufficientFunds),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+
+    // 带转账手续费的mint到账会少一些，按vault实际增加的数量记账
+    let (_, token_program) = ctx.token_account(&source.pubkey)?;
+    let before = ctx.token_account(&vault.pubkey)?.0.amount;
+    let deposit = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(source.pubkey, token_program, false),
+            AccountMeta::new(vault.pubkey, token_program, false),
+            AccountMeta::new_readonly(contributor.pubkey, contributor.owner, true),
+        ],
+    );
+    ctx.invoke(&deposit, metas)?;
+    let received = ctx.token_account(&vault.pubkey)?.0.amount - before;
+    campaign.raised = campaign.raised.checked_add(received).ok_or(TransactionResult::ArithmeticOverflow)?;
+    contribution.amount = contribution.amount.checked_add(received).ok_or(TransactionResult::ArithmeticOverflow)?;
+    account.data = AccountPayload::Contribution(contribution);
+    ctx.staged().set_account(contribution_key, account)?;
+    store_campaign(ctx, campaign_meta.pubkey, campaign)
+}
+
+// accounts: [众筹账户(可写), 发起人(签名), vault(可写), 接收的Token账户(可写)]
+fn withdraw(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let [campaign_meta, creator, vault, destination, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    let mut campaign = load_campaign(ctx, program_id, &campaign_meta.pubkey)?;
+    if campaign.vault != vault.pubkey || !campaign_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if campaign.creator != creator.pubkey || !creator.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    match campaign.state(ctx.slot()) {
+        CampaignState::Funding => return Err(TransactionResult::CampaignInProgress),
+        CampaignState::Failed => return Err(TransactionResult::GoalNotReached),
+        CampaignState::Withdrawn => return Err(TransactionResult::NothingToClaim),
+        CampaignState::Succeeded => {}
+    }
+    let amount = ctx.token_account(&vault.pubkey)?.0.amount;
+    release(ctx, program_id, metas, (campaign_meta, &campaign), destination, amount)?;
+    campaign.withdrawn = true;
+    store_campaign(ctx, campaign_meta.pubkey, campaign)
+}
+
+// accounts: [众筹账户, 出资记录(可写), 支持者(签名), vault(可写), 接收的Token账户(可写)]
+fn refund(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let [campaign_meta, contribution_meta, contributor, vault, destination, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    let campaign = load_campaign(ctx, program_id, &campaign_meta.pubkey)?;
+    if campaign.vault != vault.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let mut account = match ctx.staged().get_account(&contribution_meta.pubkey) {
+        Some(account) if account.owner == *program_id && contribution_meta.is_writable => account.clone(),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    let AccountPayload::Contribution(contribution) = &mut account.data else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if contribution.campaign != campaign_meta.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if contribution.contributor != contributor.pubkey || !contributor.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    match campaign.state(ctx.slot()) {
+        CampaignState::Funding => return Err(TransactionResult::CampaignInProgress),
+        CampaignState::Succeeded | CampaignState::Withdrawn => return Err(TransactionResult::GoalReached),
+        CampaignState::Failed if contribution.amount == 0 => return Err(TransactionResult::NothingToClaim),
+        CampaignState::Failed => {}
+    }
+    let amount = contribution.amount;
+    contribution.amount = 0;
+    release(ctx, program_id, metas, (campaign_meta, &campaign), destination, amount)?;
+    Ok(ctx.staged().set_account(contribution_meta.pubkey, account)?)
+}
+
+fn load_campaign(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, key: &Pubkey) -> Result<Campaign, TransactionResult> {
+    match ctx.staged().get_account(key) {
+        Some(account) if account.owner == *program_id => match &account.data {
+            AccountPayload::Campaign(campaign) => Ok(campaign.clone()),
+            _ => Err(TransactionResult::InvalidAccount),
+        },
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+fn store_campaign(ctx: &mut InvokeContext<'_, '_>, key: Pubkey, campaign: Campaign) -> Result<(), TransactionResult> {
+    let mut account = ctx.staged().get_account(&key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    account.data = AccountPayload::Campaign(campaign);
+    Ok(ctx.staged().set_account(key, account)?)
+}
+
+// 众筹PDA签名，把amount从vault转到destination
+fn release(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    (campaign_meta, campaign): (&AccountMeta, &Campaign),
+    destination: &AccountMeta,
+    amount: u64,
+) -> Result<(), TransactionResult> {
+    let (_, token_program) = ctx.token_account(&campaign.vault)?;
+    let bump = [campaign.bump];
+    let seeds: &[&[u8]] = &[CAMPAIGN_SEED, campaign.creator.as_ref(), campaign.vault.as_ref(), &bump];
+    let transfer = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(campaign.vault, token_program, false),
+            AccountMeta::new(destination.pubkey, token_program, false),
+            AccountMeta::new_readonly(campaign_meta.pubkey, *program_id, true),
+        ],
+    );
+    ctx.invoke_signed(&transfer, metas, &[seeds])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpi::{Entrypoint, Programs};
+    use crate::ledger::{Account, Ledger};
+    use crate::signature::{Keypair, Signer};
+    use crate::test_utils::{create_token_account, funded_keypair, sign_and_execute, token_amount};
+    use crate::transaction::TransactionError;
+    use crate::SYSTEM_PROGRAM_ID;
+
+    const GOAL: u64 = 1_000;
+    const DEADLINE: u64 = 100;
+
+    struct Fixture {
+        ledger: Ledger,
+        programs: Programs,
+        crowdfunding_program: Pubkey,
+        token_program: Pubkey,
+        creator: Keypair,
+        backers: [Keypair; 2],
+        wallets: [Pubkey; 2], // 两个支持者各自的Token账户，各有1000个
+        proceeds: Pubkey,     // 发起人接收筹款的Token账户
+        vault: Pubkey,
+        campaign: Pubkey,
+        contributions: [Pubkey; 2],
+    }
+
+    fn fixture() -> Fixture {
+        let mut ledger = Ledger::default();
+        let (crowdfunding_program, token_program, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (creator, backers) = (funded_keypair(&mut ledger), [funded_keypair(&mut ledger), funded_keypair(&mut ledger)]);
+        let vault = Pubkey::new_unique();
+        let (campaign, _) = campaign_address(&creator.pubkey(), &vault, &crowdfunding_program);
+        let contributions = backers.each_ref().map(|backer| contribution_address(&campaign, &backer.pubkey(), &crowdfunding_program).0);
+        let (wallets, proceeds) = ([Pubkey::new_unique(), Pubkey::new_unique()], Pubkey::new_unique());
+        for (key, owner, amount) in [
+            (wallets[0], backers[0].pubkey(), 1_000),
+            (wallets[1], backers[1].pubkey(), 1_000),
+            (proceeds, creator.pubkey(), 0),
+            (vault, campaign, 0),
+        ] {
+            create_token_account(&mut ledger, token_program, key, mint, owner, amount);
+        }
+        for (key, len) in [(campaign, Campaign::LEN), (contributions[0], Contribution::LEN), (contributions[1], Contribution::LEN)] {
+            let lamports = ledger.rent().minimum_balance(len);
+            ledger.create_account(key, Account::new(lamports, crowdfunding_program, AccountPayload::Empty)).unwrap();
+        }
+        let programs = Programs::from([(crowdfunding_program, process as Entrypoint)]);
+        let mut f = Fixture { ledger, programs, crowdfunding_program, token_program, creator, backers, wallets, proceeds, vault, campaign, contributions };
+        let accounts = vec![
+            AccountMeta::new(f.campaign, f.crowdfunding_program, false),
+            AccountMeta::new_readonly(f.creator.pubkey(), SYSTEM_PROGRAM_ID, true),
+            AccountMeta::new_readonly(f.vault, f.token_program, false),
+        ];
+        let instruction = Instruction::new(f.crowdfunding_program, ProgramInstruction::CreateCampaign { goal: GOAL, deadline_slot: DEADLINE }, accounts);
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.creator, vec![instruction]), Ok(()));
+        f
+    }
+
+    impl Fixture {
+        fn contribute(&mut self, backer: usize, amount: u64) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new(self.campaign, self.crowdfunding_program, false),
+                AccountMeta::new(self.contributions[backer], self.crowdfunding_program, false),
+                AccountMeta::new_readonly(self.backers[backer].pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.wallets[backer], self.token_program, false),
+                AccountMeta::new(self.vault, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.crowdfunding_program, ProgramInstruction::Contribute { amount }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.backers[backer], vec![instruction])
+        }
+
+        // signer_is_creator为false时由支持者代替发起人发起取款
+        fn withdraw(&mut self, signer_is_creator: bool) -> Result<(), TransactionError> {
+            let signer = if signer_is_creator { &self.creator } else { &self.backers[0] };
+            let accounts = vec![
+                AccountMeta::new(self.campaign, self.crowdfunding_program, false),
+                AccountMeta::new_readonly(self.creator.pubkey(), SYSTEM_PROGRAM_ID, signer_is_creator),
+                AccountMeta::new(self.vault, self.token_program, false),
+                AccountMeta::new(self.proceeds, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.crowdfunding_program, ProgramInstruction::Withdraw, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, signer, vec![instruction])
+        }
+
+        fn refund(&mut self, backer: usize) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new_readonly(self.campaign, self.crowdfunding_program, false),
+                AccountMeta::new(self.contributions[backer], self.crowdfunding_program, false),
+                AccountMeta::new_readonly(self.backers[backer].pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.vault, self.token_program, false),
+                AccountMeta::new(self.wallets[backer], self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.crowdfunding_program, ProgramInstruction::Refund, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.backers[backer], vec![instruction])
+        }
+
+        fn advance_to(&mut self, slot: u64) {
+            let slots = slot - self.ledger.slot();
+            self.ledger.tick(slots);
+        }
+    }
+
+    fn error(result: TransactionResult) -> Result<(), TransactionError> {
+        Err(TransactionError::InstructionError(0, result))
+    }
+
+    #[test]
+    fn test_campaign_state() {
+        let mut campaign = Campaign {
+            creator: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            goal: GOAL,
+            deadline_slot: DEADLINE,
+            raised: GOAL - 1,
+            withdrawn: false,
+            bump: 254,
+        };
+        assert_eq!(campaign.state(DEADLINE - 1), CampaignState::Funding);
+        assert_eq!(campaign.state(DEADLINE), CampaignState::Failed);
+        campaign.raised = GOAL;
+        // 截止之前达到目标也还是筹款中
+        assert_eq!(campaign.state(DEADLINE - 1), CampaignState::Funding);
+        assert_eq!(campaign.state(DEADLINE), CampaignState::Succeeded);
+        campaign.withdrawn = true;
+        assert_eq!(campaign.state(u64::MAX), CampaignState::Withdrawn);
+
+        let mut bytes = Vec::new();
+        campaign.serialize(&mut bytes);
+        assert_eq!(bytes.len(), Campaign::LEN);
+        assert_eq!(Campaign::deserialize(&mut bytes.as_slice()), Ok(campaign));
+    }
+
+    #[test]
+    fn test_withdraw_after_goal_met() {
+        let mut f = fixture();
+        assert_eq!(f.contribute(0, 600), Ok(()));
+        assert_eq!(f.contribute(1, 300), Ok(()));
+        assert_eq!(f.contribute(1, 200), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.vault), 1_100);
+
+        // 截止之前谁都不能取钱
+        assert_eq!(f.withdraw(true), error(TransactionResult::CampaignInProgress));
+        assert_eq!(f.refund(0), error(TransactionResult::CampaignInProgress));
+
+        f.advance_to(DEADLINE);
+        assert_eq!(f.contribute(0, 100), error(TransactionResult::CampaignEnded));
+        assert_eq!(f.refund(1), error(TransactionResult::GoalReached));
+        assert_eq!(f.withdraw(false), error(TransactionResult::MissingRequiredSignature));
+        assert_eq!(f.withdraw(true), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.proceeds), token_amount(&f.ledger, &f.vault)), (1_100, 0));
+        assert_eq!(f.withdraw(true), error(TransactionResult::NothingToClaim));
+        assert_eq!(f.refund(1), error(TransactionResult::GoalReached));
+    }
+
+    #[test]
+    fn test_refund_when_goal_missed() {
+        let mut f = fixture();
+        assert_eq!(f.contribute(0, 400), Ok(()));
+        assert_eq!(f.contribute(1, 500), Ok(()));
+        f.advance_to(DEADLINE + 10);
+
+        assert_eq!(f.withdraw(true), error(TransactionResult::GoalNotReached));
+        assert_eq!(f.refund(0), Ok(()));
+        assert_eq!(f.refund(0), error(TransactionResult::NothingToClaim));
+        assert_eq!(f.refund(1), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.wallets[0]), token_amount(&f.ledger, &f.wallets[1])), (1_000, 1_000));
+        assert_eq!(token_amount(&f.ledger, &f.vault), 0);
+        // 退款之后记录的筹款总额不变，仍然不能取钱
+        assert_eq!(f.withdraw(true), error(TransactionResult::GoalNotReached));
+    }
+
+    #[test]
+    fn test_create_rejects_past_deadline() {
+        let mut f = fixture();
+        let vault = Pubkey::new_unique();
+        let (campaign, _) = campaign_address(&f.creator.pubkey(), &vault, &f.crowdfunding_program);
+        create_token_account(&mut f.ledger, f.token_program, vault, Pubkey::new_unique(), campaign, 0);
+        let lamports = f.ledger.rent().minimum_balance(Campaign::LEN);
+        f.ledger.create_account(campaign, Account::new(lamports, f.crowdfunding_program, AccountPayload::Empty)).unwrap();
+        f.advance_to(50);
+        for (goal, deadline_slot) in [(GOAL, 50), (0, DEADLINE)] {
+            let accounts = vec![
+                AccountMeta::new(campaign, f.crowdfunding_program, false),
+                AccountMeta::new_readonly(f.creator.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new_readonly(vault, f.token_program, false),
+            ];
+            let instruction = Instruction::new(f.crowdfunding_program, ProgramInstruction::CreateCampaign { goal, deadline_slot }, accounts);
+            assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.creator, vec![instruction]), error(TransactionResult::InvalidInstructionData));
+        }
+    }
+}
@@ -0,0 +1,956 @@
+// 订单簿DEX - 类似于Serum/OpenBook：每个市场把base和quote代币存在两个由市场PDA控制的vault中，
+// 买单(bid)锁定price * quantity个quote，卖单(ask)锁定quantity个base。
+// 新订单按价格-时间优先和对手方挂单撮合，成交价是挂单(maker)的价格，没成交完的部分留在订单簿上。
+// 成交的代币先记在各自的订单账户里，owner再用SettleFunds结算到自己的Token账户
+//
+// 没有拆成单独的crate：撮合和结算都建立在这里的Ledger、TokenAccount、borsh、pda和cpi之上，
+// 而generics_test没有Cargo.toml，别的crate没法依赖它，所以和其它程序一样作为一个模块放在这里
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::cpi::InvokeContext;
+use crate::ledger::AccountPayload;
+use crate::pda::derive_pda;
+use crate::transaction::Instruction;
+use crate::{AccountMeta, ProgramInstruction, Pubkey, TransactionResult};
+
+pub const MARKET_SEED: &[u8] = b"market";
+pub const ORDER_SEED: &[u8] = b"order";
+
+// 订单簿和市场状态存在同一个账户里，按最多能挂的订单数分配空间
+pub const MAX_ORDERS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Bid, // 用quote买base
+    Ask, // 卖base换quote
+}
+
+impl BorshSerialize for Side {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            Side::Bid => 0,
+            Side::Ask => 1,
+        };
+        tag.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Side {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        match u8::deserialize(buf)? {
+            0 => Ok(Side::Bid),
+            1 => Ok(Side::Ask),
+            tag => Err(BorshError::InvalidEnumTag(tag)),
+        }
+    }
+}
+
+// 挂在订单簿上的订单，quantity是还没成交的数量
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestingOrder {
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub price: u64, // 每个base最小单位值多少个quote最小单位
+    pub quantity: u64,
+}
+
+impl RestingOrder {
+    // order_id(8) + owner(32) + price(8) + quantity(8)
+    pub const LEN: usize = 8 + 32 + 8 + 8;
+}
+
+impl BorshSerialize for RestingOrder {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.order_id.serialize(out);
+        self.owner.serialize(out);
+        self.price.serialize(out);
+        self.quantity.serialize(out);
+    }
+}
+
+impl BorshDeserialize for RestingOrder {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            order_id: u64::deserialize(buf)?,
+            owner: Pubkey::deserialize(buf)?,
+            price: u64::deserialize(buf)?,
+            quantity: u64::deserialize(buf)?,
+        })
+    }
+}
+
+// 一笔成交：新订单和maker_order_id以maker的价格成交了quantity个base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fill {
+    pub maker_order_id: u64,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+// 价格-时间优先的订单簿。order_id单调递增，同一价格下id小的先挂单、先成交；
+// 买单按价格从高到低排，卖单从低到高排，两边的第一个元素就是最优价
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrderBook {
+    bids: BTreeMap<(Reverse<u64>, u64), RestingOrder>,
+    asks: BTreeMap<(u64, u64), RestingOrder>,
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 新订单先和对手方能成交的挂单撮合，剩余部分挂进订单簿；返回按成交顺序排列的明细
+    pub fn place(&mut self, side: Side, mut order: RestingOrder) -> Vec<Fill> {
+        let mut fills = Vec::new();
+        let price = order.price;
+        match side {
+            Side::Bid => take(&mut self.asks, &mut order.quantity, |ask| ask <= price, &mut fills),
+            Side::Ask => take(&mut self.bids, &mut order.quantity, |bid| bid >= price, &mut fills),
+        }
+        if order.quantity > 0 {
+            self.insert(side, order);
+        }
+        fills
+    }
+
+    // 撤掉还在订单簿上的订单，返回它剩余的部分
+    pub fn cancel(&mut self, side: Side, price: u64, order_id: u64) -> Option<RestingOrder> {
+        match side {
+            Side::Bid => self.bids.remove(&(Reverse(price), order_id)),
+            Side::Ask => self.asks.remove(&(price, order_id)),
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<u64> {
+        self.bids.values().next().map(|order| order.price)
+    }
+
+    pub fn best_ask(&self) -> Option<u64> {
+        self.asks.values().next().map(|order| order.price)
+    }
+
+    // 按价格汇总的深度，从最优价开始
+    pub fn depth(&self, side: Side) -> Vec<(u64, u64)> {
+        let mut levels: Vec<(u64, u64)> = Vec::new();
+        for order in self.orders(side) {
+            match levels.last_mut() {
+                Some((price, quantity)) if *price == order.price => *quantity += order.quantity,
+                _ => levels.push((order.price, order.quantity)),
+            }
+        }
+        levels
+    }
+
+    // 一边的挂单，按撮合优先级排列
+    pub fn orders(&self, side: Side) -> Box<dyn Iterator<Item = &RestingOrder> + '_> {
+        match side {
+            Side::Bid => Box::new(self.bids.values()),
+            Side::Ask => Box::new(self.asks.values()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bids.len() + self.asks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn insert(&mut self, side: Side, order: RestingOrder) {
+        match side {
+            Side::Bid => self.bids.insert((Reverse(order.price), order.order_id), order),
+            Side::Ask => self.asks.insert((order.price, order.order_id), order),
+        };
+    }
+}
+
+// 从最优的挂单开始吃，直到remaining用完或者价格不再满足crosses
+fn take<K: Ord>(book: &mut BTreeMap<K, RestingOrder>, remaining: &mut u64, crosses: impl Fn(u64) -> bool, fills: &mut Vec<Fill>) {
+    while *remaining > 0 {
+        let Some(mut entry) = book.first_entry() else {
+            break;
+        };
+        let maker = entry.get_mut();
+        if !crosses(maker.price) {
+            break;
+        }
+        let quantity = maker.quantity.min(*remaining);
+        maker.quantity -= quantity;
+        *remaining -= quantity;
+        fills.push(Fill { maker_order_id: maker.order_id, price: maker.price, quantity });
+        if maker.quantity == 0 {
+            entry.remove();
+        }
+    }
+}
+
+// 先写买单再写卖单，各自按撮合优先级排列，反序列化时重新建立索引
+impl BorshSerialize for OrderBook {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        for side in [Side::Bid, Side::Ask] {
+            let orders: Vec<&RestingOrder> = self.orders(side).collect();
+            (orders.len() as u32).serialize(out);
+            for order in orders {
+                order.serialize(out);
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for OrderBook {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        let mut book = OrderBook::new();
+        for side in [Side::Bid, Side::Ask] {
+            for order in Vec::<RestingOrder>::deserialize(buf)? {
+                book.insert(side, order);
+            }
+        }
+        Ok(book)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Market {
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_vault: Pubkey,  // 保存卖单锁定的base，authority是市场PDA
+    pub quote_vault: Pubkey, // 保存买单锁定的quote，authority是市场PDA
+    pub next_order_id: u64,
+    pub bump: u8,
+    pub book: OrderBook,
+}
+
+impl Market {
+    // 四个Pubkey(各32) + next_order_id(8) + bump(1) + 两边订单列表的长度前缀(各4) + 最多MAX_ORDERS个挂单
+    pub const LEN: usize = 32 * 4 + 8 + 1 + 4 * 2 + MAX_ORDERS * RestingOrder::LEN;
+}
+
+impl BorshSerialize for Market {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.base_mint.serialize(out);
+        self.quote_mint.serialize(out);
+        self.base_vault.serialize(out);
+        self.quote_vault.serialize(out);
+        self.next_order_id.serialize(out);
+        self.bump.serialize(out);
+        self.book.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Market {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            base_mint: Pubkey::deserialize(buf)?,
+            quote_mint: Pubkey::deserialize(buf)?,
+            base_vault: Pubkey::deserialize(buf)?,
+            quote_vault: Pubkey::deserialize(buf)?,
+            next_order_id: u64::deserialize(buf)?,
+            bump: u8::deserialize(buf)?,
+            book: OrderBook::deserialize(buf)?,
+        })
+    }
+}
+
+// 订单账户，每个订单一个。订单从订单簿上撤掉或完全成交后仍然保留，直到owner结算完资金
+#[derive(Debug, Clone, PartialEq)]
+pub struct Order {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub side: Side,
+    pub price: u64,
+    pub quantity: u64,   // 下单时的base数量
+    pub remaining: u64,  // 还挂在订单簿上的数量
+    pub base_free: u64,  // 可以结算给owner的base
+    pub quote_free: u64, // 可以结算给owner的quote，包括买单以更低价格成交退回的部分
+}
+
+impl Order {
+    // 两个Pubkey(各32) + order_id(8) + side(1) + 五个u64
+    pub const LEN: usize = 32 * 2 + 8 + 1 + 8 * 5;
+
+    // 以price成交quantity个base。买单收到base，锁定时多付的(self.price - price) * quantity退回；
+    // 卖单收到price * quantity个quote
+    pub fn fill(&mut self, price: u64, quantity: u64) -> Result<(), TransactionResult> {
+        self.remaining = self.remaining.checked_sub(quantity).ok_or(TransactionResult::ArithmeticOverflow)?;
+        let (base, quote) = match self.side {
+            Side::Bid => (quantity, (self.price - price).checked_mul(quantity)),
+            Side::Ask => (0, price.checked_mul(quantity)),
+        };
+        let quote = quote.ok_or(TransactionResult::ArithmeticOverflow)?;
+        self.base_free = self.base_free.checked_add(base).ok_or(TransactionResult::ArithmeticOverflow)?;
+        self.quote_free = self.quote_free.checked_add(quote).ok_or(TransactionResult::ArithmeticOverflow)?;
+        Ok(())
+    }
+}
+
+impl BorshSerialize for Order {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.market.serialize(out);
+        self.owner.serialize(out);
+        self.order_id.serialize(out);
+        self.side.serialize(out);
+        self.price.serialize(out);
+        self.quantity.serialize(out);
+        self.remaining.serialize(out);
+        self.base_free.serialize(out);
+        self.quote_free.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Order {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            market: Pubkey::deserialize(buf)?,
+            owner: Pubkey::deserialize(buf)?,
+            order_id: u64::deserialize(buf)?,
+            side: Side::deserialize(buf)?,
+            price: u64::deserialize(buf)?,
+            quantity: u64::deserialize(buf)?,
+            remaining: u64::deserialize(buf)?,
+            base_free: u64::deserialize(buf)?,
+            quote_free: u64::deserialize(buf)?,
+        })
+    }
+}
+
+// 市场地址：种子为["market", base_mint, quote_mint]
+pub fn market_address(base_mint: &Pubkey, quote_mint: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[MARKET_SEED, base_mint.as_ref(), quote_mint.as_ref()], program_id)
+}
+
+// 订单地址：种子为["order", 市场, order_id(小端)]，下单前用市场的next_order_id算出
+pub fn order_address(market: &Pubkey, order_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[ORDER_SEED, market.as_ref(), &order_id.to_le_bytes()], program_id)
+}
+
+// DEX程序的入口函数，注册到Programs后由运行时调用
+pub fn process(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+    let program_id = instruction.program_id;
+    let metas = &instruction.accounts;
+    match instruction.data {
+        ProgramInstruction::CreateMarket => create_market(ctx, &program_id, metas),
+        ProgramInstruction::PlaceOrder { side, price, quantity } => place_order(ctx, &program_id, metas, side, price, quantity),
+        ProgramInstruction::CancelOrder => cancel_order(ctx, &program_id, metas),
+        ProgramInstruction::SettleFunds => settle_funds(ctx, &program_id, metas),
+        _ => Err(TransactionResult::InvalidInstructionData),
+    }
+}
+
+// accounts: [市场账户(可写，空的PDA账户), 创建者(签名), base_vault, quote_vault]
+fn create_market(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let [market_meta, creator, base_vault, quote_vault, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !creator.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let (base_token, _) = ctx.token_account(&base_vault.pubkey)?;
+    let (quote_token, _) = ctx.token_account(&quote_vault.pubkey)?;
+    let (market_key, bump) = market_address(&base_token.mint, &quote_token.mint, program_id);
+    if market_meta.pubkey != market_key || !market_meta.is_writable || base_token.mint == quote_token.mint {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let mut account = ctx.staged().get_account(&market_key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    if account.owner != *program_id || account.data != AccountPayload::Empty {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if account.lamports < ctx.staged().rent().minimum_balance(Market::LEN) {
+        return Err(TransactionResult::InsufficientFunds);
+    }
+    // vault必须是新的、由市场PDA控制的账户
+    for vault in [&base_token, &quote_token] {
+        if vault.owner != market_key || vault.amount != 0 {
+            return Err(TransactionResult::InvalidAccount);
+        }
+    }
+
+    account.data = AccountPayload::Market(Market {
+        base_mint: base_token.mint,
+        quote_mint: quote_token.mint,
+        base_vault: base_vault.pubkey,
+        quote_vault: quote_vault.pubkey,
+        next_order_id: 0,
+        bump,
+        book: OrderBook::new(),
+    });
+    Ok(ctx.staged().set_account(market_key, account)?)
+}
+
+// accounts: [市场(可写), 订单账户(可写，空的PDA账户), owner(签名), 付款的Token账户, 对应的vault, 被吃到的挂单的订单账户...]。
+// 买单从quote账户付款进quote_vault，卖单从base账户付款进base_vault
+fn place_order(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    side: Side,
+    price: u64,
+    quantity: u64,
+) -> Result<(), TransactionResult> {
+    let [market_meta, order_meta, owner, source, vault, makers @ ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !owner.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if price == 0 || quantity == 0 {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+    let mut market = load_market(ctx, program_id, &market_meta.pubkey)?;
+    let expected_vault = match side {
+        Side::Bid => market.quote_vault,
+        Side::Ask => market.base_vault,
+    };
+    if !market_meta.is_writable || vault.pubkey != expected_vault {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let order_id = market.next_order_id;
+    let (order_key, _) = order_address(&market_meta.pubkey, order_id, program_id);
+    let mut order_account = ctx.staged().get_account(&order_key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    if order_meta.pubkey != order_key || !order_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if order_account.owner != *program_id || order_account.data != AccountPayload::Empty {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if order_account.lamports < ctx.staged().rent().minimum_balance(Order::LEN) {
+        return Err(TransactionResult::InsufficientFunds);
+    }
+
+    // 先锁定资金；mint收转账手续费时vault到账不足，订单无法全额结算，直接拒绝
+    let locked = match side {
+        Side::Bid => price.checked_mul(quantity).ok_or(TransactionResult::ArithmeticOverflow)?,
+        Side::Ask => quantity,
+    };
+    let (_, token_program) = ctx.token_account(&source.pubkey)?;
+    if deposit(ctx, metas, token_program, owner, source, vault, locked)? != locked {
+        return Err(TransactionResult::InvalidAccount);
+    }
+
+    let fills = market.book.place(side, RestingOrder { order_id, owner: owner.pubkey, price, quantity });
+    if market.book.len() > MAX_ORDERS {
+        return Err(TransactionResult::OrderBookFull);
+    }
+    let mut order = Order {
+        market: market_meta.pubkey,
+        owner: owner.pubkey,
+        order_id,
+        side,
+        price,
+        quantity,
+        remaining: quantity,
+        base_free: 0,
+        quote_free: 0,
+    };
+    // 被吃到的挂单必须随指令传入，否则无法记下它们成交得到的资金
+    for fill in &fills {
+        let (maker_key, _) = order_address(&market_meta.pubkey, fill.maker_order_id, program_id);
+        if !makers.iter().any(|meta| meta.pubkey == maker_key && meta.is_writable) {
+            return Err(TransactionResult::InvalidAccount);
+        }
+        let mut maker = load_order(ctx, program_id, &maker_key)?;
+        maker.fill(fill.price, fill.quantity)?;
+        order.fill(fill.price, fill.quantity)?;
+        store_order(ctx, maker_key, maker)?;
+    }
+
+    order_account.data = AccountPayload::Order(order);
+    ctx.staged().set_account(order_key, order_account)?;
+    market.next_order_id += 1;
+    store_market(ctx, market_meta.pubkey, market)
+}
+
+// accounts: [市场(可写), 订单账户(可写), owner(签名)]；没成交的部分退回订单账户，等待结算
+fn cancel_order(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let [market_meta, order_meta, owner, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    let mut market = load_market(ctx, program_id, &market_meta.pubkey)?;
+    let mut order = load_order(ctx, program_id, &order_meta.pubkey)?;
+    if !market_meta.is_writable || !order_meta.is_writable || order.market != market_meta.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if order.owner != owner.pubkey || !owner.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let resting = market.book.cancel(order.side, order.price, order.order_id).ok_or(TransactionResult::InvalidAccount)?;
+
+    match order.side {
+        Side::Bid => {
+            let refund = order.price.checked_mul(resting.quantity).ok_or(TransactionResult::ArithmeticOverflow)?;
+            order.quote_free = order.quote_free.checked_add(refund).ok_or(TransactionResult::ArithmeticOverflow)?;
+        }
+        Side::Ask => order.base_free = order.base_free.checked_add(resting.quantity).ok_or(TransactionResult::ArithmeticOverflow)?,
+    }
+    order.remaining = 0;
+    store_order(ctx, order_meta.pubkey, order)?;
+    store_market(ctx, market_meta.pubkey, market)
+}
+
+// accounts: [市场, 订单账户(可写), owner(签名), base_vault, quote_vault, 接收base的Token账户, 接收quote的Token账户]
+fn settle_funds(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let [market_meta, order_meta, owner, base_vault, quote_vault, base_destination, quote_destination, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    let market = load_market(ctx, program_id, &market_meta.pubkey)?;
+    let mut order = load_order(ctx, program_id, &order_meta.pubkey)?;
+    if !order_meta.is_writable || order.market != market_meta.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if market.base_vault != base_vault.pubkey || market.quote_vault != quote_vault.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if order.owner != owner.pubkey || !owner.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let (base, quote) = (order.base_free, order.quote_free);
+    if base == 0 && quote == 0 {
+        return Err(TransactionResult::NothingToClaim);
+    }
+
+    // 先清零再转出，和锁仓领取一样不依赖转账之后的状态
+    order.base_free = 0;
+    order.quote_free = 0;
+    store_order(ctx, order_meta.pubkey, order)?;
+    let (_, token_program) = ctx.token_account(&market.base_vault)?;
+    for (amount, vault, destination) in [(base, base_vault, base_destination), (quote, quote_vault, quote_destination)] {
+        if amount > 0 {
+            withdraw(ctx, program_id, metas, market_meta, &market, (vault.pubkey, destination.pubkey), amount, token_program)?;
+        }
+    }
+    Ok(())
+}
+
+// 用户签名把amount从source转进vault，返回vault实际增加的数量
+fn deposit(
+    ctx: &mut InvokeContext<'_, '_>,
+    metas: &[AccountMeta],
+    token_program: Pubkey,
+    owner: &AccountMeta,
+    source: &AccountMeta,
+    vault: &AccountMeta,
+    amount: u64,
+) -> Result<u64, TransactionResult> {
+    let before = ctx.token_account(&vault.pubkey)?.0.amount;
+    let transfer = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(source.pubkey, token_program, false),
+            AccountMeta::new(vault.pubkey, token_program, false),
+            AccountMeta::new_readonly(owner.pubkey, owner.owner, true),
+        ],
+    );
+    ctx.invoke(&transfer, metas)?;
+    Ok(ctx.token_account(&vault.pubkey)?.0.amount - before)
+}
+
+// 市场PDA通过invoke_signed签名，把amount从vault转给destination
+#[allow(clippy::too_many_arguments)]
+fn withdraw(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    market_meta: &AccountMeta,
+    market: &Market,
+    (vault, destination): (Pubkey, Pubkey),
+    amount: u64,
+    token_program: Pubkey,
+) -> Result<(), TransactionResult> {
+    let bump = [market.bump];
+    let seeds: &[&[u8]] = &[MARKET_SEED, market.base_mint.as_ref(), market.quote_mint.as_ref(), &bump];
+    let transfer = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(vault, token_program, false),
+            AccountMeta::new(destination, token_program, false),
+            AccountMeta::new_readonly(market_meta.pubkey, *program_id, true),
+        ],
+    );
+    ctx.invoke_signed(&transfer, metas, &[seeds])
+}
+
+fn load_market(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, key: &Pubkey) -> Result<Market, TransactionResult> {
+    match ctx.staged().get_account(key) {
+        Some(account) if account.owner == *program_id => match &account.data {
+            AccountPayload::Market(market) => Ok(market.clone()),
+            _ => Err(TransactionResult::InvalidAccount),
+        },
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+fn load_order(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, key: &Pubkey) -> Result<Order, TransactionResult> {
+    match ctx.staged().get_account(key) {
+        Some(account) if account.owner == *program_id => match &account.data {
+            AccountPayload::Order(order) => Ok(order.clone()),
+            _ => Err(TransactionResult::InvalidAccount),
+        },
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+fn store_market(ctx: &mut InvokeContext<'_, '_>, key: Pubkey, market: Market) -> Result<(), TransactionResult> {
+    let mut account = ctx.staged().get_account(&key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    account.data = AccountPayload::Market(market);
+    Ok(ctx.staged().set_account(key, account)?)
+}
+
+fn store_order(ctx: &mut InvokeContext<'_, '_>, key: Pubkey, order: Order) -> Result<(), TransactionResult> {
+    let mut account = ctx.staged().get_account(&key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    account.data = AccountPayload::Order(order);
+    Ok(ctx.staged().set_account(key, account)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpi::{Entrypoint, Programs};
+    use crate::ledger::{Account, Ledger};
+    use crate::mint::Mint;
+    use crate::signature::{Keypair, Signer};
+    use crate::test_utils::{create_mint, create_token_account, funded_keypair, sign_and_execute, token_amount, XorShift};
+    use crate::transaction::TransactionError;
+    use crate::SYSTEM_PROGRAM_ID;
+
+    fn resting(order_id: u64, price: u64, quantity: u64) -> RestingOrder {
+        RestingOrder { order_id, owner: Pubkey::default(), price, quantity }
+    }
+
+    #[test]
+    fn test_price_time_priority() {
+        let mut book = OrderBook::new();
+        assert!(book.place(Side::Ask, resting(0, 10, 5)).is_empty());
+        assert!(book.place(Side::Ask, resting(1, 10, 5)).is_empty());
+        assert!(book.place(Side::Ask, resting(2, 9, 3)).is_empty());
+        assert_eq!(book.best_ask(), Some(9));
+
+        // 先吃最低价，同价先吃先挂的单；成交价是挂单价
+        let fills = book.place(Side::Bid, resting(3, 10, 10));
+        assert_eq!(
+            fills,
+            vec![
+                Fill { maker_order_id: 2, price: 9, quantity: 3 },
+                Fill { maker_order_id: 0, price: 10, quantity: 5 },
+                Fill { maker_order_id: 1, price: 10, quantity: 2 },
+            ]
+        );
+        assert_eq!(book.depth(Side::Ask), vec![(10, 3)]);
+        assert_eq!(book.best_bid(), None);
+    }
+
+    #[test]
+    fn test_partial_fill_rests_and_cancel() {
+        let mut book = OrderBook::new();
+        book.place(Side::Bid, resting(0, 8, 4));
+        book.place(Side::Bid, resting(1, 7, 4));
+        book.place(Side::Bid, resting(2, 8, 1));
+        assert_eq!(book.depth(Side::Bid), vec![(8, 5), (7, 4)]);
+
+        // 卖单价格8只能和价格8的买单成交，剩下的挂在8
+        let fills = book.place(Side::Ask, resting(3, 8, 6));
+        assert_eq!(fills, vec![Fill { maker_order_id: 0, price: 8, quantity: 4 }, Fill { maker_order_id: 2, price: 8, quantity: 1 }]);
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(7), Some(8)));
+        assert_eq!(book.len(), 2);
+
+        assert_eq!(book.cancel(Side::Ask, 8, 3), Some(resting(3, 8, 1)));
+        assert_eq!(book.cancel(Side::Ask, 8, 3), None);
+        // 价格不对找不到订单
+        assert_eq!(book.cancel(Side::Bid, 8, 1), None);
+        assert_eq!(book.cancel(Side::Bid, 7, 1), Some(resting(1, 7, 4)));
+        assert!(book.is_empty());
+    }
+
+    // 性质测试：随机下单和撤单，订单簿永远不会交叉，成交和挂单的数量加起来等于下单数量
+    #[test]
+    fn test_book_never_crosses() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut book = OrderBook::new();
+        let mut placed: Vec<(Side, u64, u64)> = Vec::new();
+        for order_id in 0..2_000 {
+            if rng.next(5) == 0 && !placed.is_empty() {
+                let (side, price, id) = placed.swap_remove(rng.next(placed.len() as u64) as usize);
+                book.cancel(side, price, id);
+                continue;
+            }
+            let side = if rng.next(2) == 0 { Side::Bid } else { Side::Ask };
+            let (price, quantity) = (rng.next(20) + 90, rng.next(50) + 1);
+            let resting_before = book.len();
+            let fills = book.place(side, resting(order_id, price, quantity));
+            let filled: u64 = fills.iter().map(|fill| fill.quantity).sum();
+            assert!(filled <= quantity);
+            for fill in &fills {
+                match side {
+                    Side::Bid => assert!(fill.price <= price),
+                    Side::Ask => assert!(fill.price >= price),
+                }
+            }
+            let rested = book.orders(side).find(|order| order.order_id == order_id).map_or(0, |order| order.quantity);
+            assert_eq!(filled + rested, quantity);
+            if rested > 0 {
+                placed.push((side, price, order_id));
+                assert!(book.len() <= resting_before + 1);
+            }
+            if let (Some(bid), Some(ask)) = (book.best_bid(), book.best_ask()) {
+                assert!(bid < ask, "订单簿交叉: bid {} >= ask {}", bid, ask);
+            }
+        }
+    }
+
+    struct Fixture {
+        ledger: Ledger,
+        programs: Programs,
+        dex_program: Pubkey,
+        token_program: Pubkey,
+        alice: Keypair, // 持有base，挂卖单
+        bob: Keypair,   // 持有quote，买入
+        alice_base: Pubkey,
+        alice_quote: Pubkey,
+        bob_base: Pubkey,
+        bob_quote: Pubkey,
+        base_vault: Pubkey,
+        quote_vault: Pubkey,
+        market: Pubkey,
+    }
+
+    fn fixture() -> Fixture {
+        let mut ledger = Ledger::default();
+        let (dex_program, token_program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (alice, bob) = (funded_keypair(&mut ledger), funded_keypair(&mut ledger));
+        let [base_mint, quote_mint] = [(); 2].map(|_| create_mint(&mut ledger, token_program, Mint::new(0, Pubkey::new_unique())));
+        let (market, _) = market_address(&base_mint, &quote_mint, &dex_program);
+        let mut token = |mint, owner, amount| create_token_account(&mut ledger, token_program, Pubkey::new_unique(), mint, owner, amount);
+        let alice_base = token(base_mint, alice.pubkey(), 1_000);
+        let alice_quote = token(quote_mint, alice.pubkey(), 0);
+        let bob_base = token(base_mint, bob.pubkey(), 0);
+        let bob_quote = token(quote_mint, bob.pubkey(), 10_000);
+        let base_vault = token(base_mint, market, 0);
+        let quote_vault = token(quote_mint, market, 0);
+        let lamports = ledger.rent().minimum_balance(Market::LEN);
+        ledger.create_account(market, Account::new(lamports, dex_program, AccountPayload::Empty)).unwrap();
+
+        let programs = Programs::from([(dex_program, process as Entrypoint)]);
+        let mut f = Fixture {
+            ledger,
+            programs,
+            dex_program,
+            token_program,
+            alice,
+            bob,
+            alice_base,
+            alice_quote,
+            bob_base,
+            bob_quote,
+            base_vault,
+            quote_vault,
+            market,
+        };
+        let accounts = vec![
+            AccountMeta::new(f.market, f.dex_program, false),
+            AccountMeta::new_readonly(f.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+            AccountMeta::new_readonly(f.base_vault, f.token_program, false),
+            AccountMeta::new_readonly(f.quote_vault, f.token_program, false),
+        ];
+        let instruction = Instruction::new(f.dex_program, ProgramInstruction::CreateMarket, accounts);
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.alice, vec![instruction]), Ok(()));
+        f
+    }
+
+    impl Fixture {
+        fn market(&self) -> Market {
+            match &self.ledger.get_account(&self.market).unwrap().data {
+                AccountPayload::Market(market) => market.clone(),
+                _ => panic!("不是市场账户"),
+            }
+        }
+
+        fn order(&self, order_id: u64) -> Order {
+            let (key, _) = order_address(&self.market, order_id, &self.dex_program);
+            match &self.ledger.get_account(&key).unwrap().data {
+                AccountPayload::Order(order) => order.clone(),
+                _ => panic!("不是订单账户"),
+            }
+        }
+
+        fn trader(&self, alice: bool) -> (Pubkey, Pubkey, Pubkey) {
+            if alice {
+                (self.alice.pubkey(), self.alice_base, self.alice_quote)
+            } else {
+                (self.bob.pubkey(), self.bob_base, self.bob_quote)
+            }
+        }
+
+        fn execute_as(&mut self, alice: bool, instruction: Instruction) -> Result<(), TransactionError> {
+            let payer = if alice { &self.alice } else { &self.bob };
+            sign_and_execute(&mut self.ledger, &self.programs, payer, vec![instruction])
+        }
+
+        // 为下一个订单分配账户后下单，makers是可能被吃到的挂单；成功时返回order_id
+        fn place(&mut self, alice: bool, side: Side, price: u64, quantity: u64, makers: &[u64]) -> Result<u64, TransactionError> {
+            let order_id = self.market().next_order_id;
+            let (order_key, _) = order_address(&self.market, order_id, &self.dex_program);
+            if self.ledger.get_account(&order_key).is_none() {
+                let lamports = self.ledger.rent().minimum_balance(Order::LEN);
+                self.ledger.create_account(order_key, Account::new(lamports, self.dex_program, AccountPayload::Empty)).unwrap();
+            }
+            let (owner, base, quote) = self.trader(alice);
+            let (source, vault) = match side {
+                Side::Bid => (quote, self.quote_vault),
+                Side::Ask => (base, self.base_vault),
+            };
+            let mut accounts = vec![
+                AccountMeta::new(self.market, self.dex_program, false),
+                AccountMeta::new(order_key, self.dex_program, false),
+                AccountMeta::new_readonly(owner, SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(source, self.token_program, false),
+                AccountMeta::new(vault, self.token_program, false),
+            ];
+            for &maker in makers {
+                accounts.push(AccountMeta::new(order_address(&self.market, maker, &self.dex_program).0, self.dex_program, false));
+            }
+            let instruction = Instruction::new(self.dex_program, ProgramInstruction::PlaceOrder { side, price, quantity }, accounts);
+            self.execute_as(alice, instruction).map(|()| order_id)
+        }
+
+        fn cancel(&mut self, alice: bool, order_id: u64) -> Result<(), TransactionError> {
+            let (owner, _, _) = self.trader(alice);
+            let accounts = vec![
+                AccountMeta::new(self.market, self.dex_program, false),
+                AccountMeta::new(order_address(&self.market, order_id, &self.dex_program).0, self.dex_program, false),
+                AccountMeta::new_readonly(owner, SYSTEM_PROGRAM_ID, true),
+            ];
+            let instruction = Instruction::new(self.dex_program, ProgramInstruction::CancelOrder, accounts);
+            self.execute_as(alice, instruction)
+        }
+
+        fn settle(&mut self, alice: bool, order_id: u64) -> Result<(), TransactionError> {
+            let (owner, base, quote) = self.trader(alice);
+            let accounts = vec![
+                AccountMeta::new_readonly(self.market, self.dex_program, false),
+                AccountMeta::new(order_address(&self.market, order_id, &self.dex_program).0, self.dex_program, false),
+                AccountMeta::new_readonly(owner, SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.base_vault, self.token_program, false),
+                AccountMeta::new(self.quote_vault, self.token_program, false),
+                AccountMeta::new(base, self.token_program, false),
+                AccountMeta::new(quote, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.dex_program, ProgramInstruction::SettleFunds, accounts);
+            self.execute_as(alice, instruction)
+        }
+    }
+
+    #[test]
+    fn test_match_settle_and_cancel() {
+        let mut f = fixture();
+        // alice挂两个卖单：5个@10，5个@12
+        let ask_low = f.place(true, Side::Ask, 10, 5, &[]).unwrap();
+        let ask_high = f.place(true, Side::Ask, 12, 5, &[]).unwrap();
+        assert_eq!(token_amount(&f.ledger, &f.base_vault), 10);
+
+        // bob出价12买7个：先以10成交5个，再以12成交2个；锁定的84个quote里多付的10个退回
+        let bid = f.place(false, Side::Bid, 12, 7, &[ask_low, ask_high]).unwrap();
+        assert_eq!(token_amount(&f.ledger, &f.quote_vault), 84);
+        let (taker, low, high) = (f.order(bid), f.order(ask_low), f.order(ask_high));
+        assert_eq!((taker.remaining, taker.base_free, taker.quote_free), (0, 7, 10));
+        assert_eq!((low.remaining, low.quote_free), (0, 50));
+        assert_eq!((high.remaining, high.quote_free), (3, 24));
+        assert_eq!(f.market().book.depth(Side::Ask), vec![(12, 3)]);
+
+        assert_eq!(f.settle(false, bid), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.bob_base), token_amount(&f.ledger, &f.bob_quote)), (7, 10_000 - 74));
+        // 结算过的订单没有可以领取的资金
+        assert_eq!(f.settle(false, bid), Err(TransactionError::InstructionError(0, TransactionResult::NothingToClaim)));
+        // 只有owner可以撤单和结算
+        assert_eq!(f.cancel(false, ask_high), Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature)));
+        assert_eq!(f.settle(false, ask_low), Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature)));
+
+        // alice撤掉剩下的3个，和成交所得一起结算
+        assert_eq!(f.cancel(true, ask_high), Ok(()));
+        assert_eq!(f.cancel(true, ask_high), Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount)));
+        for order_id in [ask_low, ask_high] {
+            assert_eq!(f.settle(true, order_id), Ok(()));
+        }
+        assert_eq!((token_amount(&f.ledger, &f.alice_base), token_amount(&f.ledger, &f.alice_quote)), (1_000 - 7, 74));
+        assert_eq!((token_amount(&f.ledger, &f.base_vault), token_amount(&f.ledger, &f.quote_vault)), (0, 0));
+        assert!(f.market().book.is_empty());
+    }
+
+    #[test]
+    fn test_maker_accounts_required() {
+        let mut f = fixture();
+        let ask = f.place(true, Side::Ask, 10, 5, &[]).unwrap();
+        // 没有传入被吃到的挂单账户，整笔交易回滚，bob的quote不会被锁定
+        assert_eq!(
+            f.place(false, Side::Bid, 10, 2, &[]),
+            Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount))
+        );
+        assert_eq!(token_amount(&f.ledger, &f.bob_quote), 10_000);
+        assert_eq!(f.order(ask).remaining, 5);
+
+        // 不交叉的订单不需要其他账户；数量为0的订单被拒绝
+        assert_eq!(f.place(false, Side::Bid, 9, 2, &[]), Ok(1));
+        assert_eq!(
+            f.place(false, Side::Bid, 9, 0, &[]),
+            Err(TransactionError::InstructionError(0, TransactionResult::InvalidInstructionData))
+        );
+    }
+
+    #[test]
+    fn test_order_book_full() {
+        let mut f = fixture();
+        for price in 1..=MAX_ORDERS as u64 {
+            assert!(f.place(false, Side::Bid, price, 1, &[]).is_ok());
+        }
+        assert_eq!(
+            f.place(false, Side::Bid, 1, 1, &[]),
+            Err(TransactionError::InstructionError(0, TransactionResult::OrderBookFull))
+        );
+        // 能完全成交的订单不占位置
+        let top = MAX_ORDERS as u64 - 1;
+        assert_eq!(f.place(true, Side::Ask, MAX_ORDERS as u64, 1, &[top]), Ok(MAX_ORDERS as u64));
+        assert_eq!(f.market().book.len(), MAX_ORDERS - 1);
+    }
+
+    #[test]
+    fn test_borsh_roundtrip() {
+        let mut book = OrderBook::new();
+        book.place(Side::Bid, resting(0, 9, 4));
+        book.place(Side::Bid, resting(2, 9, 1));
+        book.place(Side::Ask, resting(1, 11, 3));
+        let market = Market {
+            base_mint: Pubkey::new_unique(),
+            quote_mint: Pubkey::new_unique(),
+            base_vault: Pubkey::new_unique(),
+            quote_vault: Pubkey::new_unique(),
+            next_order_id: 3,
+            bump: 254,
+            book,
+        };
+        let mut bytes = Vec::new();
+        market.serialize(&mut bytes);
+        assert!(bytes.len() <= Market::LEN);
+        assert_eq!(Market::deserialize(&mut bytes.as_slice()), Ok(market));
+
+        let order = Order {
+            market: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            order_id: 7,
+            side: Side::Ask,
+            price: 11,
+            quantity: 5,
+            remaining: 3,
+            base_free: 0,
+            quote_free: 22,
+        };
+        let mut bytes = Vec::new();
+        order.serialize(&mut bytes);
+        assert_eq!(bytes.len(), Order::LEN);
+        assert_eq!(Order::deserialize(&mut bytes.as_slice()), Ok(order));
+    }
+}
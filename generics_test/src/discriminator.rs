@@ -0,0 +1,102 @@
+// Anchor风格的账户类型标识：序列化时在数据前加8字节discriminator，
+// 取值为sha256("account:<类型名>")的前8字节，反序列化时先校验它，防止把A类型的账户当成B类型读取
+
+use std::any::type_name;
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::hash::hash;
+
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+// 去掉模块路径，只保留类型名，例如generics_test::TokenAccount -> TokenAccount
+fn short_type_name<T: ?Sized>() -> &'static str {
+    let full = type_name::<T>();
+    let path_end = full.find('<').unwrap_or(full.len());
+    let start = full[..path_end].rfind("::").map_or(0, |i| i + 2);
+    &full[start..]
+}
+
+pub fn discriminator<T: ?Sized>() -> [u8; DISCRIMINATOR_LEN] {
+    let preimage = format!("account:{}", short_type_name::<T>());
+    let mut out = [0u8; DISCRIMINATOR_LEN];
+    out.copy_from_slice(&hash(preimage.as_bytes())[..DISCRIMINATOR_LEN]);
+    out
+}
+
+pub trait AccountSerialize: BorshSerialize + Sized {
+    // discriminator + Borsh数据
+    fn try_serialize(&self) -> Vec<u8> {
+        let mut out = discriminator::<Self>().to_vec();
+        self.serialize(&mut out);
+        out
+    }
+}
+
+pub trait AccountDeserialize: BorshDeserialize {
+    // 先检查discriminator，再解析剩余的数据
+    fn try_from_slice_checked(data: &[u8]) -> Result<Self, BorshError> {
+        if data.len() < DISCRIMINATOR_LEN {
+            return Err(BorshError::UnexpectedEof {
+                needed: DISCRIMINATOR_LEN,
+                remaining: data.len(),
+            });
+        }
+        let (found, rest) = data.split_at(DISCRIMINATOR_LEN);
+        let expected = discriminator::<Self>();
+        if found != expected {
+            return Err(BorshError::DiscriminatorMismatch {
+                expected,
+                found: found.try_into().unwrap(),
+            });
+        }
+        Self::try_from_slice(rest)
+    }
+}
+
+impl<T: BorshSerialize> AccountSerialize for T {}
+
+impl<T: BorshDeserialize> AccountDeserialize for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Pubkey, TokenAccount, UserAccount};
+
+    #[test]
+    fn test_short_type_name() {
+        assert_eq!(short_type_name::<TokenAccount>(), "TokenAccount");
+        assert_eq!(short_type_name::<Vec<u8>>(), "Vec<u8>");
+    }
+
+    #[test]
+    fn test_discriminator_is_per_type() {
+        assert_ne!(discriminator::<TokenAccount>(), discriminator::<UserAccount>());
+        assert_eq!(discriminator::<TokenAccount>(), discriminator::<TokenAccount>());
+    }
+
+    #[test]
+    fn test_checked_roundtrip() {
+        let token = TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 7);
+        let bytes = token.try_serialize();
+        assert_eq!(&bytes[..DISCRIMINATOR_LEN], &discriminator::<TokenAccount>());
+        assert_eq!(TokenAccount::try_from_slice_checked(&bytes), Ok(token));
+    }
+
+    #[test]
+    fn test_reject_wrong_discriminator() {
+        let user = UserAccount {
+            username: String::from("alice"),
+            balance: 1,
+            created_at: 0,
+        };
+        let bytes = user.try_serialize();
+        assert!(matches!(
+            TokenAccount::try_from_slice_checked(&bytes),
+            Err(BorshError::DiscriminatorMismatch { .. })
+        ));
+        assert!(matches!(
+            UserAccount::try_from_slice_checked(&bytes[..4]),
+            Err(BorshError::UnexpectedEof { needed: DISCRIMINATOR_LEN, remaining: 4 })
+        ));
+    }
+}
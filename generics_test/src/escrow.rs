@@ -0,0 +1,413 @@
+// 托管程序 - 经典的Solana escrow示例：initializer把代币A存进由托管PDA控制的vault，
+// taker用约定数量的代币B原子地换走代币A；在被接受之前initializer可以取消并取回代币A。
+// 托管程序通过CPI调用Token程序转账，vault的authority是PDA，由invoke_signed代为签名
+//
+// 没有做成单独的crate：托管建立在这里的cpi、pda和Token程序之上，而generics_test没有Cargo.toml，
+// 别的crate没法依赖它。指令和Escrow账户也因此直接加在ProgramInstruction和AccountPayload里——
+// 交易的编码解码、账户的Borsh/JSON表和快照都只认这两个枚举，后面的程序都沿用这个做法
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::cpi::InvokeContext;
+use crate::ledger::AccountPayload;
+use crate::pda::derive_pda;
+use crate::transaction::Instruction;
+use crate::{AccountMeta, ProgramInstruction, Pubkey, TransactionResult};
+
+pub const ESCROW_SEED: &[u8] = b"escrow";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Escrow {
+    pub initializer: Pubkey,
+    pub vault: Pubkey,           // 保存代币A的Token账户，authority是托管PDA
+    pub receive_account: Pubkey, // initializer接收代币B的Token账户
+    pub amount: u64,             // 存入的代币A数量
+    pub expected_amount: u64,    // 要求换取的代币B数量
+    pub bump: u8,                // 托管PDA的bump，签名时不用重新推导
+}
+
+impl Escrow {
+    // 三个Pubkey(各32) + 两个u64 + bump(1)
+    pub const LEN: usize = 32 * 3 + 8 + 8 + 1;
+}
+
+impl BorshSerialize for Escrow {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.initializer.serialize(out);
+        self.vault.serialize(out);
+        self.receive_account.serialize(out);
+        self.amount.serialize(out);
+        self.expected_amount.serialize(out);
+        self.bump.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Escrow {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            initializer: Pubkey::deserialize(buf)?,
+            vault: Pubkey::deserialize(buf)?,
+            receive_account: Pubkey::deserialize(buf)?,
+            amount: u64::deserialize(buf)?,
+            expected_amount: u64::deserialize(buf)?,
+            bump: u8::deserialize(buf)?,
+        })
+    }
+}
+
+// 托管账户地址：种子为["escrow", initializer, vault]，同一个initializer可以用不同的vault开多个托管
+pub fn escrow_address(initializer: &Pubkey, vault: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[ESCROW_SEED, initializer.as_ref(), vault.as_ref()], program_id)
+}
+
+// 托管程序的入口函数，注册到Programs后由运行时调用
+pub fn process(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+    let program_id = instruction.program_id;
+    let metas = &instruction.accounts;
+    match instruction.data {
+        ProgramInstruction::InitializeEscrow { amount, expected_amount } => {
+            initialize(ctx, &program_id, metas, amount, expected_amount)
+        }
+        ProgramInstruction::Exchange { amount } => exchange(ctx, &program_id, metas, amount),
+        ProgramInstruction::CancelEscrow => cancel(ctx, &program_id, metas),
+        _ => Err(TransactionResult::InvalidInstructionData),
+    }
+}
+
+// accounts: [托管账户(可写，空的PDA账户), initializer(签名), 存入代币A的账户(可写),
+//            接收代币B的账户, vault(可写，authority是托管PDA)]
+fn initialize(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    amount: u64,
+    expected_amount: u64,
+) -> Result<(), TransactionResult> {
+    let [escrow_meta, initializer, deposit, receive, vault, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !initializer.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if amount == 0 || expected_amount == 0 {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+    let (escrow_key, bump) = escrow_address(&initializer.pubkey, &vault.pubkey, program_id);
+    if escrow_meta.pubkey != escrow_key || !escrow_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let mut account = ctx.staged().get_account(&escrow_key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    if account.owner != *program_id || account.data != AccountPayload::Empty {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if account.lamports < ctx.staged().rent().minimum_balance(Escrow::LEN) {
+        return Err(TransactionResult::InsufficientFunds);
+    }
+    // vault必须是新的、由托管PDA控制的同一mint的Token账户，否则取出代币时PDA无权签名
+    let (vault_token, token_program) = ctx.token_account(&vault.pubkey)?;
+    let (deposit_token, _) = ctx.token_account(&deposit.pubkey)?;
+    let (receive_token, _) = ctx.token_account(&receive.pubkey)?;
+    if vault_token.owner != escrow_key || vault_token.amount != 0 || vault_token.mint != deposit_token.mint {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if receive_token.owner != initializer.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+
+    let transfer = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(deposit.pubkey, token_program, false),
+            AccountMeta::new(vault.pubkey, token_program, false),
+            AccountMeta::new_readonly(initializer.pubkey, initializer.owner, true),
+        ],
+    );
+    ctx.invoke(&transfer, metas)?;
+
+    account.data = AccountPayload::Escrow(Escrow {
+        initializer: initializer.pubkey,
+        vault: vault.pubkey,
+        receive_account: receive.pubkey,
+        amount,
+        expected_amount,
+        bump,
+    });
+    Ok(ctx.staged().set_account(escrow_key, account)?)
+}
+
+// accounts: [托管账户(可写), taker(签名), taker付出代币B的账户(可写), taker接收代币A的账户(可写),
+//            initializer接收代币B的账户(可写), vault(可写), initializer(可写，退还租金)]
+fn exchange(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta], amount: u64) -> Result<(), TransactionResult> {
+    let [escrow_meta, taker, taker_deposit, taker_receive, initializer_receive, vault, initializer, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !taker.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let escrow = load(ctx, program_id, &escrow_meta.pubkey)?;
+    if escrow.vault != vault.pubkey || escrow.receive_account != initializer_receive.pubkey || escrow.initializer != initializer.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    // 防止initializer在taker签名之后修改条件：taker看到的数量必须和托管记录一致
+    if escrow.amount != amount {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+    let (_, token_program) = ctx.token_account(&vault.pubkey)?;
+
+    let pay = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount: escrow.expected_amount },
+        vec![
+            AccountMeta::new(taker_deposit.pubkey, token_program, false),
+            AccountMeta::new(initializer_receive.pubkey, token_program, false),
+            AccountMeta::new_readonly(taker.pubkey, taker.owner, true),
+        ],
+    );
+    ctx.invoke(&pay, metas)?;
+    release(ctx, program_id, metas, &escrow_meta.pubkey, &escrow, taker_receive.pubkey, token_program)
+}
+
+// accounts: [托管账户(可写), initializer(签名，可写), 退回代币A的账户(可写), vault(可写)]
+fn cancel(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let [escrow_meta, initializer, refund, vault, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    let escrow = load(ctx, program_id, &escrow_meta.pubkey)?;
+    if escrow.initializer != initializer.pubkey || escrow.vault != vault.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if !initializer.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let (_, token_program) = ctx.token_account(&vault.pubkey)?;
+    release(ctx, program_id, metas, &escrow_meta.pubkey, &escrow, refund.pubkey, token_program)
+}
+
+// 把vault中的代币A全部转给recipient，然后关闭vault和托管账户，lamports都退给initializer
+fn release(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    escrow_key: &Pubkey,
+    escrow: &Escrow,
+    recipient: Pubkey,
+    token_program: Pubkey,
+) -> Result<(), TransactionResult> {
+    let bump = [escrow.bump];
+    let seeds: &[&[u8]] = &[ESCROW_SEED, escrow.initializer.as_ref(), escrow.vault.as_ref(), &bump];
+    let pda_signer = AccountMeta::new_readonly(*escrow_key, *program_id, true);
+
+    let withdraw = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount: escrow.amount },
+        vec![
+            AccountMeta::new(escrow.vault, token_program, false),
+            AccountMeta::new(recipient, token_program, false),
+            pda_signer,
+        ],
+    );
+    ctx.invoke_signed(&withdraw, metas, &[seeds])?;
+    let close_vault = Instruction::new(
+        token_program,
+        ProgramInstruction::CloseTokenAccount,
+        vec![
+            AccountMeta::new(escrow.vault, token_program, false),
+            AccountMeta::new(escrow.initializer, token_program, false),
+            pda_signer,
+        ],
+    );
+    ctx.invoke_signed(&close_vault, metas, &[seeds])?;
+
+    let closed = ctx.staged().close_account(*escrow_key)?;
+    let mut initializer = ctx.staged().get_account(&escrow.initializer).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    initializer.lamports = initializer.lamports.checked_add(closed.lamports).ok_or(TransactionResult::ArithmeticOverflow)?;
+    Ok(ctx.staged().set_account(escrow.initializer, initializer)?)
+}
+
+// 读取本程序拥有的托管账户
+fn load(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, key: &Pubkey) -> Result<Escrow, TransactionResult> {
+    match ctx.staged().get_account(key) {
+        Some(account) if account.owner == *program_id => match &account.data {
+            AccountPayload::Escrow(escrow) => Ok(escrow.clone()),
+            _ => Err(TransactionResult::InvalidAccount),
+        },
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpi::{Entrypoint, Programs};
+    use crate::ledger::{Account, Ledger};
+    use crate::mint::Mint;
+    use crate::signature::{Keypair, Signer};
+    use crate::test_utils::{create_mint, create_token_account, funded_keypair, sign_and_execute, token_amount};
+    use crate::transaction::TransactionError;
+    use crate::SYSTEM_PROGRAM_ID;
+
+    struct Fixture {
+        ledger: Ledger,
+        programs: Programs,
+        escrow_program: Pubkey,
+        token_program: Pubkey,
+        alice: Keypair, // initializer，持有代币A
+        bob: Keypair,   // taker，持有代币B
+        alice_a: Pubkey,
+        alice_b: Pubkey,
+        bob_a: Pubkey,
+        bob_b: Pubkey,
+        vault: Pubkey,
+        escrow: Pubkey,
+    }
+
+    fn fixture() -> Fixture {
+        let mut ledger = Ledger::default();
+        let (escrow_program, token_program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (alice, bob) = (funded_keypair(&mut ledger), funded_keypair(&mut ledger));
+        let [mint_a, mint_b] = [(); 2].map(|_| create_mint(&mut ledger, token_program, Mint::new(0, Pubkey::new_unique())));
+        let mut token = |key, mint, owner, amount| create_token_account(&mut ledger, token_program, key, mint, owner, amount);
+        let alice_a = token(Pubkey::new_unique(), mint_a, alice.pubkey(), 100);
+        let alice_b = token(Pubkey::new_unique(), mint_b, alice.pubkey(), 0);
+        let bob_a = token(Pubkey::new_unique(), mint_a, bob.pubkey(), 0);
+        let bob_b = token(Pubkey::new_unique(), mint_b, bob.pubkey(), 50);
+        // 托管PDA由vault地址推导，vault的authority又是托管PDA，所以先选定vault地址
+        let vault = Pubkey::new_unique();
+        let (escrow, _) = escrow_address(&alice.pubkey(), &vault, &escrow_program);
+        token(vault, mint_a, escrow, 0);
+        let lamports = ledger.rent().minimum_balance(Escrow::LEN);
+        ledger.create_account(escrow, Account::new(lamports, escrow_program, AccountPayload::Empty)).unwrap();
+
+        let programs = Programs::from([(escrow_program, process as Entrypoint)]);
+        Fixture { ledger, programs, escrow_program, token_program, alice, bob, alice_a, alice_b, bob_a, bob_b, vault, escrow }
+    }
+
+    impl Fixture {
+        fn initialize(&mut self, amount: u64, expected_amount: u64) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new(self.escrow, self.escrow_program, false),
+                AccountMeta::new_readonly(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.alice_a, self.token_program, false),
+                AccountMeta::new_readonly(self.alice_b, self.token_program, false),
+                AccountMeta::new(self.vault, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.escrow_program, ProgramInstruction::InitializeEscrow { amount, expected_amount }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.alice, vec![instruction])
+        }
+
+        fn exchange(&mut self, amount: u64) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new(self.escrow, self.escrow_program, false),
+                AccountMeta::new_readonly(self.bob.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.bob_b, self.token_program, false),
+                AccountMeta::new(self.bob_a, self.token_program, false),
+                AccountMeta::new(self.alice_b, self.token_program, false),
+                AccountMeta::new(self.vault, self.token_program, false),
+                AccountMeta::new(self.alice.pubkey(), SYSTEM_PROGRAM_ID, false),
+            ];
+            let instruction = Instruction::new(self.escrow_program, ProgramInstruction::Exchange { amount }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.bob, vec![instruction])
+        }
+
+        fn cancel(&mut self) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new(self.escrow, self.escrow_program, false),
+                AccountMeta::new(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.alice_a, self.token_program, false),
+                AccountMeta::new(self.vault, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.escrow_program, ProgramInstruction::CancelEscrow, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.alice, vec![instruction])
+        }
+    }
+
+    #[test]
+    fn test_initialize_and_exchange() {
+        let mut f = fixture();
+        assert_eq!(f.initialize(30, 20), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.alice_a), token_amount(&f.ledger, &f.vault)), (70, 30));
+        match &f.ledger.get_account(&f.escrow).unwrap().data {
+            AccountPayload::Escrow(escrow) => assert_eq!((escrow.amount, escrow.expected_amount), (30, 20)),
+            _ => panic!("不是托管账户"),
+        }
+        // 同一个托管不能初始化两次
+        assert!(f.initialize(1, 1).is_err());
+
+        // taker看到的数量和托管记录不一致
+        assert_eq!(f.exchange(31), Err(TransactionError::InstructionError(0, TransactionResult::InvalidInstructionData)));
+        let alice_lamports = f.ledger.get_account(&f.alice.pubkey()).unwrap().lamports;
+        assert_eq!(f.exchange(30), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.bob_a), token_amount(&f.ledger, &f.bob_b)), (30, 30));
+        assert_eq!(token_amount(&f.ledger, &f.alice_b), 20);
+        // vault和托管账户都已关闭，租金押金退给initializer
+        assert!(f.ledger.get_account(&f.vault).is_none());
+        assert!(f.ledger.get_account(&f.escrow).is_none());
+        assert!(f.ledger.get_account(&f.alice.pubkey()).unwrap().lamports > alice_lamports);
+    }
+
+    #[test]
+    fn test_exchange_is_atomic() {
+        let mut f = fixture();
+        assert_eq!(f.initialize(30, 80), Ok(()));
+        // bob只有50个代币B，付款失败时代币A也不会转出
+        assert_eq!(f.exchange(30), Err(TransactionError::InstructionError(0, TransactionResult::InsufficientFunds)));
+        assert_eq!((token_amount(&f.ledger, &f.vault), token_amount(&f.ledger, &f.bob_a), token_amount(&f.ledger, &f.bob_b)), (30, 0, 50));
+        assert!(f.ledger.get_account(&f.escrow).is_some());
+    }
+
+    #[test]
+    fn test_cancel_returns_deposit() {
+        let mut f = fixture();
+        assert_eq!(f.initialize(30, 20), Ok(()));
+        // 只有initializer能取消
+        let (alice, bob) = (f.alice.pubkey(), f.bob.pubkey());
+        let accounts = vec![
+            AccountMeta::new(f.escrow, f.escrow_program, false),
+            AccountMeta::new(alice, SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new(f.alice_a, f.token_program, false),
+            AccountMeta::new(f.vault, f.token_program, false),
+            AccountMeta::new_readonly(bob, SYSTEM_PROGRAM_ID, true),
+        ];
+        let instruction = Instruction::new(f.escrow_program, ProgramInstruction::CancelEscrow, accounts);
+        assert_eq!(
+            sign_and_execute(&mut f.ledger, &f.programs, &f.bob, vec![instruction]),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+
+        assert_eq!(f.cancel(), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.alice_a), 100);
+        assert!(f.ledger.get_account(&f.escrow).is_none());
+        // 取消之后不能再接受
+        assert_eq!(f.exchange(30), Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount)));
+    }
+
+    #[test]
+    fn test_vault_must_belong_to_escrow_pda() {
+        let mut f = fixture();
+        let mut account = f.ledger.get_account(&f.vault).unwrap().clone();
+        if let AccountPayload::Token(token) = &mut account.data {
+            token.owner = f.alice.pubkey();
+        }
+        let mut staged = f.ledger.stage();
+        staged.set_account(f.vault, account).unwrap();
+        staged.commit(f.alice.pubkey());
+        assert_eq!(f.initialize(30, 20), Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount)));
+        assert_eq!(token_amount(&f.ledger, &f.alice_a), 100);
+    }
+
+    #[test]
+    fn test_borsh_roundtrip_fits_len() {
+        let escrow = Escrow {
+            initializer: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            receive_account: Pubkey::new_unique(),
+            amount: 1,
+            expected_amount: 2,
+            bump: 255,
+        };
+        let mut bytes = Vec::new();
+        escrow.serialize(&mut bytes);
+        assert_eq!(bytes.len(), Escrow::LEN);
+        assert_eq!(Escrow::try_from_slice(&bytes), Ok(escrow));
+    }
+}
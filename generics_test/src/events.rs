@@ -0,0 +1,105 @@
+// Token事件日志 - 类似于程序通过emit!发出的事件：Token程序每次铸造、转账、销毁都记录一条结构化事件，
+// 事件按提交顺序编号。下游（索引器、订阅推送）记住读到的位置，用iter_events_since增量读取
+
+use std::fmt;
+
+use crate::Pubkey;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenEvent {
+    Transfer {
+        mint: Pubkey,
+        source: Pubkey,
+        destination: Pubkey,
+        amount: u64, // 从转出账户扣除的数量
+        fee: u64,    // 其中进入手续费和抽成vault的部分
+    },
+    MintTo { mint: Pubkey, destination: Pubkey, amount: u64 },
+    Burn { mint: Pubkey, source: Pubkey, amount: u64 },
+}
+
+impl TokenEvent {
+    pub fn mint(&self) -> Pubkey {
+        match self {
+            TokenEvent::Transfer { mint, .. } | TokenEvent::MintTo { mint, .. } | TokenEvent::Burn { mint, .. } => *mint,
+        }
+    }
+}
+
+impl fmt::Display for TokenEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenEvent::Transfer { source, destination, amount, fee, .. } => {
+                write!(f, "Transfer {} -> {}: {}（手续费{}）", source, destination, amount, fee)
+            }
+            TokenEvent::MintTo { destination, amount, .. } => write!(f, "MintTo {}: {}", destination, amount),
+            TokenEvent::Burn { source, amount, .. } => write!(f, "Burn {}: {}", source, amount),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventRecord {
+    pub seq: u64,  // 日志中的序号，从0开始连续递增
+    pub slot: u64, // 事件所在交易提交时的slot
+    pub event: TokenEvent,
+}
+
+impl fmt::Display for EventRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} slot={} {}", self.seq, self.slot, self.event)
+    }
+}
+
+// 只追加；失败交易的事件随暂存区一起丢弃，不会出现在这里
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    records: Vec<EventRecord>,
+}
+
+impl EventLog {
+    pub fn push(&mut self, slot: u64, event: TokenEvent) -> u64 {
+        let seq = self.next_seq();
+        self.records.push(EventRecord { seq, slot, event });
+        seq
+    }
+
+    // 下一条事件的序号，也就是订阅者从"现在"开始读时应该传给iter_events_since的值
+    pub fn next_seq(&self) -> u64 {
+        self.records.len() as u64
+    }
+
+    pub fn records(&self) -> &[EventRecord] {
+        &self.records
+    }
+
+    // 序号>=seq的所有事件。序号连续，所以直接按下标切片，不需要扫描前面的事件
+    pub fn iter_events_since(&self, seq: u64) -> impl Iterator<Item = &EventRecord> + '_ {
+        let start = usize::try_from(seq).map_or(self.records.len(), |seq| seq.min(self.records.len()));
+        self.records[start..].iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iter_events_since() {
+        let (mint, account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut log = EventLog::default();
+        assert_eq!(log.iter_events_since(0).count(), 0);
+        for amount in [10, 20, 30] {
+            log.push(1, TokenEvent::MintTo { mint, destination: account, amount });
+        }
+        log.push(2, TokenEvent::Burn { mint, source: account, amount: 5 });
+
+        let since: Vec<u64> = log.iter_events_since(2).map(|record| record.seq).collect();
+        assert_eq!(since, [2, 3]);
+        assert_eq!(log.iter_events_since(1).next().unwrap().event, TokenEvent::MintTo { mint, destination: account, amount: 20 });
+        // 读到末尾之后（或者传入更大的序号）没有新事件
+        assert_eq!(log.iter_events_since(log.next_seq()).count(), 0);
+        assert_eq!(log.iter_events_since(u64::MAX).count(), 0);
+        assert_eq!(log.records()[3].to_string(), format!("#3 slot=2 Burn {}: 5", account));
+    }
+}
@@ -0,0 +1,138 @@
+// 水龙头 - 类似测试网的airdrop：给账户发lamports，方便准备演示数据。
+// 为了不被刷，每个地址领取后要等cooldown_slots个slot才能再领，每个slot发出的总量也有上限
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::ledger::{Ledger, LedgerError};
+use crate::Pubkey;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FaucetError {
+    ZeroLamports,
+    CooldownActive { retry_slot: u64 },    // 这个地址在retry_slot之前不能再领
+    SlotBudgetExceeded { remaining: u64 }, // 本slot只剩remaining可以发
+    Ledger(LedgerError),                   // 入账失败，例如新账户的lamports不够免租
+}
+
+impl fmt::Display for FaucetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaucetError::ZeroLamports => write!(f, "airdrop数量不能为0"),
+            FaucetError::CooldownActive { retry_slot } => write!(f, "领取太频繁，到slot {}才能再领", retry_slot),
+            FaucetError::SlotBudgetExceeded { remaining } => write!(f, "本slot的额度不足，只剩{} lamports", remaining),
+            FaucetError::Ledger(error) => write!(f, "入账失败: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for FaucetError {}
+
+#[derive(Debug, Clone)]
+pub struct Faucet {
+    cooldown_slots: u64,
+    slot_budget: u64,                    // 每个slot最多发出的lamports
+    last_airdrop: BTreeMap<Pubkey, u64>, // 每个地址上一次成功领取的slot
+    spent: (u64, u64),                   // (slot, 这个slot已经发出的lamports)
+}
+
+impl Faucet {
+    pub fn new(cooldown_slots: u64, slot_budget: u64) -> Self {
+        Self { cooldown_slots, slot_budget, last_airdrop: BTreeMap::new(), spent: (0, 0) }
+    }
+
+    // 按账本当前的slot检查限制，全部通过才入账；失败的请求不计入冷却和额度
+    pub fn request_airdrop(&mut self, ledger: &mut Ledger, pubkey: Pubkey, lamports: u64) -> Result<(), FaucetError> {
+        if lamports == 0 {
+            return Err(FaucetError::ZeroLamports);
+        }
+        let slot = ledger.slot();
+        if let Some(&last) = self.last_airdrop.get(&pubkey) {
+            let retry_slot = last.saturating_add(self.cooldown_slots);
+            if slot < retry_slot {
+                return Err(FaucetError::CooldownActive { retry_slot });
+            }
+        }
+        let spent = if self.spent.0 == slot { self.spent.1 } else { 0 };
+        let remaining = self.slot_budget - spent;
+        if lamports > remaining {
+            return Err(FaucetError::SlotBudgetExceeded { remaining });
+        }
+
+        ledger.airdrop(&pubkey, lamports).map_err(FaucetError::Ledger)?;
+        self.last_airdrop.insert(pubkey, slot);
+        self.spent = (slot, spent + lamports);
+        Ok(())
+    }
+
+    // 当前slot还能发出的lamports
+    pub fn remaining_budget(&self, slot: u64) -> u64 {
+        if self.spent.0 == slot { self.slot_budget - self.spent.1 } else { self.slot_budget }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rent::Rent;
+
+    #[test]
+    fn test_airdrop_creates_and_credits_accounts() {
+        let mut ledger = Ledger::new(Rent::default());
+        let minimum = ledger.rent().minimum_balance(0);
+        let mut faucet = Faucet::new(0, minimum * 10);
+        let alice = Pubkey::new_unique();
+
+        // 新账户按System账户创建，数量必须够免租
+        assert_eq!(
+            faucet.request_airdrop(&mut ledger, alice, minimum - 1),
+            Err(FaucetError::Ledger(LedgerError::NotRentExempt { required: minimum, actual: minimum - 1 }))
+        );
+        assert_eq!(faucet.remaining_budget(ledger.slot()), minimum * 10);
+        assert_eq!(faucet.request_airdrop(&mut ledger, alice, minimum), Ok(()));
+        assert_eq!(faucet.request_airdrop(&mut ledger, alice, 5), Ok(()));
+        assert_eq!(ledger.get_account(&alice).unwrap().lamports, minimum + 5);
+        assert_eq!(faucet.request_airdrop(&mut ledger, alice, 0), Err(FaucetError::ZeroLamports));
+    }
+
+    #[test]
+    fn test_cooldown_per_address() {
+        let mut ledger = Ledger::new(Rent::default());
+        let minimum = ledger.rent().minimum_balance(0);
+        let mut faucet = Faucet::new(5, u64::MAX);
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        assert_eq!(faucet.request_airdrop(&mut ledger, alice, minimum), Ok(()));
+        let retry_slot = ledger.slot() + 5;
+        assert_eq!(faucet.request_airdrop(&mut ledger, alice, minimum), Err(FaucetError::CooldownActive { retry_slot }));
+        // 冷却只针对领过的地址
+        assert_eq!(faucet.request_airdrop(&mut ledger, bob, minimum), Ok(()));
+
+        ledger.tick(4);
+        assert_eq!(faucet.request_airdrop(&mut ledger, alice, minimum), Err(FaucetError::CooldownActive { retry_slot }));
+        ledger.tick(1);
+        assert_eq!(faucet.request_airdrop(&mut ledger, alice, minimum), Ok(()));
+        assert_eq!(ledger.get_account(&alice).unwrap().lamports, minimum * 2);
+    }
+
+    #[test]
+    fn test_budget_resets_each_slot() {
+        let mut ledger = Ledger::new(Rent::default());
+        let minimum = ledger.rent().minimum_balance(0);
+        let mut faucet = Faucet::new(0, minimum * 3);
+        let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+
+        assert_eq!(faucet.request_airdrop(&mut ledger, keys[0], minimum * 2), Ok(()));
+        assert_eq!(
+            faucet.request_airdrop(&mut ledger, keys[1], minimum * 2),
+            Err(FaucetError::SlotBudgetExceeded { remaining: minimum })
+        );
+        assert!(ledger.get_account(&keys[1]).is_none());
+        assert_eq!(faucet.request_airdrop(&mut ledger, keys[2], minimum), Ok(()));
+        assert_eq!(faucet.remaining_budget(ledger.slot()), 0);
+
+        ledger.tick(1);
+        assert_eq!(faucet.remaining_budget(ledger.slot()), minimum * 3);
+        assert_eq!(faucet.request_airdrop(&mut ledger, keys[3], minimum * 3), Ok(()));
+    }
+}
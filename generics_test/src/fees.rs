@@ -0,0 +1,36 @@
+// 交易费 - 按签名数量收取，由交易的fee payer支付
+
+// 与Solana一致：每个签名5000 lamports
+pub const DEFAULT_LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeSchedule {
+    pub lamports_per_signature: u64,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        Self {
+            lamports_per_signature: DEFAULT_LAMPORTS_PER_SIGNATURE,
+        }
+    }
+}
+
+impl FeeSchedule {
+    pub fn fee(&self, num_signatures: usize) -> u64 {
+        self.lamports_per_signature.saturating_mul(num_signatures as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_per_signature() {
+        let schedule = FeeSchedule::default();
+        assert_eq!(schedule.fee(1), 5_000);
+        assert_eq!(schedule.fee(3), 15_000);
+        assert_eq!(FeeSchedule { lamports_per_signature: u64::MAX }.fee(2), u64::MAX);
+    }
+}
@@ -0,0 +1,670 @@
+// 治理投票 - 类似于SPL Governance：持有治理代币的人对提案投票，权重是提案创建时（快照slot）的代币余额，
+// 之后再转账或买入都不会改变票数。投票期结束后任何人都可以结算提案：总票数达到quorum、
+// 赞成票占比达到threshold时，提案中保存的指令由治理PDA签名自动执行
+
+use std::fmt;
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::cpi::InvokeContext;
+use crate::ledger::AccountPayload;
+use crate::mint::MAX_FEE_BASIS_POINTS;
+use crate::pda::derive_pda;
+use crate::transaction::Instruction;
+use crate::{AccountMeta, ProgramInstruction, Pubkey, TransactionResult};
+
+pub const GOVERNANCE_SEED: &[u8] = b"governance";
+pub const PROPOSAL_SEED: &[u8] = b"proposal";
+pub const VOTE_SEED: &[u8] = b"vote";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GovernanceError {
+    InvalidThreshold(u16), // 赞成票比例必须在1到10000基点之间
+    ZeroVotingPeriod,
+}
+
+impl fmt::Display for GovernanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GovernanceError::InvalidThreshold(bps) => write!(f, "通过比例{}基点无效，必须在1到{}之间", bps, MAX_FEE_BASIS_POINTS),
+            GovernanceError::ZeroVotingPeriod => write!(f, "投票期不能为0个slot"),
+        }
+    }
+}
+
+impl std::error::Error for GovernanceError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProposalState {
+    Voting,   // 投票中，或者投票期已结束但还没有结算
+    Executed, // 通过，提案指令已经执行
+    Defeated, // 没有通过
+}
+
+impl BorshSerialize for ProposalState {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            ProposalState::Voting => 0,
+            ProposalState::Executed => 1,
+            ProposalState::Defeated => 2,
+        };
+        tag.serialize(out);
+    }
+}
+
+impl BorshDeserialize for ProposalState {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        match u8::deserialize(buf)? {
+            0 => Ok(ProposalState::Voting),
+            1 => Ok(ProposalState::Executed),
+            2 => Ok(ProposalState::Defeated),
+            tag => Err(BorshError::InvalidEnumTag(tag)),
+        }
+    }
+}
+
+// 一个治理代币对应的治理配置，账户本身是PDA，也是提案指令的签名者（例如国库Token账户的authority）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Governance {
+    pub mint: Pubkey,                // 治理代币
+    pub quorum: u64,                 // 提案通过至少需要的总票数
+    pub threshold_basis_points: u16, // 赞成票占总票数的最低比例
+    pub voting_slots: u64,           // 提案创建后可以投票的slot数
+    pub proposal_count: u64,         // 已创建的提案数，也是下一个提案的编号
+    pub bump: u8,
+}
+
+impl Governance {
+    // mint(32) + quorum(8) + threshold(2) + voting_slots(8) + proposal_count(8) + bump(1)
+    pub const LEN: usize = 32 + 8 + 2 + 8 + 8 + 1;
+
+    pub fn new(mint: Pubkey, quorum: u64, threshold_basis_points: u16, voting_slots: u64, bump: u8) -> Result<Self, GovernanceError> {
+        if threshold_basis_points == 0 || threshold_basis_points > MAX_FEE_BASIS_POINTS {
+            return Err(GovernanceError::InvalidThreshold(threshold_basis_points));
+        }
+        if voting_slots == 0 {
+            return Err(GovernanceError::ZeroVotingPeriod);
+        }
+        Ok(Self { mint, quorum, threshold_basis_points, voting_slots, proposal_count: 0, bump })
+    }
+
+    // 按最终票数结算：没有人投票的提案不会通过，即使quorum为0
+    pub fn evaluate(&self, yes_votes: u64, no_votes: u64) -> ProposalState {
+        let total = yes_votes as u128 + no_votes as u128;
+        let approved = yes_votes as u128 * MAX_FEE_BASIS_POINTS as u128 >= total * self.threshold_basis_points as u128;
+        if total > 0 && total >= self.quorum as u128 && approved {
+            ProposalState::Executed
+        } else {
+            ProposalState::Defeated
+        }
+    }
+}
+
+impl BorshSerialize for Governance {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.mint.serialize(out);
+        self.quorum.serialize(out);
+        self.threshold_basis_points.serialize(out);
+        self.voting_slots.serialize(out);
+        self.proposal_count.serialize(out);
+        self.bump.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Governance {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            mint: Pubkey::deserialize(buf)?,
+            quorum: u64::deserialize(buf)?,
+            threshold_basis_points: u16::deserialize(buf)?,
+            voting_slots: u64::deserialize(buf)?,
+            proposal_count: u64::deserialize(buf)?,
+            bump: u8::deserialize(buf)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proposal {
+    pub governance: Pubkey,
+    pub proposer: Pubkey,
+    pub index: u64,
+    pub instruction: Instruction, // 通过后由治理PDA签名执行的指令
+    pub snapshot_slot: u64,       // 投票权重按这个slot开始时的余额计算
+    pub voting_end_slot: u64,     // 从这个slot起不能再投票，可以结算
+    pub yes_votes: u64,
+    pub no_votes: u64,
+    pub state: ProposalState,
+}
+
+impl Proposal {
+    // 两个Pubkey(各32) + 五个u64 + state(1)，再加上长度不固定的指令
+    pub const BASE_LEN: usize = 32 * 2 + 8 * 5 + 1;
+
+    // 保存instruction的提案账户需要的数据长度，创建提案账户时按它计算免租金额
+    pub fn space(instruction: &Instruction) -> usize {
+        let mut bytes = Vec::new();
+        instruction.serialize(&mut bytes);
+        Self::BASE_LEN + bytes.len()
+    }
+
+    pub fn data_len(&self) -> usize {
+        Self::space(&self.instruction)
+    }
+}
+
+impl BorshSerialize for Proposal {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.governance.serialize(out);
+        self.proposer.serialize(out);
+        self.index.serialize(out);
+        self.instruction.serialize(out);
+        self.snapshot_slot.serialize(out);
+        self.voting_end_slot.serialize(out);
+        self.yes_votes.serialize(out);
+        self.no_votes.serialize(out);
+        self.state.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Proposal {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            governance: Pubkey::deserialize(buf)?,
+            proposer: Pubkey::deserialize(buf)?,
+            index: u64::deserialize(buf)?,
+            instruction: Instruction::deserialize(buf)?,
+            snapshot_slot: u64::deserialize(buf)?,
+            voting_end_slot: u64::deserialize(buf)?,
+            yes_votes: u64::deserialize(buf)?,
+            no_votes: u64::deserialize(buf)?,
+            state: ProposalState::deserialize(buf)?,
+        })
+    }
+}
+
+// 每个Token账户对每个提案只能投一次票，投过票的记录账户已经初始化，再次投票会失败
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoteRecord {
+    pub proposal: Pubkey,
+    pub token_account: Pubkey, // 提供投票权重的Token账户
+    pub weight: u64,
+    pub approve: bool,
+}
+
+impl VoteRecord {
+    // 两个Pubkey(各32) + weight(8) + approve(1)
+    pub const LEN: usize = 32 * 2 + 8 + 1;
+}
+
+impl BorshSerialize for VoteRecord {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.proposal.serialize(out);
+        self.token_account.serialize(out);
+        self.weight.serialize(out);
+        self.approve.serialize(out);
+    }
+}
+
+impl BorshDeserialize for VoteRecord {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            proposal: Pubkey::deserialize(buf)?,
+            token_account: Pubkey::deserialize(buf)?,
+            weight: u64::deserialize(buf)?,
+            approve: bool::deserialize(buf)?,
+        })
+    }
+}
+
+// 治理账户地址：种子为["governance", mint]，每个治理代币一个
+pub fn governance_address(mint: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[GOVERNANCE_SEED, mint.as_ref()], program_id)
+}
+
+// 提案地址：种子为["proposal", governance, 提案编号]
+pub fn proposal_address(governance: &Pubkey, index: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[PROPOSAL_SEED, governance.as_ref(), &index.to_le_bytes()], program_id)
+}
+
+// 投票记录地址：种子为["vote", proposal, token_account]
+pub fn vote_record_address(proposal: &Pubkey, token_account: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[VOTE_SEED, proposal.as_ref(), token_account.as_ref()], program_id)
+}
+
+// 治理程序的入口函数，注册到Programs后由运行时调用
+pub fn process(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+    let program_id = instruction.program_id;
+    let metas = &instruction.accounts;
+    match &instruction.data {
+        ProgramInstruction::CreateGovernance { quorum, threshold_basis_points, voting_slots } => {
+            create_governance(ctx, &program_id, metas, *quorum, *threshold_basis_points, *voting_slots)
+        }
+        ProgramInstruction::CreateProposal { instruction } => create_proposal(ctx, &program_id, metas, instruction),
+        ProgramInstruction::CastVote { approve } => cast_vote(ctx, &program_id, metas, *approve),
+        ProgramInstruction::FinalizeProposal => finalize(ctx, &program_id, metas),
+        _ => Err(TransactionResult::InvalidInstructionData),
+    }
+}
+
+// accounts: [治理账户(可写，空的PDA账户), 创建者(签名), 治理代币的mint]
+fn create_governance(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    quorum: u64,
+    threshold_basis_points: u16,
+    voting_slots: u64,
+) -> Result<(), TransactionResult> {
+    let [governance_meta, creator, mint, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !creator.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if !matches!(ctx.staged().get_account(&mint.pubkey).map(|account| &account.data), Some(AccountPayload::Mint(_))) {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let (governance_key, bump) = governance_address(&mint.pubkey, program_id);
+    let governance = Governance::new(mint.pubkey, quorum, threshold_basis_points, voting_slots, bump)
+        .map_err(|_| TransactionResult::InvalidInstructionData)?;
+    if governance_meta.pubkey != governance_key {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    initialize(ctx, program_id, governance_meta, AccountPayload::Governance(governance), Governance::LEN)
+}
+
+// accounts: [提案账户(可写，空的PDA账户), 提案人(签名), 治理账户(可写)]
+fn create_proposal(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    instruction: &Instruction,
+) -> Result<(), TransactionResult> {
+    let [proposal_meta, proposer, governance_meta, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !proposer.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let mut governance_account = ctx.staged().get_account(&governance_meta.pubkey).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    let AccountPayload::Governance(governance) = &mut governance_account.data else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if governance_account.owner != *program_id || !governance_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let index = governance.proposal_count;
+    if proposal_meta.pubkey != proposal_address(&governance_meta.pubkey, index, program_id).0 {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let slot = ctx.slot();
+    let proposal = Proposal {
+        governance: governance_meta.pubkey,
+        proposer: proposer.pubkey,
+        index,
+        instruction: instruction.clone(),
+        snapshot_slot: slot,
+        voting_end_slot: slot.checked_add(governance.voting_slots).ok_or(TransactionResult::ArithmeticOverflow)?,
+        yes_votes: 0,
+        no_votes: 0,
+        state: ProposalState::Voting,
+    };
+    governance.proposal_count += 1;
+    let space = proposal.data_len();
+    initialize(ctx, program_id, proposal_meta, AccountPayload::Proposal(proposal), space)?;
+    Ok(ctx.staged().set_account(governance_meta.pubkey, governance_account)?)
+}
+
+// accounts: [投票记录(可写，空的PDA账户), 投票人(签名), 提案账户(可写), 投票人的Token账户]
+fn cast_vote(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta], approve: bool) -> Result<(), TransactionResult> {
+    let [vote_meta, voter, proposal_meta, token_account, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !voter.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let mut proposal_account = ctx.staged().get_account(&proposal_meta.pubkey).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    let AccountPayload::Proposal(proposal) = &mut proposal_account.data else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if proposal_account.owner != *program_id || !proposal_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if proposal.state != ProposalState::Voting || ctx.slot() >= proposal.voting_end_slot {
+        return Err(TransactionResult::VotingClosed);
+    }
+    if vote_meta.pubkey != vote_record_address(&proposal_meta.pubkey, &token_account.pubkey, program_id).0 {
+        return Err(TransactionResult::InvalidAccount);
+    }
+
+    // 权重取快照时这个Token账户的余额：必须是由Token程序管理、属于投票人的治理代币账户
+    let governance = load_governance(ctx, program_id, &proposal.governance)?;
+    let token_program = ctx.staged().get_account(&governance.mint).map(|account| account.owner).ok_or(TransactionResult::InvalidAccount)?;
+    let weight = match ctx.staged().account_at_slot(&token_account.pubkey, proposal.snapshot_slot) {
+        Some(account) if account.owner == token_program => match &account.data {
+            AccountPayload::Token(token) if token.mint == governance.mint && token.owner == voter.pubkey => token.amount,
+            _ => 0,
+        },
+        _ => 0,
+    };
+    if weight == 0 {
+        return Err(TransactionResult::NoVotingPower);
+    }
+
+    let tally = if approve { &mut proposal.yes_votes } else { &mut proposal.no_votes };
+    *tally = tally.checked_add(weight).ok_or(TransactionResult::ArithmeticOverflow)?;
+    let record = VoteRecord { proposal: proposal_meta.pubkey, token_account: token_account.pubkey, weight, approve };
+    initialize(ctx, program_id, vote_meta, AccountPayload::VoteRecord(record), VoteRecord::LEN)?;
+    Ok(ctx.staged().set_account(proposal_meta.pubkey, proposal_account)?)
+}
+
+// accounts: [提案账户(可写), 治理账户, 提案指令用到的所有账户...]
+// 提案指令执行失败时整笔交易回滚，提案保持投票状态，之后可以再次结算
+fn finalize(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let [proposal_meta, governance_meta, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    let mut proposal_account = ctx.staged().get_account(&proposal_meta.pubkey).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    let AccountPayload::Proposal(proposal) = &mut proposal_account.data else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if proposal_account.owner != *program_id || proposal.governance != governance_meta.pubkey || proposal.state != ProposalState::Voting {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if ctx.slot() < proposal.voting_end_slot {
+        return Err(TransactionResult::VotingInProgress);
+    }
+    let governance = load_governance(ctx, program_id, &governance_meta.pubkey)?;
+    proposal.state = governance.evaluate(proposal.yes_votes, proposal.no_votes);
+    let (state, instruction) = (proposal.state, proposal.instruction.clone());
+    // 先写入结算结果，提案指令即使再调用回治理程序也不能重复执行
+    ctx.staged().set_account(proposal_meta.pubkey, proposal_account)?;
+    if state != ProposalState::Executed {
+        return Ok(());
+    }
+    let bump = [governance.bump];
+    let seeds: &[&[u8]] = &[GOVERNANCE_SEED, governance.mint.as_ref(), &bump];
+    ctx.invoke_signed(&instruction, metas, &[seeds])
+}
+
+// 把本程序拥有的空账户初始化成data，账户余额必须够space字节的免租金额
+fn initialize(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    meta: &AccountMeta,
+    data: AccountPayload,
+    space: usize,
+) -> Result<(), TransactionResult> {
+    let mut account = ctx.staged().get_account(&meta.pubkey).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    if !meta.is_writable || account.owner != *program_id || account.data != AccountPayload::Empty {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if account.lamports < ctx.staged().rent().minimum_balance(space) {
+        return Err(TransactionResult::InsufficientFunds);
+    }
+    account.data = data;
+    Ok(ctx.staged().set_account(meta.pubkey, account)?)
+}
+
+// 读取本程序拥有的治理账户
+fn load_governance(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, key: &Pubkey) -> Result<Governance, TransactionResult> {
+    match ctx.staged().get_account(key) {
+        Some(account) if account.owner == *program_id => match &account.data {
+            AccountPayload::Governance(governance) => Ok(governance.clone()),
+            _ => Err(TransactionResult::InvalidAccount),
+        },
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpi::{Entrypoint, Programs};
+    use crate::ledger::{Account, Ledger};
+    use crate::mint::Mint;
+    use crate::signature::{Keypair, Signer};
+    use crate::test_utils::{create_mint, create_token_account, funded_keypair, sign_and_execute, token_amount};
+    use crate::transaction::TransactionError;
+    use crate::SYSTEM_PROGRAM_ID;
+
+    struct Fixture {
+        ledger: Ledger,
+        programs: Programs,
+        governance_program: Pubkey,
+        token_program: Pubkey,
+        mint: Pubkey,
+        alice: Keypair, // 600票
+        bob: Keypair,   // 400票
+        alice_token: Pubkey,
+        bob_token: Pubkey,
+        treasury: Pubkey, // 国库，authority是治理PDA
+        recipient: Pubkey,
+        governance: Pubkey,
+    }
+
+    fn fixture() -> Fixture {
+        let mut ledger = Ledger::default();
+        let (governance_program, token_program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (alice, bob) = (funded_keypair(&mut ledger), funded_keypair(&mut ledger));
+        let mint = create_mint(&mut ledger, token_program, Mint::new(0, Pubkey::new_unique()));
+        let (governance, _) = governance_address(&mint, &governance_program);
+        let mut token = |owner, amount| create_token_account(&mut ledger, token_program, Pubkey::new_unique(), mint, owner, amount);
+        let alice_token = token(alice.pubkey(), 600);
+        let bob_token = token(bob.pubkey(), 400);
+        let treasury = token(governance, 1_000);
+        let recipient = token(bob.pubkey(), 0);
+        let lamports = ledger.rent().minimum_balance(Governance::LEN);
+        ledger.create_account(governance, Account::new(lamports, governance_program, AccountPayload::Empty)).unwrap();
+
+        let programs = Programs::from([(governance_program, process as Entrypoint)]);
+        let mut f = Fixture {
+            ledger,
+            programs,
+            governance_program,
+            token_program,
+            mint,
+            alice,
+            bob,
+            alice_token,
+            bob_token,
+            treasury,
+            recipient,
+            governance,
+        };
+        // quorum 500票，赞成票至少60%，投票期100个slot
+        let data = ProgramInstruction::CreateGovernance { quorum: 500, threshold_basis_points: 6_000, voting_slots: 100 };
+        let accounts = vec![
+            AccountMeta::new(f.governance, f.governance_program, false),
+            AccountMeta::new_readonly(f.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+            AccountMeta::new_readonly(f.mint, f.token_program, false),
+        ];
+        let instruction = Instruction::new(f.governance_program, data, accounts);
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.alice, vec![instruction]), Ok(()));
+        // 余额在之后的slot才算进快照
+        f.ledger.tick(1);
+        f
+    }
+
+    impl Fixture {
+        // 提案：从国库转amount个代币给recipient
+        fn payout(&self, amount: u64) -> Instruction {
+            Instruction::new(
+                self.token_program,
+                ProgramInstruction::Transfer { amount },
+                vec![
+                    AccountMeta::new(self.treasury, self.token_program, false),
+                    AccountMeta::new(self.recipient, self.token_program, false),
+                    AccountMeta::new_readonly(self.governance, self.governance_program, true),
+                ],
+            )
+        }
+
+        fn propose(&mut self, instruction: Instruction) -> Result<Pubkey, TransactionError> {
+            let index = match &self.ledger.get_account(&self.governance).unwrap().data {
+                AccountPayload::Governance(governance) => governance.proposal_count,
+                _ => panic!("不是治理账户"),
+            };
+            let (proposal, _) = proposal_address(&self.governance, index, &self.governance_program);
+            let lamports = self.ledger.rent().minimum_balance(Proposal::space(&instruction));
+            self.ledger.create_account(proposal, Account::new(lamports, self.governance_program, AccountPayload::Empty)).unwrap();
+            let accounts = vec![
+                AccountMeta::new(proposal, self.governance_program, false),
+                AccountMeta::new_readonly(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.governance, self.governance_program, false),
+            ];
+            let instruction = Instruction::new(self.governance_program, ProgramInstruction::CreateProposal { instruction: Box::new(instruction) }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.alice, vec![instruction]).map(|_| proposal)
+        }
+
+        fn vote_instruction(&self, voter: Pubkey, proposal: Pubkey, token_account: Pubkey, approve: bool) -> Instruction {
+            let (record, _) = vote_record_address(&proposal, &token_account, &self.governance_program);
+            let accounts = vec![
+                AccountMeta::new(record, self.governance_program, false),
+                AccountMeta::new_readonly(voter, SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(proposal, self.governance_program, false),
+                AccountMeta::new_readonly(token_account, self.token_program, false),
+            ];
+            Instruction::new(self.governance_program, ProgramInstruction::CastVote { approve }, accounts)
+        }
+
+        // 投票记录账户由投票人预先创建，已经存在时（重复投票）跳过
+        fn create_vote_record(&mut self, proposal: Pubkey, token_account: Pubkey) {
+            let (record, _) = vote_record_address(&proposal, &token_account, &self.governance_program);
+            if self.ledger.get_account(&record).is_none() {
+                let lamports = self.ledger.rent().minimum_balance(VoteRecord::LEN);
+                self.ledger.create_account(record, Account::new(lamports, self.governance_program, AccountPayload::Empty)).unwrap();
+            }
+        }
+
+        // 由Token账户的主人投票
+        fn vote(&mut self, proposal: Pubkey, token_account: Pubkey, approve: bool) -> Result<(), TransactionError> {
+            self.create_vote_record(proposal, token_account);
+            let voter = if token_account == self.alice_token { &self.alice } else { &self.bob };
+            let instruction = self.vote_instruction(voter.pubkey(), proposal, token_account, approve);
+            sign_and_execute(&mut self.ledger, &self.programs, voter, vec![instruction])
+        }
+
+        // 任何人都可以结算，这里由bob付手续费
+        fn finalize(&mut self, proposal: Pubkey) -> Result<(), TransactionError> {
+            let mut accounts = vec![
+                AccountMeta::new(proposal, self.governance_program, false),
+                AccountMeta::new_readonly(self.governance, self.governance_program, false),
+                AccountMeta::new_readonly(self.bob.pubkey(), SYSTEM_PROGRAM_ID, true),
+            ];
+            accounts.extend(self.payout(0).accounts.into_iter().map(|meta| AccountMeta { is_signer: false, ..meta }));
+            let instruction = Instruction::new(self.governance_program, ProgramInstruction::FinalizeProposal, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.bob, vec![instruction])
+        }
+
+        fn proposal(&self, key: &Pubkey) -> Proposal {
+            match &self.ledger.get_account(key).unwrap().data {
+                AccountPayload::Proposal(proposal) => proposal.clone(),
+                _ => panic!("不是提案账户"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let governance = Governance::new(Pubkey::new_unique(), 500, 6_000, 100, 255).unwrap();
+        assert_eq!(governance.evaluate(600, 400), ProposalState::Executed);
+        assert_eq!(governance.evaluate(599, 401), ProposalState::Defeated);
+        // 没有达到quorum
+        assert_eq!(governance.evaluate(499, 0), ProposalState::Defeated);
+        let no_quorum = Governance::new(Pubkey::new_unique(), 0, 1, 100, 255).unwrap();
+        assert_eq!(no_quorum.evaluate(0, 0), ProposalState::Defeated);
+        assert_eq!(no_quorum.evaluate(u64::MAX, u64::MAX), ProposalState::Executed);
+
+        assert_eq!(Governance::new(Pubkey::new_unique(), 0, 0, 100, 255), Err(GovernanceError::InvalidThreshold(0)));
+        assert_eq!(Governance::new(Pubkey::new_unique(), 0, 10_001, 100, 255), Err(GovernanceError::InvalidThreshold(10_001)));
+        assert_eq!(Governance::new(Pubkey::new_unique(), 0, 5_000, 0, 255), Err(GovernanceError::ZeroVotingPeriod));
+    }
+
+    #[test]
+    fn test_passed_proposal_executes_instruction() {
+        let mut f = fixture();
+        let proposal = f.propose(f.payout(300)).unwrap();
+        assert_eq!(f.vote(proposal, f.alice_token, true), Ok(()));
+        assert_eq!(f.vote(proposal, f.bob_token, false), Ok(()));
+        // 同一个Token账户不能再投一次
+        assert_eq!(f.vote(proposal, f.alice_token, true), Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount)));
+        assert_eq!((f.proposal(&proposal).yes_votes, f.proposal(&proposal).no_votes), (600, 400));
+
+        assert_eq!(f.finalize(proposal), Err(TransactionError::InstructionError(0, TransactionResult::VotingInProgress)));
+        f.ledger.tick(100);
+        assert_eq!(f.vote(proposal, f.bob_token, true), Err(TransactionError::InstructionError(0, TransactionResult::VotingClosed)));
+        assert_eq!(f.finalize(proposal), Ok(()));
+        assert_eq!(f.proposal(&proposal).state, ProposalState::Executed);
+        assert_eq!((token_amount(&f.ledger, &f.treasury), token_amount(&f.ledger, &f.recipient)), (700, 300));
+        // 不能重复执行
+        assert_eq!(f.finalize(proposal), Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount)));
+        assert_eq!(token_amount(&f.ledger, &f.recipient), 300);
+    }
+
+    #[test]
+    fn test_defeated_proposal_does_not_execute() {
+        let mut f = fixture();
+        let proposal = f.propose(f.payout(300)).unwrap();
+        // bob一个人赞成，400票没有达到quorum
+        assert_eq!(f.vote(proposal, f.bob_token, true), Ok(()));
+        f.ledger.tick(100);
+        assert_eq!(f.finalize(proposal), Ok(()));
+        assert_eq!(f.proposal(&proposal).state, ProposalState::Defeated);
+        assert_eq!(token_amount(&f.ledger, &f.recipient), 0);
+    }
+
+    #[test]
+    fn test_vote_weight_uses_snapshot() {
+        let mut f = fixture();
+        let proposal = f.propose(f.payout(300)).unwrap();
+        // 提案创建之后alice把全部代币转给bob，票数仍按快照计算
+        f.ledger.tick(1);
+        let transfer = Instruction::new(
+            f.token_program,
+            ProgramInstruction::Transfer { amount: 600 },
+            vec![
+                AccountMeta::new(f.alice_token, f.token_program, false),
+                AccountMeta::new(f.bob_token, f.token_program, false),
+                AccountMeta::new_readonly(f.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+            ],
+        );
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.alice, vec![transfer]), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.bob_token), 1_000);
+
+        // 不能用别人的Token账户投票
+        f.create_vote_record(proposal, f.alice_token);
+        let instruction = f.vote_instruction(f.bob.pubkey(), proposal, f.alice_token, true);
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.bob, vec![instruction]), Err(TransactionError::InstructionError(0, TransactionResult::NoVotingPower)));
+
+        assert_eq!(f.vote(proposal, f.bob_token, true), Ok(()));
+        assert_eq!(f.vote(proposal, f.alice_token, false), Ok(()));
+        assert_eq!((f.proposal(&proposal).yes_votes, f.proposal(&proposal).no_votes), (400, 600));
+        // 快照之前余额为0的账户没有投票权
+        assert_eq!(f.vote(proposal, f.recipient, true), Err(TransactionError::InstructionError(0, TransactionResult::NoVotingPower)));
+    }
+
+    #[test]
+    fn test_proposal_borsh_roundtrip_fits_space() {
+        let instruction = Instruction::new(
+            Pubkey::new_unique(),
+            ProgramInstruction::CreateMetadata { name: "Gov".into(), symbol: "G".into(), uri: String::new() },
+            vec![AccountMeta::new(Pubkey::new_unique(), Pubkey::new_unique(), true)],
+        );
+        let proposal = Proposal {
+            governance: Pubkey::new_unique(),
+            proposer: Pubkey::new_unique(),
+            index: 3,
+            instruction,
+            snapshot_slot: 10,
+            voting_end_slot: 110,
+            yes_votes: 1,
+            no_votes: 2,
+            state: ProposalState::Defeated,
+        };
+        let mut bytes = Vec::new();
+        proposal.serialize(&mut bytes);
+        assert_eq!(bytes.len(), proposal.data_len());
+        assert_eq!(Proposal::try_from_slice(&bytes), Ok(proposal));
+    }
+}
@@ -0,0 +1,234 @@
+// 链式构建指令 - 手写ProgramInstruction再按顺序拼AccountMeta很容易把账户顺序或权限写错，
+// builder只让调用方说明"谁转给谁、谁签名"，账户列表的顺序和可写/签名标记由builder统一生成
+
+use std::fmt;
+
+use crate::transaction::Instruction;
+use crate::{AccountMeta, ProgramInstruction, Pubkey, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstructionBuilderError {
+    MissingAmount,                // 没有设置数量
+    MissingAccount(&'static str), // 缺少某个账户，参数是账户的角色
+}
+
+impl fmt::Display for InstructionBuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstructionBuilderError::MissingAmount => write!(f, "没有设置数量"),
+            InstructionBuilderError::MissingAccount(role) => write!(f, "缺少账户: {}", role),
+        }
+    }
+}
+
+impl std::error::Error for InstructionBuilderError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Transfer,
+    MintTo,
+    Burn,
+}
+
+#[derive(Debug, Clone)]
+pub struct InstructionBuilder {
+    kind: Kind,
+    program_id: Pubkey,
+    amount: Option<u64>,
+    from: Option<Pubkey>,
+    to: Option<Pubkey>,
+    mint: Option<Pubkey>,
+    signer: Option<Pubkey>,
+    fee_vault: Option<Pubkey>,
+}
+
+impl InstructionBuilder {
+    fn new(kind: Kind) -> Self {
+        Self {
+            kind,
+            program_id: TOKEN_PROGRAM_ID,
+            amount: None,
+            from: None,
+            to: None,
+            mint: None,
+            signer: None,
+            fee_vault: None,
+        }
+    }
+
+    // 账户顺序：[from(可写), to(可写), signer(只读签名者)]
+    pub fn transfer() -> Self {
+        Self::new(Kind::Transfer)
+    }
+
+    // 账户顺序：[to(可写), mint(可写), signer(只读签名者，mint authority)]
+    pub fn mint_to() -> Self {
+        Self::new(Kind::MintTo)
+    }
+
+    // 账户顺序：[from(可写), mint(可写), signer(只读签名者，owner或delegate)]
+    pub fn burn() -> Self {
+        Self::new(Kind::Burn)
+    }
+
+    // 默认发给Token Program
+    pub fn program(mut self, program_id: Pubkey) -> Self {
+        self.program_id = program_id;
+        self
+    }
+
+    pub fn amount(mut self, amount: u64) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    pub fn from(mut self, from: Pubkey) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    pub fn to(mut self, to: Pubkey) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    pub fn mint(mut self, mint: Pubkey) -> Self {
+        self.mint = Some(mint);
+        self
+    }
+
+    pub fn signer(mut self, signer: Pubkey) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    // mint开启了转账手续费时，转账要带上可写的fee vault，放在signer之后
+    pub fn fee_vault(mut self, fee_vault: Pubkey) -> Self {
+        self.fee_vault = Some(fee_vault);
+        self
+    }
+
+    // Token账户归program所有，签名者是普通钱包；执行时运行时会按账本重新读取owner
+    pub fn build(self) -> Result<Instruction, InstructionBuilderError> {
+        let amount = self.amount.ok_or(InstructionBuilderError::MissingAmount)?;
+        let signer = self.signer.ok_or(InstructionBuilderError::MissingAccount("signer"))?;
+        let from = || self.from.ok_or(InstructionBuilderError::MissingAccount("from"));
+        let to = || self.to.ok_or(InstructionBuilderError::MissingAccount("to"));
+        let mint = || self.mint.ok_or(InstructionBuilderError::MissingAccount("mint"));
+
+        let (data, mut accounts) = match self.kind {
+            Kind::Transfer => {
+                let (from, to) = (from()?, to()?);
+                let accounts = vec![
+                    AccountMeta::new(from, self.program_id, false),
+                    AccountMeta::new(to, self.program_id, false),
+                ];
+                (ProgramInstruction::Transfer { amount }, accounts)
+            }
+            Kind::MintTo => {
+                let (to, mint) = (to()?, mint()?);
+                let accounts = vec![
+                    AccountMeta::new(to, self.program_id, false),
+                    AccountMeta::new(mint, self.program_id, false),
+                ];
+                (ProgramInstruction::Mint { amount }, accounts)
+            }
+            Kind::Burn => {
+                let (from, mint) = (from()?, mint()?);
+                let accounts = vec![
+                    AccountMeta::new(from, self.program_id, false),
+                    AccountMeta::new(mint, self.program_id, false),
+                ];
+                (ProgramInstruction::Burn { amount }, accounts)
+            }
+        };
+        accounts.push(AccountMeta::new_readonly(signer, SYSTEM_PROGRAM_ID, true));
+        if let (Kind::Transfer, Some(fee_vault)) = (self.kind, self.fee_vault) {
+            accounts.push(AccountMeta::new(fee_vault, self.program_id, false));
+        }
+        Ok(Instruction::new(self.program_id, data, accounts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_accounts_in_order() {
+        let (a, b, auth) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let instruction = InstructionBuilder::transfer().amount(100).from(a).to(b).signer(auth).build().unwrap();
+
+        assert_eq!(instruction.program_id, TOKEN_PROGRAM_ID);
+        assert!(matches!(instruction.data, ProgramInstruction::Transfer { amount: 100 }));
+        assert_eq!(
+            instruction.accounts,
+            [
+                AccountMeta::new(a, TOKEN_PROGRAM_ID, false),
+                AccountMeta::new(b, TOKEN_PROGRAM_ID, false),
+                AccountMeta::new_readonly(auth, SYSTEM_PROGRAM_ID, true),
+            ]
+        );
+        assert_eq!(instruction.signer_keys(), [auth]);
+
+        let vault = Pubkey::new_unique();
+        let with_fee = InstructionBuilder::transfer().amount(1).from(a).to(b).signer(auth).fee_vault(vault).build().unwrap();
+        assert_eq!(with_fee.accounts[3], AccountMeta::new(vault, TOKEN_PROGRAM_ID, false));
+    }
+
+    #[test]
+    fn test_mint_to_with_custom_program() {
+        let program = Pubkey::new_unique();
+        let (to, mint, auth) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let instruction =
+            InstructionBuilder::mint_to().program(program).to(to).mint(mint).signer(auth).amount(5).build().unwrap();
+
+        assert_eq!(instruction.program_id, program);
+        assert!(matches!(instruction.data, ProgramInstruction::Mint { amount: 5 }));
+        assert_eq!(
+            instruction.accounts,
+            [
+                AccountMeta::new(to, program, false),
+                AccountMeta::new(mint, program, false),
+                AccountMeta::new_readonly(auth, SYSTEM_PROGRAM_ID, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_burn_accounts_in_order() {
+        let (from, mint, auth) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let instruction = InstructionBuilder::burn().from(from).mint(mint).signer(auth).amount(3).build().unwrap();
+
+        assert!(matches!(instruction.data, ProgramInstruction::Burn { amount: 3 }));
+        assert_eq!(
+            instruction.accounts,
+            [
+                AccountMeta::new(from, TOKEN_PROGRAM_ID, false),
+                AccountMeta::new(mint, TOKEN_PROGRAM_ID, false),
+                AccountMeta::new_readonly(auth, SYSTEM_PROGRAM_ID, true),
+            ]
+        );
+        // 销毁不需要to
+        assert_eq!(
+            InstructionBuilder::burn().from(from).signer(auth).amount(3).build().unwrap_err(),
+            InstructionBuilderError::MissingAccount("mint")
+        );
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        let key = Pubkey::new_unique();
+        let builder = InstructionBuilder::transfer().to(key).signer(key);
+        assert_eq!(builder.clone().build().unwrap_err(), InstructionBuilderError::MissingAmount);
+        assert_eq!(builder.amount(1).build().unwrap_err(), InstructionBuilderError::MissingAccount("from"));
+        assert_eq!(
+            InstructionBuilder::mint_to().amount(1).to(key).signer(key).build().unwrap_err(),
+            InstructionBuilderError::MissingAccount("mint")
+        );
+        assert_eq!(
+            InstructionBuilder::transfer().amount(1).from(key).to(key).build().unwrap_err(),
+            InstructionBuilderError::MissingAccount("signer")
+        );
+    }
+}
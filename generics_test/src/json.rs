@@ -0,0 +1,400 @@
+// 最小的JSON实现 - 只够把账本状态写成可以手工编辑的文本再读回来。
+// 数字保留原始文本，读取时再按u64/i64/f64解析，这样lamports等u64的值不会因为转成f64而丢失精度
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(String), // 原始文本，例如"42"、"-1"、"2.5"
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>), // 保持键的书写顺序
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonErrorKind {
+    UnexpectedEnd,        // 文本提前结束
+    UnexpectedChar(char), // 这里不应该出现该字符
+    InvalidNumber,        // 数字格式错误
+    InvalidEscape,        // 字符串中的转义序列错误
+    TrailingCharacters,   // 值结束后还有多余内容
+}
+
+// 解析失败的位置，行和列都从1开始，方便在编辑器里定位
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: JsonErrorKind,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "JSON第{}行第{}列: ", self.line, self.column)?;
+        match &self.kind {
+            JsonErrorKind::UnexpectedEnd => write!(f, "内容不完整"),
+            JsonErrorKind::UnexpectedChar(c) => write!(f, "意外的字符{:?}", c),
+            JsonErrorKind::InvalidNumber => write!(f, "数字格式错误"),
+            JsonErrorKind::InvalidEscape => write!(f, "无效的转义序列"),
+            JsonErrorKind::TrailingCharacters => write!(f, "值后面有多余的内容"),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl JsonValue {
+    pub fn parse(text: &str) -> Result<JsonValue, JsonError> {
+        let mut parser = Parser { text, pos: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos < text.len() {
+            return Err(parser.error(JsonErrorKind::TrailingCharacters));
+        }
+        Ok(value)
+    }
+
+    pub fn number(n: impl fmt::Display) -> JsonValue {
+        JsonValue::Number(n.to_string())
+    }
+
+    pub fn object<'k>(entries: impl IntoIterator<Item = (&'k str, JsonValue)>) -> JsonValue {
+        JsonValue::Object(entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(entries) => entries.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            JsonValue::Number(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            JsonValue::Number(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            JsonValue::Null => out.push_str("null"),
+            JsonValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            JsonValue::Number(text) => out.push_str(text),
+            JsonValue::String(s) => write_string(out, s),
+            JsonValue::Array(items) if items.is_empty() => out.push_str("[]"),
+            JsonValue::Object(entries) if entries.is_empty() => out.push_str("{}"),
+            JsonValue::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    push_indent(out, indent + 1);
+                    item.write(out, indent + 1);
+                }
+                out.push('\n');
+                push_indent(out, indent);
+                out.push(']');
+            }
+            JsonValue::Object(entries) => {
+                out.push('{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    push_indent(out, indent + 1);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                }
+                out.push('\n');
+                push_indent(out, indent);
+                out.push('}');
+            }
+        }
+    }
+}
+
+// 以两个空格缩进输出，便于阅读和手工修改
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out, 0);
+        f.write_str(&out)
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    out.extend(std::iter::repeat_n("  ", indent));
+}
+
+// 非ASCII字符（比如中文用户名）原样输出，只转义引号、反斜杠和控制字符
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize, // 字节偏移
+}
+
+impl Parser<'_> {
+    fn error(&self, kind: JsonErrorKind) -> JsonError {
+        let before = &self.text[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().map_or(0, |last| last.chars().count()) + 1;
+        JsonError { line, column, kind }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Result<char, JsonError> {
+        let c = self.peek().ok_or_else(|| self.error(JsonErrorKind::UnexpectedEnd))?;
+        self.pos += c.len_utf8();
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.pos += c.len_utf8();
+                Ok(())
+            }
+            Some(c) => Err(self.error(JsonErrorKind::UnexpectedChar(c))),
+            None => Err(self.error(JsonErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn value(&mut self) -> Result<JsonValue, JsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(self.error(JsonErrorKind::UnexpectedEnd)),
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(JsonValue::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some(_) => {
+                for (word, value) in [("null", JsonValue::Null), ("true", JsonValue::Bool(true)), ("false", JsonValue::Bool(false))] {
+                    if self.text[self.pos..].starts_with(word) {
+                        self.pos += word.len();
+                        return Ok(value);
+                    }
+                }
+                Err(self.error(JsonErrorKind::UnexpectedChar(self.peek().unwrap())))
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, JsonError> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(match self.peek() {
+                    Some(c) => self.error(JsonErrorKind::UnexpectedChar(c)),
+                    None => self.error(JsonErrorKind::UnexpectedEnd),
+                });
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                '}' => return Ok(JsonValue::Object(entries)),
+                c => {
+                    self.pos -= c.len_utf8();
+                    return Err(self.error(JsonErrorKind::UnexpectedChar(c)));
+                }
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, JsonError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                ']' => return Ok(JsonValue::Array(items)),
+                c => {
+                    self.pos -= c.len_utf8();
+                    return Err(self.error(JsonErrorKind::UnexpectedChar(c)));
+                }
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.pos;
+        let rest = &self.text[start..];
+        let len = rest.find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E')).unwrap_or(rest.len());
+        let text = &rest[..len];
+        if text.parse::<f64>().is_err() || text.starts_with('+') || text.trim_start_matches('-').starts_with('.') {
+            return Err(self.error(JsonErrorKind::InvalidNumber));
+        }
+        self.pos += len;
+        Ok(JsonValue::Number(text.to_string()))
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Ok(out),
+                '\\' => {
+                    let escaped = match self.next()? {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => self.unicode_escape()?,
+                        _ => return Err(self.error(JsonErrorKind::InvalidEscape)),
+                    };
+                    out.push(escaped);
+                }
+                c if (c as u32) < 0x20 => return Err(self.error(JsonErrorKind::UnexpectedChar(c))),
+                c => out.push(c),
+            }
+        }
+    }
+
+    // \uXXXX，BMP以外的字符写成一对代理项（\uD83D\uDE00）
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.text[self.pos..].starts_with("\\u") {
+                return Err(self.error(JsonErrorKind::InvalidEscape));
+            }
+            self.pos += 2;
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error(JsonErrorKind::InvalidEscape));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error(JsonErrorKind::InvalidEscape))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self.text.get(self.pos..self.pos + 4).ok_or_else(|| self.error(JsonErrorKind::InvalidEscape))?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error(JsonErrorKind::InvalidEscape))?;
+        self.pos += 4;
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_print_roundtrip() {
+        let text = r#"{"name": "张三\n\"引号\"", "lamports": 18446744073709551615, "rate": -2.5e3,
+            "tags": [true, false, null, []], "empty": {}, "emoji": "😀é"}"#;
+        let value = JsonValue::parse(text).unwrap();
+        assert_eq!(value.get("name").and_then(JsonValue::as_str), Some("张三\n\"引号\""));
+        // u64::MAX不经过f64，不会丢失精度
+        assert_eq!(value.get("lamports").and_then(JsonValue::as_u64), Some(u64::MAX));
+        assert_eq!(value.get("rate").and_then(JsonValue::as_f64), Some(-2500.0));
+        assert_eq!(value.get("emoji").and_then(JsonValue::as_str), Some("😀é"));
+        assert_eq!(value.get("tags").and_then(JsonValue::as_array).map(|tags| tags.len()), Some(4));
+        assert!(value.get("missing").is_none());
+
+        let printed = value.to_string();
+        assert!(printed.starts_with("{\n  \"name\": \"张三\\n\\\"引号\\\"\",\n"));
+        assert_eq!(JsonValue::parse(&printed), Ok(value));
+    }
+
+    #[test]
+    fn test_errors_report_line_and_column() {
+        let error = JsonValue::parse("{\n  \"a\": 1,\n  \"b\": tru\n}").unwrap_err();
+        assert_eq!(error, JsonError { line: 3, column: 8, kind: JsonErrorKind::UnexpectedChar('t') });
+        assert_eq!(error.to_string(), "JSON第3行第8列: 意外的字符't'");
+
+        assert_eq!(JsonValue::parse("[1, 2").unwrap_err().kind, JsonErrorKind::UnexpectedEnd);
+        assert_eq!(JsonValue::parse("[1 2]").unwrap_err().kind, JsonErrorKind::UnexpectedChar('2'));
+        assert_eq!(JsonValue::parse("{} x").unwrap_err().kind, JsonErrorKind::TrailingCharacters);
+        assert_eq!(JsonValue::parse("1.2.3").unwrap_err().kind, JsonErrorKind::InvalidNumber);
+        assert_eq!(JsonValue::parse(r#""\x""#).unwrap_err().kind, JsonErrorKind::InvalidEscape);
+        assert_eq!(JsonValue::parse(r#"{"a" 1}"#).unwrap_err().kind, JsonErrorKind::UnexpectedChar('1'));
+    }
+}
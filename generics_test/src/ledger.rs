@@ -0,0 +1,1686 @@
+// 模拟Solana账本 - 按Pubkey保存所有账户
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::amm::Pool;
+use crate::auction::{Auction, BidRecord};
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::audit::AuditLog;
+use crate::escrow::Escrow;
+use crate::events::{EventLog, EventRecord, TokenEvent};
+use crate::cpi::Programs;
+use crate::crowdfunding::{Campaign, Contribution};
+use crate::dex::{Market, Order};
+use crate::fees::FeeSchedule;
+use crate::governance::{Governance, Proposal, VoteRecord};
+use crate::hash::{hashv, Hash};
+use crate::json::{JsonError, JsonValue};
+use crate::lending::{LendingMarket, Obligation};
+use crate::logs::Logs;
+use crate::lookup_table::AddressLookupTable;
+use crate::metadata::TokenMetadata;
+use crate::mint::{Mint, TransferFeeConfig};
+use crate::nonce::NonceAccount;
+use crate::oracle::PriceOracle;
+use crate::pubkey::encode_base58;
+use crate::rent::Rent;
+use crate::signature::Signature;
+use crate::streaming::Stream;
+use crate::transaction::{Transaction, TransactionError};
+use crate::transfer_hook::{TransferHook, TransferHooks};
+use crate::vesting::VestingSchedule;
+use crate::{AccountState, Pubkey, TokenAccount, TransactionResult, UserAccount, SYSTEM_PROGRAM_ID};
+
+// 和Solana一样只保留最近150个blockhash，更早的blockhash签出的交易会被拒绝
+pub const MAX_RECENT_BLOCKHASHES: usize = 150;
+
+// 账户中保存的数据，类似于Solana账户的data字段（这里直接用类型表示）
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccountPayload {
+    Empty,
+    Token(TokenAccount),
+    Mint(Mint),
+    User(UserAccount),
+    Nonce(NonceAccount),
+    LookupTable(AddressLookupTable),
+    Metadata(TokenMetadata),
+    Escrow(Escrow),
+    Vesting(VestingSchedule),
+    Pool(Pool),
+    Governance(Governance),
+    Proposal(Proposal),
+    VoteRecord(VoteRecord),
+    Market(Market),
+    Order(Order),
+    PriceOracle(PriceOracle),
+    LendingMarket(LendingMarket),
+    Obligation(Obligation),
+    Campaign(Campaign),
+    Contribution(Contribution),
+    Auction(Auction),
+    Stream(Stream),
+    BidRecord(BidRecord),
+}
+
+impl AccountPayload {
+    // 账户数据占用的字节数，用于计算租金
+    pub fn data_len(&self) -> usize {
+        match self {
+            AccountPayload::Empty => 0,
+            AccountPayload::Token(_) => TokenAccount::LEN,
+            AccountPayload::Mint(_) => Mint::LEN,
+            // 字符串长度前缀(4) + 内容 + balance(8) + created_at(8)
+            AccountPayload::User(user) => 4 + user.username.len() + 8 + 8,
+            AccountPayload::Nonce(_) => NonceAccount::LEN,
+            AccountPayload::LookupTable(table) => table.data_len(),
+            AccountPayload::Metadata(_) => TokenMetadata::LEN,
+            AccountPayload::Escrow(_) => Escrow::LEN,
+            AccountPayload::Vesting(_) => VestingSchedule::LEN,
+            AccountPayload::Pool(_) => Pool::LEN,
+            AccountPayload::Governance(_) => Governance::LEN,
+            AccountPayload::Proposal(proposal) => proposal.data_len(),
+            AccountPayload::VoteRecord(_) => VoteRecord::LEN,
+            AccountPayload::Market(_) => Market::LEN,
+            AccountPayload::Order(_) => Order::LEN,
+            AccountPayload::PriceOracle(_) => PriceOracle::LEN,
+            AccountPayload::LendingMarket(_) => LendingMarket::LEN,
+            AccountPayload::Obligation(_) => Obligation::LEN,
+            AccountPayload::Campaign(_) => Campaign::LEN,
+            AccountPayload::Contribution(_) => Contribution::LEN,
+            AccountPayload::Auction(_) => Auction::LEN,
+            AccountPayload::Stream(_) => Stream::LEN,
+            AccountPayload::BidRecord(_) => BidRecord::LEN,
+        }
+    }
+}
+
+// 1字节的变体标记（按声明顺序，Empty为0）+ 对应类型的Borsh数据，用于二进制快照
+impl BorshSerialize for AccountPayload {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        match self {
+            AccountPayload::Empty => 0u8.serialize(out),
+            AccountPayload::Token(data) => {
+                1u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Mint(data) => {
+                2u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::User(data) => {
+                3u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Nonce(data) => {
+                4u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::LookupTable(data) => {
+                5u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Metadata(data) => {
+                6u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Escrow(data) => {
+                7u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Vesting(data) => {
+                8u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Pool(data) => {
+                9u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Governance(data) => {
+                10u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Proposal(data) => {
+                11u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::VoteRecord(data) => {
+                12u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Market(data) => {
+                13u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Order(data) => {
+                14u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::PriceOracle(data) => {
+                15u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::LendingMarket(data) => {
+                16u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Obligation(data) => {
+                17u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Campaign(data) => {
+                18u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Contribution(data) => {
+                19u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Auction(data) => {
+                20u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::Stream(data) => {
+                21u8.serialize(out);
+                data.serialize(out);
+            }
+            AccountPayload::BidRecord(data) => {
+                22u8.serialize(out);
+                data.serialize(out);
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for AccountPayload {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        let payload = match u8::deserialize(buf)? {
+            0 => AccountPayload::Empty,
+            1 => AccountPayload::Token(TokenAccount::deserialize(buf)?),
+            2 => AccountPayload::Mint(Mint::deserialize(buf)?),
+            3 => AccountPayload::User(UserAccount::deserialize(buf)?),
+            4 => AccountPayload::Nonce(NonceAccount::deserialize(buf)?),
+            5 => AccountPayload::LookupTable(AddressLookupTable::deserialize(buf)?),
+            6 => AccountPayload::Metadata(TokenMetadata::deserialize(buf)?),
+            7 => AccountPayload::Escrow(Escrow::deserialize(buf)?),
+            8 => AccountPayload::Vesting(VestingSchedule::deserialize(buf)?),
+            9 => AccountPayload::Pool(Pool::deserialize(buf)?),
+            10 => AccountPayload::Governance(Governance::deserialize(buf)?),
+            11 => AccountPayload::Proposal(Proposal::deserialize(buf)?),
+            12 => AccountPayload::VoteRecord(VoteRecord::deserialize(buf)?),
+            13 => AccountPayload::Market(Market::deserialize(buf)?),
+            14 => AccountPayload::Order(Order::deserialize(buf)?),
+            15 => AccountPayload::PriceOracle(PriceOracle::deserialize(buf)?),
+            16 => AccountPayload::LendingMarket(LendingMarket::deserialize(buf)?),
+            17 => AccountPayload::Obligation(Obligation::deserialize(buf)?),
+            18 => AccountPayload::Campaign(Campaign::deserialize(buf)?),
+            19 => AccountPayload::Contribution(Contribution::deserialize(buf)?),
+            20 => AccountPayload::Auction(Auction::deserialize(buf)?),
+            21 => AccountPayload::Stream(Stream::deserialize(buf)?),
+            22 => AccountPayload::BidRecord(BidRecord::deserialize(buf)?),
+            tag => return Err(BorshError::InvalidEnumTag(tag)),
+        };
+        Ok(payload)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Account {
+    pub lamports: u64,
+    pub owner: Pubkey, // 拥有该账户的程序
+    pub data: AccountPayload,
+}
+
+impl Account {
+    pub fn new(lamports: u64, owner: Pubkey, data: AccountPayload) -> Self {
+        Self { lamports, owner, data }
+    }
+}
+
+// lamports(8) + owner(32) + data
+impl BorshSerialize for Account {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.lamports.serialize(out);
+        self.owner.serialize(out);
+        self.data.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Account {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            lamports: u64::deserialize(buf)?,
+            owner: Pubkey::deserialize(buf)?,
+            data: AccountPayload::deserialize(buf)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LedgerError {
+    AccountAlreadyExists(Pubkey),
+    AccountNotFound(Pubkey),
+    InsufficientFunds { needed: u64, available: u64 },
+    ArithmeticOverflow,
+    NotRentExempt { required: u64, actual: u64 },
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedgerError::AccountAlreadyExists(key) => write!(f, "账户已存在: {}", key),
+            LedgerError::AccountNotFound(key) => write!(f, "账户不存在: {}", key),
+            LedgerError::InsufficientFunds { needed, available } => {
+                write!(f, "余额不足: 需要{} lamports，可用{} lamports", needed, available)
+            }
+            LedgerError::ArithmeticOverflow => write!(f, "lamports计算溢出"),
+            LedgerError::NotRentExempt { required, actual } => {
+                write!(f, "余额不足以免租: 需要{} lamports，实际{} lamports", required, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LedgerError {}
+
+// 程序里读写暂存区失败时用?直接返回对应的TransactionResult
+impl From<LedgerError> for TransactionResult {
+    fn from(error: LedgerError) -> Self {
+        match error {
+            LedgerError::AccountAlreadyExists(_) | LedgerError::AccountNotFound(_) => TransactionResult::InvalidAccount,
+            LedgerError::InsufficientFunds { .. } | LedgerError::NotRentExempt { .. } => TransactionResult::InsufficientFunds,
+            LedgerError::ArithmeticOverflow => TransactionResult::ArithmeticOverflow,
+        }
+    }
+}
+
+// tick过程中发生的事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum LedgerEvent {
+    RentCollected { key: Pubkey, lamports: u64 },
+    AccountEvicted { key: Pubkey, account: Box<Account> }, // lamports归零的账户被清除
+}
+
+// 账本某一时刻的完整状态，可以克隆、比较，也可以用来回滚
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    accounts: BTreeMap<Pubkey, Account>,
+}
+
+// 账户数量(u32) + 按Pubkey排序的(key(32) + Account)
+impl BorshSerialize for Snapshot {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        (self.accounts.len() as u32).serialize(out);
+        for (key, account) in &self.accounts {
+            key.serialize(out);
+            account.serialize(out);
+        }
+    }
+}
+
+impl BorshDeserialize for Snapshot {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        let len = u32::deserialize(buf)?;
+        let mut accounts = BTreeMap::new();
+        for _ in 0..len {
+            let key = Pubkey::deserialize(buf)?;
+            let account = Account::deserialize(buf)?;
+            // 直接insert会让后一个同名账户悄悄覆盖前一个，这样的数据不可能由serialize产生
+            match accounts.entry(key) {
+                Entry::Vacant(entry) => entry.insert(account),
+                Entry::Occupied(_) => return Err(BorshError::DuplicateKey(key)),
+            };
+        }
+        Ok(Self { accounts })
+    }
+}
+
+// 两个快照之间单个账户的差异；Account比较大，放在Box里让各个变体大小相近
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccountDiff {
+    Created(Pubkey, Box<Account>),
+    Deleted(Pubkey, Box<Account>),
+    Changed { key: Pubkey, before: Box<Account>, after: Box<Account> },
+}
+
+impl AccountDiff {
+    pub fn key(&self) -> Pubkey {
+        match self {
+            AccountDiff::Created(key, _) | AccountDiff::Deleted(key, _) => *key,
+            AccountDiff::Changed { key, .. } => *key,
+        }
+    }
+}
+
+impl Snapshot {
+    pub fn get_account(&self, key: &Pubkey) -> Option<&Account> {
+        self.accounts.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    // 比较self（旧状态）到later（新状态）的变化，结果按Pubkey排序
+    pub fn diff(&self, later: &Snapshot) -> Vec<AccountDiff> {
+        let mut diffs = Vec::new();
+
+        for (key, before) in &self.accounts {
+            match later.accounts.get(key) {
+                None => diffs.push(AccountDiff::Deleted(*key, Box::new(before.clone()))),
+                Some(after) if after != before => diffs.push(AccountDiff::Changed {
+                    key: *key,
+                    before: Box::new(before.clone()),
+                    after: Box::new(after.clone()),
+                }),
+                Some(_) => {}
+            }
+        }
+        for (key, after) in &later.accounts {
+            if !self.accounts.contains_key(key) {
+                diffs.push(AccountDiff::Created(*key, Box::new(after.clone())));
+            }
+        }
+
+        diffs.sort_by_key(AccountDiff::key);
+        diffs
+    }
+}
+
+// 试运行的结果：失败时diffs中仍然包含交易费的扣除
+#[derive(Debug)]
+pub struct Simulation {
+    pub result: Result<(), TransactionError>,
+    pub logs: Logs,
+    pub compute_units_consumed: u64,
+    pub diffs: Vec<AccountDiff>,
+}
+
+// 上链交易的执行记录，类似于getTransaction返回的内容；签名是fee payer的签名
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionStatus {
+    pub signature: Signature,
+    pub slot: u64,
+    pub result: Result<(), TransactionError>,
+    pub logs: Logs,
+}
+
+// Token账户的二级索引：按钱包owner和mint查找账户地址，避免扫描整个账本
+#[derive(Debug, Clone, Default)]
+struct TokenIndex {
+    by_owner: BTreeMap<Pubkey, BTreeSet<Pubkey>>,
+    by_mint: BTreeMap<Pubkey, BTreeSet<Pubkey>>,
+}
+
+impl TokenIndex {
+    fn insert(&mut self, key: Pubkey, account: &Account) {
+        if let AccountPayload::Token(token) = &account.data {
+            self.by_owner.entry(token.owner).or_default().insert(key);
+            self.by_mint.entry(token.mint).or_default().insert(key);
+        }
+    }
+
+    fn remove(&mut self, key: &Pubkey, account: &Account) {
+        if let AccountPayload::Token(token) = &account.data {
+            Self::remove_from(&mut self.by_owner, &token.owner, key);
+            Self::remove_from(&mut self.by_mint, &token.mint, key);
+        }
+    }
+
+    // 集合为空时删掉整个条目，避免索引里残留空集合
+    fn remove_from(index: &mut BTreeMap<Pubkey, BTreeSet<Pubkey>>, group: &Pubkey, key: &Pubkey) {
+        if let Some(keys) = index.get_mut(group) {
+            keys.remove(key);
+            if keys.is_empty() {
+                index.remove(group);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ledger {
+    accounts: BTreeMap<Pubkey, Account>,
+    rent: Rent,
+    audit_log: AuditLog,      // 只追加，回滚账户状态时也不会清除
+    next_instruction_id: u64, // 每次成功修改账本的操作占用一个id
+    token_index: TokenIndex,  // 每次修改账户数据时同步维护
+    slot: u64,                // 当前slot，由tick推进
+    recent_blockhashes: VecDeque<Hash>, // 最新的在最后
+    fee_schedule: FeeSchedule,
+    collected_fees: u64,      // 累计收取的交易费
+    transactions: BTreeMap<Signature, TransactionStatus>,
+    signatures_by_address: BTreeMap<Pubkey, Vec<Signature>>, // 按执行顺序，最新的在最后
+    transfer_hooks: TransferHooks, // 不属于账户状态，回滚快照时保持不变
+    event_log: EventLog,           // 只追加，和审计日志一样不随回滚清除
+}
+
+impl Default for Ledger {
+    fn default() -> Self {
+        Self::new(Rent::default())
+    }
+}
+
+impl Ledger {
+    pub fn new(rent: Rent) -> Self {
+        Self {
+            accounts: BTreeMap::new(),
+            rent,
+            audit_log: AuditLog::default(),
+            next_instruction_id: 0,
+            token_index: TokenIndex::default(),
+            slot: 0,
+            // 创世blockhash全为0
+            recent_blockhashes: VecDeque::from([Hash::default()]),
+            fee_schedule: FeeSchedule::default(),
+            collected_fees: 0,
+            transactions: BTreeMap::new(),
+            signatures_by_address: BTreeMap::new(),
+            transfer_hooks: TransferHooks::default(),
+            event_log: EventLog::default(),
+        }
+    }
+
+    pub fn with_fee_schedule(mut self, fee_schedule: FeeSchedule) -> Self {
+        self.fee_schedule = fee_schedule;
+        self
+    }
+
+    pub fn fee_schedule(&self) -> &FeeSchedule {
+        &self.fee_schedule
+    }
+
+    pub fn collected_fees(&self) -> u64 {
+        self.collected_fees
+    }
+
+    pub fn rent(&self) -> &Rent {
+        &self.rent
+    }
+
+    pub fn audit_log(&self) -> &AuditLog {
+        &self.audit_log
+    }
+
+    pub fn event_log(&self) -> &EventLog {
+        &self.event_log
+    }
+
+    // 账户在slot开始时（这个slot里的任何修改之前）的状态，由审计日志回溯；
+    // None表示那时账户还不存在。治理投票按提案创建时的快照计算权重，之后转账不影响票数
+    pub fn account_at_slot(&self, key: &Pubkey, slot: u64) -> Option<&Account> {
+        let mut history = self.audit_log.entries().iter().filter(|entry| entry.key == *key).peekable();
+        // 没有修改记录的账户一直是当前的状态
+        let mut state = match history.peek() {
+            Some(first) => first.before.as_ref(),
+            None => self.accounts.get(key),
+        };
+        for entry in history.take_while(|entry| entry.slot < slot) {
+            state = entry.after.as_ref();
+        }
+        state
+    }
+
+    // 序号>=seq的Token事件，订阅者用上次读到的序号+1继续读取
+    pub fn iter_events_since(&self, seq: u64) -> impl Iterator<Item = &EventRecord> + '_ {
+        self.event_log.iter_events_since(seq)
+    }
+
+    // 在mint上注册转账钩子，之后这个mint的每次转账都会先调用它
+    pub fn register_transfer_hook(&mut self, mint: Pubkey, hook: Arc<dyn TransferHook>) -> Result<(), LedgerError> {
+        match self.accounts.get(&mint).map(|account| &account.data) {
+            Some(AccountPayload::Mint(_)) => {
+                self.transfer_hooks.register(mint, hook);
+                Ok(())
+            }
+            _ => Err(LedgerError::AccountNotFound(mint)),
+        }
+    }
+
+    pub fn transfer_hooks(&self) -> &TransferHooks {
+        &self.transfer_hooks
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.slot
+    }
+
+    pub fn latest_blockhash(&self) -> Hash {
+        *self.recent_blockhashes.back().expect("至少有创世blockhash")
+    }
+
+    pub fn is_blockhash_valid(&self, blockhash: &Hash) -> bool {
+        self.recent_blockhashes.contains(blockhash)
+    }
+
+    fn next_instruction_id(&mut self) -> u64 {
+        let id = self.next_instruction_id;
+        self.next_instruction_id += 1;
+        id
+    }
+
+    // 创建账户：地址不能重复，且余额必须达到免租门槛
+    pub fn create_account(&mut self, key: Pubkey, account: Account) -> Result<(), LedgerError> {
+        if self.accounts.contains_key(&key) {
+            return Err(LedgerError::AccountAlreadyExists(key));
+        }
+
+        let required = self.rent.minimum_balance(account.data.data_len());
+        if account.lamports < required {
+            return Err(LedgerError::NotRentExempt {
+                required,
+                actual: account.lamports,
+            });
+        }
+
+        let instruction_id = self.next_instruction_id();
+        self.audit_log.record(self.slot, instruction_id, key, key, None, Some(account.clone()));
+        self.token_index.insert(key, &account);
+        self.accounts.insert(key, account);
+        Ok(())
+    }
+
+    pub fn get_account(&self, key: &Pubkey) -> Option<&Account> {
+        self.accounts.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    // 某个钱包拥有的所有Token账户
+    pub fn token_accounts_by_owner<'a>(&'a self, owner: &Pubkey) -> impl Iterator<Item = (&'a Pubkey, &'a Account)> + 'a {
+        self.indexed_accounts(self.token_index.by_owner.get(owner))
+    }
+
+    // 某个mint下的所有Token账户
+    pub fn token_accounts_by_mint<'a>(&'a self, mint: &Pubkey) -> impl Iterator<Item = (&'a Pubkey, &'a Account)> + 'a {
+        self.indexed_accounts(self.token_index.by_mint.get(mint))
+    }
+
+    fn indexed_accounts<'a>(&'a self, keys: Option<&'a BTreeSet<Pubkey>>) -> impl Iterator<Item = (&'a Pubkey, &'a Account)> + 'a {
+        keys.into_iter()
+            .flatten()
+            .filter_map(move |key| self.accounts.get_key_value(key))
+    }
+
+    // 转移lamports，转出后如果还有余额，必须仍然满足免租要求
+    pub fn transfer_lamports(&mut self, from: &Pubkey, to: &Pubkey, lamports: u64) -> Result<(), LedgerError> {
+        let from_account = self.accounts.get(from).ok_or(LedgerError::AccountNotFound(*from))?;
+        let to_account = self.accounts.get(to).ok_or(LedgerError::AccountNotFound(*to))?;
+        // 转给自己不改变任何余额
+        if from == to {
+            return Ok(());
+        }
+
+        let remaining = from_account
+            .lamports
+            .checked_sub(lamports)
+            .ok_or(LedgerError::InsufficientFunds {
+                needed: lamports,
+                available: from_account.lamports,
+            })?;
+        let required = self.rent.minimum_balance(from_account.data.data_len());
+        if remaining != 0 && remaining < required {
+            return Err(LedgerError::NotRentExempt { required, actual: remaining });
+        }
+        let credited = to_account
+            .lamports
+            .checked_add(lamports)
+            .ok_or(LedgerError::ArithmeticOverflow)?;
+
+        // 所有检查都通过后再修改，保证失败时账本不变
+        let instruction_id = self.next_instruction_id();
+        for (key, lamports) in [(*from, remaining), (*to, credited)] {
+            if let Some(account) = self.accounts.get_mut(&key) {
+                let before = account.clone();
+                account.lamports = lamports;
+                self.audit_log.record(self.slot, instruction_id, *from, key, Some(before), Some(account.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    // 凭空给账户增加lamports，只给水龙头使用；不存在的账户按System账户创建，同样要满足免租
+    pub(crate) fn airdrop(&mut self, to: &Pubkey, lamports: u64) -> Result<(), LedgerError> {
+        let Some(account) = self.accounts.get(to) else {
+            return self.create_account(*to, Account::new(lamports, SYSTEM_PROGRAM_ID, AccountPayload::Empty));
+        };
+        let credited = account.lamports.checked_add(lamports).ok_or(LedgerError::ArithmeticOverflow)?;
+
+        let instruction_id = self.next_instruction_id();
+        if let Some(account) = self.accounts.get_mut(to) {
+            let before = account.clone();
+            account.lamports = credited;
+            self.audit_log.record(self.slot, instruction_id, *to, *to, Some(before), Some(account.clone()));
+        }
+        Ok(())
+    }
+
+    // 推进slots个slot：向非免租账户收取这段时间的租金，并清除lamports归零的账户
+    pub fn tick(&mut self, slots: u64) -> Vec<LedgerEvent> {
+        self.slot = self.slot.saturating_add(slots);
+        // 每次tick产生一个新的blockhash，最旧的过期
+        let blockhash = hashv(&[&self.latest_blockhash(), &self.slot.to_le_bytes()]);
+        self.recent_blockhashes.push_back(blockhash);
+        if self.recent_blockhashes.len() > MAX_RECENT_BLOCKHASHES {
+            self.recent_blockhashes.pop_front();
+        }
+
+        // 先找出需要处理的账户，没有变化时不占用instruction id
+        let pending: Vec<(Pubkey, u64)> = self
+            .accounts
+            .iter()
+            .map(|(key, account)| {
+                let due = self.rent.due(account.lamports, account.data.data_len(), slots);
+                (*key, due.min(account.lamports))
+            })
+            .filter(|(key, due)| *due > 0 || self.accounts[key].lamports == 0)
+            .collect();
+        if pending.is_empty() {
+            return Vec::new();
+        }
+
+        let instruction_id = self.next_instruction_id();
+        let mut events = Vec::new();
+        let mut evicted = Vec::new();
+        for (key, due) in pending {
+            let Some(account) = self.accounts.get_mut(&key) else {
+                continue;
+            };
+            if due > 0 {
+                let before = account.clone();
+                account.lamports -= due;
+                self.audit_log.record(self.slot, instruction_id, SYSTEM_PROGRAM_ID, key, Some(before), Some(account.clone()));
+                events.push(LedgerEvent::RentCollected { key, lamports: due });
+            }
+            if account.lamports == 0 {
+                evicted.push(key);
+            }
+        }
+        // 收完租金后再统一清除余额归零的账户
+        for key in evicted {
+            let Some(account) = self.accounts.remove(&key) else {
+                continue;
+            };
+            self.token_index.remove(&key, &account);
+            self.audit_log.record(self.slot, instruction_id, SYSTEM_PROGRAM_ID, key, Some(account.clone()), None);
+            events.push(LedgerEvent::AccountEvicted { key, account: Box::new(account) });
+        }
+        events
+    }
+
+    // 从fee payer扣除交易费：扣除后余额必须为0或仍然免租。直接写入账本，交易失败也不退还
+    pub fn charge_fee(&mut self, payer: &Pubkey, fee: u64) -> Result<(), LedgerError> {
+        let account = self.accounts.get(payer).ok_or(LedgerError::AccountNotFound(*payer))?;
+        let remaining = account.lamports.checked_sub(fee).ok_or(LedgerError::InsufficientFunds {
+            needed: fee,
+            available: account.lamports,
+        })?;
+        let required = self.rent.minimum_balance(account.data.data_len());
+        if remaining != 0 && remaining < required {
+            return Err(LedgerError::NotRentExempt { required, actual: remaining });
+        }
+
+        let instruction_id = self.next_instruction_id();
+        if let Some(account) = self.accounts.get_mut(payer) {
+            let before = account.clone();
+            account.lamports = remaining;
+            self.audit_log.record(self.slot, instruction_id, *payer, *payer, Some(before), Some(account.clone()));
+        }
+        self.collected_fees = self.collected_fees.saturating_add(fee);
+        Ok(())
+    }
+
+    // 记录一笔上链的交易；执行前已经拒绝了重复的签名，这里同样只保留第一次的状态
+    pub(crate) fn record_transaction(&mut self, status: TransactionStatus, addresses: &[Pubkey]) {
+        let signature = status.signature;
+        if let Entry::Vacant(entry) = self.transactions.entry(signature) {
+            entry.insert(status);
+            for address in addresses {
+                self.signatures_by_address.entry(*address).or_default().push(signature);
+            }
+        }
+    }
+
+    pub fn get_transaction(&self, signature: &Signature) -> Option<&TransactionStatus> {
+        self.transactions.get(signature)
+    }
+
+    // 涉及address的交易签名，最新的在前，最多limit条。
+    // 分页时把上一页最后一个签名作为before传入，返回它之前（更早）的交易
+    pub fn get_signatures_for_address(&self, address: &Pubkey, before: Option<&Signature>, limit: usize) -> Vec<Signature> {
+        let Some(signatures) = self.signatures_by_address.get(address) else {
+            return Vec::new();
+        };
+        let end = match before {
+            Some(before) => match signatures.iter().position(|signature| signature == before) {
+                Some(position) => position,
+                None => return Vec::new(),
+            },
+            None => signatures.len(),
+        };
+        signatures[..end].iter().rev().take(limit).copied().collect()
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            accounts: self.accounts.clone(),
+        }
+    }
+
+    // 在账本的临时副本上试运行交易（不校验签名，和Solana的simulateTransaction默认行为一致），
+    // 返回本来会得到的结果、日志和账户变化；自身状态不受任何影响
+    pub fn simulate(&self, tx: &Transaction) -> Simulation {
+        let mut copy = self.clone();
+        let outcome = tx.execute_unverified(&mut copy, &Programs::new());
+        Simulation {
+            result: outcome.result,
+            logs: outcome.logs,
+            compute_units_consumed: outcome.compute_units_consumed,
+            diffs: self.snapshot().diff(&copy.snapshot()),
+        }
+    }
+
+    // 开始一组暂存的修改：修改先写入暂存区，commit时才一次性写回账本
+    pub fn stage(&mut self) -> StagedLedger<'_> {
+        StagedLedger {
+            ledger: self,
+            writes: BTreeMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    // 用快照整体替换当前账户状态（租金参数保持不变），回滚产生的变化记为System Program发起
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        let instruction_id = self.next_instruction_id();
+        for diff in self.snapshot().diff(&snapshot) {
+            let (key, before, after) = match diff {
+                AccountDiff::Created(key, after) => (key, None, Some(*after)),
+                AccountDiff::Deleted(key, before) => (key, Some(*before), None),
+                AccountDiff::Changed { key, before, after } => (key, Some(*before), Some(*after)),
+            };
+            // 只更新有变化的账户的索引
+            if let Some(before) = &before {
+                self.token_index.remove(&key, before);
+            }
+            if let Some(after) = &after {
+                self.token_index.insert(key, after);
+            }
+            self.audit_log.record(self.slot, instruction_id, SYSTEM_PROGRAM_ID, key, before, after);
+        }
+        self.accounts = snapshot.accounts;
+    }
+}
+
+// to_json输出的格式版本，字段有不兼容的修改时加1
+pub const LEDGER_JSON_VERSION: u64 = 1;
+
+#[derive(Debug)]
+pub enum LedgerJsonError {
+    Json(JsonError),                                         // 不是合法的JSON
+    UnsupportedVersion { found: u64, supported: u64 },       // 不认识的格式版本
+    MissingField(String),                                    // 缺少字段，值为字段路径
+    InvalidField { path: String, expected: &'static str },   // 字段的类型或取值不对
+    UnknownAccountType { path: String, kind: String },       // data.type不是已知的账户类型
+    InvalidAccountData { path: String, source: BorshError }, // borsh字段无法解析
+    DuplicateAccount(Pubkey),                                // 同一个地址出现了两次
+}
+
+impl fmt::Display for LedgerJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedgerJsonError::Json(error) => write!(f, "{}", error),
+            LedgerJsonError::UnsupportedVersion { found, supported } => {
+                write!(f, "不支持的账本格式版本v{}，当前版本为v{}", found, supported)
+            }
+            LedgerJsonError::MissingField(path) => write!(f, "缺少字段{}", path),
+            LedgerJsonError::InvalidField { path, expected } => write!(f, "字段{}应为{}", path, expected),
+            LedgerJsonError::UnknownAccountType { path, kind } => write!(f, "{}: 未知的账户类型{}", path, kind),
+            LedgerJsonError::InvalidAccountData { path, .. } => write!(f, "字段{}的账户数据无法解析", path),
+            LedgerJsonError::DuplicateAccount(key) => write!(f, "账户重复: {}", key),
+        }
+    }
+}
+
+impl std::error::Error for LedgerJsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LedgerJsonError::Json(error) => Some(error),
+            LedgerJsonError::InvalidAccountData { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<JsonError> for LedgerJsonError {
+    fn from(error: JsonError) -> Self {
+        LedgerJsonError::Json(error)
+    }
+}
+
+impl Ledger {
+    // 导出账户、租金参数、交易费、slot和最近的blockhash，可以手工修改后用from_json读回。
+    // 审计日志、事件日志、交易记录和转账hook不导出，读回的账本从导出时的状态重新开始记录
+    pub fn to_json(&self) -> String {
+        let accounts = self.accounts.iter().map(|(key, account)| {
+            JsonValue::object([
+                ("pubkey", pubkey_json(key)),
+                ("lamports", JsonValue::number(account.lamports)),
+                ("owner", pubkey_json(&account.owner)),
+                ("data", payload_json(&account.data)),
+            ])
+        });
+        JsonValue::object([
+            ("version", JsonValue::number(LEDGER_JSON_VERSION)),
+            ("slot", JsonValue::number(self.slot)),
+            (
+                "rent",
+                JsonValue::object([
+                    ("lamports_per_byte_year", JsonValue::number(self.rent.lamports_per_byte_year)),
+                    ("exemption_threshold", JsonValue::number(self.rent.exemption_threshold)),
+                    ("burn_percent", JsonValue::number(self.rent.burn_percent)),
+                ]),
+            ),
+            ("lamports_per_signature", JsonValue::number(self.fee_schedule.lamports_per_signature)),
+            ("collected_fees", JsonValue::number(self.collected_fees)),
+            ("recent_blockhashes", JsonValue::Array(self.recent_blockhashes.iter().map(|hash| JsonValue::String(encode_base58(hash))).collect())),
+            ("accounts", JsonValue::Array(accounts.collect())),
+        ])
+        .to_string()
+    }
+
+    // 读取to_json导出的账本。先检查version，以后格式升级时在这里把旧版本迁移到当前版本；
+    // 账户原样载入，不重新检查免租，也不写审计日志
+    pub fn from_json(text: &str) -> Result<Ledger, LedgerJsonError> {
+        let value = JsonValue::parse(text)?;
+        let root = Field { path: String::new(), value: &value };
+        let version = root.get("version")?.u64()?;
+        if version != LEDGER_JSON_VERSION {
+            return Err(LedgerJsonError::UnsupportedVersion { found: version, supported: LEDGER_JSON_VERSION });
+        }
+
+        let rent = root.get("rent")?;
+        let rent = Rent {
+            lamports_per_byte_year: rent.get("lamports_per_byte_year")?.u64()?,
+            exemption_threshold: rent.get("exemption_threshold")?.f64()?,
+            burn_percent: rent.get("burn_percent")?.u8()?,
+        };
+        let lamports_per_signature = root.get("lamports_per_signature")?.u64()?;
+        let mut ledger = Ledger::new(rent).with_fee_schedule(FeeSchedule { lamports_per_signature });
+        ledger.slot = root.get("slot")?.u64()?;
+        ledger.collected_fees = root.get("collected_fees")?.u64()?;
+
+        let blockhashes = root.get("recent_blockhashes")?;
+        let hashes = blockhashes.items()?;
+        if hashes.is_empty() || hashes.len() > MAX_RECENT_BLOCKHASHES {
+            return Err(blockhashes.invalid("1到150个blockhash"));
+        }
+        ledger.recent_blockhashes = hashes.iter().map(Field::hash).collect::<Result<_, _>>()?;
+
+        for item in root.get("accounts")?.items()? {
+            let key = item.get("pubkey")?.pubkey()?;
+            let account = Account::new(item.get("lamports")?.u64()?, item.get("owner")?.pubkey()?, payload_from_json(&item.get("data")?)?);
+            if ledger.accounts.contains_key(&key) {
+                return Err(LedgerJsonError::DuplicateAccount(key));
+            }
+            ledger.token_index.insert(key, &account);
+            ledger.accounts.insert(key, account);
+        }
+        Ok(ledger)
+    }
+}
+
+fn pubkey_json(key: &Pubkey) -> JsonValue {
+    JsonValue::String(key.to_string())
+}
+
+fn optional_json<T>(value: Option<T>, to_json: impl FnOnce(T) -> JsonValue) -> JsonValue {
+    value.map_or(JsonValue::Null, to_json)
+}
+
+// Token、Mint、User和Nonce按字段展开，方便手工修改；其余类型保存为Borsh字节的十六进制
+fn payload_json(payload: &AccountPayload) -> JsonValue {
+    let kind = |name: &str| ("type", JsonValue::String(name.to_string()));
+    match payload {
+        AccountPayload::Empty => JsonValue::object([kind("Empty")]),
+        AccountPayload::Token(token) => JsonValue::object([
+            kind("Token"),
+            ("mint", pubkey_json(&token.mint)),
+            ("owner", pubkey_json(&token.owner)),
+            ("amount", JsonValue::number(token.amount)),
+            ("delegate", optional_json(token.delegate.as_ref(), pubkey_json)),
+            ("delegated_amount", JsonValue::number(token.delegated_amount)),
+            ("frozen", JsonValue::Bool(token.is_frozen())),
+        ]),
+        AccountPayload::Mint(mint) => JsonValue::object([
+            kind("Mint"),
+            ("supply", JsonValue::number(mint.supply)),
+            ("max_supply", optional_json(mint.max_supply, JsonValue::number)),
+            ("decimals", JsonValue::number(mint.decimals)),
+            ("mint_authority", optional_json(mint.mint_authority.as_ref(), pubkey_json)),
+            ("freeze_authority", optional_json(mint.freeze_authority.as_ref(), pubkey_json)),
+            (
+                "transfer_fee",
+                optional_json(mint.transfer_fee.as_ref(), |fee| {
+                    JsonValue::object([
+                        ("basis_points", JsonValue::number(fee.basis_points)),
+                        ("maximum_fee", JsonValue::number(fee.maximum_fee)),
+                        ("fee_vault", pubkey_json(&fee.fee_vault)),
+                    ])
+                }),
+            ),
+        ]),
+        AccountPayload::User(user) => JsonValue::object([
+            kind("User"),
+            ("username", JsonValue::String(user.username.clone())),
+            ("balance", JsonValue::number(user.balance)),
+            ("created_at", JsonValue::number(user.created_at)),
+        ]),
+        AccountPayload::Nonce(nonce) => JsonValue::object([
+            kind("Nonce"),
+            ("authority", pubkey_json(&nonce.authority)),
+            ("nonce", JsonValue::String(encode_base58(&nonce.nonce))),
+        ]),
+        AccountPayload::LookupTable(table) => borsh_json("LookupTable", table),
+        AccountPayload::Metadata(metadata) => borsh_json("Metadata", metadata),
+        AccountPayload::Escrow(escrow) => borsh_json("Escrow", escrow),
+        AccountPayload::Vesting(vesting) => borsh_json("Vesting", vesting),
+        AccountPayload::Pool(pool) => borsh_json("Pool", pool),
+        AccountPayload::Governance(governance) => borsh_json("Governance", governance),
+        AccountPayload::Proposal(proposal) => borsh_json("Proposal", proposal),
+        AccountPayload::VoteRecord(record) => borsh_json("VoteRecord", record),
+        AccountPayload::Market(market) => borsh_json("Market", market),
+        AccountPayload::Order(order) => borsh_json("Order", order),
+        AccountPayload::PriceOracle(oracle) => borsh_json("PriceOracle", oracle),
+        AccountPayload::LendingMarket(market) => borsh_json("LendingMarket", market),
+        AccountPayload::Obligation(obligation) => borsh_json("Obligation", obligation),
+        AccountPayload::Campaign(campaign) => borsh_json("Campaign", campaign),
+        AccountPayload::Contribution(contribution) => borsh_json("Contribution", contribution),
+        AccountPayload::Auction(auction) => borsh_json("Auction", auction),
+        AccountPayload::Stream(stream) => borsh_json("Stream", stream),
+        AccountPayload::BidRecord(record) => borsh_json("BidRecord", record),
+    }
+}
+
+fn borsh_json(kind: &str, value: &impl BorshSerialize) -> JsonValue {
+    let mut bytes = Vec::new();
+    value.serialize(&mut bytes);
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    JsonValue::object([("type", JsonValue::String(kind.to_string())), ("borsh", JsonValue::String(hex))])
+}
+
+fn payload_from_json(data: &Field<'_>) -> Result<AccountPayload, LedgerJsonError> {
+    let kind = data.get("type")?.str()?;
+    let payload = match kind {
+        "Empty" => AccountPayload::Empty,
+        "Token" => {
+            let mut token = TokenAccount::new(data.get("mint")?.pubkey()?, data.get("owner")?.pubkey()?, data.get("amount")?.u64()?);
+            token.delegate = data.get("delegate")?.optional(Field::pubkey)?;
+            token.delegated_amount = data.get("delegated_amount")?.u64()?;
+            if data.get("frozen")?.bool()? {
+                token.state = AccountState::Frozen;
+            }
+            AccountPayload::Token(token)
+        }
+        "Mint" => AccountPayload::Mint(Mint {
+            supply: data.get("supply")?.u64()?,
+            max_supply: data.get("max_supply")?.optional(Field::u64)?,
+            decimals: data.get("decimals")?.u8()?,
+            mint_authority: data.get("mint_authority")?.optional(Field::pubkey)?,
+            freeze_authority: data.get("freeze_authority")?.optional(Field::pubkey)?,
+            transfer_fee: data.get("transfer_fee")?.optional(|fee| {
+                Ok(TransferFeeConfig {
+                    basis_points: fee.get("basis_points")?.u16()?,
+                    maximum_fee: fee.get("maximum_fee")?.u64()?,
+                    fee_vault: fee.get("fee_vault")?.pubkey()?,
+                })
+            })?,
+        }),
+        "User" => AccountPayload::User(UserAccount {
+            username: data.get("username")?.str()?.to_string(),
+            balance: data.get("balance")?.u64()?,
+            created_at: data.get("created_at")?.i64()?,
+        }),
+        "Nonce" => AccountPayload::Nonce(NonceAccount::new(data.get("authority")?.pubkey()?, data.get("nonce")?.hash()?)),
+        "LookupTable" => AccountPayload::LookupTable(data.get("borsh")?.borsh()?),
+        "Metadata" => AccountPayload::Metadata(data.get("borsh")?.borsh()?),
+        "Escrow" => AccountPayload::Escrow(data.get("borsh")?.borsh()?),
+        "Vesting" => AccountPayload::Vesting(data.get("borsh")?.borsh()?),
+        "Pool" => AccountPayload::Pool(data.get("borsh")?.borsh()?),
+        "Governance" => AccountPayload::Governance(data.get("borsh")?.borsh()?),
+        "Proposal" => AccountPayload::Proposal(data.get("borsh")?.borsh()?),
+        "VoteRecord" => AccountPayload::VoteRecord(data.get("borsh")?.borsh()?),
+        "Market" => AccountPayload::Market(data.get("borsh")?.borsh()?),
+        "Order" => AccountPayload::Order(data.get("borsh")?.borsh()?),
+        "PriceOracle" => AccountPayload::PriceOracle(data.get("borsh")?.borsh()?),
+        "LendingMarket" => AccountPayload::LendingMarket(data.get("borsh")?.borsh()?),
+        "Obligation" => AccountPayload::Obligation(data.get("borsh")?.borsh()?),
+        "Campaign" => AccountPayload::Campaign(data.get("borsh")?.borsh()?),
+        "Contribution" => AccountPayload::Contribution(data.get("borsh")?.borsh()?),
+        "Auction" => AccountPayload::Auction(data.get("borsh")?.borsh()?),
+        "Stream" => AccountPayload::Stream(data.get("borsh")?.borsh()?),
+        "BidRecord" => AccountPayload::BidRecord(data.get("borsh")?.borsh()?),
+        _ => return Err(LedgerJsonError::UnknownAccountType { path: data.path.clone(), kind: kind.to_string() }),
+    };
+    Ok(payload)
+}
+
+// JSON中的一个值和它的路径（例如accounts[2].data.amount），读取失败时用路径指出是哪个字段
+struct Field<'a> {
+    path: String,
+    value: &'a JsonValue,
+}
+
+impl<'a> Field<'a> {
+    fn get(&self, name: &str) -> Result<Field<'a>, LedgerJsonError> {
+        let path = if self.path.is_empty() { name.to_string() } else { format!("{}.{}", self.path, name) };
+        match self.value.get(name) {
+            Some(value) => Ok(Field { path, value }),
+            None => Err(LedgerJsonError::MissingField(path)),
+        }
+    }
+
+    fn items(&self) -> Result<Vec<Field<'a>>, LedgerJsonError> {
+        let items = self.value.as_array().ok_or_else(|| self.invalid("数组"))?;
+        Ok(items.iter().enumerate().map(|(i, value)| Field { path: format!("{}[{}]", self.path, i), value }).collect())
+    }
+
+    fn invalid(&self, expected: &'static str) -> LedgerJsonError {
+        LedgerJsonError::InvalidField { path: self.path.clone(), expected }
+    }
+
+    // null表示None
+    fn optional<T>(&self, read: impl FnOnce(&Self) -> Result<T, LedgerJsonError>) -> Result<Option<T>, LedgerJsonError> {
+        if self.value.is_null() { Ok(None) } else { read(self).map(Some) }
+    }
+
+    fn u64(&self) -> Result<u64, LedgerJsonError> {
+        self.value.as_u64().ok_or_else(|| self.invalid("非负整数"))
+    }
+
+    fn u16(&self) -> Result<u16, LedgerJsonError> {
+        self.value.as_u64().and_then(|n| u16::try_from(n).ok()).ok_or_else(|| self.invalid("0到65535的整数"))
+    }
+
+    fn u8(&self) -> Result<u8, LedgerJsonError> {
+        self.value.as_u64().and_then(|n| u8::try_from(n).ok()).ok_or_else(|| self.invalid("0到255的整数"))
+    }
+
+    fn i64(&self) -> Result<i64, LedgerJsonError> {
+        self.value.as_i64().ok_or_else(|| self.invalid("整数"))
+    }
+
+    fn f64(&self) -> Result<f64, LedgerJsonError> {
+        self.value.as_f64().ok_or_else(|| self.invalid("数字"))
+    }
+
+    fn bool(&self) -> Result<bool, LedgerJsonError> {
+        self.value.as_bool().ok_or_else(|| self.invalid("true或false"))
+    }
+
+    fn str(&self) -> Result<&'a str, LedgerJsonError> {
+        self.value.as_str().ok_or_else(|| self.invalid("字符串"))
+    }
+
+    fn pubkey(&self) -> Result<Pubkey, LedgerJsonError> {
+        self.str()?.parse().map_err(|_| self.invalid("base58公钥"))
+    }
+
+    // blockhash和公钥一样是32字节的base58字符串
+    fn hash(&self) -> Result<Hash, LedgerJsonError> {
+        self.str()?.parse::<Pubkey>().map(Pubkey::to_bytes).map_err(|_| self.invalid("base58哈希"))
+    }
+
+    fn borsh<T: BorshDeserialize>(&self) -> Result<T, LedgerJsonError> {
+        let hex = self.str()?;
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| self.invalid("十六进制字符串"))?;
+        T::try_from_slice(&bytes).map_err(|source| LedgerJsonError::InvalidAccountData { path: self.path.clone(), source })
+    }
+}
+
+// 账本上的暂存区：读取时优先返回暂存的版本，直接drop则丢弃所有修改
+#[derive(Debug)]
+pub struct StagedLedger<'a> {
+    ledger: &'a mut Ledger,
+    writes: BTreeMap<Pubkey, Option<Account>>, // None表示账户在暂存区中已被关闭
+    events: Vec<TokenEvent>,                   // 提交时才写入事件日志
+}
+
+impl StagedLedger<'_> {
+    pub fn rent(&self) -> &Rent {
+        self.ledger.rent()
+    }
+
+    pub fn transfer_hooks(&self) -> &TransferHooks {
+        self.ledger.transfer_hooks()
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.ledger.slot()
+    }
+
+    // 暂存的修改都发生在当前slot，不会影响更早slot的状态
+    pub fn account_at_slot(&self, key: &Pubkey, slot: u64) -> Option<&Account> {
+        self.ledger.account_at_slot(key, slot)
+    }
+
+    pub fn emit(&mut self, event: TokenEvent) {
+        self.events.push(event);
+    }
+
+    pub fn get_account(&self, key: &Pubkey) -> Option<&Account> {
+        match self.writes.get(key) {
+            Some(write) => write.as_ref(),
+            None => self.ledger.get_account(key),
+        }
+    }
+
+    // 只能修改已经存在的账户
+    pub fn set_account(&mut self, key: Pubkey, account: Account) -> Result<(), LedgerError> {
+        if self.get_account(&key).is_none() {
+            return Err(LedgerError::AccountNotFound(key));
+        }
+        self.writes.insert(key, Some(account));
+        Ok(())
+    }
+
+    // 关闭账户，提交后账户从账本和Token索引中删除；返回被关闭的账户
+    pub fn close_account(&mut self, key: Pubkey) -> Result<Account, LedgerError> {
+        let account = self.get_account(&key).cloned().ok_or(LedgerError::AccountNotFound(key))?;
+        self.writes.insert(key, None);
+        Ok(account)
+    }
+
+    // 把所有暂存的修改和事件写回账本，整组修改共享一个instruction id
+    pub fn commit(self, actor: Pubkey) {
+        let ledger = self.ledger;
+        let slot = ledger.slot;
+        for event in self.events {
+            ledger.event_log.push(slot, event);
+        }
+        let changed: Vec<(Pubkey, Option<Account>)> = self
+            .writes
+            .into_iter()
+            .filter(|(key, account)| ledger.accounts.get(key) != account.as_ref())
+            .collect();
+        if changed.is_empty() {
+            return;
+        }
+
+        let instruction_id = ledger.next_instruction_id();
+        for (key, account) in changed {
+            let before = match &account {
+                Some(account) => ledger.accounts.insert(key, account.clone()),
+                None => ledger.accounts.remove(&key),
+            };
+            if let Some(before) = &before {
+                ledger.token_index.remove(&key, before);
+            }
+            if let Some(account) = &account {
+                ledger.token_index.insert(key, account);
+            }
+            ledger.audit_log.record(slot, instruction_id, actor, key, before, account);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rent::SLOTS_PER_YEAR;
+
+    fn token_payload() -> AccountPayload {
+        AccountPayload::Token(TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 0))
+    }
+
+    #[test]
+    fn test_create_rent_exempt_account() {
+        let mut ledger = Ledger::default();
+        let key = Pubkey::new_unique();
+        let lamports = ledger.rent().minimum_balance(TokenAccount::LEN);
+
+        let account = Account::new(lamports, Pubkey::new_unique(), token_payload());
+        assert_eq!(ledger.create_account(key, account.clone()), Ok(()));
+        assert_eq!(ledger.get_account(&key), Some(&account));
+
+        // 同一个地址不能创建两次
+        assert_eq!(
+            ledger.create_account(key, account),
+            Err(LedgerError::AccountAlreadyExists(key))
+        );
+    }
+
+    #[test]
+    fn test_reject_account_below_rent_exemption() {
+        let mut ledger = Ledger::default();
+        let required = ledger.rent().minimum_balance(TokenAccount::LEN);
+
+        let account = Account::new(required - 1, Pubkey::new_unique(), token_payload());
+        assert_eq!(
+            ledger.create_account(Pubkey::new_unique(), account),
+            Err(LedgerError::NotRentExempt {
+                required,
+                actual: required - 1,
+            })
+        );
+        assert!(ledger.is_empty());
+    }
+
+    fn funded_ledger() -> (Ledger, Pubkey, Pubkey) {
+        let mut ledger = Ledger::default();
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let minimum = ledger.rent().minimum_balance(0);
+        for key in [alice, bob] {
+            let account = Account::new(minimum * 10, Pubkey::default(), AccountPayload::Empty);
+            ledger.create_account(key, account).unwrap();
+        }
+        (ledger, alice, bob)
+    }
+
+    #[test]
+    fn test_transfer_lamports() {
+        let (mut ledger, alice, bob) = funded_ledger();
+        let minimum = ledger.rent().minimum_balance(0);
+
+        assert_eq!(ledger.transfer_lamports(&alice, &bob, minimum), Ok(()));
+        assert_eq!(ledger.get_account(&alice).unwrap().lamports, minimum * 9);
+        assert_eq!(ledger.get_account(&bob).unwrap().lamports, minimum * 11);
+
+        // 转出后剩余余额低于免租门槛
+        assert_eq!(
+            ledger.transfer_lamports(&alice, &bob, minimum * 9 - 1),
+            Err(LedgerError::NotRentExempt { required: minimum, actual: 1 })
+        );
+        // 全部转出是允许的
+        assert_eq!(ledger.transfer_lamports(&alice, &bob, minimum * 9), Ok(()));
+        assert_eq!(
+            ledger.transfer_lamports(&alice, &bob, 1),
+            Err(LedgerError::InsufficientFunds { needed: 1, available: 0 })
+        );
+
+        let bob_balance = ledger.get_account(&bob).unwrap().lamports;
+        assert_eq!(ledger.transfer_lamports(&bob, &bob, minimum), Ok(()));
+        assert_eq!(ledger.get_account(&bob).unwrap().lamports, bob_balance);
+
+        let missing = Pubkey::new_unique();
+        assert_eq!(
+            ledger.transfer_lamports(&missing, &bob, 1),
+            Err(LedgerError::AccountNotFound(missing))
+        );
+    }
+
+    #[test]
+    fn test_snapshot_restore_and_diff() {
+        let (mut ledger, alice, bob) = funded_ledger();
+        let minimum = ledger.rent().minimum_balance(0);
+        let before = ledger.snapshot();
+
+        ledger.transfer_lamports(&alice, &bob, minimum).unwrap();
+        let carol = Pubkey::new_unique();
+        ledger
+            .create_account(carol, Account::new(minimum, Pubkey::default(), AccountPayload::Empty))
+            .unwrap();
+        let after = ledger.snapshot();
+
+        let diffs = before.diff(&after);
+        assert_eq!(diffs.len(), 3);
+        assert!(diffs.contains(&AccountDiff::Created(
+            carol,
+            Box::new(Account::new(minimum, Pubkey::default(), AccountPayload::Empty))
+        )));
+        assert!(diffs.iter().any(|diff| matches!(
+            diff,
+            AccountDiff::Changed { key, before, after }
+                if *key == alice && before.lamports == minimum * 10 && after.lamports == minimum * 9
+        )));
+        assert!(after.diff(&before).iter().any(|diff| matches!(diff, AccountDiff::Deleted(key, _) if *key == carol)));
+
+        // 回滚后与之前的快照完全一致
+        ledger.restore(before.clone());
+        assert_eq!(ledger.snapshot(), before);
+        assert!(before.diff(&ledger.snapshot()).is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_borsh_rejects_duplicate_keys() {
+        let (ledger, alice, _) = funded_ledger();
+        let snapshot = ledger.snapshot();
+        let mut bytes = Vec::new();
+        snapshot.serialize(&mut bytes);
+        assert_eq!(Snapshot::try_from_slice(&bytes), Ok(snapshot));
+
+        // 同一个key出现两次，不能让后一个账户覆盖前一个
+        let account = ledger.get_account(&alice).unwrap();
+        let mut bytes = Vec::new();
+        2u32.serialize(&mut bytes);
+        for lamports in [account.lamports, account.lamports * 2] {
+            alice.serialize(&mut bytes);
+            Account { lamports, ..account.clone() }.serialize(&mut bytes);
+        }
+        assert_eq!(Snapshot::try_from_slice(&bytes), Err(BorshError::DuplicateKey(alice)));
+    }
+
+    // 用全表扫描的结果检查索引是否一致
+    fn assert_index_consistent(ledger: &Ledger) {
+        let tokens = || {
+            ledger.accounts.iter().filter_map(|(key, account)| match &account.data {
+                AccountPayload::Token(token) => Some((*key, token.clone())),
+                _ => None,
+            })
+        };
+        for (_, token) in tokens() {
+            let by_owner: Vec<Pubkey> = ledger.token_accounts_by_owner(&token.owner).map(|(k, _)| *k).collect();
+            let scanned: Vec<Pubkey> = tokens().filter(|(_, t)| t.owner == token.owner).map(|(k, _)| k).collect();
+            assert_eq!(by_owner, scanned);
+
+            let by_mint: Vec<Pubkey> = ledger.token_accounts_by_mint(&token.mint).map(|(k, _)| *k).collect();
+            let scanned: Vec<Pubkey> = tokens().filter(|(_, t)| t.mint == token.mint).map(|(k, _)| k).collect();
+            assert_eq!(by_mint, scanned);
+        }
+        // 索引中不能有已经不存在的账户或空集合
+        for keys in ledger.token_index.by_owner.values().chain(ledger.token_index.by_mint.values()) {
+            assert!(!keys.is_empty());
+            assert!(keys.iter().all(|key| ledger.accounts.contains_key(key)));
+        }
+    }
+
+    #[test]
+    fn test_token_indexes_stay_consistent() {
+        let mut ledger = Ledger::default();
+        let lamports = ledger.rent().minimum_balance(TokenAccount::LEN);
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (usdc, sol) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let token = |owner, mint| {
+            Account::new(lamports, Pubkey::default(), AccountPayload::Token(TokenAccount::new(mint, owner, 0)))
+        };
+
+        let alice_usdc = Pubkey::new_unique();
+        ledger.create_account(alice_usdc, token(alice, usdc)).unwrap();
+        let empty = ledger.snapshot();
+        let alice_sol = Pubkey::new_unique();
+        ledger.create_account(alice_sol, token(alice, sol)).unwrap();
+        let bob_usdc = Pubkey::new_unique();
+        ledger.create_account(bob_usdc, token(bob, usdc)).unwrap();
+        ledger
+            .create_account(Pubkey::new_unique(), Account::new(lamports, Pubkey::default(), AccountPayload::Empty))
+            .unwrap();
+        assert_index_consistent(&ledger);
+
+        assert_eq!(ledger.token_accounts_by_owner(&alice).count(), 2);
+        assert_eq!(ledger.token_accounts_by_mint(&usdc).count(), 2);
+        assert_eq!(ledger.token_accounts_by_owner(&Pubkey::new_unique()).count(), 0);
+
+        // 回滚会删除之后创建的账户，索引也要同步
+        ledger.restore(empty);
+        assert_index_consistent(&ledger);
+        assert_eq!(ledger.token_accounts_by_owner(&bob).count(), 0);
+        let owned: Vec<_> = ledger.token_accounts_by_owner(&alice).map(|(key, _)| *key).collect();
+        assert_eq!(owned, vec![alice_usdc]);
+    }
+
+    #[test]
+    fn test_recent_blockhashes_expire() {
+        let mut ledger = Ledger::default();
+        let genesis = ledger.latest_blockhash();
+        assert_eq!(genesis, Hash::default());
+
+        ledger.tick(1);
+        let first = ledger.latest_blockhash();
+        assert_ne!(first, genesis);
+        assert!(ledger.is_blockhash_valid(&genesis));
+
+        for _ in 1..MAX_RECENT_BLOCKHASHES {
+            ledger.tick(1);
+        }
+        assert!(!ledger.is_blockhash_valid(&genesis));
+        assert!(ledger.is_blockhash_valid(&first));
+    }
+
+    #[test]
+    fn test_tick_collects_rent_and_evicts() {
+        let (mut ledger, alice, bob) = funded_ledger();
+        let minimum = ledger.rent().minimum_balance(0);
+
+        // 免租账户不交租金，也没有事件
+        assert!(ledger.tick(SLOTS_PER_YEAR).is_empty());
+        assert_eq!(ledger.slot(), SLOTS_PER_YEAR);
+
+        // 直接放入一个非免租账户（正常的create_account会拒绝它）
+        let yearly = ledger.rent().due(1, 0, SLOTS_PER_YEAR);
+        let poor = Pubkey::new_unique();
+        ledger
+            .accounts
+            .insert(poor, Account::new(yearly + yearly / 2, Pubkey::default(), AccountPayload::Empty));
+
+        assert_eq!(
+            ledger.tick(SLOTS_PER_YEAR),
+            vec![LedgerEvent::RentCollected { key: poor, lamports: yearly }]
+        );
+        assert_eq!(ledger.get_account(&poor).unwrap().lamports, yearly / 2);
+
+        // 余额不足以交租时扣光并清除
+        let events = ledger.tick(SLOTS_PER_YEAR);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], LedgerEvent::RentCollected { key: poor, lamports: yearly / 2 });
+        assert!(matches!(events[1], LedgerEvent::AccountEvicted { key, .. } if key == poor));
+        assert!(ledger.get_account(&poor).is_none());
+
+        // 余额被转空的账户也会被清除
+        ledger.transfer_lamports(&alice, &bob, minimum * 10).unwrap();
+        let events = ledger.tick(1);
+        assert!(matches!(events.as_slice(), [LedgerEvent::AccountEvicted { key, .. }] if *key == alice));
+        assert_eq!(ledger.len(), 1);
+        assert!(ledger.audit_log().history(&alice).last().unwrap().after.is_none());
+    }
+
+    #[test]
+    fn test_staged_writes_commit_or_discard() {
+        let (mut ledger, alice, bob) = funded_ledger();
+        let before = ledger.snapshot();
+        let mut updated = ledger.get_account(&alice).unwrap().clone();
+        updated.lamports += 1;
+
+        // drop时丢弃暂存的修改
+        {
+            let mut staged = ledger.stage();
+            staged.set_account(alice, updated.clone()).unwrap();
+            assert_eq!(staged.get_account(&alice), Some(&updated));
+        }
+        assert_eq!(ledger.snapshot(), before);
+
+        let mut staged = ledger.stage();
+        let missing = Pubkey::new_unique();
+        assert_eq!(
+            staged.set_account(missing, updated.clone()),
+            Err(LedgerError::AccountNotFound(missing))
+        );
+        staged.set_account(alice, updated.clone()).unwrap();
+        staged.commit(bob);
+        assert_eq!(ledger.get_account(&alice), Some(&updated));
+        let last = ledger.audit_log().entries().last().unwrap();
+        assert_eq!((last.key, last.actor), (alice, bob));
+    }
+
+    #[test]
+    fn test_staged_close_account() {
+        let (mut ledger, alice, bob) = funded_ledger();
+        let token = Pubkey::new_unique();
+        let lamports = ledger.rent().minimum_balance(TokenAccount::LEN);
+        let payload = token_payload();
+        let owner = match &payload {
+            AccountPayload::Token(token) => token.owner,
+            _ => unreachable!(),
+        };
+        ledger.create_account(token, Account::new(lamports, Pubkey::default(), payload)).unwrap();
+
+        let mut staged = ledger.stage();
+        assert_eq!(staged.close_account(token).map(|account| account.lamports), Ok(lamports));
+        // 关闭后在暂存区里就读不到，也不能再修改
+        assert_eq!(staged.get_account(&token), None);
+        let updated = staged.get_account(&alice).unwrap().clone();
+        assert_eq!(staged.set_account(token, updated), Err(LedgerError::AccountNotFound(token)));
+        assert_eq!(staged.close_account(token), Err(LedgerError::AccountNotFound(token)));
+        assert_eq!(TransactionResult::from(LedgerError::AccountNotFound(token)), TransactionResult::InvalidAccount);
+        assert_eq!(
+            TransactionResult::from(LedgerError::NotRentExempt { required: 2, actual: 1 }),
+            TransactionResult::InsufficientFunds
+        );
+        staged.commit(bob);
+
+        assert_eq!(ledger.get_account(&token), None);
+        assert_eq!(ledger.token_accounts_by_owner(&owner).count(), 0);
+        assert!(ledger.audit_log().entries().last().unwrap().after.is_none());
+    }
+
+    #[test]
+    fn test_charge_fee() {
+        let (mut ledger, alice, _) = funded_ledger();
+        let minimum = ledger.rent().minimum_balance(0);
+
+        assert_eq!(ledger.charge_fee(&alice, 5_000), Ok(()));
+        assert_eq!(ledger.get_account(&alice).unwrap().lamports, minimum * 10 - 5_000);
+        assert_eq!(ledger.collected_fees(), 5_000);
+
+        let balance = minimum * 10 - 5_000;
+        assert_eq!(
+            ledger.charge_fee(&alice, balance + 1),
+            Err(LedgerError::InsufficientFunds { needed: balance + 1, available: balance })
+        );
+        assert_eq!(
+            ledger.charge_fee(&alice, balance - 1),
+            Err(LedgerError::NotRentExempt { required: minimum, actual: 1 })
+        );
+        let missing = Pubkey::new_unique();
+        assert_eq!(ledger.charge_fee(&missing, 1), Err(LedgerError::AccountNotFound(missing)));
+        assert_eq!(ledger.collected_fees(), 5_000);
+    }
+
+    #[test]
+    fn test_audit_log_records_mutations() {
+        let (mut ledger, alice, bob) = funded_ledger();
+        let minimum = ledger.rent().minimum_balance(0);
+        let before = ledger.snapshot();
+        assert_eq!(ledger.audit_log().len(), 2);
+
+        ledger.transfer_lamports(&alice, &bob, minimum).unwrap();
+        // 失败的操作不留下记录
+        assert!(ledger.transfer_lamports(&alice, &bob, u64::MAX).is_err());
+
+        let transfer: Vec<_> = ledger.audit_log().entries()[2..].to_vec();
+        assert_eq!(transfer.len(), 2);
+        assert_eq!(transfer[0].instruction_id, transfer[1].instruction_id);
+        assert!(transfer.iter().all(|entry| entry.actor == alice));
+        assert_eq!(transfer[1].key, bob);
+        assert_eq!(transfer[1].before.as_ref().unwrap().lamports, minimum * 10);
+        assert_eq!(transfer[1].after.as_ref().unwrap().lamports, minimum * 11);
+
+        // 回滚也会被记录，而且不会删除已有的记录
+        ledger.restore(before);
+        let history: Vec<_> = ledger.audit_log().history(&bob).collect();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[2].actor, SYSTEM_PROGRAM_ID);
+        assert_eq!(history[2].after.as_ref().unwrap().lamports, minimum * 10);
+    }
+
+    #[test]
+    fn test_account_at_slot() {
+        let (mut ledger, alice, bob) = funded_ledger();
+        let minimum = ledger.rent().minimum_balance(0);
+        let start = ledger.slot();
+        ledger.tick(10);
+        ledger.transfer_lamports(&alice, &bob, minimum).unwrap();
+        ledger.tick(10);
+        ledger.transfer_lamports(&alice, &bob, minimum).unwrap();
+        let lamports = |ledger: &Ledger, slot| ledger.account_at_slot(&bob, slot).map(|account| account.lamports);
+
+        assert_eq!(lamports(&ledger, start + 10), Some(minimum * 10));
+        assert_eq!(lamports(&ledger, start + 11), Some(minimum * 11));
+        assert_eq!(lamports(&ledger, start + 20), Some(minimum * 11));
+        assert_eq!(lamports(&ledger, u64::MAX), Some(minimum * 12));
+        // 创建之前账户不存在
+        assert_eq!(lamports(&ledger, start), None);
+        assert_eq!(ledger.account_at_slot(&Pubkey::new_unique(), start), None);
+    }
+
+    const FIXTURE: &str = include_str!("../fixtures/ledger_v1.json");
+
+    #[test]
+    fn test_json_fixture_roundtrip() {
+        let key = |n: u8| Pubkey::new_from_array([n; 32]);
+        let ledger = Ledger::from_json(FIXTURE).unwrap();
+        assert_eq!((ledger.slot(), ledger.collected_fees(), ledger.len()), (2, 15_000, 7));
+        assert_eq!(ledger.fee_schedule().lamports_per_signature, 5_000);
+        assert_eq!(ledger.rent(), &Rent::default());
+        assert!(ledger.is_blockhash_valid(&Hash::default()));
+
+        // Token账户的索引在载入时重建
+        let owned: Vec<&Pubkey> = ledger.token_accounts_by_owner(&key(4)).map(|(key, _)| key).collect();
+        assert_eq!(owned, vec![&key(6)]);
+        let Some(AccountPayload::Token(bob)) = ledger.get_account(&key(6)).map(|account| &account.data) else { panic!() };
+        assert!(bob.is_frozen());
+        let Some(AccountPayload::Mint(mint)) = ledger.get_account(&key(2)).map(|account| &account.data) else { panic!() };
+        assert_eq!((mint.supply, mint.max_supply, mint.transfer_fee.as_ref().map(|fee| fee.basis_points)), (5_000_000, Some(1_000_000_000), Some(50)));
+        assert_eq!(ledger.get_account(&key(10)).unwrap().data, AccountPayload::PriceOracle(PriceOracle::new(key(1), 25_000)));
+
+        // 导出的文本和fixture逐字节相同
+        assert_eq!(ledger.to_json(), FIXTURE.trim_end());
+    }
+
+    #[test]
+    fn test_json_roundtrip_after_ticks() {
+        let mut ledger = Ledger::default().with_fee_schedule(FeeSchedule { lamports_per_signature: 7 });
+        let key = Pubkey::new_unique();
+        let user = AccountPayload::User(UserAccount { username: "\"bob\"\n".to_string(), balance: u64::MAX, created_at: -5 });
+        ledger.create_account(key, Account::new(u64::MAX, SYSTEM_PROGRAM_ID, user)).unwrap();
+        ledger.tick(3);
+
+        let restored = Ledger::from_json(&ledger.to_json()).unwrap();
+        assert_eq!(restored.snapshot(), ledger.snapshot());
+        assert_eq!((restored.slot(), restored.latest_blockhash()), (3, ledger.latest_blockhash()));
+        assert_eq!(restored.fee_schedule().lamports_per_signature, 7);
+    }
+
+    #[test]
+    fn test_json_hand_edits_and_errors() {
+        // 手工修改余额后读回
+        let edited = FIXTURE.replacen("\"amount\": 4000000", "\"amount\": 3000000", 1);
+        let ledger = Ledger::from_json(&edited).unwrap();
+        let amount = ledger.token_accounts_by_owner(&Pubkey::new_from_array([3; 32])).map(|(_, account)| match &account.data {
+            AccountPayload::Token(token) => token.amount,
+            _ => unreachable!(),
+        });
+        assert_eq!(amount.collect::<Vec<_>>(), vec![3_000_000]);
+
+        let load = |from: &str, to: &str| Ledger::from_json(&FIXTURE.replacen(from, to, 1)).unwrap_err();
+        assert!(matches!(
+            load("\"version\": 1", "\"version\": 2"),
+            LedgerJsonError::UnsupportedVersion { found: 2, supported: LEDGER_JSON_VERSION }
+        ));
+        assert_eq!(load("\"version\": 1,", "").to_string(), "缺少字段version");
+        assert_eq!(load("\"decimals\": 6", "\"decimals\": 600").to_string(), "字段accounts[0].data.decimals应为0到255的整数");
+        assert_eq!(load("\"frozen\": true", "\"frozen\": \"yes\"").to_string(), "字段accounts[3].data.frozen应为true或false");
+        assert_eq!(load("\"type\": \"Empty\"", "\"type\": \"Vault\"").to_string(), "accounts[1].data: 未知的账户类型Vault");
+        assert!(matches!(load("\"borsh\": \"0101", "\"borsh\": \""), LedgerJsonError::InvalidAccountData { .. }));
+        assert!(matches!(load("\"slot\": 2,", "\"slot\": 2"), LedgerJsonError::Json(_)));
+        assert!(matches!(
+            load("\"pubkey\": \"CktRuQ2mttgRGkXJtyksdKHjUdc2C4TgDzyB98oEzy8\"", "\"pubkey\": \"8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR\""),
+            LedgerJsonError::DuplicateAccount(_)
+        ));
+    }
+}
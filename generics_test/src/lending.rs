@@ -0,0 +1,730 @@
+// 借贷协议 - 类似于Solend的单一市场：用户存入抵押代币，按预言机价格和抵押率借出另一种代币。
+// 每个用户在市场里有一个债务仓位(obligation)，记录抵押数量和欠款。
+// 价格下跌使欠款超过借款上限时，任何人都可以替他还款，并以清算奖励的折扣拿走对应的抵押品
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::cpi::InvokeContext;
+use crate::ledger::AccountPayload;
+use crate::oracle::{PriceOracle, MAX_PRICE_AGE_SLOTS, ORACLE_PROGRAM_ID, PRICE_SCALE};
+use crate::pda::derive_pda;
+use crate::transaction::Instruction;
+use crate::{AccountMeta, ProgramInstruction, Pubkey, TransactionResult};
+
+pub const LENDING_SEED: &[u8] = b"lending";
+pub const OBLIGATION_SEED: &[u8] = b"obligation";
+
+const BASIS_POINTS: u128 = 10_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LendingMarket {
+    pub collateral_mint: Pubkey,
+    pub borrow_mint: Pubkey,
+    pub collateral_vault: Pubkey,   // 保存抵押品的Token账户，authority是市场PDA
+    pub liquidity_vault: Pubkey,    // 可以借出的代币，authority是市场PDA
+    pub oracle: Pubkey,             // 抵押代币以借出代币计价的价格账户
+    pub collateral_factor_bps: u16, // 抵押率：借款上限是抵押品价值的这个比例
+    pub liquidation_bonus_bps: u16, // 清算人多拿的抵押品比例
+    pub bump: u8,
+}
+
+impl LendingMarket {
+    // 五个Pubkey(各32) + 两个u16 + bump(1)
+    pub const LEN: usize = 32 * 5 + 2 * 2 + 1;
+}
+
+impl BorshSerialize for LendingMarket {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.collateral_mint.serialize(out);
+        self.borrow_mint.serialize(out);
+        self.collateral_vault.serialize(out);
+        self.liquidity_vault.serialize(out);
+        self.oracle.serialize(out);
+        self.collateral_factor_bps.serialize(out);
+        self.liquidation_bonus_bps.serialize(out);
+        self.bump.serialize(out);
+    }
+}
+
+impl BorshDeserialize for LendingMarket {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            collateral_mint: Pubkey::deserialize(buf)?,
+            borrow_mint: Pubkey::deserialize(buf)?,
+            collateral_vault: Pubkey::deserialize(buf)?,
+            liquidity_vault: Pubkey::deserialize(buf)?,
+            oracle: Pubkey::deserialize(buf)?,
+            collateral_factor_bps: u16::deserialize(buf)?,
+            liquidation_bonus_bps: u16::deserialize(buf)?,
+            bump: u8::deserialize(buf)?,
+        })
+    }
+}
+
+// 一个用户在一个市场里的仓位
+#[derive(Debug, Clone, PartialEq)]
+pub struct Obligation {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub collateral: u64, // 存入的抵押代币数量
+    pub debt: u64,       // 欠的借出代币数量
+}
+
+impl Obligation {
+    // 两个Pubkey(各32) + 两个u64
+    pub const LEN: usize = 32 * 2 + 8 * 2;
+}
+
+impl BorshSerialize for Obligation {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.market.serialize(out);
+        self.owner.serialize(out);
+        self.collateral.serialize(out);
+        self.debt.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Obligation {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            market: Pubkey::deserialize(buf)?,
+            owner: Pubkey::deserialize(buf)?,
+            collateral: u64::deserialize(buf)?,
+            debt: u64::deserialize(buf)?,
+        })
+    }
+}
+
+// 市场地址：种子为["lending", collateral_mint, borrow_mint]
+pub fn lending_market_address(collateral_mint: &Pubkey, borrow_mint: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[LENDING_SEED, collateral_mint.as_ref(), borrow_mint.as_ref()], program_id)
+}
+
+// 仓位地址：种子为["obligation", 市场, owner]，每个用户在每个市场只有一个仓位
+pub fn obligation_address(market: &Pubkey, owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[OBLIGATION_SEED, market.as_ref(), owner.as_ref()], program_id)
+}
+
+// collateral个抵押代币按price最多能借多少，向下取整
+pub fn borrow_limit(collateral: u64, price: u64, collateral_factor_bps: u16) -> u64 {
+    let value = (collateral as u128 * price as u128).checked_mul(collateral_factor_bps as u128);
+    value.map_or(u64::MAX, |value| u64::try_from(value / (PRICE_SCALE as u128 * BASIS_POINTS)).unwrap_or(u64::MAX))
+}
+
+// 替别人还repay个借出代币能拿走多少抵押品：价值加上清算奖励，按price折算，向下取整
+pub fn seize_amount(repay: u64, price: u64, liquidation_bonus_bps: u16) -> u64 {
+    if price == 0 {
+        return u64::MAX;
+    }
+    let seized = repay as u128 * (BASIS_POINTS + liquidation_bonus_bps as u128) * PRICE_SCALE as u128 / (BASIS_POINTS * price as u128);
+    u64::try_from(seized).unwrap_or(u64::MAX)
+}
+
+// 借贷程序的入口函数，注册到Programs后由运行时调用
+pub fn process(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+    let program_id = instruction.program_id;
+    let metas = &instruction.accounts;
+    match instruction.data {
+        ProgramInstruction::InitLendingMarket { collateral_factor_bps, liquidation_bonus_bps } => {
+            init_market(ctx, &program_id, metas, collateral_factor_bps, liquidation_bonus_bps)
+        }
+        ProgramInstruction::DepositCollateral { amount } => deposit_collateral(ctx, &program_id, metas, amount),
+        ProgramInstruction::Borrow { amount } => borrow(ctx, &program_id, metas, amount),
+        ProgramInstruction::Repay { amount } => repay(ctx, &program_id, metas, amount),
+        ProgramInstruction::Liquidate { amount } => liquidate(ctx, &program_id, metas, amount),
+        _ => Err(TransactionResult::InvalidInstructionData),
+    }
+}
+
+// accounts: [市场账户(可写，空的PDA账户), 创建者(签名), collateral_vault, liquidity_vault, 价格账户]
+fn init_market(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    collateral_factor_bps: u16,
+    liquidation_bonus_bps: u16,
+) -> Result<(), TransactionResult> {
+    let [market_meta, creator, collateral_vault, liquidity_vault, oracle, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !creator.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    // 抵押率必须小于100%，否则价格一动就资不抵债
+    if collateral_factor_bps as u128 >= BASIS_POINTS || liquidation_bonus_bps as u128 >= BASIS_POINTS {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+    let (collateral_token, _) = ctx.token_account(&collateral_vault.pubkey)?;
+    let (liquidity_token, _) = ctx.token_account(&liquidity_vault.pubkey)?;
+    let (market_key, bump) = lending_market_address(&collateral_token.mint, &liquidity_token.mint, program_id);
+    if market_meta.pubkey != market_key || !market_meta.is_writable || collateral_token.mint == liquidity_token.mint {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if collateral_token.owner != market_key || liquidity_token.owner != market_key || collateral_token.amount != 0 {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    load_oracle(ctx, &oracle.pubkey)?;
+    let mut account = ctx.staged().get_account(&market_key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    if account.owner != *program_id || account.data != AccountPayload::Empty {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if account.lamports < ctx.staged().rent().minimum_balance(LendingMarket::LEN) {
+        return Err(TransactionResult::InsufficientFunds);
+    }
+
+    account.data = AccountPayload::LendingMarket(LendingMarket {
+        collateral_mint: collateral_token.mint,
+        borrow_mint: liquidity_token.mint,
+        collateral_vault: collateral_vault.pubkey,
+        liquidity_vault: liquidity_vault.pubkey,
+        oracle: oracle.pubkey,
+        collateral_factor_bps,
+        liquidation_bonus_bps,
+        bump,
+    });
+    Ok(ctx.staged().set_account(market_key, account)?)
+}
+
+// accounts: [市场, 仓位(可写，第一次存入时是空的PDA账户), owner(签名), 抵押代币账户, collateral_vault]
+fn deposit_collateral(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta], amount: u64) -> Result<(), TransactionResult> {
+    let [market_meta, obligation_meta, owner, source, vault, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !owner.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if amount == 0 {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+    let market = load_market(ctx, program_id, &market_meta.pubkey)?;
+    if vault.pubkey != market.collateral_vault {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let (obligation_key, _) = obligation_address(&market_meta.pubkey, &owner.pubkey, program_id);
+    if obligation_meta.pubkey != obligation_key || !obligation_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let minimum = ctx.staged().rent().minimum_balance(Obligation::LEN);
+    let mut obligation = match ctx.staged().get_account(&obligation_key) {
+        Some(account) if account.owner == *program_id => match &account.data {
+            AccountPayload::Obligation(obligation) => obligation.clone(),
+            AccountPayload::Empty if account.lamports >= minimum => Obligation {
+                market: market_meta.pubkey,
+                owner: owner.pubkey,
+                collateral: 0,
+                debt: 0,
+            },
+            _ => return Err(TransactionResult::InvalidAccount),
+        },
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+
+    // 按vault实际到账的数量记账
+    let (_, token_program) = ctx.token_account(&source.pubkey)?;
+    let received = deposit(ctx, metas, token_program, owner, source, vault, amount)?;
+    obligation.collateral = obligation.collateral.checked_add(received).ok_or(TransactionResult::ArithmeticOverflow)?;
+    store_obligation(ctx, obligation_key, obligation)
+}
+
+// accounts: [市场, 仓位(可写), owner(签名), 价格账户, liquidity_vault, 接收的Token账户]
+fn borrow(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta], amount: u64) -> Result<(), TransactionResult> {
+    let [market_meta, obligation_meta, owner, oracle, vault, destination, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !owner.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if amount == 0 {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+    let market = load_market(ctx, program_id, &market_meta.pubkey)?;
+    if oracle.pubkey != market.oracle || vault.pubkey != market.liquidity_vault {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let mut obligation = load_obligation(ctx, program_id, &obligation_meta.pubkey, &market_meta.pubkey)?;
+    if obligation.owner != owner.pubkey || !obligation_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let price = load_price(ctx, &oracle.pubkey)?;
+    let debt = obligation.debt.checked_add(amount).ok_or(TransactionResult::ArithmeticOverflow)?;
+    if debt > borrow_limit(obligation.collateral, price, market.collateral_factor_bps) {
+        return Err(TransactionResult::BorrowLimitExceeded);
+    }
+
+    obligation.debt = debt;
+    store_obligation(ctx, obligation_meta.pubkey, obligation)?;
+    let (_, token_program) = ctx.token_account(&vault.pubkey)?;
+    withdraw(ctx, program_id, metas, market_meta, &market, (vault.pubkey, destination.pubkey), amount, token_program)
+}
+
+// accounts: [市场, 仓位(可写), 还款人(签名), 还款的Token账户, liquidity_vault]。
+// 任何人都可以替仓位还款，超过欠款的部分不会转出
+fn repay(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta], amount: u64) -> Result<(), TransactionResult> {
+    let [market_meta, obligation_meta, payer, source, vault, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !payer.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let market = load_market(ctx, program_id, &market_meta.pubkey)?;
+    if vault.pubkey != market.liquidity_vault {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let mut obligation = load_obligation(ctx, program_id, &obligation_meta.pubkey, &market_meta.pubkey)?;
+    if !obligation_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let amount = amount.min(obligation.debt);
+    if amount == 0 {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+
+    let (_, token_program) = ctx.token_account(&source.pubkey)?;
+    if deposit(ctx, metas, token_program, payer, source, vault, amount)? != amount {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    obligation.debt -= amount;
+    store_obligation(ctx, obligation_meta.pubkey, obligation)
+}
+
+// accounts: [市场, 仓位(可写), 清算人(签名), 价格账户, 还款的Token账户, liquidity_vault, collateral_vault, 接收抵押品的Token账户]。
+// 只有欠款超过借款上限的仓位可以清算；清算人最多还清全部欠款，拿走的抵押品不超过仓位里的数量
+fn liquidate(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta], amount: u64) -> Result<(), TransactionResult> {
+    let [market_meta, obligation_meta, liquidator, oracle, source, liquidity_vault, collateral_vault, destination, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !liquidator.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let market = load_market(ctx, program_id, &market_meta.pubkey)?;
+    if oracle.pubkey != market.oracle
+        || liquidity_vault.pubkey != market.liquidity_vault
+        || collateral_vault.pubkey != market.collateral_vault
+    {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let mut obligation = load_obligation(ctx, program_id, &obligation_meta.pubkey, &market_meta.pubkey)?;
+    if !obligation_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let price = load_price(ctx, &oracle.pubkey)?;
+    if obligation.debt <= borrow_limit(obligation.collateral, price, market.collateral_factor_bps) {
+        return Err(TransactionResult::ObligationHealthy);
+    }
+    let repay = amount.min(obligation.debt);
+    let seized = seize_amount(repay, price, market.liquidation_bonus_bps).min(obligation.collateral);
+    if repay == 0 || seized == 0 {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+
+    let (_, token_program) = ctx.token_account(&source.pubkey)?;
+    if deposit(ctx, metas, token_program, liquidator, source, liquidity_vault, repay)? != repay {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    obligation.debt -= repay;
+    obligation.collateral -= seized;
+    store_obligation(ctx, obligation_meta.pubkey, obligation)?;
+    let (_, collateral_program) = ctx.token_account(&collateral_vault.pubkey)?;
+    withdraw(ctx, program_id, metas, market_meta, &market, (collateral_vault.pubkey, destination.pubkey), seized, collateral_program)
+}
+
+// 用户签名把amount从source转进vault，返回vault实际增加的数量
+fn deposit(
+    ctx: &mut InvokeContext<'_, '_>,
+    metas: &[AccountMeta],
+    token_program: Pubkey,
+    owner: &AccountMeta,
+    source: &AccountMeta,
+    vault: &AccountMeta,
+    amount: u64,
+) -> Result<u64, TransactionResult> {
+    let before = ctx.token_account(&vault.pubkey)?.0.amount;
+    let transfer = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(source.pubkey, token_program, false),
+            AccountMeta::new(vault.pubkey, token_program, false),
+            AccountMeta::new_readonly(owner.pubkey, owner.owner, true),
+        ],
+    );
+    ctx.invoke(&transfer, metas)?;
+    Ok(ctx.token_account(&vault.pubkey)?.0.amount - before)
+}
+
+// 市场PDA通过invoke_signed签名，把amount从vault转给destination
+#[allow(clippy::too_many_arguments)]
+fn withdraw(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    market_meta: &AccountMeta,
+    market: &LendingMarket,
+    (vault, destination): (Pubkey, Pubkey),
+    amount: u64,
+    token_program: Pubkey,
+) -> Result<(), TransactionResult> {
+    let bump = [market.bump];
+    let seeds: &[&[u8]] = &[LENDING_SEED, market.collateral_mint.as_ref(), market.borrow_mint.as_ref(), &bump];
+    let transfer = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(vault, token_program, false),
+            AccountMeta::new(destination, token_program, false),
+            AccountMeta::new_readonly(market_meta.pubkey, *program_id, true),
+        ],
+    );
+    ctx.invoke_signed(&transfer, metas, &[seeds])
+}
+
+fn load_market(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, key: &Pubkey) -> Result<LendingMarket, TransactionResult> {
+    match ctx.staged().get_account(key) {
+        Some(account) if account.owner == *program_id => match &account.data {
+            AccountPayload::LendingMarket(market) => Ok(market.clone()),
+            _ => Err(TransactionResult::InvalidAccount),
+        },
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+// 读取仓位，并确认它属于market
+fn load_obligation(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    key: &Pubkey,
+    market: &Pubkey,
+) -> Result<Obligation, TransactionResult> {
+    match ctx.staged().get_account(key) {
+        Some(account) if account.owner == *program_id => match &account.data {
+            AccountPayload::Obligation(obligation) if obligation.market == *market => Ok(obligation.clone()),
+            _ => Err(TransactionResult::InvalidAccount),
+        },
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+// 价格账户由预言机程序维护，这里只读取。不检查owner的话谁都可以建一个自己能随意改价的账户
+fn load_oracle(ctx: &mut InvokeContext<'_, '_>, key: &Pubkey) -> Result<PriceOracle, TransactionResult> {
+    match ctx.staged().get_account(key) {
+        Some(account) if account.owner == ORACLE_PROGRAM_ID => match &account.data {
+            AccountPayload::PriceOracle(oracle) => Ok(oracle.clone()),
+            _ => Err(TransactionResult::InvalidAccount),
+        },
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+// 借款和清算用的价格，太久没有更新的价格拒绝使用
+fn load_price(ctx: &mut InvokeContext<'_, '_>, key: &Pubkey) -> Result<u64, TransactionResult> {
+    let oracle = load_oracle(ctx, key)?;
+    if ctx.slot().saturating_sub(oracle.last_update_slot) > MAX_PRICE_AGE_SLOTS {
+        return Err(TransactionResult::StalePrice);
+    }
+    Ok(oracle.price)
+}
+
+fn store_obligation(ctx: &mut InvokeContext<'_, '_>, key: Pubkey, obligation: Obligation) -> Result<(), TransactionResult> {
+    let mut account = ctx.staged().get_account(&key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    account.data = AccountPayload::Obligation(obligation);
+    Ok(ctx.staged().set_account(key, account)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpi::{Entrypoint, Programs};
+    use crate::ledger::{Account, Ledger};
+    use crate::mint::Mint;
+    use crate::oracle;
+    use crate::signature::{Keypair, Signer};
+    use crate::test_utils::{create_mint, create_token_account, funded_keypair, sign_and_execute, token_amount};
+    use crate::transaction::TransactionError;
+    use crate::SYSTEM_PROGRAM_ID;
+
+    // 价格2.0，抵押率75%，清算奖励5%
+    const PRICE: u64 = 2 * PRICE_SCALE;
+
+    struct Fixture {
+        ledger: Ledger,
+        programs: Programs,
+        lending_program: Pubkey,
+        oracle_program: Pubkey,
+        token_program: Pubkey,
+        alice: Keypair,          // 抵押借款
+        bob: Keypair,            // 清算人
+        price_authority: Keypair,
+        alice_collateral: Pubkey,
+        alice_borrowed: Pubkey,
+        bob_collateral: Pubkey,
+        bob_borrowed: Pubkey,
+        collateral_vault: Pubkey,
+        liquidity_vault: Pubkey,
+        oracle: Pubkey,
+        market: Pubkey,
+        obligation: Pubkey,
+    }
+
+    fn fixture() -> Fixture {
+        let mut ledger = Ledger::default();
+        let (lending_program, oracle_program, token_program) = (Pubkey::new_unique(), ORACLE_PROGRAM_ID, Pubkey::new_unique());
+        let (alice, bob, price_authority) = (funded_keypair(&mut ledger), funded_keypair(&mut ledger), funded_keypair(&mut ledger));
+        let [collateral_mint, borrow_mint] = [(); 2].map(|_| create_mint(&mut ledger, token_program, Mint::new(0, Pubkey::new_unique())));
+        let (market, _) = lending_market_address(&collateral_mint, &borrow_mint, &lending_program);
+        let (obligation, _) = obligation_address(&market, &alice.pubkey(), &lending_program);
+        let mut token = |mint, owner, amount| create_token_account(&mut ledger, token_program, Pubkey::new_unique(), mint, owner, amount);
+        let alice_collateral = token(collateral_mint, alice.pubkey(), 1_000);
+        let alice_borrowed = token(borrow_mint, alice.pubkey(), 0);
+        let bob_collateral = token(collateral_mint, bob.pubkey(), 0);
+        let bob_borrowed = token(borrow_mint, bob.pubkey(), 10_000);
+        let collateral_vault = token(collateral_mint, market, 0);
+        let liquidity_vault = token(borrow_mint, market, 100_000);
+        let oracle = Pubkey::new_unique();
+        let data = AccountPayload::PriceOracle(PriceOracle::new(price_authority.pubkey(), PRICE));
+        let lamports = ledger.rent().minimum_balance(PriceOracle::LEN);
+        ledger.create_account(oracle, Account::new(lamports, oracle_program, data)).unwrap();
+        for (key, len) in [(market, LendingMarket::LEN), (obligation, Obligation::LEN)] {
+            let lamports = ledger.rent().minimum_balance(len);
+            ledger.create_account(key, Account::new(lamports, lending_program, AccountPayload::Empty)).unwrap();
+        }
+
+        let programs = Programs::from([(lending_program, process as Entrypoint), (oracle_program, oracle::process as Entrypoint)]);
+        let mut f = Fixture {
+            ledger,
+            programs,
+            lending_program,
+            oracle_program,
+            token_program,
+            alice,
+            bob,
+            price_authority,
+            alice_collateral,
+            alice_borrowed,
+            bob_collateral,
+            bob_borrowed,
+            collateral_vault,
+            liquidity_vault,
+            oracle,
+            market,
+            obligation,
+        };
+        let accounts = vec![
+            AccountMeta::new(f.market, f.lending_program, false),
+            AccountMeta::new_readonly(f.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+            AccountMeta::new_readonly(f.collateral_vault, f.token_program, false),
+            AccountMeta::new_readonly(f.liquidity_vault, f.token_program, false),
+            AccountMeta::new_readonly(f.oracle, f.oracle_program, false),
+        ];
+        let data = ProgramInstruction::InitLendingMarket { collateral_factor_bps: 7_500, liquidation_bonus_bps: 500 };
+        let instruction = Instruction::new(f.lending_program, data, accounts);
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.alice, vec![instruction]), Ok(()));
+        f
+    }
+
+    impl Fixture {
+        // (抵押数量, 欠款)
+        fn position(&self) -> (u64, u64) {
+            match &self.ledger.get_account(&self.obligation).unwrap().data {
+                AccountPayload::Obligation(obligation) => (obligation.collateral, obligation.debt),
+                _ => panic!("不是仓位账户"),
+            }
+        }
+
+        fn set_price(&mut self, signer_is_authority: bool, price: u64) -> Result<(), TransactionError> {
+            let signer = if signer_is_authority { &self.price_authority } else { &self.alice };
+            let accounts = vec![
+                AccountMeta::new(self.oracle, self.oracle_program, false),
+                AccountMeta::new_readonly(signer.pubkey(), SYSTEM_PROGRAM_ID, true),
+            ];
+            let instruction = Instruction::new(self.oracle_program, ProgramInstruction::UpdatePrice { price }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, signer, vec![instruction])
+        }
+
+        fn deposit(&mut self, amount: u64) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new_readonly(self.market, self.lending_program, false),
+                AccountMeta::new(self.obligation, self.lending_program, false),
+                AccountMeta::new_readonly(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.alice_collateral, self.token_program, false),
+                AccountMeta::new(self.collateral_vault, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.lending_program, ProgramInstruction::DepositCollateral { amount }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.alice, vec![instruction])
+        }
+
+        fn borrow(&mut self, amount: u64) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new_readonly(self.market, self.lending_program, false),
+                AccountMeta::new(self.obligation, self.lending_program, false),
+                AccountMeta::new_readonly(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new_readonly(self.oracle, self.oracle_program, false),
+                AccountMeta::new(self.liquidity_vault, self.token_program, false),
+                AccountMeta::new(self.alice_borrowed, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.lending_program, ProgramInstruction::Borrow { amount }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.alice, vec![instruction])
+        }
+
+        fn repay(&mut self, amount: u64) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new_readonly(self.market, self.lending_program, false),
+                AccountMeta::new(self.obligation, self.lending_program, false),
+                AccountMeta::new_readonly(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.alice_borrowed, self.token_program, false),
+                AccountMeta::new(self.liquidity_vault, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.lending_program, ProgramInstruction::Repay { amount }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.alice, vec![instruction])
+        }
+
+        fn liquidate(&mut self, amount: u64) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new_readonly(self.market, self.lending_program, false),
+                AccountMeta::new(self.obligation, self.lending_program, false),
+                AccountMeta::new_readonly(self.bob.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new_readonly(self.oracle, self.oracle_program, false),
+                AccountMeta::new(self.bob_borrowed, self.token_program, false),
+                AccountMeta::new(self.liquidity_vault, self.token_program, false),
+                AccountMeta::new(self.collateral_vault, self.token_program, false),
+                AccountMeta::new(self.bob_collateral, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.lending_program, ProgramInstruction::Liquidate { amount }, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.bob, vec![instruction])
+        }
+    }
+
+    #[test]
+    fn test_borrow_limit_and_seize_amount() {
+        // 100个抵押品值200，75%可借150
+        assert_eq!(borrow_limit(100, PRICE, 7_500), 150);
+        assert_eq!(borrow_limit(100, PRICE_SCALE / 3, 7_500), 24);
+        assert_eq!(borrow_limit(u64::MAX, u64::MAX, 7_500), u64::MAX);
+        // 还50，价值加5%是52.5，价格1.5时折合35个抵押品
+        assert_eq!(seize_amount(50, 3 * PRICE_SCALE / 2, 500), 35);
+        assert_eq!(seize_amount(50, PRICE, 0), 25);
+        assert_eq!(seize_amount(1, 0, 500), u64::MAX);
+    }
+
+    #[test]
+    fn test_deposit_borrow_repay() {
+        let mut f = fixture();
+        // 没有抵押品时不能借
+        assert_eq!(f.borrow(1), Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount)));
+        assert_eq!(f.deposit(60), Ok(()));
+        assert_eq!(f.deposit(40), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.collateral_vault), 100);
+
+        assert_eq!(f.borrow(151), Err(TransactionError::InstructionError(0, TransactionResult::BorrowLimitExceeded)));
+        assert_eq!(f.borrow(100), Ok(()));
+        assert_eq!(f.borrow(51), Err(TransactionError::InstructionError(0, TransactionResult::BorrowLimitExceeded)));
+        assert_eq!(f.borrow(50), Ok(()));
+        assert_eq!((f.position(), token_amount(&f.ledger, &f.alice_borrowed)), ((100, 150), 150));
+
+        // 多还的部分不会转出
+        assert_eq!(f.repay(40), Ok(()));
+        assert_eq!(f.repay(1_000), Ok(()));
+        assert_eq!((f.position(), token_amount(&f.ledger, &f.alice_borrowed)), ((100, 0), 0));
+        assert_eq!(token_amount(&f.ledger, &f.liquidity_vault), 100_000);
+        assert_eq!(f.repay(1), Err(TransactionError::InstructionError(0, TransactionResult::InvalidInstructionData)));
+    }
+
+    #[test]
+    fn test_liquidation_after_price_drop() {
+        let mut f = fixture();
+        assert_eq!(f.deposit(100), Ok(()));
+        assert_eq!(f.borrow(150), Ok(()));
+        assert_eq!(f.liquidate(50), Err(TransactionError::InstructionError(0, TransactionResult::ObligationHealthy)));
+
+        // 只有预言机的authority可以改价格
+        assert_eq!(
+            f.set_price(false, PRICE_SCALE),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+        // 价格跌到1.5，借款上限变成112
+        assert_eq!(f.set_price(true, 3 * PRICE_SCALE / 2), Ok(()));
+        assert_eq!(f.borrow(1), Err(TransactionError::InstructionError(0, TransactionResult::BorrowLimitExceeded)));
+        assert_eq!(f.liquidate(50), Ok(()));
+        assert_eq!(f.position(), (65, 100));
+        assert_eq!((token_amount(&f.ledger, &f.bob_borrowed), token_amount(&f.ledger, &f.bob_collateral)), (9_950, 35));
+        assert_eq!(token_amount(&f.ledger, &f.collateral_vault), 65);
+
+        // 价格涨到3.0，剩下的65个抵押品可借146，仓位恢复健康
+        assert_eq!(f.set_price(true, 3 * PRICE_SCALE), Ok(()));
+        assert_eq!(f.liquidate(10), Err(TransactionError::InstructionError(0, TransactionResult::ObligationHealthy)));
+    }
+
+    #[test]
+    fn test_liquidation_caps_at_collateral() {
+        let mut f = fixture();
+        assert_eq!(f.deposit(100), Ok(()));
+        assert_eq!(f.borrow(150), Ok(()));
+        // 价格暴跌到0.5，抵押品只值50
+        assert_eq!(f.set_price(true, PRICE_SCALE / 2), Ok(()));
+        assert_eq!(f.liquidate(40), Ok(()));
+        assert_eq!(f.position(), (16, 110));
+        // 剩下的抵押品不够支付奖励时全部给清算人，没还上的欠款留在仓位里
+        assert_eq!(f.liquidate(100), Ok(()));
+        assert_eq!(f.position(), (0, 10));
+        assert_eq!(token_amount(&f.ledger, &f.bob_collateral), 100);
+        assert_eq!(token_amount(&f.ledger, &f.collateral_vault), 0);
+        assert_eq!(f.liquidate(10), Err(TransactionError::InstructionError(0, TransactionResult::InvalidInstructionData)));
+    }
+
+    #[test]
+    fn test_stale_price_rejected() {
+        let mut f = fixture();
+        assert_eq!(f.deposit(100), Ok(()));
+        assert_eq!(f.borrow(50), Ok(()));
+        f.ledger.tick(MAX_PRICE_AGE_SLOTS + 1);
+        assert_eq!(f.borrow(50), Err(TransactionError::InstructionError(0, TransactionResult::StalePrice)));
+        assert_eq!(f.liquidate(10), Err(TransactionError::InstructionError(0, TransactionResult::StalePrice)));
+        // 预言机更新后价格重新可用
+        assert_eq!(f.set_price(true, PRICE), Ok(()));
+        assert_eq!(f.borrow(50), Ok(()));
+        assert_eq!(f.position(), (100, 100));
+    }
+
+    #[test]
+    fn test_oracle_must_be_owned_by_oracle_program() {
+        let mut f = fixture();
+        assert_eq!(f.deposit(100), Ok(()));
+        // 把价格账户换成别的程序所有的同样数据，借款方就能随意抬高价格
+        let mut account = f.ledger.get_account(&f.oracle).unwrap().clone();
+        account.owner = Pubkey::new_unique();
+        account.data = AccountPayload::PriceOracle(PriceOracle::new(f.alice.pubkey(), 1_000 * PRICE_SCALE));
+        let mut staged = f.ledger.stage();
+        staged.set_account(f.oracle, account).unwrap();
+        staged.commit(f.alice.pubkey());
+        assert_eq!(f.borrow(1_000), Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount)));
+        assert_eq!(f.position(), (100, 0));
+    }
+
+    #[test]
+    fn test_borsh_roundtrip() {
+        let market = LendingMarket {
+            collateral_mint: Pubkey::new_unique(),
+            borrow_mint: Pubkey::new_unique(),
+            collateral_vault: Pubkey::new_unique(),
+            liquidity_vault: Pubkey::new_unique(),
+            oracle: Pubkey::new_unique(),
+            collateral_factor_bps: 7_500,
+            liquidation_bonus_bps: 500,
+            bump: 253,
+        };
+        let mut bytes = Vec::new();
+        market.serialize(&mut bytes);
+        assert_eq!(bytes.len(), LendingMarket::LEN);
+        assert_eq!(LendingMarket::deserialize(&mut bytes.as_slice()), Ok(market));
+
+        let obligation = Obligation { market: Pubkey::new_unique(), owner: Pubkey::new_unique(), collateral: 100, debt: 150 };
+        let mut bytes = Vec::new();
+        obligation.serialize(&mut bytes);
+        assert_eq!(bytes.len(), Obligation::LEN);
+        assert_eq!(Obligation::deserialize(&mut bytes.as_slice()), Ok(obligation));
+
+        let oracle = PriceOracle { authority: Pubkey::new_unique(), price: PRICE, last_update_slot: 42 };
+        let mut bytes = Vec::new();
+        oracle.serialize(&mut bytes);
+        assert_eq!(bytes.len(), PriceOracle::LEN);
+        assert_eq!(PriceOracle::deserialize(&mut bytes.as_slice()), Ok(oracle));
+    }
+}
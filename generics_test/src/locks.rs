@@ -0,0 +1,156 @@
+// 运行时账户锁 - 模拟Solana运行时对账户的并发访问控制
+// 规则和Rust的借用规则一样：
+//   只读账户可以同时被多个交易共享（类似 &T）
+//   可写账户同一时间只能被一个交易独占（类似 &mut T）
+
+use std::collections::HashMap;
+
+use crate::{AccountMeta, Pubkey, TransactionResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountLock {
+    Shared(usize), // 被多少个交易以只读方式持有
+    Exclusive,     // 被一个交易以可写方式持有
+}
+
+#[derive(Debug, Default)]
+pub struct AccountLockManager {
+    locks: HashMap<Pubkey, AccountLock>,
+}
+
+impl AccountLockManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lock_state(&self, key: &Pubkey) -> Option<AccountLock> {
+        self.locks.get(key).copied()
+    }
+
+    // 同一个账户在一笔交易中出现多次时，只要有一次可写就需要独占锁
+    fn requested_locks(metas: &[AccountMeta]) -> HashMap<Pubkey, bool> {
+        let mut requested: HashMap<Pubkey, bool> = HashMap::new();
+        for meta in metas {
+            *requested.entry(meta.pubkey).or_insert(false) |= meta.is_writable;
+        }
+        requested
+    }
+
+    // 要么全部加锁成功，要么一个都不加（冲突时返回AccountInUse）
+    pub fn lock_accounts(&mut self, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+        let requested = Self::requested_locks(metas);
+
+        for (key, writable) in &requested {
+            match (self.locks.get(key), writable) {
+                (None, _) => {}
+                (Some(AccountLock::Shared(_)), false) => {}
+                _ => return Err(TransactionResult::AccountInUse),
+            }
+        }
+
+        for (key, writable) in requested {
+            if writable {
+                self.locks.insert(key, AccountLock::Exclusive);
+            } else {
+                let lock = self.locks.entry(key).or_insert(AccountLock::Shared(0));
+                if let AccountLock::Shared(count) = lock {
+                    *count += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 释放lock_accounts获得的锁，metas需要和加锁时相同
+    pub fn unlock_accounts(&mut self, metas: &[AccountMeta]) {
+        for (key, writable) in Self::requested_locks(metas) {
+            match self.locks.get_mut(&key) {
+                Some(AccountLock::Exclusive) if writable => {
+                    self.locks.remove(&key);
+                }
+                Some(AccountLock::Shared(count)) if !writable => {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.locks.remove(&key);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn writable(key: Pubkey) -> AccountMeta {
+        AccountMeta::new(key, Pubkey::default(), false)
+    }
+
+    fn readonly(key: Pubkey) -> AccountMeta {
+        AccountMeta::new_readonly(key, Pubkey::default(), false)
+    }
+
+    #[test]
+    fn test_shared_locks_coexist() {
+        let key = Pubkey::new_unique();
+        let mut locks = AccountLockManager::new();
+
+        assert_eq!(locks.lock_accounts(&[readonly(key)]), Ok(()));
+        assert_eq!(locks.lock_accounts(&[readonly(key)]), Ok(()));
+        assert_eq!(locks.lock_state(&key), Some(AccountLock::Shared(2)));
+
+        locks.unlock_accounts(&[readonly(key)]);
+        assert_eq!(locks.lock_state(&key), Some(AccountLock::Shared(1)));
+        locks.unlock_accounts(&[readonly(key)]);
+        assert_eq!(locks.lock_state(&key), None);
+    }
+
+    #[test]
+    fn test_exclusive_lock_conflicts() {
+        let key = Pubkey::new_unique();
+        let mut locks = AccountLockManager::new();
+
+        assert_eq!(locks.lock_accounts(&[writable(key)]), Ok(()));
+        assert_eq!(locks.lock_accounts(&[readonly(key)]), Err(TransactionResult::AccountInUse));
+        assert_eq!(locks.lock_accounts(&[writable(key)]), Err(TransactionResult::AccountInUse));
+
+        locks.unlock_accounts(&[writable(key)]);
+        assert_eq!(locks.lock_accounts(&[writable(key)]), Ok(()));
+    }
+
+    #[test]
+    fn test_write_after_read_conflicts() {
+        let key = Pubkey::new_unique();
+        let mut locks = AccountLockManager::new();
+
+        locks.lock_accounts(&[readonly(key)]).unwrap();
+        assert_eq!(locks.lock_accounts(&[writable(key)]), Err(TransactionResult::AccountInUse));
+    }
+
+    #[test]
+    fn test_failed_lock_is_all_or_nothing() {
+        let busy = Pubkey::new_unique();
+        let free = Pubkey::new_unique();
+        let mut locks = AccountLockManager::new();
+
+        locks.lock_accounts(&[writable(busy)]).unwrap();
+        assert_eq!(
+            locks.lock_accounts(&[writable(free), readonly(busy)]),
+            Err(TransactionResult::AccountInUse)
+        );
+        assert_eq!(locks.lock_state(&free), None);
+    }
+
+    #[test]
+    fn test_duplicate_account_uses_strongest_lock() {
+        let key = Pubkey::new_unique();
+        let mut locks = AccountLockManager::new();
+
+        locks.lock_accounts(&[readonly(key), writable(key)]).unwrap();
+        assert_eq!(locks.lock_state(&key), Some(AccountLock::Exclusive));
+        locks.unlock_accounts(&[readonly(key), writable(key)]);
+        assert_eq!(locks.lock_state(&key), None);
+    }
+}
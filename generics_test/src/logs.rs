@@ -0,0 +1,74 @@
+// 程序日志 - 类似于Solana的log collector：msg!写入的日志不直接打印，
+// 而是先收集起来，交易结束后随执行结果一起返回
+
+use std::fmt;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Logs {
+    lines: Vec<String>,
+}
+
+impl Logs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, line: impl Into<String>) {
+        self.lines.push(line.into());
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    // 是否有某一行包含needle，方便测试断言
+    pub fn contains(&self, needle: &str) -> bool {
+        self.lines.iter().any(|line| line.contains(needle))
+    }
+}
+
+impl fmt::Display for Logs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+// 写一行程序日志：msg!(target, "格式", 参数...)
+// target可以是ComputeBudget或InvokeContext，日志按长度消耗compute units，预算不足时返回错误
+macro_rules! msg {
+    ($target:expr, $($arg:tt)+) => {
+        $target.log(&format!($($arg)+))
+    };
+}
+
+pub(crate) use msg;
+
+#[cfg(test)]
+mod tests {
+    use crate::compute::{ComputeBudget, LOG_BASE_COST};
+
+    #[test]
+    fn test_msg_collects_lines() {
+        let mut budget = ComputeBudget::new(1_000);
+        msg!(budget, "转账 {} lamports", 42).unwrap();
+        msg!(budget, "完成").unwrap();
+
+        let logs = budget.logs();
+        assert_eq!(logs.lines(), ["转账 42 lamports", "完成"]);
+        assert!(logs.contains("42"));
+        assert!(!logs.contains("失败"));
+        assert_eq!(logs.to_string(), "转账 42 lamports\n完成\n");
+        assert_eq!(budget.consumed(), 2 * LOG_BASE_COST);
+    }
+}
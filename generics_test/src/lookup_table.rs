@@ -0,0 +1,124 @@
+// 地址查找表 - 类似于Solana的Address Lookup Table：把常用的账户地址预先存进一个链上账户，
+// V0交易只需写(查找表, 下标)两个字节就能引用其中的地址，不必在消息里重复32字节的pubkey
+
+use std::fmt;
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::Pubkey;
+
+// 下标只有1个字节
+pub const LOOKUP_TABLE_MAX_ADDRESSES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookupTableError {
+    TableFull,                // 超过256个地址
+    DuplicateAddress(Pubkey), // 地址已经在表里
+}
+
+impl fmt::Display for LookupTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LookupTableError::TableFull => write!(f, "查找表最多保存{}个地址", LOOKUP_TABLE_MAX_ADDRESSES),
+            LookupTableError::DuplicateAddress(key) => write!(f, "地址已在查找表中: {}", key),
+        }
+    }
+}
+
+impl std::error::Error for LookupTableError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressLookupTable {
+    pub authority: Pubkey, // 只有authority可以往表里追加地址
+    addresses: Vec<Pubkey>,
+}
+
+impl AddressLookupTable {
+    pub fn new(authority: Pubkey) -> Self {
+        Self { authority, addresses: Vec::new() }
+    }
+
+    // 只能追加不能修改，已经发出的交易引用的下标不会失效；整批追加要么全部成功要么不变
+    pub fn extend(&mut self, addresses: &[Pubkey]) -> Result<(), LookupTableError> {
+        if self.addresses.len() + addresses.len() > LOOKUP_TABLE_MAX_ADDRESSES {
+            return Err(LookupTableError::TableFull);
+        }
+        for (i, key) in addresses.iter().enumerate() {
+            if self.addresses.contains(key) || addresses[..i].contains(key) {
+                return Err(LookupTableError::DuplicateAddress(*key));
+            }
+        }
+        self.addresses.extend_from_slice(addresses);
+        Ok(())
+    }
+
+    pub fn get(&self, index: u8) -> Option<Pubkey> {
+        self.addresses.get(index as usize).copied()
+    }
+
+    pub fn index_of(&self, key: &Pubkey) -> Option<u8> {
+        self.addresses.iter().position(|address| address == key).map(|index| index as u8)
+    }
+
+    pub fn addresses(&self) -> &[Pubkey] {
+        &self.addresses
+    }
+
+    // authority(32) + 地址数量(4) + 每个地址32字节
+    pub fn data_len(&self) -> usize {
+        32 + 4 + 32 * self.addresses.len()
+    }
+}
+
+impl BorshSerialize for AddressLookupTable {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.authority.serialize(out);
+        self.addresses.serialize(out);
+    }
+}
+
+impl BorshDeserialize for AddressLookupTable {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self { authority: Pubkey::deserialize(buf)?, addresses: Vec::deserialize(buf)? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extend_and_lookup() {
+        let mut table = AddressLookupTable::new(Pubkey::new_unique());
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        table.extend(&[a, b]).unwrap();
+
+        assert_eq!(table.get(1), Some(b));
+        assert_eq!(table.get(2), None);
+        assert_eq!(table.index_of(&a), Some(0));
+        assert_eq!(table.extend(&[b]), Err(LookupTableError::DuplicateAddress(b)));
+
+        // 追加失败时表保持不变
+        let c = Pubkey::new_unique();
+        assert_eq!(table.extend(&[c, c]), Err(LookupTableError::DuplicateAddress(c)));
+        assert_eq!(table.addresses(), [a, b]);
+    }
+
+    #[test]
+    fn test_table_full() {
+        let mut table = AddressLookupTable::new(Pubkey::new_unique());
+        let keys: Vec<Pubkey> = (0..LOOKUP_TABLE_MAX_ADDRESSES).map(|_| Pubkey::new_unique()).collect();
+        table.extend(&keys).unwrap();
+        assert_eq!(table.get(255), Some(keys[255]));
+        assert_eq!(table.extend(&[Pubkey::new_unique()]), Err(LookupTableError::TableFull));
+    }
+
+    #[test]
+    fn test_borsh_roundtrip() {
+        let mut table = AddressLookupTable::new(Pubkey::new_unique());
+        table.extend(&[Pubkey::new_unique(), Pubkey::new_unique()]).unwrap();
+        let mut bytes = Vec::new();
+        table.serialize(&mut bytes);
+        assert_eq!(bytes.len(), table.data_len());
+        assert_eq!(AddressLookupTable::try_from_slice(&bytes), Ok(table));
+    }
+}
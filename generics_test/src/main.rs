@@ -1,6 +1,109 @@
 // Solana合约开发中的Trait与泛型基础 - 实践代码
 
+mod amm;
+mod auction;
+mod audit;
+mod borsh;
+mod compute;
+mod cpi;
+mod crowdfunding;
+mod dex;
+mod discriminator;
+mod escrow;
+mod events;
+mod faucet;
+mod fees;
+mod governance;
+mod hash;
+mod instruction_builder;
+mod json;
+mod ledger;
+mod lending;
+mod locks;
+mod logs;
+mod lookup_table;
+mod metadata;
+mod mint;
+mod multisig;
+mod nft;
+mod nonce;
+mod oracle;
+mod pda;
+mod pubkey;
+mod queue;
+mod realloc;
+mod registry;
+mod rent;
+mod safe_math;
+mod shortvec;
+mod signature;
+mod snapshot_file;
+mod streaming;
+mod style;
+#[cfg(test)]
+mod test_utils;
+mod transaction;
+mod transaction_builder;
+mod transfer_hook;
+mod tutorial_token;
+mod ui_amount;
+mod validator;
+mod versioned_transaction;
+mod versioning;
+mod vesting;
+mod wallet;
+
+use std::collections::BTreeMap;
+use std::error::Error;
 use std::fmt;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use logs::msg;
+
+pub use amm::{pool_address, Pool};
+pub use auction::{auction_address, bid_record_address, Auction, BidRecord};
+pub use compute::ComputeBudget;
+pub use cpi::{Entrypoint, InvokeContext, Programs, MAX_INVOKE_DEPTH};
+pub use crowdfunding::{campaign_address, contribution_address, Campaign, CampaignState, Contribution};
+pub use borsh::{BorshDeserialize, BorshError, BorshSerialize};
+pub use dex::{market_address, order_address, Market, Order, OrderBook, Side};
+pub use discriminator::{AccountDeserialize, AccountSerialize};
+pub use escrow::{escrow_address, Escrow};
+pub use events::{EventLog, EventRecord, TokenEvent};
+pub use faucet::{Faucet, FaucetError};
+pub use fees::FeeSchedule;
+pub use governance::{governance_address, proposal_address, vote_record_address, Governance, GovernanceError, Proposal, ProposalState, VoteRecord};
+pub use instruction_builder::{InstructionBuilder, InstructionBuilderError};
+pub use ledger::{Account, AccountDiff, AccountPayload, Ledger, LedgerJsonError, LEDGER_JSON_VERSION};
+pub use lending::{lending_market_address, obligation_address, LendingMarket, Obligation};
+pub use locks::AccountLockManager;
+pub use logs::Logs;
+pub use lookup_table::{AddressLookupTable, LookupTableError};
+pub use metadata::{metadata_address, MetadataError, TokenMetadata};
+pub use mint::{Mint, TransferFeeConfig, TransferFeeError};
+pub use multisig::{Authority, Multisig};
+pub use nft::{mint_nft, transfer_nft, Nft, NftError};
+pub use nonce::NonceAccount;
+pub use oracle::{PriceOracle, ORACLE_PROGRAM_ID, PRICE_SCALE};
+pub use pda::{derive_pda, find_program_address, get_associated_token_address};
+pub use pubkey::{Pubkey, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID};
+pub use queue::TransactionQueue;
+pub use realloc::DataBuffer;
+pub use registry::AccountRegistry;
+pub use rent::Rent;
+pub use signature::{Keypair, Signature, Signer, SignerError};
+pub use snapshot_file::{read_snapshot, write_snapshot, SnapshotFileError};
+pub use streaming::{stream_address, Stream};
+pub use transaction::{Instruction, Transaction, TransactionOutcome};
+pub use transaction_builder::{BuildError, BuildIssue, TransactionBuilder};
+pub use transfer_hook::{Blocklist, FeeSkim, HookAction, TransferHook, TransferHookContext, TransferHookError, TransferHooks};
+pub use ui_amount::{UiAmount, UiAmountError};
+pub use validator::{ValidationError, Validator};
+pub use versioned_transaction::{AccountLookup, TransactionV0, VersionedTransaction};
+pub use versioning::{TokenAccountV2, Versioned};
+pub use vesting::{vesting_address, VestingError, VestingSchedule};
+pub use wallet::{Holding, Portfolio, Wallet};
 
 // ===============================
 // 1. 基础 Trait 定义和实现
@@ -16,12 +119,65 @@ pub trait Summary {
     }
 }
 
+// Token账户的状态：被冻结的账户不能转入或转出
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountState {
+    Initialized,
+    Frozen,
+}
+
+impl BorshSerialize for AccountState {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        match self {
+            AccountState::Initialized => 0u8.serialize(out),
+            AccountState::Frozen => 1u8.serialize(out),
+        }
+    }
+}
+
+impl BorshDeserialize for AccountState {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        match u8::deserialize(buf)? {
+            0 => Ok(AccountState::Initialized),
+            1 => Ok(AccountState::Frozen),
+            tag => Err(BorshError::InvalidEnumTag(tag)),
+        }
+    }
+}
+
 // 模拟Solana账户结构
 #[derive(Debug, Clone, PartialEq)]
 pub struct TokenAccount {
-    pub mint: String,     // 在实际Solana中是Pubkey
-    pub owner: String,    // 在实际Solana中是Pubkey
+    pub mint: Pubkey,
+    pub owner: Pubkey,
     pub amount: u64,
+    pub delegate: Option<Pubkey>, // 被授权代为转账的账户
+    pub delegated_amount: u64,    // delegate还能转出的数量
+    pub state: AccountState,
+}
+
+impl TokenAccount {
+    // 与SPL Token账户在链上占用的字节数一致
+    pub const LEN: usize = 165;
+
+    pub fn new(mint: Pubkey, owner: Pubkey, amount: u64) -> Self {
+        Self { mint, owner, amount, delegate: None, delegated_amount: 0, state: AccountState::Initialized }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.state == AccountState::Frozen
+    }
+
+    // 新的授权会覆盖之前的授权
+    pub fn approve(&mut self, delegate: Pubkey, amount: u64) {
+        self.delegate = Some(delegate);
+        self.delegated_amount = amount;
+    }
+
+    pub fn revoke(&mut self) {
+        self.delegate = None;
+        self.delegated_amount = 0;
+    }
 }
 
 // 为TokenAccount实现Summary trait
@@ -32,8 +188,33 @@ impl Summary for TokenAccount {
     }
 }
 
+// 按字段顺序序列化：mint(32) + owner(32) + amount(8) + delegate(1或33) + delegated_amount(8) + state(1)
+impl BorshSerialize for TokenAccount {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.mint.serialize(out);
+        self.owner.serialize(out);
+        self.amount.serialize(out);
+        self.delegate.serialize(out);
+        self.delegated_amount.serialize(out);
+        self.state.serialize(out);
+    }
+}
+
+impl BorshDeserialize for TokenAccount {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            mint: Pubkey::deserialize(buf)?,
+            owner: Pubkey::deserialize(buf)?,
+            amount: u64::deserialize(buf)?,
+            delegate: Option::deserialize(buf)?,
+            delegated_amount: u64::deserialize(buf)?,
+            state: AccountState::deserialize(buf)?,
+        })
+    }
+}
+
 // 另一个账户类型
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct UserAccount {
     pub username: String,
     pub balance: u64,
@@ -46,6 +227,25 @@ impl Summary for UserAccount {
     }
 }
 
+// username(4字节长度 + UTF-8字节) + balance(8) + created_at(8)，长度随用户名变化
+impl BorshSerialize for UserAccount {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.username.serialize(out);
+        self.balance.serialize(out);
+        self.created_at.serialize(out);
+    }
+}
+
+impl BorshDeserialize for UserAccount {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            username: String::deserialize(buf)?,
+            balance: u64::deserialize(buf)?,
+            created_at: i64::deserialize(buf)?,
+        })
+    }
+}
+
 // ===============================
 // 2. 特征作为函数参数
 // ===============================
@@ -62,9 +262,9 @@ pub fn validate_and_process<T: Summary + fmt::Debug>(account: &T) {
     println!("账户摘要: {}", account.summarize());
     
     if account.validate() {
-        println!("✓ 账户验证通过");
+        println!("{} 账户验证通过", style::ok());
     } else {
-        println!("✗ 账户验证失败");
+        println!("{} 账户验证失败", style::fail());
     }
 }
 
@@ -77,30 +277,108 @@ fn serialize_data<T: fmt::Debug>(data: T) -> String {
     format!("{:?}", data)
 }
 
+// 能放进账户里的数据：必须可以和字节互相转换，链上账户只保存字节
+pub trait AccountData: AccountSerialize + AccountDeserialize {}
+
+impl<T: AccountSerialize + AccountDeserialize> AccountData for T {}
+
 // 泛型结构体 - 用于包装不同类型的账户数据
 #[derive(Debug)]
-pub struct AccountWrapper<T> {
-    pub key: String,      // 在实际Solana中是Pubkey
+pub struct AccountWrapper<T: AccountData> {
+    pub key: Pubkey,
     pub data: T,
-    pub owner: String,    // 在实际Solana中是Pubkey
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub is_signer: bool,   // 该账户是否对交易签了名
+    pub is_writable: bool, // 该账户是否允许被修改
 }
 
-impl<T> AccountWrapper<T> {
-    pub fn new(key: String, data: T, owner: String) -> Self {
-        Self { key, data, owner }
+impl<T: AccountData> AccountWrapper<T> {
+    // 默认既不是签名者也不可写，需要时通过with_signer/with_writable开启
+    pub fn new(key: Pubkey, data: T, owner: Pubkey) -> Self {
+        Self { key, data, owner, lamports: 0, is_signer: false, is_writable: false }
     }
     
-    pub fn get_key(&self) -> &String {
+    pub fn with_lamports(mut self, lamports: u64) -> Self {
+        self.lamports = lamports;
+        self
+    }
+    
+    pub fn with_signer(mut self, is_signer: bool) -> Self {
+        self.is_signer = is_signer;
+        self
+    }
+    
+    pub fn with_writable(mut self, is_writable: bool) -> Self {
+        self.is_writable = is_writable;
+        self
+    }
+    
+    // 类似于Anchor中的Signer检查
+    pub fn assert_signer(&self) -> Result<(), TransactionResult> {
+        if self.is_signer {
+            Ok(())
+        } else {
+            Err(TransactionResult::MissingRequiredSignature)
+        }
+    }
+    
+    // 类似于Anchor中的#[account(mut)]检查
+    pub fn assert_writable(&self) -> Result<(), TransactionResult> {
+        if self.is_writable {
+            Ok(())
+        } else {
+            Err(TransactionResult::AccountNotWritable)
+        }
+    }
+    
+    pub fn get_key(&self) -> &Pubkey {
         &self.key
     }
     
     pub fn get_data(&self) -> &T {
         &self.data
     }
+    
+    // 把账户数据序列化成字节，相当于写回链上账户的data字段（开头是8字节的类型标识）
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.data.try_serialize()
+    }
+    
+    // 从字节中解析账户数据，类型标识不匹配时拒绝，类似于Anchor中的Account<'info, T>
+    pub fn from_bytes(key: Pubkey, owner: Pubkey, bytes: &[u8]) -> Result<Self, BorshError> {
+        Ok(Self::new(key, T::try_from_slice_checked(bytes)?, owner))
+    }
+}
+
+// 整个账户（包括元数据）的布局：key(32) + owner(32) + lamports(8) + is_signer(1) + is_writable(1) + data，
+// data放在最后，按T自己的Borsh布局编码，不带discriminator
+impl<T: AccountData> BorshSerialize for AccountWrapper<T> {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.key.serialize(out);
+        self.owner.serialize(out);
+        self.lamports.serialize(out);
+        self.is_signer.serialize(out);
+        self.is_writable.serialize(out);
+        self.data.serialize(out);
+    }
+}
+
+impl<T: AccountData> BorshDeserialize for AccountWrapper<T> {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            key: Pubkey::deserialize(buf)?,
+            owner: Pubkey::deserialize(buf)?,
+            lamports: u64::deserialize(buf)?,
+            is_signer: bool::deserialize(buf)?,
+            is_writable: bool::deserialize(buf)?,
+            data: T::deserialize(buf)?,
+        })
+    }
 }
 
 // 为泛型结构体实现trait
-impl<T: Summary> Summary for AccountWrapper<T> {
+impl<T: AccountData + Summary> Summary for AccountWrapper<T> {
     fn summarize(&self) -> String {
         format!("包装账户 [{}]: {}", self.key, self.data.summarize())
     }
@@ -111,25 +389,85 @@ impl<T: Summary> Summary for AccountWrapper<T> {
 // ===============================
 
 // 模拟CPI调用的结果
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TransactionResult {
     Success,
     InsufficientFunds,
     InvalidAccount,
+    IllegalOwner,             // 试图写入不属于本程序的账户
+    AccountNotWritable,       // 试图修改只读账户
+    AccountInUse,             // 账户正被其他交易锁定
+    MissingRequiredSignature, // 缺少必需的签名者
+    ArithmeticOverflow,       // 余额计算溢出
+    InvalidInstructionData,   // 指令数据无法解析
+    ComputeBudgetExceeded,    // compute units用完
+    CallDepthExceeded,        // CPI调用栈超过最大深度
+    AccountFrozen,            // Token账户已被冻结
+    MintAuthorityMismatch,    // 铸造指令给出的authority不是mint的mint authority
+    NonZeroBalance,           // 关闭的Token账户里还有代币
+    TransferHookRejected,     // mint上注册的转账钩子拒绝了这笔转账
+    SupplyCapExceeded,        // 铸造后的总供应量超过mint的max_supply
+    NothingToClaim,           // 没有可以领取的代币：锁仓还没有新释放，或者订单没有待结算的资金
+    InsufficientLiquidity,    // 池子储备为空，或者存入、取出、兑换的数量太小
+    SlippageExceeded,         // 兑换得到的数量少于min_amount_out
+    VotingClosed,             // 投票期已结束或提案已结算
+    VotingInProgress,         // 投票期还没结束，不能结算
+    NoVotingPower,            // 快照时这个Token账户没有治理代币
+    OrderBookFull,            // 订单簿挂单数达到上限
+    BorrowLimitExceeded,      // 借款后欠款超过抵押品按抵押率折算的上限
+    ObligationHealthy,        // 仓位欠款没有超过借款上限，不能清算
+    StalePrice,               // 预言机价格太久没有更新
+    CampaignEnded,            // 众筹已过截止slot，不再接受出资
+    CampaignInProgress,       // 众筹还没到截止slot，不能取款或退款
+    GoalNotReached,           // 众筹没有达到目标，发起人不能取款
+    GoalReached,              // 众筹达到了目标，支持者不能退款
+    BidTooLow,                // 出价低于起拍价，或者比最高价高出的不到最小加价
+    AuctionEnded,             // 拍卖已到end_slot，不再接受出价
+    AuctionInProgress,        // 拍卖还没到end_slot，不能结算
+    StreamCanceled,           // 支付流已经取消
 }
 
 // 通用的转账函数 - 类似于Solana中的CPI调用
-pub fn transfer_tokens<T: Summary + fmt::Debug>(
-    from: &mut T,
-    to: &mut T,
+// from和to都会被修改，因此必须可写；authority可以是单个签名者，也可以是达到门限的多签
+pub fn transfer_tokens<A: AccountData>(
+    from: &mut AccountWrapper<TokenAccount>,
+    to: &mut AccountWrapper<TokenAccount>,
+    authority: Authority<'_, A>,
     amount: u64,
 ) -> TransactionResult {
+    let checks = from
+        .assert_writable()
+        .and_then(|_| to.assert_writable())
+        .and_then(|_| authority.verify());
+    if let Err(result) = checks {
+        return result;
+    }
+    
+    // 不同mint的代币不能互转
+    if from.data.mint != to.data.mint {
+        return TransactionResult::InvalidAccount;
+    }
+    
+    // 冻结的账户既不能转出也不能转入
+    if from.data.is_frozen() || to.data.is_frozen() {
+        return TransactionResult::AccountFrozen;
+    }
+    
+    // 先算出两边的新余额，都成功后再写回，失败时账户保持不变
+    let amounts = safe_math::checked_sub(from.data.amount, amount)
+        .and_then(|from_amount| Ok((from_amount, safe_math::checked_add(to.data.amount, amount)?)));
+    let (from_amount, to_amount) = match amounts {
+        Ok(amounts) => amounts,
+        Err(error) => return error.into(),
+    };
+    
     println!("开始转账:");
     println!("  从: {}", from.summarize());
     println!("  到: {}", to.summarize());
     println!("  金额: {}", amount);
     
-    // 模拟转账逻辑
+    from.data.amount = from_amount;
+    to.data.amount = to_amount;
     TransactionResult::Success
 }
 
@@ -138,38 +476,658 @@ pub fn transfer_tokens<T: Summary + fmt::Debug>(
 // ===============================
 
 // 模拟程序指令
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ProgramInstruction {
     Initialize { initial_supply: u64 },
     Transfer { amount: u64 },
     Mint { amount: u64 },
+    AdvanceNonce, // 推进nonce账户，使用持久化nonce的交易必须以它作为第一条指令
+    Approve { amount: u64 }, // 授权delegate从Token账户转出最多amount
+    Revoke,                  // 撤销Token账户的授权
+    Burn { amount: u64 },    // 从Token账户销毁代币，mint的供应量同时减少
+    FreezeAccount,           // 冻结Token账户，需要mint的freeze authority签名
+    ThawAccount,             // 解冻Token账户
+    // 创建mint的元数据账户，需要mint authority签名
+    CreateMetadata { name: String, symbol: String, uri: String },
+    // 修改元数据，需要update authority签名
+    UpdateMetadata { name: String, symbol: String, uri: String },
+    // 关闭余额为0的Token账户，lamports退给接收账户
+    CloseTokenAccount,
+    // 托管程序：存入amount个代币A，等待对方用expected_amount个代币B交换
+    InitializeEscrow { amount: u64, expected_amount: u64 },
+    // 接受托管：amount是taker预期收到的代币A数量，和托管记录不一致时拒绝
+    Exchange { amount: u64 },
+    // 取消托管，代币A退回initializer
+    CancelEscrow,
+    // 锁仓程序：存入amount个代币，cliff_slot之前不能领取，从start_slot到end_slot线性释放
+    CreateVesting { amount: u64, start_slot: u64, cliff_slot: u64, end_slot: u64 },
+    // 受益人领取到当前slot为止已释放、还没领取的部分
+    Claim,
+    // AMM程序：创建交易对池子，兑换手续费为fee_basis_points
+    InitializePool { fee_basis_points: u16 },
+    // 按池子当前比例存入最多amount_a个A和amount_b个B，得到LP代币
+    AddLiquidity { amount_a: u64, amount_b: u64 },
+    // 销毁lp_amount个LP代币，按份额取回A和B
+    RemoveLiquidity { lp_amount: u64 },
+    // 按x*y=k兑换，换出的数量少于min_amount_out时失败
+    Swap { amount_in: u64, min_amount_out: u64 },
+    // 治理程序：为一个治理代币创建治理配置
+    CreateGovernance { quorum: u64, threshold_basis_points: u16, voting_slots: u64 },
+    // 创建提案，通过后执行instruction（指令里包含指令，需要Box）
+    CreateProposal { instruction: Box<Instruction> },
+    // 用快照时的代币余额投赞成或反对票
+    CastVote { approve: bool },
+    // 投票期结束后结算提案，通过时执行提案指令
+    FinalizeProposal,
+    // 把元数据的update authority交给new_authority，需要当前update authority签名
+    SetMetadataAuthority { new_authority: Pubkey },
+    // DEX程序：为一对base/quote代币创建订单簿市场
+    CreateMarket,
+    // 下单：以price买入或卖出quantity个base，能成交的部分立即成交，其余挂单
+    PlaceOrder { side: Side, price: u64, quantity: u64 },
+    // 撤销订单簿上剩余的部分
+    CancelOrder,
+    // 把订单成交得到和撤单退回的代币结算到owner的Token账户
+    SettleFunds,
+    // 模拟预言机：authority写入新价格
+    UpdatePrice { price: u64 },
+    // 借贷程序：创建抵押代币/借出代币市场，设置抵押率和清算奖励
+    InitLendingMarket { collateral_factor_bps: u16, liquidation_bonus_bps: u16 },
+    // 存入抵押品
+    DepositCollateral { amount: u64 },
+    // 按抵押品价值借出，欠款不能超过借款上限
+    Borrow { amount: u64 },
+    // 还款，超过欠款的部分不会转出
+    Repay { amount: u64 },
+    // 替超过借款上限的仓位还最多amount，拿走带奖励的抵押品
+    Liquidate { amount: u64 },
+    // 众筹程序：创建目标为goal、到deadline_slot截止的众筹
+    CreateCampaign { goal: u64, deadline_slot: u64 },
+    // 截止之前出资amount
+    Contribute { amount: u64 },
+    // 众筹达到目标并截止后，发起人取走全部筹款；支付流的接收者取出已经释放的部分
+    Withdraw,
+    // 没达到目标并截止后，支持者取回自己的出资；拍卖中被超过的出价者取回出价
+    Refund,
+    // 拍卖程序：存入NFT开始拍卖，起拍价starting_bid，每次至少加价min_increment，到end_slot截止
+    CreateAuction { starting_bid: u64, min_increment: u64, end_slot: u64 },
+    // 出价amount，被超过的出价记到原出价者的出价记录上，由他用Refund取回
+    PlaceBid { amount: u64 },
+    // 截止后把NFT转给最高出价者，出价转给卖家
+    SettleAuction,
+    // 支付流程序：存入amount个代币，从start_slot开始每个slot释放rate_per_slot个给接收者
+    CreateStream { amount: u64, rate_per_slot: u64, start_slot: u64 },
+    // 发送者取消支付流，已释放的给接收者，剩下的退回
+    CancelStream,
+}
+
+// 和Borsh对枚举的编码一致：先写1字节的变体序号，再写字段
+impl BorshSerialize for ProgramInstruction {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        match self {
+            ProgramInstruction::Initialize { initial_supply } => {
+                0u8.serialize(out);
+                initial_supply.serialize(out);
+            }
+            ProgramInstruction::Transfer { amount } => {
+                1u8.serialize(out);
+                amount.serialize(out);
+            }
+            ProgramInstruction::Mint { amount } => {
+                2u8.serialize(out);
+                amount.serialize(out);
+            }
+            ProgramInstruction::AdvanceNonce => 3u8.serialize(out),
+            ProgramInstruction::Approve { amount } => {
+                4u8.serialize(out);
+                amount.serialize(out);
+            }
+            ProgramInstruction::Revoke => 5u8.serialize(out),
+            ProgramInstruction::Burn { amount } => {
+                6u8.serialize(out);
+                amount.serialize(out);
+            }
+            ProgramInstruction::FreezeAccount => 7u8.serialize(out),
+            ProgramInstruction::ThawAccount => 8u8.serialize(out),
+            ProgramInstruction::CreateMetadata { name, symbol, uri } => {
+                9u8.serialize(out);
+                name.serialize(out);
+                symbol.serialize(out);
+                uri.serialize(out);
+            }
+            ProgramInstruction::UpdateMetadata { name, symbol, uri } => {
+                10u8.serialize(out);
+                name.serialize(out);
+                symbol.serialize(out);
+                uri.serialize(out);
+            }
+            ProgramInstruction::CloseTokenAccount => 11u8.serialize(out),
+            ProgramInstruction::InitializeEscrow { amount, expected_amount } => {
+                12u8.serialize(out);
+                amount.serialize(out);
+                expected_amount.serialize(out);
+            }
+            ProgramInstruction::Exchange { amount } => {
+                13u8.serialize(out);
+                amount.serialize(out);
+            }
+            ProgramInstruction::CancelEscrow => 14u8.serialize(out),
+            ProgramInstruction::CreateVesting { amount, start_slot, cliff_slot, end_slot } => {
+                15u8.serialize(out);
+                amount.serialize(out);
+                start_slot.serialize(out);
+                cliff_slot.serialize(out);
+                end_slot.serialize(out);
+            }
+            ProgramInstruction::Claim => 16u8.serialize(out),
+            ProgramInstruction::InitializePool { fee_basis_points } => {
+                17u8.serialize(out);
+                fee_basis_points.serialize(out);
+            }
+            ProgramInstruction::AddLiquidity { amount_a, amount_b } => {
+                18u8.serialize(out);
+                amount_a.serialize(out);
+                amount_b.serialize(out);
+            }
+            ProgramInstruction::RemoveLiquidity { lp_amount } => {
+                19u8.serialize(out);
+                lp_amount.serialize(out);
+            }
+            ProgramInstruction::Swap { amount_in, min_amount_out } => {
+                20u8.serialize(out);
+                amount_in.serialize(out);
+                min_amount_out.serialize(out);
+            }
+            ProgramInstruction::CreateGovernance { quorum, threshold_basis_points, voting_slots } => {
+                21u8.serialize(out);
+                quorum.serialize(out);
+                threshold_basis_points.serialize(out);
+                voting_slots.serialize(out);
+            }
+            ProgramInstruction::CreateProposal { instruction } => {
+                22u8.serialize(out);
+                instruction.serialize(out);
+            }
+            ProgramInstruction::CastVote { approve } => {
+                23u8.serialize(out);
+                approve.serialize(out);
+            }
+            ProgramInstruction::FinalizeProposal => 24u8.serialize(out),
+            ProgramInstruction::SetMetadataAuthority { new_authority } => {
+                25u8.serialize(out);
+                new_authority.serialize(out);
+            }
+            ProgramInstruction::CreateMarket => 26u8.serialize(out),
+            ProgramInstruction::PlaceOrder { side, price, quantity } => {
+                27u8.serialize(out);
+                side.serialize(out);
+                price.serialize(out);
+                quantity.serialize(out);
+            }
+            ProgramInstruction::CancelOrder => 28u8.serialize(out),
+            ProgramInstruction::SettleFunds => 29u8.serialize(out),
+            ProgramInstruction::UpdatePrice { price } => {
+                30u8.serialize(out);
+                price.serialize(out);
+            }
+            ProgramInstruction::InitLendingMarket { collateral_factor_bps, liquidation_bonus_bps } => {
+                31u8.serialize(out);
+                collateral_factor_bps.serialize(out);
+                liquidation_bonus_bps.serialize(out);
+            }
+            ProgramInstruction::DepositCollateral { amount } => {
+                32u8.serialize(out);
+                amount.serialize(out);
+            }
+            ProgramInstruction::Borrow { amount } => {
+                33u8.serialize(out);
+                amount.serialize(out);
+            }
+            ProgramInstruction::Repay { amount } => {
+                34u8.serialize(out);
+                amount.serialize(out);
+            }
+            ProgramInstruction::Liquidate { amount } => {
+                35u8.serialize(out);
+                amount.serialize(out);
+            }
+            ProgramInstruction::CreateCampaign { goal, deadline_slot } => {
+                36u8.serialize(out);
+                goal.serialize(out);
+                deadline_slot.serialize(out);
+            }
+            ProgramInstruction::Contribute { amount } => {
+                37u8.serialize(out);
+                amount.serialize(out);
+            }
+            ProgramInstruction::Withdraw => 38u8.serialize(out),
+            ProgramInstruction::Refund => 39u8.serialize(out),
+            ProgramInstruction::CreateAuction { starting_bid, min_increment, end_slot } => {
+                40u8.serialize(out);
+                starting_bid.serialize(out);
+                min_increment.serialize(out);
+                end_slot.serialize(out);
+            }
+            ProgramInstruction::PlaceBid { amount } => {
+                41u8.serialize(out);
+                amount.serialize(out);
+            }
+            ProgramInstruction::SettleAuction => 42u8.serialize(out),
+            ProgramInstruction::CreateStream { amount, rate_per_slot, start_slot } => {
+                43u8.serialize(out);
+                amount.serialize(out);
+                rate_per_slot.serialize(out);
+                start_slot.serialize(out);
+            }
+            ProgramInstruction::CancelStream => 44u8.serialize(out),
+        }
+    }
+}
+
+impl BorshDeserialize for ProgramInstruction {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        match u8::deserialize(buf)? {
+            0 => Ok(ProgramInstruction::Initialize { initial_supply: u64::deserialize(buf)? }),
+            1 => Ok(ProgramInstruction::Transfer { amount: u64::deserialize(buf)? }),
+            2 => Ok(ProgramInstruction::Mint { amount: u64::deserialize(buf)? }),
+            3 => Ok(ProgramInstruction::AdvanceNonce),
+            4 => Ok(ProgramInstruction::Approve { amount: u64::deserialize(buf)? }),
+            5 => Ok(ProgramInstruction::Revoke),
+            6 => Ok(ProgramInstruction::Burn { amount: u64::deserialize(buf)? }),
+            7 => Ok(ProgramInstruction::FreezeAccount),
+            8 => Ok(ProgramInstruction::ThawAccount),
+            9 => Ok(ProgramInstruction::CreateMetadata {
+                name: String::deserialize(buf)?,
+                symbol: String::deserialize(buf)?,
+                uri: String::deserialize(buf)?,
+            }),
+            10 => Ok(ProgramInstruction::UpdateMetadata {
+                name: String::deserialize(buf)?,
+                symbol: String::deserialize(buf)?,
+                uri: String::deserialize(buf)?,
+            }),
+            11 => Ok(ProgramInstruction::CloseTokenAccount),
+            12 => Ok(ProgramInstruction::InitializeEscrow {
+                amount: u64::deserialize(buf)?,
+                expected_amount: u64::deserialize(buf)?,
+            }),
+            13 => Ok(ProgramInstruction::Exchange { amount: u64::deserialize(buf)? }),
+            14 => Ok(ProgramInstruction::CancelEscrow),
+            15 => Ok(ProgramInstruction::CreateVesting {
+                amount: u64::deserialize(buf)?,
+                start_slot: u64::deserialize(buf)?,
+                cliff_slot: u64::deserialize(buf)?,
+                end_slot: u64::deserialize(buf)?,
+            }),
+            16 => Ok(ProgramInstruction::Claim),
+            17 => Ok(ProgramInstruction::InitializePool { fee_basis_points: u16::deserialize(buf)? }),
+            18 => Ok(ProgramInstruction::AddLiquidity { amount_a: u64::deserialize(buf)?, amount_b: u64::deserialize(buf)? }),
+            19 => Ok(ProgramInstruction::RemoveLiquidity { lp_amount: u64::deserialize(buf)? }),
+            20 => Ok(ProgramInstruction::Swap { amount_in: u64::deserialize(buf)?, min_amount_out: u64::deserialize(buf)? }),
+            21 => Ok(ProgramInstruction::CreateGovernance {
+                quorum: u64::deserialize(buf)?,
+                threshold_basis_points: u16::deserialize(buf)?,
+                voting_slots: u64::deserialize(buf)?,
+            }),
+            22 => Ok(ProgramInstruction::CreateProposal { instruction: Box::new(Instruction::deserialize(buf)?) }),
+            23 => Ok(ProgramInstruction::CastVote { approve: bool::deserialize(buf)? }),
+            24 => Ok(ProgramInstruction::FinalizeProposal),
+            25 => Ok(ProgramInstruction::SetMetadataAuthority { new_authority: Pubkey::deserialize(buf)? }),
+            26 => Ok(ProgramInstruction::CreateMarket),
+            27 => Ok(ProgramInstruction::PlaceOrder {
+                side: Side::deserialize(buf)?,
+                price: u64::deserialize(buf)?,
+                quantity: u64::deserialize(buf)?,
+            }),
+            28 => Ok(ProgramInstruction::CancelOrder),
+            29 => Ok(ProgramInstruction::SettleFunds),
+            30 => Ok(ProgramInstruction::UpdatePrice { price: u64::deserialize(buf)? }),
+            31 => Ok(ProgramInstruction::InitLendingMarket {
+                collateral_factor_bps: u16::deserialize(buf)?,
+                liquidation_bonus_bps: u16::deserialize(buf)?,
+            }),
+            32 => Ok(ProgramInstruction::DepositCollateral { amount: u64::deserialize(buf)? }),
+            33 => Ok(ProgramInstruction::Borrow { amount: u64::deserialize(buf)? }),
+            34 => Ok(ProgramInstruction::Repay { amount: u64::deserialize(buf)? }),
+            35 => Ok(ProgramInstruction::Liquidate { amount: u64::deserialize(buf)? }),
+            36 => Ok(ProgramInstruction::CreateCampaign { goal: u64::deserialize(buf)?, deadline_slot: u64::deserialize(buf)? }),
+            37 => Ok(ProgramInstruction::Contribute { amount: u64::deserialize(buf)? }),
+            38 => Ok(ProgramInstruction::Withdraw),
+            39 => Ok(ProgramInstruction::Refund),
+            40 => Ok(ProgramInstruction::CreateAuction {
+                starting_bid: u64::deserialize(buf)?,
+                min_increment: u64::deserialize(buf)?,
+                end_slot: u64::deserialize(buf)?,
+            }),
+            41 => Ok(ProgramInstruction::PlaceBid { amount: u64::deserialize(buf)? }),
+            42 => Ok(ProgramInstruction::SettleAuction),
+            43 => Ok(ProgramInstruction::CreateStream {
+                amount: u64::deserialize(buf)?,
+                rate_per_slot: u64::deserialize(buf)?,
+                start_slot: u64::deserialize(buf)?,
+            }),
+            44 => Ok(ProgramInstruction::CancelStream),
+            tag => Err(BorshError::InvalidEnumTag(tag)),
+        }
+    }
+}
+
+impl ProgramInstruction {
+    // 指令数据在链上就是这样一串字节
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.serialize(&mut out);
+        out
+    }
+
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, BorshError> {
+        Self::try_from_slice(data)
+    }
+}
+
+// 账户元信息 - 类似于Solana的AccountMeta，描述指令如何使用某个账户
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccountMeta {
+    pub pubkey: Pubkey,
+    pub owner: Pubkey, // 拥有该账户的程序
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl AccountMeta {
+    // 可写账户
+    pub fn new(pubkey: Pubkey, owner: Pubkey, is_signer: bool) -> Self {
+        Self { pubkey, owner, is_signer, is_writable: true }
+    }
+
+    // 只读账户
+    pub fn new_readonly(pubkey: Pubkey, owner: Pubkey, is_signer: bool) -> Self {
+        Self { pubkey, owner, is_signer, is_writable: false }
+    }
+}
+
+impl BorshSerialize for AccountMeta {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.pubkey.serialize(out);
+        self.owner.serialize(out);
+        self.is_signer.serialize(out);
+        self.is_writable.serialize(out);
+    }
+}
+
+impl BorshDeserialize for AccountMeta {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            pubkey: Pubkey::deserialize(buf)?,
+            owner: Pubkey::deserialize(buf)?,
+            is_signer: bool::deserialize(buf)?,
+            is_writable: bool::deserialize(buf)?,
+        })
+    }
+}
+
+// 批量执行时遇到失败指令的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchMode {
+    StopOnError,     // 遇到第一条失败的指令就停止，后面的指令不再执行
+    ContinueOnError, // 每条指令都执行，互不影响
+}
+
+// 批量执行提前停止的原因，作为ControlFlow::Break带回给调用方
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchHalt {
+    Failed { index: usize, result: TransactionResult }, // 第index条指令失败
+    ComputeLimit { consumed: u64 },                      // 累计消耗的compute units达到了调用方的上限
+}
+
+// 批量执行的结果：results[i]是第i条指令的结果，被跳过的指令没有结果
+#[derive(Debug, PartialEq)]
+pub struct BatchReport {
+    pub results: Vec<TransactionResult>,
+    pub skipped: usize,
+    pub compute_units_consumed: u64,
+}
+
+impl BatchReport {
+    pub fn succeeded(&self) -> usize {
+        self.results.iter().filter(|result| **result == TransactionResult::Success).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.succeeded()
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "成功{}条，失败{}条，跳过{}条，消耗{} compute units",
+            self.succeeded(),
+            self.failed(),
+            self.skipped,
+            self.compute_units_consumed
+        )
+    }
 }
 
 // 程序处理器 - 使用泛型处理不同类型的账户
 pub struct ProgramProcessor;
 
 impl ProgramProcessor {
-    pub fn process_instruction<T: Summary + fmt::Debug>(
-        instruction: ProgramInstruction,
-        accounts: Vec<&T>,
+    // 执行指令前的账户校验，类似于Anchor中#[account(mut)]和Signer的检查：
+    // 1. 第一个账户是指令要修改的账户，必须可写
+    // 2. 只有账户的owner程序才能修改它
+    // 3. 至少要有一个签名者授权这条指令
+    pub fn validate_accounts(program_id: &Pubkey, metas: &[AccountMeta]) -> TransactionResult {
+        match metas.first() {
+            Some(meta) if meta.is_writable => {}
+            _ => return TransactionResult::InvalidAccount,
+        }
+
+        if metas.iter().any(|meta| meta.is_writable && meta.owner != *program_id) {
+            return TransactionResult::IllegalOwner;
+        }
+
+        if !metas.iter().any(|meta| meta.is_signer) {
+            return TransactionResult::MissingRequiredSignature;
+        }
+
+        TransactionResult::Success
+    }
+
+    // 程序入口，类似于Solana的entrypoint：指令以字节形式传入，
+    // 根据第一个字节解析出对应的指令，再分发给各自的处理函数
+    pub fn process<T: Summary + fmt::Debug>(
+        program_id: &Pubkey,
+        accounts: Vec<(&T, AccountMeta)>,
+        instruction_data: &[u8],
     ) -> TransactionResult {
-        match instruction {
-            ProgramInstruction::Initialize { initial_supply } => {
-                println!("初始化程序，初始供应量: {}", initial_supply);
-                for account in accounts {
-                    println!("  处理账户: {}", account.summarize());
-                }
-                TransactionResult::Success
-            },
-            ProgramInstruction::Transfer { amount } => {
-                println!("执行转账，金额: {}", amount);
-                TransactionResult::Success
-            },
-            ProgramInstruction::Mint { amount } => {
-                println!("铸造代币，数量: {}", amount);
-                TransactionResult::Success
-            },
+        // 没有调用方来收集日志，执行完直接打印出来
+        let mut budget = ComputeBudget::default();
+        let result = Self::process_with_budget(&mut budget, program_id, accounts, instruction_data);
+        print!("{}", budget.logs());
+        result
+    }
+    
+    // 与process相同，但执行指令和写日志都会从budget中扣除compute units，日志收集在budget中
+    pub fn process_with_budget<T: Summary + fmt::Debug>(
+        budget: &mut ComputeBudget,
+        program_id: &Pubkey,
+        accounts: Vec<(&T, AccountMeta)>,
+        instruction_data: &[u8],
+    ) -> TransactionResult {
+        let instruction = match ProgramInstruction::try_from_bytes(instruction_data) {
+            Ok(instruction) => instruction,
+            Err(_) => return TransactionResult::InvalidInstructionData,
+        };
+
+        let metas: Vec<AccountMeta> = accounts.iter().map(|(_, meta)| *meta).collect();
+        let validation = Self::validate_accounts(program_id, &metas);
+        if validation != TransactionResult::Success {
+            return validation;
+        }
+
+        let result = budget.consume(compute::INSTRUCTION_COST).and_then(|_| match instruction {
+            ProgramInstruction::Initialize { initial_supply } => Self::process_initialize(budget, &accounts, initial_supply),
+            ProgramInstruction::Transfer { amount } => Self::process_transfer(budget, &accounts, amount),
+            ProgramInstruction::Mint { amount } => Self::process_mint(budget, &accounts, amount),
+            ProgramInstruction::AdvanceNonce => msg!(budget, "推进nonce"),
+            ProgramInstruction::Approve { amount } => msg!(budget, "授权delegate转出{}", amount),
+            ProgramInstruction::Revoke => msg!(budget, "撤销授权"),
+            ProgramInstruction::Burn { amount } => msg!(budget, "销毁{}", amount),
+            ProgramInstruction::FreezeAccount => msg!(budget, "冻结账户"),
+            ProgramInstruction::ThawAccount => msg!(budget, "解冻账户"),
+            ProgramInstruction::CreateMetadata { name, symbol, .. } => msg!(budget, "创建元数据: {} ({})", name, symbol),
+            ProgramInstruction::UpdateMetadata { name, symbol, .. } => msg!(budget, "修改元数据: {} ({})", name, symbol),
+            ProgramInstruction::CloseTokenAccount => msg!(budget, "关闭Token账户"),
+            ProgramInstruction::InitializeEscrow { amount, expected_amount } => {
+                msg!(budget, "创建托管: 存入{}，换取{}", amount, expected_amount)
+            }
+            ProgramInstruction::Exchange { amount } => msg!(budget, "接受托管: 换出{}", amount),
+            ProgramInstruction::CancelEscrow => msg!(budget, "取消托管"),
+            ProgramInstruction::CreateVesting { amount, start_slot, end_slot, .. } => {
+                msg!(budget, "创建锁仓: {}，slot {}到{}线性释放", amount, start_slot, end_slot)
+            }
+            ProgramInstruction::Claim => msg!(budget, "领取已释放的锁仓代币"),
+            ProgramInstruction::InitializePool { fee_basis_points } => msg!(budget, "创建池子，手续费{}基点", fee_basis_points),
+            ProgramInstruction::AddLiquidity { amount_a, amount_b } => msg!(budget, "存入流动性: 最多{}个A和{}个B", amount_a, amount_b),
+            ProgramInstruction::RemoveLiquidity { lp_amount } => msg!(budget, "取出流动性: 销毁{}个LP", lp_amount),
+            ProgramInstruction::Swap { amount_in, min_amount_out } => {
+                msg!(budget, "兑换: 输入{}，至少换出{}", amount_in, min_amount_out)
+            }
+            ProgramInstruction::CreateGovernance { quorum, threshold_basis_points, voting_slots } => {
+                msg!(budget, "创建治理: quorum={}，通过比例{}基点，投票期{}个slot", quorum, threshold_basis_points, voting_slots)
+            }
+            ProgramInstruction::CreateProposal { instruction } => msg!(budget, "创建提案: 通过后调用{}", instruction.program_id),
+            ProgramInstruction::CastVote { approve } => msg!(budget, "投票: {}", if approve { "赞成" } else { "反对" }),
+            ProgramInstruction::FinalizeProposal => msg!(budget, "结算提案"),
+            ProgramInstruction::SetMetadataAuthority { new_authority } => msg!(budget, "移交元数据update authority: {}", new_authority),
+            ProgramInstruction::CreateMarket => msg!(budget, "创建订单簿市场"),
+            ProgramInstruction::PlaceOrder { side, price, quantity } => msg!(budget, "下单: {:?} {}个，价格{}", side, quantity, price),
+            ProgramInstruction::CancelOrder => msg!(budget, "撤单"),
+            ProgramInstruction::SettleFunds => msg!(budget, "结算订单资金"),
+            ProgramInstruction::UpdatePrice { price } => msg!(budget, "更新价格: {}", price),
+            ProgramInstruction::InitLendingMarket { collateral_factor_bps, liquidation_bonus_bps } => {
+                msg!(budget, "创建借贷市场: 抵押率{}基点，清算奖励{}基点", collateral_factor_bps, liquidation_bonus_bps)
+            }
+            ProgramInstruction::DepositCollateral { amount } => msg!(budget, "存入抵押品: {}", amount),
+            ProgramInstruction::Borrow { amount } => msg!(budget, "借款: {}", amount),
+            ProgramInstruction::Repay { amount } => msg!(budget, "还款: {}", amount),
+            ProgramInstruction::Liquidate { amount } => msg!(budget, "清算: 代还{}", amount),
+            ProgramInstruction::CreateCampaign { goal, deadline_slot } => msg!(budget, "创建众筹: 目标{}，slot {}截止", goal, deadline_slot),
+            ProgramInstruction::Contribute { amount } => msg!(budget, "出资: {}", amount),
+            ProgramInstruction::Withdraw => msg!(budget, "取款"),
+            ProgramInstruction::Refund => msg!(budget, "退回出资"),
+            ProgramInstruction::CreateAuction { starting_bid, min_increment, end_slot } => {
+                msg!(budget, "创建拍卖: 起拍价{}，最小加价{}，slot {}截止", starting_bid, min_increment, end_slot)
+            }
+            ProgramInstruction::PlaceBid { amount } => msg!(budget, "出价: {}", amount),
+            ProgramInstruction::SettleAuction => msg!(budget, "结算拍卖"),
+            ProgramInstruction::CreateStream { amount, rate_per_slot, start_slot } => {
+                msg!(budget, "创建支付流: {}个，从slot {}开始每个slot释放{}", amount, start_slot, rate_per_slot)
+            }
+            ProgramInstruction::CancelStream => msg!(budget, "取消支付流"),
+        });
+        match result {
+            Ok(()) => TransactionResult::Success,
+            Err(result) => result,
+        }
+    }
+    
+    fn process_initialize<T: Summary>(
+        budget: &mut ComputeBudget,
+        accounts: &[(&T, AccountMeta)],
+        initial_supply: u64,
+    ) -> Result<(), TransactionResult> {
+        msg!(budget, "初始化程序，初始供应量: {}", initial_supply)?;
+        for (account, _) in accounts {
+            msg!(budget, "  处理账户: {}", account.summarize())?;
         }
+        Ok(())
+    }
+    
+    fn process_transfer<T>(budget: &mut ComputeBudget, _accounts: &[(&T, AccountMeta)], amount: u64) -> Result<(), TransactionResult> {
+        msg!(budget, "执行转账，金额: {}", amount)
+    }
+    
+    fn process_mint<T>(budget: &mut ComputeBudget, _accounts: &[(&T, AccountMeta)], amount: u64) -> Result<(), TransactionResult> {
+        msg!(budget, "铸造代币，数量: {}", amount)
+    }
+    
+    // 先为所有账户加锁再执行，执行完毕后释放；和其他交易冲突时返回AccountInUse
+    pub fn process_instruction_locked<T: Summary + fmt::Debug>(
+        locks: &mut AccountLockManager,
+        program_id: &Pubkey,
+        accounts: Vec<(&T, AccountMeta)>,
+        instruction_data: &[u8],
+    ) -> TransactionResult {
+        let metas: Vec<AccountMeta> = accounts.iter().map(|(_, meta)| *meta).collect();
+        if let Err(result) = locks.lock_accounts(&metas) {
+            return result;
+        }
+        let result = Self::process(program_id, accounts, instruction_data);
+        locks.unlock_accounts(&metas);
+        result
+    }
+    
+    // 依次执行一批指令，每条指令使用独立的compute budget。
+    // 指令只携带账户的AccountMeta，账户数据按pubkey从accounts中查找，找不到时该指令返回InvalidAccount
+    pub fn process_batch<T: Summary + fmt::Debug>(
+        accounts: &BTreeMap<Pubkey, T>,
+        instructions: Vec<Instruction>,
+        mode: BatchMode,
+    ) -> BatchReport {
+        let (report, _) = Self::process_batch_until(accounts, instructions, |index, result, _| match mode {
+            BatchMode::StopOnError if *result != TransactionResult::Success => {
+                ControlFlow::Break(BatchHalt::Failed { index, result: result.clone() })
+            }
+            _ => ControlFlow::Continue(()),
+        });
+        report
+    }
+
+    // 每条指令执行完后把(序号, 结果, 累计消耗的compute units)交给inspect：
+    // 返回Continue继续执行下一条，返回Break则停止，剩下的指令计入skipped，Break的值原样返回。
+    // 停止不一定是出错（比如已经用完了预算），所以用ControlFlow而不是Result
+    pub fn process_batch_until<T, B, F>(
+        accounts: &BTreeMap<Pubkey, T>,
+        instructions: Vec<Instruction>,
+        mut inspect: F,
+    ) -> (BatchReport, ControlFlow<B>)
+    where
+        T: Summary + fmt::Debug,
+        F: FnMut(usize, &TransactionResult, u64) -> ControlFlow<B>,
+    {
+        let total = instructions.len();
+        let mut results = Vec::with_capacity(total);
+        let mut compute_units_consumed = 0;
+        let flow = instructions.into_iter().enumerate().try_for_each(|(index, instruction)| {
+            let resolved: Option<Vec<(&T, AccountMeta)>> = instruction
+                .accounts
+                .iter()
+                .map(|meta| accounts.get(&meta.pubkey).map(|account| (account, *meta)))
+                .collect();
+            let result = match resolved {
+                Some(resolved) => {
+                    let mut budget = ComputeBudget::default();
+                    let result = Self::process_with_budget(
+                        &mut budget,
+                        &instruction.program_id,
+                        resolved,
+                        &instruction.data.to_bytes(),
+                    );
+                    print!("{}", budget.logs());
+                    compute_units_consumed += budget.consumed();
+                    result
+                }
+                None => TransactionResult::InvalidAccount,
+            };
+            let flow = inspect(index, &result, compute_units_consumed);
+            results.push(result);
+            flow
+        });
+        (BatchReport { skipped: total - results.len(), results, compute_units_consumed }, flow)
     }
 }
 
@@ -177,16 +1135,17 @@ impl ProgramProcessor {
 // 6. 主函数 - 演示所有概念
 // ===============================
 
-fn main() {
+fn main() -> Result<(), Box<dyn Error>> {
+    // 传入--ascii（或终端不支持UTF-8）时只输出ASCII符号
+    let args: Vec<String> = std::env::args().collect();
+    style::init(&args);
+    
     println!("=== Solana合约开发中的Trait与泛型基础 ===\n");
     
     // 1. 基础trait使用
     println!("1. 基础Trait使用:");
-    let token_account = TokenAccount {
-        mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
-        owner: "3LKJFWgogznfBhWUk6QqKi9ePeAg6x7J4XR9fFTGw2vG".to_string(),
-        amount: 1000,
-    };
+    let usdc_mint: Pubkey = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".parse()?;
+    let token_account = TokenAccount::new(usdc_mint, "3LKJFWgogznfBhWUk6QqKi9ePeAg6x7J4XR9fFTGw2vG".parse()?, 1000);
     
     let user_account = UserAccount {
         username: "alice".to_string(),
@@ -214,33 +1173,65 @@ fn main() {
     
     // 4. 泛型结构体
     println!("4. 泛型结构体:");
+    // System Program的地址是32个0字节，base58编码后就是32个'1'
+    let system_program = SYSTEM_PROGRAM_ID;
+    let my_program = Pubkey::new_unique();
     let wrapped_token = AccountWrapper::new(
-        "TokenAccount123".to_string(),
+        Pubkey::new_unique(),
         token_account.clone(),
-        "SystemProgram".to_string(),
+        system_program,
     );
     
     let wrapped_user = AccountWrapper::new(
-        "UserAccount456".to_string(),
+        Pubkey::new_unique(),
         user_account.clone(),
-        "MyProgram".to_string(),
+        my_program,
     );
     
     println!("包装的Token账户: {}", wrapped_token.summarize());
     println!("包装的User账户: {}", wrapped_user.summarize());
+    
+    // 账户数据和字节互相转换：链上账户只保存字节
+    let user_bytes = wrapped_user.to_bytes();
+    println!("User账户序列化为{}字节", user_bytes.len());
+    match AccountWrapper::<UserAccount>::from_bytes(wrapped_user.key, wrapped_user.owner, &user_bytes) {
+        Ok(restored) => println!("反序列化结果一致: {}", restored.data == wrapped_user.data),
+        Err(e) => println!("{} 反序列化失败: {}", style::fail(), e),
+    }
     println!();
     
     // 5. 模拟转账
     println!("5. 模拟转账:");
-    let mut from_account = token_account.clone();
-    let mut to_account = TokenAccount {
-        mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
-        owner: "7xKJ2nGnWWvR9mHsq4g8X3T2vE6UyB1RfGfVwYnPt9QE".to_string(),
-        amount: 500,
-    };
+    let mut from_account = AccountWrapper::new(Pubkey::new_unique(), token_account.clone(), TOKEN_PROGRAM_ID)
+        .with_writable(true);
+    let mut to_account = AccountWrapper::new(
+        Pubkey::new_unique(),
+        TokenAccount::new(usdc_mint, "7xKJ2nGnWWvR9mHsq4g8X3T2vE6UyB1RfGfVwYnPt9QE".parse()?, 500),
+        TOKEN_PROGRAM_ID,
+    )
+    .with_writable(true);
+    let authority = AccountWrapper::new(token_account.owner, user_account.clone(), system_program).with_signer(true);
     
-    let result = transfer_tokens(&mut from_account, &mut to_account, 100);
+    let result = transfer_tokens(&mut from_account, &mut to_account, Authority::Single(&authority), 100);
     println!("转账结果: {:?}", result);
+    
+    // authority没有签名时转账被拒绝
+    let unsigned_authority = AccountWrapper::new(token_account.owner, user_account.clone(), system_program);
+    let result = transfer_tokens(&mut from_account, &mut to_account, Authority::Single(&unsigned_authority), 100);
+    println!("未签名的转账结果: {:?}", result);
+    
+    // 2-of-3多签授权
+    let council: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let multisig = Multisig::new(2, council.clone())?;
+    let result = transfer_tokens(&mut from_account, &mut to_account, Authority::<()>::Multisig(&multisig, &council[..2]), 100);
+    println!("多签(2/3)转账结果: {:?}", result);
+    let result = transfer_tokens(&mut from_account, &mut to_account, Authority::<()>::Multisig(&multisig, &council[..1]), 100);
+    println!("多签(1/3)转账结果: {:?}", result);
+    println!("转账后余额: from={}, to={}", from_account.data.amount, to_account.data.amount);
+    
+    // 余额不足时两边余额都不变
+    let result = transfer_tokens(&mut from_account, &mut to_account, Authority::Single(&authority), u64::MAX);
+    println!("超额转账结果: {:?}", result);
     println!();
     
     // 6. 程序指令处理
@@ -248,15 +1239,60 @@ fn main() {
     let initialize_instruction = ProgramInstruction::Initialize { initial_supply: 1000000 };
     let transfer_instruction = ProgramInstruction::Transfer { amount: 100 };
     
+    // 指令在链上以字节传输：1字节变体序号 + 字段，由程序入口解码后分发
+    let transfer_data = transfer_instruction.to_bytes();
+    println!("Transfer指令数据: {:02x?}", transfer_data);
+    
     // 由于不同类型无法放在同一个Vec中，我们分别处理
-    let token_accounts = vec![&token_account];
-    let user_accounts = vec![&user_account];
+    // 每个账户都附带AccountMeta：是否签名、是否可写、属于哪个程序
+    let token_accounts = vec![(&token_account, AccountMeta::new(Pubkey::new_unique(), my_program, true))];
+    let user_accounts = vec![(&user_account, AccountMeta::new(Pubkey::new_unique(), my_program, true))];
     
-    let result1 = ProgramProcessor::process_instruction(initialize_instruction, token_accounts);
-    let result2 = ProgramProcessor::process_instruction(transfer_instruction, user_accounts);
+    let result1 = ProgramProcessor::process(&my_program, token_accounts, &initialize_instruction.to_bytes());
+    let result2 = ProgramProcessor::process(&my_program, user_accounts, &transfer_data);
     
     println!("初始化结果: {:?}", result1);
     println!("转账结果: {:?}", result2);
+    
+    // 校验失败的情况：写入其他程序的账户、缺少签名者
+    let foreign_accounts = vec![(&token_account, AccountMeta::new(Pubkey::new_unique(), system_program, true))];
+    let unsigned_accounts = vec![(&token_account, AccountMeta::new(Pubkey::new_unique(), my_program, false))];
+    let mint_data = ProgramInstruction::Mint { amount: 10 }.to_bytes();
+    let result3 = ProgramProcessor::process(&my_program, foreign_accounts, &mint_data);
+    let result4 = ProgramProcessor::process(&my_program, unsigned_accounts, &mint_data);
+    println!("写入其他程序的账户: {:?}", result3);
+    println!("缺少签名者: {:?}", result4);
+    
+    // 计算预算：执行指令和打印日志都消耗compute units
+    let mut budget = ComputeBudget::new(5_000);
+    let budget_accounts = vec![(&token_account, AccountMeta::new(Pubkey::new_unique(), my_program, true))];
+    let result5 = ProgramProcessor::process_with_budget(&mut budget, &my_program, budget_accounts, &mint_data);
+    println!("消耗{}/{} compute units: {:?}", budget.consumed(), budget.limit(), result5);
+    println!("程序日志: {:?}", budget.logs().lines());
+    let mut tiny_budget = ComputeBudget::new(1_050);
+    let tiny_accounts = vec![(&token_account, AccountMeta::new(Pubkey::new_unique(), my_program, true))];
+    let result6 = ProgramProcessor::process_with_budget(&mut tiny_budget, &my_program, tiny_accounts, &mint_data);
+    println!("预算不足: {:?}", result6);
+    
+    // 批量执行：第二条指令写入其他程序的账户
+    let batch_key = Pubkey::new_unique();
+    let batch_accounts = BTreeMap::from([(batch_key, token_account.clone())]);
+    let batch = || {
+        vec![
+            Instruction::new(my_program, ProgramInstruction::Mint { amount: 1 }, vec![AccountMeta::new(batch_key, my_program, true)]),
+            Instruction::new(my_program, ProgramInstruction::Mint { amount: 2 }, vec![AccountMeta::new(batch_key, system_program, true)]),
+            Instruction::new(my_program, ProgramInstruction::Mint { amount: 3 }, vec![AccountMeta::new(batch_key, my_program, true)]),
+        ]
+    };
+    for mode in [BatchMode::StopOnError, BatchMode::ContinueOnError] {
+        let report = ProgramProcessor::process_batch(&batch_accounts, batch(), mode);
+        println!("{:?}: {:?}，{}", mode, report.results, report);
+    }
+    // 自定义停止条件：累计消耗超过1000 compute units就不再继续
+    let (report, flow) = ProgramProcessor::process_batch_until(&batch_accounts, batch(), |_, _, consumed| {
+        if consumed > 1_000 { ControlFlow::Break(BatchHalt::ComputeLimit { consumed }) } else { ControlFlow::Continue(()) }
+    });
+    println!("预算上限1000: {}，{:?}", report, flow);
     println!();
     
     // 7. 展示泛型的威力
@@ -270,8 +1306,1255 @@ fn main() {
     println!("字符串点: {:?}", point_string);
     println!();
     
+    // 8. 账本与租金豁免
+    println!("8. 账本与租金豁免:");
+    let mut ledger = Ledger::new(Rent::default());
+    // 演示用的System账户都从水龙头领lamports：同一地址10个slot内只能领一次，每个slot最多发100倍免租门槛
+    let mut faucet = Faucet::new(10, ledger.rent().minimum_balance(0) * 100);
+    let required = ledger.rent().minimum_balance(TokenAccount::LEN);
+    println!("Token账户({}字节)免租最低余额: {} lamports", TokenAccount::LEN, required);
+    
+    let token_program = TOKEN_PROGRAM_ID;
+    let funded = Account::new(required, token_program, AccountPayload::Token(token_account.clone()));
+    let underfunded = Account::new(required - 1, token_program, AccountPayload::Token(token_account.clone()));
+    
+    match ledger.create_account(Pubkey::new_unique(), funded) {
+        Ok(()) => println!("{} 创建账户成功，账本中共有{}个账户", style::ok(), ledger.len()),
+        Err(e) => println!("{} 创建账户失败: {}", style::fail(), e),
+    }
+    match ledger.create_account(Pubkey::new_unique(), underfunded) {
+        Ok(()) => println!("{} 创建账户成功，账本中共有{}个账户", style::ok(), ledger.len()),
+        Err(e) => println!("{} 创建账户失败: {}", style::fail(), e),
+    }
+    // 通过二级索引查询，不需要扫描整个账本
+    println!(
+        "owner持有{}个Token账户，该mint下有{}个Token账户",
+        ledger.token_accounts_by_owner(&token_account.owner).count(),
+        ledger.token_accounts_by_mint(&token_account.mint).count()
+    );
+    
+    // 快照与回滚：执行几笔转账，再恢复到之前的状态
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    let minimum = ledger.rent().minimum_balance(0);
+    for key in [alice, bob] {
+        faucet.request_airdrop(&mut ledger, key, minimum * 5)?;
+    }
+    // 冷却期内再领会被拒绝
+    if let Err(e) = faucet.request_airdrop(&mut ledger, alice, minimum) {
+        println!("alice再次airdrop: {}", e);
+    }
+    let before = ledger.snapshot();
+    for _ in 0..3 {
+        if let Err(e) = ledger.transfer_lamports(&alice, &bob, minimum) {
+            println!("{} 转账失败: {}", style::fail(), e);
+        }
+    }
+    for diff in before.diff(&ledger.snapshot()) {
+        if let AccountDiff::Changed { key, before, after } = diff {
+            println!("  {}: {} -> {} lamports", key, before.lamports, after.lamports);
+        }
+    }
+    ledger.restore(before.clone());
+    println!("回滚后与快照一致: {}", ledger.snapshot() == before);
+    // 审计日志保留了每一次修改，包括回滚
+    print!("{}", ledger.audit_log().dump_history(&bob));
+    
+    // 推进slot：免租账户不受影响，余额被转空的账户会被清除
+    if let Err(e) = ledger.transfer_lamports(&alice, &bob, minimum * 5) {
+        println!("{} 转账失败: {}", style::fail(), e);
+    }
+    for event in ledger.tick(432_000) {
+        println!("slot {}: {:?}", ledger.slot(), event);
+    }
+    println!();
+    
+    // 9. PDA推导
+    println!("9. PDA推导:");
+    let seeds: [&[u8]; 2] = [b"vault", token_account.owner.as_ref()];
+    let (vault, bump) = derive_pda(&seeds, &my_program);
+    println!("vault PDA: {}, bump: {}", vault, bump);
+    println!("find_program_address结果一致: {}", find_program_address(&seeds, &my_program) == Some((vault, bump)));
+    
+    // 关联代币账户(ATA)也是PDA：由钱包和mint唯一确定
+    let ata = get_associated_token_address(&token_account.owner, &token_account.mint);
+    println!("owner在该mint下的ATA: {}", ata);
+    println!();
+    
+    // 10. 账户数据realloc
+    println!("10. 账户数据realloc:");
+    let rent = Rent::default();
+    let mut raw_account = AccountWrapper::new(Pubkey::new_unique(), DataBuffer::new(vec![1, 2, 3]), my_program)
+        .with_lamports(rent.minimum_balance(1024));
+    for new_size in [512, 1024, 2048, 20_000] {
+        match raw_account.realloc(new_size, true, &rent) {
+            Ok(()) => println!("{} realloc到{}字节成功", style::ok(), raw_account.data.len()),
+            Err(e) => println!("{} realloc到{}字节失败: {}", style::fail(), new_size, e),
+        }
+    }
+    println!();
+    
+    // 11. 账户注册表与迭代器
+    println!("11. 账户注册表与迭代器:");
+    let mut registry = AccountRegistry::new();
+    registry.insert(from_account.key, from_account.data.clone());
+    registry.insert(to_account.key, to_account.data.clone());
+    registry.insert(
+        Pubkey::new_unique(),
+        TokenAccount::new(Pubkey::new_unique(), token_account.owner, 42),
+    );
+    println!("注册表中共有{}个账户", registry.len());
+    for (key, account) in registry.iter_by_owner(token_account.owner) {
+        println!("  owner持有: {} -> mint={}, amount={}", key, account.mint, account.amount);
+    }
+    let usdc_total: u64 = registry.iter_by_mint(usdc_mint).map(|(_, account)| account.amount).sum();
+    println!("USDC总量: {}", usdc_total);
+    println!();
+    
+    // 12. 运行时账户锁
+    println!("12. 运行时账户锁:");
+    let mut locks = AccountLockManager::new();
+    let shared_meta = AccountMeta::new(Pubkey::new_unique(), my_program, true);
+    // 模拟另一笔正在执行的交易以可写方式持有该账户
+    locks.lock_accounts(&[shared_meta]).map_err(|result| format!("加锁失败: {:?}", result))?;
+    let result = ProgramProcessor::process_instruction_locked(
+        &mut locks,
+        &my_program,
+        vec![(&token_account, shared_meta)],
+        &ProgramInstruction::Transfer { amount: 1 }.to_bytes(),
+    );
+    println!("账户被占用时: {:?}", result);
+    locks.unlock_accounts(&[shared_meta]);
+    let result = ProgramProcessor::process_instruction_locked(
+        &mut locks,
+        &my_program,
+        vec![(&token_account, shared_meta)],
+        &ProgramInstruction::Transfer { amount: 1 }.to_bytes(),
+    );
+    println!("释放锁之后: {:?}", result);
+    println!();
+    
+    // 13. 账户数据版本迁移
+    println!("13. 账户数据版本迁移:");
+    let old_layout = Versioned::new(token_account.clone());
+    println!("v{}: {:?}", old_layout.version(), old_layout.data());
+    let new_layout: Versioned<TokenAccountV2> = old_layout.upgrade();
+    println!("v{}: {:?}", new_layout.version(), new_layout.data());
+    println!();
+    
+    // 14. shortvec紧凑长度编码
+    println!("14. shortvec紧凑长度编码:");
+    for len in [3u16, 200, 40_000] {
+        let mut encoded = Vec::new();
+        shortvec::encode_len(len, &mut encoded);
+        println!("长度{} -> {:02x?} -> {:?}", len, encoded, shortvec::decode_len(&encoded));
+    }
+    println!();
+    
+    // 15. 原子交易
+    println!("15. 原子交易:");
+    // 钱包用密钥对表示，交易执行前会校验它的签名
+    let wallet_keypair = Keypair::new();
+    let wallet = wallet_keypair.pubkey();
+    // 钱包同时是fee payer，需要有lamports支付交易费
+    faucet.request_airdrop(&mut ledger, wallet, minimum * 10)?;
+    // 钱包是mint authority和freeze authority，只有它签名才能铸造或冻结账户；总供应量最多1个代币
+    let token_mint = Pubkey::new_unique();
+    let mint_data = AccountPayload::Mint(Mint::new(6, wallet).with_freeze_authority(wallet).with_max_supply(1_000_000));
+    ledger
+        .create_account(token_mint, Account::new(ledger.rent().minimum_balance(Mint::LEN), my_program, mint_data))?;
+    let token_lamports = ledger.rent().minimum_balance(TokenAccount::LEN);
+    let (source, destination) = (Pubkey::new_unique(), Pubkey::new_unique());
+    for (key, owner) in [(source, wallet), (destination, Pubkey::new_unique())] {
+        let data = AccountPayload::Token(TokenAccount::new(token_mint, owner, 0));
+        ledger
+            .create_account(key, Account::new(token_lamports, my_program, data))?;
+    }
+    // 记下当前的事件序号，最后像订阅者一样读取这之后的Token事件
+    let event_cursor = ledger.event_log().next_seq();
+    // 用builder生成指令，账户顺序和签名标记不用手写
+    let mint_to_source = |amount| {
+        InstructionBuilder::mint_to().program(my_program).amount(amount).to(source).mint(token_mint).signer(wallet).build()
+    };
+    let transfer_out = |amount| {
+        InstructionBuilder::transfer()
+            .program(my_program)
+            .amount(amount)
+            .from(source)
+            .to(destination)
+            .signer(wallet)
+            .build()
+    };
+    // 元数据账户地址由mint推导，先分配好空间，再由mint authority写入名称和符号
+    let metadata_key = metadata_address(&token_mint, &my_program);
+    ledger
+        .create_account(metadata_key, Account::new(ledger.rent().minimum_balance(TokenMetadata::LEN), my_program, AccountPayload::Empty))?;
+    let create_metadata = ProgramInstruction::CreateMetadata {
+        name: "Demo Token".to_string(),
+        symbol: "DEMO".to_string(),
+        uri: "https://example.com/demo.json".to_string(),
+    };
+    let mut metadata_tx = Transaction::new(wallet, vec![Instruction::new(my_program, create_metadata, vec![
+        AccountMeta::new(metadata_key, my_program, false),
+        AccountMeta::new_readonly(token_mint, my_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+    ])]);
+    metadata_tx.sign(&[&wallet_keypair])?;
+    println!("创建元数据: {:?}", metadata_tx.execute(&mut ledger));
+    let symbol = match ledger.get_account(&metadata_key) {
+        Some(Account { data: AccountPayload::Metadata(metadata), .. }) => {
+            println!("代币: {}，URI: {}", metadata, metadata.uri);
+            metadata.symbol.clone()
+        }
+        _ => token_mint.to_string(),
+    };
+    let mut ok_tx = Transaction::new(wallet, vec![
+        mint_to_source(500)?,
+        transfer_out(200)?,
+    ]);
+    // 未签名的交易在执行前就被拒绝
+    println!("签名前执行: {:?}", ok_tx.execute(&mut ledger));
+    ok_tx.sign(&[&wallet_keypair])?;
+    println!("签名: {}", ok_tx.signatures[0]);
+    println!("交易费: {} lamports", ok_tx.fee(&ledger));
+    println!("铸造500并转出200: {:?}", ok_tx.execute(&mut ledger));
+    // 第二条指令余额不足，第一条指令的铸造也会被回滚
+    let mut failing_tx = Transaction::new(wallet, vec![
+        mint_to_source(500)?,
+        transfer_out(5_000)?,
+    ]);
+    failing_tx.sign(&[&wallet_keypair])?;
+    // 先在账本副本上试运行，看看会发生什么
+    let simulation = ledger.simulate(&failing_tx);
+    println!("试运行: {:?}，{}个账户会变化", simulation.result, simulation.diffs.len());
+    match failing_tx.execute(&mut ledger) {
+        Ok(()) => println!("{} 交易成功", style::ok()),
+        Err(e) => println!("{} {}", style::fail(), e),
+    }
+    // 钱包授权delegate代为转出最多100，delegate签名的转账从额度里扣除
+    let delegate_keypair = Keypair::new();
+    let approve = Instruction::new(my_program, ProgramInstruction::Approve { amount: 100 }, vec![
+        AccountMeta::new(source, my_program, false),
+        AccountMeta::new_readonly(delegate_keypair.pubkey(), system_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+    ]);
+    let delegated_transfer = |amount| {
+        InstructionBuilder::transfer()
+            .program(my_program)
+            .amount(amount)
+            .from(source)
+            .to(destination)
+            .signer(delegate_keypair.pubkey())
+            .build()
+    };
+    let mut approve_tx = Transaction::new(wallet, vec![approve, delegated_transfer(60)?]);
+    approve_tx.sign(&[&wallet_keypair, &delegate_keypair])?;
+    println!("授权100并由delegate转出60: {:?}", approve_tx.execute(&mut ledger));
+    let mut over_tx = Transaction::new(wallet, vec![delegated_transfer(60)?]);
+    over_tx.sign(&[&wallet_keypair, &delegate_keypair])?;
+    println!("delegate再转出60(超出剩余额度): {:?}", over_tx.execute(&mut ledger));
+    // 销毁会同时减少余额和总供应量
+    let burn = InstructionBuilder::burn().program(my_program).amount(40).from(source).mint(token_mint).signer(wallet);
+    let mut burn_tx = Transaction::new(wallet, vec![burn.build()?]);
+    burn_tx.sign(&[&wallet_keypair])?;
+    println!("销毁40: {:?}", burn_tx.execute(&mut ledger));
+    // 冻结接收方后转账被拒绝，解冻后恢复
+    let freeze = |data| {
+        Instruction::new(my_program, data, vec![
+            AccountMeta::new(destination, my_program, false),
+            AccountMeta::new_readonly(token_mint, my_program, false),
+            AccountMeta::new_readonly(wallet, system_program, true),
+        ])
+    };
+    let mut frozen_tx = Transaction::new(wallet, vec![
+        freeze(ProgramInstruction::FreezeAccount),
+        transfer_out(1)?,
+    ]);
+    frozen_tx.sign(&[&wallet_keypair])?;
+    println!("冻结后转入: {:?}", frozen_tx.execute(&mut ledger));
+    let mut thaw_tx = Transaction::new(wallet, vec![
+        freeze(ProgramInstruction::FreezeAccount),
+        freeze(ProgramInstruction::ThawAccount),
+        transfer_out(1)?,
+    ]);
+    thaw_tx.sign(&[&wallet_keypair])?;
+    println!("冻结、解冻后转入: {:?}", thaw_tx.execute(&mut ledger));
+    // 余额按mint的精度显示，例如decimals=6时960显示为0.00096
+    if let Some(Account { data: AccountPayload::Mint(mint), .. }) = ledger.get_account(&token_mint) {
+        for key in [source, destination] {
+            if let Some(Account { data: AccountPayload::Token(token), .. }) = ledger.get_account(&key) {
+                println!("  {}: amount={} {}, delegated_amount={}", key, mint.ui_amount(token.amount), symbol, mint.ui_amount(token.delegated_amount));
+            }
+        }
+        println!("  {}", mint.summarize());
+        println!("  \"1.5\"换算为最小单位: {:?}，\"0.0000001\": {:?}", mint.parse_ui_amount("1.5"), mint.parse_ui_amount("0.0000001"));
+    }
+    // 再铸造1个代币就会超过max_supply
+    let mut capped_tx = Transaction::new(wallet, vec![mint_to_source(1_000_000)?]);
+    capped_tx.sign(&[&wallet_keypair])?;
+    println!("铸造超过上限: {:?}", capped_tx.execute(&mut ledger));
+    // 按钱包汇总：同一mint的所有Token账户合并，按mint的精度显示
+    println!("{}", Wallet::new(&ledger, wallet).portfolio());
+    // 带转账手续费的mint：费率0.5%，单笔最多收10，手续费存进fee vault
+    let fee_mint = Pubkey::new_unique();
+    let (fee_source, fee_recipient, fee_vault) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let fee_config = TransferFeeConfig::new(50, 10, fee_vault)?;
+    let fee_mint_data = AccountPayload::Mint(Mint::new(2, wallet).with_transfer_fee(fee_config));
+    ledger
+        .create_account(fee_mint, Account::new(ledger.rent().minimum_balance(Mint::LEN), my_program, fee_mint_data))?;
+    for (key, owner) in [(fee_source, wallet), (fee_recipient, Pubkey::new_unique()), (fee_vault, system_program)] {
+        let data = AccountPayload::Token(TokenAccount::new(fee_mint, owner, 0));
+        ledger
+            .create_account(key, Account::new(token_lamports, my_program, data))?;
+    }
+    let fee_transfer = |amount| {
+        InstructionBuilder::transfer()
+            .program(my_program)
+            .amount(amount)
+            .from(fee_source)
+            .to(fee_recipient)
+            .signer(wallet)
+            .fee_vault(fee_vault)
+            .build()
+    };
+    let mut fee_tx = Transaction::new(wallet, vec![
+        InstructionBuilder::mint_to().program(my_program).amount(10_000).to(fee_source).mint(fee_mint).signer(wallet).build()?,
+        fee_transfer(1_000)?,
+        fee_transfer(5_000)?,
+    ]);
+    fee_tx.sign(&[&wallet_keypair])?;
+    println!("带手续费的转账(1000和5000): {:?}", fee_tx.execute(&mut ledger));
+    for (label, key) in [("收款方", fee_recipient), ("fee vault", fee_vault)] {
+        if let Some(Account { data: AccountPayload::Token(token), .. }) = ledger.get_account(&key) {
+            println!("  {}: amount={}", label, token.amount);
+        }
+    }
+    // 在mint上注册转账钩子：钱包被拉黑后，它的转出会被钩子拒绝
+    ledger.register_transfer_hook(fee_mint, Arc::new(Blocklist::new([wallet])))?;
+    let mut blocked_tx = Transaction::new(wallet, vec![fee_transfer(100)?]);
+    blocked_tx.sign(&[&wallet_keypair])?;
+    println!("被拉黑后转账: {:?}", blocked_tx.execute(&mut ledger));
+    // 关闭Token账户：有余额时被拒绝，转空后关闭，租金押金退回钱包
+    let close_metas = |token| {
+        vec![
+            AccountMeta::new(token, my_program, false),
+            AccountMeta::new(wallet, system_program, false),
+            AccountMeta::new_readonly(wallet, system_program, true),
+        ]
+    };
+    let mut close_tx = Transaction::new(wallet, vec![
+        Instruction::new(my_program, ProgramInstruction::CloseTokenAccount, close_metas(source)),
+    ]);
+    close_tx.sign(&[&wallet_keypair])?;
+    println!("关闭有余额的账户: {:?}", close_tx.execute(&mut ledger));
+    let empty_token = Pubkey::new_unique();
+    ledger
+        .create_account(empty_token, Account::new(token_lamports, my_program, AccountPayload::Token(TokenAccount::new(token_mint, wallet, 0))))?;
+    let lamports_before = ledger.get_account(&wallet).map_or(0, |account| account.lamports);
+    let mut close_tx = Transaction::new(wallet, vec![
+        Instruction::new(my_program, ProgramInstruction::CloseTokenAccount, close_metas(empty_token)),
+    ]);
+    close_tx.sign(&[&wallet_keypair])?;
+    let fee = close_tx.fee(&ledger);
+    println!("关闭空账户: {:?}，账户仍存在: {}", close_tx.execute(&mut ledger), ledger.get_account(&empty_token).is_some());
+    let lamports_after = ledger.get_account(&wallet).map_or(0, |account| account.lamports);
+    println!("  退还{} lamports（已扣除交易费{}）", lamports_after + fee - lamports_before, fee);
+    println!("Token事件（从#{}开始）:", event_cursor);
+    for record in ledger.iter_events_since(event_cursor) {
+        println!("  {}", record);
+    }
+    // 失败的交易同样要付交易费
+    println!("累计收取交易费: {} lamports", ledger.collected_fees());
+    println!();
+    
+    // 16. 跨程序调用(CPI)
+    println!("16. 跨程序调用(CPI):");
+    // 中转程序：记录调用栈后，把铸造指令通过CPI转给Token账户所属的程序
+    fn relay(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+        let depth = ctx.depth();
+        msg!(ctx, "中转程序被调用，调用栈深度: {}", depth)?;
+        let inner = Instruction::new(instruction.accounts[0].owner, instruction.data.clone(), instruction.accounts.clone());
+        ctx.invoke(&inner, &instruction.accounts)
+    }
+    // 不断调用自己，最终超过调用深度上限
+    fn recurse(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+        ctx.invoke(instruction, &instruction.accounts)
+    }
+    let relay_program = Pubkey::new_unique();
+    let recurse_program = Pubkey::new_unique();
+    let programs = Programs::from([(relay_program, relay as Entrypoint), (recurse_program, recurse as Entrypoint)]);
+    let cpi_metas = vec![
+        AccountMeta::new(source, my_program, false),
+        AccountMeta::new(token_mint, my_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+    ];
+    let mut cpi_tx = Transaction::new(wallet, vec![
+        Instruction::new(relay_program, ProgramInstruction::Mint { amount: 100 }, cpi_metas.clone()),
+    ]);
+    cpi_tx.sign(&[&wallet_keypair])?;
+    let outcome = cpi_tx.execute_with_programs(&mut ledger, &programs);
+    println!("通过中转程序铸造100: {:?}，消耗{} compute units", outcome.result, outcome.compute_units_consumed);
+    // 执行日志随结果一起返回
+    for line in outcome.logs.lines() {
+        println!("  {}", line);
+    }
+    let mut deep_tx = Transaction::new(wallet, vec![
+        Instruction::new(recurse_program, ProgramInstruction::Mint { amount: 1 }, cpi_metas),
+    ]);
+    deep_tx.sign(&[&wallet_keypair])?;
+    let outcome = deep_tx.execute_with_programs(&mut ledger, &programs);
+    println!("递归调用(最大深度{}): {:?}，日志{}行", MAX_INVOKE_DEPTH, outcome.result, outcome.logs.len());
+    if let Some(Account { data: AccountPayload::Token(token), .. }) = ledger.get_account(&source) {
+        println!("  {}: amount={}", source, token.amount);
+    }
+    // 托管程序：钱包把100个代币A存进由托管PDA控制的vault，taker用40个代币B换走，
+    // vault转出时由托管程序通过invoke_signed为PDA签名
+    let escrow_program = Pubkey::new_unique();
+    let programs = Programs::from([(escrow_program, escrow::process as Entrypoint)]);
+    let taker = Keypair::new();
+    faucet.request_airdrop(&mut ledger, taker.pubkey(), minimum * 10)?;
+    let mint_b = Pubkey::new_unique();
+    let mint_b_data = AccountPayload::Mint(Mint::new(0, Pubkey::new_unique()));
+    ledger
+        .create_account(mint_b, Account::new(ledger.rent().minimum_balance(Mint::LEN), my_program, mint_b_data))?;
+    let vault = Pubkey::new_unique();
+    let (escrow_key, _) = escrow_address(&wallet, &vault, &escrow_program);
+    let (wallet_b, taker_a, taker_b) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    for (key, mint, owner, amount) in [(vault, token_mint, escrow_key, 0), (wallet_b, mint_b, wallet, 0), (taker_a, token_mint, taker.pubkey(), 0), (taker_b, mint_b, taker.pubkey(), 40)] {
+        let data = AccountPayload::Token(TokenAccount::new(mint, owner, amount));
+        ledger
+            .create_account(key, Account::new(token_lamports, my_program, data))?;
+    }
+    ledger
+        .create_account(escrow_key, Account::new(ledger.rent().minimum_balance(Escrow::LEN), escrow_program, AccountPayload::Empty))?;
+    let mut init_tx = Transaction::new(wallet, vec![Instruction::new(
+        escrow_program,
+        ProgramInstruction::InitializeEscrow { amount: 100, expected_amount: 40 },
+        vec![
+            AccountMeta::new(escrow_key, escrow_program, false),
+            AccountMeta::new_readonly(wallet, system_program, true),
+            AccountMeta::new(source, my_program, false),
+            AccountMeta::new_readonly(wallet_b, my_program, false),
+            AccountMeta::new(vault, my_program, false),
+        ],
+    )]);
+    init_tx.sign(&[&wallet_keypair])?;
+    println!("创建托管(100个A换40个B): {:?}", init_tx.execute_with_programs(&mut ledger, &programs).result);
+    let mut exchange_tx = Transaction::new(taker.pubkey(), vec![Instruction::new(
+        escrow_program,
+        ProgramInstruction::Exchange { amount: 100 },
+        vec![
+            AccountMeta::new(escrow_key, escrow_program, false),
+            AccountMeta::new_readonly(taker.pubkey(), system_program, true),
+            AccountMeta::new(taker_b, my_program, false),
+            AccountMeta::new(taker_a, my_program, false),
+            AccountMeta::new(wallet_b, my_program, false),
+            AccountMeta::new(vault, my_program, false),
+            AccountMeta::new(wallet, system_program, false),
+        ],
+    )]);
+    exchange_tx.sign(&[&taker])?;
+    println!("taker接受托管: {:?}", exchange_tx.execute_with_programs(&mut ledger, &programs).result);
+    for (label, key) in [("taker的A", taker_a), ("钱包的B", wallet_b)] {
+        if let Some(Account { data: AccountPayload::Token(token), .. }) = ledger.get_account(&key) {
+            println!("  {}: amount={}", label, token.amount);
+        }
+    }
+    println!("  托管账户和vault已关闭: {}", ledger.get_account(&escrow_key).is_none() && ledger.get_account(&vault).is_none());
+
+    // 锁仓程序：钱包给taker锁仓150个代币A，cliff之前不能领取，之后按slot线性释放
+    let vesting_program = Pubkey::new_unique();
+    let programs = Programs::from([(vesting_program, vesting::process as Entrypoint)]);
+    let (vesting_vault, taker_vested) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (vesting_key, _) = vesting_address(&taker.pubkey(), &vesting_vault, &vesting_program);
+    for (key, owner) in [(vesting_vault, vesting_key), (taker_vested, taker.pubkey())] {
+        let data = AccountPayload::Token(TokenAccount::new(token_mint, owner, 0));
+        ledger
+            .create_account(key, Account::new(token_lamports, my_program, data))?;
+    }
+    ledger
+        .create_account(vesting_key, Account::new(ledger.rent().minimum_balance(VestingSchedule::LEN), vesting_program, AccountPayload::Empty))?;
+    let start = ledger.slot();
+    let mut vesting_tx = Transaction::new(wallet, vec![Instruction::new(
+        vesting_program,
+        ProgramInstruction::CreateVesting { amount: 150, start_slot: start, cliff_slot: start + 10, end_slot: start + 30 },
+        vec![
+            AccountMeta::new(vesting_key, vesting_program, false),
+            AccountMeta::new_readonly(wallet, system_program, true),
+            AccountMeta::new(source, my_program, false),
+            AccountMeta::new_readonly(taker.pubkey(), system_program, false),
+            AccountMeta::new(vesting_vault, my_program, false),
+        ],
+    )]);
+    vesting_tx.sign(&[&wallet_keypair])?;
+    println!("创建锁仓(150个A，slot {}起30个slot释放完): {:?}", start, vesting_tx.execute_with_programs(&mut ledger, &programs).result);
+    for ticks in [5, 15, 20] {
+        ledger.tick(ticks);
+        let mut claim_tx = Transaction::new(taker.pubkey(), vec![Instruction::new(
+            vesting_program,
+            ProgramInstruction::Claim,
+            vec![
+                AccountMeta::new(vesting_key, vesting_program, false),
+                AccountMeta::new_readonly(taker.pubkey(), system_program, true),
+                AccountMeta::new(vesting_vault, my_program, false),
+                AccountMeta::new(taker_vested, my_program, false),
+            ],
+        )])
+        .with_recent_blockhash(ledger.latest_blockhash());
+        claim_tx.sign(&[&taker])?;
+        let result = claim_tx.execute_with_programs(&mut ledger, &programs).result;
+        if let Some(Account { data: AccountPayload::Token(token), .. }) = ledger.get_account(&taker_vested) {
+            println!("  slot {} 领取: {:?}，累计领到{}", ledger.slot(), result, token.amount);
+        }
+    }
+
+    // AMM：钱包按1:2存入流动性，taker用代币X兑换代币Y，储备乘积只增不减
+    let amm_program = Pubkey::new_unique();
+    let programs = Programs::from([(amm_program, amm::process as Entrypoint)]);
+    let (mint_x, mint_y, lp_mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let (pool_key, _) = pool_address(&mint_x, &mint_y, &amm_program);
+    for (mint, authority) in [(mint_x, wallet), (mint_y, wallet), (lp_mint, pool_key)] {
+        ledger
+            .create_account(mint, Account::new(ledger.rent().minimum_balance(Mint::LEN), my_program, AccountPayload::Mint(Mint::new(0, authority))))?;
+    }
+    let [wallet_x, wallet_y, wallet_lp, taker_x, taker_y, vault_x, vault_y] = [(); 7].map(|_| Pubkey::new_unique());
+    for (key, mint, owner, amount) in [
+        (wallet_x, mint_x, wallet, 10_000),
+        (wallet_y, mint_y, wallet, 20_000),
+        (wallet_lp, lp_mint, wallet, 0),
+        (taker_x, mint_x, taker.pubkey(), 1_000),
+        (taker_y, mint_y, taker.pubkey(), 0),
+        (vault_x, mint_x, pool_key, 0),
+        (vault_y, mint_y, pool_key, 0),
+    ] {
+        let data = AccountPayload::Token(TokenAccount::new(mint, owner, amount));
+        ledger
+            .create_account(key, Account::new(token_lamports, my_program, data))?;
+    }
+    ledger
+        .create_account(pool_key, Account::new(ledger.rent().minimum_balance(Pool::LEN), amm_program, AccountPayload::Empty))?;
+    let mut pool_tx = Transaction::new(wallet, vec![
+        Instruction::new(
+            amm_program,
+            ProgramInstruction::InitializePool { fee_basis_points: 30 },
+            vec![
+                AccountMeta::new(pool_key, amm_program, false),
+                AccountMeta::new_readonly(wallet, system_program, true),
+                AccountMeta::new_readonly(vault_x, my_program, false),
+                AccountMeta::new_readonly(vault_y, my_program, false),
+                AccountMeta::new_readonly(lp_mint, my_program, false),
+            ],
+        ),
+        Instruction::new(
+            amm_program,
+            ProgramInstruction::AddLiquidity { amount_a: 10_000, amount_b: 20_000 },
+            vec![
+                AccountMeta::new_readonly(pool_key, amm_program, false),
+                AccountMeta::new_readonly(wallet, system_program, true),
+                AccountMeta::new(wallet_x, my_program, false),
+                AccountMeta::new(wallet_y, my_program, false),
+                AccountMeta::new(wallet_lp, my_program, false),
+                AccountMeta::new(vault_x, my_program, false),
+                AccountMeta::new(vault_y, my_program, false),
+                AccountMeta::new(lp_mint, my_program, false),
+            ],
+        ),
+    ]);
+    pool_tx.sign(&[&wallet_keypair])?;
+    println!("创建池子并存入10000个X和20000个Y: {:?}", pool_tx.execute_with_programs(&mut ledger, &programs).result);
+    let token_amount = |ledger: &Ledger, key: &Pubkey| match ledger.get_account(key) {
+        Some(Account { data: AccountPayload::Token(token), .. }) => token.amount,
+        _ => 0,
+    };
+    for min_amount_out in [2_000, 1_500] {
+        let k = token_amount(&ledger, &vault_x) as u128 * token_amount(&ledger, &vault_y) as u128;
+        let mut swap_tx = Transaction::new(taker.pubkey(), vec![Instruction::new(
+            amm_program,
+            ProgramInstruction::Swap { amount_in: 1_000, min_amount_out },
+            vec![
+                AccountMeta::new_readonly(pool_key, amm_program, false),
+                AccountMeta::new_readonly(taker.pubkey(), system_program, true),
+                AccountMeta::new(taker_x, my_program, false),
+                AccountMeta::new(taker_y, my_program, false),
+                AccountMeta::new(vault_x, my_program, false),
+                AccountMeta::new(vault_y, my_program, false),
+            ],
+        )]);
+        swap_tx.sign(&[&taker])?;
+        let result = swap_tx.execute_with_programs(&mut ledger, &programs).result;
+        let (x, y) = (token_amount(&ledger, &vault_x), token_amount(&ledger, &vault_y));
+        println!("  用1000个X兑换(至少{}个Y): {:?}，taker得到{}个Y", min_amount_out, result, token_amount(&ledger, &taker_y));
+        println!("    储备 {} * {}，k {} -> {}", x, y, k, x as u128 * y as u128);
+    }
+    println!();
+    
+    // 17. 带版本的交易格式
+    println!("17. 带版本的交易格式:");
+    let mint_metas = vec![
+        AccountMeta::new(source, my_program, false),
+        AccountMeta::new(token_mint, my_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+    ];
+    let mut legacy_tx = Transaction::new(wallet, vec![
+        Instruction::new(my_program, ProgramInstruction::Mint { amount: 1 }, mint_metas.clone()),
+    ]);
+    legacy_tx.sign(&[&wallet_keypair])?;
+    let mut v0_tx = TransactionV0::new(wallet, vec![
+        Instruction::new(my_program, ProgramInstruction::Mint { amount: 2 }, mint_metas),
+    ]);
+    v0_tx.sign(&[&wallet_keypair])?;
+    for tx in [VersionedTransaction::Legacy(legacy_tx), VersionedTransaction::V0(v0_tx)] {
+        let bytes = tx.to_bytes();
+        // 跳过1字节签名数量和64字节签名，就是消息的第一个字节
+        println!("版本{:?}: {}字节，消息首字节0x{:02x}", tx.version(), bytes.len(), bytes[65]);
+        match VersionedTransaction::from_bytes(&bytes) {
+            Ok(decoded) => println!("  解码后执行: {:?}", decoded.execute(&mut ledger)),
+            Err(e) => println!("  解码失败: {}", e),
+        }
+    }
+    // 把source和mint放进地址查找表，V0交易里只需写它们在表中的下标
+    let table_key = Pubkey::new_unique();
+    let mut lookup_table = AddressLookupTable::new(wallet);
+    lookup_table.extend(&[source, token_mint])?;
+    let mut lookup_tx = TransactionV0::new(wallet, vec![
+        mint_to_source(3)?,
+    ])
+    .with_address_lookup_tables(&[(table_key, &lookup_table)]);
+    lookup_tx.sign(&[&wallet_keypair])?;
+    let lookup_tx = VersionedTransaction::V0(lookup_tx);
+    println!("使用查找表: {}字节", lookup_tx.to_bytes().len());
+    println!("  查找表还未创建: {:?}", lookup_tx.execute(&mut ledger));
+    let table_lamports = ledger.rent().minimum_balance(lookup_table.data_len());
+    ledger
+        .create_account(table_key, Account::new(table_lamports, system_program, AccountPayload::LookupTable(lookup_table)))?;
+    println!("  创建查找表后执行: {:?}", lookup_tx.execute(&mut ledger));
+    println!();
+    
+    // 18. 持久化nonce
+    println!("18. 持久化nonce:");
+    let nonce_key = Pubkey::new_unique();
+    let nonce_account = NonceAccount::new(wallet, ledger.latest_blockhash());
+    let stored_nonce = nonce_account.nonce;
+    ledger
+        .create_account(nonce_key, Account::new(ledger.rent().minimum_balance(NonceAccount::LEN), system_program, AccountPayload::Nonce(nonce_account)))?;
+    // 用nonce代替recent blockhash，第一条指令推进nonce
+    let mut nonce_tx = Transaction::new(wallet, vec![
+        Instruction::new(system_program, ProgramInstruction::AdvanceNonce, vec![
+            AccountMeta::new(nonce_key, system_program, false),
+            AccountMeta::new_readonly(wallet, system_program, true),
+        ]),
+        Instruction::new(my_program, ProgramInstruction::Mint { amount: 5 }, vec![
+            AccountMeta::new(source, my_program, false),
+            AccountMeta::new(token_mint, my_program, false),
+            AccountMeta::new_readonly(wallet, system_program, true),
+        ]),
+    ])
+    .with_recent_blockhash(stored_nonce);
+    nonce_tx.sign(&[&wallet_keypair])?;
+    println!("第一次提交: {:?}", nonce_tx.execute(&mut ledger));
+    println!("重复提交: {:?}", nonce_tx.execute(&mut ledger));
+    println!();
+    
+    // 19. 按优先费调度交易
+    println!("19. 按优先费调度交易:");
+    let mut queue = TransactionQueue::new(2);
+    for (amount, priority_fee) in [(1, None), (2, Some(1_000)), (3, Some(50)), (4, Some(1_000))] {
+        let metas = vec![
+            AccountMeta::new(source, my_program, false),
+            AccountMeta::new(token_mint, my_program, false),
+            AccountMeta::new_readonly(wallet, system_program, true),
+        ];
+        let mut tx = Transaction::new(wallet, vec![
+            Instruction::new(my_program, ProgramInstruction::Mint { amount }, metas),
+        ])
+        .with_recent_blockhash(ledger.latest_blockhash());
+        tx.sign(&[&wallet_keypair])?;
+        queue.push(tx, priority_fee);
+    }
+    // 这些交易都写同一个账户，每批只能放一笔
+    while !queue.is_empty() {
+        for tx in queue.pop_next_batch() {
+            println!("  执行 {:?}: {:?}", tx.instructions[0].data, tx.execute(&mut ledger));
+        }
+    }
+    println!();
+    
+    // 20. 离线多方签名
+    println!("20. 离线多方签名:");
+    // 另一方代付交易费，钱包作为mint authority授权铸造；未签名的交易先序列化发给双方
+    let partner = Keypair::new();
+    faucet.request_airdrop(&mut ledger, partner.pubkey(), minimum * 10)?;
+    let unsigned = Transaction::new(partner.pubkey(), vec![mint_to_source(7)?]);
+    let unsigned_bytes = VersionedTransaction::Legacy(unsigned).to_bytes();
+    let receive = |bytes: &[u8]| -> Result<Transaction, Box<dyn Error>> {
+        match VersionedTransaction::from_bytes(bytes)? {
+            VersionedTransaction::Legacy(tx) => Ok(tx),
+            other => Err(format!("期望legacy交易，收到{:?}", other).into()),
+        }
+    };
+    let mut wallet_copy = receive(&unsigned_bytes)?;
+    wallet_copy.partial_sign(&wallet_keypair)?;
+    let mut partner_copy = receive(&unsigned_bytes)?;
+    partner_copy.partial_sign(&partner)?;
+    println!("钱包签名后还缺: {:?}", wallet_copy.missing_signers());
+    println!("  直接执行: {:?}", wallet_copy.execute(&mut ledger));
+    wallet_copy.merge_signatures(&partner_copy)?;
+    println!("合并后还缺: {:?}", wallet_copy.missing_signers());
+    println!("  执行: {:?}", wallet_copy.execute(&mut ledger));
+    println!();
+    
+    // 21. 交易历史
+    println!("21. 交易历史:");
+    // 每页3条，从最新的开始往前翻
+    let mut before = None;
+    for page in 1..=2 {
+        let signatures = ledger.get_signatures_for_address(&source, before.as_ref(), 3);
+        println!("source第{}页:", page);
+        for signature in &signatures {
+            if let Some(status) = ledger.get_transaction(signature) {
+                println!("  slot {} {:?} {}", status.slot, status.result, signature);
+            }
+        }
+        before = signatures.last().copied();
+    }
+    println!();
+    
+    // 22. 治理投票
+    println!("22. 治理投票:");
+    // 钱包持有700票、taker持有300票，提案从国库转200个治理代币给taker
+    let governance_program = Pubkey::new_unique();
+    let programs = Programs::from([(governance_program, governance::process as Entrypoint)]);
+    let gov_mint = Pubkey::new_unique();
+    ledger
+        .create_account(gov_mint, Account::new(ledger.rent().minimum_balance(Mint::LEN), my_program, AccountPayload::Mint(Mint::new(0, wallet))))?;
+    let (governance_key, _) = governance_address(&gov_mint, &governance_program);
+    let [wallet_votes, taker_votes, treasury] = [(); 3].map(|_| Pubkey::new_unique());
+    for (key, owner, amount) in [(wallet_votes, wallet, 700), (taker_votes, taker.pubkey(), 300), (treasury, governance_key, 500)] {
+        let data = AccountPayload::Token(TokenAccount::new(gov_mint, owner, amount));
+        ledger
+            .create_account(key, Account::new(token_lamports, my_program, data))?;
+    }
+    let payout = Instruction::new(
+        my_program,
+        ProgramInstruction::Transfer { amount: 200 },
+        vec![
+            AccountMeta::new(treasury, my_program, false),
+            AccountMeta::new(taker_votes, my_program, false),
+            AccountMeta::new_readonly(governance_key, governance_program, true),
+        ],
+    );
+    let (proposal_key, _) = proposal_address(&governance_key, 0, &governance_program);
+    for (key, space) in [(governance_key, Governance::LEN), (proposal_key, Proposal::space(&payout))] {
+        ledger
+            .create_account(key, Account::new(ledger.rent().minimum_balance(space), governance_program, AccountPayload::Empty))?;
+    }
+    // 快照取提案创建那个slot开始时的余额，上面的代币要在更早的slot里到账
+    ledger.tick(1);
+    let mut proposal_tx = Transaction::new(wallet, vec![
+        Instruction::new(
+            governance_program,
+            ProgramInstruction::CreateGovernance { quorum: 500, threshold_basis_points: 6_000, voting_slots: 10 },
+            vec![
+                AccountMeta::new(governance_key, governance_program, false),
+                AccountMeta::new_readonly(wallet, system_program, true),
+                AccountMeta::new_readonly(gov_mint, my_program, false),
+            ],
+        ),
+        Instruction::new(
+            governance_program,
+            ProgramInstruction::CreateProposal { instruction: Box::new(payout.clone()) },
+            vec![
+                AccountMeta::new(proposal_key, governance_program, false),
+                AccountMeta::new_readonly(wallet, system_program, true),
+                AccountMeta::new(governance_key, governance_program, false),
+            ],
+        ),
+    ]);
+    proposal_tx.sign(&[&wallet_keypair])?;
+    println!("创建治理和提案(quorum 500票，赞成60%通过): {:?}", proposal_tx.execute_with_programs(&mut ledger, &programs).result);
+    for (voter, token_account, approve) in [(&wallet_keypair, wallet_votes, true), (&taker, taker_votes, false)] {
+        let (record, _) = vote_record_address(&proposal_key, &token_account, &governance_program);
+        ledger
+            .create_account(record, Account::new(ledger.rent().minimum_balance(VoteRecord::LEN), governance_program, AccountPayload::Empty))?;
+        let mut vote_tx = Transaction::new(voter.pubkey(), vec![Instruction::new(
+            governance_program,
+            ProgramInstruction::CastVote { approve },
+            vec![
+                AccountMeta::new(record, governance_program, false),
+                AccountMeta::new_readonly(voter.pubkey(), system_program, true),
+                AccountMeta::new(proposal_key, governance_program, false),
+                AccountMeta::new_readonly(token_account, my_program, false),
+            ],
+        )]);
+        vote_tx.sign(&[voter])?;
+        println!("  {}投{}: {:?}", voter.pubkey(), if approve { "赞成" } else { "反对" }, vote_tx.execute_with_programs(&mut ledger, &programs).result);
+    }
+    ledger.tick(10);
+    let mut finalize_accounts = vec![
+        AccountMeta::new(proposal_key, governance_program, false),
+        AccountMeta::new_readonly(governance_key, governance_program, false),
+    ];
+    finalize_accounts.extend(payout.accounts.iter().map(|meta| AccountMeta { is_signer: false, ..*meta }));
+    let mut finalize_tx = Transaction::new(taker.pubkey(), vec![Instruction::new(governance_program, ProgramInstruction::FinalizeProposal, finalize_accounts)]);
+    finalize_tx.sign(&[&taker])?;
+    println!("投票期结束后结算: {:?}", finalize_tx.execute_with_programs(&mut ledger, &programs).result);
+    if let Some(Account { data: AccountPayload::Proposal(proposal), .. }) = ledger.get_account(&proposal_key) {
+        println!("  赞成{}票，反对{}票，状态: {:?}", proposal.yes_votes, proposal.no_votes, proposal.state);
+    }
+    for (label, key) in [("国库", treasury), ("taker", taker_votes)] {
+        if let Some(Account { data: AccountPayload::Token(token), .. }) = ledger.get_account(&key) {
+            println!("  {}: {}", label, token.amount);
+        }
+    }
+    println!();
+
+    // 23. NFT
+    println!("23. NFT:");
+    // 钱包铸造一个NFT给自己，再转给taker，update authority跟着一起移交
+    match mint_nft(&mut ledger, my_program, &wallet_keypair, wallet, ("Sunset #1", "SUN", "https://example.com/sunset/1.json")) {
+        Ok(nft) => {
+            println!("铸造NFT: mint={}，持有账户={}", nft.mint, nft.token_account);
+            let mut again = Transaction::new(wallet, vec![Instruction::new(my_program, ProgramInstruction::Mint { amount: 1 }, vec![
+                AccountMeta::new(nft.token_account, my_program, false),
+                AccountMeta::new(nft.mint, my_program, false),
+                AccountMeta::new_readonly(wallet, system_program, true),
+            ])]);
+            again.sign(&[&wallet_keypair])?;
+            println!("再铸造一个: {:?}", again.execute(&mut ledger));
+            match transfer_nft(&mut ledger, my_program, &nft, &wallet_keypair, taker.pubkey()) {
+                Ok(moved) => {
+                    if let Some(Account { data: AccountPayload::Metadata(metadata), .. }) = ledger.get_account(&moved.metadata) {
+                        println!("转给taker: 持有账户={}，update authority是taker: {}", moved.token_account, metadata.update_authority == taker.pubkey());
+                    }
+                }
+                Err(e) => println!("转移失败: {}", e),
+            }
+        }
+        Err(e) => println!("铸造失败: {}", e),
+    }
+    println!();
+
+    // 24. 订单簿DEX
+    println!("24. 订单簿DEX:");
+    // 钱包挂卖单卖出base，taker出更高的价买入，以挂单价成交
+    let dex_program = Pubkey::new_unique();
+    let programs = Programs::from([(dex_program, dex::process as Entrypoint)]);
+    let [base_mint, quote_mint] = [(); 2].map(|_| Pubkey::new_unique());
+    for mint in [base_mint, quote_mint] {
+        ledger
+            .create_account(mint, Account::new(ledger.rent().minimum_balance(Mint::LEN), my_program, AccountPayload::Mint(Mint::new(0, wallet))))?;
+    }
+    let (market_key, _) = market_address(&base_mint, &quote_mint, &dex_program);
+    let [wallet_base, wallet_quote, taker_base, taker_quote, base_vault, quote_vault] = [(); 6].map(|_| Pubkey::new_unique());
+    for (key, mint, owner, amount) in [
+        (wallet_base, base_mint, wallet, 100),
+        (wallet_quote, quote_mint, wallet, 0),
+        (taker_base, base_mint, taker.pubkey(), 0),
+        (taker_quote, quote_mint, taker.pubkey(), 1_000),
+        (base_vault, base_mint, market_key, 0),
+        (quote_vault, quote_mint, market_key, 0),
+    ] {
+        let data = AccountPayload::Token(TokenAccount::new(mint, owner, amount));
+        ledger.create_account(key, Account::new(token_lamports, my_program, data))?;
+    }
+    let order_keys: Vec<Pubkey> = (0..2).map(|order_id| order_address(&market_key, order_id, &dex_program).0).collect();
+    for key in std::iter::once(market_key).chain(order_keys.iter().copied()) {
+        let space = if key == market_key { Market::LEN } else { Order::LEN };
+        ledger
+            .create_account(key, Account::new(ledger.rent().minimum_balance(space), dex_program, AccountPayload::Empty))?;
+    }
+    let dex_tx = |signer: &Keypair, data: ProgramInstruction, accounts: Vec<AccountMeta>| -> Result<Transaction, SignerError> {
+        let mut tx = Transaction::new(signer.pubkey(), vec![Instruction::new(dex_program, data, accounts)]);
+        tx.sign(&[signer])?;
+        Ok(tx)
+    };
+    let create_market = dex_tx(&wallet_keypair, ProgramInstruction::CreateMarket, vec![
+        AccountMeta::new(market_key, dex_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+        AccountMeta::new_readonly(base_vault, my_program, false),
+        AccountMeta::new_readonly(quote_vault, my_program, false),
+    ])?;
+    println!("创建市场: {:?}", create_market.execute_with_programs(&mut ledger, &programs).result);
+    let ask = dex_tx(&wallet_keypair, ProgramInstruction::PlaceOrder { side: Side::Ask, price: 5, quantity: 10 }, vec![
+        AccountMeta::new(market_key, dex_program, false),
+        AccountMeta::new(order_keys[0], dex_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+        AccountMeta::new(wallet_base, my_program, false),
+        AccountMeta::new(base_vault, my_program, false),
+    ])?;
+    println!("钱包卖出10个@5: {:?}", ask.execute_with_programs(&mut ledger, &programs).result);
+    let bid = dex_tx(&taker, ProgramInstruction::PlaceOrder { side: Side::Bid, price: 6, quantity: 6 }, vec![
+        AccountMeta::new(market_key, dex_program, false),
+        AccountMeta::new(order_keys[1], dex_program, false),
+        AccountMeta::new_readonly(taker.pubkey(), system_program, true),
+        AccountMeta::new(taker_quote, my_program, false),
+        AccountMeta::new(quote_vault, my_program, false),
+        AccountMeta::new(order_keys[0], dex_program, false),
+    ])?;
+    println!("taker买入6个@6: {:?}", bid.execute_with_programs(&mut ledger, &programs).result);
+    if let Some(Account { data: AccountPayload::Market(market), .. }) = ledger.get_account(&market_key) {
+        println!("  卖单深度: {:?}", market.book.depth(Side::Ask));
+    }
+    for (signer, order_key, base, quote) in [(&wallet_keypair, order_keys[0], wallet_base, wallet_quote), (&taker, order_keys[1], taker_base, taker_quote)] {
+        let settle = dex_tx(signer, ProgramInstruction::SettleFunds, vec![
+            AccountMeta::new_readonly(market_key, dex_program, false),
+            AccountMeta::new(order_key, dex_program, false),
+            AccountMeta::new_readonly(signer.pubkey(), system_program, true),
+            AccountMeta::new(base_vault, my_program, false),
+            AccountMeta::new(quote_vault, my_program, false),
+            AccountMeta::new(base, my_program, false),
+            AccountMeta::new(quote, my_program, false),
+        ])?;
+        settle.execute_with_programs(&mut ledger, &programs).result?;
+        let balance = |key| match ledger.get_account(&key) {
+            Some(Account { data: AccountPayload::Token(token), .. }) => token.amount,
+            _ => 0,
+        };
+        println!("  结算后{}: base={}，quote={}", signer.pubkey(), balance(base), balance(quote));
+    }
+    println!();
+
+    // 25. 借贷
+    println!("25. 借贷:");
+    // 钱包抵押后按75%的抵押率借款，价格下跌后taker替它还款并拿走带5%奖励的抵押品
+    let (lending_program, oracle_program) = (Pubkey::new_unique(), ORACLE_PROGRAM_ID);
+    let programs = Programs::from([(lending_program, lending::process as Entrypoint), (oracle_program, oracle::process as Entrypoint)]);
+    let [collateral_mint, borrow_mint, price_oracle] = [(); 3].map(|_| Pubkey::new_unique());
+    for mint in [collateral_mint, borrow_mint] {
+        ledger
+            .create_account(mint, Account::new(ledger.rent().minimum_balance(Mint::LEN), my_program, AccountPayload::Mint(Mint::new(0, wallet))))?;
+    }
+    // 价格从当前slot开始算新鲜度，否则前面的演示推进过的slot会让它一创建就过期
+    let oracle_data = AccountPayload::PriceOracle(PriceOracle { last_update_slot: ledger.slot(), ..PriceOracle::new(wallet, 2 * PRICE_SCALE) });
+    ledger
+        .create_account(price_oracle, Account::new(ledger.rent().minimum_balance(PriceOracle::LEN), oracle_program, oracle_data))?;
+    let (lending_key, _) = lending_market_address(&collateral_mint, &borrow_mint, &lending_program);
+    let (obligation_key, _) = obligation_address(&lending_key, &wallet, &lending_program);
+    for (key, space) in [(lending_key, LendingMarket::LEN), (obligation_key, Obligation::LEN)] {
+        ledger
+            .create_account(key, Account::new(ledger.rent().minimum_balance(space), lending_program, AccountPayload::Empty))?;
+    }
+    let [wallet_collateral, wallet_borrowed, taker_collateral, taker_borrowed, collateral_vault, liquidity_vault] = [(); 6].map(|_| Pubkey::new_unique());
+    for (key, mint, owner, amount) in [
+        (wallet_collateral, collateral_mint, wallet, 100),
+        (wallet_borrowed, borrow_mint, wallet, 0),
+        (taker_collateral, collateral_mint, taker.pubkey(), 0),
+        (taker_borrowed, borrow_mint, taker.pubkey(), 1_000),
+        (collateral_vault, collateral_mint, lending_key, 0),
+        (liquidity_vault, borrow_mint, lending_key, 10_000),
+    ] {
+        let data = AccountPayload::Token(TokenAccount::new(mint, owner, amount));
+        ledger.create_account(key, Account::new(token_lamports, my_program, data))?;
+    }
+    let lending_tx = |signer: &Keypair, program: Pubkey, data: ProgramInstruction, accounts: Vec<AccountMeta>| -> Result<Transaction, SignerError> {
+        let mut tx = Transaction::new(signer.pubkey(), vec![Instruction::new(program, data, accounts)]);
+        tx.sign(&[signer])?;
+        Ok(tx)
+    };
+    let init = lending_tx(&wallet_keypair, lending_program, ProgramInstruction::InitLendingMarket { collateral_factor_bps: 7_500, liquidation_bonus_bps: 500 }, vec![
+        AccountMeta::new(lending_key, lending_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+        AccountMeta::new_readonly(collateral_vault, my_program, false),
+        AccountMeta::new_readonly(liquidity_vault, my_program, false),
+        AccountMeta::new_readonly(price_oracle, oracle_program, false),
+    ])?;
+    println!("创建借贷市场: {:?}", init.execute_with_programs(&mut ledger, &programs).result);
+    let deposit = lending_tx(&wallet_keypair, lending_program, ProgramInstruction::DepositCollateral { amount: 100 }, vec![
+        AccountMeta::new_readonly(lending_key, lending_program, false),
+        AccountMeta::new(obligation_key, lending_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+        AccountMeta::new(wallet_collateral, my_program, false),
+        AccountMeta::new(collateral_vault, my_program, false),
+    ])?;
+    println!("抵押100个: {:?}", deposit.execute_with_programs(&mut ledger, &programs).result);
+    for amount in [151, 150] {
+        let borrow = lending_tx(&wallet_keypair, lending_program, ProgramInstruction::Borrow { amount }, vec![
+            AccountMeta::new_readonly(lending_key, lending_program, false),
+            AccountMeta::new(obligation_key, lending_program, false),
+            AccountMeta::new_readonly(wallet, system_program, true),
+            AccountMeta::new_readonly(price_oracle, oracle_program, false),
+            AccountMeta::new(liquidity_vault, my_program, false),
+            AccountMeta::new(wallet_borrowed, my_program, false),
+        ])?;
+        println!("价格2.0时借{}: {:?}", amount, borrow.execute_with_programs(&mut ledger, &programs).result);
+    }
+    let update_price = lending_tx(&wallet_keypair, oracle_program, ProgramInstruction::UpdatePrice { price: 3 * PRICE_SCALE / 2 }, vec![
+        AccountMeta::new(price_oracle, oracle_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+    ])?;
+    println!("价格跌到1.5: {:?}", update_price.execute_with_programs(&mut ledger, &programs).result);
+    let liquidate = lending_tx(&taker, lending_program, ProgramInstruction::Liquidate { amount: 50 }, vec![
+        AccountMeta::new_readonly(lending_key, lending_program, false),
+        AccountMeta::new(obligation_key, lending_program, false),
+        AccountMeta::new_readonly(taker.pubkey(), system_program, true),
+        AccountMeta::new_readonly(price_oracle, oracle_program, false),
+        AccountMeta::new(taker_borrowed, my_program, false),
+        AccountMeta::new(liquidity_vault, my_program, false),
+        AccountMeta::new(collateral_vault, my_program, false),
+        AccountMeta::new(taker_collateral, my_program, false),
+    ])?;
+    println!("taker清算50: {:?}", liquidate.execute_with_programs(&mut ledger, &programs).result);
+    if let Some(Account { data: AccountPayload::Obligation(obligation), .. }) = ledger.get_account(&obligation_key) {
+        println!("  清算后仓位: 抵押{}，欠款{}", obligation.collateral, obligation.debt);
+    }
+    if let Some(Account { data: AccountPayload::Token(token), .. }) = ledger.get_account(&taker_collateral) {
+        println!("  taker拿到抵押品: {}", token.amount);
+    }
+    println!();
+
+    // 26. 众筹
+    println!("26. 众筹:");
+    // 钱包发起目标为500的众筹，taker出资600；截止之后达到目标，钱包取走筹款
+    let crowdfunding_program = Pubkey::new_unique();
+    let programs = Programs::from([(crowdfunding_program, crowdfunding::process as Entrypoint)]);
+    let [raise_mint, campaign_vault, taker_funds, wallet_proceeds] = [(); 4].map(|_| Pubkey::new_unique());
+    ledger
+        .create_account(raise_mint, Account::new(ledger.rent().minimum_balance(Mint::LEN), my_program, AccountPayload::Mint(Mint::new(0, wallet))))?;
+    let (campaign_key, _) = campaign_address(&wallet, &campaign_vault, &crowdfunding_program);
+    let (contribution_key, _) = contribution_address(&campaign_key, &taker.pubkey(), &crowdfunding_program);
+    for (key, space) in [(campaign_key, Campaign::LEN), (contribution_key, Contribution::LEN)] {
+        ledger
+            .create_account(key, Account::new(ledger.rent().minimum_balance(space), crowdfunding_program, AccountPayload::Empty))?;
+    }
+    for (key, owner, amount) in [(campaign_vault, campaign_key, 0), (taker_funds, taker.pubkey(), 1_000), (wallet_proceeds, wallet, 0)] {
+        let data = AccountPayload::Token(TokenAccount::new(raise_mint, owner, amount));
+        ledger.create_account(key, Account::new(token_lamports, my_program, data))?;
+    }
+    // 截止前后的取款是同一条指令，靠不同的blockhash区分，否则第二次会被当成重放
+    let crowdfunding_tx = |recent_blockhash, signer: &Keypair, data: ProgramInstruction, accounts: Vec<AccountMeta>| -> Result<Transaction, SignerError> {
+        let mut tx = Transaction::new(signer.pubkey(), vec![Instruction::new(crowdfunding_program, data, accounts)])
+            .with_recent_blockhash(recent_blockhash);
+        tx.sign(&[signer])?;
+        Ok(tx)
+    };
+    let deadline_slot = ledger.slot() + 10;
+    let create_campaign = crowdfunding_tx(ledger.latest_blockhash(), &wallet_keypair, ProgramInstruction::CreateCampaign { goal: 500, deadline_slot }, vec![
+        AccountMeta::new(campaign_key, crowdfunding_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+        AccountMeta::new_readonly(campaign_vault, my_program, false),
+    ])?;
+    println!("创建众筹（slot {}截止）: {:?}", deadline_slot, create_campaign.execute_with_programs(&mut ledger, &programs).result);
+    let contribute = crowdfunding_tx(ledger.latest_blockhash(), &taker, ProgramInstruction::Contribute { amount: 600 }, vec![
+        AccountMeta::new(campaign_key, crowdfunding_program, false),
+        AccountMeta::new(contribution_key, crowdfunding_program, false),
+        AccountMeta::new_readonly(taker.pubkey(), system_program, true),
+        AccountMeta::new(taker_funds, my_program, false),
+        AccountMeta::new(campaign_vault, my_program, false),
+    ])?;
+    println!("taker出资600: {:?}", contribute.execute_with_programs(&mut ledger, &programs).result);
+    let withdraw_accounts = vec![
+        AccountMeta::new(campaign_key, crowdfunding_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+        AccountMeta::new(campaign_vault, my_program, false),
+        AccountMeta::new(wallet_proceeds, my_program, false),
+    ];
+    let early = crowdfunding_tx(ledger.latest_blockhash(), &wallet_keypair, ProgramInstruction::Withdraw, withdraw_accounts.clone())?;
+    println!("截止前取款: {:?}", early.execute_with_programs(&mut ledger, &programs).result);
+    ledger.tick(10);
+    let withdraw = crowdfunding_tx(ledger.latest_blockhash(), &wallet_keypair, ProgramInstruction::Withdraw, withdraw_accounts)?;
+    println!("截止后取款: {:?}", withdraw.execute_with_programs(&mut ledger, &programs).result);
+    if let Some(Account { data: AccountPayload::Token(token), .. }) = ledger.get_account(&wallet_proceeds) {
+        println!("  钱包收到筹款: {}", token.amount);
+    }
+    println!();
+
+    // 27. 拍卖
+    println!("27. 拍卖:");
+    // 钱包再铸造一个NFT拿去拍卖，taker用众筹剩下的代币出价，截止后结算
+    match mint_nft(&mut ledger, my_program, &wallet_keypair, wallet, ("Sunset #2", "SUN", "https://example.com/sunset/2.json")) {
+        Ok(nft) => {
+            let auction_program = Pubkey::new_unique();
+            let programs = Programs::from([(auction_program, auction::process as Entrypoint)]);
+            let (auction_key, _) = auction_address(&wallet, &nft.mint, &auction_program);
+            let (taker_bid_record, _) = bid_record_address(&auction_key, &taker.pubkey(), &auction_program);
+            for (key, len) in [(auction_key, Auction::LEN), (taker_bid_record, BidRecord::LEN)] {
+                ledger.create_account(key, Account::new(ledger.rent().minimum_balance(len), auction_program, AccountPayload::Empty))?;
+            }
+            let [nft_vault, bid_vault, taker_nft] = [(); 3].map(|_| Pubkey::new_unique());
+            for (key, mint, owner) in [(nft_vault, nft.mint, auction_key), (bid_vault, raise_mint, auction_key), (taker_nft, nft.mint, taker.pubkey())] {
+                let data = AccountPayload::Token(TokenAccount::new(mint, owner, 0));
+                ledger.create_account(key, Account::new(token_lamports, my_program, data))?;
+            }
+            let auction_tx = |signer: &Keypair, data: ProgramInstruction, accounts: Vec<AccountMeta>| -> Result<Transaction, SignerError> {
+                let mut tx = Transaction::new(signer.pubkey(), vec![Instruction::new(auction_program, data, accounts)]);
+                tx.sign(&[signer])?;
+                Ok(tx)
+            };
+            let end_slot = ledger.slot() + 5;
+            let create_auction = auction_tx(&wallet_keypair, ProgramInstruction::CreateAuction { starting_bid: 100, min_increment: 20, end_slot }, vec![
+                AccountMeta::new(auction_key, auction_program, false),
+                AccountMeta::new_readonly(wallet, system_program, true),
+                AccountMeta::new(nft.token_account, my_program, false),
+                AccountMeta::new(nft_vault, my_program, false),
+                AccountMeta::new_readonly(bid_vault, my_program, false),
+            ])?;
+            println!("创建拍卖（起拍价100，最小加价20）: {:?}", create_auction.execute_with_programs(&mut ledger, &programs).result);
+            for amount in [90, 150] {
+                let bid = auction_tx(&taker, ProgramInstruction::PlaceBid { amount }, vec![
+                    AccountMeta::new(auction_key, auction_program, false),
+                    AccountMeta::new_readonly(taker.pubkey(), system_program, true),
+                    AccountMeta::new(taker_funds, my_program, false),
+                    AccountMeta::new(bid_vault, my_program, false),
+                    AccountMeta::new(taker_bid_record, auction_program, false),
+                ])?;
+                println!("taker出价{}: {:?}", amount, bid.execute_with_programs(&mut ledger, &programs).result);
+            }
+            ledger.tick(5);
+            let settle = auction_tx(&taker, ProgramInstruction::SettleAuction, vec![
+                AccountMeta::new(auction_key, auction_program, false),
+                AccountMeta::new(nft_vault, my_program, false),
+                AccountMeta::new(taker_nft, my_program, false),
+                AccountMeta::new(bid_vault, my_program, false),
+                AccountMeta::new(wallet_proceeds, my_program, false),
+            ])?;
+            println!("截止后结算: {:?}", settle.execute_with_programs(&mut ledger, &programs).result);
+            let balance = |key| match ledger.get_account(&key) {
+                Some(Account { data: AccountPayload::Token(token), .. }) => token.amount,
+                _ => 0,
+            };
+            println!("  taker持有NFT: {}，钱包累计收到: {}", balance(taker_nft), balance(wallet_proceeds));
+        }
+        Err(e) => println!("铸造失败: {}", e),
+    }
+    println!();
+
+    // 28. 支付流
+    println!("28. 支付流:");
+    // 钱包用收到的筹款给taker开一个每slot 10个的支付流，taker中途取款，之后钱包取消
+    let streaming_program = Pubkey::new_unique();
+    let programs = Programs::from([(streaming_program, streaming::process as Entrypoint)]);
+    let stream_vault = Pubkey::new_unique();
+    let (stream_key, _) = stream_address(&wallet, &stream_vault, &streaming_program);
+    ledger
+        .create_account(stream_key, Account::new(ledger.rent().minimum_balance(Stream::LEN), streaming_program, AccountPayload::Empty))?;
+    let data = AccountPayload::Token(TokenAccount::new(raise_mint, stream_key, 0));
+    ledger.create_account(stream_vault, Account::new(token_lamports, my_program, data))?;
+    let stream_tx = |signer: &Keypair, data: ProgramInstruction, accounts: Vec<AccountMeta>| -> Result<Transaction, SignerError> {
+        let mut tx = Transaction::new(signer.pubkey(), vec![Instruction::new(streaming_program, data, accounts)]);
+        tx.sign(&[signer])?;
+        Ok(tx)
+    };
+    let create_stream = stream_tx(&wallet_keypair, ProgramInstruction::CreateStream { amount: 100, rate_per_slot: 10, start_slot: ledger.slot() }, vec![
+        AccountMeta::new(stream_key, streaming_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+        AccountMeta::new(wallet_proceeds, my_program, false),
+        AccountMeta::new_readonly(taker.pubkey(), system_program, false),
+        AccountMeta::new(stream_vault, my_program, false),
+    ])?;
+    println!("创建支付流（100个，每slot 10个）: {:?}", create_stream.execute_with_programs(&mut ledger, &programs).result);
+    ledger.tick(3);
+    let withdraw = stream_tx(&taker, ProgramInstruction::Withdraw, vec![
+        AccountMeta::new(stream_key, streaming_program, false),
+        AccountMeta::new_readonly(taker.pubkey(), system_program, true),
+        AccountMeta::new(stream_vault, my_program, false),
+        AccountMeta::new(taker_funds, my_program, false),
+    ])?;
+    println!("3个slot后taker取款: {:?}", withdraw.execute_with_programs(&mut ledger, &programs).result);
+    ledger.tick(2);
+    let cancel = stream_tx(&wallet_keypair, ProgramInstruction::CancelStream, vec![
+        AccountMeta::new(stream_key, streaming_program, false),
+        AccountMeta::new_readonly(wallet, system_program, true),
+        AccountMeta::new(stream_vault, my_program, false),
+        AccountMeta::new(wallet_proceeds, my_program, false),
+        AccountMeta::new(taker_funds, my_program, false),
+    ])?;
+    println!("再过2个slot钱包取消: {:?}", cancel.execute_with_programs(&mut ledger, &programs).result);
+    if let Some(Account { data: AccountPayload::Stream(stream), .. }) = ledger.get_account(&stream_key) {
+        println!("  taker共收到{}，退回钱包{}", stream.withdrawn, stream.deposited - stream.withdrawn);
+    }
+    println!();
+
+    // 29. 校验器
+    println!("29. 校验器:");
+    // 一次列出所有问题：taker的收款账户既不是钱包的、余额也不够1000
+    let token_rules = Validator::token_account().mint(raise_mint).owner(wallet).min_balance(1_000);
+    if let Some(Account { data: AccountPayload::Token(token), .. }) = ledger.get_account(&taker_funds) {
+        match token_rules.validate(token) {
+            Ok(()) => println!("taker收款账户通过校验"),
+            Err(errors) => {
+                println!("taker收款账户有{}个问题:", errors.len());
+                for error in &errors {
+                    println!("  - {}", error);
+                }
+            }
+        }
+    }
+    let unsigned = Transaction::new(wallet, vec![]);
+    let tx_rules = Validator::transaction().check(move |tx: &Transaction| {
+        if tx.fee_payer == wallet { Ok(()) } else { Err(ValidationError::Custom("fee payer必须是钱包".to_string())) }
+    });
+    println!("空的未签名交易: {:?}", tx_rules.validate(&unsigned).map_err(|errors| errors.iter().map(|e| e.to_string()).collect::<Vec<_>>()));
+    println!();
+
+    // 30. 交易builder
+    println!("30. 交易builder:");
+    // 忘了fee payer和指令、同一个签名者传了两次：一次报告所有问题
+    match TransactionBuilder::new().signer(&taker).signer(&taker).build() {
+        Ok(_) => println!("构建成功"),
+        Err(error) => println!("{}", error),
+    }
+    let refund = InstructionBuilder::transfer()
+        .program(my_program)
+        .amount(50)
+        .from(taker_funds)
+        .to(wallet_proceeds)
+        .signer(taker.pubkey())
+        .build()?;
+    let built = TransactionBuilder::new()
+        .fee_payer(taker.pubkey())
+        .instruction(refund)
+        .recent_blockhash(ledger.latest_blockhash())
+        .signer(&taker)
+        .build();
+    match built {
+        Ok(tx) => println!("taker退给钱包50: {:?}", tx.execute(&mut ledger)),
+        Err(error) => println!("{}", error),
+    }
+    println!();
+    
+    // 31. 账本JSON导入导出
+    println!("31. 账本JSON导入导出:");
+    let path = std::env::temp_dir().join("generics_test_ledger.json");
+    std::fs::write(&path, ledger.to_json())?;
+    println!("导出{}个账户到{}", ledger.len(), path.display());
+    // 模拟手工修改导出的文件：把slot改成1000后读回
+    let edited = std::fs::read_to_string(&path)?.replacen(&format!("\"slot\": {}", ledger.slot()), "\"slot\": 1000", 1);
+    let reloaded = Ledger::from_json(&edited)?;
+    let same = if reloaded.snapshot() == ledger.snapshot() { "一致" } else { "不一致" };
+    println!("读回后slot={}，{}个账户，账户状态与导出前{}", reloaded.slot(), reloaded.len(), same);
+    let newer = edited.replacen(&format!("\"version\": {}", LEDGER_JSON_VERSION), "\"version\": 99", 1);
+    if let Err(error) = Ledger::from_json(&newer) {
+        println!("读取更新版本的文件: {}", error);
+    }
+    std::fs::remove_file(&path)?;
+    println!();
+    
+    // 32. 二进制快照文件
+    println!("32. 二进制快照文件:");
+    let path = std::env::temp_dir().join("generics_test_ledger.snapshot");
+    write_snapshot(&ledger.snapshot(), std::fs::File::create(&path)?)?;
+    println!("保存{}个账户的快照: {}字节", ledger.len(), std::fs::metadata(&path)?.len());
+    let before = ledger.snapshot();
+    ledger.transfer_lamports(&taker.pubkey(), &wallet, 1_000)?;
+    ledger.restore(read_snapshot(std::fs::File::open(&path)?)?);
+    println!("转账后从文件恢复: 账户状态与保存时{}", if ledger.snapshot() == before { "一致" } else { "不一致" });
+    // 文件中间有一个字节被改动
+    let mut damaged = std::fs::read(&path)?;
+    damaged[100] ^= 0xff;
+    if let Err(error) = read_snapshot(damaged.as_slice()) {
+        println!("读取损坏的快照: {}", error);
+    }
+    std::fs::remove_file(&path)?;
+    println!();
+    
+    // 33. 端到端教程：从零发行一个代币
+    println!("33. 端到端教程:");
+    tutorial_token::run()?;
+    println!();
+    
     println!("=== 学习完成！你现在已经掌握了Trait和泛型的基础知识 ===");
     println!("这些概念在Solana合约开发中无处不在，继续深入学习吧！");
+    Ok(())
 }
 
 // ===============================
@@ -307,11 +2590,7 @@ mod tests {
     
     #[test]
     fn test_trait_implementation() {
-        let token = TokenAccount {
-            mint: "test_mint".to_string(),
-            owner: "test_owner".to_string(),
-            amount: 100,
-        };
+        let token = TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 100);
         
         assert!(token.validate());
         assert!(token.summarize().contains("Token账户"));
@@ -325,27 +2604,373 @@ mod tests {
             created_at: 1640995200,
         };
         
+        let key = Pubkey::new_unique();
         let wrapped = AccountWrapper::new(
-            "test_key".to_string(),
+            key,
             user,
-            "test_owner".to_string(),
+            Pubkey::new_unique(),
         );
         
-        assert_eq!(wrapped.get_key(), "test_key");
+        assert_eq!(wrapped.get_key(), &key);
         assert!(wrapped.summarize().contains("包装账户"));
     }
     
     #[test]
-    fn test_program_processor() {
-        let token = TokenAccount {
-            mint: "test_mint".to_string(),
-            owner: "test_owner".to_string(),
-            amount: 100,
+    fn test_instruction_bytes() {
+        let instruction = ProgramInstruction::Transfer { amount: 258 };
+        let bytes = instruction.to_bytes();
+        assert_eq!(bytes, vec![1, 2, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(ProgramInstruction::try_from_bytes(&bytes), Ok(instruction));
+        
+        for instruction in [
+            ProgramInstruction::Initialize { initial_supply: 1_000_000 },
+            ProgramInstruction::Mint { amount: u64::MAX },
+            ProgramInstruction::AdvanceNonce,
+            ProgramInstruction::Approve { amount: 42 },
+            ProgramInstruction::Revoke,
+            ProgramInstruction::Burn { amount: 7 },
+            ProgramInstruction::FreezeAccount,
+            ProgramInstruction::ThawAccount,
+            ProgramInstruction::CreateMetadata { name: "Coin".into(), symbol: "C".into(), uri: String::new() },
+            ProgramInstruction::CloseTokenAccount,
+            ProgramInstruction::InitializeEscrow { amount: 5, expected_amount: 9 },
+            ProgramInstruction::Exchange { amount: 5 },
+            ProgramInstruction::CancelEscrow,
+            ProgramInstruction::CreateVesting { amount: 1_000, start_slot: 1, cliff_slot: 2, end_slot: 3 },
+            ProgramInstruction::Claim,
+            ProgramInstruction::InitializePool { fee_basis_points: 30 },
+            ProgramInstruction::AddLiquidity { amount_a: 1, amount_b: 2 },
+            ProgramInstruction::RemoveLiquidity { lp_amount: 3 },
+            ProgramInstruction::Swap { amount_in: 4, min_amount_out: 5 },
+            ProgramInstruction::CreateGovernance { quorum: 500, threshold_basis_points: 6_000, voting_slots: 100 },
+            ProgramInstruction::CreateProposal {
+                instruction: Box::new(Instruction::new(
+                    Pubkey::new_unique(),
+                    ProgramInstruction::Transfer { amount: 1 },
+                    vec![AccountMeta::new_readonly(Pubkey::new_unique(), Pubkey::new_unique(), true)],
+                )),
+            },
+            ProgramInstruction::CastVote { approve: true },
+            ProgramInstruction::FinalizeProposal,
+            ProgramInstruction::SetMetadataAuthority { new_authority: Pubkey::new_unique() },
+            ProgramInstruction::CreateMarket,
+            ProgramInstruction::PlaceOrder { side: Side::Ask, price: 25, quantity: 4 },
+            ProgramInstruction::CancelOrder,
+            ProgramInstruction::SettleFunds,
+            ProgramInstruction::UpdatePrice { price: 1_500_000 },
+            ProgramInstruction::InitLendingMarket { collateral_factor_bps: 7_500, liquidation_bonus_bps: 500 },
+            ProgramInstruction::DepositCollateral { amount: 100 },
+            ProgramInstruction::Borrow { amount: 50 },
+            ProgramInstruction::Repay { amount: 20 },
+            ProgramInstruction::Liquidate { amount: 10 },
+            ProgramInstruction::CreateCampaign { goal: 1_000, deadline_slot: 100 },
+            ProgramInstruction::Contribute { amount: 300 },
+            ProgramInstruction::Withdraw,
+            ProgramInstruction::Refund,
+            ProgramInstruction::CreateAuction { starting_bid: 100, min_increment: 10, end_slot: 50 },
+            ProgramInstruction::PlaceBid { amount: 120 },
+            ProgramInstruction::SettleAuction,
+            ProgramInstruction::CreateStream { amount: 1_000, rate_per_slot: 7, start_slot: 10 },
+            ProgramInstruction::CancelStream,
+        ] {
+            assert_eq!(ProgramInstruction::try_from_bytes(&instruction.to_bytes()), Ok(instruction));
+        }
+        
+        assert_eq!(ProgramInstruction::AdvanceNonce.to_bytes(), vec![3]);
+        assert_eq!(ProgramInstruction::try_from_bytes(&[255]), Err(BorshError::InvalidEnumTag(255)));
+        assert!(ProgramInstruction::try_from_bytes(&[2, 1]).is_err());
+        assert!(ProgramInstruction::try_from_bytes(&[]).is_err());
+    }
+    
+    #[test]
+    fn test_wrapper_bytes_roundtrip() {
+        let token = TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 42);
+        let wrapped = AccountWrapper::new(Pubkey::new_unique(), token.clone(), TOKEN_PROGRAM_ID);
+        let bytes = wrapped.to_bytes();
+        assert_eq!(bytes.len(), 8 + 32 + 32 + 8 + 1 + 8 + 1);
+        
+        let restored = AccountWrapper::<TokenAccount>::from_bytes(wrapped.key, wrapped.owner, &bytes).unwrap();
+        assert_eq!(restored.data, token);
+        assert_eq!(restored.key, wrapped.key);
+        
+        // 数据被截断或者类型不匹配时解析失败
+        assert!(AccountWrapper::<TokenAccount>::from_bytes(wrapped.key, wrapped.owner, &bytes[..40]).is_err());
+        assert!(matches!(
+            AccountWrapper::<UserAccount>::from_bytes(wrapped.key, wrapped.owner, &bytes),
+            Err(BorshError::DiscriminatorMismatch { .. })
+        ));
+    }
+    
+    #[test]
+    fn test_account_borsh_layouts() {
+        fn borsh_bytes(value: &impl BorshSerialize) -> Vec<u8> {
+            let mut out = Vec::new();
+            value.serialize(&mut out);
+            out
+        }
+        
+        let mut token = TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 500);
+        token.approve(Pubkey::new_unique(), 200);
+        let bytes = borsh_bytes(&token);
+        assert_eq!(bytes.len(), 32 + 32 + 8 + 33 + 8 + 1);
+        assert_eq!(&bytes[..32], token.mint.as_ref());
+        assert_eq!(&bytes[64..72], &500u64.to_le_bytes());
+        assert_eq!(TokenAccount::try_from_slice(&bytes), Ok(token.clone()));
+        
+        let user = UserAccount { username: "alice".to_string(), balance: 7, created_at: -1 };
+        let bytes = borsh_bytes(&user);
+        assert_eq!(&bytes[..9], &[5, 0, 0, 0, b'a', b'l', b'i', b'c', b'e']);
+        assert_eq!(bytes.len(), 4 + 5 + 8 + 8);
+        assert_eq!(UserAccount::try_from_slice(&bytes), Ok(user));
+        
+        // 整个包装账户：元数据在前，数据在后
+        let wrapped = AccountWrapper::new(Pubkey::new_unique(), token.clone(), TOKEN_PROGRAM_ID)
+            .with_lamports(2_039_280)
+            .with_writable(true);
+        let bytes = borsh_bytes(&wrapped);
+        assert_eq!(bytes.len(), 32 + 32 + 8 + 1 + 1 + borsh_bytes(&token).len());
+        assert_eq!(&bytes[72..74], &[0, 1]);
+        let restored = AccountWrapper::<TokenAccount>::try_from_slice(&bytes).unwrap();
+        assert_eq!(
+            (restored.key, restored.owner, restored.lamports, restored.is_signer, restored.is_writable, restored.data),
+            (wrapped.key, wrapped.owner, 2_039_280, false, true, token)
+        );
+        assert!(AccountWrapper::<TokenAccount>::try_from_slice(&bytes[..bytes.len() - 1]).is_err());
+    }
+    
+    #[test]
+    fn test_signer_and_writable_checks() {
+        let token = TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 100);
+        let wrap = |writable: bool| {
+            AccountWrapper::new(Pubkey::new_unique(), token.clone(), TOKEN_PROGRAM_ID).with_writable(writable)
+        };
+        let signer = AccountWrapper::new(token.owner, (), SYSTEM_PROGRAM_ID).with_signer(true);
+        let non_signer = AccountWrapper::new(token.owner, (), SYSTEM_PROGRAM_ID);
+        
+        assert_eq!(signer.assert_signer(), Ok(()));
+        assert_eq!(non_signer.assert_signer(), Err(TransactionResult::MissingRequiredSignature));
+        assert_eq!(wrap(false).assert_writable(), Err(TransactionResult::AccountNotWritable));
+        
+        assert_eq!(
+            transfer_tokens(&mut wrap(true), &mut wrap(true), Authority::Single(&signer), 10),
+            TransactionResult::Success
+        );
+        assert_eq!(
+            transfer_tokens(&mut wrap(false), &mut wrap(true), Authority::Single(&signer), 10),
+            TransactionResult::AccountNotWritable
+        );
+        assert_eq!(
+            transfer_tokens(&mut wrap(true), &mut wrap(false), Authority::Single(&signer), 10),
+            TransactionResult::AccountNotWritable
+        );
+        assert_eq!(
+            transfer_tokens(&mut wrap(true), &mut wrap(true), Authority::Single(&non_signer), 10),
+            TransactionResult::MissingRequiredSignature
+        );
+        
+        // 多签：达到门限才允许转账
+        let council = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let multisig = Multisig::new(2, council.to_vec()).unwrap();
+        assert_eq!(
+            transfer_tokens(&mut wrap(true), &mut wrap(true), Authority::<()>::Multisig(&multisig, &council[1..]), 10),
+            TransactionResult::Success
+        );
+        assert_eq!(
+            transfer_tokens(&mut wrap(true), &mut wrap(true), Authority::<()>::Multisig(&multisig, &council[..1]), 10),
+            TransactionResult::MissingRequiredSignature
+        );
+    }
+    
+    #[test]
+    fn test_transfer_tokens_moves_balances() {
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let wrap = |mint: Pubkey, amount: u64| {
+            AccountWrapper::new(Pubkey::new_unique(), TokenAccount::new(mint, owner, amount), TOKEN_PROGRAM_ID)
+                .with_writable(true)
         };
+        let signer = AccountWrapper::new(owner, (), SYSTEM_PROGRAM_ID).with_signer(true);
+        
+        let (mut from, mut to) = (wrap(mint, 100), wrap(mint, 5));
+        assert_eq!(transfer_tokens(&mut from, &mut to, Authority::Single(&signer), 40), TransactionResult::Success);
+        assert_eq!((from.data.amount, to.data.amount), (60, 45));
+        
+        // 恰好转出全部余额
+        assert_eq!(transfer_tokens(&mut from, &mut to, Authority::Single(&signer), 60), TransactionResult::Success);
+        assert_eq!((from.data.amount, to.data.amount), (0, 105));
+        
+        // 余额不足
+        assert_eq!(transfer_tokens(&mut from, &mut to, Authority::Single(&signer), 1), TransactionResult::InsufficientFunds);
+        assert_eq!((from.data.amount, to.data.amount), (0, 105));
         
+        // mint不一致
+        let mut other = wrap(Pubkey::new_unique(), 0);
+        assert_eq!(transfer_tokens(&mut to, &mut other, Authority::Single(&signer), 1), TransactionResult::InvalidAccount);
+        assert_eq!((to.data.amount, other.data.amount), (105, 0));
+        
+        // 收款方余额溢出
+        let mut full = wrap(mint, u64::MAX);
+        assert_eq!(transfer_tokens(&mut to, &mut full, Authority::Single(&signer), 1), TransactionResult::ArithmeticOverflow);
+        assert_eq!((to.data.amount, full.data.amount), (105, u64::MAX));
+        
+        // 任意一方被冻结
+        let mut frozen = wrap(mint, 0);
+        frozen.data.state = AccountState::Frozen;
+        assert_eq!(transfer_tokens(&mut to, &mut frozen, Authority::Single(&signer), 1), TransactionResult::AccountFrozen);
+        frozen.data.amount = 10;
+        assert_eq!(transfer_tokens(&mut frozen, &mut to, Authority::Single(&signer), 1), TransactionResult::AccountFrozen);
+        assert_eq!((to.data.amount, frozen.data.amount), (105, 10));
+    }
+    
+    #[test]
+    fn test_program_processor() {
+        let token = TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 100);
+        
+        let program_id = Pubkey::new_unique();
+        let meta = AccountMeta::new(Pubkey::new_unique(), program_id, true);
         let instruction = ProgramInstruction::Initialize { initial_supply: 1000 };
-        let result = ProgramProcessor::process_instruction(instruction, vec![&token]);
+        let result = ProgramProcessor::process(&program_id, vec![(&token, meta)], &instruction.to_bytes());
+        
+        assert_eq!(result, TransactionResult::Success);
         
+        // 未知的指令类型或字段不完整
+        for data in [&[9u8, 0][..], &[1, 2, 3], &[]] {
+            assert_eq!(
+                ProgramProcessor::process(&program_id, vec![(&token, meta)], data),
+                TransactionResult::InvalidInstructionData
+            );
+        }
+    }
+    
+    #[test]
+    fn test_compute_budget() {
+        let token = TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 100);
+        let program_id = Pubkey::new_unique();
+        let meta = AccountMeta::new(Pubkey::new_unique(), program_id, true);
+        let data = ProgramInstruction::Mint { amount: 1 }.to_bytes();
+        
+        // 指令固定开销 + 一行日志
+        let mut budget = ComputeBudget::new(10_000);
+        let result = ProgramProcessor::process_with_budget(&mut budget, &program_id, vec![(&token, meta)], &data);
         assert_eq!(result, TransactionResult::Success);
+        assert_eq!(budget.consumed(), compute::INSTRUCTION_COST + compute::LOG_BASE_COST);
+        assert_eq!(budget.logs().lines(), ["铸造代币，数量: 1"]);
+        
+        // 够执行指令但不够打印日志
+        let mut budget = ComputeBudget::new(compute::INSTRUCTION_COST + 1);
+        let result = ProgramProcessor::process_with_budget(&mut budget, &program_id, vec![(&token, meta)], &data);
+        assert_eq!(result, TransactionResult::ComputeBudgetExceeded);
+        assert!(budget.logs().is_empty());
+    }
+    
+    #[test]
+    fn test_process_instruction_locked() {
+        let token = TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 100);
+        let program_id = Pubkey::new_unique();
+        let meta = AccountMeta::new(Pubkey::new_unique(), program_id, true);
+        let mut locks = AccountLockManager::new();
+        
+        let result = ProgramProcessor::process_instruction_locked(
+            &mut locks,
+            &program_id,
+            vec![(&token, meta)],
+            &ProgramInstruction::Mint { amount: 1 }.to_bytes(),
+        );
+        assert_eq!(result, TransactionResult::Success);
+        // 执行结束后锁已释放
+        assert_eq!(locks.lock_state(&meta.pubkey), None);
+        
+        locks.lock_accounts(&[meta]).unwrap();
+        let result = ProgramProcessor::process_instruction_locked(
+            &mut locks,
+            &program_id,
+            vec![(&token, meta)],
+            &ProgramInstruction::Mint { amount: 1 }.to_bytes(),
+        );
+        assert_eq!(result, TransactionResult::AccountInUse);
+    }
+    
+    #[test]
+    fn test_process_batch() {
+        let program_id = Pubkey::new_unique();
+        let (signer, missing) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let token = TokenAccount::new(Pubkey::new_unique(), signer, 100);
+        let accounts = BTreeMap::from([(signer, token)]);
+        let mint = |key, amount| {
+            Instruction::new(program_id, ProgramInstruction::Mint { amount }, vec![AccountMeta::new(key, program_id, true)])
+        };
+        let batch = || vec![mint(signer, 1), mint(missing, 2), mint(signer, 3)];
+        
+        let report = ProgramProcessor::process_batch(&accounts, batch(), BatchMode::ContinueOnError);
+        assert_eq!(
+            report.results,
+            [TransactionResult::Success, TransactionResult::InvalidAccount, TransactionResult::Success]
+        );
+        assert_eq!((report.succeeded(), report.failed(), report.skipped), (2, 1, 0));
+        assert_eq!(report.compute_units_consumed, 2 * (compute::INSTRUCTION_COST + compute::LOG_BASE_COST));
+        
+        // 第二条失败后停止，第三条被跳过
+        let report = ProgramProcessor::process_batch(&accounts, batch(), BatchMode::StopOnError);
+        assert_eq!(report.results, [TransactionResult::Success, TransactionResult::InvalidAccount]);
+        assert_eq!((report.succeeded(), report.failed(), report.skipped), (1, 1, 1));
+        assert_eq!(report.to_string(), format!("成功1条，失败1条，跳过1条，消耗{} compute units", report.compute_units_consumed));
+    }
+    
+    #[test]
+    fn test_process_batch_until() {
+        let program_id = Pubkey::new_unique();
+        let signer = Pubkey::new_unique();
+        let accounts = BTreeMap::from([(signer, TokenAccount::new(Pubkey::new_unique(), signer, 100))]);
+        let batch: Vec<Instruction> = (1..=4)
+            .map(|amount| Instruction::new(program_id, ProgramInstruction::Mint { amount }, vec![AccountMeta::new(signer, program_id, true)]))
+            .collect();
+        let per_instruction = compute::INSTRUCTION_COST + compute::LOG_BASE_COST;
+        
+        // 累计消耗达到两条指令的量就停止，Break的值原样返回
+        let (report, flow) = ProgramProcessor::process_batch_until(&accounts, batch.clone(), |index, _, consumed| {
+            if consumed >= 2 * per_instruction { ControlFlow::Break(index) } else { ControlFlow::Continue(()) }
+        });
+        assert_eq!(flow, ControlFlow::Break(1));
+        assert_eq!((report.succeeded(), report.skipped, report.compute_units_consumed), (2, 2, 2 * per_instruction));
+        
+        let (report, flow) = ProgramProcessor::process_batch_until(&accounts, batch, |_, _, _| ControlFlow::<()>::Continue(()));
+        assert_eq!((flow, report.succeeded()), (ControlFlow::Continue(()), 4));
+    }
+    
+    #[test]
+    fn test_account_validation() {
+        let program_id = Pubkey::new_unique();
+        let other_program = Pubkey::new_unique();
+        let signer = AccountMeta::new_readonly(Pubkey::new_unique(), other_program, true);
+        
+        // 可写且属于本程序的账户 + 签名者
+        let writable = AccountMeta::new(Pubkey::new_unique(), program_id, false);
+        assert_eq!(
+            ProgramProcessor::validate_accounts(&program_id, &[writable, signer]),
+            TransactionResult::Success
+        );
+        
+        // 写入其他程序拥有的账户
+        let foreign = AccountMeta::new(Pubkey::new_unique(), other_program, true);
+        assert_eq!(
+            ProgramProcessor::validate_accounts(&program_id, &[foreign]),
+            TransactionResult::IllegalOwner
+        );
+        
+        // 没有任何签名者
+        assert_eq!(
+            ProgramProcessor::validate_accounts(&program_id, &[writable]),
+            TransactionResult::MissingRequiredSignature
+        );
+        
+        // 没有账户，或者第一个账户只读
+        assert_eq!(
+            ProgramProcessor::validate_accounts(&program_id, &[]),
+            TransactionResult::InvalidAccount
+        );
+        assert_eq!(
+            ProgramProcessor::validate_accounts(&program_id, &[signer, writable]),
+            TransactionResult::InvalidAccount
+        );
     }
 }
\ No newline at end of file
@@ -0,0 +1,156 @@
+// Token元数据 - 类似于Metaplex的Metadata账户：保存代币的名称、符号和URI。
+// 元数据账户地址是由mint推导出的PDA，知道mint就能找到它，不需要另外记录
+
+use std::fmt;
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::pda::derive_pda;
+use crate::Pubkey;
+
+pub const METADATA_SEED: &[u8] = b"metadata";
+
+// 与Metaplex一致的长度上限
+pub const MAX_NAME_LEN: usize = 32;
+pub const MAX_SYMBOL_LEN: usize = 10;
+pub const MAX_URI_LEN: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataError {
+    NameTooLong,
+    SymbolTooLong,
+    UriTooLong,
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataError::NameTooLong => write!(f, "名称最长{}字节", MAX_NAME_LEN),
+            MetadataError::SymbolTooLong => write!(f, "符号最长{}字节", MAX_SYMBOL_LEN),
+            MetadataError::UriTooLong => write!(f, "URI最长{}字节", MAX_URI_LEN),
+        }
+    }
+}
+
+impl std::error::Error for MetadataError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenMetadata {
+    pub mint: Pubkey,
+    pub update_authority: Pubkey, // 只有它可以修改元数据
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+impl TokenMetadata {
+    // 按最大长度分配空间，修改元数据时不需要realloc：
+    // mint(32) + update_authority(32) + 三个字符串(各4字节长度前缀 + 内容)
+    pub const LEN: usize = 32 + 32 + (4 + MAX_NAME_LEN) + (4 + MAX_SYMBOL_LEN) + (4 + MAX_URI_LEN);
+
+    pub fn new(mint: Pubkey, update_authority: Pubkey, name: &str, symbol: &str, uri: &str) -> Result<Self, MetadataError> {
+        let mut metadata = Self {
+            mint,
+            update_authority,
+            name: String::new(),
+            symbol: String::new(),
+            uri: String::new(),
+        };
+        metadata.update(name, symbol, uri)?;
+        Ok(metadata)
+    }
+
+    // 三个字段都检查通过后才会修改
+    pub fn update(&mut self, name: &str, symbol: &str, uri: &str) -> Result<(), MetadataError> {
+        if name.len() > MAX_NAME_LEN {
+            return Err(MetadataError::NameTooLong);
+        }
+        if symbol.len() > MAX_SYMBOL_LEN {
+            return Err(MetadataError::SymbolTooLong);
+        }
+        if uri.len() > MAX_URI_LEN {
+            return Err(MetadataError::UriTooLong);
+        }
+        self.name = name.to_string();
+        self.symbol = symbol.to_string();
+        self.uri = uri.to_string();
+        Ok(())
+    }
+}
+
+impl fmt::Display for TokenMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.symbol)
+    }
+}
+
+// 元数据账户地址：种子为["metadata", mint]，由处理元数据指令的程序推导
+pub fn metadata_address(mint: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    derive_pda(&[METADATA_SEED, mint.as_ref()], program_id).0
+}
+
+impl BorshSerialize for TokenMetadata {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.mint.serialize(out);
+        self.update_authority.serialize(out);
+        self.name.serialize(out);
+        self.symbol.serialize(out);
+        self.uri.serialize(out);
+    }
+}
+
+impl BorshDeserialize for TokenMetadata {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            mint: Pubkey::deserialize(buf)?,
+            update_authority: Pubkey::deserialize(buf)?,
+            name: String::deserialize(buf)?,
+            symbol: String::deserialize(buf)?,
+            uri: String::deserialize(buf)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_limits() {
+        let (mint, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut metadata = TokenMetadata::new(mint, authority, "USD Coin", "USDC", "https://example.com/usdc.json").unwrap();
+        assert_eq!(metadata.to_string(), "USD Coin (USDC)");
+
+        let long_symbol = "S".repeat(MAX_SYMBOL_LEN + 1);
+        assert_eq!(metadata.update("New", &long_symbol, ""), Err(MetadataError::SymbolTooLong));
+        // 失败时保持原样
+        assert_eq!(metadata.name, "USD Coin");
+        assert_eq!(
+            TokenMetadata::new(mint, authority, &"n".repeat(MAX_NAME_LEN + 1), "", "").unwrap_err(),
+            MetadataError::NameTooLong
+        );
+        assert_eq!(
+            TokenMetadata::new(mint, authority, "", "", &"u".repeat(MAX_URI_LEN + 1)).unwrap_err(),
+            MetadataError::UriTooLong
+        );
+    }
+
+    #[test]
+    fn test_borsh_roundtrip_fits_len() {
+        let name = "n".repeat(MAX_NAME_LEN);
+        let symbol = "s".repeat(MAX_SYMBOL_LEN);
+        let uri = "u".repeat(MAX_URI_LEN);
+        let metadata = TokenMetadata::new(Pubkey::new_unique(), Pubkey::new_unique(), &name, &symbol, &uri).unwrap();
+        let mut bytes = Vec::new();
+        metadata.serialize(&mut bytes);
+        assert_eq!(bytes.len(), TokenMetadata::LEN);
+        assert_eq!(TokenMetadata::try_from_slice(&bytes), Ok(metadata));
+    }
+
+    #[test]
+    fn test_metadata_address_depends_on_mint() {
+        let program = Pubkey::new_unique();
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(metadata_address(&a, &program), metadata_address(&a, &program));
+        assert_ne!(metadata_address(&a, &program), metadata_address(&b, &program));
+    }
+}
@@ -0,0 +1,230 @@
+// Mint账户 - 类似于SPL Token的Mint：记录一种代币的总供应量、精度和权限。
+// Token账户的mint字段指向它，铸造时supply和Token账户余额一起增加
+
+use std::fmt;
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::ui_amount::{UiAmount, UiAmountError};
+use crate::{Pubkey, Summary};
+
+// 1个基点 = 0.01%
+pub const MAX_FEE_BASIS_POINTS: u16 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferFeeError {
+    BasisPointsTooHigh(u16), // 费率超过100%
+}
+
+impl fmt::Display for TransferFeeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferFeeError::BasisPointsTooHigh(bps) => {
+                write!(f, "费率{}个基点超过上限{}", bps, MAX_FEE_BASIS_POINTS)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TransferFeeError {}
+
+// 转账手续费扩展 - 类似于Token-2022的TransferFeeConfig：
+// 每笔转账按比例扣下一部分，存进fee_vault（同一mint的Token账户），收款方收到扣费后的数量
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferFeeConfig {
+    pub basis_points: u16,
+    pub maximum_fee: u64, // 单笔手续费上限
+    pub fee_vault: Pubkey,
+}
+
+impl TransferFeeConfig {
+    // basis_points(2) + maximum_fee(8) + fee_vault(32)
+    pub const LEN: usize = 2 + 8 + 32;
+
+    pub fn new(basis_points: u16, maximum_fee: u64, fee_vault: Pubkey) -> Result<Self, TransferFeeError> {
+        if basis_points > MAX_FEE_BASIS_POINTS {
+            return Err(TransferFeeError::BasisPointsTooHigh(basis_points));
+        }
+        Ok(Self { basis_points, maximum_fee, fee_vault })
+    }
+
+    // 和Token-2022一样向上取整，避免把大额转账拆成小额来逃避手续费；
+    // 用u128计算乘积，amount接近u64::MAX时也不会溢出
+    pub fn calculate_fee(&self, amount: u64) -> u64 {
+        let numerator = amount as u128 * self.basis_points as u128;
+        let fee = numerator.div_ceil(MAX_FEE_BASIS_POINTS as u128);
+        fee.min(self.maximum_fee as u128) as u64
+    }
+}
+
+impl BorshSerialize for TransferFeeConfig {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.basis_points.serialize(out);
+        self.maximum_fee.serialize(out);
+        self.fee_vault.serialize(out);
+    }
+}
+
+impl BorshDeserialize for TransferFeeConfig {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            basis_points: u16::deserialize(buf)?,
+            maximum_fee: u64::deserialize(buf)?,
+            fee_vault: Pubkey::deserialize(buf)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mint {
+    pub supply: u64,
+    pub max_supply: Option<u64>,                 // None表示不限制总供应量
+    pub decimals: u8,                            // 1个代币 = 10^decimals个最小单位
+    pub mint_authority: Option<Pubkey>,          // None表示供应量已固定，不能再铸造
+    pub freeze_authority: Option<Pubkey>,        // 可以冻结Token账户的权限
+    pub transfer_fee: Option<TransferFeeConfig>, // None表示转账不收手续费
+}
+
+impl Mint {
+    // supply(8) + max_supply(1 + 8) + decimals(1) + 两个Option<Pubkey>(各1 + 32) + Option<TransferFeeConfig>
+    pub const LEN: usize = 8 + 9 + 1 + 33 + 33 + 1 + TransferFeeConfig::LEN;
+
+    pub fn new(decimals: u8, mint_authority: Pubkey) -> Self {
+        Self {
+            supply: 0,
+            max_supply: None,
+            decimals,
+            mint_authority: Some(mint_authority),
+            freeze_authority: None,
+            transfer_fee: None,
+        }
+    }
+
+    pub fn with_freeze_authority(mut self, freeze_authority: Pubkey) -> Self {
+        self.freeze_authority = Some(freeze_authority);
+        self
+    }
+
+    pub fn with_transfer_fee(mut self, transfer_fee: TransferFeeConfig) -> Self {
+        self.transfer_fee = Some(transfer_fee);
+        self
+    }
+
+    pub fn with_max_supply(mut self, max_supply: u64) -> Self {
+        self.max_supply = Some(max_supply);
+        self
+    }
+
+    // 新的供应量不超过上限时才允许铸造
+    pub fn within_cap(&self, supply: u64) -> bool {
+        self.max_supply.is_none_or(|max_supply| supply <= max_supply)
+    }
+
+    // 按这个mint的精度显示最小单位的数量
+    pub fn ui_amount(&self, amount: u64) -> UiAmount {
+        UiAmount::new(amount, self.decimals)
+    }
+
+    // 把用户输入的数量（如"1.5"）换算成最小单位
+    pub fn parse_ui_amount(&self, s: &str) -> Result<u64, UiAmountError> {
+        UiAmount::parse(s, self.decimals).map(|ui| ui.amount)
+    }
+}
+
+impl Summary for Mint {
+    fn summarize(&self) -> String {
+        match self.max_supply {
+            Some(max_supply) => format!(
+                "Mint: supply={}/{}, decimals={}",
+                self.ui_amount(self.supply),
+                self.ui_amount(max_supply),
+                self.decimals
+            ),
+            None => format!("Mint: supply={}, decimals={}", self.ui_amount(self.supply), self.decimals),
+        }
+    }
+}
+
+// 按字段顺序序列化，各字段长度见Mint::LEN；Option为None时只有1字节的标签，所以实际长度可能小于LEN
+impl BorshSerialize for Mint {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.supply.serialize(out);
+        self.max_supply.serialize(out);
+        self.decimals.serialize(out);
+        self.mint_authority.serialize(out);
+        self.freeze_authority.serialize(out);
+        self.transfer_fee.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Mint {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            supply: u64::deserialize(buf)?,
+            max_supply: Option::deserialize(buf)?,
+            decimals: u8::deserialize(buf)?,
+            mint_authority: Option::deserialize(buf)?,
+            freeze_authority: Option::deserialize(buf)?,
+            transfer_fee: Option::deserialize(buf)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borsh_roundtrip() {
+        let fee = TransferFeeConfig::new(50, 1_000, Pubkey::new_unique()).unwrap();
+        let mint = Mint::new(6, Pubkey::new_unique())
+            .with_freeze_authority(Pubkey::new_unique())
+            .with_transfer_fee(fee)
+            .with_max_supply(1_000_000);
+        let mut bytes = Vec::new();
+        mint.serialize(&mut bytes);
+        assert_eq!(bytes.len(), Mint::LEN);
+        assert_eq!(Mint::try_from_slice(&bytes), Ok(mint));
+
+        // 没有权限时Option只占1字节
+        let fixed = Mint { mint_authority: None, ..Mint::new(0, Pubkey::new_unique()) };
+        let mut bytes = Vec::new();
+        fixed.serialize(&mut bytes);
+        assert_eq!(bytes.len(), 8 + 1 + 1 + 1 + 1 + 1);
+    }
+
+    #[test]
+    fn test_calculate_fee() {
+        let fee = TransferFeeConfig::new(50, 1_000, Pubkey::new_unique()).unwrap();
+        assert_eq!(fee.calculate_fee(10_000), 50);
+        // 不足1个最小单位的部分向上取整
+        assert_eq!(fee.calculate_fee(1), 1);
+        assert_eq!(fee.calculate_fee(0), 0);
+        // 超过上限按上限收取，大额也不会溢出
+        assert_eq!(fee.calculate_fee(u64::MAX), 1_000);
+
+        assert_eq!(TransferFeeConfig::new(0, 1_000, Pubkey::new_unique()).unwrap().calculate_fee(u64::MAX), 0);
+        assert_eq!(
+            TransferFeeConfig::new(MAX_FEE_BASIS_POINTS + 1, 0, Pubkey::new_unique()),
+            Err(TransferFeeError::BasisPointsTooHigh(MAX_FEE_BASIS_POINTS + 1))
+        );
+    }
+
+    #[test]
+    fn test_within_cap() {
+        let mint = Mint::new(0, Pubkey::new_unique());
+        assert!(mint.within_cap(u64::MAX));
+        let capped = mint.clone().with_max_supply(100);
+        assert!(capped.within_cap(100));
+        assert!(!capped.within_cap(101));
+        assert!(mint.with_max_supply(u64::MAX).within_cap(u64::MAX));
+    }
+
+    #[test]
+    fn test_ui_amount_uses_decimals() {
+        let mut mint = Mint::new(6, Pubkey::new_unique());
+        mint.supply = 1_500_000;
+        assert_eq!(mint.summarize(), "Mint: supply=1.5, decimals=6");
+        assert_eq!(mint.parse_ui_amount("2.25"), Ok(2_250_000));
+        assert_eq!(mint.parse_ui_amount("0.0000001"), Err(UiAmountError::PrecisionOverflow));
+    }
+}
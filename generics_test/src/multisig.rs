@@ -0,0 +1,148 @@
+// 多签账户 - 模拟SPL Token的Multisig：n个签名者中至少m个签名才能授权
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::{AccountData, AccountWrapper, Pubkey, TransactionResult};
+
+// 与SPL Token一致，最多11个签名者
+pub const MAX_SIGNERS: usize = 11;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultisigError {
+    InvalidThreshold { m: usize, n: usize }, // 要求1 <= m <= n
+    TooManySigners(usize),
+    DuplicateSigner(Pubkey),
+}
+
+impl fmt::Display for MultisigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MultisigError::InvalidThreshold { m, n } => write!(f, "无效的门限: {}-of-{}", m, n),
+            MultisigError::TooManySigners(n) => write!(f, "签名者过多: {}个，最多{}个", n, MAX_SIGNERS),
+            MultisigError::DuplicateSigner(key) => write!(f, "重复的签名者: {}", key),
+        }
+    }
+}
+
+impl std::error::Error for MultisigError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Multisig {
+    m: usize,
+    signers: Vec<Pubkey>,
+}
+
+impl Multisig {
+    pub fn new(m: usize, signers: Vec<Pubkey>) -> Result<Self, MultisigError> {
+        let n = signers.len();
+        if n > MAX_SIGNERS {
+            return Err(MultisigError::TooManySigners(n));
+        }
+        if m == 0 || m > n {
+            return Err(MultisigError::InvalidThreshold { m, n });
+        }
+        let mut seen = HashSet::new();
+        if let Some(duplicate) = signers.iter().find(|key| !seen.insert(**key)) {
+            return Err(MultisigError::DuplicateSigner(*duplicate));
+        }
+        Ok(Self { m, signers })
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.m
+    }
+
+    pub fn signers(&self) -> &[Pubkey] {
+        &self.signers
+    }
+
+    // 统计提供的签名中属于本多签的不同签名者，达到m个才通过
+    pub fn verify(&self, provided: &[Pubkey]) -> Result<(), TransactionResult> {
+        let approvals: HashSet<&Pubkey> = provided
+            .iter()
+            .filter(|key| self.signers.contains(key))
+            .collect();
+        if approvals.len() >= self.m {
+            Ok(())
+        } else {
+            Err(TransactionResult::MissingRequiredSignature)
+        }
+    }
+}
+
+// 转账的授权方：单个签名账户，或者多签账户加上实际提供的签名
+#[derive(Debug)]
+pub enum Authority<'a, A: AccountData> {
+    Single(&'a AccountWrapper<A>),
+    Multisig(&'a Multisig, &'a [Pubkey]),
+}
+
+impl<A: AccountData> Authority<'_, A> {
+    pub fn verify(&self) -> Result<(), TransactionResult> {
+        match self {
+            Authority::Single(account) => account.assert_signer(),
+            Authority::Multisig(multisig, provided) => multisig.verify(provided),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(n: usize) -> Vec<Pubkey> {
+        (0..n).map(|_| Pubkey::new_unique()).collect()
+    }
+
+    #[test]
+    fn test_new_validates_configuration() {
+        let signers = keys(3);
+        assert!(Multisig::new(2, signers.clone()).is_ok());
+        assert_eq!(
+            Multisig::new(0, signers.clone()),
+            Err(MultisigError::InvalidThreshold { m: 0, n: 3 })
+        );
+        assert_eq!(
+            Multisig::new(4, signers.clone()),
+            Err(MultisigError::InvalidThreshold { m: 4, n: 3 })
+        );
+        assert_eq!(
+            Multisig::new(1, keys(MAX_SIGNERS + 1)),
+            Err(MultisigError::TooManySigners(MAX_SIGNERS + 1))
+        );
+        assert_eq!(
+            Multisig::new(1, vec![signers[0], signers[0]]),
+            Err(MultisigError::DuplicateSigner(signers[0]))
+        );
+    }
+
+    #[test]
+    fn test_threshold_approval() {
+        let signers = keys(3);
+        let multisig = Multisig::new(2, signers.clone()).unwrap();
+
+        assert_eq!(multisig.verify(&signers[..2]), Ok(()));
+        assert_eq!(multisig.verify(&signers[..1]), Err(TransactionResult::MissingRequiredSignature));
+        // 同一个签名者重复出现只算一次，外部签名者不计入
+        assert_eq!(
+            multisig.verify(&[signers[0], signers[0], Pubkey::new_unique()]),
+            Err(TransactionResult::MissingRequiredSignature)
+        );
+    }
+
+    #[test]
+    fn test_authority_variants() {
+        let signer = AccountWrapper::new(Pubkey::new_unique(), (), Pubkey::default()).with_signer(true);
+        let non_signer = AccountWrapper::new(Pubkey::new_unique(), (), Pubkey::default());
+        assert_eq!(Authority::Single(&signer).verify(), Ok(()));
+        assert_eq!(
+            Authority::Single(&non_signer).verify(),
+            Err(TransactionResult::MissingRequiredSignature)
+        );
+
+        let signers = keys(2);
+        let multisig = Multisig::new(2, signers.clone()).unwrap();
+        assert_eq!(Authority::<()>::Multisig(&multisig, &signers).verify(), Ok(()));
+    }
+}
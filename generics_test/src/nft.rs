@@ -0,0 +1,255 @@
+// NFT - 在现有Token指令上拼出来的铸造流程：decimals为0、max_supply为1的mint，
+// 加上由mint推导出的元数据PDA，和持有者的关联代币账户。供应量达到1之后再MintTo会因为超过上限失败。
+// 转移NFT时把代币转到新持有者的关联账户并关闭旧账户；如果原持有者同时是update authority，一起移交给新持有者
+
+use std::fmt;
+
+use crate::ledger::{Account, AccountPayload, Ledger, LedgerError};
+use crate::metadata::{metadata_address, MetadataError, TokenMetadata};
+use crate::mint::Mint;
+use crate::pda::get_associated_token_address;
+use crate::signature::{Keypair, Signer};
+use crate::transaction::{Instruction, Transaction, TransactionError};
+use crate::{AccountMeta, ProgramInstruction, Pubkey, TokenAccount, SYSTEM_PROGRAM_ID};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NftError {
+    Metadata(MetadataError),       // 名称、符号或URI超长
+    Ledger(LedgerError),           // 创建账户失败
+    Transaction(TransactionError), // 铸造或转移交易失败
+    NotOwner,                      // 签名的不是NFT当前的持有者
+}
+
+impl fmt::Display for NftError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NftError::Metadata(error) => write!(f, "元数据无效: {}", error),
+            NftError::Ledger(error) => write!(f, "创建账户失败: {}", error),
+            NftError::Transaction(error) => write!(f, "交易失败: {}", error),
+            NftError::NotOwner => write!(f, "不是NFT的持有者"),
+        }
+    }
+}
+
+impl std::error::Error for NftError {}
+
+// 一个NFT涉及的三个账户
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nft {
+    pub mint: Pubkey,
+    pub metadata: Pubkey,
+    pub token_account: Pubkey, // 当前持有者的关联代币账户，余额为1
+}
+
+// creator是mint authority、初始的update authority，也支付交易费；NFT铸造到owner的关联代币账户
+pub fn mint_nft(
+    ledger: &mut Ledger,
+    program_id: Pubkey,
+    creator: &Keypair,
+    owner: Pubkey,
+    (name, symbol, uri): (&str, &str, &str),
+) -> Result<Nft, NftError> {
+    // 先检查元数据，避免账户已经创建了交易才失败
+    TokenMetadata::new(Pubkey::default(), creator.pubkey(), name, symbol, uri).map_err(NftError::Metadata)?;
+
+    let mint = Pubkey::new_unique();
+    let metadata = metadata_address(&mint, &program_id);
+    let token_account = get_associated_token_address(&owner, &mint);
+    let mint_data = AccountPayload::Mint(Mint::new(0, creator.pubkey()).with_max_supply(1));
+    create(ledger, mint, program_id, Mint::LEN, mint_data)?;
+    create(ledger, metadata, program_id, TokenMetadata::LEN, AccountPayload::Empty)?;
+    create(ledger, token_account, program_id, TokenAccount::LEN, AccountPayload::Token(TokenAccount::new(mint, owner, 0)))?;
+
+    let create_metadata = ProgramInstruction::CreateMetadata { name: name.into(), symbol: symbol.into(), uri: uri.into() };
+    let instructions = vec![
+        Instruction::new(program_id, create_metadata, vec![
+            AccountMeta::new(metadata, program_id, false),
+            AccountMeta::new_readonly(mint, program_id, false),
+            AccountMeta::new_readonly(creator.pubkey(), SYSTEM_PROGRAM_ID, true),
+        ]),
+        Instruction::new(program_id, ProgramInstruction::Mint { amount: 1 }, vec![
+            AccountMeta::new(token_account, program_id, false),
+            AccountMeta::new(mint, program_id, false),
+            AccountMeta::new_readonly(creator.pubkey(), SYSTEM_PROGRAM_ID, true),
+        ]),
+    ];
+    execute(ledger, creator, instructions)?;
+    Ok(Nft { mint, metadata, token_account })
+}
+
+// owner把NFT转给new_owner，返回转移后的NFT；旧的关联账户关闭，租金退回owner。
+// update authority在owner手里时跟着NFT一起移交，在创作者手里时保持不变
+pub fn transfer_nft(ledger: &mut Ledger, program_id: Pubkey, nft: &Nft, owner: &Keypair, new_owner: Pubkey) -> Result<Nft, NftError> {
+    match ledger.get_account(&nft.token_account).map(|account| &account.data) {
+        Some(AccountPayload::Token(token)) if token.owner == owner.pubkey() => {}
+        _ => return Err(NftError::NotOwner),
+    }
+    let update_authority = match ledger.get_account(&nft.metadata).map(|account| &account.data) {
+        Some(AccountPayload::Metadata(metadata)) => metadata.update_authority,
+        _ => return Err(NftError::Ledger(LedgerError::AccountNotFound(nft.metadata))),
+    };
+
+    let destination = get_associated_token_address(&new_owner, &nft.mint);
+    if ledger.get_account(&destination).is_none() {
+        let data = AccountPayload::Token(TokenAccount::new(nft.mint, new_owner, 0));
+        create(ledger, destination, program_id, TokenAccount::LEN, data)?;
+    }
+    let mut instructions = vec![
+        Instruction::new(program_id, ProgramInstruction::Transfer { amount: 1 }, vec![
+            AccountMeta::new(nft.token_account, program_id, false),
+            AccountMeta::new(destination, program_id, false),
+            AccountMeta::new_readonly(owner.pubkey(), SYSTEM_PROGRAM_ID, true),
+        ]),
+        Instruction::new(program_id, ProgramInstruction::CloseTokenAccount, vec![
+            AccountMeta::new(nft.token_account, program_id, false),
+            AccountMeta::new(owner.pubkey(), SYSTEM_PROGRAM_ID, true),
+        ]),
+    ];
+    if update_authority == owner.pubkey() {
+        instructions.push(Instruction::new(program_id, ProgramInstruction::SetMetadataAuthority { new_authority: new_owner }, vec![
+            AccountMeta::new(nft.metadata, program_id, false),
+            AccountMeta::new_readonly(owner.pubkey(), SYSTEM_PROGRAM_ID, true),
+        ]));
+    }
+    execute(ledger, owner, instructions)?;
+    Ok(Nft { token_account: destination, ..*nft })
+}
+
+fn create(ledger: &mut Ledger, key: Pubkey, owner: Pubkey, len: usize, data: AccountPayload) -> Result<(), NftError> {
+    let lamports = ledger.rent().minimum_balance(len);
+    ledger.create_account(key, Account::new(lamports, owner, data)).map_err(NftError::Ledger)
+}
+
+fn execute(ledger: &mut Ledger, signer: &Keypair, instructions: Vec<Instruction>) -> Result<(), NftError> {
+    let mut tx = Transaction::new(signer.pubkey(), instructions);
+    tx.sign(&[signer]).map_err(|_| NftError::Transaction(TransactionError::SignatureFailure))?;
+    tx.execute(ledger).map_err(NftError::Transaction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpi::Programs;
+    use crate::test_utils::{funded_keypair, sign_and_execute, token_amount};
+    use crate::TransactionResult;
+
+    struct Fixture {
+        ledger: Ledger,
+        programs: Programs,
+        program: Pubkey,
+        creator: Keypair,
+        alice: Keypair,
+        bob: Keypair,
+    }
+
+    fn fixture() -> Fixture {
+        let mut ledger = Ledger::default();
+        let (creator, alice, bob) = (funded_keypair(&mut ledger), funded_keypair(&mut ledger), funded_keypair(&mut ledger));
+        Fixture { ledger, programs: Programs::new(), program: Pubkey::new_unique(), creator, alice, bob }
+    }
+
+    fn metadata(ledger: &Ledger, nft: &Nft) -> TokenMetadata {
+        match &ledger.get_account(&nft.metadata).unwrap().data {
+            AccountPayload::Metadata(metadata) => metadata.clone(),
+            _ => panic!("不是元数据账户"),
+        }
+    }
+
+    const ART: (&str, &str, &str) = ("Sunset #1", "SUN", "https://example.com/sunset/1.json");
+
+    #[test]
+    fn test_mint_nft_fixed_supply() {
+        let mut f = fixture();
+        let nft = mint_nft(&mut f.ledger, f.program, &f.creator, f.alice.pubkey(), ART).unwrap();
+
+        assert_eq!(nft.token_account, get_associated_token_address(&f.alice.pubkey(), &nft.mint));
+        assert_eq!(token_amount(&f.ledger, &nft.token_account), 1);
+        match &f.ledger.get_account(&nft.mint).unwrap().data {
+            AccountPayload::Mint(mint) => assert_eq!((mint.supply, mint.decimals, mint.max_supply), (1, 0, Some(1))),
+            _ => panic!("不是mint账户"),
+        }
+        let stored = metadata(&f.ledger, &nft);
+        assert_eq!((stored.mint, stored.update_authority), (nft.mint, f.creator.pubkey()));
+        assert_eq!(stored.to_string(), "Sunset #1 (SUN)");
+
+        // mint authority还在，但供应量已经到上限
+        let instruction = Instruction::new(f.program, ProgramInstruction::Mint { amount: 1 }, vec![
+            AccountMeta::new(nft.token_account, f.program, false),
+            AccountMeta::new(nft.mint, f.program, false),
+            AccountMeta::new_readonly(f.creator.pubkey(), SYSTEM_PROGRAM_ID, true),
+        ]);
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.creator, vec![instruction]), Err(TransactionError::InstructionError(0, TransactionResult::SupplyCapExceeded)));
+        assert_eq!(token_amount(&f.ledger, &nft.token_account), 1);
+    }
+
+    #[test]
+    fn test_mint_nft_rejects_long_metadata_before_creating_accounts() {
+        let mut f = fixture();
+        let before = f.ledger.snapshot();
+        let name = "x".repeat(64);
+        assert_eq!(
+            mint_nft(&mut f.ledger, f.program, &f.creator, f.alice.pubkey(), (&name, "SUN", "")),
+            Err(NftError::Metadata(MetadataError::NameTooLong))
+        );
+        assert_eq!(f.ledger.snapshot(), before);
+    }
+
+    #[test]
+    fn test_transfer_keeps_creator_update_authority() {
+        let mut f = fixture();
+        let nft = mint_nft(&mut f.ledger, f.program, &f.creator, f.alice.pubkey(), ART).unwrap();
+        let alice_lamports = f.ledger.get_account(&f.alice.pubkey()).unwrap().lamports;
+
+        // 只有持有者可以转移
+        assert_eq!(transfer_nft(&mut f.ledger, f.program, &nft, &f.bob, f.bob.pubkey()), Err(NftError::NotOwner));
+
+        let moved = transfer_nft(&mut f.ledger, f.program, &nft, &f.alice, f.bob.pubkey()).unwrap();
+        assert_eq!(moved.token_account, get_associated_token_address(&f.bob.pubkey(), &nft.mint));
+        assert_eq!(token_amount(&f.ledger, &moved.token_account), 1);
+        // 旧账户关闭，租金退回alice（扣掉交易费后仍然增加）
+        assert!(f.ledger.get_account(&nft.token_account).is_none());
+        assert!(f.ledger.get_account(&f.alice.pubkey()).unwrap().lamports > alice_lamports);
+        assert_eq!(metadata(&f.ledger, &moved).update_authority, f.creator.pubkey());
+
+        // 转回alice时重新创建她的关联账户
+        let back = transfer_nft(&mut f.ledger, f.program, &moved, &f.bob, f.alice.pubkey()).unwrap();
+        assert_eq!(back.token_account, nft.token_account);
+        assert_eq!(token_amount(&f.ledger, &back.token_account), 1);
+    }
+
+    #[test]
+    fn test_transfer_hands_over_update_authority() {
+        let mut f = fixture();
+        // 创作者铸造给自己，此时持有者就是update authority
+        let nft = mint_nft(&mut f.ledger, f.program, &f.creator, f.creator.pubkey(), ART).unwrap();
+        let moved = transfer_nft(&mut f.ledger, f.program, &nft, &f.creator, f.alice.pubkey()).unwrap();
+        assert_eq!(metadata(&f.ledger, &moved).update_authority, f.alice.pubkey());
+
+        // 移交之后原authority不能再修改元数据，新authority可以
+        let update = |authority: Pubkey| {
+            let data = ProgramInstruction::UpdateMetadata { name: "Sunset #1 (signed)".into(), symbol: "SUN".into(), uri: String::new() };
+            Instruction::new(f.program, data, vec![
+                AccountMeta::new(moved.metadata, f.program, false),
+                AccountMeta::new_readonly(authority, SYSTEM_PROGRAM_ID, true),
+            ])
+        };
+        let (by_creator, by_alice) = (update(f.creator.pubkey()), update(f.alice.pubkey()));
+        assert_eq!(
+            sign_and_execute(&mut f.ledger, &f.programs, &f.creator, vec![by_creator]),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.alice, vec![by_alice]), Ok(()));
+        assert_eq!(metadata(&f.ledger, &moved).name, "Sunset #1 (signed)");
+
+        // 原authority也不能把权限再拿回来
+        let instruction = Instruction::new(
+            f.program,
+            ProgramInstruction::SetMetadataAuthority { new_authority: f.creator.pubkey() },
+            vec![
+                AccountMeta::new(moved.metadata, f.program, false),
+                AccountMeta::new_readonly(f.creator.pubkey(), SYSTEM_PROGRAM_ID, true),
+            ],
+        );
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.creator, vec![instruction]), Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature)));
+    }
+}
@@ -0,0 +1,59 @@
+// 持久化nonce账户 - 离线签名的交易可能很久以后才提交，那时recent blockhash早已过期。
+// 这类交易改为引用nonce账户里保存的值，并把AdvanceNonce作为第一条指令：
+// 每次使用后nonce都会推进，旧的nonce不能再次使用，从而防止重放
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::hash::{hash, Hash};
+use crate::Pubkey;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NonceAccount {
+    pub authority: Pubkey, // 只有authority签名才能推进nonce
+    pub nonce: Hash,
+}
+
+impl NonceAccount {
+    // authority(32) + nonce(32)
+    pub const LEN: usize = 32 + 32;
+
+    pub fn new(authority: Pubkey, nonce: Hash) -> Self {
+        Self { authority, nonce }
+    }
+
+    // 新的nonce由旧值推出，和用过的值都不相同
+    pub fn advance(&mut self) {
+        self.nonce = hash(&self.nonce);
+    }
+}
+
+impl BorshSerialize for NonceAccount {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.authority.serialize(out);
+        self.nonce.serialize(out);
+    }
+}
+
+impl BorshDeserialize for NonceAccount {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            authority: Pubkey::deserialize(buf)?,
+            nonce: Hash::deserialize(buf)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance_changes_nonce() {
+        let mut account = NonceAccount::new(Pubkey::new_unique(), [7; 32]);
+        let mut seen = vec![account.nonce];
+        for _ in 0..3 {
+            account.advance();
+            assert!(!seen.contains(&account.nonce));
+            seen.push(account.nonce);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// 模拟价格预言机 - 类似于Pyth/Switchboard的价格账户，但价格由authority直接写入。
+// 价格是定点数：1个抵押代币最小单位值price / PRICE_SCALE个借出代币最小单位
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::cpi::InvokeContext;
+use crate::ledger::AccountPayload;
+use crate::transaction::Instruction;
+use crate::{ProgramInstruction, Pubkey, TransactionResult};
+
+pub const PRICE_SCALE: u64 = 1_000_000;
+// 价格账户必须归这个程序所有，借用Pyth Oracle Program的地址: FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH
+pub const ORACLE_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    220, 229, 235, 225, 228, 156, 59, 159, 17, 76, 181, 84, 76, 80, 169, 158, 192, 214, 146, 214, 63, 86, 121, 90, 224, 41,
+    172, 131, 217, 234, 139, 226,
+]);
+// 价格超过这么多个slot没有更新就认为过期，借款和清算都不能用
+pub const MAX_PRICE_AGE_SLOTS: u64 = 25;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceOracle {
+    pub authority: Pubkey, // 只有它可以更新价格
+    pub price: u64,
+    pub last_update_slot: u64,
+}
+
+impl PriceOracle {
+    // authority(32) + price(8) + last_update_slot(8)
+    pub const LEN: usize = 32 + 8 + 8;
+
+    pub fn new(authority: Pubkey, price: u64) -> Self {
+        Self { authority, price, last_update_slot: 0 }
+    }
+}
+
+impl BorshSerialize for PriceOracle {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.authority.serialize(out);
+        self.price.serialize(out);
+        self.last_update_slot.serialize(out);
+    }
+}
+
+impl BorshDeserialize for PriceOracle {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            authority: Pubkey::deserialize(buf)?,
+            price: u64::deserialize(buf)?,
+            last_update_slot: u64::deserialize(buf)?,
+        })
+    }
+}
+
+// 预言机程序的入口函数。accounts: [价格账户(可写), authority(签名)]
+pub fn process(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+    let ProgramInstruction::UpdatePrice { price } = instruction.data else {
+        return Err(TransactionResult::InvalidInstructionData);
+    };
+    let [oracle_meta, authority, ..] = instruction.accounts.as_slice() else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    let slot = ctx.slot();
+    let mut account = ctx.staged().get_account(&oracle_meta.pubkey).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    if account.owner != instruction.program_id || !oracle_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let AccountPayload::PriceOracle(oracle) = &mut account.data else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if oracle.authority != authority.pubkey || !authority.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    oracle.price = price;
+    oracle.last_update_slot = slot;
+    Ok(ctx.staged().set_account(oracle_meta.pubkey, account)?)
+}
@@ -0,0 +1,157 @@
+// 模拟PDA（Program Derived Address）推导
+// PDA是由种子和程序ID哈希得到、且不在ed25519曲线上的地址，因此没有对应的私钥，
+// 只有推导它的程序能通过invoke_signed为它"签名"
+
+use std::fmt;
+
+use crate::hash::hashv;
+use crate::pubkey::{Pubkey, ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID};
+
+// 与Solana一致的种子限制
+pub const MAX_SEEDS: usize = 16;
+pub const MAX_SEED_LEN: usize = 32;
+
+const PDA_MARKER: &[u8] = b"ProgramDerivedAddress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdaError {
+    MaxSeedLengthExceeded, // 种子太多或单个种子太长
+    InvalidSeeds,          // 推导出的地址落在曲线上，不能作为PDA
+}
+
+impl fmt::Display for PdaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PdaError::MaxSeedLengthExceeded => {
+                write!(f, "种子超出限制: 最多{}个，每个最长{}字节", MAX_SEEDS, MAX_SEED_LEN)
+            }
+            PdaError::InvalidSeeds => write!(f, "推导出的地址在曲线上，不是有效的PDA"),
+        }
+    }
+}
+
+impl std::error::Error for PdaError {}
+
+// 模拟ed25519曲线检查。
+// 真实实现会尝试把32字节解压为曲线上的点，随机字节大约一半能成功；
+// 这里用哈希首字节的最低位模拟同样的概率，足以演示bump的作用
+fn is_on_curve(bytes: &[u8; 32]) -> bool {
+    bytes[0] & 1 == 0
+}
+
+// 用给定的种子（已包含bump）计算PDA，对应Pubkey::create_program_address
+pub fn create_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> Result<Pubkey, PdaError> {
+    if seeds.len() > MAX_SEEDS || seeds.iter().any(|seed| seed.len() > MAX_SEED_LEN) {
+        return Err(PdaError::MaxSeedLengthExceeded);
+    }
+
+    let mut parts: Vec<&[u8]> = seeds.to_vec();
+    parts.push(program_id.as_ref());
+    parts.push(PDA_MARKER);
+    let hash = hashv(&parts);
+
+    if is_on_curve(&hash) {
+        return Err(PdaError::InvalidSeeds);
+    }
+    Ok(Pubkey::new_from_array(hash))
+}
+
+// 从bump=255开始向下尝试，返回第一个不在曲线上的地址和对应的bump
+pub fn find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> Option<(Pubkey, u8)> {
+    // bump本身也占用一个种子位置
+    if seeds.len() >= MAX_SEEDS {
+        return None;
+    }
+
+    for bump in (0..=u8::MAX).rev() {
+        let bump_seed = [bump];
+        let mut seeds_with_bump = seeds.to_vec();
+        seeds_with_bump.push(&bump_seed);
+
+        match create_program_address(&seeds_with_bump, program_id) {
+            Ok(address) => return Some((address, bump)),
+            Err(PdaError::InvalidSeeds) => continue,
+            Err(PdaError::MaxSeedLengthExceeded) => return None,
+        }
+    }
+    None
+}
+
+// 推导PDA并返回(地址, bump)，与Pubkey::find_program_address一样，找不到时panic
+pub fn derive_pda(seeds: &[&[u8]], program_id: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(seeds, program_id).expect("无法找到有效的PDA bump")
+}
+
+// 关联代币账户(ATA)：种子为[钱包, Token程序ID, mint]，由ATA程序推导
+pub fn get_associated_token_address(wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let seeds: [&[u8]; 3] = [wallet.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()];
+    derive_pda(&seeds, &ASSOCIATED_TOKEN_PROGRAM_ID).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_program_address_is_deterministic() {
+        let program_id = Pubkey::new_unique();
+        let seeds: [&[u8]; 2] = [b"escrow", b"alice"];
+
+        let (address, bump) = derive_pda(&seeds, &program_id);
+        assert_eq!(derive_pda(&seeds, &program_id), (address, bump));
+
+        // 用找到的bump重新计算，应得到同一个地址
+        let bump_seed = [bump];
+        let with_bump: [&[u8]; 3] = [b"escrow", b"alice", &bump_seed];
+        assert_eq!(create_program_address(&with_bump, &program_id), Ok(address));
+    }
+
+    #[test]
+    fn test_bump_skips_on_curve_addresses() {
+        let program_id = Pubkey::new_unique();
+        let seeds: [&[u8]; 1] = [b"vault"];
+        let (_, bump) = derive_pda(&seeds, &program_id);
+
+        // 比找到的bump更大的值都落在曲线上
+        for higher in (bump as u16 + 1)..=255 {
+            let bump_seed = [higher as u8];
+            let with_bump: [&[u8]; 2] = [b"vault", &bump_seed];
+            assert_eq!(
+                create_program_address(&with_bump, &program_id),
+                Err(PdaError::InvalidSeeds)
+            );
+        }
+    }
+
+    #[test]
+    fn test_different_seeds_or_programs_give_different_addresses() {
+        let program_a = Pubkey::new_unique();
+        let program_b = Pubkey::new_unique();
+        let (a, _) = derive_pda(&[b"seed"], &program_a);
+        let (b, _) = derive_pda(&[b"seed"], &program_b);
+        let (c, _) = derive_pda(&[b"other"], &program_a);
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_seed_limits() {
+        let program_id = Pubkey::new_unique();
+        let long_seed = [0u8; MAX_SEED_LEN + 1];
+        assert_eq!(
+            create_program_address(&[&long_seed], &program_id),
+            Err(PdaError::MaxSeedLengthExceeded)
+        );
+        let too_many: Vec<&[u8]> = vec![b"s"; MAX_SEEDS];
+        assert_eq!(find_program_address(&too_many, &program_id), None);
+    }
+
+    #[test]
+    fn test_associated_token_address() {
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let ata = get_associated_token_address(&wallet, &mint);
+        assert_eq!(ata, get_associated_token_address(&wallet, &mint));
+        assert_ne!(ata, get_associated_token_address(&mint, &wallet));
+    }
+}
@@ -0,0 +1,211 @@
+// 模拟Solana中的Pubkey - 32字节公钥，文本形式使用base58编码
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const PUBKEY_BYTES: usize = 32;
+
+// base58字母表：去掉了容易混淆的 0、O、I、l
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+// 解析公钥时可能出现的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsePubkeyError {
+    InvalidCharacter(char), // 不在base58字母表中的字符
+    InvalidLength(usize),   // 解码后不是32字节
+}
+
+impl fmt::Display for ParsePubkeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsePubkeyError::InvalidCharacter(c) => write!(f, "无效的base58字符: {:?}", c),
+            ParsePubkeyError::InvalidLength(len) => {
+                write!(f, "公钥长度无效: 期望{}字节，实际{}字节", PUBKEY_BYTES, len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParsePubkeyError {}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Pubkey([u8; PUBKEY_BYTES]);
+
+// 常用程序的地址
+// System Program: 11111111111111111111111111111111
+pub const SYSTEM_PROGRAM_ID: Pubkey = Pubkey([0; PUBKEY_BYTES]);
+// SPL Token Program: TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA
+pub const TOKEN_PROGRAM_ID: Pubkey = Pubkey([
+    6, 221, 246, 225, 215, 101, 161, 147, 217, 203, 225, 70, 206, 235, 121, 172, 28, 180, 133,
+    237, 95, 91, 55, 145, 58, 140, 245, 133, 126, 255, 0, 169,
+]);
+// Associated Token Account Program: ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey = Pubkey([
+    140, 151, 37, 143, 78, 36, 137, 241, 187, 61, 16, 41, 20, 142, 13, 131, 11, 90, 19, 153, 218,
+    255, 16, 132, 4, 142, 123, 216, 219, 233, 248, 89,
+]);
+
+impl Pubkey {
+    pub const fn new_from_array(bytes: [u8; PUBKEY_BYTES]) -> Self {
+        Self(bytes)
+    }
+
+    // 生成一个进程内唯一的公钥，类似solana_program中的Pubkey::new_unique，方便测试和演示
+    pub fn new_unique() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(1);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let mut bytes = [0u8; PUBKEY_BYTES];
+        bytes[..8].copy_from_slice(&n.to_be_bytes());
+        Self(bytes)
+    }
+
+    pub fn to_bytes(self) -> [u8; PUBKEY_BYTES] {
+        self.0
+    }
+}
+
+impl AsRef<[u8]> for Pubkey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl FromStr for Pubkey {
+    type Err = ParsePubkeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = decode_base58(s)?;
+        let array: [u8; PUBKEY_BYTES] = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| ParsePubkeyError::InvalidLength(bytes.len()))?;
+        Ok(Self(array))
+    }
+}
+
+impl fmt::Display for Pubkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode_base58(&self.0))
+    }
+}
+
+// 和Solana一样，Debug也直接输出base58字符串，便于阅读
+impl fmt::Debug for Pubkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode_base58(&self.0))
+    }
+}
+
+// ===============================
+// base58编解码
+// ===============================
+
+pub(crate) fn encode_base58(input: &[u8]) -> String {
+    // 前导的0字节编码为字符'1'
+    let zeros = input.iter().take_while(|&&b| b == 0).count();
+
+    // digits保存58进制的各位（低位在前）
+    let mut digits: Vec<u8> = Vec::with_capacity(input.len() * 138 / 100 + 1);
+    for &byte in &input[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let mut result = String::with_capacity(zeros + digits.len());
+    result.extend(std::iter::repeat_n('1', zeros));
+    result.extend(digits.iter().rev().map(|&d| BASE58_ALPHABET[d as usize] as char));
+    result
+}
+
+fn decode_base58(input: &str) -> Result<Vec<u8>, ParsePubkeyError> {
+    let zeros = input.chars().take_while(|&c| c == '1').count();
+
+    // bytes保存256进制的各位（低位在前）
+    let mut bytes: Vec<u8> = Vec::with_capacity(input.len());
+    for c in input.chars().skip(zeros) {
+        let value = BASE58_ALPHABET
+            .iter()
+            .position(|&a| a as char == c)
+            .ok_or(ParsePubkeyError::InvalidCharacter(c))?;
+
+        let mut carry = value as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+
+    let mut result = vec![0u8; zeros];
+    result.extend(bytes.iter().rev());
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display_roundtrip() {
+        let text = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let key: Pubkey = text.parse().unwrap();
+        assert_eq!(key.to_string(), text);
+    }
+
+    #[test]
+    fn test_system_program_is_all_zeros() {
+        let key: Pubkey = "11111111111111111111111111111111".parse().unwrap();
+        assert_eq!(key, Pubkey::default());
+        assert_eq!(Pubkey::default().to_string(), "11111111111111111111111111111111");
+    }
+
+    #[test]
+    fn test_reject_invalid_input() {
+        assert_eq!(
+            "test_owner".parse::<Pubkey>(),
+            Err(ParsePubkeyError::InvalidCharacter('_'))
+        );
+        assert_eq!(
+            "0OIl".parse::<Pubkey>(),
+            Err(ParsePubkeyError::InvalidCharacter('0'))
+        );
+        assert!(matches!(
+            "abc".parse::<Pubkey>(),
+            Err(ParsePubkeyError::InvalidLength(_))
+        ));
+    }
+
+    #[test]
+    fn test_well_known_program_ids() {
+        assert_eq!(SYSTEM_PROGRAM_ID.to_string(), "11111111111111111111111111111111");
+        assert_eq!(
+            TOKEN_PROGRAM_ID.to_string(),
+            "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
+        );
+        assert_eq!(
+            ASSOCIATED_TOKEN_PROGRAM_ID.to_string(),
+            "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"
+        );
+    }
+
+    #[test]
+    fn test_new_unique() {
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+        assert_ne!(a, b);
+        assert_eq!(a.to_string().parse::<Pubkey>().unwrap(), a);
+    }
+}
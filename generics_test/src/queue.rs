@@ -0,0 +1,148 @@
+// 待处理交易队列 - 模拟验证者的调度器：优先费高的交易先出队，优先费相同时先到先出。
+// 同一批交易会被并行执行，所以批内交易之间不能有账户锁冲突；冲突的交易留在队列里等下一批
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use crate::locks::AccountLockManager;
+use crate::transaction::Transaction;
+use crate::{AccountMeta, SYSTEM_PROGRAM_ID};
+
+pub const DEFAULT_BATCH_SIZE: usize = 4;
+
+// 排序键：(优先费从高到低, 入队顺序)
+type QueueKey = (Reverse<u64>, u64);
+
+#[derive(Debug)]
+pub struct TransactionQueue {
+    pending: BTreeMap<QueueKey, Transaction>,
+    next_seq: u64,
+    batch_size: usize,
+}
+
+impl Default for TransactionQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_BATCH_SIZE)
+    }
+}
+
+impl TransactionQueue {
+    pub fn new(batch_size: usize) -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            next_seq: 0,
+            batch_size,
+        }
+    }
+
+    // 没有优先费的交易按0处理
+    pub fn push(&mut self, tx: Transaction, priority_fee: Option<u64>) {
+        let key = (Reverse(priority_fee.unwrap_or(0)), self.next_seq);
+        self.next_seq += 1;
+        self.pending.insert(key, tx);
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    // 按优先级取出最多batch_size笔互不冲突的交易
+    pub fn pop_next_batch(&mut self) -> Vec<Transaction> {
+        let mut locks = AccountLockManager::new();
+        let mut selected = Vec::new();
+        for (key, tx) in &self.pending {
+            if selected.len() == self.batch_size {
+                break;
+            }
+            if locks.lock_accounts(&account_metas(tx)).is_ok() {
+                selected.push(*key);
+            }
+        }
+        selected
+            .into_iter()
+            .filter_map(|key| self.pending.remove(&key))
+            .collect()
+    }
+}
+
+// 交易需要锁定的账户：fee payer要扣交易费，总是可写
+fn account_metas(tx: &Transaction) -> Vec<AccountMeta> {
+    let mut metas = vec![AccountMeta::new(tx.fee_payer, SYSTEM_PROGRAM_ID, true)];
+    metas.extend(tx.instructions.iter().flat_map(|instruction| instruction.accounts.iter().copied()));
+    metas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::Instruction;
+    use crate::{ProgramInstruction, Pubkey};
+
+    // 每笔交易的fee payer不同，只写入account
+    fn tx(account: Pubkey, amount: u64) -> Transaction {
+        let accounts = vec![AccountMeta::new(account, SYSTEM_PROGRAM_ID, false)];
+        let instruction = Instruction::new(SYSTEM_PROGRAM_ID, ProgramInstruction::Mint { amount }, accounts);
+        Transaction::new(Pubkey::new_unique(), vec![instruction])
+    }
+
+    fn amounts(batch: &[Transaction]) -> Vec<u64> {
+        batch
+            .iter()
+            .map(|tx| match tx.instructions[0].data {
+                ProgramInstruction::Mint { amount } => amount,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_priority_order() {
+        let mut queue = TransactionQueue::new(3);
+        queue.push(tx(Pubkey::new_unique(), 1), None);
+        queue.push(tx(Pubkey::new_unique(), 2), Some(100));
+        queue.push(tx(Pubkey::new_unique(), 3), Some(500));
+        queue.push(tx(Pubkey::new_unique(), 4), Some(100));
+        queue.push(tx(Pubkey::new_unique(), 5), Some(0));
+
+        // 优先费相同的按入队顺序
+        assert_eq!(amounts(&queue.pop_next_batch()), [3, 2, 4]);
+        assert_eq!(amounts(&queue.pop_next_batch()), [1, 5]);
+        assert!(queue.is_empty());
+        assert!(queue.pop_next_batch().is_empty());
+    }
+
+    #[test]
+    fn test_conflicting_transactions_wait() {
+        let mut queue = TransactionQueue::new(3);
+        let hot = Pubkey::new_unique();
+        queue.push(tx(hot, 1), Some(300));
+        queue.push(tx(hot, 2), Some(200));
+        queue.push(tx(Pubkey::new_unique(), 3), Some(100));
+
+        // 第二笔和第一笔争用同一个可写账户，优先费更低的第三笔先进入本批
+        assert_eq!(amounts(&queue.pop_next_batch()), [1, 3]);
+        assert_eq!(amounts(&queue.pop_next_batch()), [2]);
+    }
+
+    #[test]
+    fn test_low_priority_starves() {
+        let mut queue = TransactionQueue::new(2);
+        queue.push(tx(Pubkey::new_unique(), 0), None);
+
+        // 只要不断有更高优先费的交易到来，低优先费的交易就一直排不上
+        for round in 1..=5 {
+            queue.push(tx(Pubkey::new_unique(), round), Some(10));
+            queue.push(tx(Pubkey::new_unique(), round), Some(10));
+            let batch = queue.pop_next_batch();
+            assert_eq!(amounts(&batch), [round, round]);
+            assert_eq!(queue.len(), 1);
+        }
+
+        // 高优先费的交易不再到来后才轮到它
+        assert_eq!(amounts(&queue.pop_next_batch()), [0]);
+    }
+}
@@ -0,0 +1,191 @@
+// 模拟Solana账户数据的realloc：以字节缓冲区保存数据，可以在限制内扩容或缩容
+
+use std::fmt;
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::rent::Rent;
+use crate::AccountWrapper;
+
+// 与Solana一致：单次最多扩容10KiB，账户数据最大10MiB
+pub const MAX_PERMITTED_DATA_INCREASE: usize = 10 * 1024;
+pub const MAX_PERMITTED_DATA_LENGTH: usize = 10 * 1024 * 1024;
+
+// 字节形式的账户数据。
+// 和链上一样，缩容只改变长度，不会擦除后面的字节；
+// 再次扩容时如果不要求zero_init，就可能读到之前残留的数据
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DataBuffer {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl DataBuffer {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        let len = bytes.len();
+        Self { bytes, len }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.bytes[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+// 只序列化有效长度内的字节，和Vec<u8>的格式相同
+impl BorshSerialize for DataBuffer {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        (self.len as u32).serialize(out);
+        out.extend_from_slice(self.as_slice());
+    }
+}
+
+impl BorshDeserialize for DataBuffer {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self::new(Vec::<u8>::deserialize(buf)?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReallocError {
+    ExceedsMaxIncrease { requested: usize, max: usize }, // 单次扩容超过限制
+    ExceedsMaxLength(usize),                            // 超过账户数据最大长度
+    NotRentExempt { required: u64, actual: u64 },       // 扩容后余额不足以免租
+}
+
+impl fmt::Display for ReallocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReallocError::ExceedsMaxIncrease { requested, max } => {
+                write!(f, "单次扩容{}字节，超过上限{}字节", requested, max)
+            }
+            ReallocError::ExceedsMaxLength(len) => {
+                write!(f, "数据长度{}超过上限{}字节", len, MAX_PERMITTED_DATA_LENGTH)
+            }
+            ReallocError::NotRentExempt { required, actual } => {
+                write!(f, "余额不足以免租: 需要{} lamports，实际{} lamports", required, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReallocError {}
+
+// 只有字节缓冲区形式的账户才支持realloc
+impl AccountWrapper<DataBuffer> {
+    pub fn realloc(&mut self, new_size: usize, zero_init: bool, rent: &Rent) -> Result<(), ReallocError> {
+        let old_size = self.data.len;
+
+        if new_size > MAX_PERMITTED_DATA_LENGTH {
+            return Err(ReallocError::ExceedsMaxLength(new_size));
+        }
+        if new_size.saturating_sub(old_size) > MAX_PERMITTED_DATA_INCREASE {
+            return Err(ReallocError::ExceedsMaxIncrease {
+                requested: new_size - old_size,
+                max: MAX_PERMITTED_DATA_INCREASE,
+            });
+        }
+
+        // 数据变大后免租门槛也会提高，需要重新检查
+        let required = rent.minimum_balance(new_size);
+        if self.lamports < required {
+            return Err(ReallocError::NotRentExempt {
+                required,
+                actual: self.lamports,
+            });
+        }
+
+        let buffer = &mut self.data;
+        if new_size > buffer.bytes.len() {
+            buffer.bytes.resize(new_size, 0);
+        }
+        if zero_init && new_size > old_size {
+            buffer.bytes[old_size..new_size].fill(0);
+        }
+        buffer.len = new_size;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pubkey;
+
+    fn account(data: Vec<u8>, lamports: u64) -> AccountWrapper<DataBuffer> {
+        AccountWrapper::new(Pubkey::new_unique(), DataBuffer::new(data), Pubkey::new_unique())
+            .with_lamports(lamports)
+    }
+
+    #[test]
+    fn test_grow_and_shrink() {
+        let rent = Rent::default();
+        let mut wrapped = account(vec![7; 4], rent.minimum_balance(100));
+
+        assert_eq!(wrapped.realloc(100, true, &rent), Ok(()));
+        assert_eq!(wrapped.data.len(), 100);
+        assert_eq!(&wrapped.data.as_slice()[..5], &[7, 7, 7, 7, 0]);
+
+        assert_eq!(wrapped.realloc(2, false, &rent), Ok(()));
+        assert_eq!(wrapped.data.as_slice(), &[7, 7]);
+    }
+
+    #[test]
+    fn test_zero_init_clears_stale_bytes() {
+        let rent = Rent::default();
+        let mut wrapped = account(vec![9; 4], rent.minimum_balance(4));
+
+        wrapped.realloc(1, false, &rent).unwrap();
+        // 不要求清零时，缩容前的数据会重新出现
+        wrapped.realloc(4, false, &rent).unwrap();
+        assert_eq!(wrapped.data.as_slice(), &[9, 9, 9, 9]);
+
+        wrapped.realloc(1, false, &rent).unwrap();
+        wrapped.realloc(4, true, &rent).unwrap();
+        assert_eq!(wrapped.data.as_slice(), &[9, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_growth_limits() {
+        let rent = Rent::default();
+        let mut wrapped = account(vec![], u64::MAX);
+
+        assert_eq!(
+            wrapped.realloc(MAX_PERMITTED_DATA_INCREASE + 1, true, &rent),
+            Err(ReallocError::ExceedsMaxIncrease {
+                requested: MAX_PERMITTED_DATA_INCREASE + 1,
+                max: MAX_PERMITTED_DATA_INCREASE,
+            })
+        );
+        assert_eq!(
+            wrapped.realloc(MAX_PERMITTED_DATA_LENGTH + 1, true, &rent),
+            Err(ReallocError::ExceedsMaxLength(MAX_PERMITTED_DATA_LENGTH + 1))
+        );
+        assert_eq!(wrapped.realloc(MAX_PERMITTED_DATA_INCREASE, true, &rent), Ok(()));
+    }
+
+    #[test]
+    fn test_rent_recheck() {
+        let rent = Rent::default();
+        let mut wrapped = account(vec![], rent.minimum_balance(10));
+
+        assert_eq!(
+            wrapped.realloc(11, true, &rent),
+            Err(ReallocError::NotRentExempt {
+                required: rent.minimum_balance(11),
+                actual: rent.minimum_balance(10),
+            })
+        );
+        assert!(wrapped.data.is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+// 账户注册表 - 保存所有Token账户，并提供按owner/mint过滤的惰性迭代器
+
+use std::collections::BTreeMap;
+
+use crate::{Pubkey, TokenAccount};
+
+#[derive(Debug, Default)]
+pub struct AccountRegistry {
+    accounts: BTreeMap<Pubkey, TokenAccount>,
+}
+
+impl AccountRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 插入或替换账户，返回被替换的旧账户
+    pub fn insert(&mut self, key: Pubkey, account: TokenAccount) -> Option<TokenAccount> {
+        self.accounts.insert(key, account)
+    }
+
+    pub fn get(&self, key: &Pubkey) -> Option<&TokenAccount> {
+        self.accounts.get(key)
+    }
+
+    pub fn remove(&mut self, key: &Pubkey) -> Option<TokenAccount> {
+        self.accounts.remove(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    // 返回的迭代器借用了self，所以在迭代期间不能修改注册表
+    pub fn iter(&self) -> impl Iterator<Item = (&Pubkey, &TokenAccount)> {
+        self.accounts.iter()
+    }
+
+    // 惰性过滤：只有在迭代时才会逐个检查账户
+    pub fn iter_by_owner(&self, owner: Pubkey) -> impl Iterator<Item = (&Pubkey, &TokenAccount)> {
+        self.iter().filter(move |(_, account)| account.owner == owner)
+    }
+
+    pub fn iter_by_mint(&self, mint: Pubkey) -> impl Iterator<Item = (&Pubkey, &TokenAccount)> {
+        self.iter().filter(move |(_, account)| account.mint == mint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_iterators() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let usdc = Pubkey::new_unique();
+        let sol = Pubkey::new_unique();
+
+        let mut registry = AccountRegistry::new();
+        for (owner, mint, amount) in [(alice, usdc, 10), (alice, sol, 20), (bob, usdc, 30)] {
+            registry.insert(Pubkey::new_unique(), TokenAccount::new(mint, owner, amount));
+        }
+
+        assert_eq!(registry.iter().count(), 3);
+        assert_eq!(registry.iter_by_owner(alice).map(|(_, a)| a.amount).sum::<u64>(), 30);
+        assert_eq!(registry.iter_by_mint(usdc).map(|(_, a)| a.amount).sum::<u64>(), 40);
+        assert_eq!(registry.iter_by_owner(Pubkey::new_unique()).next(), None);
+    }
+
+    #[test]
+    fn test_insert_replace_and_remove() {
+        let key = Pubkey::new_unique();
+        let account = TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 1);
+
+        let mut registry = AccountRegistry::new();
+        assert_eq!(registry.insert(key, account.clone()), None);
+        let updated = TokenAccount { amount: 2, ..account.clone() };
+        assert_eq!(registry.insert(key, updated.clone()), Some(account));
+        assert_eq!(registry.get(&key), Some(&updated));
+        assert_eq!(registry.remove(&key), Some(updated));
+        assert!(registry.is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+// 模拟Solana的Rent sysvar - 账户需要存入足够的lamports才能免交租金
+
+// 每个账户除数据外固定占用的存储开销（字节），与Solana一致
+pub const ACCOUNT_STORAGE_OVERHEAD: u64 = 128;
+
+// 按每个slot 400ms计算，一年大约有78,894,000个slot
+pub const SLOTS_PER_YEAR: u64 = 78_894_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rent {
+    pub lamports_per_byte_year: u64, // 每字节每年的租金
+    pub exemption_threshold: f64,    // 存够多少年的租金即可豁免
+    pub burn_percent: u8,            // 收取的租金中被销毁的比例
+}
+
+// 默认值取自Solana主网
+impl Default for Rent {
+    fn default() -> Self {
+        Self {
+            lamports_per_byte_year: 3480,
+            exemption_threshold: 2.0,
+            burn_percent: 50,
+        }
+    }
+}
+
+impl Rent {
+    // 数据大小为data_len的账户免租所需的最低余额。
+    // Rent可以自己配置，乘积溢出时取u64::MAX，这样的账户永远达不到免租
+    pub fn minimum_balance(&self, data_len: usize) -> u64 {
+        let bytes = ACCOUNT_STORAGE_OVERHEAD.saturating_add(data_len as u64);
+        (bytes.saturating_mul(self.lamports_per_byte_year) as f64 * self.exemption_threshold) as u64
+    }
+
+    pub fn is_exempt(&self, balance: u64, data_len: usize) -> bool {
+        balance >= self.minimum_balance(data_len)
+    }
+
+    // 经过slots个slot后账户应交的租金，免租账户不交；不足1 lamport的部分向下取整
+    pub fn due(&self, balance: u64, data_len: usize, slots: u64) -> u64 {
+        if self.is_exempt(balance, data_len) {
+            return 0;
+        }
+        let bytes = ACCOUNT_STORAGE_OVERHEAD.saturating_add(data_len as u64);
+        let due = bytes as u128 * self.lamports_per_byte_year as u128 * slots as u128 / SLOTS_PER_YEAR as u128;
+        due.min(u64::MAX as u128) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimum_balance_matches_solana() {
+        let rent = Rent::default();
+        // 0字节账户：solana rent 0 => 890880 lamports
+        assert_eq!(rent.minimum_balance(0), 890_880);
+        // SPL Token账户(165字节)：solana rent 165 => 2039280 lamports
+        assert_eq!(rent.minimum_balance(165), 2_039_280);
+    }
+
+    #[test]
+    fn test_is_exempt() {
+        let rent = Rent::default();
+        let required = rent.minimum_balance(10);
+        assert!(rent.is_exempt(required, 10));
+        assert!(!rent.is_exempt(required - 1, 10));
+    }
+
+    #[test]
+    fn test_due() {
+        let rent = Rent::default();
+        let yearly = (ACCOUNT_STORAGE_OVERHEAD + 10) * rent.lamports_per_byte_year;
+        assert_eq!(rent.due(1, 10, SLOTS_PER_YEAR), yearly);
+        assert_eq!(rent.due(1, 10, SLOTS_PER_YEAR / 2), yearly / 2);
+        assert_eq!(rent.due(rent.minimum_balance(10), 10, SLOTS_PER_YEAR), 0);
+    }
+
+    #[test]
+    fn test_minimum_balance_saturates() {
+        let rent = Rent { lamports_per_byte_year: u64::MAX, ..Rent::default() };
+        assert_eq!(rent.minimum_balance(0), u64::MAX);
+        assert_eq!(Rent::default().minimum_balance(usize::MAX), u64::MAX);
+        assert!(!rent.is_exempt(u64::MAX - 1, 0));
+    }
+}
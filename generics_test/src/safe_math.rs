@@ -0,0 +1,74 @@
+// 代币数量的checked运算 - u64的+、-、*在release模式下溢出会静默回绕，
+// 余额从0减1会变成u64::MAX。程序里所有会改变余额的计算都走这里，溢出时返回MathError
+
+use std::fmt;
+
+use crate::TransactionResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathError {
+    Overflow,       // 结果超过u64::MAX
+    Underflow,      // 结果小于0
+    DivisionByZero, // 除数为0
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MathError::Overflow => write!(f, "计算溢出"),
+            MathError::Underflow => write!(f, "计算下溢"),
+            MathError::DivisionByZero => write!(f, "除数为0"),
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
+
+// 扣减余额时下溢就是余额不足
+impl From<MathError> for TransactionResult {
+    fn from(error: MathError) -> Self {
+        match error {
+            MathError::Underflow => TransactionResult::InsufficientFunds,
+            MathError::Overflow | MathError::DivisionByZero => TransactionResult::ArithmeticOverflow,
+        }
+    }
+}
+
+pub fn checked_add(a: u64, b: u64) -> Result<u64, MathError> {
+    a.checked_add(b).ok_or(MathError::Overflow)
+}
+
+pub fn checked_sub(a: u64, b: u64) -> Result<u64, MathError> {
+    a.checked_sub(b).ok_or(MathError::Underflow)
+}
+
+// value * numerator / denominator，向下取整；乘积用u128保存，只有最终结果超过u64时才算溢出
+pub fn checked_mul_div(value: u64, numerator: u64, denominator: u64) -> Result<u64, MathError> {
+    if denominator == 0 {
+        return Err(MathError::DivisionByZero);
+    }
+    let result = value as u128 * numerator as u128 / denominator as u128;
+    u64::try_from(result).map_err(|_| MathError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_operations() {
+        assert_eq!(checked_add(u64::MAX - 1, 1), Ok(u64::MAX));
+        assert_eq!(checked_add(u64::MAX, 1), Err(MathError::Overflow));
+        assert_eq!(checked_sub(5, 5), Ok(0));
+        assert_eq!(checked_sub(0, 1), Err(MathError::Underflow));
+
+        // 中间结果超过u64也没关系
+        assert_eq!(checked_mul_div(u64::MAX, u64::MAX, u64::MAX), Ok(u64::MAX));
+        assert_eq!(checked_mul_div(10, 1, 3), Ok(3));
+        assert_eq!(checked_mul_div(u64::MAX, 2, 1), Err(MathError::Overflow));
+        assert_eq!(checked_mul_div(1, 1, 0), Err(MathError::DivisionByZero));
+
+        assert_eq!(TransactionResult::from(MathError::Underflow), TransactionResult::InsufficientFunds);
+        assert_eq!(TransactionResult::from(MathError::Overflow), TransactionResult::ArithmeticOverflow);
+    }
+}
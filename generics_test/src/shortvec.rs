@@ -0,0 +1,130 @@
+// shortvec紧凑长度编码 - Solana交易格式中账户数量、指令数据长度等都用它表示
+// 每字节低7位存数据，最高位表示后面还有字节；最多3字节，能表示0..=u16::MAX
+
+use std::fmt;
+
+pub const MAX_ENCODING_LENGTH: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortVecError {
+    UnexpectedEof, // 最后一个字节仍然标记"后面还有"
+    Overflow,      // 超过u16范围或超过3字节
+    Alias,         // 非最短编码，例如用[0x80, 0x00]表示0
+}
+
+impl fmt::Display for ShortVecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShortVecError::UnexpectedEof => write!(f, "shortvec数据不完整"),
+            ShortVecError::Overflow => write!(f, "shortvec长度超过u16范围"),
+            ShortVecError::Alias => write!(f, "shortvec不是最短编码"),
+        }
+    }
+}
+
+impl std::error::Error for ShortVecError {}
+
+pub fn encode_len(len: u16, out: &mut Vec<u8>) {
+    let mut rem = len;
+    loop {
+        let mut byte = (rem & 0x7f) as u8;
+        rem >>= 7;
+        if rem == 0 {
+            out.push(byte);
+            return;
+        }
+        byte |= 0x80;
+        out.push(byte);
+    }
+}
+
+// 返回解码出的长度和占用的字节数
+pub fn decode_len(bytes: &[u8]) -> Result<(u16, usize), ShortVecError> {
+    let mut value: u32 = 0;
+    for i in 0..MAX_ENCODING_LENGTH {
+        let byte = *bytes.get(i).ok_or(ShortVecError::UnexpectedEof)?;
+        // 除了第一个字节，值为0的结尾字节说明可以用更短的编码
+        if byte == 0 && i > 0 {
+            return Err(ShortVecError::Alias);
+        }
+        value |= ((byte & 0x7f) as u32) << (i * 7);
+        if value > u16::MAX as u32 {
+            return Err(ShortVecError::Overflow);
+        }
+        if byte & 0x80 == 0 {
+            return Ok((value as u16, i + 1));
+        }
+    }
+    Err(ShortVecError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(len: u16) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_len(len, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_boundary_values() {
+        let cases: [(u16, &[u8]); 8] = [
+            (0, &[0x00]),
+            (0x7f, &[0x7f]),
+            (0x80, &[0x80, 0x01]),
+            (0xff, &[0xff, 0x01]),
+            (0x100, &[0x80, 0x02]),
+            (0x3fff, &[0xff, 0x7f]),
+            (0x4000, &[0x80, 0x80, 0x01]),
+            (u16::MAX, &[0xff, 0xff, 0x03]),
+        ];
+        for (len, bytes) in cases {
+            assert_eq!(encoded(len), bytes, "encode {:#x}", len);
+            assert_eq!(decode_len(bytes), Ok((len, bytes.len())), "decode {:#x}", len);
+        }
+    }
+
+    #[test]
+    fn test_decode_ignores_trailing_data() {
+        assert_eq!(decode_len(&[0x05, 0xaa, 0xbb]), Ok((5, 1)));
+    }
+
+    #[test]
+    fn test_reject_invalid_encodings() {
+        assert_eq!(decode_len(&[]), Err(ShortVecError::UnexpectedEof));
+        assert_eq!(decode_len(&[0x80]), Err(ShortVecError::UnexpectedEof));
+        assert_eq!(decode_len(&[0x80, 0x00]), Err(ShortVecError::Alias));
+        assert_eq!(decode_len(&[0xff, 0x80, 0x00]), Err(ShortVecError::Alias));
+        assert_eq!(decode_len(&[0xff, 0xff, 0x04]), Err(ShortVecError::Overflow));
+        assert_eq!(decode_len(&[0x80, 0x80, 0x80, 0x01]), Err(ShortVecError::Overflow));
+    }
+
+    #[test]
+    fn test_roundtrip_all_lengths() {
+        for len in 0..=u16::MAX {
+            let bytes = encoded(len);
+            assert!(bytes.len() <= MAX_ENCODING_LENGTH);
+            assert_eq!(decode_len(&bytes), Ok((len, bytes.len())));
+        }
+    }
+
+    // 简单的模糊测试：随机字节要么解码失败，要么重新编码后得到完全相同的字节
+    #[test]
+    fn test_fuzz_random_input() {
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        for _ in 0..100_000 {
+            // xorshift64伪随机数，保证测试可重复
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let bytes = state.to_le_bytes();
+            let input = &bytes[..(state % 5) as usize];
+
+            if let Ok((len, consumed)) = decode_len(input) {
+                assert_eq!(encoded(len), &input[..consumed]);
+            }
+        }
+    }
+}
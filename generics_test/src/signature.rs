@@ -0,0 +1,266 @@
+// 交易签名 - 模拟Ed25519签名的流程
+// 派生公钥、签名和验签都通过SignatureScheme，默认的ToySchnorr是一个很小的群上的Schnorr签名：
+// 签名需要私钥，只用公钥就能验证，但61位的离散对数很容易破解，只用来演示签名在交易中的位置；
+// 真实实现可以为ed25519-dalek实现SignatureScheme，再把DefaultScheme指向它
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::hash::{hash, hashv};
+use crate::pubkey::encode_base58;
+use crate::Pubkey;
+
+pub const SIGNATURE_BYTES: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Signature([u8; SIGNATURE_BYTES]);
+
+impl Signature {
+    pub fn new_from_array(bytes: [u8; SIGNATURE_BYTES]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn to_bytes(self) -> [u8; SIGNATURE_BYTES] {
+        self.0
+    }
+
+    // 检查签名是否由pubkey对应的私钥对message签出
+    pub fn verify(&self, pubkey: &Pubkey, message: &[u8]) -> bool {
+        DefaultScheme::verify(pubkey, message, self)
+    }
+}
+
+impl Default for Signature {
+    fn default() -> Self {
+        Self([0; SIGNATURE_BYTES])
+    }
+}
+
+// 和Solana一样以base58显示
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode_base58(&self.0))
+    }
+}
+
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode_base58(&self.0))
+    }
+}
+
+// 为交易签名时提供的signer与需要的签名者不一致
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerError {
+    MissingSigner(Pubkey),    // 需要签名但没有提供对应的signer
+    DuplicateSigner(Pubkey),  // 同一个signer提供了多次
+    UnexpectedSigner(Pubkey), // 提供了交易不需要的signer
+    MessageMismatch,          // 合并签名的两份交易内容不同
+}
+
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignerError::MissingSigner(key) => write!(f, "缺少签名者: {}", key),
+            SignerError::DuplicateSigner(key) => write!(f, "重复的签名者: {}", key),
+            SignerError::UnexpectedSigner(key) => write!(f, "交易不需要该签名者: {}", key),
+            SignerError::MessageMismatch => write!(f, "两份交易的消息不同，不能合并签名"),
+        }
+    }
+}
+
+impl std::error::Error for SignerError {}
+
+// 能对消息签名的对象，类似于solana_sdk::signer::Signer
+pub trait Signer {
+    fn pubkey(&self) -> Pubkey;
+    fn sign_message(&self, message: &[u8]) -> Signature;
+}
+
+// 签名方案：由私钥派生公钥、用私钥签名、只用公钥验签
+pub trait SignatureScheme {
+    fn derive_pubkey(secret: &[u8; 32]) -> Pubkey;
+    fn sign(secret: &[u8; 32], message: &[u8]) -> Signature;
+    fn verify(pubkey: &Pubkey, message: &[u8], signature: &Signature) -> bool;
+}
+
+// Keypair和Signature::verify使用的方案
+pub type DefaultScheme = ToySchnorr;
+
+// 模数p = 2^61 - 1的乘法群上的Schnorr签名，指数按群的阶p - 1取模
+pub struct ToySchnorr;
+
+const MODULUS: u64 = (1 << 61) - 1;
+const GENERATOR: u64 = 37;
+
+fn mul_mod(a: u64, b: u64) -> u64 {
+    (a as u128 * b as u128 % MODULUS as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exponent: u64) -> u64 {
+    let mut result = 1;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base);
+        }
+        base = mul_mod(base, base);
+        exponent >>= 1;
+    }
+    result
+}
+
+// 取哈希的前8字节，按群的阶取模
+fn to_exponent(bytes: &[u8]) -> u64 {
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(head) % (MODULUS - 1)
+}
+
+impl ToySchnorr {
+    // 私钥对应的指数x，不能为0
+    fn secret_exponent(secret: &[u8; 32]) -> u64 {
+        to_exponent(&hash(secret)).max(1)
+    }
+
+    // 公钥的前8字节是y = g^x，其余24字节是y的哈希，只是让地址看起来和真实的一样随机
+    fn encode_pubkey(y: u64) -> Pubkey {
+        let mut bytes = [0u8; 32];
+        bytes[..8].copy_from_slice(&y.to_le_bytes());
+        bytes[8..].copy_from_slice(&hash(&y.to_le_bytes())[..24]);
+        Pubkey::new_from_array(bytes)
+    }
+
+    fn decode_pubkey(pubkey: &Pubkey) -> Option<u64> {
+        let mut head = [0u8; 8];
+        head.copy_from_slice(&pubkey.as_ref()[..8]);
+        let y = u64::from_le_bytes(head);
+        (y != 0 && y < MODULUS && Self::encode_pubkey(y) == *pubkey).then_some(y)
+    }
+
+    fn challenge(r: u64, pubkey: &Pubkey, message: &[u8]) -> [u8; 32] {
+        hashv(&[&r.to_le_bytes(), pubkey.as_ref(), message])
+    }
+}
+
+// 签名 = e(32字节) || s(8字节) || 24个0字节，其中
+// k = H(secret || message)，r = g^k，e = H(r || pubkey || message)，s = k + e·x
+// 验签时算出r' = g^s · y^(-e)，再检查H(r' || pubkey || message) == e
+impl SignatureScheme for ToySchnorr {
+    fn derive_pubkey(secret: &[u8; 32]) -> Pubkey {
+        Self::encode_pubkey(pow_mod(GENERATOR, Self::secret_exponent(secret)))
+    }
+
+    fn sign(secret: &[u8; 32], message: &[u8]) -> Signature {
+        let order = MODULUS - 1;
+        let x = Self::secret_exponent(secret);
+        let k = to_exponent(&hashv(&[secret, message]));
+        let e = Self::challenge(pow_mod(GENERATOR, k), &Self::derive_pubkey(secret), message);
+        let s = ((k as u128 + to_exponent(&e) as u128 * x as u128) % order as u128) as u64;
+        let mut bytes = [0u8; SIGNATURE_BYTES];
+        bytes[..32].copy_from_slice(&e);
+        bytes[32..40].copy_from_slice(&s.to_le_bytes());
+        Signature(bytes)
+    }
+
+    fn verify(pubkey: &Pubkey, message: &[u8], signature: &Signature) -> bool {
+        let Some(y) = Self::decode_pubkey(pubkey) else {
+            return false;
+        };
+        let bytes = &signature.0;
+        if bytes[40..].iter().any(|&byte| byte != 0) {
+            return false;
+        }
+        let mut s = [0u8; 8];
+        s.copy_from_slice(&bytes[32..40]);
+        let s = u64::from_le_bytes(s);
+        if s >= MODULUS - 1 {
+            return false;
+        }
+        // y^(-e) = y^(p-1-e)
+        let r = mul_mod(pow_mod(GENERATOR, s), pow_mod(y, MODULUS - 1 - to_exponent(&bytes[..32])));
+        Self::challenge(r, pubkey, message)[..] == bytes[..32]
+    }
+}
+
+#[derive(Debug)]
+pub struct Keypair {
+    secret: [u8; 32],
+    pubkey: Pubkey,
+}
+
+impl Keypair {
+    // 公钥由签名方案从私钥派生
+    pub fn from_secret(secret: [u8; 32]) -> Self {
+        let pubkey = DefaultScheme::derive_pubkey(&secret);
+        Self { secret, pubkey }
+    }
+
+    // 进程内生成互不相同的密钥对，方便演示和测试
+    pub fn new() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(1);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        Self::from_secret(hashv(&[b"keypair", &n.to_le_bytes()]))
+    }
+
+    pub fn secret(&self) -> &[u8; 32] {
+        &self.secret
+    }
+}
+
+impl Default for Keypair {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Signer for Keypair {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Signature {
+        DefaultScheme::sign(&self.secret, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let keypair = Keypair::new();
+        let signature = keypair.sign_message(b"hello");
+        assert!(signature.verify(&keypair.pubkey(), b"hello"));
+        assert!(!signature.verify(&keypair.pubkey(), b"hello!"));
+        assert!(!signature.verify(&Keypair::new().pubkey(), b"hello"));
+    }
+
+    #[test]
+    fn test_keypair_is_deterministic() {
+        let a = Keypair::from_secret([7; 32]);
+        let b = Keypair::from_secret([7; 32]);
+        assert_eq!(a.pubkey(), b.pubkey());
+        assert_eq!(a.secret(), b.secret());
+        assert_ne!(Keypair::new().pubkey(), Keypair::new().pubkey());
+    }
+
+    #[test]
+    fn test_signature_depends_on_secret() {
+        let keypair = Keypair::from_secret([7; 32]);
+        let signature = keypair.sign_message(b"hello");
+        // 只知道公钥，用别的私钥签出的签名验证不过
+        let forged = ToySchnorr::sign(&[8; 32], b"hello");
+        assert_ne!(forged, signature);
+        assert!(!forged.verify(&keypair.pubkey(), b"hello"));
+        // 篡改签名的任何部分都会失败
+        let mut bytes = signature.to_bytes();
+        bytes[35] ^= 1;
+        assert!(!Signature::new_from_array(bytes).verify(&keypair.pubkey(), b"hello"));
+        let mut bytes = signature.to_bytes();
+        bytes[63] = 1;
+        assert!(!Signature::new_from_array(bytes).verify(&keypair.pubkey(), b"hello"));
+        // 不是由私钥派生的地址没有对应的私钥
+        assert!(!signature.verify(&Pubkey::new_unique(), b"hello"));
+    }
+}
@@ -0,0 +1,168 @@
+// 账本快照的二进制文件格式，配合Ledger::snapshot/restore把账户状态保存到磁盘：
+// magic(4) "LSNP" + 版本(1) + Borsh编码的Snapshot + 校验和(8)
+// 校验和是前面所有字节SHA-256的前8字节，文件被截断或改动过都会在解析数据之前发现
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::hash::hash;
+use crate::ledger::Snapshot;
+
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"LSNP";
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = SNAPSHOT_MAGIC.len() + 1;
+const CHECKSUM_LEN: usize = 8;
+
+#[derive(Debug)]
+pub enum SnapshotFileError {
+    Io(io::Error),                                   // 读取失败
+    NotASnapshot,                                    // 开头不是LSNP
+    UnsupportedVersion { found: u8, supported: u8 }, // 不认识的格式版本，通常是更新的程序写的
+    Truncated { len: usize },                        // 连头部和校验和都放不下
+    ChecksumMismatch,                                // 内容和校验和对不上
+    Corrupt(BorshError),                             // 校验和正确但数据无法解析
+}
+
+impl fmt::Display for SnapshotFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotFileError::Io(_) => write!(f, "读取快照失败"),
+            SnapshotFileError::NotASnapshot => write!(f, "不是账本快照文件"),
+            SnapshotFileError::UnsupportedVersion { found, supported } if found > supported => {
+                write!(f, "快照版本v{}比当前程序支持的v{}更新，请升级程序", found, supported)
+            }
+            SnapshotFileError::UnsupportedVersion { found, .. } => write!(f, "不支持的快照版本v{}", found),
+            SnapshotFileError::Truncated { len } => write!(f, "快照文件不完整: 只有{}字节", len),
+            SnapshotFileError::ChecksumMismatch => write!(f, "快照校验和不匹配，文件可能已损坏"),
+            SnapshotFileError::Corrupt(_) => write!(f, "快照数据已损坏"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SnapshotFileError::Io(error) => Some(error),
+            SnapshotFileError::Corrupt(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotFileError {
+    fn from(error: io::Error) -> Self {
+        SnapshotFileError::Io(error)
+    }
+}
+
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_LEN] {
+    hash(bytes)[..CHECKSUM_LEN].try_into().unwrap()
+}
+
+pub fn snapshot_to_bytes(snapshot: &Snapshot) -> Vec<u8> {
+    let mut out = SNAPSHOT_MAGIC.to_vec();
+    out.push(SNAPSHOT_VERSION);
+    snapshot.serialize(&mut out);
+    let sum = checksum(&out);
+    out.extend_from_slice(&sum);
+    out
+}
+
+// 依次检查magic、版本、校验和，最后才解析数据：版本不对时后面的布局可能完全不同
+pub fn snapshot_from_bytes(bytes: &[u8]) -> Result<Snapshot, SnapshotFileError> {
+    if !bytes.starts_with(&SNAPSHOT_MAGIC) {
+        return Err(SnapshotFileError::NotASnapshot);
+    }
+    let Some(&version) = bytes.get(SNAPSHOT_MAGIC.len()) else {
+        return Err(SnapshotFileError::Truncated { len: bytes.len() });
+    };
+    if version != SNAPSHOT_VERSION {
+        return Err(SnapshotFileError::UnsupportedVersion { found: version, supported: SNAPSHOT_VERSION });
+    }
+    if bytes.len() < HEADER_LEN + CHECKSUM_LEN {
+        return Err(SnapshotFileError::Truncated { len: bytes.len() });
+    }
+
+    let (content, sum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    if checksum(content) != sum {
+        return Err(SnapshotFileError::ChecksumMismatch);
+    }
+    Snapshot::try_from_slice(&content[HEADER_LEN..]).map_err(SnapshotFileError::Corrupt)
+}
+
+pub fn write_snapshot<W: Write>(snapshot: &Snapshot, mut writer: W) -> io::Result<()> {
+    writer.write_all(&snapshot_to_bytes(snapshot))?;
+    writer.flush()
+}
+
+pub fn read_snapshot<R: Read>(mut reader: R) -> Result<Snapshot, SnapshotFileError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    snapshot_from_bytes(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{Account, AccountPayload, Ledger};
+    use crate::nonce::NonceAccount;
+    use crate::{Pubkey, TokenAccount, UserAccount, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID};
+
+    fn sample_snapshot() -> Snapshot {
+        let mut ledger = Ledger::default();
+        let payloads = [
+            (TOKEN_PROGRAM_ID, AccountPayload::Token(TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 42))),
+            (SYSTEM_PROGRAM_ID, AccountPayload::User(UserAccount { username: "张三".to_string(), balance: 7, created_at: -1 })),
+            (SYSTEM_PROGRAM_ID, AccountPayload::Nonce(NonceAccount::new(Pubkey::new_unique(), [9; 32]))),
+            (SYSTEM_PROGRAM_ID, AccountPayload::Empty),
+        ];
+        for (owner, payload) in payloads {
+            let lamports = ledger.rent().minimum_balance(payload.data_len());
+            ledger.create_account(Pubkey::new_unique(), Account::new(lamports, owner, payload)).unwrap();
+        }
+        ledger.snapshot()
+    }
+
+    #[test]
+    fn test_write_and_read_roundtrip() {
+        let snapshot = sample_snapshot();
+        let mut file = Vec::new();
+        write_snapshot(&snapshot, &mut file).unwrap();
+        assert_eq!(&file[..5], b"LSNP\x01");
+        assert_eq!(read_snapshot(file.as_slice()).unwrap(), snapshot);
+
+        // 空快照只有头部、账户数量和校验和
+        let empty = Ledger::default().snapshot();
+        assert_eq!(snapshot_to_bytes(&empty).len(), HEADER_LEN + 4 + CHECKSUM_LEN);
+        assert_eq!(snapshot_from_bytes(&snapshot_to_bytes(&empty)).unwrap(), empty);
+    }
+
+    #[test]
+    fn test_reject_corrupt_and_future_files() {
+        let bytes = snapshot_to_bytes(&sample_snapshot());
+        assert!(matches!(snapshot_from_bytes(b"{\"version\": 1}"), Err(SnapshotFileError::NotASnapshot)));
+        assert!(matches!(snapshot_from_bytes(b"LSNP"), Err(SnapshotFileError::Truncated { len: 4 })));
+        assert!(matches!(snapshot_from_bytes(&bytes[..10]), Err(SnapshotFileError::Truncated { len: 10 })));
+        assert!(matches!(snapshot_from_bytes(&bytes[..bytes.len() - 1]), Err(SnapshotFileError::ChecksumMismatch)));
+
+        let mut flipped = bytes.clone();
+        flipped[20] ^= 1;
+        assert!(matches!(snapshot_from_bytes(&flipped), Err(SnapshotFileError::ChecksumMismatch)));
+
+        let mut future = bytes.clone();
+        future[4] = SNAPSHOT_VERSION + 1;
+        let error = snapshot_from_bytes(&future).unwrap_err();
+        assert_eq!(error.to_string(), "快照版本v2比当前程序支持的v1更新，请升级程序");
+
+        // 校验和正确但数据本身不合法，比如未知的账户类型标记
+        let mut content = bytes[..HEADER_LEN].to_vec();
+        content.extend_from_slice(&[1, 0, 0, 0]);
+        content.extend_from_slice(&[0; 32 + 8 + 32]);
+        content.push(200);
+        let sum = checksum(&content);
+        content.extend_from_slice(&sum);
+        assert!(matches!(snapshot_from_bytes(&content), Err(SnapshotFileError::Corrupt(BorshError::InvalidEnumTag(200)))));
+    }
+}
@@ -13,6 +13,13 @@ pub struct TokenAccount {
     pub mint: String,
     pub owner: String,
     pub amount: u64,
+    pub frozen: bool, // 被冻结的账户既不能转出也不能转入
+}
+
+impl TokenAccount {
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
 }
 
 impl Summary for TokenAccount {
@@ -105,25 +112,33 @@ pub enum TransactionResult {
     Success,
     InsufficientFunds,
     InvalidAccount,
+    ArithmeticOverflow,
+    AccountFrozen,
 }
 
-pub fn transfer_tokens<T: Summary + fmt::Debug, U: Summary + fmt::Debug>(
-    from: &T,
-    to: &U,
-    amount: u64,
-) -> TransactionResult {
+// 真正移动余额：先算出两边的新余额，都成功后再写回
+pub fn transfer_tokens(from: &mut TokenAccount, to: &mut TokenAccount, amount: u64) -> TransactionResult {
     println!("开始转账：");
     println!("从：{}", from.summarize());
     println!("  到: {}", to.summarize());
     println!("  金额: {}", amount);
 
-    if amount == 0 {
-        TransactionResult::InvalidAccount
-    } else if amount > 10000 {
-        TransactionResult::InsufficientFunds
-    } else {
-        TransactionResult::Success
+    if amount == 0 || from.mint != to.mint {
+        return TransactionResult::InvalidAccount;
+    }
+    if from.is_frozen() || to.is_frozen() {
+        return TransactionResult::AccountFrozen;
     }
+    let Some(from_amount) = from.amount.checked_sub(amount) else {
+        return TransactionResult::InsufficientFunds;
+    };
+    let Some(to_amount) = to.amount.checked_add(amount) else {
+        return TransactionResult::ArithmeticOverflow;
+    };
+
+    from.amount = from_amount;
+    to.amount = to_amount;
+    TransactionResult::Success
 }
 
 // 处理交易结果
@@ -138,6 +153,12 @@ pub fn handle_transaction_result(result: TransactionResult) {
         TransactionResult::InvalidAccount => {
             println!("❌ 账户无效!");
         },
+        TransactionResult::ArithmeticOverflow => {
+            println!("❌ 余额溢出!");
+        },
+        TransactionResult::AccountFrozen => {
+            println!("❌ 账户已冻结!");
+        },
     }
 }
 
@@ -185,6 +206,7 @@ fn main() {
         mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
         owner: "3LKJFWgogznfBhWUk6QqKi9ePeAg6x7J4XR9fFTGw2vG".to_string(),
         amount: 1000,
+        frozen: false,
     };
 
     println!("摘要： {}", token_account.summarize());
@@ -257,8 +279,19 @@ fn main() {
     println!("\n--- 测试转账和结果处理 ---");
 
     // 测试成功转账
-    let result1 = transfer_tokens(&token_account, &user_account, 100);
+    let mut sender = token_account.clone();
+    let mut receiver = TokenAccount {
+        owner: "7xKJ2nGnWWvR9mHsq4g8X3T2vE6UyB1RfGfVwYnPt9QE".to_string(),
+        amount: 0,
+        ..token_account.clone()
+    };
+    let result1 = transfer_tokens(&mut sender, &mut receiver, 100);
     handle_transaction_result(result1);
+    println!("转账后余额: sender = {}, receiver = {}", sender.amount, receiver.amount);
+
+    // 测试余额不足
+    let result2 = transfer_tokens(&mut sender, &mut receiver, 10000);
+    handle_transaction_result(result2);
 
     // 新增：测试程序指令处理
     println!("\n--- 测试程序指令处理 ---");
@@ -282,3 +315,44 @@ fn main() {
 
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(mint: &str, amount: u64) -> TokenAccount {
+        TokenAccount { mint: mint.to_string(), owner: "owner".to_string(), amount, frozen: false }
+    }
+
+    #[test]
+    fn test_transfer_moves_balances() {
+        let (mut from, mut to) = (account("usdc", 100), account("usdc", 5));
+        assert_eq!(transfer_tokens(&mut from, &mut to, 100), TransactionResult::Success);
+        assert_eq!((from.amount, to.amount), (0, 105));
+    }
+
+    #[test]
+    fn test_transfer_rejections_leave_balances() {
+        let (mut from, mut to) = (account("usdc", 100), account("usdc", 5));
+        assert_eq!(transfer_tokens(&mut from, &mut to, 0), TransactionResult::InvalidAccount);
+        assert_eq!(transfer_tokens(&mut from, &mut to, 101), TransactionResult::InsufficientFunds);
+
+        let mut other = account("sol", 0);
+        assert_eq!(transfer_tokens(&mut from, &mut other, 1), TransactionResult::InvalidAccount);
+
+        let mut full = account("usdc", u64::MAX);
+        assert_eq!(transfer_tokens(&mut from, &mut full, 1), TransactionResult::ArithmeticOverflow);
+        assert_eq!((from.amount, to.amount, other.amount, full.amount), (100, 5, 0, u64::MAX));
+    }
+
+    #[test]
+    fn test_transfer_rejects_frozen_accounts() {
+        let (mut from, mut to) = (account("usdc", 100), account("usdc", 5));
+        from.frozen = true;
+        assert_eq!(transfer_tokens(&mut from, &mut to, 10), TransactionResult::AccountFrozen);
+        from.frozen = false;
+        to.frozen = true;
+        assert_eq!(transfer_tokens(&mut from, &mut to, 10), TransactionResult::AccountFrozen);
+        assert_eq!((from.amount, to.amount), (100, 5));
+    }
+}
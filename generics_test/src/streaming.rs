@@ -0,0 +1,426 @@
+// 支付流 - 发送者把代币存进由支付流PDA控制的vault，从start_slot开始每个slot给接收者释放rate_per_slot个，
+// 直到存入的代币全部释放。接收者随时可以取出已经累计的部分；
+// 发送者取消时按当前slot结算：已经累计的给接收者，剩下的退回发送者
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::cpi::InvokeContext;
+use crate::ledger::AccountPayload;
+use crate::pda::derive_pda;
+use crate::transaction::Instruction;
+use crate::{AccountMeta, ProgramInstruction, Pubkey, TransactionResult};
+
+pub const STREAM_SEED: &[u8] = b"stream";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stream {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub vault: Pubkey, // 保存代币的Token账户，authority是支付流PDA
+    pub deposited: u64,
+    pub withdrawn: u64, // 接收者已经取出的数量
+    pub rate_per_slot: u64,
+    pub start_slot: u64,
+    pub stop_slot: u64, // 取消时的slot，没取消时是u64::MAX
+    pub bump: u8,
+}
+
+impl Stream {
+    // 三个Pubkey(各32) + 五个u64 + bump(1)
+    pub const LEN: usize = 32 * 3 + 8 * 5 + 1;
+
+    pub fn is_canceled(&self) -> bool {
+        self.stop_slot != u64::MAX
+    }
+
+    // 到slot为止累计释放的数量（包括已经取出的），不超过存入的数量。
+    // 两个u64相乘最多128位，用u128计算不会溢出
+    pub fn streamed_amount(&self, slot: u64) -> u64 {
+        let end = slot.min(self.stop_slot);
+        if end <= self.start_slot {
+            return 0;
+        }
+        let accrued = (end - self.start_slot) as u128 * self.rate_per_slot as u128;
+        accrued.min(self.deposited as u128) as u64
+    }
+
+    pub fn withdrawable(&self, slot: u64) -> u64 {
+        self.streamed_amount(slot) - self.withdrawn
+    }
+
+    // 全部释放完的slot，太远时停在u64::MAX
+    pub fn end_slot(&self) -> u64 {
+        self.start_slot.saturating_add(self.deposited.div_ceil(self.rate_per_slot))
+    }
+}
+
+impl BorshSerialize for Stream {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.sender.serialize(out);
+        self.recipient.serialize(out);
+        self.vault.serialize(out);
+        self.deposited.serialize(out);
+        self.withdrawn.serialize(out);
+        self.rate_per_slot.serialize(out);
+        self.start_slot.serialize(out);
+        self.stop_slot.serialize(out);
+        self.bump.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Stream {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            sender: Pubkey::deserialize(buf)?,
+            recipient: Pubkey::deserialize(buf)?,
+            vault: Pubkey::deserialize(buf)?,
+            deposited: u64::deserialize(buf)?,
+            withdrawn: u64::deserialize(buf)?,
+            rate_per_slot: u64::deserialize(buf)?,
+            start_slot: u64::deserialize(buf)?,
+            stop_slot: u64::deserialize(buf)?,
+            bump: u8::deserialize(buf)?,
+        })
+    }
+}
+
+// 支付流地址：种子为["stream", sender, vault]
+pub fn stream_address(sender: &Pubkey, vault: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[STREAM_SEED, sender.as_ref(), vault.as_ref()], program_id)
+}
+
+// 支付流程序的入口函数，注册到Programs后由运行时调用
+pub fn process(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+    let program_id = instruction.program_id;
+    let metas = &instruction.accounts;
+    match instruction.data {
+        ProgramInstruction::CreateStream { amount, rate_per_slot, start_slot } => {
+            create(ctx, &program_id, metas, amount, rate_per_slot, start_slot)
+        }
+        ProgramInstruction::Withdraw => withdraw(ctx, &program_id, metas),
+        ProgramInstruction::CancelStream => cancel(ctx, &program_id, metas),
+        _ => Err(TransactionResult::InvalidInstructionData),
+    }
+}
+
+// accounts: [支付流账户(可写，空的PDA账户), 发送者(签名), 出资的Token账户(可写),
+//            接收者, vault(可写，authority是支付流PDA)]
+fn create(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    amount: u64,
+    rate_per_slot: u64,
+    start_slot: u64,
+) -> Result<(), TransactionResult> {
+    let [stream_meta, sender, source, recipient, vault, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !sender.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if amount == 0 || rate_per_slot == 0 {
+        return Err(TransactionResult::InvalidInstructionData);
+    }
+    let (stream_key, bump) = stream_address(&sender.pubkey, &vault.pubkey, program_id);
+    if stream_meta.pubkey != stream_key || !stream_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let mut account = ctx.staged().get_account(&stream_key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    if account.owner != *program_id || account.data != AccountPayload::Empty {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if account.lamports < ctx.staged().rent().minimum_balance(Stream::LEN) {
+        return Err(TransactionResult::InsufficientFunds);
+    }
+    let (vault_token, token_program) = ctx.token_account(&vault.pubkey)?;
+    if vault_token.owner != stream_key || vault_token.amount != 0 {
+        return Err(TransactionResult::InvalidAccount);
+    }
+
+    let deposit = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(source.pubkey, token_program, false),
+            AccountMeta::new(vault.pubkey, token_program, false),
+            AccountMeta::new_readonly(sender.pubkey, sender.owner, true),
+        ],
+    );
+    ctx.invoke(&deposit, metas)?;
+    // 带转账手续费的mint到账会少一些，按vault实际收到的数量释放
+    let deposited = ctx.token_account(&vault.pubkey)?.0.amount;
+    let stream = Stream {
+        sender: sender.pubkey,
+        recipient: recipient.pubkey,
+        vault: vault.pubkey,
+        deposited,
+        withdrawn: 0,
+        rate_per_slot,
+        start_slot,
+        stop_slot: u64::MAX,
+        bump,
+    };
+    account.data = AccountPayload::Stream(stream);
+    Ok(ctx.staged().set_account(stream_key, account)?)
+}
+
+// accounts: [支付流账户(可写), 接收者(签名), vault(可写), 接收的Token账户(可写)]
+fn withdraw(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let [stream_meta, recipient, vault, destination, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    let mut stream = load_stream(ctx, program_id, stream_meta, vault)?;
+    if stream.recipient != recipient.pubkey || !recipient.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let amount = stream.withdrawable(ctx.slot());
+    if amount == 0 {
+        return Err(TransactionResult::NothingToClaim);
+    }
+    release(ctx, program_id, metas, (stream_meta, &stream), destination.pubkey, amount)?;
+    stream.withdrawn += amount;
+    store_stream(ctx, stream_meta.pubkey, stream)
+}
+
+// accounts: [支付流账户(可写), 发送者(签名), vault(可写),
+//            发送者接收退款的Token账户(可写), 接收者的Token账户(可写)]
+fn cancel(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let [stream_meta, sender, vault, sender_destination, recipient_destination, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    let mut stream = load_stream(ctx, program_id, stream_meta, vault)?;
+    if stream.sender != sender.pubkey || !sender.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if stream.is_canceled() {
+        return Err(TransactionResult::StreamCanceled);
+    }
+    if ctx.token_account(&sender_destination.pubkey)?.0.owner != stream.sender
+        || ctx.token_account(&recipient_destination.pubkey)?.0.owner != stream.recipient
+    {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    // 停在当前slot，之后不再累计
+    stream.stop_slot = ctx.slot();
+    let streamed = stream.streamed_amount(stream.stop_slot);
+    let (to_recipient, to_sender) = (streamed - stream.withdrawn, stream.deposited - streamed);
+    for (destination, amount) in [(recipient_destination, to_recipient), (sender_destination, to_sender)] {
+        if amount > 0 {
+            release(ctx, program_id, metas, (stream_meta, &stream), destination.pubkey, amount)?;
+        }
+    }
+    stream.withdrawn = streamed;
+    store_stream(ctx, stream_meta.pubkey, stream)
+}
+
+fn load_stream(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    stream_meta: &AccountMeta,
+    vault: &AccountMeta,
+) -> Result<Stream, TransactionResult> {
+    let stream = match ctx.staged().get_account(&stream_meta.pubkey) {
+        Some(account) if account.owner == *program_id => match &account.data {
+            AccountPayload::Stream(stream) => stream.clone(),
+            _ => return Err(TransactionResult::InvalidAccount),
+        },
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    if stream.vault != vault.pubkey || !stream_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    Ok(stream)
+}
+
+fn store_stream(ctx: &mut InvokeContext<'_, '_>, key: Pubkey, stream: Stream) -> Result<(), TransactionResult> {
+    let mut account = ctx.staged().get_account(&key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    account.data = AccountPayload::Stream(stream);
+    Ok(ctx.staged().set_account(key, account)?)
+}
+
+// 支付流PDA签名，把amount从vault转到destination
+fn release(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    (stream_meta, stream): (&AccountMeta, &Stream),
+    destination: Pubkey,
+    amount: u64,
+) -> Result<(), TransactionResult> {
+    let (_, token_program) = ctx.token_account(&stream.vault)?;
+    let bump = [stream.bump];
+    let seeds: &[&[u8]] = &[STREAM_SEED, stream.sender.as_ref(), stream.vault.as_ref(), &bump];
+    let transfer = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(stream.vault, token_program, false),
+            AccountMeta::new(destination, token_program, false),
+            AccountMeta::new_readonly(stream_meta.pubkey, *program_id, true),
+        ],
+    );
+    ctx.invoke_signed(&transfer, metas, &[seeds])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpi::{Entrypoint, Programs};
+    use crate::ledger::{Account, Ledger};
+    use crate::signature::{Keypair, Signer};
+    use crate::test_utils::{create_token_account, funded_keypair, sign_and_execute, token_amount};
+    use crate::transaction::TransactionError;
+    use crate::SYSTEM_PROGRAM_ID;
+
+    struct Fixture {
+        ledger: Ledger,
+        programs: Programs,
+        streaming_program: Pubkey,
+        token_program: Pubkey,
+        sender: Keypair,
+        recipient: Keypair,
+        source: Pubkey,
+        destination: Pubkey,
+        vault: Pubkey,
+        stream: Pubkey,
+    }
+
+    // 从slot 10开始每个slot释放7个，共1000个，slot 153释放完
+    fn fixture() -> Fixture {
+        let mut ledger = Ledger::default();
+        let (streaming_program, token_program, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (sender, recipient) = (funded_keypair(&mut ledger), funded_keypair(&mut ledger));
+        let vault = Pubkey::new_unique();
+        let (stream, _) = stream_address(&sender.pubkey(), &vault, &streaming_program);
+        let (source, destination) = (Pubkey::new_unique(), Pubkey::new_unique());
+        for (key, owner, amount) in [(source, sender.pubkey(), 1_000), (destination, recipient.pubkey(), 0), (vault, stream, 0)] {
+            create_token_account(&mut ledger, token_program, key, mint, owner, amount);
+        }
+        let lamports = ledger.rent().minimum_balance(Stream::LEN);
+        ledger.create_account(stream, Account::new(lamports, streaming_program, AccountPayload::Empty)).unwrap();
+        let programs = Programs::from([(streaming_program, process as Entrypoint)]);
+        let mut f = Fixture { ledger, programs, streaming_program, token_program, sender, recipient, source, destination, vault, stream };
+        let accounts = vec![
+            AccountMeta::new(f.stream, f.streaming_program, false),
+            AccountMeta::new_readonly(f.sender.pubkey(), SYSTEM_PROGRAM_ID, true),
+            AccountMeta::new(f.source, f.token_program, false),
+            AccountMeta::new_readonly(f.recipient.pubkey(), SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new(f.vault, f.token_program, false),
+        ];
+        let instruction = Instruction::new(f.streaming_program, ProgramInstruction::CreateStream { amount: 1_000, rate_per_slot: 7, start_slot: 10 }, accounts);
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &f.sender, vec![instruction]), Ok(()));
+        f
+    }
+
+    fn error(result: TransactionResult) -> Result<(), TransactionError> {
+        Err(TransactionError::InstructionError(0, result))
+    }
+
+    impl Fixture {
+        fn withdraw(&mut self) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new(self.stream, self.streaming_program, false),
+                AccountMeta::new_readonly(self.recipient.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.vault, self.token_program, false),
+                AccountMeta::new(self.destination, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.streaming_program, ProgramInstruction::Withdraw, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.recipient, vec![instruction])
+        }
+
+        // signer_is_sender为false时由接收者冒充发送者取消
+        fn cancel(&mut self, signer_is_sender: bool) -> Result<(), TransactionError> {
+            let signer = if signer_is_sender { &self.sender } else { &self.recipient };
+            let accounts = vec![
+                AccountMeta::new(self.stream, self.streaming_program, false),
+                AccountMeta::new_readonly(self.sender.pubkey(), SYSTEM_PROGRAM_ID, signer_is_sender),
+                AccountMeta::new(self.vault, self.token_program, false),
+                AccountMeta::new(self.source, self.token_program, false),
+                AccountMeta::new(self.destination, self.token_program, false),
+            ];
+            let instruction = Instruction::new(self.streaming_program, ProgramInstruction::CancelStream, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, signer, vec![instruction])
+        }
+
+        fn advance_to(&mut self, slot: u64) {
+            let slots = slot - self.ledger.slot();
+            self.ledger.tick(slots);
+        }
+    }
+
+    #[test]
+    fn test_streamed_amount() {
+        let mut stream = Stream {
+            sender: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            deposited: 1_000,
+            withdrawn: 0,
+            rate_per_slot: 7,
+            start_slot: 10,
+            stop_slot: u64::MAX,
+            bump: 255,
+        };
+        assert_eq!(stream.streamed_amount(0), 0);
+        assert_eq!(stream.streamed_amount(10), 0);
+        assert_eq!(stream.streamed_amount(11), 7);
+        assert_eq!(stream.streamed_amount(152), 994);
+        assert_eq!(stream.end_slot(), 153);
+        assert_eq!(stream.streamed_amount(153), 1_000);
+        assert_eq!(stream.streamed_amount(u64::MAX), 1_000);
+        // 取消之后停止累计
+        stream.stop_slot = 20;
+        assert_eq!(stream.streamed_amount(u64::MAX), 70);
+
+        // 速率和经过的slot都很大时不会溢出
+        let large = Stream { deposited: u64::MAX, rate_per_slot: u64::MAX, start_slot: 0, stop_slot: u64::MAX, ..stream.clone() };
+        assert_eq!(large.streamed_amount(u64::MAX), u64::MAX);
+        assert_eq!(large.end_slot(), 1);
+        let slow = Stream { deposited: u64::MAX, rate_per_slot: 1, start_slot: u64::MAX - 1, stop_slot: u64::MAX, ..stream.clone() };
+        assert_eq!(slow.end_slot(), u64::MAX);
+
+        let mut bytes = Vec::new();
+        stream.serialize(&mut bytes);
+        assert_eq!(bytes.len(), Stream::LEN);
+        assert_eq!(Stream::deserialize(&mut bytes.as_slice()), Ok(stream));
+    }
+
+    #[test]
+    fn test_withdraw_accrued() {
+        let mut f = fixture();
+        assert_eq!(token_amount(&f.ledger, &f.vault), 1_000);
+        f.advance_to(10);
+        assert_eq!(f.withdraw(), error(TransactionResult::NothingToClaim));
+        f.advance_to(20);
+        assert_eq!(f.withdraw(), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.destination), 70);
+        assert_eq!(f.withdraw(), error(TransactionResult::NothingToClaim));
+        f.advance_to(500);
+        assert_eq!(f.withdraw(), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.destination), token_amount(&f.ledger, &f.vault)), (1_000, 0));
+    }
+
+    #[test]
+    fn test_cancel_splits_remaining() {
+        let mut f = fixture();
+        f.advance_to(20);
+        assert_eq!(f.withdraw(), Ok(()));
+        f.advance_to(50);
+        assert_eq!(f.cancel(false), error(TransactionResult::MissingRequiredSignature));
+        // 已累计280，接收者取过70，再得到210；剩下的720退回发送者
+        assert_eq!(f.cancel(true), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.destination), token_amount(&f.ledger, &f.source)), (280, 720));
+        assert_eq!(token_amount(&f.ledger, &f.vault), 0);
+        assert_eq!(f.cancel(true), error(TransactionResult::StreamCanceled));
+        f.advance_to(100);
+        assert_eq!(f.withdraw(), error(TransactionResult::NothingToClaim));
+    }
+
+    #[test]
+    fn test_cancel_after_fully_streamed() {
+        let mut f = fixture();
+        f.advance_to(200);
+        assert_eq!(f.cancel(true), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.destination), token_amount(&f.ledger, &f.source)), (1_000, 0));
+    }
+}
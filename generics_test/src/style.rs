@@ -0,0 +1,68 @@
+// 输出样式 - 统一管理✓/✗等符号，支持纯ASCII模式
+// 使用--ascii参数，或者终端编码不是UTF-8时，自动换成ASCII符号
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+// 根据命令行参数和locale环境变量决定是否使用ASCII
+pub fn init(args: &[String]) {
+    let ascii = args.iter().any(|arg| arg == "--ascii") || !locale_is_utf8(&locale_vars());
+    set_ascii(ascii);
+}
+
+pub fn set_ascii(ascii: bool) {
+    ASCII.store(ascii, Ordering::Relaxed);
+}
+
+pub fn is_ascii() -> bool {
+    ASCII.load(Ordering::Relaxed)
+}
+
+pub fn ok() -> &'static str {
+    if is_ascii() { "[OK]" } else { "✓" }
+}
+
+pub fn fail() -> &'static str {
+    if is_ascii() { "[FAIL]" } else { "✗" }
+}
+
+// 按优先级读取LC_ALL、LC_CTYPE、LANG
+fn locale_vars() -> Vec<Option<String>> {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .map(|name| std::env::var(name).ok())
+        .collect()
+}
+
+// 与C库相同的规则：第一个非空的变量生效；都没有设置时是"C" locale
+fn locale_is_utf8(vars: &[Option<String>]) -> bool {
+    match vars.iter().flatten().find(|value| !value.is_empty()) {
+        Some(value) => {
+            let value = value.to_ascii_lowercase();
+            value.contains("utf-8") || value.contains("utf8")
+        }
+        // Windows终端没有这些变量，默认按UTF-8处理
+        None => cfg!(windows),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(values: &[Option<&str>]) -> Vec<Option<String>> {
+        values.iter().map(|v| v.map(String::from)).collect()
+    }
+
+    #[test]
+    fn test_locale_detection() {
+        assert!(locale_is_utf8(&vars(&[None, None, Some("zh_CN.UTF-8")])));
+        assert!(locale_is_utf8(&vars(&[Some("en_US.utf8"), None, None])));
+        // LC_ALL优先于LANG
+        assert!(!locale_is_utf8(&vars(&[Some("C"), None, Some("en_US.UTF-8")])));
+        // 空值会被跳过
+        assert!(locale_is_utf8(&vars(&[Some(""), Some("C.UTF-8"), None])));
+        assert!(!locale_is_utf8(&vars(&[None, None, Some("POSIX")])));
+    }
+}
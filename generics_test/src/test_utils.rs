@@ -0,0 +1,72 @@
+// 各个程序的测试共用的辅助函数：准备账户、读取Token余额、签名并执行交易。
+// 每个程序的Fixture只保留自己特有的账户和指令
+
+use crate::cpi::Programs;
+use crate::ledger::{Account, AccountPayload, Ledger};
+use crate::mint::Mint;
+use crate::signature::{Keypair, Signer};
+use crate::transaction::{Instruction, Transaction, TransactionError};
+use crate::{Pubkey, TokenAccount, SYSTEM_PROGRAM_ID};
+
+// 创建一个有足够lamports支付多笔交易费的系统账户
+pub fn fund(ledger: &mut Ledger, key: Pubkey) {
+    let lamports = ledger.rent().minimum_balance(0) * 100;
+    ledger.create_account(key, Account::new(lamports, SYSTEM_PROGRAM_ID, AccountPayload::Empty)).unwrap();
+}
+
+pub fn funded_keypair(ledger: &mut Ledger) -> Keypair {
+    let keypair = Keypair::new();
+    fund(ledger, keypair.pubkey());
+    keypair
+}
+
+// 在token_program下创建一个免租的mint账户，返回它的地址
+pub fn create_mint(ledger: &mut Ledger, token_program: Pubkey, mint: Mint) -> Pubkey {
+    let key = Pubkey::new_unique();
+    let lamports = ledger.rent().minimum_balance(Mint::LEN);
+    ledger.create_account(key, Account::new(lamports, token_program, AccountPayload::Mint(mint))).unwrap();
+    key
+}
+
+// 在key处创建一个免租的Token账户并返回key。
+// 由调用方给出地址，因为vault往往要先确定地址，再用它推导作为owner的PDA
+pub fn create_token_account(ledger: &mut Ledger, token_program: Pubkey, key: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) -> Pubkey {
+    let lamports = ledger.rent().minimum_balance(TokenAccount::LEN);
+    let data = AccountPayload::Token(TokenAccount::new(mint, owner, amount));
+    ledger.create_account(key, Account::new(lamports, token_program, data)).unwrap();
+    key
+}
+
+pub fn token_amount(ledger: &Ledger, key: &Pubkey) -> u64 {
+    match ledger.get_account(key).map(|account| &account.data) {
+        Some(AccountPayload::Token(token)) => token.amount,
+        _ => panic!("不是Token账户"),
+    }
+}
+
+// 由payer支付并签名，用最新的blockhash执行。执行之后产生新的blockhash，
+// 这样重试相同的指令时签名不同，不会被当成重放
+pub fn sign_and_execute(
+    ledger: &mut Ledger,
+    programs: &Programs,
+    payer: &Keypair,
+    instructions: Vec<Instruction>,
+) -> Result<(), TransactionError> {
+    let mut tx = Transaction::new(payer.pubkey(), instructions).with_recent_blockhash(ledger.latest_blockhash());
+    tx.sign(&[payer]).unwrap();
+    let result = tx.execute_with_programs(ledger, programs).result;
+    ledger.tick(0);
+    result
+}
+
+// xorshift64伪随机数，保证测试可重复
+pub struct XorShift(pub u64);
+
+impl XorShift {
+    pub fn next(&mut self, bound: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % bound
+    }
+}
@@ -0,0 +1,2047 @@
+// 原子交易 - 把多条指令打包执行：任意一条失败，之前所有指令的修改都会回滚
+
+use std::fmt;
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::compute::{ComputeBudget, DEFAULT_COMPUTE_UNIT_LIMIT};
+use crate::cpi::{InvokeContext, Programs};
+use crate::events::TokenEvent;
+use crate::hash::Hash;
+use crate::ledger::{AccountPayload, Ledger, LedgerError, StagedLedger, TransactionStatus};
+use crate::logs::Logs;
+use crate::metadata::{metadata_address, TokenMetadata};
+use crate::safe_math;
+use crate::signature::{Signature, Signer, SignerError};
+use crate::transfer_hook::TransferHookContext;
+use crate::{
+    AccountMeta, AccountState, ProgramInstruction, ProgramProcessor, Pubkey, TokenAccount, TransactionResult,
+    SYSTEM_PROGRAM_ID,
+};
+
+// 一条完整的指令：目标程序、用到的账户以及指令内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<AccountMeta>,
+    pub data: ProgramInstruction,
+}
+
+impl Instruction {
+    pub fn new(program_id: Pubkey, data: ProgramInstruction, accounts: Vec<AccountMeta>) -> Self {
+        Self { program_id, accounts, data }
+    }
+
+    // 这条指令要求签名的账户（去重，保持出现顺序）
+    pub fn signer_keys(&self) -> Vec<Pubkey> {
+        let mut keys = Vec::new();
+        for meta in self.accounts.iter().filter(|meta| meta.is_signer) {
+            if !keys.contains(&meta.pubkey) {
+                keys.push(meta.pubkey);
+            }
+        }
+        keys
+    }
+}
+
+// 治理提案把要执行的指令整条保存在账户里
+impl BorshSerialize for Instruction {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.program_id.serialize(out);
+        self.accounts.serialize(out);
+        self.data.serialize(out);
+    }
+}
+
+impl BorshDeserialize for Instruction {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            program_id: Pubkey::deserialize(buf)?,
+            accounts: Vec::deserialize(buf)?,
+            data: ProgramInstruction::deserialize(buf)?,
+        })
+    }
+}
+
+// 交易失败的原因，类似于Solana的TransactionError
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransactionError {
+    SignatureFailure,                           // 签名缺失或校验不通过，交易不会执行
+    AccountNotFound,                            // fee payer账户不存在
+    InsufficientFundsForFee,                    // fee payer付不起交易费
+    BlockhashNotFound,                          // blockhash已过期，或nonce与账户中保存的不一致
+    AddressLookupTableNotFound,                 // V0交易引用的查找表不存在
+    InvalidAddressLookupTableIndex,             // 查找表中没有这个下标
+    AlreadyProcessed,                           // 相同签名的交易已经上链，拒绝重放
+    InstructionError(usize, TransactionResult), // 第几条指令出错（交易费照收）
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionError::SignatureFailure => write!(f, "交易签名校验失败"),
+            TransactionError::AccountNotFound => write!(f, "fee payer账户不存在"),
+            TransactionError::InsufficientFundsForFee => write!(f, "fee payer余额不足以支付交易费"),
+            TransactionError::BlockhashNotFound => write!(f, "blockhash已过期或nonce无效"),
+            TransactionError::AddressLookupTableNotFound => write!(f, "地址查找表不存在"),
+            TransactionError::InvalidAddressLookupTableIndex => write!(f, "地址查找表下标无效"),
+            TransactionError::AlreadyProcessed => write!(f, "交易已经处理过"),
+            TransactionError::InstructionError(index, result) => {
+                write!(f, "第{}条指令执行失败: {:?}", index, result)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TransactionError {}
+
+// 交易执行的完整结果：失败的交易同样会返回已经写出的日志，便于排查
+#[derive(Debug)]
+pub struct TransactionOutcome {
+    pub result: Result<(), TransactionError>,
+    pub logs: Logs,
+    pub compute_units_consumed: u64,
+}
+
+#[derive(Debug)]
+pub struct Transaction {
+    pub fee_payer: Pubkey,
+    pub instructions: Vec<Instruction>,
+    pub signatures: Vec<Signature>, // 与signer_keys()按位置一一对应
+    pub compute_unit_limit: u64,     // 整笔交易可用的compute units
+    pub recent_blockhash: Hash,      // 最近的blockhash；使用持久化nonce时是nonce账户中的值
+}
+
+impl Transaction {
+    pub fn new(fee_payer: Pubkey, instructions: Vec<Instruction>) -> Self {
+        Self {
+            fee_payer,
+            instructions,
+            signatures: Vec::new(),
+            compute_unit_limit: DEFAULT_COMPUTE_UNIT_LIMIT,
+            recent_blockhash: Hash::default(),
+        }
+    }
+
+    // 类似于ComputeBudgetInstruction::set_compute_unit_limit，需要在签名前设置
+    pub fn with_compute_unit_limit(mut self, limit: u64) -> Self {
+        self.compute_unit_limit = limit;
+        self
+    }
+
+    // 和compute unit上限一样，需要在签名前设置
+    pub fn with_recent_blockhash(mut self, blockhash: Hash) -> Self {
+        self.recent_blockhash = blockhash;
+        self
+    }
+
+    // 第一条指令是AdvanceNonce时，交易使用持久化nonce，返回nonce账户
+    pub fn nonce_account(&self) -> Option<Pubkey> {
+        match self.instructions.first() {
+            Some(Instruction { data: ProgramInstruction::AdvanceNonce, accounts, .. }) => {
+                accounts.first().map(|meta| meta.pubkey)
+            }
+            _ => None,
+        }
+    }
+
+    // 需要签名的账户：fee payer排第一，其余按各指令中出现的顺序去重
+    pub fn signer_keys(&self) -> Vec<Pubkey> {
+        let mut keys = vec![self.fee_payer];
+        for key in self.instructions.iter().flat_map(Instruction::signer_keys) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    pub fn num_required_signatures(&self) -> usize {
+        self.signer_keys().len()
+    }
+
+    // 交易涉及的所有地址：fee payer、各指令的程序和账户，去重后保持出现顺序
+    pub fn account_keys(&self) -> Vec<Pubkey> {
+        let mut keys = vec![self.fee_payer];
+        for instruction in &self.instructions {
+            let used = std::iter::once(instruction.program_id).chain(instruction.accounts.iter().map(|meta| meta.pubkey));
+            for key in used {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        keys
+    }
+
+    // 被签名的消息：签名者数量 + fee payer + compute unit上限 + recent blockhash + 每条指令的程序、账户和指令数据。
+    // 和Solana的legacy消息一样，第一个字节是签名者数量（小于0x80），以此和带版本前缀的消息区分
+    pub fn message_data(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        (self.num_required_signatures() as u8).serialize(&mut out);
+        self.fee_payer.serialize(&mut out);
+        self.compute_unit_limit.serialize(&mut out);
+        out.extend_from_slice(&self.recent_blockhash);
+        (self.instructions.len() as u32).serialize(&mut out);
+        for instruction in &self.instructions {
+            instruction.program_id.serialize(&mut out);
+            (instruction.accounts.len() as u32).serialize(&mut out);
+            for meta in &instruction.accounts {
+                meta.pubkey.serialize(&mut out);
+                meta.is_signer.serialize(&mut out);
+                meta.is_writable.serialize(&mut out);
+            }
+            instruction.data.to_bytes().serialize(&mut out);
+        }
+        out
+    }
+
+    // 提供的signer必须和signer_keys()完全一致：不能缺少、重复或多余
+    pub fn sign(&mut self, signers: &[&dyn Signer]) -> Result<(), SignerError> {
+        let message = self.message_data();
+        self.sign_message(signers, &message)
+    }
+
+    // 对指定的消息签名；带版本的交易签的是加了版本前缀的消息
+    pub(crate) fn sign_message(&mut self, signers: &[&dyn Signer], message: &[u8]) -> Result<(), SignerError> {
+        let keys = self.signer_keys();
+        let mut provided: Vec<Pubkey> = Vec::with_capacity(signers.len());
+        for signer in signers {
+            let key = signer.pubkey();
+            if provided.contains(&key) {
+                return Err(SignerError::DuplicateSigner(key));
+            }
+            if !keys.contains(&key) {
+                return Err(SignerError::UnexpectedSigner(key));
+            }
+            provided.push(key);
+        }
+        if let Some(missing) = keys.iter().find(|key| !provided.contains(key)) {
+            return Err(SignerError::MissingSigner(*missing));
+        }
+
+        self.signatures = keys
+            .iter()
+            .map(|key| {
+                let signer = signers.iter().find(|signer| signer.pubkey() == *key).unwrap();
+                signer.sign_message(message)
+            })
+            .collect();
+        Ok(())
+    }
+
+    // 多方签名：每一方拿到同一份未签名的交易，各自只签自己的那一份，最后再用merge_signatures合并。
+    // 还没签的位置先用全零签名占位，保证签名和signer_keys()按位置对应
+    pub fn partial_sign(&mut self, signer: &dyn Signer) -> Result<(), SignerError> {
+        let keys = self.signer_keys();
+        let key = signer.pubkey();
+        let position = keys.iter().position(|k| *k == key).ok_or(SignerError::UnexpectedSigner(key))?;
+        self.signatures.resize(keys.len(), Signature::default());
+        self.signatures[position] = signer.sign_message(&self.message_data());
+        Ok(())
+    }
+
+    // 还没有提供有效签名的签名者
+    pub fn missing_signers(&self) -> Vec<Pubkey> {
+        let message = self.message_data();
+        self.signer_keys()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, key)| !self.signatures.get(*i).is_some_and(|signature| signature.verify(key, &message)))
+            .map(|(_, key)| key)
+            .collect()
+    }
+
+    // 把另一份副本上的有效签名合并进来；两份交易的消息必须完全相同
+    pub fn merge_signatures(&mut self, other: &Transaction) -> Result<(), SignerError> {
+        let message = self.message_data();
+        if other.message_data() != message {
+            return Err(SignerError::MessageMismatch);
+        }
+        let keys = self.signer_keys();
+        self.signatures.resize(keys.len(), Signature::default());
+        for (i, key) in keys.iter().enumerate() {
+            if let Some(signature) = other.signatures.get(i).filter(|signature| signature.verify(key, &message)) {
+                self.signatures[i] = *signature;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn verify_signatures(&self) -> Result<(), TransactionError> {
+        self.verify_message(&self.message_data())
+    }
+
+    pub(crate) fn verify_message(&self, message: &[u8]) -> Result<(), TransactionError> {
+        let keys = self.signer_keys();
+        if self.signatures.len() != keys.len() {
+            return Err(TransactionError::SignatureFailure);
+        }
+        if keys
+            .iter()
+            .zip(&self.signatures)
+            .all(|(key, signature)| signature.verify(key, message))
+        {
+            Ok(())
+        } else {
+            Err(TransactionError::SignatureFailure)
+        }
+    }
+
+    pub fn fee(&self, ledger: &Ledger) -> u64 {
+        ledger.fee_schedule().fee(self.num_required_signatures())
+    }
+
+    // 先校验签名，再向fee payer收取交易费（之后指令失败也不退还）；
+    // 所有指令都在同一个暂存区上执行，全部成功才提交
+    pub fn execute(&self, ledger: &mut Ledger) -> Result<(), TransactionError> {
+        self.execute_with_programs(ledger, &Programs::new()).result
+    }
+
+    // 和execute相同，但指令（以及其中的CPI）可以调用programs里注册的程序，
+    // 并且连同执行日志和消耗的compute units一起返回
+    pub fn execute_with_programs(&self, ledger: &mut Ledger, programs: &Programs) -> TransactionOutcome {
+        self.execute_message(ledger, programs, &self.message_data())
+    }
+
+    // 签名按message校验，其余执行流程与消息格式无关
+    pub(crate) fn execute_message(&self, ledger: &mut Ledger, programs: &Programs, message: &[u8]) -> TransactionOutcome {
+        self.execute_inner(ledger, programs, Some(message))
+    }
+
+    // 跳过签名校验直接执行，只用于在账本副本上试运行
+    pub(crate) fn execute_unverified(&self, ledger: &mut Ledger, programs: &Programs) -> TransactionOutcome {
+        self.execute_inner(ledger, programs, None)
+    }
+
+    fn execute_inner(&self, ledger: &mut Ledger, programs: &Programs, message: Option<&[u8]>) -> TransactionOutcome {
+        let mut budget = ComputeBudget::new(self.compute_unit_limit);
+        let result = self.run(ledger, programs, &mut budget, message);
+        let logs = budget.take_logs();
+        // 收了交易费的交易（成功或指令失败）才算上链，留下执行记录；试运行不记录
+        if message.is_some() && matches!(result, Ok(()) | Err(TransactionError::InstructionError(..))) {
+            let status = TransactionStatus {
+                signature: self.signatures[0],
+                slot: ledger.slot(),
+                result: result.clone(),
+                logs: logs.clone(),
+            };
+            ledger.record_transaction(status, &self.account_keys());
+        }
+        TransactionOutcome {
+            result,
+            compute_units_consumed: budget.consumed(),
+            logs,
+        }
+    }
+
+    fn run(&self, ledger: &mut Ledger, programs: &Programs, budget: &mut ComputeBudget, message: Option<&[u8]>) -> Result<(), TransactionError> {
+        if let Some(message) = message {
+            self.verify_message(message)?;
+            // 在收取交易费之前拦下重放的交易，账本中保留第一次执行的状态
+            if ledger.get_transaction(&self.signatures[0]).is_some() {
+                return Err(TransactionError::AlreadyProcessed);
+            }
+        }
+        self.check_lifetime(ledger)?;
+        ledger
+            .charge_fee(&self.fee_payer, self.fee(ledger))
+            .map_err(|e| match e {
+                LedgerError::AccountNotFound(_) => TransactionError::AccountNotFound,
+                _ => TransactionError::InsufficientFundsForFee,
+            })?;
+
+        let mut staged = ledger.stage();
+        let mut ctx = InvokeContext::new(&mut staged, budget, programs);
+        let result = self.instructions.iter().enumerate().try_for_each(|(index, instruction)| {
+            ctx.process_instruction(instruction)
+                .map_err(|result| TransactionError::InstructionError(index, result))
+        });
+        match result {
+            Ok(()) => staged.commit(self.fee_payer),
+            // staged被丢弃，之前的修改不会写入账本。但nonce照样推进（和交易费一样不退），
+            // 否则这笔失败的交易可以被重放
+            Err(TransactionError::InstructionError(index, _)) if index > 0 && self.nonce_account().is_some() => {
+                drop(staged);
+                let mut staged = ledger.stage();
+                if apply_instruction(&mut staged, &self.instructions[0]).is_ok() {
+                    staged.commit(self.fee_payer);
+                }
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    // 检查交易的有效期：普通交易的blockhash必须还在最近的blockhash中，
+    // 使用持久化nonce的交易必须和nonce账户当前保存的值一致
+    fn check_lifetime(&self, ledger: &Ledger) -> Result<(), TransactionError> {
+        let valid = match self.nonce_account() {
+            Some(key) => matches!(
+                ledger.get_account(&key).map(|account| &account.data),
+                Some(AccountPayload::Nonce(nonce)) if nonce.nonce == self.recent_blockhash
+            ),
+            None => ledger.is_blockhash_valid(&self.recent_blockhash),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(TransactionError::BlockhashNotFound)
+        }
+    }
+}
+
+// 内置的Token处理逻辑，没有注册入口函数的程序都由它执行
+pub(crate) fn apply_instruction(staged: &mut StagedLedger<'_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+    // 用账本中账户真实的owner做校验，而不是相信调用方填写的owner；
+    // 和Solana一样，不存在的账户视为属于System Program的空账户
+    let metas: Vec<AccountMeta> = instruction
+        .accounts
+        .iter()
+        .map(|meta| {
+            let owner = staged.get_account(&meta.pubkey).map_or(SYSTEM_PROGRAM_ID, |account| account.owner);
+            AccountMeta { owner, ..*meta }
+        })
+        .collect();
+    // 关闭账户时接收lamports的账户只增加余额，和Solana一样不要求它归本程序所有
+    let mut checked = metas.clone();
+    if let (ProgramInstruction::CloseTokenAccount, Some(destination)) = (&instruction.data, checked.get_mut(1)) {
+        destination.owner = instruction.program_id;
+    }
+    let validation = ProgramProcessor::validate_accounts(&instruction.program_id, &checked);
+    if validation != TransactionResult::Success {
+        return Err(validation);
+    }
+
+    let target = metas[0].pubkey;
+    match instruction.data {
+        // 初始余额也是铸造出来的：和MintTo一样需要mint和mint authority签名，计入供应量并检查上限
+        ProgramInstruction::Initialize { initial_supply } => {
+            let (_, _, amount) = token_state(staged, &target)?;
+            if amount != 0 {
+                return Err(TransactionResult::InvalidAccount); // 已经初始化过
+            }
+            mint_to(staged, &metas, initial_supply)
+        }
+        ProgramInstruction::Mint { amount } => mint_to(staged, &metas, amount),
+        ProgramInstruction::Transfer { amount } => transfer(staged, &metas, amount),
+        ProgramInstruction::AdvanceNonce => advance_nonce(staged, &metas),
+        ProgramInstruction::Approve { amount } => approve(staged, &metas, amount),
+        ProgramInstruction::Revoke => revoke(staged, &metas),
+        ProgramInstruction::Burn { amount } => burn(staged, &metas, amount),
+        ProgramInstruction::FreezeAccount => set_frozen(staged, &metas, true),
+        ProgramInstruction::ThawAccount => set_frozen(staged, &metas, false),
+        ProgramInstruction::CreateMetadata { ref name, ref symbol, ref uri } => {
+            create_metadata(staged, &instruction.program_id, &metas, (name, symbol, uri))
+        }
+        ProgramInstruction::UpdateMetadata { ref name, ref symbol, ref uri } => {
+            update_metadata(staged, &metas, (name, symbol, uri))
+        }
+        ProgramInstruction::CloseTokenAccount => close_token_account(staged, &metas),
+        ProgramInstruction::SetMetadataAuthority { new_authority } => set_metadata_authority(staged, &metas, new_authority),
+        // 托管、锁仓、AMM、治理、DEX、预言机、借贷、众筹、拍卖和支付流指令由注册的程序处理，内置的Token程序不认识它们
+        ProgramInstruction::InitializeEscrow { .. }
+        | ProgramInstruction::Exchange { .. }
+        | ProgramInstruction::CancelEscrow
+        | ProgramInstruction::CreateVesting { .. }
+        | ProgramInstruction::Claim
+        | ProgramInstruction::InitializePool { .. }
+        | ProgramInstruction::AddLiquidity { .. }
+        | ProgramInstruction::RemoveLiquidity { .. }
+        | ProgramInstruction::Swap { .. }
+        | ProgramInstruction::CreateGovernance { .. }
+        | ProgramInstruction::CreateProposal { .. }
+        | ProgramInstruction::CastVote { .. }
+        | ProgramInstruction::FinalizeProposal
+        | ProgramInstruction::CreateMarket
+        | ProgramInstruction::PlaceOrder { .. }
+        | ProgramInstruction::CancelOrder
+        | ProgramInstruction::SettleFunds
+        | ProgramInstruction::UpdatePrice { .. }
+        | ProgramInstruction::InitLendingMarket { .. }
+        | ProgramInstruction::DepositCollateral { .. }
+        | ProgramInstruction::Borrow { .. }
+        | ProgramInstruction::Repay { .. }
+        | ProgramInstruction::Liquidate { .. }
+        | ProgramInstruction::CreateCampaign { .. }
+        | ProgramInstruction::Contribute { .. }
+        | ProgramInstruction::Withdraw
+        | ProgramInstruction::Refund
+        | ProgramInstruction::CreateAuction { .. }
+        | ProgramInstruction::PlaceBid { .. }
+        | ProgramInstruction::SettleAuction
+        | ProgramInstruction::CreateStream { .. }
+        | ProgramInstruction::CancelStream => Err(TransactionResult::InvalidInstructionData),
+    }
+}
+
+fn is_signed(metas: &[AccountMeta], key: &Pubkey) -> bool {
+    metas.iter().any(|meta| meta.pubkey == *key && meta.is_signer)
+}
+
+fn token_account(staged: &StagedLedger<'_>, key: &Pubkey) -> Result<TokenAccount, TransactionResult> {
+    match staged.get_account(key).map(|account| &account.data) {
+        Some(AccountPayload::Token(token)) => Ok(token.clone()),
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+fn set_token_account(staged: &mut StagedLedger<'_>, key: &Pubkey, token: TokenAccount) -> Result<(), TransactionResult> {
+    let mut account = staged.get_account(key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    account.data = AccountPayload::Token(token);
+    Ok(staged.set_account(*key, account)?)
+}
+
+// 返回Token账户的(mint, owner, amount)
+fn token_state(staged: &StagedLedger<'_>, key: &Pubkey) -> Result<(Pubkey, Pubkey, u64), TransactionResult> {
+    match staged.get_account(key).map(|account| &account.data) {
+        Some(AccountPayload::Token(token)) => Ok((token.mint, token.owner, token.amount)),
+        _ => Err(TransactionResult::InvalidAccount),
+    }
+}
+
+fn set_token_amount(staged: &mut StagedLedger<'_>, key: &Pubkey, amount: u64) -> Result<(), TransactionResult> {
+    let mut account = staged.get_account(key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    match &mut account.data {
+        AccountPayload::Token(token) => token.amount = amount,
+        _ => return Err(TransactionResult::InvalidAccount),
+    }
+    Ok(staged.set_account(*key, account)?)
+}
+
+// accounts[0]是接收的Token账户，accounts[1]是它的mint（可写，要增加supply，且不能超过max_supply），
+// accounts[2]必须是mint authority本身并且已经签名
+fn mint_to(staged: &mut StagedLedger<'_>, metas: &[AccountMeta], amount: u64) -> Result<(), TransactionResult> {
+    let (destination, mint_key, authority) = match metas {
+        [destination, mint, authority, ..] if mint.is_writable => (destination.pubkey, mint.pubkey, authority),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    let token = token_account(staged, &destination)?;
+    if token.mint != mint_key {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if token.is_frozen() {
+        return Err(TransactionResult::AccountFrozen);
+    }
+    let mut mint_account = staged.get_account(&mint_key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    let AccountPayload::Mint(mint) = &mut mint_account.data else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    // 供应量已固定（没有mint authority）时任何账户都不匹配
+    if mint.mint_authority != Some(authority.pubkey) {
+        return Err(TransactionResult::MintAuthorityMismatch);
+    }
+    if !authority.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+
+    let supply = safe_math::checked_add(mint.supply, amount)?;
+    if !mint.within_cap(supply) {
+        return Err(TransactionResult::SupplyCapExceeded);
+    }
+    mint.supply = supply;
+    let credited = safe_math::checked_add(token.amount, amount)?;
+    staged.set_account(mint_key, mint_account)?;
+    set_token_amount(staged, &destination, credited)?;
+    staged.emit(TokenEvent::MintTo { mint: mint_key, destination, amount });
+    Ok(())
+}
+
+// accounts[0]是nonce账户，nonce的authority必须签名
+fn advance_nonce(staged: &mut StagedLedger<'_>, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let key = metas[0].pubkey;
+    let mut account = staged.get_account(&key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    let AccountPayload::Nonce(nonce) = &mut account.data else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !metas.iter().any(|meta| meta.pubkey == nonce.authority && meta.is_signer) {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    nonce.advance();
+    Ok(staged.set_account(key, account)?)
+}
+
+// accounts[0]是Token账户，accounts[1]是delegate；Token账户的owner必须签名
+fn approve(staged: &mut StagedLedger<'_>, metas: &[AccountMeta], amount: u64) -> Result<(), TransactionResult> {
+    let (source, delegate) = match metas {
+        [source, delegate, ..] => (source.pubkey, delegate.pubkey),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    let mut token = token_account(staged, &source)?;
+    if !is_signed(metas, &token.owner) {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    token.approve(delegate, amount);
+    set_token_account(staged, &source, token)
+}
+
+// accounts[0]是Token账户；只有owner可以撤销授权
+fn revoke(staged: &mut StagedLedger<'_>, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let source = metas[0].pubkey;
+    let mut token = token_account(staged, &source)?;
+    if !is_signed(metas, &token.owner) {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    token.revoke();
+    set_token_account(staged, &source, token)
+}
+
+// accounts[0]是要关闭的Token账户，accounts[1]接收退还的lamports；只有owner可以关闭
+fn close_token_account(staged: &mut StagedLedger<'_>, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let (key, destination_key) = match metas {
+        [token, destination, ..] if destination.is_writable => (token.pubkey, destination.pubkey),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    if key == destination_key {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let token = token_account(staged, &key)?;
+    if !is_signed(metas, &token.owner) {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if token.is_frozen() {
+        return Err(TransactionResult::AccountFrozen);
+    }
+    if token.amount != 0 {
+        return Err(TransactionResult::NonZeroBalance);
+    }
+
+    let mut destination = staged.get_account(&destination_key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    let closed = staged.close_account(key)?;
+    destination.lamports = safe_math::checked_add(destination.lamports, closed.lamports)?;
+    Ok(staged.set_account(destination_key, destination)?)
+}
+
+// 元数据指令里的(name, symbol, uri)
+type MetadataFields<'a> = (&'a str, &'a str, &'a str);
+
+// accounts[0]是由mint推导出的元数据PDA（已分配、尚未写入数据），accounts[1]是mint；mint authority必须签名
+fn create_metadata(
+    staged: &mut StagedLedger<'_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    (name, symbol, uri): MetadataFields<'_>,
+) -> Result<(), TransactionResult> {
+    let (key, mint_key) = match metas {
+        [metadata, mint, ..] => (metadata.pubkey, mint.pubkey),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    if key != metadata_address(&mint_key, program_id) {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let mint_authority = match staged.get_account(&mint_key).map(|account| &account.data) {
+        Some(AccountPayload::Mint(mint)) => mint.mint_authority,
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    let Some(authority) = mint_authority.filter(|authority| is_signed(metas, authority)) else {
+        return Err(TransactionResult::MissingRequiredSignature);
+    };
+
+    let mut account = staged.get_account(&key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    // 只能写入空账户，不能覆盖已有的元数据
+    if account.data != AccountPayload::Empty {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if account.lamports < staged.rent().minimum_balance(TokenMetadata::LEN) {
+        return Err(TransactionResult::InsufficientFunds);
+    }
+    let metadata = TokenMetadata::new(mint_key, authority, name, symbol, uri)
+        .map_err(|_| TransactionResult::InvalidInstructionData)?;
+    account.data = AccountPayload::Metadata(metadata);
+    Ok(staged.set_account(key, account)?)
+}
+
+// accounts[0]是元数据账户；update authority必须签名
+fn update_metadata(
+    staged: &mut StagedLedger<'_>,
+    metas: &[AccountMeta],
+    (name, symbol, uri): MetadataFields<'_>,
+) -> Result<(), TransactionResult> {
+    let key = metas[0].pubkey;
+    let mut account = staged.get_account(&key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    let AccountPayload::Metadata(metadata) = &mut account.data else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !is_signed(metas, &metadata.update_authority) {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    metadata.update(name, symbol, uri).map_err(|_| TransactionResult::InvalidInstructionData)?;
+    Ok(staged.set_account(key, account)?)
+}
+
+// accounts[0]是元数据账户；当前update authority必须签名，移交之后原authority不能再修改
+fn set_metadata_authority(staged: &mut StagedLedger<'_>, metas: &[AccountMeta], new_authority: Pubkey) -> Result<(), TransactionResult> {
+    let key = metas[0].pubkey;
+    let mut account = staged.get_account(&key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    let AccountPayload::Metadata(metadata) = &mut account.data else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !is_signed(metas, &metadata.update_authority) {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    metadata.update_authority = new_authority;
+    Ok(staged.set_account(key, account)?)
+}
+
+// accounts[0]是Token账户，accounts[1]是它的mint；mint的freeze authority必须签名
+fn set_frozen(staged: &mut StagedLedger<'_>, metas: &[AccountMeta], frozen: bool) -> Result<(), TransactionResult> {
+    let (key, mint_key) = match metas {
+        [token, mint, ..] => (token.pubkey, mint.pubkey),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    let mut token = token_account(staged, &key)?;
+    if token.mint != mint_key {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let freeze_authority = match staged.get_account(&mint_key).map(|account| &account.data) {
+        Some(AccountPayload::Mint(mint)) => mint.freeze_authority,
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    // 没有freeze authority的mint永远不能冻结账户
+    if !freeze_authority.is_some_and(|authority| is_signed(metas, &authority)) {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    // 重复冻结或解冻未冻结的账户都是无效操作
+    if token.is_frozen() == frozen {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    token.state = if frozen { AccountState::Frozen } else { AccountState::Initialized };
+    set_token_account(staged, &key, token)
+}
+
+// 从source转出或销毁amount需要owner或delegate签名
+fn authorize_debit(source: &mut TokenAccount, metas: &[AccountMeta], amount: u64) -> Result<(), TransactionResult> {
+    if source.is_frozen() {
+        return Err(TransactionResult::AccountFrozen);
+    }
+    if is_signed(metas, &source.owner) {
+        // owner签名时不消耗授权额度
+    } else if source.delegate.is_some_and(|delegate| is_signed(metas, &delegate)) {
+        // delegate只能在授权额度内转出（超出时下溢，返回InsufficientFunds），额度用完后授权失效
+        source.delegated_amount = safe_math::checked_sub(source.delegated_amount, amount)?;
+        if source.delegated_amount == 0 {
+            source.delegate = None;
+        }
+    } else {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    if source.amount < amount {
+        return Err(TransactionResult::InsufficientFunds);
+    }
+    Ok(())
+}
+
+// accounts[0]是Token账户，accounts[1]是它的mint（可写，要减少supply）；owner或delegate必须签名
+fn burn(staged: &mut StagedLedger<'_>, metas: &[AccountMeta], amount: u64) -> Result<(), TransactionResult> {
+    let (source_key, mint_key) = match metas {
+        [source, mint, ..] if mint.is_writable => (source.pubkey, mint.pubkey),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    let mut source = token_account(staged, &source_key)?;
+    if source.mint != mint_key {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let mut mint_account = staged.get_account(&mint_key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    let AccountPayload::Mint(mint) = &mut mint_account.data else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    authorize_debit(&mut source, metas, amount)?;
+
+    mint.supply = safe_math::checked_sub(mint.supply, amount).map_err(|_| TransactionResult::ArithmeticOverflow)?;
+    source.amount = safe_math::checked_sub(source.amount, amount)?;
+    staged.set_account(mint_key, mint_account)?;
+    set_token_account(staged, &source_key, source)?;
+    staged.emit(TokenEvent::Burn { mint: mint_key, source: source_key, amount });
+    Ok(())
+}
+
+// mint开启了转账手续费时返回(fee_vault, 手续费)。
+// 简化：直接从账本读取mint，但fee vault要被修改，必须作为可写账户出现在指令中
+fn withheld_fee(
+    staged: &StagedLedger<'_>,
+    mint: &Pubkey,
+    metas: &[AccountMeta],
+    amount: u64,
+) -> Result<Option<(Pubkey, u64)>, TransactionResult> {
+    let config = match staged.get_account(mint).map(|account| &account.data) {
+        Some(AccountPayload::Mint(mint)) => mint.transfer_fee,
+        _ => None,
+    };
+    let Some(config) = config else {
+        return Ok(None);
+    };
+    check_vault(staged, mint, metas, &config.fee_vault)?;
+    Ok(Some((config.fee_vault, config.calculate_fee(amount))))
+}
+
+// 收取手续费或抽成的vault必须是同一mint的Token账户，并且作为可写账户出现在指令中
+fn check_vault(staged: &StagedLedger<'_>, mint: &Pubkey, metas: &[AccountMeta], vault: &Pubkey) -> Result<(), TransactionResult> {
+    if !metas.iter().any(|meta| meta.pubkey == *vault && meta.is_writable) {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if token_account(staged, vault)?.mint != *mint {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    Ok(())
+}
+
+// 调用mint上注册的转账钩子，返回需要存进各个vault的抽成
+fn run_transfer_hooks(
+    staged: &StagedLedger<'_>,
+    metas: &[AccountMeta],
+    transfer: &TransferHookContext,
+) -> Result<Vec<(Pubkey, u64)>, TransactionResult> {
+    let skims = staged.transfer_hooks().run(transfer).map_err(|_| TransactionResult::TransferHookRejected)?;
+    for (vault, _) in &skims {
+        check_vault(staged, &transfer.mint, metas, vault)?;
+    }
+    Ok(skims)
+}
+
+// accounts[0]是转出账户，accounts[1]是转入账户；转出账户的owner或delegate必须签名。
+// mint有转账手续费时还要传入可写的fee vault，收款方收到扣除手续费后的数量。
+// mint上注册的转账钩子在写入余额之前执行，钩子的抽成vault同样要作为可写账户传入
+fn transfer(staged: &mut StagedLedger<'_>, metas: &[AccountMeta], amount: u64) -> Result<(), TransactionResult> {
+    let (from, to) = match metas {
+        [from, to, ..] if to.is_writable => (from.pubkey, to.pubkey),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    let mut source = token_account(staged, &from)?;
+    let destination = token_account(staged, &to)?;
+    if source.mint != destination.mint {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if destination.is_frozen() {
+        return Err(TransactionResult::AccountFrozen);
+    }
+    authorize_debit(&mut source, metas, amount)?;
+    // 转给自己不改变余额，也不收手续费，但delegate的额度照样扣除
+    if from == to {
+        let mint = source.mint;
+        set_token_account(staged, &from, source)?;
+        staged.emit(TokenEvent::Transfer { mint, source: from, destination: to, amount, fee: 0 });
+        return Ok(());
+    }
+    let hook_context = TransferHookContext {
+        mint: source.mint,
+        source: from,
+        destination: to,
+        source_owner: source.owner,
+        destination_owner: destination.owner,
+        amount,
+    };
+    let skims = run_transfer_hooks(staged, metas, &hook_context)?;
+    let withheld = withheld_fee(staged, &source.mint, metas, amount)?;
+    // 手续费和抽成都从收款方应得的数量中扣除，加起来不能超过转账金额
+    let deductions: Vec<(Pubkey, u64)> = withheld.into_iter().chain(skims).collect();
+    let deducted = deductions
+        .iter()
+        .try_fold(0u64, |total, (_, fee)| total.checked_add(*fee))
+        .filter(|total| *total <= amount)
+        .ok_or(TransactionResult::InsufficientFunds)?;
+
+    let received = safe_math::checked_sub(amount, deducted)?;
+    let credited = safe_math::checked_add(destination.amount, received)?;
+    let mint = source.mint;
+    source.amount = safe_math::checked_sub(source.amount, amount)?;
+    set_token_account(staged, &from, source)?;
+    set_token_amount(staged, &to, credited)?;
+    // vault可能就是收款方（或者多个钩子共用一个vault），所以每次都重新读取余额
+    for (vault, fee) in deductions {
+        let (_, _, vault_amount) = token_state(staged, &vault)?;
+        let vault_amount = safe_math::checked_add(vault_amount, fee)?;
+        set_token_amount(staged, &vault, vault_amount)?;
+    }
+    staged.emit(TokenEvent::Transfer { mint, source: from, destination: to, amount, fee: deducted });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::compute::INSTRUCTION_COST;
+    use crate::events::EventRecord;
+    use crate::ledger::{Account, AccountDiff, MAX_RECENT_BLOCKHASHES};
+    use crate::mint::{Mint, TransferFeeConfig};
+    use crate::nonce::NonceAccount;
+    use crate::signature::Keypair;
+    use crate::test_utils::{create_mint, create_token_account, fund, funded_keypair, token_amount};
+    use crate::transfer_hook::{Blocklist, FeeSkim};
+    use crate::versioned_transaction::VersionedTransaction;
+
+    struct Fixture {
+        ledger: Ledger,
+        program: Pubkey,
+        mint: Pubkey, // alice是mint authority和freeze authority
+        alice: Keypair,
+        alice_token: Pubkey,
+        bob_token: Pubkey,
+    }
+
+    fn fixture() -> Fixture {
+        let mut ledger = Ledger::default();
+        let program = Pubkey::new_unique();
+        let alice = funded_keypair(&mut ledger);
+        let mint = create_mint(&mut ledger, program, Mint::new(6, alice.pubkey()).with_freeze_authority(alice.pubkey()));
+        let mut token = |owner| create_token_account(&mut ledger, program, Pubkey::new_unique(), mint, owner, 0);
+        let alice_token = token(alice.pubkey());
+        let bob_token = token(Pubkey::new_unique());
+        Fixture { ledger, program, mint, alice, alice_token, bob_token }
+    }
+
+    fn lamports(ledger: &Ledger, key: &Pubkey) -> u64 {
+        ledger.get_account(key).map_or(0, |account| account.lamports)
+    }
+
+    fn supply(ledger: &Ledger, key: &Pubkey) -> u64 {
+        match &ledger.get_account(key).unwrap().data {
+            AccountPayload::Mint(mint) => mint.supply,
+            _ => panic!("不是Mint账户"),
+        }
+    }
+
+    impl Fixture {
+        // 由alice支付并签名的交易
+        fn transaction(&self, instructions: Vec<Instruction>) -> Transaction {
+            self.transaction_with_limit(instructions, DEFAULT_COMPUTE_UNIT_LIMIT)
+        }
+
+        fn transaction_with_limit(&self, instructions: Vec<Instruction>, limit: u64) -> Transaction {
+            let mut tx = Transaction::new(self.alice.pubkey(), instructions)
+                .with_compute_unit_limit(limit)
+                .with_recent_blockhash(self.ledger.latest_blockhash());
+            tx.sign(&[&self.alice]).unwrap();
+            tx
+        }
+
+        fn mint(&self, amount: u64) -> Instruction {
+            Instruction::new(
+                self.program,
+                ProgramInstruction::Mint { amount },
+                vec![
+                    AccountMeta::new(self.alice_token, self.program, false),
+                    AccountMeta::new(self.mint, self.program, false),
+                    AccountMeta::new_readonly(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                ],
+            )
+        }
+
+        fn transfer(&self, amount: u64, signed: bool) -> Instruction {
+            self.transfer_signed_by(amount, self.alice.pubkey(), signed)
+        }
+
+        fn transfer_signed_by(&self, amount: u64, authority: Pubkey, signed: bool) -> Instruction {
+            Instruction::new(
+                self.program,
+                ProgramInstruction::Transfer { amount },
+                vec![
+                    AccountMeta::new(self.alice_token, self.program, false),
+                    AccountMeta::new(self.bob_token, self.program, false),
+                    AccountMeta::new_readonly(authority, SYSTEM_PROGRAM_ID, signed),
+                ],
+            )
+        }
+
+        // alice授权delegate从alice_token转出
+        fn approve(&self, delegate: Pubkey, amount: u64) -> Instruction {
+            Instruction::new(
+                self.program,
+                ProgramInstruction::Approve { amount },
+                vec![
+                    AccountMeta::new(self.alice_token, self.program, false),
+                    AccountMeta::new_readonly(delegate, SYSTEM_PROGRAM_ID, false),
+                    AccountMeta::new_readonly(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                ],
+            )
+        }
+
+        fn burn(&self, amount: u64) -> Instruction {
+            Instruction::new(
+                self.program,
+                ProgramInstruction::Burn { amount },
+                vec![
+                    AccountMeta::new(self.alice_token, self.program, false),
+                    AccountMeta::new(self.mint, self.program, false),
+                    AccountMeta::new_readonly(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                ],
+            )
+        }
+
+        // alice作为freeze authority冻结或解冻bob_token
+        fn freeze(&self, data: ProgramInstruction) -> Instruction {
+            Instruction::new(
+                self.program,
+                data,
+                vec![
+                    AccountMeta::new(self.bob_token, self.program, false),
+                    AccountMeta::new_readonly(self.mint, self.program, false),
+                    AccountMeta::new_readonly(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                ],
+            )
+        }
+
+        fn revoke(&self) -> Instruction {
+            Instruction::new(
+                self.program,
+                ProgramInstruction::Revoke,
+                vec![
+                    AccountMeta::new(self.alice_token, self.program, false),
+                    AccountMeta::new_readonly(self.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+                ],
+            )
+        }
+    }
+
+    #[test]
+    fn test_partial_signing() {
+        let mut f = fixture();
+        let bob = Keypair::new();
+        fund(&mut f.ledger, bob.pubkey());
+        // bob支付交易费，alice授权转账，两人分别签名
+        let unsigned = Transaction::new(bob.pubkey(), vec![f.mint(100), f.transfer(30, true)]);
+        let bytes = VersionedTransaction::Legacy(unsigned).to_bytes();
+        let copy = |bytes: &[u8]| match VersionedTransaction::from_bytes(bytes).unwrap() {
+            VersionedTransaction::Legacy(tx) => tx,
+            VersionedTransaction::V0(_) => unreachable!(),
+        };
+
+        let mut alice_copy = copy(&bytes);
+        alice_copy.partial_sign(&f.alice).unwrap();
+        assert_eq!(alice_copy.missing_signers(), [bob.pubkey()]);
+        assert_eq!(alice_copy.verify_signatures(), Err(TransactionError::SignatureFailure));
+        let stranger = Keypair::new();
+        assert_eq!(alice_copy.partial_sign(&stranger), Err(SignerError::UnexpectedSigner(stranger.pubkey())));
+
+        // alice把签过的交易序列化后交给bob，bob补上自己的签名
+        let mut bob_copy = copy(&VersionedTransaction::Legacy(alice_copy).to_bytes());
+        bob_copy.partial_sign(&bob).unwrap();
+        assert!(bob_copy.missing_signers().is_empty());
+        assert_eq!(bob_copy.execute(&mut f.ledger), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.bob_token), 30);
+    }
+
+    #[test]
+    fn test_merge_signatures() {
+        let f = fixture();
+        let bob = Keypair::new();
+        let build = || Transaction::new(bob.pubkey(), vec![f.transfer(30, true)]);
+        let (mut alice_copy, mut bob_copy) = (build(), build());
+        alice_copy.partial_sign(&f.alice).unwrap();
+        bob_copy.partial_sign(&bob).unwrap();
+
+        let mut combined = build();
+        combined.merge_signatures(&alice_copy).unwrap();
+        assert_eq!(combined.missing_signers(), [bob.pubkey()]);
+        combined.merge_signatures(&bob_copy).unwrap();
+        assert_eq!(combined.verify_signatures(), Ok(()));
+
+        // 内容不同的交易不能合并
+        let other = Transaction::new(bob.pubkey(), vec![f.transfer(31, true)]);
+        assert_eq!(combined.merge_signatures(&other), Err(SignerError::MessageMismatch));
+    }
+
+    #[test]
+    fn test_transaction_history() {
+        let mut f = fixture();
+        let txs = [
+            f.transaction(vec![f.mint(1)]),
+            f.transaction(vec![f.mint(2)]),
+            f.transaction(vec![f.transfer(500, true)]),
+        ];
+        for tx in &txs {
+            let _ = tx.execute(&mut f.ledger);
+        }
+        let [first, second, third] = txs.map(|tx| tx.signatures[0]);
+
+        let status = f.ledger.get_transaction(&third).unwrap();
+        assert_eq!(status.slot, f.ledger.slot());
+        assert_eq!(status.result, Err(TransactionError::InstructionError(0, TransactionResult::InsufficientFunds)));
+        assert!(status.logs.contains("failed"));
+
+        // 最新的在前，用上一页最后一个签名翻页
+        let page = f.ledger.get_signatures_for_address(&f.alice_token, None, 2);
+        assert_eq!(page, [third, second]);
+        assert_eq!(f.ledger.get_signatures_for_address(&f.alice_token, Some(&second), 2), [first]);
+        assert_eq!(f.ledger.get_signatures_for_address(&f.bob_token, None, 10), [third]);
+        assert_eq!(f.ledger.get_signatures_for_address(&f.program, None, 10).len(), 3);
+
+        // 签名无效的交易没有上链，不留记录
+        let mut unsigned = Transaction::new(f.alice.pubkey(), vec![f.mint(3)]);
+        unsigned.signatures = vec![Signature::default()];
+        assert_eq!(unsigned.execute(&mut f.ledger), Err(TransactionError::SignatureFailure));
+        assert_eq!(f.ledger.get_transaction(&Signature::default()), None);
+        assert_eq!(f.ledger.get_signatures_for_address(&f.alice.pubkey(), None, 10).len(), 3);
+    }
+
+    #[test]
+    fn test_replayed_transaction_rejected() {
+        let mut f = fixture();
+        let tx = f.transaction(vec![f.mint(5)]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        let alice = f.alice.pubkey();
+        let balance = lamports(&f.ledger, &alice);
+
+        // 同一笔交易再次提交：不收交易费、不重复铸造，保留第一次的执行记录
+        assert_eq!(tx.execute(&mut f.ledger), Err(TransactionError::AlreadyProcessed));
+        assert_eq!(lamports(&f.ledger, &alice), balance);
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 5);
+        assert_eq!(f.ledger.get_transaction(&tx.signatures[0]).unwrap().result, Ok(()));
+        assert_eq!(f.ledger.get_signatures_for_address(&alice, None, 10), [tx.signatures[0]]);
+
+        // 指令失败的交易同样已经上链，不能重试同一个签名
+        let failed = f.transaction(vec![f.transfer(500, true)]);
+        assert!(matches!(failed.execute(&mut f.ledger), Err(TransactionError::InstructionError(..))));
+        assert_eq!(failed.execute(&mut f.ledger), Err(TransactionError::AlreadyProcessed));
+        assert!(matches!(
+            f.ledger.get_transaction(&failed.signatures[0]).unwrap().result,
+            Err(TransactionError::InstructionError(..))
+        ));
+    }
+
+    #[test]
+    fn test_mint_to_updates_supply() {
+        let mut f = fixture();
+        let tx = f.transaction(vec![f.mint(100), f.mint(20)]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 120);
+        assert_eq!(supply(&f.ledger, &f.mint), 120);
+
+        // bob签了名，但他不是mint authority
+        let bob = Keypair::new();
+        fund(&mut f.ledger, bob.pubkey());
+        let mut forged = f.mint(1);
+        forged.accounts[2].pubkey = bob.pubkey();
+        let mut tx = Transaction::new(bob.pubkey(), vec![forged]);
+        tx.sign(&[&bob]).unwrap();
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MintAuthorityMismatch))
+        );
+
+        // mint authority出现在其他位置也不行：authority位置上的账户必须是它本身
+        let mut misplaced = f.mint(1);
+        misplaced.accounts[2].pubkey = bob.pubkey();
+        misplaced.accounts.push(AccountMeta::new_readonly(f.alice.pubkey(), SYSTEM_PROGRAM_ID, true));
+        let mut tx = Transaction::new(bob.pubkey(), vec![misplaced]);
+        tx.sign(&[&bob, &f.alice]).unwrap();
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MintAuthorityMismatch))
+        );
+
+        // authority正确但没有签名，由bob签名支付
+        let mut unsigned = f.mint(1);
+        unsigned.accounts[2].is_signer = false;
+        unsigned.accounts.push(AccountMeta::new_readonly(bob.pubkey(), SYSTEM_PROGRAM_ID, true));
+        let mut tx = Transaction::new(bob.pubkey(), vec![unsigned]);
+        tx.sign(&[&bob]).unwrap();
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+
+        // 目标Token账户属于另一个mint
+        let mut wrong_mint = f.mint(1);
+        let other = Pubkey::new_unique();
+        let lamports = f.ledger.rent().minimum_balance(TokenAccount::LEN);
+        let data = AccountPayload::Token(TokenAccount::new(Pubkey::new_unique(), f.alice.pubkey(), 0));
+        f.ledger.create_account(other, Account::new(lamports, f.program, data)).unwrap();
+        wrong_mint.accounts[0].pubkey = other;
+        assert_eq!(
+            f.transaction(vec![wrong_mint]).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount))
+        );
+
+        // 供应量固定后不能再铸造
+        let mut account = f.ledger.get_account(&f.mint).unwrap().clone();
+        if let AccountPayload::Mint(mint) = &mut account.data {
+            mint.mint_authority = None;
+        }
+        let mut staged = f.ledger.stage();
+        staged.set_account(f.mint, account).unwrap();
+        staged.commit(f.alice.pubkey());
+        assert_eq!(
+            f.transaction(vec![f.mint(1)]).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MintAuthorityMismatch))
+        );
+        assert_eq!(supply(&f.ledger, &f.mint), 120);
+    }
+
+    #[test]
+    fn test_mint_to_respects_max_supply() {
+        let mut f = fixture();
+        let set_max_supply = |ledger: &mut Ledger, mint_key: Pubkey, max_supply: u64| {
+            let mut account = ledger.get_account(&mint_key).unwrap().clone();
+            if let AccountPayload::Mint(mint) = &mut account.data {
+                mint.max_supply = Some(max_supply);
+            }
+            let mut staged = ledger.stage();
+            staged.set_account(mint_key, account).unwrap();
+            staged.commit(mint_key);
+        };
+
+        // 正好达到上限可以，再多1个就失败，整笔交易回滚
+        set_max_supply(&mut f.ledger, f.mint, 100);
+        assert_eq!(f.transaction(vec![f.mint(60), f.mint(40)]).execute(&mut f.ledger), Ok(()));
+        assert_eq!(
+            f.transaction(vec![f.mint(1)]).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::SupplyCapExceeded))
+        );
+        assert_eq!((supply(&f.ledger, &f.mint), token_amount(&f.ledger, &f.alice_token)), (100, 100));
+
+        // 上限为u64::MAX时和不设上限一样：可以铸造到u64::MAX，超出时是溢出而不是超过上限
+        set_max_supply(&mut f.ledger, f.mint, u64::MAX);
+        assert_eq!(f.transaction(vec![f.mint(u64::MAX - 100)]).execute(&mut f.ledger), Ok(()));
+        assert_eq!(supply(&f.ledger, &f.mint), u64::MAX);
+        f.ledger.tick(0);
+        assert_eq!(
+            f.transaction(vec![f.mint(1)]).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::ArithmeticOverflow))
+        );
+    }
+
+    #[test]
+    fn test_burn_reduces_supply() {
+        let mut f = fixture();
+        let tx = f.transaction(vec![f.mint(100), f.burn(30)]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 70);
+        assert_eq!(supply(&f.ledger, &f.mint), 70);
+
+        // 销毁超过余额：整笔交易失败，余额和供应量都不变
+        let tx = f.transaction(vec![f.burn(10), f.burn(71)]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(1, TransactionResult::InsufficientFunds))
+        );
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 70);
+        assert_eq!(supply(&f.ledger, &f.mint), 70);
+
+        // 不是owner也没有被授权的签名者
+        let bob = Keypair::new();
+        fund(&mut f.ledger, bob.pubkey());
+        let mut forged = f.burn(1);
+        forged.accounts[2].pubkey = bob.pubkey();
+        let mut tx = Transaction::new(bob.pubkey(), vec![forged]);
+        tx.sign(&[&bob]).unwrap();
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+
+        // mint账户和Token账户不匹配
+        let mut wrong_mint = f.burn(1);
+        wrong_mint.accounts[1].pubkey = f.bob_token;
+        assert_eq!(
+            f.transaction(vec![wrong_mint]).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount))
+        );
+        assert_eq!(supply(&f.ledger, &f.mint), 70);
+    }
+
+    #[test]
+    fn test_freeze_and_thaw() {
+        let mut f = fixture();
+        let tx = f.transaction(vec![f.mint(100), f.transfer(40, true), f.freeze(ProgramInstruction::FreezeAccount)]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+
+        // 冻结的账户不能转入
+        assert_eq!(
+            f.transaction(vec![f.transfer(1, true)]).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::AccountFrozen))
+        );
+        // 也不能转出：bob_token转给alice_token
+        let bob = Keypair::new();
+        fund(&mut f.ledger, bob.pubkey());
+        let mut account = f.ledger.get_account(&f.bob_token).unwrap().clone();
+        if let AccountPayload::Token(token) = &mut account.data {
+            token.owner = bob.pubkey();
+        }
+        let mut staged = f.ledger.stage();
+        staged.set_account(f.bob_token, account).unwrap();
+        staged.commit(f.alice.pubkey());
+        let mut out = f.transfer_signed_by(1, bob.pubkey(), true);
+        out.accounts.swap(0, 1);
+        let mut tx = Transaction::new(bob.pubkey(), vec![out.clone()]);
+        tx.sign(&[&bob]).unwrap();
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::AccountFrozen))
+        );
+        // 重复冻结无效
+        assert_eq!(
+            f.transaction(vec![f.freeze(ProgramInstruction::FreezeAccount)]).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount))
+        );
+
+        // 解冻后恢复转账；失败的那笔已经上链，要用新的blockhash重新签名
+        assert_eq!(f.transaction(vec![f.freeze(ProgramInstruction::ThawAccount)]).execute(&mut f.ledger), Ok(()));
+        f.ledger.tick(1);
+        let mut tx = Transaction::new(bob.pubkey(), vec![out]).with_recent_blockhash(f.ledger.latest_blockhash());
+        tx.sign(&[&bob]).unwrap();
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.bob_token), 39);
+        // 和上面的解冻是同一条指令，需要新的blockhash
+        f.ledger.tick(0);
+        assert_eq!(
+            f.transaction(vec![f.freeze(ProgramInstruction::ThawAccount)]).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount))
+        );
+    }
+
+    #[test]
+    fn test_frozen_account_rejects_mint_and_close() {
+        let mut f = fixture();
+        let mut freeze = f.freeze(ProgramInstruction::FreezeAccount);
+        freeze.accounts[0].pubkey = f.alice_token;
+        assert_eq!(f.transaction(vec![freeze]).execute(&mut f.ledger), Ok(()));
+
+        // 冻结的账户不能接收新铸造的代币
+        assert_eq!(
+            f.transaction(vec![f.mint(1)]).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::AccountFrozen))
+        );
+        assert_eq!((token_amount(&f.ledger, &f.alice_token), supply(&f.ledger, &f.mint)), (0, 0));
+
+        // 余额为0也不能关闭
+        let close = Instruction::new(
+            f.program,
+            ProgramInstruction::CloseTokenAccount,
+            vec![
+                AccountMeta::new(f.alice_token, f.program, false),
+                AccountMeta::new(f.alice.pubkey(), SYSTEM_PROGRAM_ID, false),
+                AccountMeta::new_readonly(f.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+            ],
+        );
+        assert_eq!(
+            f.transaction(vec![close]).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::AccountFrozen))
+        );
+        assert!(f.ledger.get_account(&f.alice_token).is_some());
+    }
+
+    #[test]
+    fn test_freeze_requires_freeze_authority() {
+        let mut f = fixture();
+        // token owner不是freeze authority
+        let bob = Keypair::new();
+        fund(&mut f.ledger, bob.pubkey());
+        let mut forged = f.freeze(ProgramInstruction::FreezeAccount);
+        forged.accounts[2].pubkey = bob.pubkey();
+        let mut tx = Transaction::new(bob.pubkey(), vec![forged]);
+        tx.sign(&[&bob]).unwrap();
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+
+        // mint没有freeze authority
+        let mut account = f.ledger.get_account(&f.mint).unwrap().clone();
+        if let AccountPayload::Mint(mint) = &mut account.data {
+            mint.freeze_authority = None;
+        }
+        let mut staged = f.ledger.stage();
+        staged.set_account(f.mint, account).unwrap();
+        staged.commit(f.alice.pubkey());
+        assert_eq!(
+            f.transaction(vec![f.freeze(ProgramInstruction::FreezeAccount)]).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+    }
+
+    #[test]
+    fn test_token_metadata() {
+        let mut f = fixture();
+        let key = metadata_address(&f.mint, &f.program);
+        let lamports = f.ledger.rent().minimum_balance(TokenMetadata::LEN);
+        f.ledger.create_account(key, Account::new(lamports, f.program, AccountPayload::Empty)).unwrap();
+        let metadata_instruction = |data, signer: Pubkey| {
+            Instruction::new(
+                f.program,
+                data,
+                vec![
+                    AccountMeta::new(key, f.program, false),
+                    AccountMeta::new_readonly(f.mint, f.program, false),
+                    AccountMeta::new_readonly(signer, SYSTEM_PROGRAM_ID, true),
+                ],
+            )
+        };
+        let create = |name: &str| ProgramInstruction::CreateMetadata {
+            name: name.to_string(),
+            symbol: "GOLD".to_string(),
+            uri: "https://example.com/gold.json".to_string(),
+        };
+        let update = |name: &str| ProgramInstruction::UpdateMetadata {
+            name: name.to_string(),
+            symbol: "GLD".to_string(),
+            uri: String::new(),
+        };
+        let stored = |ledger: &Ledger| match &ledger.get_account(&key).unwrap().data {
+            AccountPayload::Metadata(metadata) => metadata.clone(),
+            _ => panic!("不是元数据账户"),
+        };
+
+        // 只有mint authority可以创建
+        let bob = Keypair::new();
+        fund(&mut f.ledger, bob.pubkey());
+        let mut tx = Transaction::new(bob.pubkey(), vec![metadata_instruction(create("Gold"), bob.pubkey())]);
+        tx.sign(&[&bob]).unwrap();
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+
+        let tx = f.transaction(vec![metadata_instruction(create("Gold"), f.alice.pubkey())]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!(stored(&f.ledger).to_string(), "Gold (GOLD)");
+        assert_eq!(stored(&f.ledger).update_authority, f.alice.pubkey());
+        // 不能重复创建
+        let tx = f.transaction(vec![metadata_instruction(create("Other"), f.alice.pubkey())]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount))
+        );
+
+        // update authority修改；名称过长时元数据不变
+        let tx = f.transaction(vec![metadata_instruction(update("Gold v2"), f.alice.pubkey())]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!(stored(&f.ledger).to_string(), "Gold v2 (GLD)");
+        let tx = f.transaction(vec![metadata_instruction(update(&"x".repeat(64)), f.alice.pubkey())]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::InvalidInstructionData))
+        );
+        let mut tx = Transaction::new(bob.pubkey(), vec![metadata_instruction(update("Stolen"), bob.pubkey())]);
+        tx.sign(&[&bob]).unwrap();
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+        assert_eq!(stored(&f.ledger).name, "Gold v2");
+    }
+
+    #[test]
+    fn test_metadata_must_use_mint_pda() {
+        let mut f = fixture();
+        // 随便一个空账户不是这个mint的元数据地址
+        let key = Pubkey::new_unique();
+        let lamports = f.ledger.rent().minimum_balance(TokenMetadata::LEN);
+        f.ledger.create_account(key, Account::new(lamports, f.program, AccountPayload::Empty)).unwrap();
+        let data = ProgramInstruction::CreateMetadata { name: "A".into(), symbol: "A".into(), uri: String::new() };
+        let accounts = vec![
+            AccountMeta::new(key, f.program, false),
+            AccountMeta::new_readonly(f.mint, f.program, false),
+            AccountMeta::new_readonly(f.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+        ];
+        let tx = f.transaction(vec![Instruction::new(f.program, data, accounts)]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount))
+        );
+    }
+
+    #[test]
+    fn test_transfer_fee_withheld_into_vault() {
+        let mut f = fixture();
+        // fee vault是同一mint下的Token账户，费率1%，单笔最多收5
+        let vault = Pubkey::new_unique();
+        let lamports = f.ledger.rent().minimum_balance(TokenAccount::LEN);
+        let data = AccountPayload::Token(TokenAccount::new(f.mint, Pubkey::new_unique(), 0));
+        f.ledger.create_account(vault, Account::new(lamports, f.program, data)).unwrap();
+        let mut account = f.ledger.get_account(&f.mint).unwrap().clone();
+        if let AccountPayload::Mint(mint) = &mut account.data {
+            mint.transfer_fee = Some(TransferFeeConfig::new(100, 5, vault).unwrap());
+        }
+        let mut staged = f.ledger.stage();
+        staged.set_account(f.mint, account).unwrap();
+        staged.commit(f.alice.pubkey());
+        let with_vault = |amount| {
+            let mut instruction = f.transfer(amount, true);
+            instruction.accounts.push(AccountMeta::new(vault, f.program, false));
+            instruction
+        };
+
+        let tx = f.transaction(vec![f.mint(1_000), with_vault(250), with_vault(1_000 - 250)]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        // 250收3（2.5向上取整），750达到上限收5
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 0);
+        assert_eq!(token_amount(&f.ledger, &f.bob_token), 247 + 745);
+        assert_eq!(token_amount(&f.ledger, &vault), 8);
+        // 手续费只是转移，总供应量不变
+        assert_eq!(supply(&f.ledger, &f.mint), 1_000);
+
+        // 没有传入fee vault
+        let tx = f.transaction(vec![f.mint(10), f.transfer(10, true)]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(1, TransactionResult::InvalidAccount))
+        );
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 0);
+    }
+
+    #[test]
+    fn test_transfer_hooks() {
+        let mut f = fixture();
+        let vault = Pubkey::new_unique();
+        let lamports = f.ledger.rent().minimum_balance(TokenAccount::LEN);
+        let data = AccountPayload::Token(TokenAccount::new(f.mint, Pubkey::new_unique(), 0));
+        f.ledger.create_account(vault, Account::new(lamports, f.program, data)).unwrap();
+        // 只能在已存在的mint上注册
+        assert_eq!(
+            f.ledger.register_transfer_hook(vault, Arc::new(FeeSkim::new(vault, 100))).unwrap_err(),
+            LedgerError::AccountNotFound(vault)
+        );
+        f.ledger.register_transfer_hook(f.mint, Arc::new(FeeSkim::new(vault, 100))).unwrap();
+        let with_vault = |f: &Fixture, amount| {
+            let mut instruction = f.transfer(amount, true);
+            instruction.accounts.push(AccountMeta::new(vault, f.program, false));
+            instruction
+        };
+
+        // 抽成1%存进vault，收款方收到剩下的部分
+        let tx = f.transaction(vec![f.mint(1_000), with_vault(&f, 500)]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.alice_token), token_amount(&f.ledger, &f.bob_token), token_amount(&f.ledger, &vault)), (500, 495, 5));
+        // 抽成vault没有作为可写账户传入
+        let tx = f.transaction(vec![f.transfer(100, true)]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount))
+        );
+
+        // 收款方的owner被拉黑后，后面注册的钩子不影响拒绝结果，余额不变
+        let AccountPayload::Token(bob_token) = &f.ledger.get_account(&f.bob_token).unwrap().data else {
+            panic!("不是Token账户");
+        };
+        let bob = bob_token.owner;
+        f.ledger.register_transfer_hook(f.mint, Arc::new(Blocklist::new([bob]))).unwrap();
+        let tx = f.transaction(vec![with_vault(&f, 100)]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::TransferHookRejected))
+        );
+        assert_eq!((token_amount(&f.ledger, &f.alice_token), token_amount(&f.ledger, &vault)), (500, 5));
+    }
+
+    #[test]
+    fn test_token_events() {
+        let mut f = fixture();
+        let tx = f.transaction(vec![f.mint(100), f.transfer(30, true), f.burn(20)]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        let events: Vec<TokenEvent> = f.ledger.iter_events_since(0).map(|record| record.event).collect();
+        assert_eq!(
+            events,
+            [
+                TokenEvent::MintTo { mint: f.mint, destination: f.alice_token, amount: 100 },
+                TokenEvent::Transfer { mint: f.mint, source: f.alice_token, destination: f.bob_token, amount: 30, fee: 0 },
+                TokenEvent::Burn { mint: f.mint, source: f.alice_token, amount: 20 },
+            ]
+        );
+
+        // 失败交易中已经执行的指令不留下事件
+        let cursor = f.ledger.event_log().next_seq();
+        let tx = f.transaction(vec![f.mint(5), f.transfer(1_000, true)]);
+        assert!(tx.execute(&mut f.ledger).is_err());
+        assert_eq!(f.ledger.iter_events_since(cursor).count(), 0);
+
+        f.ledger.tick(1);
+        let tx = f.transaction(vec![f.mint(5)]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        let records: Vec<&EventRecord> = f.ledger.iter_events_since(cursor).collect();
+        assert_eq!((records.len(), records[0].seq, records[0].slot), (1, cursor, 1));
+    }
+
+    #[test]
+    fn test_close_token_account() {
+        let mut f = fixture();
+        let close = |destination: Pubkey, authority: Pubkey| {
+            Instruction::new(
+                f.program,
+                ProgramInstruction::CloseTokenAccount,
+                vec![
+                    AccountMeta::new(f.alice_token, f.program, false),
+                    AccountMeta::new(destination, SYSTEM_PROGRAM_ID, false),
+                    AccountMeta::new_readonly(authority, SYSTEM_PROGRAM_ID, true),
+                ],
+            )
+        };
+        let alice = f.alice.pubkey();
+
+        // 还有余额时不能关闭
+        let tx = f.transaction(vec![f.mint(10), close(alice, alice)]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(1, TransactionResult::NonZeroBalance))
+        );
+        // 只有owner能关闭
+        let bob = Keypair::new();
+        fund(&mut f.ledger, bob.pubkey());
+        let mut tx = Transaction::new(bob.pubkey(), vec![close(bob.pubkey(), bob.pubkey())]);
+        tx.sign(&[&bob]).unwrap();
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+        // 不能把lamports退给自己
+        let tx = f.transaction(vec![close(f.alice_token, alice)]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount))
+        );
+
+        // 转走余额后关闭，lamports退给alice的钱包
+        let token_lamports = lamports(&f.ledger, &f.alice_token);
+        let tx = f.transaction(vec![f.mint(10), f.transfer(10, true), close(alice, alice)]);
+        let expected = lamports(&f.ledger, &alice) - tx.fee(&f.ledger) + token_lamports;
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!(lamports(&f.ledger, &alice), expected);
+        assert!(f.ledger.get_account(&f.alice_token).is_none());
+        assert_eq!(f.ledger.token_accounts_by_owner(&alice).count(), 0);
+        // 已关闭的账户不能再使用
+        assert_eq!(
+            f.transaction(vec![f.mint(1)]).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::IllegalOwner))
+        );
+    }
+
+    #[test]
+    fn test_delegate_transfer() {
+        let mut f = fixture();
+        let delegate = Keypair::new();
+        fund(&mut f.ledger, delegate.pubkey());
+        let tx = f.transaction(vec![f.mint(100), f.approve(delegate.pubkey(), 30)]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+
+        // 由delegate支付并签名
+        let by_delegate = |f: &Fixture, amount| {
+            let mut tx = Transaction::new(delegate.pubkey(), vec![f.transfer_signed_by(amount, delegate.pubkey(), true)]);
+            tx.sign(&[&delegate]).unwrap();
+            tx
+        };
+        assert_eq!(by_delegate(&f, 20).execute(&mut f.ledger), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.bob_token), 20);
+        let token = match &f.ledger.get_account(&f.alice_token).unwrap().data {
+            AccountPayload::Token(token) => token.clone(),
+            _ => unreachable!(),
+        };
+        assert_eq!((token.delegate, token.delegated_amount), (Some(delegate.pubkey()), 10));
+
+        // 超出剩余额度，即使账户余额足够
+        assert_eq!(
+            by_delegate(&f, 11).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::InsufficientFunds))
+        );
+
+        // owner转账不消耗额度；额度用完后授权失效
+        assert_eq!(f.transaction(vec![f.transfer(50, true)]).execute(&mut f.ledger), Ok(()));
+        assert_eq!(by_delegate(&f, 10).execute(&mut f.ledger), Ok(()));
+        assert_eq!(
+            by_delegate(&f, 1).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 20);
+        assert_eq!(token_amount(&f.ledger, &f.bob_token), 80);
+    }
+
+    #[test]
+    fn test_approve_and_revoke() {
+        let mut f = fixture();
+        let delegate = Keypair::new();
+        fund(&mut f.ledger, delegate.pubkey());
+        let by_delegate = |f: &Fixture, amount| {
+            let mut tx = Transaction::new(delegate.pubkey(), vec![f.transfer_signed_by(amount, delegate.pubkey(), true)]);
+            tx.sign(&[&delegate]).unwrap();
+            tx
+        };
+
+        // 只有owner能授权，delegate不能给自己授权
+        let mut forged = f.approve(delegate.pubkey(), 10);
+        forged.accounts[2].pubkey = delegate.pubkey();
+        let mut tx = Transaction::new(delegate.pubkey(), vec![forged]);
+        tx.sign(&[&delegate]).unwrap();
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+
+        // 新的授权覆盖旧的，撤销后delegate不能再转账
+        let tx = f.transaction(vec![f.mint(100), f.approve(delegate.pubkey(), 10), f.approve(delegate.pubkey(), 40)]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!(by_delegate(&f, 40).execute(&mut f.ledger), Ok(()));
+        assert_eq!(f.transaction(vec![f.approve(delegate.pubkey(), 5), f.revoke()]).execute(&mut f.ledger), Ok(()));
+        assert_eq!(
+            by_delegate(&f, 1).execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature))
+        );
+        assert_eq!(token_amount(&f.ledger, &f.bob_token), 40);
+    }
+
+    #[test]
+    fn test_all_instructions_commit() {
+        let mut f = fixture();
+        let tx = f.transaction(vec![f.mint(100), f.transfer(30, true)]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 70);
+        assert_eq!(token_amount(&f.ledger, &f.bob_token), 30);
+
+        // 先扣交易费，然后整笔交易的修改共享一个instruction id
+        let entries = &f.ledger.audit_log().entries()[4..];
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].key, f.alice.pubkey());
+        let entries = &entries[1..];
+        assert!(entries.iter().all(|e| e.instruction_id == entries[0].instruction_id && e.actor == f.alice.pubkey()));
+    }
+
+    #[test]
+    fn test_failure_rolls_back_earlier_instructions() {
+        let mut f = fixture();
+        let payer_before = lamports(&f.ledger, &f.alice.pubkey());
+        let audit_len = f.ledger.audit_log().len();
+
+        let tx = f.transaction(vec![f.mint(100), f.transfer(30, true), f.transfer(500, true)]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(2, TransactionResult::InsufficientFunds))
+        );
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 0);
+        assert_eq!(token_amount(&f.ledger, &f.bob_token), 0);
+        // 只留下扣交易费的记录
+        assert_eq!(f.ledger.audit_log().len(), audit_len + 1);
+
+        let tx = f.transaction(vec![f.mint(100), f.transfer(30, false)]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(1, TransactionResult::MissingRequiredSignature))
+        );
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 0);
+
+        // 失败的交易交易费照收
+        let fee = tx.fee(&f.ledger);
+        assert_eq!(lamports(&f.ledger, &f.alice.pubkey()), payer_before - 2 * fee);
+        assert_eq!(f.ledger.collected_fees(), 2 * fee);
+    }
+
+    #[test]
+    fn test_fee_payer() {
+        let mut f = fixture();
+        let payer = Keypair::new();
+        let mut tx = Transaction::new(payer.pubkey(), vec![f.mint(10)]);
+        tx.sign(&[&payer, &f.alice]).unwrap();
+        assert_eq!(tx.fee(&f.ledger), 2 * f.ledger.fee_schedule().lamports_per_signature);
+
+        // fee payer账户不存在
+        assert_eq!(tx.execute(&mut f.ledger), Err(TransactionError::AccountNotFound));
+
+        // fee payer余额不够：交易不执行
+        let minimum = f.ledger.rent().minimum_balance(0);
+        f.ledger
+            .create_account(payer.pubkey(), Account::new(minimum, SYSTEM_PROGRAM_ID, AccountPayload::Empty))
+            .unwrap();
+        assert_eq!(tx.execute(&mut f.ledger), Err(TransactionError::InsufficientFundsForFee));
+        assert_eq!(lamports(&f.ledger, &payer.pubkey()), minimum);
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 0);
+
+        // 不够付费时不会动用token账户里的资产
+        let fee = tx.fee(&f.ledger);
+        f.ledger.transfer_lamports(&f.alice.pubkey(), &payer.pubkey(), fee).unwrap();
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!(lamports(&f.ledger, &payer.pubkey()), minimum);
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 10);
+    }
+
+    #[test]
+    fn test_signatures_verified_before_execution() {
+        let mut f = fixture();
+        let before = f.ledger.snapshot();
+
+        let mut tx = f.transaction(vec![f.mint(100)]);
+        assert_eq!(tx.signer_keys(), vec![f.alice.pubkey()]);
+        assert_eq!(tx.verify_signatures(), Ok(()));
+
+        // 签名后修改交易内容，签名失效
+        tx.instructions.push(f.mint(1));
+        assert_eq!(tx.execute(&mut f.ledger), Err(TransactionError::SignatureFailure));
+
+        // 别人的密钥签不出alice的签名
+        let mut forged = Transaction::new(f.alice.pubkey(), vec![f.mint(100)]);
+        let mallory = Keypair::new();
+        forged.signatures = vec![mallory.sign_message(&forged.message_data())];
+        assert_eq!(forged.execute(&mut f.ledger), Err(TransactionError::SignatureFailure));
+
+        let unsigned = Transaction::new(f.alice.pubkey(), vec![f.mint(100)]);
+        assert_eq!(unsigned.execute(&mut f.ledger), Err(TransactionError::SignatureFailure));
+        assert_eq!(f.ledger.snapshot(), before);
+    }
+
+    #[test]
+    fn test_compute_unit_limit() {
+        let mut f = fixture();
+
+        // 预算只够一条指令，第二条指令中止并回滚第一条
+        let tx = f.transaction_with_limit(vec![f.mint(100), f.transfer(30, true)], INSTRUCTION_COST);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(1, TransactionResult::ComputeBudgetExceeded))
+        );
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 0);
+
+        let tx = f.transaction_with_limit(vec![f.mint(100), f.transfer(30, true)], 2 * INSTRUCTION_COST);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+
+        // compute unit上限也在签名范围内
+        let mut tx = f.transaction(vec![f.mint(1)]);
+        tx.compute_unit_limit = 1;
+        assert_eq!(tx.execute(&mut f.ledger), Err(TransactionError::SignatureFailure));
+    }
+
+    #[test]
+    fn test_outcome_returns_logs() {
+        let mut f = fixture();
+        let tx = f.transaction(vec![f.mint(100), f.transfer(500, true)]);
+        let outcome = tx.execute_with_programs(&mut f.ledger, &Programs::new());
+        assert_eq!(
+            outcome.result,
+            Err(TransactionError::InstructionError(1, TransactionResult::InsufficientFunds))
+        );
+        // 失败的交易也能拿到日志
+        assert_eq!(
+            outcome.logs.lines(),
+            [
+                format!("Program {} invoke [1]", f.program),
+                format!("Program {} success", f.program),
+                format!("Program {} invoke [1]", f.program),
+                format!("Program {} failed: InsufficientFunds", f.program),
+            ]
+        );
+        assert_eq!(outcome.compute_units_consumed, 2 * INSTRUCTION_COST);
+    }
+
+    #[test]
+    fn test_expired_blockhash_rejected() {
+        let mut f = fixture();
+        let tx = f.transaction(vec![f.mint(100)]);
+        for _ in 0..MAX_RECENT_BLOCKHASHES {
+            f.ledger.tick(1);
+        }
+        let audit_len = f.ledger.audit_log().len();
+        assert_eq!(tx.execute(&mut f.ledger), Err(TransactionError::BlockhashNotFound));
+        // 过期的交易不收交易费
+        assert_eq!(f.ledger.audit_log().len(), audit_len);
+
+        let mut tx = Transaction::new(f.alice.pubkey(), vec![f.mint(100)]).with_recent_blockhash(f.ledger.latest_blockhash());
+        tx.sign(&[&f.alice]).unwrap();
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+    }
+
+    #[test]
+    fn test_durable_nonce() {
+        let mut f = fixture();
+        let nonce_key = Pubkey::new_unique();
+        let stored = f.ledger.latest_blockhash();
+        let lamports = f.ledger.rent().minimum_balance(NonceAccount::LEN);
+        let data = AccountPayload::Nonce(NonceAccount::new(f.alice.pubkey(), stored));
+        f.ledger.create_account(nonce_key, Account::new(lamports, SYSTEM_PROGRAM_ID, data)).unwrap();
+        // 签名之后很久才提交，blockhash早就过期了
+        for _ in 0..MAX_RECENT_BLOCKHASHES {
+            f.ledger.tick(1);
+        }
+
+        let advance = || {
+            let accounts = vec![
+                AccountMeta::new(nonce_key, SYSTEM_PROGRAM_ID, false),
+                AccountMeta::new_readonly(f.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+            ];
+            Instruction::new(SYSTEM_PROGRAM_ID, ProgramInstruction::AdvanceNonce, accounts)
+        };
+        let nonce = |ledger: &Ledger| match ledger.get_account(&nonce_key).map(|account| &account.data) {
+            Some(AccountPayload::Nonce(nonce)) => nonce.nonce,
+            _ => panic!("不是nonce账户"),
+        };
+        let signed = |instructions, blockhash| {
+            let mut tx = Transaction::new(f.alice.pubkey(), instructions).with_recent_blockhash(blockhash);
+            tx.sign(&[&f.alice]).unwrap();
+            tx
+        };
+
+        let tx = signed(vec![advance(), f.mint(100)], stored);
+        assert_eq!(tx.nonce_account(), Some(nonce_key));
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_ne!(nonce(&f.ledger), stored);
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 100);
+
+        // 同一笔交易不能再次提交
+        assert_eq!(tx.execute(&mut f.ledger), Err(TransactionError::AlreadyProcessed));
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 100);
+
+        // 指令失败时其余修改回滚，但nonce照样推进
+        let current = nonce(&f.ledger);
+        let failing = signed(vec![advance(), f.transfer(500, true)], current);
+        assert_eq!(
+            failing.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(1, TransactionResult::InsufficientFunds))
+        );
+        assert_ne!(nonce(&f.ledger), current);
+        assert_eq!(failing.execute(&mut f.ledger), Err(TransactionError::AlreadyProcessed));
+
+        // AdvanceNonce不是第一条指令时，交易按普通blockhash校验
+        let current = nonce(&f.ledger);
+        let misplaced = signed(vec![f.mint(1), advance()], current);
+        assert_eq!(misplaced.nonce_account(), None);
+        assert_eq!(misplaced.execute(&mut f.ledger), Err(TransactionError::BlockhashNotFound));
+    }
+
+    #[test]
+    fn test_simulate_does_not_commit() {
+        let mut f = fixture();
+        let before = f.ledger.snapshot();
+        let audit_len = f.ledger.audit_log().len();
+
+        // 试运行不校验签名，未签名的交易也可以
+        let unsigned = Transaction::new(f.alice.pubkey(), vec![f.mint(100), f.transfer(30, true)]);
+        let simulation = f.ledger.simulate(&unsigned);
+        assert_eq!(simulation.result, Ok(()));
+        assert_eq!(simulation.compute_units_consumed, 2 * INSTRUCTION_COST);
+        assert!(simulation.logs.contains("success"));
+        let changed: Vec<Pubkey> = simulation.diffs.iter().map(AccountDiff::key).collect();
+        assert_eq!(changed.len(), 4);
+        for key in [f.alice.pubkey(), f.mint, f.alice_token, f.bob_token] {
+            assert!(changed.contains(&key));
+        }
+        assert_eq!(f.ledger.snapshot(), before);
+        assert_eq!(f.ledger.audit_log().len(), audit_len);
+
+        // 失败的交易只留下交易费的扣除
+        let failing = Transaction::new(f.alice.pubkey(), vec![f.mint(100), f.transfer(500, true)]);
+        let simulation = f.ledger.simulate(&failing);
+        assert_eq!(
+            simulation.result,
+            Err(TransactionError::InstructionError(1, TransactionResult::InsufficientFunds))
+        );
+        assert_eq!(simulation.diffs.len(), 1);
+        assert_eq!(simulation.diffs[0].key(), f.alice.pubkey());
+
+        // 真正执行的结果和试运行一致
+        let tx = f.transaction(vec![f.mint(100), f.transfer(30, true)]);
+        let simulation = f.ledger.simulate(&tx);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!(before.diff(&f.ledger.snapshot()), simulation.diffs);
+    }
+
+    #[test]
+    fn test_multiple_signers() {
+        let mut f = fixture();
+        let payer = Keypair::new();
+        fund(&mut f.ledger, payer.pubkey());
+        let mut tx = Transaction::new(payer.pubkey(), vec![f.mint(10), f.transfer(5, true)]);
+
+        // alice在两条指令中都是签名者，只需要签一次
+        assert_eq!(tx.instructions[1].signer_keys(), vec![f.alice.pubkey()]);
+        assert_eq!(tx.signer_keys(), vec![payer.pubkey(), f.alice.pubkey()]);
+        assert_eq!(tx.num_required_signatures(), 2);
+
+        assert_eq!(tx.sign(&[&payer, &f.alice]), Ok(()));
+        assert_eq!(tx.signatures.len(), 2);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.bob_token), 5);
+    }
+
+    #[test]
+    fn test_missing_and_duplicate_signers() {
+        let mut f = fixture();
+        let payer = Keypair::new();
+        let mut tx = Transaction::new(payer.pubkey(), vec![f.mint(10)]);
+
+        assert_eq!(tx.sign(&[&payer]), Err(SignerError::MissingSigner(f.alice.pubkey())));
+        assert_eq!(
+            tx.sign(&[&payer, &f.alice, &payer]),
+            Err(SignerError::DuplicateSigner(payer.pubkey()))
+        );
+        let stranger = Keypair::new();
+        assert_eq!(
+            tx.sign(&[&payer, &f.alice, &stranger]),
+            Err(SignerError::UnexpectedSigner(stranger.pubkey()))
+        );
+        // 签名失败时交易仍然没有签名，不能执行
+        assert!(tx.signatures.is_empty());
+        assert_eq!(tx.execute(&mut f.ledger), Err(TransactionError::SignatureFailure));
+
+        // 只带fee payer签名的交易：is_signer账户alice没有签名
+        tx.signatures = vec![payer.sign_message(&tx.message_data())];
+        assert_eq!(tx.execute(&mut f.ledger), Err(TransactionError::SignatureFailure));
+        // 同一个签名重复两次也不行
+        let payer_signature = tx.signatures[0];
+        tx.signatures = vec![payer_signature, payer_signature];
+        assert_eq!(tx.execute(&mut f.ledger), Err(TransactionError::SignatureFailure));
+    }
+
+    #[test]
+    fn test_instruction_errors() {
+        let mut f = fixture();
+        let tx = f.transaction(vec![f.mint(u64::MAX), f.mint(1)]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(1, TransactionResult::ArithmeticOverflow))
+        );
+
+        // 账本中的owner不是目标程序
+        let mut foreign = f.mint(1);
+        foreign.program_id = Pubkey::new_unique();
+        let tx = f.transaction(vec![foreign]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::IllegalOwner))
+        );
+
+        let init = Instruction::new(
+            f.program,
+            ProgramInstruction::Initialize { initial_supply: 5 },
+            f.mint(0).accounts,
+        );
+        let tx = f.transaction(vec![init]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.alice_token), 5);
+        assert_eq!(supply(&f.ledger, &f.mint), 5);
+        let again = Instruction::new(f.program, ProgramInstruction::Initialize { initial_supply: 5 }, f.mint(0).accounts);
+        f.ledger.tick(0);
+        let tx = f.transaction(vec![again]);
+        assert_eq!(
+            tx.execute(&mut f.ledger),
+            Err(TransactionError::InstructionError(0, TransactionResult::InvalidAccount))
+        );
+    }
+
+    #[test]
+    fn test_initialize_requires_mint_authority_and_respects_cap() {
+        let mut f = fixture();
+        // bob只作为交易费支付者签名，不是mint authority
+        let bob = funded_keypair(&mut f.ledger);
+        let accounts = vec![
+            AccountMeta::new(f.bob_token, f.program, false),
+            AccountMeta::new(f.mint, f.program, false),
+            AccountMeta::new_readonly(bob.pubkey(), SYSTEM_PROGRAM_ID, true),
+        ];
+        let init = Instruction::new(f.program, ProgramInstruction::Initialize { initial_supply: 1_000_000 }, accounts);
+        let mut tx = Transaction::new(bob.pubkey(), vec![init]).with_recent_blockhash(f.ledger.latest_blockhash());
+        tx.sign(&[&bob]).unwrap();
+        assert_eq!(tx.execute(&mut f.ledger), Err(TransactionError::InstructionError(0, TransactionResult::MintAuthorityMismatch)));
+        assert_eq!((token_amount(&f.ledger, &f.bob_token), supply(&f.ledger, &f.mint)), (0, 0));
+
+        // 有供应量上限的mint，初始余额也不能超过上限
+        let capped = create_mint(&mut f.ledger, f.program, Mint::new(0, f.alice.pubkey()).with_max_supply(10));
+        let token = create_token_account(&mut f.ledger, f.program, Pubkey::new_unique(), capped, f.alice.pubkey(), 0);
+        let init = |initial_supply| {
+            let accounts = vec![
+                AccountMeta::new(token, f.program, false),
+                AccountMeta::new(capped, f.program, false),
+                AccountMeta::new_readonly(f.alice.pubkey(), SYSTEM_PROGRAM_ID, true),
+            ];
+            Instruction::new(f.program, ProgramInstruction::Initialize { initial_supply }, accounts)
+        };
+        let tx = f.transaction(vec![init(11)]);
+        assert_eq!(tx.execute(&mut f.ledger), Err(TransactionError::InstructionError(0, TransactionResult::SupplyCapExceeded)));
+        let tx = f.transaction(vec![init(10)]);
+        assert_eq!(tx.execute(&mut f.ledger), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &token), supply(&f.ledger, &capped)), (10, 10));
+    }
+}
@@ -0,0 +1,213 @@
+// 链式构建交易 - Transaction::new总能构造出来，但没有fee payer、没有指令、签名者重复或缺失的交易
+// 要到签名或执行时才会失败。builder在build()时一次检查所有字段，把发现的问题全部放进BuildError返回，
+// 而不是panic或者只报告第一个问题
+
+use std::fmt;
+
+use crate::compute::DEFAULT_COMPUTE_UNIT_LIMIT;
+use crate::hash::Hash;
+use crate::signature::Signer;
+use crate::transaction::{Instruction, Transaction};
+use crate::Pubkey;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildIssue {
+    MissingFeePayer,          // 没有设置fee payer
+    NoInstructions,           // 没有任何指令
+    DuplicateSigner(Pubkey),  // 同一个签名者提供了多次
+    UnexpectedSigner(Pubkey), // 提供的签名者不在交易的签名者列表中
+    MissingSigner(Pubkey),    // 交易需要的签名者没有提供
+}
+
+impl fmt::Display for BuildIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildIssue::MissingFeePayer => write!(f, "没有设置fee payer"),
+            BuildIssue::NoInstructions => write!(f, "交易没有指令"),
+            BuildIssue::DuplicateSigner(key) => write!(f, "签名者重复: {}", key),
+            BuildIssue::UnexpectedSigner(key) => write!(f, "多余的签名者: {}", key),
+            BuildIssue::MissingSigner(key) => write!(f, "缺少签名者: {}", key),
+        }
+    }
+}
+
+// build()失败时发现的全部问题，按检查顺序排列
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildError {
+    issues: Vec<BuildIssue>,
+}
+
+impl BuildError {
+    pub fn issues(&self) -> &[BuildIssue] {
+        &self.issues
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let issues: Vec<String> = self.issues.iter().map(|issue| issue.to_string()).collect();
+        write!(f, "构建交易失败: {}", issues.join("; "))
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+pub struct TransactionBuilder<'a> {
+    fee_payer: Option<Pubkey>,
+    instructions: Vec<Instruction>,
+    compute_unit_limit: u64,
+    recent_blockhash: Hash,
+    signers: Vec<&'a dyn Signer>,
+}
+
+impl Default for TransactionBuilder<'_> {
+    fn default() -> Self {
+        Self {
+            fee_payer: None,
+            instructions: Vec::new(),
+            compute_unit_limit: DEFAULT_COMPUTE_UNIT_LIMIT,
+            recent_blockhash: Hash::default(),
+            signers: Vec::new(),
+        }
+    }
+}
+
+impl<'a> TransactionBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fee_payer(mut self, fee_payer: Pubkey) -> Self {
+        self.fee_payer = Some(fee_payer);
+        self
+    }
+
+    pub fn instruction(mut self, instruction: Instruction) -> Self {
+        self.instructions.push(instruction);
+        self
+    }
+
+    pub fn instructions(mut self, instructions: impl IntoIterator<Item = Instruction>) -> Self {
+        self.instructions.extend(instructions);
+        self
+    }
+
+    pub fn compute_unit_limit(mut self, limit: u64) -> Self {
+        self.compute_unit_limit = limit;
+        self
+    }
+
+    pub fn recent_blockhash(mut self, blockhash: Hash) -> Self {
+        self.recent_blockhash = blockhash;
+        self
+    }
+
+    // 提供了签名者时build()会直接签名，签名者必须和交易需要的完全一致；
+    // 一个都不提供则返回未签名的交易，留给partial_sign分别签名
+    pub fn signer(mut self, signer: &'a dyn Signer) -> Self {
+        self.signers.push(signer);
+        self
+    }
+
+    pub fn build(self) -> Result<Transaction, BuildError> {
+        let mut issues = Vec::new();
+        if self.fee_payer.is_none() {
+            issues.push(BuildIssue::MissingFeePayer);
+        }
+        if self.instructions.is_empty() {
+            issues.push(BuildIssue::NoInstructions);
+        }
+
+        let mut provided: Vec<Pubkey> = Vec::with_capacity(self.signers.len());
+        for key in self.signers.iter().map(|signer| signer.pubkey()) {
+            if provided.contains(&key) {
+                issues.push(BuildIssue::DuplicateSigner(key));
+            } else {
+                provided.push(key);
+            }
+        }
+
+        // 没有fee payer时不知道完整的签名者列表，只检查重复
+        let transaction = self.fee_payer.map(|fee_payer| {
+            Transaction::new(fee_payer, self.instructions)
+                .with_compute_unit_limit(self.compute_unit_limit)
+                .with_recent_blockhash(self.recent_blockhash)
+        });
+        if let Some(transaction) = transaction.as_ref().filter(|_| !provided.is_empty()) {
+            let keys = transaction.signer_keys();
+            issues.extend(provided.iter().filter(|key| !keys.contains(key)).map(|key| BuildIssue::UnexpectedSigner(*key)));
+            issues.extend(keys.iter().filter(|key| !provided.contains(key)).map(|key| BuildIssue::MissingSigner(*key)));
+        }
+
+        match transaction {
+            Some(mut transaction) if issues.is_empty() => {
+                if !self.signers.is_empty() {
+                    transaction.sign(&self.signers).expect("签名者已经和signer_keys()核对过");
+                }
+                Ok(transaction)
+            }
+            _ => Err(BuildError { issues }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::Keypair;
+    use crate::{AccountMeta, ProgramInstruction, TOKEN_PROGRAM_ID};
+
+    fn transfer(authority: Pubkey) -> Instruction {
+        Instruction::new(TOKEN_PROGRAM_ID, ProgramInstruction::Transfer { amount: 1 }, vec![
+            AccountMeta::new(Pubkey::new_unique(), TOKEN_PROGRAM_ID, false),
+            AccountMeta::new(Pubkey::new_unique(), TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(authority, TOKEN_PROGRAM_ID, true),
+        ])
+    }
+
+    #[test]
+    fn test_build_and_sign() {
+        let (payer, authority) = (Keypair::new(), Keypair::new());
+        let blockhash = [7; 32];
+        let tx = TransactionBuilder::new()
+            .fee_payer(payer.pubkey())
+            .instruction(transfer(authority.pubkey()))
+            .compute_unit_limit(50_000)
+            .recent_blockhash(blockhash)
+            .signer(&authority)
+            .signer(&payer)
+            .build()
+            .unwrap();
+        assert_eq!(tx.compute_unit_limit, 50_000);
+        assert_eq!(tx.recent_blockhash, blockhash);
+        assert_eq!(tx.verify_signatures(), Ok(()));
+
+        // 不提供签名者时得到未签名的交易
+        let unsigned = TransactionBuilder::new().fee_payer(payer.pubkey()).instruction(transfer(authority.pubkey())).build().unwrap();
+        assert_eq!(unsigned.missing_signers(), vec![payer.pubkey(), authority.pubkey()]);
+    }
+
+    #[test]
+    fn test_build_collects_every_issue() {
+        let payer = Keypair::new();
+        let error = TransactionBuilder::new().signer(&payer).signer(&payer).build().unwrap_err();
+        assert_eq!(
+            error.issues(),
+            &[BuildIssue::MissingFeePayer, BuildIssue::NoInstructions, BuildIssue::DuplicateSigner(payer.pubkey())]
+        );
+
+        let (authority, stranger) = (Keypair::new(), Keypair::new());
+        let error = TransactionBuilder::new()
+            .fee_payer(payer.pubkey())
+            .instructions([transfer(authority.pubkey())])
+            .signer(&payer)
+            .signer(&stranger)
+            .build()
+            .unwrap_err();
+        assert_eq!(error.issues(), &[BuildIssue::UnexpectedSigner(stranger.pubkey()), BuildIssue::MissingSigner(authority.pubkey())]);
+        assert_eq!(
+            error.to_string(),
+            format!("构建交易失败: 多余的签名者: {}; 缺少签名者: {}", stranger.pubkey(), authority.pubkey())
+        );
+    }
+}
@@ -0,0 +1,174 @@
+// 转账钩子 - 类似于Token-2022的Transfer Hook扩展：在mint上注册的钩子会在每次转账写入余额之前被调用，
+// 可以拒绝转账（黑名单），也可以从转账金额中再抽取一部分存进指定的vault（抽成）。
+// 钩子是trait object，同一个mint上可以注册多个不同类型的钩子，按注册顺序执行
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::mint::MAX_FEE_BASIS_POINTS;
+use crate::Pubkey;
+
+// 钩子看到的转账信息，金额是扣除mint手续费之前的数量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferHookContext {
+    pub mint: Pubkey,
+    pub source: Pubkey, // 转出的Token账户
+    pub destination: Pubkey,
+    pub source_owner: Pubkey,
+    pub destination_owner: Pubkey,
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookAction {
+    Allow,
+    Skim { vault: Pubkey, amount: u64 }, // 从收款方应得的数量中抽取amount存进vault
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferHookError {
+    Blocked(Pubkey),  // 转出方或收款方的owner在黑名单中
+    Rejected(String), // 其他钩子自定义的拒绝原因
+}
+
+impl fmt::Display for TransferHookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferHookError::Blocked(owner) => write!(f, "{}在黑名单中", owner),
+            TransferHookError::Rejected(reason) => write!(f, "转账被拒绝: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for TransferHookError {}
+
+// 要求Debug是为了让持有钩子的Ledger可以继续derive(Debug)
+pub trait TransferHook: fmt::Debug {
+    fn name(&self) -> &str;
+
+    fn on_transfer(&self, transfer: &TransferHookContext) -> Result<HookAction, TransferHookError>;
+}
+
+// 拒绝黑名单中的owner转出或收款
+#[derive(Debug, Clone, Default)]
+pub struct Blocklist {
+    blocked: BTreeSet<Pubkey>,
+}
+
+impl Blocklist {
+    pub fn new(blocked: impl IntoIterator<Item = Pubkey>) -> Self {
+        Self { blocked: blocked.into_iter().collect() }
+    }
+}
+
+impl TransferHook for Blocklist {
+    fn name(&self) -> &str {
+        "blocklist"
+    }
+
+    fn on_transfer(&self, transfer: &TransferHookContext) -> Result<HookAction, TransferHookError> {
+        [transfer.source_owner, transfer.destination_owner]
+            .into_iter()
+            .find(|owner| self.blocked.contains(owner))
+            .map_or(Ok(HookAction::Allow), |owner| Err(TransferHookError::Blocked(owner)))
+    }
+}
+
+// 按基点抽成，向下取整，不足1个最小单位的部分不收
+#[derive(Debug, Clone, Copy)]
+pub struct FeeSkim {
+    vault: Pubkey,
+    basis_points: u16,
+}
+
+impl FeeSkim {
+    // 超过100%时按100%计算
+    pub fn new(vault: Pubkey, basis_points: u16) -> Self {
+        Self { vault, basis_points: basis_points.min(MAX_FEE_BASIS_POINTS) }
+    }
+}
+
+impl TransferHook for FeeSkim {
+    fn name(&self) -> &str {
+        "fee-skim"
+    }
+
+    fn on_transfer(&self, transfer: &TransferHookContext) -> Result<HookAction, TransferHookError> {
+        let amount = transfer.amount as u128 * self.basis_points as u128 / MAX_FEE_BASIS_POINTS as u128;
+        match amount as u64 {
+            0 => Ok(HookAction::Allow),
+            amount => Ok(HookAction::Skim { vault: self.vault, amount }),
+        }
+    }
+}
+
+// mint -> 已注册的钩子。用Arc共享，克隆账本（试运行）时不需要复制钩子本身
+#[derive(Debug, Clone, Default)]
+pub struct TransferHooks {
+    hooks: BTreeMap<Pubkey, Vec<Arc<dyn TransferHook>>>,
+}
+
+impl TransferHooks {
+    pub fn register(&mut self, mint: Pubkey, hook: Arc<dyn TransferHook>) {
+        self.hooks.entry(mint).or_default().push(hook);
+    }
+
+    pub fn for_mint(&self, mint: &Pubkey) -> &[Arc<dyn TransferHook>] {
+        self.hooks.get(mint).map_or(&[], Vec::as_slice)
+    }
+
+    // 依次调用mint上的所有钩子，任何一个拒绝就停止；返回所有抽成
+    pub fn run(&self, transfer: &TransferHookContext) -> Result<Vec<(Pubkey, u64)>, TransferHookError> {
+        let mut skims = Vec::new();
+        for hook in self.for_mint(&transfer.mint) {
+            if let HookAction::Skim { vault, amount } = hook.on_transfer(transfer)? {
+                skims.push((vault, amount));
+            }
+        }
+        Ok(skims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer(mint: Pubkey, amount: u64) -> TransferHookContext {
+        TransferHookContext {
+            mint,
+            source: Pubkey::new_unique(),
+            destination: Pubkey::new_unique(),
+            source_owner: Pubkey::new_unique(),
+            destination_owner: Pubkey::new_unique(),
+            amount,
+        }
+    }
+
+    #[test]
+    fn test_hooks_run_in_order_per_mint() {
+        let (mint, other_mint, vault) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut hooks = TransferHooks::default();
+        hooks.register(mint, Arc::new(FeeSkim::new(vault, 100)));
+        hooks.register(mint, Arc::new(FeeSkim::new(vault, 50)));
+        assert_eq!(hooks.for_mint(&mint).iter().map(|hook| hook.name()).collect::<Vec<_>>(), ["fee-skim", "fee-skim"]);
+
+        assert_eq!(hooks.run(&transfer(mint, 1_000)), Ok(vec![(vault, 10), (vault, 5)]));
+        // 向下取整后为0时不抽成
+        assert_eq!(hooks.run(&transfer(mint, 99)), Ok(vec![]));
+        // 没有注册钩子的mint不受影响
+        assert_eq!(hooks.run(&transfer(other_mint, 1_000)), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_blocklist_stops_later_hooks() {
+        let mint = Pubkey::new_unique();
+        let blocked = transfer(mint, 1_000);
+        let mut hooks = TransferHooks::default();
+        hooks.register(mint, Arc::new(Blocklist::new([blocked.destination_owner])));
+        hooks.register(mint, Arc::new(FeeSkim::new(Pubkey::new_unique(), 100)));
+
+        assert_eq!(hooks.run(&blocked), Err(TransferHookError::Blocked(blocked.destination_owner)));
+        assert_eq!(hooks.run(&transfer(mint, 1_000)).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,131 @@
+// 端到端教程：只用公开的API从零发行一个代币。
+// 依次创建mint、为两个钱包创建关联Token账户(ATA)、铸造、带手续费转账、冻结和解冻、最后关闭账户，
+// 每一步都用断言检查账本状态，既是可以照着读的文档，也是整套Token API的集成测试。
+// generics_test没有Cargo.toml，不能单独作为一个bin，所以放在这里由main和测试调用
+
+use std::error::Error;
+
+use crate::transaction::TransactionError;
+use crate::{
+    get_associated_token_address, Account, AccountMeta, AccountPayload, Faucet, Instruction, InstructionBuilder, Keypair, Ledger, Mint,
+    ProgramInstruction, Pubkey, Signer, TokenAccount, Transaction, TransactionResult, TransferFeeConfig, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID,
+};
+
+// 由payer签名并用最新的blockhash提交，提交后产生新的blockhash
+fn send(ledger: &mut Ledger, payer: &Keypair, instructions: Vec<Instruction>) -> Result<Result<(), TransactionError>, Box<dyn Error>> {
+    let mut tx = Transaction::new(payer.pubkey(), instructions).with_recent_blockhash(ledger.latest_blockhash());
+    tx.sign(&[payer])?;
+    let result = tx.execute(ledger);
+    ledger.tick(0);
+    Ok(result)
+}
+
+fn balance(ledger: &Ledger, key: &Pubkey) -> u64 {
+    match ledger.get_account(key).map(|account| &account.data) {
+        Some(AccountPayload::Token(token)) => token.amount,
+        _ => 0,
+    }
+}
+
+fn supply(ledger: &Ledger, key: &Pubkey) -> u64 {
+    match ledger.get_account(key).map(|account| &account.data) {
+        Some(AccountPayload::Mint(mint)) => mint.supply,
+        _ => 0,
+    }
+}
+
+pub fn run() -> Result<(), Box<dyn Error>> {
+    // 第0步：准备账本，从水龙头给发行方和两个用户领lamports
+    let mut ledger = Ledger::default();
+    let mut faucet = Faucet::new(10, ledger.rent().minimum_balance(0) * 100);
+    let airdrop = ledger.rent().minimum_balance(0) * 10;
+    let (issuer, alice, bob) = (Keypair::new(), Keypair::new(), Keypair::new());
+    for keypair in [&issuer, &alice, &bob] {
+        faucet.request_airdrop(&mut ledger, keypair.pubkey(), airdrop)?;
+    }
+
+    // 第1步：创建mint。精度6位，发行方同时是mint authority和freeze authority，
+    // 每笔转账收0.5%的手续费（最多10个最小单位），手续费存进发行方的fee vault
+    let mint = Pubkey::new_unique();
+    let fee_vault = get_associated_token_address(&issuer.pubkey(), &mint);
+    let fee_config = TransferFeeConfig::new(50, 10, fee_vault)?;
+    let mint_data = Mint::new(6, issuer.pubkey()).with_freeze_authority(issuer.pubkey()).with_transfer_fee(fee_config);
+    ledger.create_account(mint, Account::new(ledger.rent().minimum_balance(Mint::LEN), TOKEN_PROGRAM_ID, AccountPayload::Mint(mint_data)))?;
+    assert_eq!(supply(&ledger, &mint), 0);
+    println!("第1步 创建mint: {}", mint);
+
+    // 第2步：为发行方、alice和bob创建关联Token账户，地址由(owner, mint)唯一确定
+    let token_lamports = ledger.rent().minimum_balance(TokenAccount::LEN);
+    let [alice_ata, bob_ata] = [&alice, &bob].map(|owner| get_associated_token_address(&owner.pubkey(), &mint));
+    for (key, owner) in [(fee_vault, issuer.pubkey()), (alice_ata, alice.pubkey()), (bob_ata, bob.pubkey())] {
+        let data = AccountPayload::Token(TokenAccount::new(mint, owner, 0));
+        ledger.create_account(key, Account::new(token_lamports, TOKEN_PROGRAM_ID, data))?;
+    }
+    assert_eq!(ledger.token_accounts_by_mint(&mint).count(), 3);
+    println!("第2步 创建ATA: alice={}，bob={}", alice_ata, bob_ata);
+
+    // 第3步：铸造。只有mint authority能铸造，alice自己签名会被拒绝
+    let mint_to = |signer: Pubkey, amount| {
+        InstructionBuilder::mint_to().amount(amount).to(alice_ata).mint(mint).signer(signer).build()
+    };
+    let rejected = send(&mut ledger, &alice, vec![mint_to(alice.pubkey(), 1_000_000)?])?;
+    assert_eq!(rejected, Err(TransactionError::InstructionError(0, TransactionResult::MintAuthorityMismatch)));
+    assert_eq!(send(&mut ledger, &issuer, vec![mint_to(issuer.pubkey(), 1_000_000)?])?, Ok(()));
+    assert_eq!((balance(&ledger, &alice_ata), supply(&ledger, &mint)), (1_000_000, 1_000_000));
+    println!("第3步 铸造: alice持有{}", balance(&ledger, &alice_ata));
+
+    // 第4步：带手续费转账。bob收到扣除手续费后的数量，手续费进fee vault，总供应量不变
+    let transfer = |amount| {
+        InstructionBuilder::transfer().amount(amount).from(alice_ata).to(bob_ata).signer(alice.pubkey()).fee_vault(fee_vault).build()
+    };
+    assert_eq!(send(&mut ledger, &alice, vec![transfer(1_000)?])?, Ok(()));
+    assert_eq!(send(&mut ledger, &alice, vec![transfer(10_000)?])?, Ok(()));
+    // 1000的0.5%是5；10000的0.5%是50，超过上限按10收
+    assert_eq!(balance(&ledger, &bob_ata), 995 + 9_990);
+    assert_eq!(balance(&ledger, &fee_vault), 5 + 10);
+    assert_eq!(balance(&ledger, &alice_ata), 1_000_000 - 11_000);
+    assert_eq!(supply(&ledger, &mint), 1_000_000);
+    println!("第4步 转账: bob收到{}，手续费{}", balance(&ledger, &bob_ata), balance(&ledger, &fee_vault));
+
+    // 第5步：冻结。freeze authority冻结alice的账户后她不能转出，解冻后恢复
+    let freeze = |data| {
+        Instruction::new(TOKEN_PROGRAM_ID, data, vec![
+            AccountMeta::new(alice_ata, TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(mint, TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(issuer.pubkey(), SYSTEM_PROGRAM_ID, true),
+        ])
+    };
+    assert_eq!(send(&mut ledger, &issuer, vec![freeze(ProgramInstruction::FreezeAccount)])?, Ok(()));
+    let frozen = send(&mut ledger, &alice, vec![transfer(1)?])?;
+    assert_eq!(frozen, Err(TransactionError::InstructionError(0, TransactionResult::AccountFrozen)));
+    assert_eq!(send(&mut ledger, &issuer, vec![freeze(ProgramInstruction::ThawAccount)])?, Ok(()));
+    println!("第5步 冻结时转出: {:?}", frozen);
+
+    // 第6步：关闭。bob把余额全部销毁后关闭ATA，租金押金退回bob；有余额时不能关闭
+    let close = Instruction::new(TOKEN_PROGRAM_ID, ProgramInstruction::CloseTokenAccount, vec![
+        AccountMeta::new(bob_ata, TOKEN_PROGRAM_ID, false),
+        AccountMeta::new(bob.pubkey(), SYSTEM_PROGRAM_ID, false),
+        AccountMeta::new_readonly(bob.pubkey(), SYSTEM_PROGRAM_ID, true),
+    ]);
+    let not_empty = send(&mut ledger, &bob, vec![close.clone()])?;
+    assert_eq!(not_empty, Err(TransactionError::InstructionError(0, TransactionResult::NonZeroBalance)));
+    let bob_balance = balance(&ledger, &bob_ata);
+    let burn = InstructionBuilder::burn().amount(bob_balance).from(bob_ata).mint(mint).signer(bob.pubkey()).build()?;
+    let lamports_before = ledger.get_account(&bob.pubkey()).map_or(0, |account| account.lamports);
+    assert_eq!(send(&mut ledger, &bob, vec![burn, close])?, Ok(()));
+    assert!(ledger.get_account(&bob_ata).is_none());
+    assert!(ledger.get_account(&bob.pubkey()).map_or(0, |account| account.lamports) > lamports_before);
+    assert_eq!(supply(&ledger, &mint), 1_000_000 - bob_balance);
+    println!("第6步 关闭bob的ATA，剩余供应量{}", supply(&ledger, &mint));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tutorial_runs() {
+        assert!(run().is_ok());
+    }
+}
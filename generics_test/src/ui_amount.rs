@@ -0,0 +1,123 @@
+// 显示用的代币数量 - 链上只保存最小单位的整数，显示时按mint的精度加上小数点，
+// 例如decimals=6时1_500_000显示为"1.5"；解析方向相反，小数位超过精度时拒绝而不是悄悄截断
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiAmountError {
+    InvalidFormat,     // 不是"整数"或"整数.小数"的形式
+    PrecisionOverflow, // 小数位数超过mint的精度
+    AmountOverflow,    // 换算成最小单位后超过u64
+}
+
+impl fmt::Display for UiAmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UiAmountError::InvalidFormat => write!(f, "数量格式无效"),
+            UiAmountError::PrecisionOverflow => write!(f, "小数位数超过精度"),
+            UiAmountError::AmountOverflow => write!(f, "数量超出u64范围"),
+        }
+    }
+}
+
+impl std::error::Error for UiAmountError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UiAmount {
+    pub amount: u64, // 最小单位的数量
+    pub decimals: u8,
+}
+
+impl UiAmount {
+    pub fn new(amount: u64, decimals: u8) -> Self {
+        Self { amount, decimals }
+    }
+
+    // 按给定精度解析显示用的字符串，末尾多余的0不算超出精度（"1.50"在decimals=1时是合法的）
+    pub fn parse(s: &str, decimals: u8) -> Result<Self, UiAmountError> {
+        let (integer, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if integer.is_empty() || !is_digits(integer) || !is_digits(fraction) || (s.contains('.') && fraction.is_empty()) {
+            return Err(UiAmountError::InvalidFormat);
+        }
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.len() > decimals as usize {
+            return Err(UiAmountError::PrecisionOverflow);
+        }
+
+        // 小数部分补0到decimals位后和整数部分拼接，就是最小单位的数量
+        let mut amount: u64 = 0;
+        let padding = decimals as usize - fraction.len();
+        for digit in integer.bytes().chain(fraction.bytes()).chain(std::iter::repeat_n(b'0', padding)) {
+            amount = amount
+                .checked_mul(10)
+                .and_then(|amount| amount.checked_add((digit - b'0') as u64))
+                .ok_or(UiAmountError::AmountOverflow)?;
+        }
+        Ok(Self { amount, decimals })
+    }
+}
+
+// 用字符串切分而不是浮点运算，任何精度下都不会丢失数位
+impl fmt::Display for UiAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decimals = self.decimals as usize;
+        let digits = format!("{:0>width$}", self.amount, width = decimals + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - decimals);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            write!(f, "{}", integer)
+        } else {
+            write!(f, "{}.{}", integer, fraction)
+        }
+    }
+}
+
+// 不带精度时按0位小数解析
+impl FromStr for UiAmount {
+    type Err = UiAmountError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(UiAmount::new(1_500_000, 6).to_string(), "1.5");
+        assert_eq!(UiAmount::new(1_000_000, 6).to_string(), "1");
+        assert_eq!(UiAmount::new(1, 6).to_string(), "0.000001");
+        assert_eq!(UiAmount::new(0, 6).to_string(), "0");
+        assert_eq!(UiAmount::new(42, 0).to_string(), "42");
+        // 精度超过u64的位数也不会出错
+        assert_eq!(UiAmount::new(u64::MAX, 20).to_string(), "0.18446744073709551615");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(UiAmount::parse("1.5", 6), Ok(UiAmount::new(1_500_000, 6)));
+        assert_eq!(UiAmount::parse("0.000001", 6), Ok(UiAmount::new(1, 6)));
+        assert_eq!(UiAmount::parse("1.50", 1), Ok(UiAmount::new(15, 1)));
+        assert_eq!("7".parse(), Ok(UiAmount::new(7, 0)));
+
+        assert_eq!(UiAmount::parse("0.0000001", 6), Err(UiAmountError::PrecisionOverflow));
+        assert_eq!(UiAmount::parse("1.5", 0), Err(UiAmountError::PrecisionOverflow));
+        assert_eq!(UiAmount::parse("18446744073709.551616", 6), Err(UiAmountError::AmountOverflow));
+        for invalid in ["", ".5", "1.", "-1", "1.2.3", "1,5", " 1"] {
+            assert_eq!(UiAmount::parse(invalid, 6), Err(UiAmountError::InvalidFormat), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for (amount, decimals) in [(1_500_000, 6), (u64::MAX, 9), (0, 2), (10, 1)] {
+            let ui = UiAmount::new(amount, decimals);
+            assert_eq!(UiAmount::parse(&ui.to_string(), decimals), Ok(ui));
+        }
+    }
+}
@@ -0,0 +1,246 @@
+// 校验器 - 把一组规则依次跑在TokenAccount或Transaction上，收集所有不满足的规则一起返回，
+// 而不是遇到第一个错误就停下。规则可以用check/ensure逐条添加，也可以用and/when把几个校验器组合起来
+
+use std::fmt;
+
+use crate::transaction::Transaction;
+use crate::{Pubkey, TokenAccount};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    AccountFrozen,                                                 // 账户已被冻结
+    DelegatedAmountExceedsBalance { delegated: u64, amount: u64 }, // 授权额度超过余额
+    DelegatedAmountWithoutDelegate(u64),                           // 没有被授权人却有授权额度
+    WrongMint { expected: Pubkey, actual: Pubkey },                // 不是指定的mint
+    WrongOwner { expected: Pubkey, actual: Pubkey },               // 不属于指定的owner
+    InsufficientBalance { required: u64, available: u64 },         // 余额低于要求
+    NoInstructions,                                                // 交易里没有指令
+    TooManyInstructions { count: usize, max: usize },              // 指令数量超过上限
+    MissingSignature(Pubkey),                                      // 缺少某个签名者的有效签名
+    Custom(String),                                                // 调用方自定义的规则
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::AccountFrozen => write!(f, "账户已被冻结"),
+            ValidationError::DelegatedAmountExceedsBalance { delegated, amount } => {
+                write!(f, "授权额度{}超过余额{}", delegated, amount)
+            }
+            ValidationError::DelegatedAmountWithoutDelegate(amount) => write!(f, "没有被授权人，授权额度却是{}", amount),
+            ValidationError::WrongMint { expected, actual } => write!(f, "mint应为{}，实际为{}", expected, actual),
+            ValidationError::WrongOwner { expected, actual } => write!(f, "owner应为{}，实际为{}", expected, actual),
+            ValidationError::InsufficientBalance { required, available } => {
+                write!(f, "余额不足: 需要{}，只有{}", required, available)
+            }
+            ValidationError::NoInstructions => write!(f, "交易没有指令"),
+            ValidationError::TooManyInstructions { count, max } => write!(f, "指令数量{}超过上限{}", count, max),
+            ValidationError::MissingSignature(key) => write!(f, "缺少签名: {}", key),
+            ValidationError::Custom(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+// 每条规则把发现的问题追加到errors里，一条规则可以报告多个问题（比如缺少多个签名）
+type Rule<T> = Box<dyn Fn(&T, &mut Vec<ValidationError>)>;
+
+pub struct Validator<T> {
+    rules: Vec<Rule<T>>,
+}
+
+impl<T> Default for Validator<T> {
+    fn default() -> Self {
+        Self { rules: Vec::new() }
+    }
+}
+
+impl<T: 'static> Validator<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn check<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&T) -> Result<(), ValidationError> + 'static,
+    {
+        self.rules.push(Box::new(move |value, errors| {
+            if let Err(error) = check(value) {
+                errors.push(error);
+            }
+        }));
+        self
+    }
+
+    // predicate不成立时报告error
+    pub fn ensure<F>(self, predicate: F, error: ValidationError) -> Self
+    where
+        F: Fn(&T) -> bool + 'static,
+    {
+        self.check(move |value| if predicate(value) { Ok(()) } else { Err(error.clone()) })
+    }
+
+    // 追加另一个校验器的所有规则
+    pub fn and(mut self, other: Validator<T>) -> Self {
+        self.rules.extend(other.rules);
+        self
+    }
+
+    // 只有condition成立时才运行then里的规则
+    pub fn when<F>(mut self, condition: F, then: Validator<T>) -> Self
+    where
+        F: Fn(&T) -> bool + 'static,
+    {
+        self.rules.push(Box::new(move |value, errors| {
+            if condition(value) {
+                then.collect(value, errors);
+            }
+        }));
+        self
+    }
+
+    fn collect(&self, value: &T, errors: &mut Vec<ValidationError>) {
+        for rule in &self.rules {
+            rule(value, errors);
+        }
+    }
+
+    // 运行所有规则，按添加顺序返回全部问题
+    pub fn validate(&self, value: &T) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        self.collect(value, &mut errors);
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+impl Validator<TokenAccount> {
+    // Token账户的基本一致性：未冻结，授权额度和被授权人对得上且不超过余额
+    pub fn token_account() -> Self {
+        Self::new()
+            .ensure(|account| !account.is_frozen(), ValidationError::AccountFrozen)
+            .check(|account| match account.delegate {
+                None if account.delegated_amount > 0 => {
+                    Err(ValidationError::DelegatedAmountWithoutDelegate(account.delegated_amount))
+                }
+                _ => Ok(()),
+            })
+            .check(|account| {
+                if account.delegated_amount > account.amount {
+                    Err(ValidationError::DelegatedAmountExceedsBalance { delegated: account.delegated_amount, amount: account.amount })
+                } else {
+                    Ok(())
+                }
+            })
+    }
+
+    pub fn mint(self, expected: Pubkey) -> Self {
+        self.check(move |account| {
+            if account.mint == expected { Ok(()) } else { Err(ValidationError::WrongMint { expected, actual: account.mint }) }
+        })
+    }
+
+    pub fn owner(self, expected: Pubkey) -> Self {
+        self.check(move |account| {
+            if account.owner == expected { Ok(()) } else { Err(ValidationError::WrongOwner { expected, actual: account.owner }) }
+        })
+    }
+
+    pub fn min_balance(self, required: u64) -> Self {
+        self.check(move |account| {
+            if account.amount >= required {
+                Ok(())
+            } else {
+                Err(ValidationError::InsufficientBalance { required, available: account.amount })
+            }
+        })
+    }
+}
+
+impl Validator<Transaction> {
+    // 交易的基本要求：至少一条指令，每个签名者都提供了有效签名
+    pub fn transaction() -> Self {
+        let mut validator = Self::new().ensure(|tx| !tx.instructions.is_empty(), ValidationError::NoInstructions);
+        validator.rules.push(Box::new(|tx: &Transaction, errors| {
+            errors.extend(tx.missing_signers().into_iter().map(ValidationError::MissingSignature));
+        }));
+        validator
+    }
+
+    pub fn max_instructions(self, max: usize) -> Self {
+        self.check(move |tx| {
+            let count = tx.instructions.len();
+            if count <= max { Ok(()) } else { Err(ValidationError::TooManyInstructions { count, max }) }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::{Keypair, Signer};
+    use crate::transaction::Instruction;
+    use crate::{AccountMeta, AccountState, ProgramInstruction, TOKEN_PROGRAM_ID};
+
+    #[test]
+    fn test_token_account_collects_all_errors() {
+        let (mint, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut account = TokenAccount::new(mint, owner, 50);
+        let validator = Validator::token_account().mint(mint).owner(owner).min_balance(10);
+        assert_eq!(validator.validate(&account), Ok(()));
+
+        account.delegated_amount = 80;
+        account.state = AccountState::Frozen;
+        let other = Pubkey::new_unique();
+        let errors = Validator::token_account().owner(other).min_balance(100).validate(&account).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                ValidationError::AccountFrozen,
+                ValidationError::DelegatedAmountWithoutDelegate(80),
+                ValidationError::DelegatedAmountExceedsBalance { delegated: 80, amount: 50 },
+                ValidationError::WrongOwner { expected: other, actual: owner },
+                ValidationError::InsufficientBalance { required: 100, available: 50 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_when_and_custom_rules() {
+        let account = TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 0);
+        let empty_not_allowed = Validator::new().ensure(|account: &TokenAccount| account.amount > 0, ValidationError::Custom("余额为零".to_string()));
+
+        let only_delegated = Validator::new().when(|account: &TokenAccount| account.delegate.is_some(), empty_not_allowed);
+        assert_eq!(only_delegated.validate(&account), Ok(()));
+
+        let mut delegated = account.clone();
+        delegated.approve(Pubkey::new_unique(), 0);
+        assert_eq!(only_delegated.validate(&delegated), Err(vec![ValidationError::Custom("余额为零".to_string())]));
+        assert_eq!(ValidationError::Custom("余额为零".to_string()).to_string(), "余额为零");
+    }
+
+    #[test]
+    fn test_transaction_reports_every_missing_signer() {
+        let (payer, authority) = (Keypair::new(), Keypair::new());
+        let transfer = Instruction::new(TOKEN_PROGRAM_ID, ProgramInstruction::Transfer { amount: 1 }, vec![
+            AccountMeta::new(Pubkey::new_unique(), TOKEN_PROGRAM_ID, false),
+            AccountMeta::new(Pubkey::new_unique(), TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(authority.pubkey(), TOKEN_PROGRAM_ID, true),
+        ]);
+        let mut tx = Transaction::new(payer.pubkey(), vec![transfer.clone(), transfer]);
+        let validator = Validator::transaction().max_instructions(1);
+        assert_eq!(
+            validator.validate(&tx),
+            Err(vec![
+                ValidationError::MissingSignature(payer.pubkey()),
+                ValidationError::MissingSignature(authority.pubkey()),
+                ValidationError::TooManyInstructions { count: 2, max: 1 },
+            ])
+        );
+
+        tx.sign(&[&payer, &authority]).unwrap();
+        assert_eq!(Validator::transaction().validate(&tx), Ok(()));
+        let empty = Transaction::new(payer.pubkey(), vec![]);
+        assert_eq!(Validator::transaction().validate(&empty).unwrap_err()[0], ValidationError::NoInstructions);
+    }
+}
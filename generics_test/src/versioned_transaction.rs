@@ -0,0 +1,510 @@
+// 带版本的交易格式 - 类似于Solana的VersionedTransaction
+// 序列化格式：shortvec签名数量 + 每个64字节签名 + 消息。
+// legacy消息第一个字节是签名者数量，一定小于0x80；V0消息前面多一个版本前缀字节0x80 | 版本号，
+// 解码时看消息第一个字节的最高位就能区分两种格式。
+// V0消息在legacy消息之后还带有地址查找表：指令中的非签名账户可以用(查找表, 下标)代替pubkey，
+// 执行前先到账本中读取查找表，把这些引用还原成完整的账户列表（sanitization）
+
+use std::fmt;
+
+use crate::borsh::{take, BorshDeserialize, BorshError, BorshSerialize};
+use crate::cpi::Programs;
+use crate::hash::Hash;
+use crate::ledger::{AccountPayload, Ledger};
+use crate::logs::Logs;
+use crate::lookup_table::AddressLookupTable;
+use crate::shortvec::{self, ShortVecError};
+use crate::signature::{Signature, Signer, SignerError, SIGNATURE_BYTES};
+use crate::transaction::{Instruction, Transaction, TransactionError, TransactionOutcome};
+use crate::{AccountMeta, ProgramInstruction, Pubkey, SYSTEM_PROGRAM_ID};
+
+// 最高位为1表示带版本的消息，低7位是版本号
+pub const VERSION_PREFIX_MASK: u8 = 0x80;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionDecodeError {
+    ShortVec(ShortVecError),                      // 签名数量编码错误
+    Borsh(BorshError),                            // 消息内容无法解析
+    UnsupportedVersion(u8),                       // 不认识的消息版本
+    InvalidHeader { header: u8, signers: usize }, // 消息头的签名者数量和指令中的签名者不一致
+}
+
+impl fmt::Display for TransactionDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionDecodeError::ShortVec(e) => write!(f, "签名数量解析失败: {}", e),
+            TransactionDecodeError::Borsh(e) => write!(f, "消息解析失败: {}", e),
+            TransactionDecodeError::UnsupportedVersion(version) => write!(f, "不支持的消息版本: v{}", version),
+            TransactionDecodeError::InvalidHeader { header, signers } => {
+                write!(f, "消息头声明{}个签名者，实际需要{}个", header, signers)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TransactionDecodeError {}
+
+impl From<ShortVecError> for TransactionDecodeError {
+    fn from(e: ShortVecError) -> Self {
+        TransactionDecodeError::ShortVec(e)
+    }
+}
+
+impl From<BorshError> for TransactionDecodeError {
+    fn from(e: BorshError) -> Self {
+        TransactionDecodeError::Borsh(e)
+    }
+}
+
+// 通过查找表引用的账户：还原后插入到第instruction条指令账户列表的position位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountLookup {
+    pub instruction: u8,
+    pub position: u8,
+    pub table: u8, // address_tables中的下标
+    pub index: u8, // 查找表中的下标
+    pub is_writable: bool,
+}
+
+impl BorshSerialize for AccountLookup {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.instruction.serialize(out);
+        self.position.serialize(out);
+        self.table.serialize(out);
+        self.index.serialize(out);
+        self.is_writable.serialize(out);
+    }
+}
+
+impl BorshDeserialize for AccountLookup {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            instruction: u8::deserialize(buf)?,
+            position: u8::deserialize(buf)?,
+            table: u8::deserialize(buf)?,
+            index: u8::deserialize(buf)?,
+            is_writable: bool::deserialize(buf)?,
+        })
+    }
+}
+
+// V0交易：签名的消息带有版本前缀，因此同一份签名不能在两种格式之间挪用。
+// transaction中的指令只保留直接写出pubkey的账户，其余账户记录在lookups中
+#[derive(Debug)]
+pub struct TransactionV0 {
+    pub transaction: Transaction,
+    pub address_tables: Vec<Pubkey>,
+    pub lookups: Vec<AccountLookup>, // 按(instruction, position)排序
+}
+
+impl TransactionV0 {
+    pub const VERSION: u8 = 0;
+
+    pub fn new(fee_payer: Pubkey, instructions: Vec<Instruction>) -> Self {
+        Self { transaction: Transaction::new(fee_payer, instructions), address_tables: Vec::new(), lookups: Vec::new() }
+    }
+
+    // 把指令中出现在查找表里的非签名账户换成查找表引用；签名者必须直接写出pubkey。
+    // 和compute unit上限一样需要在签名前调用，并且只能调用一次
+    pub fn with_address_lookup_tables(mut self, tables: &[(Pubkey, &AddressLookupTable)]) -> Self {
+        self.address_tables = tables.iter().map(|(key, _)| *key).collect();
+        for (i, instruction) in self.transaction.instructions.iter_mut().enumerate() {
+            let mut remaining = Vec::new();
+            for (position, meta) in instruction.accounts.iter().enumerate() {
+                let found = tables.iter().enumerate().find_map(|(table, (_, lookup_table))| {
+                    lookup_table.index_of(&meta.pubkey).map(|index| (table as u8, index))
+                });
+                match found {
+                    Some((table, index)) if !meta.is_signer => self.lookups.push(AccountLookup {
+                        instruction: i as u8,
+                        position: position as u8,
+                        table,
+                        index,
+                        is_writable: meta.is_writable,
+                    }),
+                    _ => remaining.push(*meta),
+                }
+            }
+            instruction.accounts = remaining;
+        }
+        self
+    }
+
+    // 前缀 + legacy消息 + 查找表地址 + 查找表引用
+    pub fn message_data(&self) -> Vec<u8> {
+        let mut out = vec![VERSION_PREFIX_MASK | Self::VERSION];
+        out.extend(self.transaction.message_data());
+        self.address_tables.serialize(&mut out);
+        self.lookups.serialize(&mut out);
+        out
+    }
+
+    // 从账本读取查找表，还原出带完整账户列表的交易；查找表不会引入新的签名者，签名原样保留
+    pub fn resolve(&self, ledger: &Ledger) -> Result<Transaction, TransactionError> {
+        let mut instructions = self.transaction.instructions.clone();
+        for lookup in &self.lookups {
+            let table_key = self
+                .address_tables
+                .get(lookup.table as usize)
+                .ok_or(TransactionError::InvalidAddressLookupTableIndex)?;
+            let table = match ledger.get_account(table_key).map(|account| &account.data) {
+                Some(AccountPayload::LookupTable(table)) => table,
+                _ => return Err(TransactionError::AddressLookupTableNotFound),
+            };
+            let pubkey = table.get(lookup.index).ok_or(TransactionError::InvalidAddressLookupTableIndex)?;
+            let accounts = match instructions.get_mut(lookup.instruction as usize) {
+                Some(instruction) if lookup.position as usize <= instruction.accounts.len() => &mut instruction.accounts,
+                _ => return Err(TransactionError::InvalidAddressLookupTableIndex),
+            };
+            let meta = AccountMeta { pubkey, owner: SYSTEM_PROGRAM_ID, is_signer: false, is_writable: lookup.is_writable };
+            accounts.insert(lookup.position as usize, meta);
+        }
+        Ok(Transaction {
+            fee_payer: self.transaction.fee_payer,
+            instructions,
+            signatures: self.transaction.signatures.clone(),
+            compute_unit_limit: self.transaction.compute_unit_limit,
+            recent_blockhash: self.transaction.recent_blockhash,
+        })
+    }
+
+    pub fn sign(&mut self, signers: &[&dyn Signer]) -> Result<(), SignerError> {
+        let message = self.message_data();
+        self.transaction.sign_message(signers, &message)
+    }
+
+    pub fn verify_signatures(&self) -> Result<(), TransactionError> {
+        self.transaction.verify_message(&self.message_data())
+    }
+
+    pub fn execute(&self, ledger: &mut Ledger) -> Result<(), TransactionError> {
+        self.execute_with_programs(ledger, &Programs::new()).result
+    }
+
+    // 查找表解析失败时交易不会执行，也不收交易费
+    pub fn execute_with_programs(&self, ledger: &mut Ledger, programs: &Programs) -> TransactionOutcome {
+        match self.resolve(ledger) {
+            Ok(resolved) => resolved.execute_message(ledger, programs, &self.message_data()),
+            Err(e) => TransactionOutcome { result: Err(e), logs: Logs::new(), compute_units_consumed: 0 },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum VersionedTransaction {
+    Legacy(Transaction),
+    V0(TransactionV0),
+}
+
+impl VersionedTransaction {
+    // legacy交易没有版本号
+    pub fn version(&self) -> Option<u8> {
+        match self {
+            VersionedTransaction::Legacy(_) => None,
+            VersionedTransaction::V0(_) => Some(TransactionV0::VERSION),
+        }
+    }
+
+    pub fn transaction(&self) -> &Transaction {
+        match self {
+            VersionedTransaction::Legacy(tx) => tx,
+            VersionedTransaction::V0(tx) => &tx.transaction,
+        }
+    }
+
+    pub fn message_data(&self) -> Vec<u8> {
+        match self {
+            VersionedTransaction::Legacy(tx) => tx.message_data(),
+            VersionedTransaction::V0(tx) => tx.message_data(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let signatures = &self.transaction().signatures;
+        let mut out = Vec::new();
+        shortvec::encode_len(signatures.len() as u16, &mut out);
+        for signature in signatures {
+            out.extend_from_slice(&signature.to_bytes());
+        }
+        out.extend(self.message_data());
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, TransactionDecodeError> {
+        let (count, offset) = shortvec::decode_len(data)?;
+        let mut buf = &data[offset..];
+        let mut signatures = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let bytes: [u8; SIGNATURE_BYTES] = take(&mut buf, SIGNATURE_BYTES)?.try_into().unwrap();
+            signatures.push(Signature::new_from_array(bytes));
+        }
+
+        // 根据消息第一个字节判断格式
+        let first = *buf.first().ok_or(BorshError::UnexpectedEof { needed: 1, remaining: 0 })?;
+        let tx = if first & VERSION_PREFIX_MASK == 0 {
+            let mut tx = decode_message(&mut buf)?;
+            tx.signatures = signatures;
+            VersionedTransaction::Legacy(tx)
+        } else {
+            match first & !VERSION_PREFIX_MASK {
+                TransactionV0::VERSION => {
+                    buf = &buf[1..];
+                    let mut transaction = decode_message(&mut buf)?;
+                    transaction.signatures = signatures;
+                    let address_tables = Vec::deserialize(&mut buf)?;
+                    let lookups = Vec::deserialize(&mut buf)?;
+                    VersionedTransaction::V0(TransactionV0 { transaction, address_tables, lookups })
+                }
+                version => return Err(TransactionDecodeError::UnsupportedVersion(version)),
+            }
+        };
+        if !buf.is_empty() {
+            return Err(BorshError::TrailingBytes(buf.len()).into());
+        }
+        Ok(tx)
+    }
+
+    pub fn execute(&self, ledger: &mut Ledger) -> Result<(), TransactionError> {
+        match self {
+            VersionedTransaction::Legacy(tx) => tx.execute(ledger),
+            VersionedTransaction::V0(tx) => tx.execute(ledger),
+        }
+    }
+}
+
+// 从buf开头解析Transaction::message_data()的格式。消息里没有账户的owner，
+// 解码后先填System Program，执行时运行时会从账本读取真实的owner
+fn decode_message(buf: &mut &[u8]) -> Result<Transaction, TransactionDecodeError> {
+    let header = u8::deserialize(buf)?;
+    let fee_payer = Pubkey::deserialize(buf)?;
+    let compute_unit_limit = u64::deserialize(buf)?;
+    let recent_blockhash: Hash = take(buf, 32)?.try_into().unwrap();
+    let count = u32::deserialize(buf)?;
+    let mut instructions = Vec::new();
+    for _ in 0..count {
+        let program_id = Pubkey::deserialize(buf)?;
+        let account_count = u32::deserialize(buf)?;
+        let mut accounts = Vec::new();
+        for _ in 0..account_count {
+            let pubkey = Pubkey::deserialize(buf)?;
+            let is_signer = bool::deserialize(buf)?;
+            let is_writable = bool::deserialize(buf)?;
+            accounts.push(AccountMeta { pubkey, owner: SYSTEM_PROGRAM_ID, is_signer, is_writable });
+        }
+        let data = Vec::<u8>::deserialize(buf)?;
+        instructions.push(Instruction::new(program_id, ProgramInstruction::try_from_bytes(&data)?, accounts));
+    }
+
+    let tx = Transaction::new(fee_payer, instructions)
+        .with_compute_unit_limit(compute_unit_limit)
+        .with_recent_blockhash(recent_blockhash);
+    let signers = tx.num_required_signatures();
+    if header as usize != signers {
+        return Err(TransactionDecodeError::InvalidHeader { header, signers });
+    }
+    Ok(tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::Account;
+    use crate::mint::Mint;
+    use crate::signature::Keypair;
+    use crate::{InstructionBuilder, TokenAccount};
+
+    fn instructions(authority: Pubkey) -> Vec<Instruction> {
+        let accounts = vec![
+            AccountMeta::new(Pubkey::new_unique(), SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new_readonly(authority, SYSTEM_PROGRAM_ID, true),
+        ];
+        vec![
+            Instruction::new(Pubkey::new_unique(), ProgramInstruction::Mint { amount: 7 }, accounts.clone()),
+            Instruction::new(Pubkey::new_unique(), ProgramInstruction::Transfer { amount: 3 }, accounts),
+        ]
+    }
+
+    #[test]
+    fn test_roundtrip_both_versions() {
+        let payer = Keypair::new();
+        let authority = Keypair::new();
+
+        let mut legacy = Transaction::new(payer.pubkey(), instructions(authority.pubkey()))
+            .with_compute_unit_limit(5_000)
+            .with_recent_blockhash([9; 32]);
+        legacy.sign(&[&payer, &authority]).unwrap();
+        let legacy = VersionedTransaction::Legacy(legacy);
+
+        let mut v0 = TransactionV0::new(payer.pubkey(), instructions(authority.pubkey()));
+        v0.sign(&[&payer, &authority]).unwrap();
+        let v0 = VersionedTransaction::V0(v0);
+
+        for tx in [legacy, v0] {
+            let bytes = tx.to_bytes();
+            let decoded = VersionedTransaction::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded.version(), tx.version());
+            assert_eq!(decoded.to_bytes(), bytes);
+            assert_eq!(decoded.transaction().signatures, tx.transaction().signatures);
+            assert_eq!(decoded.transaction().compute_unit_limit, tx.transaction().compute_unit_limit);
+            assert_eq!(decoded.transaction().recent_blockhash, tx.transaction().recent_blockhash);
+        }
+    }
+
+    #[test]
+    fn test_version_prefix() {
+        let payer = Keypair::new();
+        let mut v0 = TransactionV0::new(payer.pubkey(), Vec::new());
+        let legacy_message = v0.transaction.message_data();
+        assert_eq!(legacy_message[0], 1); // 只有fee payer一个签名者
+        assert_eq!(v0.message_data()[0], 0x80);
+        // 之后是legacy消息，最后是两个空列表：查找表地址和查找表引用
+        let message = v0.message_data();
+        assert_eq!(&message[1..=legacy_message.len()], &legacy_message[..]);
+        assert_eq!(&message[1 + legacy_message.len()..], &[0; 8]);
+
+        // V0签名不能当作legacy签名使用
+        v0.sign(&[&payer]).unwrap();
+        assert_eq!(v0.verify_signatures(), Ok(()));
+        assert_eq!(v0.transaction.verify_signatures(), Err(TransactionError::SignatureFailure));
+    }
+
+    struct LookupFixture {
+        ledger: Ledger,
+        payer: Keypair, // 同时是mint authority
+        program: Pubkey,
+        token: Pubkey,
+        mint: Pubkey,
+        table_key: Pubkey,
+    }
+
+    // 账本中有一张查找表，表里是由program拥有的Token账户和它的mint；payer有lamports支付交易费
+    fn lookup_fixture() -> LookupFixture {
+        let mut ledger = Ledger::default();
+        let payer = Keypair::new();
+        let (program, token, mint, table_key) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let rent = *ledger.rent();
+        let mint_data = AccountPayload::Mint(Mint::new(0, payer.pubkey()));
+        ledger.create_account(mint, Account::new(rent.minimum_balance(Mint::LEN), program, mint_data)).unwrap();
+        let data = AccountPayload::Token(TokenAccount::new(mint, payer.pubkey(), 0));
+        ledger.create_account(token, Account::new(rent.minimum_balance(TokenAccount::LEN), program, data)).unwrap();
+        let fee_lamports = rent.minimum_balance(0) * 10;
+        ledger
+            .create_account(payer.pubkey(), Account::new(fee_lamports, SYSTEM_PROGRAM_ID, AccountPayload::Empty))
+            .unwrap();
+        let mut table = AddressLookupTable::new(payer.pubkey());
+        table.extend(&[Pubkey::new_unique(), token, mint]).unwrap();
+        let table_lamports = rent.minimum_balance(table.data_len());
+        ledger
+            .create_account(table_key, Account::new(table_lamports, SYSTEM_PROGRAM_ID, AccountPayload::LookupTable(table)))
+            .unwrap();
+        LookupFixture { ledger, payer, program, token, mint, table_key }
+    }
+
+    impl LookupFixture {
+        fn table(&self) -> AddressLookupTable {
+            match &self.ledger.get_account(&self.table_key).unwrap().data {
+                AccountPayload::LookupTable(table) => table.clone(),
+                _ => unreachable!(),
+            }
+        }
+
+        fn token_amount(&self) -> u64 {
+            match &self.ledger.get_account(&self.token).unwrap().data {
+                AccountPayload::Token(token) => token.amount,
+                _ => unreachable!(),
+            }
+        }
+
+        // 通过查找表引用token和mint的铸造交易
+        fn mint_to(&self, amount: u64) -> TransactionV0 {
+            let instruction = InstructionBuilder::mint_to()
+                .program(self.program)
+                .amount(amount)
+                .to(self.token)
+                .mint(self.mint)
+                .signer(self.payer.pubkey())
+                .build()
+                .unwrap();
+            TransactionV0::new(self.payer.pubkey(), vec![instruction])
+                .with_address_lookup_tables(&[(self.table_key, &self.table())])
+        }
+    }
+
+    #[test]
+    fn test_lookup_table_accounts() {
+        let mut f = lookup_fixture();
+        let mut tx = f.mint_to(9);
+
+        // token和mint换成了查找表引用，签名者仍然直接写出
+        assert_eq!(
+            tx.lookups,
+            [
+                AccountLookup { instruction: 0, position: 0, table: 0, index: 1, is_writable: true },
+                AccountLookup { instruction: 0, position: 1, table: 0, index: 2, is_writable: true },
+            ]
+        );
+        assert_eq!(tx.transaction.instructions[0].accounts.len(), 1);
+        let resolved = tx.resolve(&f.ledger).unwrap();
+        let keys: Vec<Pubkey> = resolved.instructions[0].accounts.iter().map(|meta| meta.pubkey).collect();
+        assert_eq!(keys, [f.token, f.mint, f.payer.pubkey()]);
+
+        tx.sign(&[&f.payer]).unwrap();
+        let bytes = VersionedTransaction::V0(tx).to_bytes();
+        let decoded = VersionedTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(decoded.execute(&mut f.ledger), Ok(()));
+        assert_eq!(f.token_amount(), 9);
+    }
+
+    #[test]
+    fn test_lookup_resolution_errors() {
+        let mut f = lookup_fixture();
+
+        let mut out_of_range = f.mint_to(1);
+        out_of_range.lookups[0].index = 3;
+        out_of_range.sign(&[&f.payer]).unwrap();
+        assert_eq!(out_of_range.resolve(&f.ledger).unwrap_err(), TransactionError::InvalidAddressLookupTableIndex);
+
+        // 查找表账户被换成了普通账户
+        let mut missing = f.mint_to(1);
+        missing.address_tables[0] = f.payer.pubkey();
+        missing.sign(&[&f.payer]).unwrap();
+        let balance = f.ledger.get_account(&f.payer.pubkey()).unwrap().lamports;
+        assert_eq!(missing.execute(&mut f.ledger), Err(TransactionError::AddressLookupTableNotFound));
+        assert_eq!(f.ledger.get_account(&f.payer.pubkey()).unwrap().lamports, balance);
+        assert_eq!(f.token_amount(), 0);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let payer = Keypair::new();
+        let mut tx = TransactionV0::new(payer.pubkey(), Vec::new());
+        tx.sign(&[&payer]).unwrap();
+        let bytes = VersionedTransaction::V0(tx).to_bytes();
+        let message_start = 1 + SIGNATURE_BYTES;
+
+        let mut unknown = bytes.clone();
+        unknown[message_start] = 0x85;
+        assert!(matches!(
+            VersionedTransaction::from_bytes(&unknown),
+            Err(TransactionDecodeError::UnsupportedVersion(5))
+        ));
+
+        let mut bad_header = bytes.clone();
+        bad_header[message_start + 1] = 2;
+        assert!(matches!(
+            VersionedTransaction::from_bytes(&bad_header),
+            Err(TransactionDecodeError::InvalidHeader { header: 2, signers: 1 })
+        ));
+
+        assert!(matches!(
+            VersionedTransaction::from_bytes(&bytes[..message_start]),
+            Err(TransactionDecodeError::Borsh(BorshError::UnexpectedEof { .. }))
+        ));
+        assert!(matches!(
+            VersionedTransaction::from_bytes(&[0x80]),
+            Err(TransactionDecodeError::ShortVec(ShortVecError::UnexpectedEof))
+        ));
+    }
+}
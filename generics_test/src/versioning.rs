@@ -0,0 +1,144 @@
+// 账户数据版本化与迁移
+// 链上账户的数据布局一旦上线就很难修改，常见做法是在数据前面加一个版本字节，
+// 读取时根据版本把旧布局迁移到新布局
+
+use std::fmt;
+
+use crate::{Pubkey, TokenAccount};
+
+// 每个数据布局都有一个固定的版本号
+pub trait AccountVersion {
+    const VERSION: u8;
+}
+
+// 可以从上一个版本迁移过来的布局
+pub trait Migratable: AccountVersion + Sized {
+    type Previous: AccountVersion;
+
+    fn migrate(previous: Self::Previous) -> Self;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionMismatch {
+    pub expected: u8,
+    pub found: u8,
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "账户数据版本不匹配: 期望v{}，实际v{}", self.expected, self.found)
+    }
+}
+
+impl std::error::Error for VersionMismatch {}
+
+// 带版本字节的账户数据
+#[derive(Debug, Clone, PartialEq)]
+pub struct Versioned<T> {
+    version: u8,
+    data: T,
+}
+
+impl<T: AccountVersion> Versioned<T> {
+    pub fn new(data: T) -> Self {
+        Self { version: T::VERSION, data }
+    }
+
+    // 从存储中读出(版本, 数据)时使用，版本必须与T的布局一致
+    pub fn from_parts(version: u8, data: T) -> Result<Self, VersionMismatch> {
+        if version != T::VERSION {
+            return Err(VersionMismatch { expected: T::VERSION, found: version });
+        }
+        Ok(Self { version, data })
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+
+    // 升级到下一个版本，类型系统保证只能按顺序一步步迁移
+    pub fn upgrade<U: Migratable<Previous = T>>(self) -> Versioned<U> {
+        Versioned::new(U::migrate(self.data))
+    }
+}
+
+// ===============================
+// 示例：TokenAccount从v1迁移到v2
+// ===============================
+
+// 现有的TokenAccount就是v1布局（没有decimals）
+impl AccountVersion for TokenAccount {
+    const VERSION: u8 = 1;
+}
+
+// v2布局增加了decimals字段
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenAccountV2 {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub decimals: u8,
+}
+
+impl AccountVersion for TokenAccountV2 {
+    const VERSION: u8 = 2;
+}
+
+impl Migratable for TokenAccountV2 {
+    type Previous = TokenAccount;
+
+    // v1中没有精度信息，迁移时先记为0（按原始数量显示），之后可以从mint读取真实值
+    fn migrate(previous: TokenAccount) -> Self {
+        Self {
+            mint: previous.mint,
+            owner: previous.owner,
+            amount: previous.amount,
+            decimals: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v1_account() -> TokenAccount {
+        TokenAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), 500)
+    }
+
+    #[test]
+    fn test_upgrade_v1_to_v2() {
+        let old = v1_account();
+        let versioned = Versioned::new(old.clone());
+        assert_eq!(versioned.version(), 1);
+
+        let upgraded: Versioned<TokenAccountV2> = versioned.upgrade();
+        assert_eq!(upgraded.version(), 2);
+        assert_eq!(
+            upgraded.into_inner(),
+            TokenAccountV2 {
+                mint: old.mint,
+                owner: old.owner,
+                amount: old.amount,
+                decimals: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_parts_checks_version() {
+        assert!(Versioned::from_parts(1, v1_account()).is_ok());
+        assert_eq!(
+            Versioned::from_parts(2, v1_account()),
+            Err(VersionMismatch { expected: 1, found: 2 })
+        );
+    }
+}
@@ -0,0 +1,353 @@
+// 代币锁仓释放 - 代币先存进由锁仓PDA控制的vault，从start_slot开始线性释放，到end_slot全部释放；
+// cliff_slot之前一个都不能领取，过了cliff可以一次领取从start开始累计的部分。
+// 受益人用Claim领取时，程序按当前slot（模拟的Clock）计算可领取数量，再通过invoke_signed从vault转出
+
+use std::fmt;
+
+use crate::borsh::{BorshDeserialize, BorshError, BorshSerialize};
+use crate::cpi::InvokeContext;
+use crate::ledger::AccountPayload;
+use crate::pda::derive_pda;
+use crate::transaction::Instruction;
+use crate::{AccountMeta, ProgramInstruction, Pubkey, TransactionResult};
+
+pub const VESTING_SEED: &[u8] = b"vesting";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VestingError {
+    ZeroAmount,
+    InvalidSchedule, // 必须满足start <= cliff <= end且start < end
+}
+
+impl fmt::Display for VestingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VestingError::ZeroAmount => write!(f, "锁仓数量不能为0"),
+            VestingError::InvalidSchedule => write!(f, "释放时间无效: 需要start <= cliff <= end且start < end"),
+        }
+    }
+}
+
+impl std::error::Error for VestingError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VestingSchedule {
+    pub beneficiary: Pubkey,
+    pub vault: Pubkey, // 保存锁仓代币的Token账户，authority是锁仓PDA
+    pub total_amount: u64,
+    pub released_amount: u64, // 已经领取的数量
+    pub start_slot: u64,
+    pub cliff_slot: u64,
+    pub end_slot: u64,
+    pub bump: u8,
+}
+
+impl VestingSchedule {
+    // 两个Pubkey(各32) + 五个u64 + bump(1)
+    pub const LEN: usize = 32 * 2 + 8 * 5 + 1;
+
+    pub fn new(
+        beneficiary: Pubkey,
+        vault: Pubkey,
+        total_amount: u64,
+        (start_slot, cliff_slot, end_slot): (u64, u64, u64),
+        bump: u8,
+    ) -> Result<Self, VestingError> {
+        if total_amount == 0 {
+            return Err(VestingError::ZeroAmount);
+        }
+        if !(start_slot <= cliff_slot && cliff_slot <= end_slot && start_slot < end_slot) {
+            return Err(VestingError::InvalidSchedule);
+        }
+        Ok(Self { beneficiary, vault, total_amount, released_amount: 0, start_slot, cliff_slot, end_slot, bump })
+    }
+
+    // 到slot为止累计释放的数量（包括已经领取的），中间按比例向下取整
+    pub fn vested_amount(&self, slot: u64) -> u64 {
+        if slot < self.cliff_slot {
+            return 0;
+        }
+        if slot >= self.end_slot {
+            return self.total_amount;
+        }
+        // 用u128计算，total_amount * elapsed不会溢出
+        let elapsed = (slot - self.start_slot) as u128;
+        let duration = (self.end_slot - self.start_slot) as u128;
+        (self.total_amount as u128 * elapsed / duration) as u64
+    }
+
+    pub fn claimable(&self, slot: u64) -> u64 {
+        self.vested_amount(slot).saturating_sub(self.released_amount)
+    }
+}
+
+impl BorshSerialize for VestingSchedule {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.beneficiary.serialize(out);
+        self.vault.serialize(out);
+        self.total_amount.serialize(out);
+        self.released_amount.serialize(out);
+        self.start_slot.serialize(out);
+        self.cliff_slot.serialize(out);
+        self.end_slot.serialize(out);
+        self.bump.serialize(out);
+    }
+}
+
+impl BorshDeserialize for VestingSchedule {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, BorshError> {
+        Ok(Self {
+            beneficiary: Pubkey::deserialize(buf)?,
+            vault: Pubkey::deserialize(buf)?,
+            total_amount: u64::deserialize(buf)?,
+            released_amount: u64::deserialize(buf)?,
+            start_slot: u64::deserialize(buf)?,
+            cliff_slot: u64::deserialize(buf)?,
+            end_slot: u64::deserialize(buf)?,
+            bump: u8::deserialize(buf)?,
+        })
+    }
+}
+
+// 锁仓账户地址：种子为["vesting", beneficiary, vault]
+pub fn vesting_address(beneficiary: &Pubkey, vault: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_pda(&[VESTING_SEED, beneficiary.as_ref(), vault.as_ref()], program_id)
+}
+
+// 锁仓程序的入口函数，注册到Programs后由运行时调用
+pub fn process(ctx: &mut InvokeContext<'_, '_>, instruction: &Instruction) -> Result<(), TransactionResult> {
+    let program_id = instruction.program_id;
+    let metas = &instruction.accounts;
+    match instruction.data {
+        ProgramInstruction::CreateVesting { amount, start_slot, cliff_slot, end_slot } => {
+            create(ctx, &program_id, metas, amount, (start_slot, cliff_slot, end_slot))
+        }
+        ProgramInstruction::Claim => claim(ctx, &program_id, metas),
+        _ => Err(TransactionResult::InvalidInstructionData),
+    }
+}
+
+// accounts: [锁仓账户(可写，空的PDA账户), 出资人(签名), 出资的Token账户(可写),
+//            受益人, vault(可写，authority是锁仓PDA)]
+fn create(
+    ctx: &mut InvokeContext<'_, '_>,
+    program_id: &Pubkey,
+    metas: &[AccountMeta],
+    amount: u64,
+    slots: (u64, u64, u64),
+) -> Result<(), TransactionResult> {
+    let [vesting_meta, funder, source, beneficiary, vault, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if !funder.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let (vesting_key, bump) = vesting_address(&beneficiary.pubkey, &vault.pubkey, program_id);
+    if vesting_meta.pubkey != vesting_key || !vesting_meta.is_writable {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    let schedule = VestingSchedule::new(beneficiary.pubkey, vault.pubkey, amount, slots, bump)
+        .map_err(|_| TransactionResult::InvalidInstructionData)?;
+    let mut account = ctx.staged().get_account(&vesting_key).cloned().ok_or(TransactionResult::InvalidAccount)?;
+    if account.owner != *program_id || account.data != AccountPayload::Empty {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if account.lamports < ctx.staged().rent().minimum_balance(VestingSchedule::LEN) {
+        return Err(TransactionResult::InsufficientFunds);
+    }
+    let (vault_token, token_program) = ctx.token_account(&vault.pubkey)?;
+    let (source_token, _) = ctx.token_account(&source.pubkey)?;
+    if vault_token.owner != vesting_key || vault_token.amount != 0 || vault_token.mint != source_token.mint {
+        return Err(TransactionResult::InvalidAccount);
+    }
+
+    let deposit = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(source.pubkey, token_program, false),
+            AccountMeta::new(vault.pubkey, token_program, false),
+            AccountMeta::new_readonly(funder.pubkey, funder.owner, true),
+        ],
+    );
+    ctx.invoke(&deposit, metas)?;
+    account.data = AccountPayload::Vesting(schedule);
+    Ok(ctx.staged().set_account(vesting_key, account)?)
+}
+
+// accounts: [锁仓账户(可写), 受益人(签名), vault(可写), 接收的Token账户(可写)]
+fn claim(ctx: &mut InvokeContext<'_, '_>, program_id: &Pubkey, metas: &[AccountMeta]) -> Result<(), TransactionResult> {
+    let [vesting_meta, beneficiary, vault, destination, ..] = metas else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    let mut account = match ctx.staged().get_account(&vesting_meta.pubkey) {
+        Some(account) if account.owner == *program_id => account.clone(),
+        _ => return Err(TransactionResult::InvalidAccount),
+    };
+    let AccountPayload::Vesting(schedule) = &mut account.data else {
+        return Err(TransactionResult::InvalidAccount);
+    };
+    if schedule.beneficiary != beneficiary.pubkey || schedule.vault != vault.pubkey {
+        return Err(TransactionResult::InvalidAccount);
+    }
+    if !beneficiary.is_signer {
+        return Err(TransactionResult::MissingRequiredSignature);
+    }
+    let amount = schedule.claimable(ctx.slot());
+    if amount == 0 {
+        return Err(TransactionResult::NothingToClaim);
+    }
+
+    let (_, token_program) = ctx.token_account(&vault.pubkey)?;
+    let bump = [schedule.bump];
+    let seeds: &[&[u8]] = &[VESTING_SEED, schedule.beneficiary.as_ref(), schedule.vault.as_ref(), &bump];
+    let release = Instruction::new(
+        token_program,
+        ProgramInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(vault.pubkey, token_program, false),
+            AccountMeta::new(destination.pubkey, token_program, false),
+            AccountMeta::new_readonly(vesting_meta.pubkey, *program_id, true),
+        ],
+    );
+    ctx.invoke_signed(&release, metas, &[seeds])?;
+    schedule.released_amount += amount;
+    Ok(ctx.staged().set_account(vesting_meta.pubkey, account)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpi::{Entrypoint, Programs};
+    use crate::ledger::{Account, Ledger};
+    use crate::signature::{Keypair, Signer};
+    use crate::test_utils::{create_token_account, funded_keypair, sign_and_execute, token_amount};
+    use crate::transaction::TransactionError;
+    use crate::SYSTEM_PROGRAM_ID;
+
+    struct Fixture {
+        ledger: Ledger,
+        programs: Programs,
+        vesting_program: Pubkey,
+        token_program: Pubkey,
+        funder: Keypair,
+        beneficiary: Keypair,
+        source: Pubkey,
+        destination: Pubkey,
+        vault: Pubkey,
+        vesting: Pubkey,
+    }
+
+    fn fixture() -> Fixture {
+        let mut ledger = Ledger::default();
+        let (vesting_program, token_program, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (funder, beneficiary) = (funded_keypair(&mut ledger), funded_keypair(&mut ledger));
+        let vault = Pubkey::new_unique();
+        let (vesting, _) = vesting_address(&beneficiary.pubkey(), &vault, &vesting_program);
+        let (source, destination) = (Pubkey::new_unique(), Pubkey::new_unique());
+        for (key, owner, amount) in [(source, funder.pubkey(), 1_000), (destination, beneficiary.pubkey(), 0), (vault, vesting, 0)] {
+            create_token_account(&mut ledger, token_program, key, mint, owner, amount);
+        }
+        let lamports = ledger.rent().minimum_balance(VestingSchedule::LEN);
+        ledger.create_account(vesting, Account::new(lamports, vesting_program, AccountPayload::Empty)).unwrap();
+        let programs = Programs::from([(vesting_program, process as Entrypoint)]);
+        Fixture { ledger, programs, vesting_program, token_program, funder, beneficiary, source, destination, vault, vesting }
+    }
+
+    impl Fixture {
+        // 出资人锁仓1000个代币给受益人
+        fn create(&mut self, start_slot: u64, cliff_slot: u64, end_slot: u64) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new(self.vesting, self.vesting_program, false),
+                AccountMeta::new_readonly(self.funder.pubkey(), SYSTEM_PROGRAM_ID, true),
+                AccountMeta::new(self.source, self.token_program, false),
+                AccountMeta::new_readonly(self.beneficiary.pubkey(), SYSTEM_PROGRAM_ID, false),
+                AccountMeta::new(self.vault, self.token_program, false),
+            ];
+            let data = ProgramInstruction::CreateVesting { amount: 1_000, start_slot, cliff_slot, end_slot };
+            let instruction = Instruction::new(self.vesting_program, data, accounts);
+            sign_and_execute(&mut self.ledger, &self.programs, &self.funder, vec![instruction])
+        }
+
+        fn claim_instruction(&self, signer: Pubkey) -> Instruction {
+            let signed = signer == self.beneficiary.pubkey();
+            let accounts = vec![
+                AccountMeta::new(self.vesting, self.vesting_program, false),
+                AccountMeta::new_readonly(self.beneficiary.pubkey(), SYSTEM_PROGRAM_ID, signed),
+                AccountMeta::new(self.vault, self.token_program, false),
+                AccountMeta::new(self.destination, self.token_program, false),
+            ];
+            Instruction::new(self.vesting_program, ProgramInstruction::Claim, accounts)
+        }
+
+        fn claim(&mut self) -> Result<(), TransactionError> {
+            let instruction = self.claim_instruction(self.beneficiary.pubkey());
+            sign_and_execute(&mut self.ledger, &self.programs, &self.beneficiary, vec![instruction])
+        }
+
+        // tick到指定的slot
+        fn advance_to(&mut self, slot: u64) {
+            let slots = slot - self.ledger.slot();
+            self.ledger.tick(slots);
+        }
+    }
+
+    #[test]
+    fn test_vested_amount() {
+        let schedule = VestingSchedule::new(Pubkey::new_unique(), Pubkey::new_unique(), 1_000, (100, 200, 300), 255).unwrap();
+        assert_eq!(schedule.vested_amount(0), 0);
+        assert_eq!(schedule.vested_amount(199), 0);
+        // 过了cliff一次释放从start开始累计的部分
+        assert_eq!(schedule.vested_amount(200), 500);
+        assert_eq!(schedule.vested_amount(250), 750);
+        assert_eq!(schedule.vested_amount(300), 1_000);
+        assert_eq!(schedule.vested_amount(u64::MAX), 1_000);
+        // 大额也不会溢出
+        let large = VestingSchedule::new(Pubkey::new_unique(), Pubkey::new_unique(), u64::MAX, (0, 0, 3), 255).unwrap();
+        assert_eq!(large.vested_amount(1), u64::MAX / 3);
+
+        let invalid = |slots| VestingSchedule::new(Pubkey::new_unique(), Pubkey::new_unique(), 1, slots, 255).unwrap_err();
+        assert_eq!(invalid((200, 100, 300)), VestingError::InvalidSchedule);
+        assert_eq!(invalid((100, 300, 200)), VestingError::InvalidSchedule);
+        assert_eq!(invalid((100, 100, 100)), VestingError::InvalidSchedule);
+    }
+
+    #[test]
+    fn test_claim_over_schedule() {
+        let mut f = fixture();
+        assert_eq!(f.create(100, 200, 300), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.source), token_amount(&f.ledger, &f.vault)), (0, 1_000));
+
+        // cliff之前
+        f.advance_to(150);
+        assert_eq!(f.claim(), Err(TransactionError::InstructionError(0, TransactionResult::NothingToClaim)));
+        // 中途：领取到当前为止的累计数量，再领一次没有新的释放
+        f.advance_to(250);
+        assert_eq!(f.claim(), Ok(()));
+        assert_eq!(token_amount(&f.ledger, &f.destination), 750);
+        assert_eq!(f.claim(), Err(TransactionError::InstructionError(0, TransactionResult::NothingToClaim)));
+        // 结束之后领取剩余的全部
+        f.advance_to(400);
+        assert_eq!(f.claim(), Ok(()));
+        assert_eq!((token_amount(&f.ledger, &f.destination), token_amount(&f.ledger, &f.vault)), (1_000, 0));
+        assert_eq!(f.claim(), Err(TransactionError::InstructionError(0, TransactionResult::NothingToClaim)));
+    }
+
+    #[test]
+    fn test_only_beneficiary_can_claim() {
+        let mut f = fixture();
+        assert_eq!(f.create(0, 0, 10), Ok(()));
+        f.advance_to(5);
+        let stranger = funded_keypair(&mut f.ledger);
+        let instruction = f.claim_instruction(stranger.pubkey());
+        assert_eq!(sign_and_execute(&mut f.ledger, &f.programs, &stranger, vec![instruction]), Err(TransactionError::InstructionError(0, TransactionResult::MissingRequiredSignature)));
+        assert_eq!(token_amount(&f.ledger, &f.destination), 0);
+    }
+
+    #[test]
+    fn test_create_rejects_invalid_schedule() {
+        let mut f = fixture();
+        assert_eq!(f.create(300, 200, 100), Err(TransactionError::InstructionError(0, TransactionResult::InvalidInstructionData)));
+        assert_eq!(token_amount(&f.ledger, &f.source), 1_000);
+    }
+}
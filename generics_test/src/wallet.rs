@@ -0,0 +1,167 @@
+// 钱包视图 - 类似于钱包应用展示的资产列表：按owner从账本中收集所有Token账户，
+// 同一个mint的余额合并在一起，并从Mint账户读取精度换算成显示用的数量
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::ledger::{AccountPayload, Ledger};
+use crate::ui_amount::UiAmount;
+use crate::{Pubkey, Summary, TokenAccount};
+
+// 某个mint的持仓
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holding {
+    pub mint: Pubkey,
+    pub amount: u64,           // 最小单位的总数
+    pub decimals: Option<u8>,  // 账本里找不到Mint账户时为None，只能按原始数量显示
+    pub accounts: usize,       // 这个mint下的Token账户数量
+}
+
+impl Holding {
+    // 按精度换算后的数量，例如decimals=6时1_500_000显示为1.5；不知道精度时按原始数量
+    pub fn ui_amount(&self) -> UiAmount {
+        UiAmount::new(self.amount, self.decimals.unwrap_or(0))
+    }
+}
+
+impl Summary for Holding {
+    fn summarize(&self) -> String {
+        match self.decimals {
+            Some(_) => format!("{}: {}", self.mint, self.ui_amount()),
+            None => format!("{}: {} (未知精度)", self.mint, self.amount),
+        }
+    }
+}
+
+impl fmt::Display for Holding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}，{}个账户", self.summarize(), self.accounts)
+    }
+}
+
+// 一个owner的全部持仓，按mint排序
+#[derive(Debug, Clone, PartialEq)]
+pub struct Portfolio {
+    pub owner: Pubkey,
+    pub holdings: BTreeMap<Pubkey, Holding>,
+}
+
+impl Portfolio {
+    pub fn get(&self, mint: &Pubkey) -> Option<&Holding> {
+        self.holdings.get(mint)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.holdings.is_empty()
+    }
+}
+
+impl Summary for Portfolio {
+    fn summarize(&self) -> String {
+        format!("钱包 {}: 持有{}种代币", self.owner, self.holdings.len())
+    }
+}
+
+impl fmt::Display for Portfolio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.summarize())?;
+        for holding in self.holdings.values() {
+            write!(f, "\n  {}", holding)?;
+        }
+        Ok(())
+    }
+}
+
+// 只读视图，不持有账户数据，每次查询都读取账本的最新状态
+#[derive(Debug, Clone, Copy)]
+pub struct Wallet<'a> {
+    ledger: &'a Ledger,
+    owner: Pubkey,
+}
+
+impl<'a> Wallet<'a> {
+    pub fn new(ledger: &'a Ledger, owner: Pubkey) -> Self {
+        Self { ledger, owner }
+    }
+
+    pub fn owner(&self) -> Pubkey {
+        self.owner
+    }
+
+    // 通过账本的owner索引查找，不需要扫描全部账户
+    pub fn token_accounts(&self) -> impl Iterator<Item = (Pubkey, &'a TokenAccount)> + 'a {
+        self.ledger.token_accounts_by_owner(&self.owner).filter_map(|(key, account)| match &account.data {
+            AccountPayload::Token(token) => Some((*key, token)),
+            _ => None,
+        })
+    }
+
+    pub fn portfolio(&self) -> Portfolio {
+        let mut holdings: BTreeMap<Pubkey, Holding> = BTreeMap::new();
+        for (_, token) in self.token_accounts() {
+            let holding = holdings.entry(token.mint).or_insert_with(|| Holding {
+                mint: token.mint,
+                amount: 0,
+                decimals: self.decimals(&token.mint),
+                accounts: 0,
+            });
+            // 同一mint的余额之和不会超过supply，这里只是防御没有Mint账户的情况
+            holding.amount = holding.amount.saturating_add(token.amount);
+            holding.accounts += 1;
+        }
+        Portfolio { owner: self.owner, holdings }
+    }
+
+    fn decimals(&self, mint: &Pubkey) -> Option<u8> {
+        match self.ledger.get_account(mint).map(|account| &account.data) {
+            Some(AccountPayload::Mint(mint)) => Some(mint.decimals),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::Account;
+    use crate::mint::Mint;
+
+    fn create(ledger: &mut Ledger, data: AccountPayload) -> Pubkey {
+        let key = Pubkey::new_unique();
+        let lamports = ledger.rent().minimum_balance(data.data_len());
+        ledger.create_account(key, Account::new(lamports, Pubkey::default(), data)).unwrap();
+        key
+    }
+
+    #[test]
+    fn test_portfolio_groups_by_mint() {
+        let mut ledger = Ledger::default();
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let usdc = create(&mut ledger, AccountPayload::Mint(Mint::new(6, Pubkey::new_unique())));
+        let nft = create(&mut ledger, AccountPayload::Mint(Mint::new(0, Pubkey::new_unique())));
+        let unknown = Pubkey::new_unique();
+        for (mint, owner, amount) in [(usdc, alice, 1_500_000), (usdc, alice, 250_000), (nft, alice, 1), (unknown, alice, 7), (usdc, bob, 9)] {
+            create(&mut ledger, AccountPayload::Token(TokenAccount::new(mint, owner, amount)));
+        }
+
+        let portfolio = Wallet::new(&ledger, alice).portfolio();
+        assert_eq!(portfolio.holdings.len(), 3);
+        let usdc_holding = portfolio.get(&usdc).unwrap();
+        assert_eq!((usdc_holding.amount, usdc_holding.accounts, usdc_holding.decimals), (1_750_000, 2, Some(6)));
+        assert_eq!(usdc_holding.ui_amount(), UiAmount::new(1_750_000, 6));
+        assert_eq!(usdc_holding.to_string(), format!("{}: 1.75，2个账户", usdc));
+        assert_eq!(portfolio.get(&nft).unwrap().summarize(), format!("{}: 1", nft));
+        // 没有Mint账户时按原始数量显示
+        assert_eq!(portfolio.get(&unknown).unwrap().decimals, None);
+        assert_eq!(portfolio.get(&unknown).unwrap().summarize(), format!("{}: 7 (未知精度)", unknown));
+        assert!(portfolio.summarize().contains("持有3种代币"));
+    }
+
+    #[test]
+    fn test_empty_wallet() {
+        let ledger = Ledger::default();
+        let wallet = Wallet::new(&ledger, Pubkey::new_unique());
+        assert_eq!(wallet.token_accounts().count(), 0);
+        assert!(wallet.portfolio().is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+// 带类型的CSV读取：第一行非空行是表头，之后每一行解析成一个T。
+// 每一行单独返回Result，一行写错不影响其他行，错误里带着行号，方便回到原文里找。
+// 只支持最简单的格式：逗号分隔、字段两边的空白会被去掉，不支持用引号包裹含逗号的字段
+
+use std::fmt;
+use std::iter::Enumerate;
+use std::marker::PhantomData;
+use std::str::{FromStr, Lines};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvErrorKind {
+    MissingHeader,                                        // 没有任何非空行
+    HeaderMismatch { expected: String, found: String },   // 表头和T的列不一致
+    FieldCount { expected: usize, found: usize },         // 这一行的字段数量不对
+    InvalidField { column: &'static str, value: String }, // 字段无法解析成需要的类型
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvError {
+    pub line: usize, // 从1开始的行号
+    pub kind: CsvErrorKind,
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "第{}行: ", self.line)?;
+        match &self.kind {
+            CsvErrorKind::MissingHeader => write!(f, "缺少表头"),
+            CsvErrorKind::HeaderMismatch { expected, found } => write!(f, "表头应为\"{}\"，实际为\"{}\"", expected, found),
+            CsvErrorKind::FieldCount { expected, found } => write!(f, "应有{}个字段，实际有{}个", expected, found),
+            CsvErrorKind::InvalidField { column, value } => write!(f, "字段\"{}\"的值\"{}\"无效", column, value),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+// 一行中已经按逗号拆开的字段，数量已经和列数核对过
+pub struct Record<'a> {
+    fields: Vec<&'a str>,
+    columns: &'static [&'static str],
+}
+
+impl<'a> Record<'a> {
+    pub fn get(&self, index: usize) -> &'a str {
+        self.fields[index]
+    }
+
+    // 把第index个字段解析成F，失败时报告列名和原始值
+    pub fn parse<F: FromStr>(&self, index: usize) -> Result<F, CsvErrorKind> {
+        self.fields[index].parse().map_err(|_| self.invalid(index))
+    }
+
+    pub fn invalid(&self, index: usize) -> CsvErrorKind {
+        CsvErrorKind::InvalidField { column: self.columns[index], value: self.fields[index].to_string() }
+    }
+}
+
+// 能从CSV的一行构造出来的类型
+pub trait FromCsvRecord: Sized {
+    // 列名，按顺序和表头逐个比较
+    const COLUMNS: &'static [&'static str];
+
+    fn from_record(record: &Record<'_>) -> Result<Self, CsvErrorKind>;
+}
+
+pub struct CsvReader<'a, T> {
+    // lines()是懒加载的迭代器，只有在遍历时才会逐行切分
+    lines: Enumerate<Lines<'a>>,
+    header_checked: bool,
+    done: bool, // 表头出错后不再继续读
+    _marker: PhantomData<T>,
+}
+
+impl<'a, T: FromCsvRecord> CsvReader<'a, T> {
+    pub fn new(text: &'a str) -> Self {
+        Self { lines: text.lines().enumerate(), header_checked: false, done: false, _marker: PhantomData }
+    }
+
+    fn split(line: &str) -> Vec<&str> {
+        // Vec<_>中的_让编译器根据collect的结果推断元素类型，这里是&str
+        line.split(',').map(|field| field.trim()).collect()
+    }
+
+    fn check_header(line: &str) -> Result<(), CsvErrorKind> {
+        if Self::split(line) == T::COLUMNS {
+            Ok(())
+        } else {
+            Err(CsvErrorKind::HeaderMismatch { expected: T::COLUMNS.join(","), found: line.trim().to_string() })
+        }
+    }
+
+    fn parse_line(line: &str) -> Result<T, CsvErrorKind> {
+        let fields = Self::split(line);
+        if fields.len() != T::COLUMNS.len() {
+            return Err(CsvErrorKind::FieldCount { expected: T::COLUMNS.len(), found: fields.len() });
+        }
+        T::from_record(&Record { fields, columns: T::COLUMNS })
+    }
+}
+
+impl<T: FromCsvRecord> Iterator for CsvReader<'_, T> {
+    type Item = Result<T, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        // 空行直接跳过
+        let Some((index, line)) = self.lines.by_ref().find(|(_, line)| !line.trim().is_empty()) else {
+            self.done = true;
+            if !self.header_checked {
+                return Some(Err(CsvError { line: 1, kind: CsvErrorKind::MissingHeader }));
+            }
+            return None;
+        };
+        let line_number = index + 1;
+
+        if !self.header_checked {
+            self.header_checked = true;
+            if let Err(kind) = Self::check_header(line) {
+                self.done = true;
+                return Some(Err(CsvError { line: line_number, kind }));
+            }
+            return self.next();
+        }
+        Some(Self::parse_line(line).map_err(|kind| CsvError { line: line_number, kind }))
+    }
+}
+
+// ===============================
+// 示例类型
+// ===============================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Penguin {
+    pub name: String,
+    pub length_cm: f32,
+}
+
+impl FromCsvRecord for Penguin {
+    const COLUMNS: &'static [&'static str] = &["common name", "length (cm)"];
+
+    fn from_record(record: &Record<'_>) -> Result<Self, CsvErrorKind> {
+        Ok(Penguin { name: record.get(0).to_string(), length_cm: record.parse(1)? })
+    }
+}
+
+// 简化的Token账户，owner和mint用base58字符串表示
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenAccount {
+    pub owner: String,
+    pub mint: String,
+    pub amount: u64,
+    pub frozen: bool,
+}
+
+impl FromCsvRecord for TokenAccount {
+    const COLUMNS: &'static [&'static str] = &["owner", "mint", "amount", "frozen"];
+
+    fn from_record(record: &Record<'_>) -> Result<Self, CsvErrorKind> {
+        for index in 0..2 {
+            if record.get(index).is_empty() {
+                return Err(record.invalid(index));
+            }
+        }
+        Ok(TokenAccount {
+            owner: record.get(0).to_string(),
+            mint: record.get(1).to_string(),
+            // 余额不能为负数，也不能超过u64::MAX
+            amount: record.parse(2)?,
+            frozen: record.parse(3)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_penguins_with_line_numbers() {
+        let data = "common name, length (cm)\n\nLittle penguin,33\nInvalid,data\nEmperor penguin,122.5,extra\n";
+        let results: Vec<_> = CsvReader::<Penguin>::new(data).collect();
+        assert_eq!(
+            results,
+            vec![
+                Ok(Penguin { name: "Little penguin".to_string(), length_cm: 33.0 }),
+                Err(CsvError { line: 4, kind: CsvErrorKind::InvalidField { column: "length (cm)", value: "data".to_string() } }),
+                Err(CsvError { line: 5, kind: CsvErrorKind::FieldCount { expected: 2, found: 3 } }),
+            ]
+        );
+        assert_eq!(results[1].as_ref().unwrap_err().to_string(), "第4行: 字段\"length (cm)\"的值\"data\"无效");
+    }
+
+    #[test]
+    fn test_header_errors_stop_reading() {
+        let mut reader = CsvReader::<Penguin>::new("\nname,length\nLittle penguin,33\n");
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.to_string(), "第2行: 表头应为\"common name,length (cm)\"，实际为\"name,length\"");
+        assert!(reader.next().is_none());
+
+        let mut empty = CsvReader::<Penguin>::new("  \n");
+        assert_eq!(empty.next(), Some(Err(CsvError { line: 1, kind: CsvErrorKind::MissingHeader })));
+        assert_eq!(empty.next(), None);
+        // 只有表头时没有记录
+        assert_eq!(CsvReader::<Penguin>::new("common name,length (cm)").count(), 0);
+    }
+
+    #[test]
+    fn test_read_token_accounts() {
+        let data = "owner,mint,amount,frozen\n\
+                    Alice111,Mint111,500,false\n\
+                    Bob111,Mint111,-5,false\n\
+                    ,Mint111,1,false\n\
+                    Carol111,Mint111,18446744073709551615,yes\n";
+        let results: Vec<_> = CsvReader::<TokenAccount>::new(data).collect();
+        assert_eq!(
+            results[0],
+            Ok(TokenAccount { owner: "Alice111".to_string(), mint: "Mint111".to_string(), amount: 500, frozen: false })
+        );
+        let invalid = |line, column: &'static str, value: &str| {
+            Err(CsvError { line, kind: CsvErrorKind::InvalidField { column, value: value.to_string() } })
+        };
+        assert_eq!(results[1], invalid(3, "amount", "-5"));
+        assert_eq!(results[2], invalid(4, "owner", ""));
+        assert_eq!(results[3], invalid(5, "frozen", "yes"));
+    }
+}
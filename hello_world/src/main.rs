@@ -1,3 +1,10 @@
+// 前面几个示例函数保留在文件里，需要时在main中取消注释调用
+#![allow(dead_code)]
+
+mod csv;
+
+use csv::{CsvReader, Penguin, TokenAccount};
+
 fn greet_world() {
     let southern_germany = "Grüß Gott!";
     let chinese = "你好!";
@@ -63,7 +70,7 @@ fn type_examples() {
     
     // 3. 数字类型都实现了Copy
     let _number = 42;      // 类型: i32
-    let _float = 3.14;     // 类型: f64
+    let _float = 2.5;      // 类型: f64
     
     // 4. 布尔类型实现了Copy
     let _flag = true;      // 类型: bool
@@ -164,38 +171,25 @@ fn main() {
     Invalid,data
     ";
     
-    let records = penguin_data.lines(); // lines() 是懒加载迭代器，这里其实没有分割，只有在遍历时才会逐行处理，<'_>中的'_表示生命周期，让编译器自动推断，和penguin_data的生命周期一致
-    
-    for (i, record) in records.enumerate() { // enumerate() 返回(index, &str)
-        if i == 0 || record.trim().len() == 0 { // trim是&str的方法，返回&str
-        continue;
-        }
-    
-        // 声明一个 fields 变量，类型是 Vec
-        // Vec 是 vector 的缩写，是一个可伸缩的集合类型，可以认为是一个动态数组
-        // <_>表示 Vec 中的元素类型由编译器自行推断，在很多场景下，都会帮我们省却不少功夫
-        let fields: Vec<_> = record
-        .split(',')
-        .map(|field| field.trim())
-        .collect();
-        if cfg!(debug_assertions) {
-            // 输出到标准错误输出
-        eprintln!("debug: {:?} -> {:?}",
-                record, fields);
-        }
-    
-        let name = fields[0];
-        // 1. 尝试把 fields[1] 的值转换为 f32 类型的浮点数，如果成功，则把 f32 值赋给 length 变量
-        //
-        // 2. if let 是一个匹配表达式，用来从=右边的结果中，匹配出 length 的值：
-        //   1）当=右边的表达式执行成功，则会返回一个 Ok(f32) 的类型，若失败，则会返回一个 Err(e) 类型，if let 的作用就是仅匹配 Ok 也就是成功的情况，如果是错误，就直接忽略
-        //   2）同时 if let 还会做一次解构匹配，通过 Ok(length) 去匹配右边的 Ok(f32)，最终把相应的 f32 值赋给 length
-        //
-        // 3. 当然你也可以忽略成功的情况，用 if let Err(e) = fields[1].parse::<f32>() {...}匹配出错误，然后打印出来，但是没啥卵用
-        if let Ok(length) = fields[1].parse::<f32>() {
-            // 输出到标准输出
-            println!("{}, {}cm", name, length);
+    // CsvReader::<Penguin>中的Penguin决定了每一行解析成什么类型，解析规则写在FromCsvRecord的实现里
+    for result in CsvReader::<Penguin>::new(penguin_data) {
+        // 每一行都是一个Result：成功的行正常输出，出错的行带着行号输出到标准错误，然后继续读下一行
+        match result {
+            Ok(penguin) => println!("{}, {}cm", penguin.name, penguin.length_cm),
+            Err(error) => eprintln!("跳过: {}", error),
         }
     }
 
+    // 同一个读取器换一种类型就能读别的数据
+    let account_data = "\
+    owner,mint,amount,frozen
+    Alice,USDC,500,false
+    Bob,USDC,-5,false
+    ";
+    for result in CsvReader::<TokenAccount>::new(account_data) {
+        match result {
+            Ok(account) => println!("{} 持有 {} {}，冻结: {}", account.owner, account.amount, account.mint, account.frozen),
+            Err(error) => eprintln!("跳过: {}", error),
+        }
+    }
 }
@@ -0,0 +1,276 @@
+// 程序配置：账户文件路径、语言和compute budget。
+// 每一项按 环境变量 > 配置文件 > 默认值 的顺序取值：配置文件不存在时跳过这一层，
+// 但文件读不出来、某一行写错或者值不合法都会返回ConfigError，并说明是哪一层、哪个键出的错
+
+use std::env::{self, VarError};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const MAX_COMPUTE_BUDGET: u64 = 1_400_000;
+
+// (配置文件中的键, 对应的环境变量)
+const KEYS: [(&str, &str); 3] = [
+    ("ledger_path", "RESULT_TEST_LEDGER_PATH"),
+    ("locale", "RESULT_TEST_LOCALE"),
+    ("compute_budget", "RESULT_TEST_COMPUTE_BUDGET"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    Zh,
+    En,
+}
+
+impl FromStr for Locale {
+    type Err = ValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zh" | "zh-CN" => Ok(Locale::Zh),
+            "en" | "en-US" => Ok(Locale::En),
+            _ => Err(ValueError::UnknownLocale(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub ledger_path: PathBuf,
+    pub locale: Locale,
+    pub compute_budget: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { ledger_path: PathBuf::from("accounts.toml"), locale: Locale::Zh, compute_budget: 200_000 }
+    }
+}
+
+// 一个值来自哪里
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    Env(&'static str),                   // 环境变量名
+    File { path: PathBuf, line: usize }, // 配置文件的第几行
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Env(name) => write!(f, "环境变量{}", name),
+            Origin::File { path, line } => write!(f, "{}第{}行", path.display(), line),
+        }
+    }
+}
+
+// 值本身的问题
+#[derive(Debug)]
+pub enum ValueError {
+    NotUnicode,              // 环境变量不是合法的UTF-8
+    Number(ParseIntError),   // 不是合法的整数
+    OutOfRange { max: u64 }, // 超过上限
+    UnknownLocale(String),   // 不支持的语言
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueError::NotUnicode => write!(f, "不是合法的UTF-8"),
+            ValueError::Number(_) => write!(f, "不是合法的整数"),
+            ValueError::OutOfRange { max } => write!(f, "不能超过{}", max),
+            ValueError::UnknownLocale(locale) => write!(f, "不支持的语言: {}（可选zh、en）", locale),
+        }
+    }
+}
+
+impl Error for ValueError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ValueError::Number(error) => Some(error),
+            ValueError::NotUnicode | ValueError::OutOfRange { .. } | ValueError::UnknownLocale(_) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    ReadFile { path: PathBuf, source: io::Error },                          // 配置文件存在但读不出来
+    InvalidLine { path: PathBuf, line: usize },                             // 不是 key = value 的格式
+    UnknownKey { path: PathBuf, line: usize, key: String },                 // 不认识的键
+    InvalidValue { origin: Origin, key: &'static str, source: ValueError }, // 值不合法
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ReadFile { path, .. } => write!(f, "读取配置文件{}失败", path.display()),
+            ConfigError::InvalidLine { path, line } => {
+                write!(f, "{}第{}行格式错误，应为 key = value", path.display(), line)
+            }
+            ConfigError::UnknownKey { path, line, key } => write!(f, "{}第{}行: 未知的配置项{}", path.display(), line, key),
+            ConfigError::InvalidValue { origin, key, .. } => write!(f, "{}: 配置项{}的值无效", origin, key),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::ReadFile { source, .. } => Some(source),
+            ConfigError::InvalidValue { source, .. } => Some(source),
+            ConfigError::InvalidLine { .. } | ConfigError::UnknownKey { .. } => None,
+        }
+    }
+}
+
+impl Config {
+    // 从真实的环境变量和path处的配置文件加载
+    pub fn load(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        Self::load_with(path, |name| env::var(name))
+    }
+
+    // env用来读取环境变量，测试和演示时可以传入假的环境
+    pub fn load_with<F>(path: impl AsRef<Path>, env: F) -> Result<Config, ConfigError>
+    where
+        F: Fn(&str) -> Result<String, VarError>,
+    {
+        let mut config = Config::default();
+        config.apply_file(path.as_ref())?;
+        config.apply_env(env)?;
+        Ok(config)
+    }
+
+    fn apply_file(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(source) => return Err(ConfigError::ReadFile { path: path.to_path_buf(), source }),
+        };
+
+        for (index, line) in content.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ConfigError::InvalidLine { path: path.to_path_buf(), line: line_number })?;
+            let key = key.trim();
+            let Some(&(key, _)) = KEYS.iter().find(|(name, _)| *name == key) else {
+                return Err(ConfigError::UnknownKey { path: path.to_path_buf(), line: line_number, key: key.to_string() });
+            };
+            let origin = Origin::File { path: path.to_path_buf(), line: line_number };
+            self.set(key, value.trim().trim_matches('"'))
+                .map_err(|source| ConfigError::InvalidValue { origin, key, source })?;
+        }
+        Ok(())
+    }
+
+    // 没有设置的环境变量不覆盖前面几层的值
+    fn apply_env<F>(&mut self, env: F) -> Result<(), ConfigError>
+    where
+        F: Fn(&str) -> Result<String, VarError>,
+    {
+        for (key, name) in KEYS {
+            let result = match env(name) {
+                Ok(value) => self.set(key, &value),
+                Err(VarError::NotPresent) => continue,
+                Err(VarError::NotUnicode(_)) => Err(ValueError::NotUnicode),
+            };
+            result.map_err(|source| ConfigError::InvalidValue { origin: Origin::Env(name), key, source })?;
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), ValueError> {
+        match key {
+            "ledger_path" => self.ledger_path = PathBuf::from(value),
+            "locale" => self.locale = value.parse()?,
+            "compute_budget" => {
+                let budget: u64 = value.parse().map_err(ValueError::Number)?;
+                if budget > MAX_COMPUTE_BUDGET {
+                    return Err(ValueError::OutOfRange { max: MAX_COMPUTE_BUDGET });
+                }
+                self.compute_budget = budget;
+            }
+            _ => unreachable!("KEYS之外的键在调用前就被拒绝了"),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::report;
+
+    fn temp_config(name: &str, content: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("result_test_config_{}_{}.toml", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn no_env(_: &str) -> Result<String, VarError> {
+        Err(VarError::NotPresent)
+    }
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let missing = env::temp_dir().join("result_test_config_missing.toml");
+        assert_eq!(Config::load_with(&missing, no_env).unwrap(), Config::default());
+
+        let path = temp_config("layers", "# 配置\nledger_path = \"ledger.toml\"\ncompute_budget = 50000\n");
+        let config = Config::load_with(&path, no_env).unwrap();
+        assert_eq!((config.ledger_path, config.locale, config.compute_budget), (PathBuf::from("ledger.toml"), Locale::Zh, 50_000));
+
+        // 环境变量覆盖配置文件
+        let env = |name: &str| match name {
+            "RESULT_TEST_COMPUTE_BUDGET" => Ok("1000".to_string()),
+            "RESULT_TEST_LOCALE" => Ok("en".to_string()),
+            _ => Err(VarError::NotPresent),
+        };
+        let config = Config::load_with(&path, env).unwrap();
+        assert_eq!((config.ledger_path, config.locale, config.compute_budget), (PathBuf::from("ledger.toml"), Locale::En, 1_000));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_errors_name_the_failing_layer() {
+        let path = temp_config("errors", "locale = \"zh\"\ncompute_budget = 12x\n");
+        let error = Config::load_with(&path, no_env).unwrap_err();
+        assert_eq!(
+            report(&error),
+            format!(
+                "错误: {}第2行: 配置项compute_budget的值无效\n原因:\n    0: 不是合法的整数\n    1: {}",
+                path.display(),
+                "12x".parse::<u64>().unwrap_err()
+            )
+        );
+
+        fs::write(&path, "timeout = 3\n").unwrap();
+        let error = Config::load_with(&path, no_env).unwrap_err();
+        assert_eq!(error.to_string(), format!("{}第1行: 未知的配置项timeout", path.display()));
+        fs::write(&path, "locale\n").unwrap();
+        assert!(matches!(Config::load_with(&path, no_env), Err(ConfigError::InvalidLine { line: 1, .. })));
+        fs::remove_file(&path).unwrap();
+
+        let env = |name: &str| match name {
+            "RESULT_TEST_LOCALE" => Ok("fr".to_string()),
+            _ => Err(VarError::NotPresent),
+        };
+        let error = Config::load_with(&path, env).unwrap_err();
+        assert_eq!(report(&error), "错误: 环境变量RESULT_TEST_LOCALE: 配置项locale的值无效\n原因:\n    0: 不支持的语言: fr（可选zh、en）");
+
+        let env = |name: &str| match name {
+            "RESULT_TEST_COMPUTE_BUDGET" => Ok("2000000".to_string()),
+            _ => Err(VarError::NotPresent),
+        };
+        let error = Config::load_with(&path, env).unwrap_err();
+        assert_eq!(error.source().unwrap().to_string(), "不能超过1400000");
+    }
+}
@@ -0,0 +1,113 @@
+// 给错误加上下文：底层错误只知道“文件不存在”，上层调用者知道“正在加载账户文件”。
+// .context("...")把原来的错误包进ContextError，source()指向原来的错误，一层层形成错误链，
+// report把整条链打印成多行，方便看出错误是在哪一步、因为什么发生的
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub struct ContextError {
+    context: String,
+    source: Box<dyn Error>,
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.context)
+    }
+}
+
+impl Error for ContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+pub trait Context<T> {
+    fn context(self, context: impl Into<String>) -> Result<T, ContextError>;
+
+    // 只在出错时才生成上下文信息，避免成功路径上的format!
+    fn with_context<F: FnOnce() -> String>(self, f: F) -> Result<T, ContextError>;
+}
+
+impl<T, E: Error + 'static> Context<T> for Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T, ContextError> {
+        self.map_err(|error| ContextError { context: context.into(), source: Box::new(error) })
+    }
+
+    fn with_context<F: FnOnce() -> String>(self, f: F) -> Result<T, ContextError> {
+        self.map_err(|error| ContextError { context: f(), source: Box::new(error) })
+    }
+}
+
+// 多行报告：第一行是最外层的错误，下面按顺序列出每一层原因
+pub fn report(error: &dyn Error) -> String {
+    format!("错误: {}", chain(error))
+}
+
+fn chain(error: &dyn Error) -> String {
+    let mut out = error.to_string();
+    let mut source = error.source();
+    if source.is_some() {
+        out.push_str("\n原因:");
+    }
+    let mut depth = 0;
+    while let Some(error) = source {
+        out.push_str(&format!("\n    {}: {}", depth, error));
+        depth += 1;
+        source = error.source();
+    }
+    out
+}
+
+// main的错误类型，类似anyhow::Error：任何错误都能用?转换进来。
+// main返回Err时标准库用Debug打印"Error: ..."，这里的Debug打印整条错误链，而不是最外层错误的结构体。
+// 不实现Error，否则和标准库的impl From<T> for T冲突
+pub struct AppError(Box<dyn Error>);
+
+impl<E: Error + 'static> From<E> for AppError {
+    fn from(error: E) -> Self {
+        AppError(Box::new(error))
+    }
+}
+
+impl fmt::Debug for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", chain(self.0.as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program_error::ProgramError;
+
+    #[test]
+    fn test_context_chain() {
+        let result: Result<(), ProgramError> = Err(ProgramError::AccountNotFound);
+        let error = result.context("查找发送方").context("执行转账").unwrap_err();
+
+        assert_eq!(error.to_string(), "执行转账");
+        assert_eq!(error.source().unwrap().to_string(), "查找发送方");
+        assert_eq!(report(&error), "错误: 执行转账\n原因:\n    0: 查找发送方\n    1: 账户不存在 (0x2)");
+    }
+
+    #[test]
+    fn test_with_context_is_lazy() {
+        let ok: Result<u8, ProgramError> = Ok(1);
+        assert_eq!(ok.with_context(|| unreachable!()).unwrap(), 1);
+
+        let error = "x".parse::<u8>().with_context(|| format!("解析第{}个参数", 2)).unwrap_err();
+        assert_eq!(report(&error), format!("错误: 解析第2个参数\n原因:\n    0: {}", "x".parse::<u8>().unwrap_err()));
+        assert_eq!(report(&ProgramError::DivisionByZero), "错误: 除数不能为零 (0x4)");
+    }
+
+    #[test]
+    fn test_app_error_debug_prints_chain() {
+        fn run() -> Result<(), AppError> {
+            Err(ProgramError::AccountNotFound).context("查找发送方")?;
+            Ok(())
+        }
+        assert_eq!(format!("{:?}", run().unwrap_err()), "查找发送方\n原因:\n    0: 账户不存在 (0x2)");
+    }
+}
@@ -0,0 +1,93 @@
+// 账本相关操作的统一错误类型，写法和thiserror生成的代码一样：
+// 每个变体对应一条Display信息（相当于#[error("...")]），包装的底层错误通过From自动转换（相当于#[from]），
+// 并且从source()返回，调用方可以沿着错误链找到最初的原因。
+// Display只描述这一层，不重复底层错误的信息，整条链交给context::report打印；
+// Program相当于#[error(transparent)]，直接使用ProgramError自己的信息
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::num::ParseIntError;
+
+use crate::program_error::ProgramError;
+
+#[derive(Debug)]
+pub enum ExerciseError {
+    Io(io::Error),                                         // 读写文件失败
+    InvalidLine { line: usize },                           // 账户文件的某一行不是 "地址" = 余额 的格式
+    InvalidBalance { line: usize, source: ParseIntError }, // 余额不是合法的u64
+    Program(ProgramError),                                 // 转账等程序逻辑返回的错误
+}
+
+impl ExerciseError {
+    // 超时、被中断这类临时错误，稍后重试可能成功
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ExerciseError::Io(error) => matches!(error.kind(), io::ErrorKind::TimedOut | io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock),
+            ExerciseError::InvalidLine { .. } | ExerciseError::InvalidBalance { .. } | ExerciseError::Program(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for ExerciseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExerciseError::Io(_) => write!(f, "读写文件失败"),
+            ExerciseError::InvalidLine { line } => write!(f, "第{}行格式错误，应为 \"地址\" = 余额", line),
+            ExerciseError::InvalidBalance { line, .. } => write!(f, "第{}行余额无效", line),
+            ExerciseError::Program(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for ExerciseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExerciseError::Io(error) => Some(error),
+            ExerciseError::InvalidBalance { source, .. } => Some(source),
+            ExerciseError::Program(error) => error.source(),
+            ExerciseError::InvalidLine { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ExerciseError {
+    fn from(error: io::Error) -> ExerciseError {
+        ExerciseError::Io(error)
+    }
+}
+
+impl From<ProgramError> for ExerciseError {
+    fn from(error: ProgramError) -> ExerciseError {
+        ExerciseError::Program(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_and_source() {
+        let error = ExerciseError::from(io::Error::new(io::ErrorKind::NotFound, "accounts.toml"));
+        assert_eq!(error.to_string(), "读写文件失败");
+        assert_eq!(error.source().map(|e| e.to_string()), Some("accounts.toml".to_string()));
+
+        let source = "abc".parse::<u64>().unwrap_err();
+        let error = ExerciseError::InvalidBalance { line: 3, source: source.clone() };
+        assert_eq!(error.to_string(), "第3行余额无效");
+        assert_eq!(error.source().map(|e| e.to_string()), Some(source.to_string()));
+
+        let error = ExerciseError::from(ProgramError::AccountNotFound);
+        assert_eq!(error.to_string(), "账户不存在 (0x2)");
+        assert!(error.source().is_none());
+        assert!(ExerciseError::InvalidLine { line: 1 }.source().is_none());
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(ExerciseError::from(io::Error::from(io::ErrorKind::TimedOut)).is_transient());
+        assert!(!ExerciseError::from(io::Error::from(io::ErrorKind::NotFound)).is_transient());
+        assert!(!ExerciseError::from(ProgramError::AccountNotFound).is_transient());
+    }
+}
@@ -0,0 +1,90 @@
+// Lamports：SOL的最小单位，1 SOL = 10^9 lamports
+// 用newtype包装u64，所有加减乘都走checked运算，溢出时返回错误而不是静默回绕
+
+use std::fmt;
+
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LamportsError {
+    Overflow,  // 结果超过u64::MAX
+    Underflow, // 结果小于0
+}
+
+impl fmt::Display for LamportsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LamportsError::Overflow => write!(f, "lamports计算溢出"),
+            LamportsError::Underflow => write!(f, "lamports计算下溢"),
+        }
+    }
+}
+
+impl std::error::Error for LamportsError {}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Lamports(pub u64);
+
+impl Lamports {
+    pub const ZERO: Lamports = Lamports(0);
+
+    // SOL -> lamports，乘法溢出时返回错误
+    pub fn from_sol(sol: u64) -> Result<Lamports, LamportsError> {
+        sol.checked_mul(LAMPORTS_PER_SOL)
+            .map(Lamports)
+            .ok_or(LamportsError::Overflow)
+    }
+
+    // lamports -> SOL，仅用于展示
+    pub fn as_sol(self) -> f64 {
+        self.0 as f64 / LAMPORTS_PER_SOL as f64
+    }
+
+    pub fn checked_add(self, other: Lamports) -> Result<Lamports, LamportsError> {
+        self.0
+            .checked_add(other.0)
+            .map(Lamports)
+            .ok_or(LamportsError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Lamports) -> Result<Lamports, LamportsError> {
+        self.0
+            .checked_sub(other.0)
+            .map(Lamports)
+            .ok_or(LamportsError::Underflow)
+    }
+
+    pub fn checked_mul(self, factor: u64) -> Result<Lamports, LamportsError> {
+        self.0
+            .checked_mul(factor)
+            .map(Lamports)
+            .ok_or(LamportsError::Overflow)
+    }
+}
+
+impl fmt::Display for Lamports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} lamports", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sol_conversion() {
+        assert_eq!(Lamports::from_sol(2), Ok(Lamports(2_000_000_000)));
+        assert_eq!(Lamports(1_500_000_000).as_sol(), 1.5);
+        assert_eq!(Lamports::from_sol(u64::MAX), Err(LamportsError::Overflow));
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(Lamports(100).checked_add(Lamports(50)), Ok(Lamports(150)));
+        assert_eq!(Lamports(100).checked_sub(Lamports(100)), Ok(Lamports::ZERO));
+        assert_eq!(Lamports(100).checked_sub(Lamports(101)), Err(LamportsError::Underflow));
+        assert_eq!(Lamports(u64::MAX).checked_add(Lamports(1)), Err(LamportsError::Overflow));
+        assert_eq!(Lamports(u64::MAX).checked_mul(2), Err(LamportsError::Overflow));
+    }
+}
@@ -1,21 +1,51 @@
+mod config;
+mod context;
+mod error;
+mod lamports;
+mod program_error;
+mod result_ext;
+mod retry;
+mod store;
+
+use std::cell::Cell;
+use std::env::VarError;
 use std::fs;
+use std::io;
+use std::time::Duration;
+
+use config::Config;
+use context::{report, AppError, Context};
+use error::ExerciseError;
+use lamports::Lamports;
+use program_error::ProgramError;
+use result_ext::ResultExt;
+use retry::{retry, RetryPolicy};
+use store::AccountStore;
+
+// 账户文件不存在时使用的初始账户
+const DEFAULT_ACCOUNTS: &[(&str, Lamports)] = &[
+    ("0x1234567890", Lamports(1000)),
+    ("0x1234567891", Lamports(500)),
+    ("0x1234567892", Lamports(800)),
+];
 
-fn main() {
+// main返回Result，读文件、解析这类顶层操作出错时直接用?退出，退出时打印完整的错误链
+fn main() -> Result<(), AppError> {
     println!("=== Result<T, E> 和 ? 操作符学习 ===\n");
 
+    // 启动时先读取配置（环境变量 > result_test.toml > 默认值），再从配置的路径加载账户
+    let config = Config::load("result_test.toml").context("加载配置")?;
+    let mut store = AccountStore::open(&config.ledger_path, DEFAULT_ACCOUNTS)
+        .with_context(|| format!("加载账户文件{}", config.ledger_path.display()))?;
+
     // 1. 基本的Result用法
     println!("1. 基本Result用法:");
-    let result1 = divide(10, 2);
-    let result2 = divide(10, 0);
-
-    match result1 {
-        Ok(value) => println!("10 / 2 = {}", value),
-        Err(error) => println!("错误: {}", error),
+    // 出错时log_err把错误打印到标准错误，成功时才进入if let
+    if let Ok(value) = divide(10, 2).log_err("divide") {
+        println!("10 / 2 = {}", value);
     }
-
-    match result2 {
-        Ok(value) => println!("10 / 0 = {}", value),
-        Err(error) => println!("错误: {}", error),
+    if let Ok(value) = divide(10, 0).log_err("divide") {
+        println!("10 / 0 = {}", value);
     }
 
     // 2. unwrap和expect的使用
@@ -28,38 +58,47 @@ fn main() {
 
     // 3. Option和ok_or的转换
     println!("\n3. Option转换为Result:");
-    let account1 = find_account("0x1234567890");
-    let account2 = find_account("不存在的地址");
+    let account1 = find_account(&store, "0x1234567890");
+    let account2 = find_account(&store, "不存在的地址");
 
     println!("账户1: {:?}", account1);
     println!("账户2: {:?}", account2);
 
     // 使用ok_or转换
-    let balance1 = find_account("0x1234567890").ok_or("账户不存在");
-    let balance2 = find_account("不存在的地址").ok_or("账户不存在");
+    let balance1 = find_account(&store, "0x1234567890").ok_or(ProgramError::AccountNotFound);
+    let balance2 = find_account(&store, "不存在的地址").ok_or(ProgramError::AccountNotFound);
 
     println!("转换后的余额1: {:?}", balance1);
     println!("转换后的余额2: {:?}", balance2);
 
     // 4. ?操作符的使用
     println!("\n4. ?操作符:");
-    let transfer1 = safe_transfer("0x1234567890", "0x1234567891", 50);
-    let transfer2 = safe_transfer("不存在", "0x1234567891", 50);
+    let transfer1 = safe_transfer(&store, "0x1234567890", "0x1234567891", Lamports(50));
+    let transfer2 = safe_transfer(&store, "不存在", "0x1234567891", Lamports(50));
 
     println!("转账1结果: {:?}", transfer1);
     println!("转账2结果: {:?}", transfer2);
 
     // 5. 链式调用
     println!("\n5. 链式调用:");
-    let chain_result = complex_operation("0x1234567890", 30);
+    let chain_result = complex_operation(&store, "0x1234567890", Lamports(30));
     println!("复杂操作结果: {:?}", chain_result);
 
     // 6. 错误传播示例
     println!("\n6. 错误传播:");
-    let file_content = read_file_content("test.txt");
-    match file_content {
-        Ok(content) => println!("文件内容: {}", content),
-        Err(error) => println!("读取文件失败: {}", error),
+    let file_content = read_file_content("test.txt")
+        .context("读取测试数据")
+        .or_default_with(|error| println!("{}", report(&error)));
+    println!("文件内容: {:?}", file_content);
+
+    // 账户文件内容有误时，报告里能同时看到是哪个文件、哪一行、什么原因
+    let broken = std::env::temp_dir().join("result_test_broken_accounts.toml");
+    if fs::write(&broken, "\"0x1234567890\" = 1000\n\"0x1234567891\" = 五百\n").is_ok() {
+        let loaded = AccountStore::open(&broken, &[]).with_context(|| format!("加载账户文件{}", broken.display()));
+        if let Err(error) = loaded {
+            println!("{}", report(&error));
+        }
+        let _ = fs::remove_file(&broken);
     }
 
     // 7. ?操作符用于Option的正确用法
@@ -78,62 +117,139 @@ fn main() {
     let empty_text = "";
     println!("空字符串的第一个字符: {:?}", get_first_char(empty_text));
     println!("空字符串的第二个字符: {:?}", get_second_char(empty_text));
+
+    // 8. Lamports的checked运算
+    println!("\n8. Lamports checked运算:");
+    println!("1 SOL = {:?}", Lamports::from_sol(1));
+    println!("{} = {} SOL", Lamports(2_500_000_000), Lamports(2_500_000_000).as_sol());
+    println!("乘法溢出: {:?}", Lamports(u64::MAX).checked_mul(2));
+    println!("零余额减1: {:?}", Lamports::ZERO.checked_sub(Lamports(1)));
+
+    // 9. 修改账户并写回文件
+    println!("\n9. 持久化账户:");
+    if store.set_balance("0x1234567893", Lamports(300)).log_err("保存账户文件").is_ok() {
+        println!("新账户已保存: {:?}", find_account(&store, "0x1234567893"));
+    }
+
+    // 10. 结构化错误码
+    println!("\n10. 错误码:");
+    let error = safe_transfer(&store, "0x1234567891", "0x1234567890", Lamports(10_000)).unwrap_err();
+    let code = u64::from(error); // 链上程序返回给客户端的只是一个数字
+    println!("转账失败，错误码: 0x{:x}", code);
+    println!("客户端解码: {}", ProgramError::decode(code));
+    println!("未知错误码: {}", ProgramError::decode(0x64));
+
+    // 11. ?自动转换错误类型
+    println!("\n11. ?自动转换:");
+    // 解析失败的数量按0计算
+    for text in ["250", "25o", "-1"] {
+        let amount = parse_lamports(text).or_default_with(|error| println!("解析 {:?} 失败: {}", text, error));
+        println!("解析 {:?}: {}", text, amount);
+    }
+    let amount = read_lamports("amount.txt")
+        .or_default_with(|error| println!("读取amount.txt失败，错误码0x{:x}: {}", u64::from(error), error));
+    println!("文件中的数量: {}", amount);
+
+    // 12. 失败重试
+    println!("\n12. 失败重试:");
+    let policy = RetryPolicy::new(4)
+        .backoff(Duration::from_millis(10), 2)
+        .max_backoff(Duration::from_millis(50))
+        .retry_if(ExerciseError::is_transient);
+    let rpc = SimulatedRpc::new(&store, 2);
+    let balance = retry(&policy, || rpc.get_balance("0x1234567890"));
+    println!("RPC前两次超时，第{}次查询: {:?}", rpc.calls.get(), balance);
+    let rpc = SimulatedRpc::new(&store, 0);
+    let missing = retry(&policy, || rpc.get_balance("不存在的地址"));
+    println!("账户不存在不是临时错误，只查询{}次: {:?}", rpc.calls.get(), missing.map_err(|e| e.to_string()));
+    let mut reads = 0;
+    let content = retry(&policy, || {
+        reads += 1;
+        read_file_content("test.txt")
+    });
+    println!("文件不存在也不重试，读取{}次: {}", reads, content.is_ok());
+
+    // 13. 分层配置
+    println!("\n13. 分层配置:");
+    println!("当前配置: {:?}", config);
+    // 用假的环境变量演示：出错时能看出是哪一层、哪个配置项
+    let bad_env = |name: &str| match name {
+        "RESULT_TEST_COMPUTE_BUDGET" => Ok("20万".to_string()),
+        _ => Err(VarError::NotPresent),
+    };
+    if let Err(error) = Config::load_with("result_test.toml", bad_env) {
+        println!("{}", report(&error));
+    }
+    Ok(())
+}
+
+// 模拟的RPC节点：前failures次请求超时，之后从账户文件返回余额
+struct SimulatedRpc<'a> {
+    store: &'a AccountStore,
+    failures: u32,
+    calls: Cell<u32>,
+}
+
+impl<'a> SimulatedRpc<'a> {
+    fn new(store: &'a AccountStore, failures: u32) -> Self {
+        Self { store, failures, calls: Cell::new(0) }
+    }
+
+    fn get_balance(&self, address: &str) -> Result<Lamports, ExerciseError> {
+        self.calls.set(self.calls.get() + 1);
+        if self.calls.get() <= self.failures {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "RPC请求超时").into());
+        }
+        Ok(find_account(self.store, address).ok_or(ProgramError::AccountNotFound)?)
+    }
 }
 
 // 1. 基本的Result函数
-fn divide(a: i32, b: i32) -> Result<i32, String> {
+fn divide(a: i32, b: i32) -> Result<i32, ProgramError> {
     if b == 0 {
-        Err("除数不能为零".to_string())
+        Err(ProgramError::DivisionByZero)
     } else {
         Ok(a / b)
     }
 }
 
-// 2. 返回Option的函数（模拟账户查找）
-fn find_account(address: &str) -> Option<u64> {
-    match address {
-        "0x1234567890" => Some(1000),
-        "0x1234567891" => Some(500),
-        "0x1234567892" => Some(800),
-        _ => None,
-    }
+// 2. 返回Option的函数（从账户文件中查找）
+fn find_account(store: &AccountStore, address: &str) -> Option<Lamports> {
+    store.find_account(address)
 }
 
 // 3. 使用?操作符的函数
-fn safe_transfer(from: &str, to: &str, amount: u64) -> Result<u64, String> {
+fn safe_transfer(store: &AccountStore, from: &str, to: &str, amount: Lamports) -> Result<Lamports, ProgramError> {
     // 使用?操作符处理Option到Result的转换
-    let from_balance = find_account(from).ok_or("发送方账户不存在")?;
-    let _to_balance = find_account(to).ok_or("接收方账户不存在")?;
+    let from_balance = find_account(store, from).ok_or(ProgramError::AccountNotFound)?;
+    let to_balance = find_account(store, to).ok_or(ProgramError::AccountNotFound)?;
 
-    // 检查余额
-    if from_balance < amount {
-        return Err("余额不足".to_string());
-    }
+    // 检查接收方入账是否会溢出，LamportsError通过From自动转换成ProgramError
+    to_balance.checked_add(amount)?;
 
-    // 返回转账后的余额
-    Ok(from_balance - amount)
+    // 返回转账后的余额，checked_sub下溢说明余额不足
+    from_balance
+        .checked_sub(amount)
+        .map_err(|_| ProgramError::InsufficientFunds)
 }
 
 // 4. 链式调用示例
-fn complex_operation(address: &str, amount: u64) -> Result<String, String> {
-    let balance = find_account(address).ok_or("账户不存在")?;
+fn complex_operation(store: &AccountStore, address: &str, amount: Lamports) -> Result<String, ProgramError> {
+    let balance = find_account(store, address).ok_or(ProgramError::AccountNotFound)?;
 
     // 链式调用：先检查余额，再执行转账
-    if balance >= amount {
-        let remaining = balance - amount;
-        Ok(format!("操作成功，剩余余额: {}", remaining))
-    } else {
-        Err("余额不足".to_string())
-    }
+    balance
+        .checked_sub(amount)
+        .map(|remaining| format!("操作成功，剩余余额: {}", remaining))
+        .map_err(|_| ProgramError::InsufficientFunds)
 }
 
 // 5. 文件操作示例（展示真实的IO错误处理）
-fn read_file_content(filename: &str) -> Result<String, String> {
+// io::Error通过From自动转换成ExerciseError::Io
+fn read_file_content(filename: &str) -> Result<String, ExerciseError> {
     // 尝试读取文件，如果失败则返回错误
-    match fs::read_to_string(filename) {
-        Ok(content) => Ok(content),
-        Err(error) => Err(format!("读取文件失败: {}", error)),
-    }
+    let content = fs::read_to_string(filename)?;
+    Ok(content)
 }
 
 // 6. 使用?操作符的文件操作
@@ -142,6 +258,18 @@ fn _read_file_with_question_mark(filename: &str) -> Result<String, Box<dyn std::
     Ok(content)
 }
 
+// ParseIntError通过From<ParseIntError>自动转换成ProgramError::InvalidNumber，不需要map_err
+fn parse_lamports(text: &str) -> Result<Lamports, ProgramError> {
+    let amount: u64 = text.trim().parse()?;
+    Ok(Lamports(amount))
+}
+
+// io::Error和ParseIntError在同一个函数里都可以直接用?
+fn read_lamports(filename: &str) -> Result<Lamports, ProgramError> {
+    let content = fs::read_to_string(filename)?;
+    parse_lamports(&content)
+}
+
 // 7. ?操作符用于Option
 fn get_first_char(text: &str) -> Option<char> {
     text.chars().next() // 直接返回Option<char>
@@ -0,0 +1,132 @@
+// 程序错误码：和链上程序一样，错误最终以一个数字返回给客户端，客户端再把数字解码成可读的信息
+
+use std::fmt;
+use std::io;
+use std::num::ParseIntError;
+
+use crate::lamports::LamportsError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgramError {
+    InsufficientFunds,  // 0x1 余额不足
+    AccountNotFound,    // 0x2 账户不存在
+    ArithmeticOverflow, // 0x3 数值计算溢出
+    DivisionByZero,     // 0x4 除数为零
+    InvalidNumber,      // 0x5 数字解析失败
+    IoFailure,          // 0x6 读写文件失败
+}
+
+impl ProgramError {
+    pub const ALL: [ProgramError; 6] = [
+        ProgramError::InsufficientFunds,
+        ProgramError::AccountNotFound,
+        ProgramError::ArithmeticOverflow,
+        ProgramError::DivisionByZero,
+        ProgramError::InvalidNumber,
+        ProgramError::IoFailure,
+    ];
+
+    pub fn code(self) -> u64 {
+        match self {
+            ProgramError::InsufficientFunds => 0x1,
+            ProgramError::AccountNotFound => 0x2,
+            ProgramError::ArithmeticOverflow => 0x3,
+            ProgramError::DivisionByZero => 0x4,
+            ProgramError::InvalidNumber => 0x5,
+            ProgramError::IoFailure => 0x6,
+        }
+    }
+
+    // 把错误码还原成错误；不认识的错误码返回None
+    pub fn from_code(code: u64) -> Option<ProgramError> {
+        Self::ALL.into_iter().find(|error| error.code() == code)
+    }
+
+    // 客户端拿到错误码后的展示方式
+    pub fn decode(code: u64) -> String {
+        match Self::from_code(code) {
+            Some(error) => error.to_string(),
+            None => format!("未知错误 (0x{:x})", code),
+        }
+    }
+
+    fn message(self) -> &'static str {
+        match self {
+            ProgramError::InsufficientFunds => "余额不足",
+            ProgramError::AccountNotFound => "账户不存在",
+            ProgramError::ArithmeticOverflow => "数值计算溢出",
+            ProgramError::DivisionByZero => "除数不能为零",
+            ProgramError::InvalidNumber => "数字格式无效",
+            ProgramError::IoFailure => "读写文件失败",
+        }
+    }
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (0x{:x})", self.message(), self.code())
+    }
+}
+
+impl std::error::Error for ProgramError {}
+
+impl From<ProgramError> for u64 {
+    fn from(error: ProgramError) -> u64 {
+        error.code()
+    }
+}
+
+// lamports加减溢出都按数值溢出处理；需要区分余额不足的地方由调用方自己map_err
+impl From<LamportsError> for ProgramError {
+    fn from(_: LamportsError) -> ProgramError {
+        ProgramError::ArithmeticOverflow
+    }
+}
+
+// 错误码只有一个数字，带不走底层错误的细节；需要细节时用ExerciseError
+impl From<ParseIntError> for ProgramError {
+    fn from(_: ParseIntError) -> ProgramError {
+        ProgramError::InvalidNumber
+    }
+}
+
+impl From<io::Error> for ProgramError {
+    fn from(_: io::Error) -> ProgramError {
+        ProgramError::IoFailure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_roundtrip() {
+        for error in ProgramError::ALL {
+            assert_eq!(ProgramError::from_code(u64::from(error)), Some(error));
+        }
+        assert_eq!(u64::from(ProgramError::InsufficientFunds), 0x1);
+        assert_eq!(ProgramError::from_code(0), None);
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(ProgramError::decode(0x1), "余额不足 (0x1)");
+        assert_eq!(ProgramError::decode(0x2a), "未知错误 (0x2a)");
+        assert_eq!(ProgramError::from(LamportsError::Overflow), ProgramError::ArithmeticOverflow);
+    }
+
+    #[test]
+    fn test_question_mark_conversions() {
+        fn parse(text: &str) -> Result<u64, ProgramError> {
+            Ok(text.parse::<u64>()?)
+        }
+        fn read(path: &str) -> Result<String, ProgramError> {
+            Ok(std::fs::read_to_string(path)?)
+        }
+        assert_eq!(parse("42"), Ok(42));
+        assert_eq!(parse("-1"), Err(ProgramError::InvalidNumber));
+        assert_eq!(read("/不存在的目录/amount.txt"), Err(ProgramError::IoFailure));
+        assert_eq!(u64::from(ProgramError::IoFailure), 0x6);
+    }
+}
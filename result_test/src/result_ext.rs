@@ -0,0 +1,103 @@
+// Result的扩展方法：很多match只是为了在出错时打印一下错误，或者出错时换成默认值，
+// 用ResultExt可以把这些写成一行链式调用，正常的值照样往下传
+
+use std::fmt::Display;
+
+pub trait ResultExt<T, E> {
+    /// 出错时用错误的引用调用`f`（比如打印或计数），然后原样返回这个Result。
+    ///
+    /// ```
+    /// use result_ext::ResultExt;
+    ///
+    /// let mut failures = 0;
+    /// let result: Result<u64, &str> = Err("余额不足");
+    /// let result = result.tap_err(|_| failures += 1);
+    /// assert_eq!((result, failures), (Err("余额不足"), 1));
+    ///
+    /// let ok: Result<u64, &str> = Ok(5);
+    /// assert_eq!(ok.tap_err(|_| unreachable!()), Ok(5));
+    /// ```
+    fn tap_err<F: FnOnce(&E)>(self, f: F) -> Self;
+
+    /// 出错时向标准错误输出一行`[target] 错误: ...`，然后原样返回这个Result。
+    ///
+    /// ```
+    /// use result_ext::ResultExt;
+    ///
+    /// let amount = "25o".parse::<u64>().log_err("parse_amount"); // 输出: [parse_amount] 错误: invalid digit found in string
+    /// assert!(amount.is_err());
+    /// ```
+    fn log_err(self, target: &str) -> Self
+    where
+        E: Display;
+
+    /// 出错时把错误交给`f`处理，然后返回`T::default()`；成功时返回其中的值。
+    ///
+    /// ```
+    /// use result_ext::ResultExt;
+    ///
+    /// let mut skipped = Vec::new();
+    /// let total: u64 = ["10", "x", "5"]
+    ///     .iter()
+    ///     .map(|text| text.parse::<u64>().or_default_with(|_| skipped.push(*text)))
+    ///     .sum();
+    /// assert_eq!((total, skipped), (15, vec!["x"]));
+    /// ```
+    fn or_default_with<F: FnOnce(E)>(self, f: F) -> T
+    where
+        T: Default;
+}
+
+impl<T, E> ResultExt<T, E> for Result<T, E> {
+    fn tap_err<F: FnOnce(&E)>(self, f: F) -> Self {
+        if let Err(error) = &self {
+            f(error);
+        }
+        self
+    }
+
+    fn log_err(self, target: &str) -> Self
+    where
+        E: Display,
+    {
+        self.tap_err(|error| eprintln!("{}", log_line(target, error)))
+    }
+
+    fn or_default_with<F: FnOnce(E)>(self, f: F) -> T
+    where
+        T: Default,
+    {
+        self.unwrap_or_else(|error| {
+            f(error);
+            T::default()
+        })
+    }
+}
+
+fn log_line(target: &str, error: &dyn Display) -> String {
+    format!("[{}] 错误: {}", target, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tap_err_only_runs_on_error() {
+        let mut seen = Vec::new();
+        let ok: Result<u8, &str> = Ok(1);
+        let err: Result<u8, &str> = Err("超时");
+        assert_eq!(ok.tap_err(|e| seen.push(*e)), Ok(1));
+        assert_eq!(err.tap_err(|e| seen.push(*e)).log_err("rpc"), Err("超时"));
+        assert_eq!(seen, vec!["超时"]);
+        assert_eq!(log_line("rpc", &"超时"), "[rpc] 错误: 超时");
+    }
+
+    #[test]
+    fn test_or_default_with() {
+        let mut errors = Vec::new();
+        assert_eq!("7".parse::<u64>().or_default_with(|e| errors.push(e)), 7);
+        assert_eq!("-1".parse::<u64>().or_default_with(|e| errors.push(e)), 0);
+        assert_eq!(errors.len(), 1);
+    }
+}
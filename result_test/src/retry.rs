@@ -0,0 +1,117 @@
+// 失败重试：RPC超时这类临时错误，过一会儿再试往往就成功了；账户不存在这类错误重试多少次都一样。
+// RetryPolicy决定最多试几次、每次失败后等多久（指数退避：每次等待时间翻倍，不超过上限），
+// 以及哪些错误值得重试
+
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy<E> {
+    max_attempts: u32, // 包括第一次在内最多调用几次
+    initial_backoff: Duration,
+    multiplier: u32,
+    max_backoff: Duration,
+    retryable: fn(&E) -> bool,
+}
+
+impl<E> RetryPolicy<E> {
+    // 默认所有错误都重试，不等待
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::ZERO,
+            multiplier: 2,
+            max_backoff: Duration::MAX,
+            retryable: |_| true,
+        }
+    }
+
+    pub fn backoff(mut self, initial: Duration, multiplier: u32) -> Self {
+        self.initial_backoff = initial;
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn max_backoff(mut self, max: Duration) -> Self {
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn retry_if(mut self, retryable: fn(&E) -> bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    // 第attempt次（从1开始）失败后等待的时间：initial * multiplier^(attempt-1)，溢出时取上限
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.checked_pow(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        self.initial_backoff.checked_mul(factor).unwrap_or(Duration::MAX).min(self.max_backoff)
+    }
+}
+
+// 调用f直到成功、遇到不可重试的错误，或者用完次数；失败时返回最后一次的错误
+pub fn retry<T, E, F>(policy: &RetryPolicy<E>, mut f: F) -> Result<T, E>
+where
+    F: FnMut() -> Result<T, E>,
+{
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(error) if attempt >= policy.max_attempts || !(policy.retryable)(&error) => return Err(error),
+            Err(_) => {
+                thread::sleep(policy.delay_after(attempt));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 前failures次调用失败，之后成功；返回值是第几次调用
+    fn flaky(failures: u32) -> impl FnMut() -> Result<u32, &'static str> {
+        let mut calls = 0;
+        move || {
+            calls += 1;
+            if calls <= failures { Err("超时") } else { Ok(calls) }
+        }
+    }
+
+    #[test]
+    fn test_retry_until_success() {
+        let policy = RetryPolicy::new(3);
+        assert_eq!(retry(&policy, flaky(0)), Ok(1));
+        assert_eq!(retry(&policy, flaky(2)), Ok(3));
+        // 次数用完时返回最后一次的错误
+        assert_eq!(retry(&policy, flaky(3)), Err("超时"));
+        assert_eq!(retry(&RetryPolicy::new(0), flaky(1)), Err("超时"));
+    }
+
+    #[test]
+    fn test_non_retryable_error_stops_immediately() {
+        let policy = RetryPolicy::new(5).retry_if(|error: &&str| *error == "超时");
+        let mut calls = 0;
+        let result: Result<(), &str> = retry(&policy, || {
+            calls += 1;
+            Err(if calls == 1 { "超时" } else { "账户不存在" })
+        });
+        assert_eq!((result, calls), (Err("账户不存在"), 2));
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy = RetryPolicy::<()>::new(10).backoff(Duration::from_millis(10), 2).max_backoff(Duration::from_millis(50));
+        let delays: Vec<u128> = (1..=5).map(|attempt| policy.delay_after(attempt).as_millis()).collect();
+        assert_eq!(delays, vec![10, 20, 40, 50, 50]);
+
+        // 10的幂溢出u32时按u32::MAX倍计算，不会panic
+        let unbounded = RetryPolicy::<()>::new(100).backoff(Duration::from_secs(1), 10);
+        assert_eq!(unbounded.delay_after(20), Duration::from_secs(u32::MAX as u64));
+        let huge = RetryPolicy::<()>::new(100).backoff(Duration::MAX / 2, 3);
+        assert_eq!(huge.delay_after(2), Duration::MAX);
+        assert_eq!(RetryPolicy::<()>::new(3).delay_after(2), Duration::ZERO);
+    }
+}
@@ -0,0 +1,129 @@
+// 基于文件的账户存储：启动时从文件加载，每次修改后立即写回文件
+// 文件格式是TOML的一个子集，每行一个账户：
+//   "0x1234567890" = 1000
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::ExerciseError;
+use crate::lamports::Lamports;
+
+#[derive(Debug)]
+pub struct AccountStore {
+    path: PathBuf,
+    accounts: BTreeMap<String, Lamports>,
+}
+
+impl AccountStore {
+    // 打开账户文件；文件不存在时用defaults创建一个新文件
+    pub fn open(path: impl AsRef<Path>, defaults: &[(&str, Lamports)]) -> Result<Self, ExerciseError> {
+        let path = path.as_ref().to_path_buf();
+
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                let accounts = parse_accounts(&content)?; // 解析错误直接向上传播
+                Ok(Self { path, accounts })
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                let accounts = defaults
+                    .iter()
+                    .map(|(address, balance)| (address.to_string(), *balance))
+                    .collect();
+                let store = Self { path, accounts };
+                store.save()?;
+                Ok(store)
+            }
+            Err(error) => Err(ExerciseError::Io(error)), // 其他IO错误（如权限不足）
+        }
+    }
+
+    pub fn find_account(&self, address: &str) -> Option<Lamports> {
+        self.accounts.get(address).copied()
+    }
+
+    // 修改余额后马上保存到文件
+    pub fn set_balance(&mut self, address: &str, balance: Lamports) -> Result<(), ExerciseError> {
+        self.accounts.insert(address.to_string(), balance);
+        self.save()
+    }
+
+    pub fn save(&self) -> Result<(), ExerciseError> {
+        let content: String = self
+            .accounts
+            .iter()
+            .map(|(address, balance)| format!("\"{}\" = {}\n", address, balance.0))
+            .collect();
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+}
+
+fn parse_accounts(content: &str) -> Result<BTreeMap<String, Lamports>, ExerciseError> {
+    let mut accounts = BTreeMap::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (address, balance) = line
+            .split_once('=')
+            .ok_or(ExerciseError::InvalidLine { line: index + 1 })?;
+        let address = address.trim().trim_matches('"');
+        let balance: u64 = balance
+            .trim()
+            .parse()
+            .map_err(|source| ExerciseError::InvalidBalance { line: index + 1, source })?;
+
+        accounts.insert(address.to_string(), Lamports(balance));
+    }
+
+    Ok(accounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("result_test_{}_{}.toml", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_missing_file_is_created_from_defaults() {
+        let path = temp_path("defaults");
+        let store = AccountStore::open(&path, &[("alice", Lamports(100))]).unwrap();
+
+        assert_eq!(store.find_account("alice"), Some(Lamports(100)));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "\"alice\" = 100\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_mutation_is_persisted() {
+        let path = temp_path("persist");
+        let mut store = AccountStore::open(&path, &[]).unwrap();
+        store.set_balance("bob", Lamports(42)).unwrap();
+
+        let reopened = AccountStore::open(&path, &[]).unwrap();
+        assert_eq!(reopened.find_account("bob"), Some(Lamports(42)));
+        assert_eq!(reopened.find_account("alice"), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_errors_report_line_numbers() {
+        let error = parse_accounts("# 注释\n\"a\" = 1\n\"b\" = abc\n").unwrap_err();
+        assert!(matches!(error, ExerciseError::InvalidBalance { line: 3, .. }));
+        assert!(error.to_string().starts_with("第3行余额无效"));
+
+        let error = parse_accounts("\"a\" 1\n").unwrap_err();
+        assert!(matches!(error, ExerciseError::InvalidLine { line: 1 }));
+        assert!(error.to_string().starts_with("第1行格式错误"));
+    }
+}
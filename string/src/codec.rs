@@ -0,0 +1,284 @@
+// 字节和文本之间的编码：hex、base64和base58
+// Solana里公钥、签名用base58显示，账户数据用base64传输，调试时常看hex。
+// 解码一律严格：不认识的字符、长度不对、多余的填充都会返回DecodeError，而不是悄悄忽略
+
+use std::fmt;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+// 比特币/Solana使用的base58字母表：去掉了容易看错的 0、O、I、l
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+pub const PUBKEY_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    InvalidCharacter { character: char, index: usize }, // 不属于字母表的字符，index是字符所在的字节位置
+    InvalidLength(usize),                               // 文本长度不可能是合法编码（hex为奇数、base64不是4的倍数）
+    InvalidPadding,                                     // base64的'='位置不对，或者最后一个字符带有多余的比特
+    WrongByteLength { expected: usize, actual: usize }, // 解码成功但字节数不对，比如公钥不是32字节
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidCharacter { character, index } => write!(f, "第{}个字节处有无效字符{:?}", index, character),
+            DecodeError::InvalidLength(len) => write!(f, "编码长度{}无效", len),
+            DecodeError::InvalidPadding => write!(f, "填充无效"),
+            DecodeError::WrongByteLength { expected, actual } => {
+                write!(f, "解码后长度错误: 期望{}字节，实际{}字节", expected, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+fn invalid_character(text: &str, index: usize) -> DecodeError {
+    // index总是落在字符边界上：出错的位置要么是ASCII字符，要么是多字节字符的开头
+    let character = text[index..].chars().next().unwrap();
+    DecodeError::InvalidCharacter { character, index }
+}
+
+// ===============================
+// hex
+// ===============================
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        out.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+        out.push(HEX_DIGITS[(byte & 0x0f) as usize] as char);
+    }
+    out
+}
+
+// 大小写都接受
+pub fn decode_hex(text: &str) -> Result<Vec<u8>, DecodeError> {
+    let digit = |index: usize| match text.as_bytes()[index] {
+        c @ b'0'..=b'9' => Ok(c - b'0'),
+        c @ b'a'..=b'f' => Ok(c - b'a' + 10),
+        c @ b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(invalid_character(text, index)),
+    };
+    // 先检查字符再检查长度，这样"abc中"报告的是无效字符而不是长度
+    for index in 0..text.len() {
+        if text.is_char_boundary(index) {
+            digit(index)?;
+        }
+    }
+    if !text.len().is_multiple_of(2) {
+        return Err(DecodeError::InvalidLength(text.len()));
+    }
+    (0..text.len()).step_by(2).map(|i| Ok(digit(i)? << 4 | digit(i + 1)?)).collect()
+}
+
+// ===============================
+// base64（标准字母表，带'='填充）
+// ===============================
+
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        // 3个字节 = 4个6比特的组，不足3字节时用'='补齐
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+pub fn decode_base64(text: &str) -> Result<Vec<u8>, DecodeError> {
+    let bytes = text.as_bytes();
+    let padding = bytes.iter().rev().take_while(|&&c| c == b'=').count();
+    let data_len = bytes.len() - padding;
+
+    let mut values = Vec::with_capacity(data_len);
+    for (index, &c) in bytes.iter().enumerate() {
+        if index >= data_len {
+            break;
+        }
+        match BASE64_ALPHABET.iter().position(|&a| a == c) {
+            Some(value) => values.push(value as u32),
+            // 数据中间出现'='说明填充放错了位置
+            None if c == b'=' => return Err(DecodeError::InvalidPadding),
+            None => return Err(invalid_character(text, index)),
+        }
+    }
+    if !bytes.len().is_multiple_of(4) {
+        return Err(DecodeError::InvalidLength(bytes.len()));
+    }
+    // 最多两个'='，而且一组4个字符里至少有2个数据字符
+    if padding > 2 {
+        return Err(DecodeError::InvalidPadding);
+    }
+
+    let mut out = Vec::with_capacity(data_len * 3 / 4);
+    for group in values.chunks(4) {
+        let n = group.iter().enumerate().fold(0u32, |n, (i, value)| n | value << (18 - 6 * i));
+        let byte_count = group.len() - 1;
+        // 不完整的最后一组里没用到的低位必须是0，否则同一段数据会有多种编码
+        if n & ((1 << (8 * (3 - byte_count))) - 1) != 0 {
+            return Err(DecodeError::InvalidPadding);
+        }
+        out.extend_from_slice(&n.to_be_bytes()[1..1 + byte_count]);
+    }
+    Ok(out)
+}
+
+// ===============================
+// base58
+// ===============================
+
+// 把字节看成一个大端的大整数，反复除以58；开头的每个0字节写成一个'1'
+pub fn encode_base58(bytes: &[u8]) -> String {
+    let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+    // 58进制的数字，低位在前
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+    for &byte in &bytes[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let mut out = String::with_capacity(zeros + digits.len());
+    out.extend(std::iter::repeat_n('1', zeros));
+    out.extend(digits.iter().rev().map(|&d| BASE58_ALPHABET[d as usize] as char));
+    out
+}
+
+pub fn decode_base58(text: &str) -> Result<Vec<u8>, DecodeError> {
+    let zeros = text.bytes().take_while(|&c| c == b'1').count();
+    // 256进制的字节，低位在前
+    let mut bytes: Vec<u8> = Vec::with_capacity(text.len() * 733 / 1000 + 1);
+    for (index, c) in text.bytes().enumerate().skip(zeros) {
+        let value = BASE58_ALPHABET.iter().position(|&a| a == c).ok_or_else(|| invalid_character(text, index))?;
+        let mut carry = value as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let mut out = vec![0u8; zeros];
+    out.extend(bytes.iter().rev());
+    Ok(out)
+}
+
+// 解码Solana公钥：base58文本必须恰好对应32字节
+pub fn decode_pubkey(text: &str) -> Result<[u8; PUBKEY_LEN], DecodeError> {
+    let bytes = decode_base58(text)?;
+    bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| DecodeError::WrongByteLength { expected: PUBKEY_LEN, actual: bytes.len() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 测试用的伪随机数（xorshift），固定种子保证每次运行结果一样
+    struct Rng(u64);
+
+    impl Rng {
+        fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        // 长度0到max的随机字节，大约四分之一的样本以0字节开头，覆盖base58的前导'1'
+        fn bytes(&mut self, max: usize) -> Vec<u8> {
+            let len = (self.next_u64() % (max as u64 + 1)) as usize;
+            let zeros = if self.next_u64().is_multiple_of(4) { len.min(3) } else { 0 };
+            (0..len).map(|i| if i < zeros { 0 } else { self.next_u64() as u8 }).collect()
+        }
+    }
+
+    #[test]
+    fn test_known_vectors() {
+        assert_eq!(encode_hex(&[0x00, 0xab, 0xff]), "00abff");
+        assert_eq!(decode_hex("00ABff"), Ok(vec![0x00, 0xab, 0xff]));
+
+        // RFC 4648中的例子
+        for (plain, encoded) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foobar", "Zm9vYmFy")] {
+            assert_eq!(encode_base64(plain.as_bytes()), encoded);
+            assert_eq!(decode_base64(encoded), Ok(plain.as_bytes().to_vec()));
+        }
+
+        assert_eq!(encode_base58(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(encode_base58(&[0, 0, 1]), "112");
+        assert_eq!(decode_base58("112"), Ok(vec![0, 0, 1]));
+        assert_eq!(encode_base58(&[0; PUBKEY_LEN]), "11111111111111111111111111111111");
+
+        let token_program = decode_pubkey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
+        assert_eq!(&token_program[..4], &[6, 221, 246, 225]);
+        assert_eq!(encode_base58(&token_program), "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+    }
+
+    #[test]
+    fn test_strict_errors() {
+        assert_eq!(decode_hex("abc"), Err(DecodeError::InvalidLength(3)));
+        assert_eq!(decode_hex("0g"), Err(DecodeError::InvalidCharacter { character: 'g', index: 1 }));
+        assert_eq!(decode_hex("ab中"), Err(DecodeError::InvalidCharacter { character: '中', index: 2 }));
+
+        assert_eq!(decode_base64("Zm9"), Err(DecodeError::InvalidLength(3)));
+        assert_eq!(decode_base64("Zm-v"), Err(DecodeError::InvalidCharacter { character: '-', index: 2 }));
+        assert_eq!(decode_base64("Z=9v"), Err(DecodeError::InvalidPadding));
+        assert_eq!(decode_base64("Z==="), Err(DecodeError::InvalidPadding));
+        // "Zh=="和"Zg=="解码出同一个字节，只有没有多余比特的"Zg=="是合法的
+        assert_eq!(decode_base64("Zh=="), Err(DecodeError::InvalidPadding));
+
+        assert_eq!(decode_base58("1O1"), Err(DecodeError::InvalidCharacter { character: 'O', index: 1 }));
+        assert_eq!(decode_base58("abc🦀"), Err(DecodeError::InvalidCharacter { character: '🦀', index: 3 }));
+        assert_eq!(decode_pubkey("StV1DL6CwTryKyV"), Err(DecodeError::WrongByteLength { expected: PUBKEY_LEN, actual: 11 }));
+        assert_eq!(
+            DecodeError::InvalidCharacter { character: 'O', index: 1 }.to_string(),
+            "第1个字节处有无效字符'O'"
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_random_bytes() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..2000 {
+            let bytes = rng.bytes(64);
+            assert_eq!(decode_hex(&encode_hex(&bytes)), Ok(bytes.clone()));
+            assert_eq!(decode_base64(&encode_base64(&bytes)), Ok(bytes.clone()));
+            assert_eq!(decode_base58(&encode_base58(&bytes)), Ok(bytes.clone()));
+            // 编码结果只包含字母表中的字符
+            assert!(encode_base58(&bytes).bytes().all(|c| BASE58_ALPHABET.contains(&c)));
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_random_text() {
+        // 反方向：任意合法的base58文本解码再编码得到原文
+        let mut rng = Rng(42);
+        for _ in 0..500 {
+            let len = (rng.next_u64() % 50) as usize;
+            let text: String = (0..len).map(|_| BASE58_ALPHABET[(rng.next_u64() % 58) as usize] as char).collect();
+            assert_eq!(encode_base58(&decode_base58(&text).unwrap()), text);
+        }
+    }
+}
@@ -1,3 +1,7 @@
+mod codec;
+
+use codec::{decode_base58, decode_base64, decode_hex, decode_pubkey, encode_base58, encode_base64, encode_hex};
+
 fn main() {
     // 演示为什么不能索引字符串
     println!("=== 为什么 Rust 不允许字符串索引 ===\n");
@@ -27,7 +31,7 @@ fn main() {
 
     // 方法2: 获取特定位置的字符
     println!("获取特定位置字符:");
-    if let Some(first) = mixed.chars().nth(0) {
+    if let Some(first) = mixed.chars().next() {
         println!("  第0个字符: '{}'", first);
     }
     if let Some(third) = mixed.chars().nth(2) {
@@ -70,6 +74,42 @@ fn main() {
             None => println!("  位置 {}: 超出范围", i),
         }
     }
+    println!("前6个字符: '{}'", take_chars(&text, 6));
+    println!();
+
+    // 5. 字节与文本的编码
+    println!("=== 字节与文本的编码 (hex / base64 / base58) ===\n");
+
+    let data = "Hi你好".as_bytes();
+    println!("原始字节: {:?}", data);
+    println!("hex:    {}", encode_hex(data));
+    println!("base64: {}", encode_base64(data));
+    println!("base58: {}", encode_base58(data));
+    // 解码得到的是字节，还要再检查一次UTF-8才能变回String
+    let decoded = decode_base64(&encode_base64(data)).map(String::from_utf8);
+    println!("base64解码再转回字符串: {:?}", decoded);
+    println!();
+
+    // Solana公钥就是32字节的base58文本
+    let token_program = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+    match decode_pubkey(token_program) {
+        Ok(bytes) => println!("Token Program公钥的hex: {}", encode_hex(&bytes)),
+        Err(error) => println!("解码失败: {}", error),
+    }
+    // 解码失败时说明原因和位置
+    for (name, result) in [
+        ("hex \"abc\"", decode_hex("abc")),
+        ("base64 \"Zh==\"", decode_base64("Zh==")),
+        ("base58 \"0OIl\"", decode_base58("0OIl")),
+    ] {
+        match result {
+            Ok(bytes) => println!("  {}: {:?}", name, bytes),
+            Err(error) => println!("  {}: {}", name, error),
+        }
+    }
+    if let Err(error) = decode_pubkey("StV1DL6CwTryKyV") {
+        println!("  公钥 \"StV1DL6CwTryKyV\": {}", error);
+    }
 }
 
 // 安全的字符获取函数
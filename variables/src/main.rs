@@ -1,4 +1,7 @@
-fn main() {
+use std::error::Error;
+
+// parse失败时用?把ParseIntError交给main返回，而不是expect直接panic
+fn main() -> Result<(), Box<dyn Error>> {
     let x = 5;
     // 在main函数的作用域内对之前的x进行遮蔽
     let x = x + 1;
@@ -13,7 +16,8 @@ fn main() {
 
     println!("The value of x is: {}", x);
 
-    let guess: i32 = "42".parse().expect("Not a number!");
+    let guess: i32 = "42".parse()?;
+    println!("The guess is: {}", guess);
 
     let mut s = "hello";
     println!("s 指向: {}, 地址: {:p}", s, s.as_ptr());
@@ -31,4 +35,5 @@ fn main() {
     s_mut_str.make_ascii_uppercase();
     // s_mut_str.push('x');
     println!("{}", s_mut_str);
+    Ok(())
 }